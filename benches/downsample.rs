@@ -0,0 +1,82 @@
+//! The box filter behind high quality downscaling, against the cost of the
+//! supersampled render it shrinks.
+//!
+//! Run with `cargo bench --bench downsample`.
+
+use std::hint::black_box;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use svg_viewer::renderer::{self, RenderScale};
+use svg_viewer::svg_document::SvgDocument;
+use svg_viewer::viewport::Viewport;
+use tiny_skia::Pixmap;
+
+const ROUNDS: u32 = 20;
+
+fn best_of(mut run: impl FnMut()) -> Duration {
+    run();
+    let mut best = Duration::MAX;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        run();
+        best = best.min(start.elapsed());
+    }
+    best
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn main() {
+    println!("Box filter, best of {ROUNDS}");
+    for size in [800, 1600, 3200] {
+        let mut pixmap = Pixmap::new(size, size).expect("pixmap");
+        pixmap.fill(tiny_skia::Color::from_rgba8(200, 80, 40, 180));
+        let time = best_of(|| {
+            black_box(renderer::downsample(black_box(&pixmap), 2));
+        });
+        println!(
+            "  {size}x{size} -> {0}x{0}  {1:>8.2} ms",
+            size / 2,
+            ms(time)
+        );
+    }
+
+    let lines: String = (0..2500)
+        .map(|i| {
+            format!(
+                "<path d=\"M{} 0 L{} 10000\" stroke=\"black\"/>",
+                i * 4,
+                i * 4 + 50
+            )
+        })
+        .collect();
+    let svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="10000" height="10000">{lines}</svg>"#
+    );
+    let doc = SvgDocument::from_data(Path::new("map.svg"), svg.into_bytes()).expect("parses");
+    let mut viewport = Viewport::default();
+    viewport.fit_to_area(doc.width(), doc.height(), 800.0, 800.0);
+
+    println!("10000x10000 document fitted to 800x800, best of {ROUNDS}");
+    let direct = best_of(|| {
+        black_box(renderer::render_to_pixmap(&doc, &viewport, 800.0, 800.0, 1.0).expect("renders"));
+    });
+    println!("  direct        {:>8.2} ms", ms(direct));
+    let supersampled = best_of(|| {
+        black_box(
+            renderer::render_to_pixmap_supersampled(
+                &doc,
+                &viewport,
+                800.0,
+                800.0,
+                1.0,
+                RenderScale::Full,
+            )
+            .expect("renders"),
+        );
+    });
+    println!("  supersampled  {:>8.2} ms", ms(supersampled));
+}
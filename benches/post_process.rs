@@ -0,0 +1,54 @@
+//! One combined post-processing pass against one pass per effect.
+//!
+//! Run with `cargo bench --bench post_process`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use svg_viewer::color_management::{ColorManagement, DisplayConversion, DisplayProfile};
+use svg_viewer::renderer::{Grayscale, Invert, PostPipeline};
+use tiny_skia::Pixmap;
+
+const SIZE: u32 = 2048;
+const ROUNDS: u32 = 20;
+
+fn conversion() -> DisplayConversion {
+    DisplayConversion::new(ColorManagement {
+        enabled: true,
+        profile: DisplayProfile::DisplayP3,
+    })
+    .expect("Display P3 converts")
+}
+
+fn time(name: &str, mut run: impl FnMut(&mut Pixmap)) {
+    let mut pixmap = Pixmap::new(SIZE, SIZE).expect("pixmap");
+    pixmap.fill(tiny_skia::Color::from_rgba8(200, 80, 40, 180));
+    run(&mut pixmap);
+    let mut best = Duration::MAX;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        run(black_box(&mut pixmap));
+        best = best.min(start.elapsed());
+    }
+    println!("{name:<12} {:>8.2} ms", best.as_secs_f64() * 1000.0);
+}
+
+fn main() {
+    println!("{SIZE}x{SIZE}, grayscale + invert + display conversion, best of {ROUNDS}");
+    let combined = PostPipeline::new()
+        .with(Grayscale)
+        .with(Invert)
+        .with(conversion());
+    time("combined", |pixmap| combined.run(pixmap));
+
+    let passes = [
+        PostPipeline::new().with(Grayscale),
+        PostPipeline::new().with(Invert),
+        PostPipeline::new().with(conversion()),
+    ];
+    time("sequential", |pixmap| {
+        for pass in &passes {
+            pass.run(pixmap);
+        }
+    });
+}
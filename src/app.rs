@@ -1,57 +1,293 @@
 use std::path::{Path, PathBuf};
-use std::sync::mpsc;
-use std::time::Instant;
-
-use tiny_skia::Pixmap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
 
+use crate::archive;
+use crate::batch;
+use crate::checkerboard::{self, Backdrop, CHECKER_SIZE};
 use crate::clipboard;
-use crate::export;
-use crate::file_navigator::FileNavigator;
-use crate::renderer::{Renderer, MAX_RENDER_SCALE};
-use crate::svg_document::SvgDocument;
+use crate::color_management::ColorManagement;
+use crate::diagnostics::XmlDiagnostic;
+use crate::error::SvgError;
+use crate::export::{self, ExportFormat, SizePlan};
+use crate::eyedropper;
+use crate::file_navigator::{self, FileNavigator};
+use crate::file_watcher::{DirectoryWatcher, FileWatcher};
+use crate::guides::GuideSet;
+use crate::latest::Latest;
+use crate::measure::Measurement;
+use crate::reload_queue::{self, Finished, ReloadQueue};
+use crate::remote;
+use crate::render_state::{RenderEvent, RenderState};
+use crate::renderer::{
+    self, CanvasRender, CanvasRequest, RenderQuality, RenderScale, RenderWorker, Renderer,
+    MAX_RENDER_SCALE,
+};
+use crate::reveal;
+use crate::settings::Settings;
+use crate::structure::Inspector;
+use crate::svg_document::{EmbeddedImage, EmptyNotice, SvgDocument, CLIPBOARD_NAME};
+use crate::tiles;
+use crate::trash;
+use crate::ui::batch_progress::{self, BatchProgressState};
 use crate::ui::canvas;
-use crate::ui::export_dialog::{self, ExportDialogResult, ExportDialogState};
+use crate::ui::canvas_interaction::CanvasInteraction;
+use crate::ui::delete_confirm;
+use crate::ui::diff_overlay::DiffOverlay;
+use crate::ui::event_log_window::{self, EventLogWindowState};
+use crate::ui::export_dialog::{self, ExportDialogResult, ExportDialogState, SizeConfirmChoice};
+use crate::ui::folder_summary::{self, FolderSummaryState};
+use crate::ui::gallery::{self, GalleryState};
+use crate::ui::image_inspector::{self, ImageInspectorState};
+use crate::ui::measurements::{self, MeasureState};
+use crate::ui::position_popover::{self, PositionAction, PositionPopoverState};
+use crate::ui::properties::{self, PropertiesState};
+use crate::ui::rotation_entry::{self, RotationEntryState};
+use crate::ui::rulers;
 use crate::ui::shortcuts;
-use crate::ui::status_bar;
-use crate::ui::toolbar::{self, ToolbarAction};
-use crate::viewport::Viewport;
+use crate::ui::source_panel::{self, SourcePanelState};
+use crate::ui::status_bar::{self, ReadoutEntry};
+use crate::ui::thumbnail_strip::{self, ThumbnailStripState};
+use crate::ui::thumbnail_textures::ThumbnailTextures;
+use crate::ui::toolbar::{self, ToolbarAction, ToolbarState};
+use crate::view_action::{self, ViewAction, ViewContext};
+use crate::view_history::ViewHistory;
+use crate::viewport::{self, FitMode, ResizeAction, ViewPolicy, Viewport, ZoomPreset};
+
+/// How long stepping through files has to pause before the file stepped to
+/// is loaded, so holding an arrow key doesn't render every file it passes.
+const NAVIGATION_SETTLE: Duration = Duration::from_millis(120);
+
+/// A load's result; `None` once the load noticed it was cancelled.
+type LoadResult = Result<Option<LoadedFile>, SvgError>;
 
 struct PendingLoad {
-    receiver: mpsc::Receiver<Result<LoadedFile, String>>,
+    path: PathBuf,
+    /// Finished without a result if the loader panicked
+    thread: std::thread::JoinHandle<()>,
+    cancel: Arc<AtomicBool>,
+    reload: bool,
+}
+
+/// A render of the view asked of the render worker; the old texture is
+/// stretched to the view until it arrives.
+struct PendingRender {
+    /// Results from any other generation are outdated
+    generation: u64,
+    /// What is rendered; a result for a document no longer open is dropped
+    doc: Arc<SvgDocument>,
+}
+
+/// A file stepped to while navigating quickly, loaded once it settles.
+struct QueuedLoad {
+    path: PathBuf,
+    view: LoadView,
+    at: Instant,
+}
+
+/// The view a background load is shown with.
+enum LoadView {
+    /// Fitted to the canvas, as a newly opened file is
+    Fit,
+    /// The open document read again, keeping its view
+    Reload(Viewport),
+    /// Another file shown exactly as the last one was, for comparing them
+    Locked(Viewport),
 }
 
 struct LoadedFile {
     doc: SvgDocument,
-    pixmap: Pixmap,
+    render: CanvasRender,
     viewport: Viewport,
-    logical_display_w: f32,
-    logical_display_h: f32,
+    // A reload of the open document, which keeps the current view
+    reload: bool,
+    /// Shown with the view locked while stepping through files
+    locked: bool,
+}
+
+/// A document that failed to parse, shown on the canvas instead of a render.
+struct ParseFailure {
+    filename: String,
+    diagnostic: Box<XmlDiagnostic>,
+}
+
+/// The element the canvas context menu was opened on.
+struct InspectedElement {
+    id: Option<String>,
+    selector: String,
+    bounds_json: String,
+}
+
+/// The last export that succeeded this session, for Export again.
+#[derive(Clone)]
+struct LastExport {
+    source: PathBuf,
+    output: PathBuf,
+    settings: export::ExportSettings,
+    current_view: bool,
+    /// Times the screen's resolution a current-view export is at
+    view_scale: f32,
+    region: Option<export::ExportRegion>,
+    include_background: bool,
+    over_declared_background: bool,
+    /// Times the size each of several outputs is written at, named from
+    /// `output` with `@2x` and so on; empty for just `output`
+    scales: Vec<f32>,
+}
+
+impl LastExport {
+    /// Each file the export writes, with the settings it's written with.
+    fn outputs(&self) -> Vec<(PathBuf, export::ExportSettings)> {
+        if self.scales.is_empty() {
+            return vec![(self.output.clone(), self.settings.clone())];
+        }
+        self.scales
+            .iter()
+            .map(|&scale| {
+                (
+                    export::scaled_path(&self.output, scale),
+                    export::scaled_settings(&self.settings, scale),
+                )
+            })
+            .collect()
+    }
+}
+
+/// An export being rendered and written on a background thread.
+struct PendingExport {
+    export: LastExport,
+    /// The files being written, in order
+    outputs: Vec<PathBuf>,
+    /// How writing each output went, as far as it's got
+    receiver: mpsc::Receiver<Result<(), SvgError>>,
+    results: Vec<Result<(), SvgError>>,
+    /// Added to the status message once it's written
+    notice: Option<String>,
+}
+
+/// What a current-view export captures, taken when it starts so the view can
+/// move while it renders.
+struct ViewCapture {
+    viewport: Viewport,
+    canvas: (f32, f32),
+    image: egui::Rect,
+    scale: f32,
+    backdrop: Option<Backdrop>,
 }
 
 pub struct SvgViewerApp {
-    document: Option<SvgDocument>,
+    /// Shared with background exports, which render it while it's shown
+    document: Option<Arc<SvgDocument>>,
     viewport: Viewport,
+    /// Earlier views to go back to with Ctrl+Z
+    view_history: ViewHistory,
+    /// Dragging on the canvas zooms to a rectangle, as Ctrl+drag always does
+    zoom_select: bool,
+    /// Dragging on the canvas picks the region to export, for the export dialog
+    region_select: bool,
+    /// Files stepped to keep the current view instead of being fitted
+    lock_view: bool,
+    /// Clicking on the canvas copies the color under the pointer
+    eyedropper: bool,
+    /// The rendered color under the pointer, while the eyedropper is on
+    hover_color: Option<[u8; 4]>,
+    /// The previous file, shown under the current one to compare them
+    diff_overlay: DiffOverlay,
+    /// Where the rectangle being dragged out to zoom to started
+    selection_start: Option<egui::Pos2>,
     renderer: Renderer,
     navigator: FileNavigator,
 
     show_checkerboard: bool,
+    /// Show a background color declared on the root `<svg>` instead of the checkerboard
+    use_declared_background: bool,
+    /// Flatten copies onto the declared background
+    copy_over_declared: bool,
     dark_mode: bool,
+    // Solid canvas color chosen by the user, overriding the theme background
+    canvas_background: Option<[u8; 3]>,
+    copy_include_background: bool,
+    /// Flatten copies onto white, over any other background, for apps that
+    /// show transparency as black
+    copy_white_background: bool,
     error_message: Option<String>,
+    parse_failure: Option<ParseFailure>,
     status_message: Option<String>,
 
     export_dialog: ExportDialogState,
-    render_dirty: bool,
+    // Export waiting on confirmation because its size had to be reduced
+    pending_size_confirm: Option<SizePlan>,
+    // File waiting on confirmation before it's moved to the trash
+    pending_delete: Option<PathBuf>,
+    last_export: Option<LastExport>,
+    /// One export at a time, so two can't write the same file at once
+    pending_export: Option<PendingExport>,
+    rotation_entry: RotationEntryState,
+    position_popover: PositionPopoverState,
+    event_log_window: EventLogWindowState,
+    folder_summary: FolderSummaryState,
+    batch_progress: BatchProgressState,
+    properties: PropertiesState,
+    image_inspector: ImageInspectorState,
+    source_panel: SourcePanelState,
+    thumbnails: ThumbnailStripState,
+    gallery: GalleryState,
+    /// Shared by the thumbnail strip and the gallery
+    thumbnail_textures: ThumbnailTextures,
+    readout_entry: ReadoutEntry,
+    /// What's typed in the toolbar's file filter
+    filter_text: String,
+    // Why the last render came out blank, when it did and that's surprising
+    empty_notice: Option<EmptyNotice>,
+    // Built on the first right-click, for the current document
+    inspector: Option<Inspector>,
+    inspected: Option<InspectedElement>,
+    render_state: RenderState,
+    canvas_interaction: CanvasInteraction,
     last_area_size: (f32, f32),
+    // Screen position of the canvas's top-left corner
+    canvas_origin: egui::Pos2,
+    /// The document point under the pointer, while it's over the canvas
+    cursor_svg: Option<[f32; 2]>,
+    measure: MeasureState,
+    view_policy: ViewPolicy,
+    // Fraction of the display resolution the canvas renders at
+    render_scale: RenderScale,
+    // Sample enlarged renders nearest-neighbour
+    pixel_inspection: bool,
+    // Underline text whose font had to be substituted
+    show_font_substitutions: bool,
+    guides: GuideSet,
+    // Remembered between sessions; view choices are copied back in before saving
+    settings: Settings,
+
+    // Panning keeps part of the image on screen
+    constrain_pan: bool,
+    /// Rulers along the top and left of the canvas
+    show_rulers: bool,
 
-    // Deferred zoom re-render
-    zoom_idle_since: Option<Instant>,
-    pending_rerender: bool,
+    // Reload the document when its file changes
+    auto_reload: bool,
+    watcher: Option<FileWatcher>,
+    // Files added to or removed from the folder being navigated
+    dir_watcher: Option<DirectoryWatcher>,
+    reloads: ReloadQueue,
 
-    // Initial file to load
-    initial_file: Option<PathBuf>,
+    /// Files to open on the first frame
+    initial_files: Vec<PathBuf>,
 
-    // Background loading
+    // The background load in flight; `render_state` tracks the load itself
     pending_load: Option<PendingLoad>,
+    /// Every load's result; only the latest one's is taken
+    load_results: Latest<LoadResult>,
+    queued_load: Option<QueuedLoad>,
+    pending_render: Option<PendingRender>,
+    /// Counts the renders started; only the latest one's result is shown
+    render_generation: u64,
+    /// Started with the first render
+    render_worker: Option<RenderWorker>,
+    last_navigation: Option<Instant>,
     last_pixels_per_point: f32,
 
     // Cap initial zoom to MAX_RENDER_SCALE (cleared after first auto-fit)
@@ -59,47 +295,222 @@ pub struct SvgViewerApp {
 }
 
 impl SvgViewerApp {
+    /// Open `file_path`, or with none, the file open when the viewer last closed.
     pub fn new(file_path: Option<PathBuf>) -> Self {
+        let mut app = Self::blank();
+        app.initial_files = file_path
+            .or_else(|| app.settings.last_existing_file().map(Path::to_path_buf))
+            .into_iter()
+            .collect();
+        app
+    }
+
+    /// The viewer with the saved settings applied and nothing to open.
+    fn blank() -> Self {
+        let settings = Settings::load();
+        let mut renderer = Renderer::new();
+        renderer.set_color_management(ColorManagement {
+            enabled: settings.color_management,
+            profile: settings.display_profile,
+        });
+        renderer.set_high_quality_downscale(settings.high_quality_downscale);
+        renderer.set_quality(settings.render_quality);
+        let mut export_dialog = ExportDialogState::new();
+        export_dialog.restore(&settings.export);
         Self {
             document: None,
             viewport: Viewport::default(),
-            renderer: Renderer::new(),
+            view_history: ViewHistory::default(),
+            zoom_select: false,
+            region_select: false,
+            eyedropper: false,
+            hover_color: None,
+            diff_overlay: DiffOverlay::default(),
+            lock_view: false,
+            selection_start: None,
+            renderer,
             navigator: FileNavigator::new(),
-            show_checkerboard: true,
-            dark_mode: true,
+            show_checkerboard: settings.show_checkerboard,
+            use_declared_background: true,
+            copy_over_declared: false,
+            dark_mode: settings.dark_mode,
+            canvas_background: None,
+            copy_include_background: false,
+            copy_white_background: settings.copy_white_background,
             error_message: None,
+            parse_failure: None,
             status_message: None,
-            export_dialog: ExportDialogState::new(),
-            render_dirty: true,
+            export_dialog,
+            pending_size_confirm: None,
+            pending_delete: None,
+            last_export: None,
+            pending_export: None,
+            rotation_entry: RotationEntryState::new(),
+            position_popover: PositionPopoverState::default(),
+            event_log_window: EventLogWindowState::default(),
+            folder_summary: FolderSummaryState::default(),
+            batch_progress: BatchProgressState::default(),
+            properties: PropertiesState::default(),
+            image_inspector: ImageInspectorState::default(),
+            source_panel: SourcePanelState::default(),
+            thumbnails: ThumbnailStripState::new(settings.show_thumbnails),
+            gallery: GalleryState::default(),
+            thumbnail_textures: ThumbnailTextures::default(),
+            readout_entry: ReadoutEntry::default(),
+            filter_text: String::new(),
+            empty_notice: None,
+            inspector: None,
+            inspected: None,
+            render_state: RenderState::default(),
+            canvas_interaction: CanvasInteraction::new(),
             last_area_size: (0.0, 0.0),
-            zoom_idle_since: None,
-            pending_rerender: false,
-            initial_file: file_path,
+            canvas_origin: egui::Pos2::ZERO,
+            cursor_svg: None,
+            measure: MeasureState::default(),
+            view_policy: ViewPolicy::default(),
+            render_scale: RenderScale::default(),
+            pixel_inspection: false,
+            show_font_substitutions: false,
+            guides: GuideSet::load(),
+            constrain_pan: settings.constrain_pan,
+            show_rulers: settings.show_rulers,
+            settings,
+            auto_reload: true,
+            watcher: None,
+            dir_watcher: None,
+            reloads: ReloadQueue::new(),
+            initial_files: Vec::new(),
             pending_load: None,
+            load_results: Latest::new(),
+            queued_load: None,
+            pending_render: None,
+            render_generation: 0,
+            render_worker: None,
+            last_navigation: None,
             last_pixels_per_point: 0.0,
             cap_initial_zoom: true,
         }
     }
 
+    /// Open `files` on the first frame, navigating between just those.
+    pub fn with_files(files: Vec<PathBuf>) -> Self {
+        let mut app = Self::blank();
+        app.initial_files = files;
+        app
+    }
+
+    /// Open with an already loaded document, such as one read from standard input.
+    /// The file navigator stays empty.
+    pub fn with_document(doc: SvgDocument) -> Self {
+        let mut app = Self::blank();
+        app.document = Some(Arc::new(doc));
+        app.render_state.apply(RenderEvent::Invalidate);
+        app.set_render_quality(app.renderer.quality());
+        app
+    }
+
+    /// Open `path`, navigating its directory; a directory or zip archive opens
+    /// its first SVG. A URL has no directory, so there's nothing to navigate.
     fn load_file(&mut self, path: &Path) {
+        // A new list of files starts unfiltered
+        self.filter_text.clear();
+        if remote::is_url(path) {
+            self.navigator.set_files(Vec::new());
+            self.load_path(path);
+            return;
+        }
+        if archive::is_archive(path) {
+            if let Err(e) = self.navigator.scan_archive(path) {
+                self.error_message = Some(format!("Error: {e}"));
+                return;
+            }
+            match self.navigator.current().map(Path::to_path_buf) {
+                Some(first) => self.load_path(&first),
+                None => {
+                    self.error_message = Some(format!("No SVG files in {}", path.display()));
+                }
+            }
+            return;
+        }
+        if path.is_dir() {
+            self.navigator.scan_dir_direct(path);
+            match self.navigator.current().map(Path::to_path_buf) {
+                Some(first) => self.load_path(&first),
+                None => {
+                    self.error_message = Some(format!("No SVG files in {}", path.display()));
+                }
+            }
+            return;
+        }
+        self.navigator.scan_directory(path);
+        self.load_path(path);
+    }
+
+    /// Open several files, navigating between exactly those. A single file
+    /// navigates its directory as usual.
+    fn open_files(&mut self, files: Vec<PathBuf>) {
+        match files.as_slice() {
+            [] => {}
+            [path] => self.load_file(&path.clone()),
+            _ => {
+                // Directories and archives in the list stand for the SVG files inside them
+                let files: Vec<PathBuf> = files
+                    .into_iter()
+                    .flat_map(|path| {
+                        if archive::is_archive(&path) {
+                            archive::svg_paths(&path).unwrap_or_else(|e| {
+                                log::warn!("Skipping {}: {e}", path.display());
+                                Vec::new()
+                            })
+                        } else if path.is_dir() {
+                            file_navigator::svg_files_in(&path)
+                        } else {
+                            vec![path]
+                        }
+                    })
+                    .collect();
+                let Some(first) = files.first().cloned() else {
+                    self.error_message =
+                        Some("No SVG files in the opened folders or archives".into());
+                    return;
+                };
+                self.navigator.set_files(files);
+                self.filter_text.clear();
+                self.load_path(&first);
+            }
+        }
+    }
+
+    /// Load `path` without touching the navigator.
+    fn load_path(&mut self, path: &Path) {
+        self.remember_view();
+        self.cancel_loads();
         self.error_message = None;
+        self.parse_failure = None;
         self.status_message = None;
-        self.navigator.scan_directory(path);
 
         if self.last_pixels_per_point > 0.0 && self.last_area_size.0 > 0.0 {
-            self.start_background_load(path);
+            self.start_background_load(path, LoadView::Fit, Arc::default());
         } else {
             // First frame: area size unknown, load synchronously
-            match SvgDocument::load(path) {
+            let start = Instant::now();
+            match SvgDocument::load_with_quality(path, self.renderer.quality()) {
                 Ok(doc) => {
+                    log::info!(
+                        "Loaded {} in {:.1} ms",
+                        path.display(),
+                        start.elapsed().as_secs_f64() * 1000.0
+                    );
                     self.viewport.reset();
-                    self.document = Some(doc);
-                    self.render_dirty = true;
+                    self.document = Some(Arc::new(doc));
+                    self.inspector = None;
+                    self.render_state.apply(RenderEvent::Invalidate);
                     self.cap_initial_zoom = true;
+                    self.restore_view();
                 }
                 Err(e) => {
-                    self.error_message = Some(format!("Error: {}", e));
                     log::error!("Failed to load {}: {}", path.display(), e);
+                    self.show_load_error(path, e);
                 }
             }
         }
@@ -108,242 +519,1712 @@ impl SvgViewerApp {
     fn open_file_dialog(&mut self) {
         let file = rfd::FileDialog::new()
             .add_filter("SVG Files", &["svg", "svgz"])
+            .add_filter("Zip Archives", &["zip"])
             .add_filter("All Files", &["*"])
-            .pick_file();
+            .pick_files();
 
-        if let Some(path) = file {
-            self.load_file(&path);
+        if let Some(files) = file {
+            self.open_files(files);
         }
     }
 
     fn navigate_prev(&mut self) {
-        if let Some(path) = self.navigator.prev().map(|p| p.to_path_buf()) {
+        if let Some(path) = self.navigator.prev_file().map(|p| p.to_path_buf()) {
             self.load_file_keep_navigator(&path);
         }
     }
 
     fn navigate_next(&mut self) {
-        if let Some(path) = self.navigator.next().map(|p| p.to_path_buf()) {
+        if let Some(path) = self.navigator.next_file().map(|p| p.to_path_buf()) {
             self.load_file_keep_navigator(&path);
         }
     }
 
+    fn navigate_first(&mut self) {
+        if let Some(path) = self.navigator.first().map(|p| p.to_path_buf()) {
+            self.load_file_keep_navigator(&path);
+        }
+    }
+
+    fn navigate_last(&mut self) {
+        if let Some(path) = self.navigator.last().map(|p| p.to_path_buf()) {
+            self.load_file_keep_navigator(&path);
+        }
+    }
+
+    fn navigate_to(&mut self, index: usize) {
+        if index == self.navigator.current_index() {
+            return;
+        }
+        match self.navigator.jump_to(index).map(|p| p.to_path_buf()) {
+            Some(path) => self.load_file_keep_navigator(&path),
+            None => {
+                self.status_message = Some(format!(
+                    "No file {}; there are {}",
+                    index + 1,
+                    self.navigator.file_count()
+                ));
+            }
+        }
+    }
+
     fn load_file_keep_navigator(&mut self, path: &Path) {
+        self.remember_view();
+        self.cancel_loads();
         self.error_message = None;
-        self.start_background_load(path);
+        self.parse_failure = None;
+        let view = if self.lock_view && self.document.is_some() {
+            // Same zoom, in pixels per SVG unit, so differently sized
+            // documents still line up; never refitted to the new one
+            let mut locked = self.viewport.clone();
+            if locked.fit_mode.is_fit() {
+                locked.fit_mode = FitMode::Custom;
+            }
+            locked.user_adjusted = true;
+            LoadView::Locked(locked)
+        } else {
+            LoadView::Fit
+        };
+        // Steps in quick succession wait for the last one
+        let now = Instant::now();
+        let rapid = self
+            .last_navigation
+            .is_some_and(|at| now.duration_since(at) < NAVIGATION_SETTLE);
+        self.last_navigation = Some(now);
+        if rapid {
+            self.queued_load = Some(QueuedLoad {
+                path: path.to_path_buf(),
+                view,
+                at: now,
+            });
+            self.render_state.apply(RenderEvent::LoadStarted {
+                had_document: self.document.is_some(),
+            });
+        } else {
+            self.start_background_load(path, view, Arc::default());
+        }
+    }
+
+    /// Start the queued load once navigation has paused long enough.
+    fn start_queued_load(&mut self, ctx: &egui::Context) {
+        let Some(queued) = &self.queued_load else {
+            return;
+        };
+        let wait = NAVIGATION_SETTLE.saturating_sub(queued.at.elapsed());
+        if !wait.is_zero() {
+            ctx.request_repaint_after(wait);
+            return;
+        }
+        if let Some(queued) = self.queued_load.take() {
+            self.start_background_load(&queued.path, queued.view, Arc::default());
+        }
     }
 
-    fn start_background_load(&mut self, path: &Path) {
+    /// Whether another file is being opened, as opposed to the open one reloaded.
+    fn opening_file(&self) -> bool {
+        self.queued_load.is_some() || self.pending_load.as_ref().is_some_and(|p| !p.reload)
+    }
+
+    /// Open the SVG markup on the clipboard as a document of its own, with
+    /// nothing to navigate. Markup that doesn't parse is reported as a file's would be.
+    fn paste_svg(&mut self) {
+        let text = match clipboard::read_svg_text() {
+            Ok(text) => text,
+            Err(e) => {
+                log::error!("Paste from clipboard failed: {e}");
+                self.error_message = Some(format!("Clipboard error: {}", e));
+                return;
+            }
+        };
+        self.remember_view();
+        self.cancel_loads();
+        self.error_message = None;
+        self.parse_failure = None;
+        self.status_message = None;
+        match SvgDocument::from_clipboard_text(text) {
+            Ok(doc) => {
+                log::info!("Pasted {} bytes of SVG", doc.raw_data().len());
+                self.navigator.set_files(Vec::new());
+                self.filter_text.clear();
+                self.gallery.open = false;
+                self.viewport.reset();
+                self.document = Some(Arc::new(doc));
+                self.inspector = None;
+                self.inspected = None;
+                self.render_state.apply(RenderEvent::Invalidate);
+                self.cap_initial_zoom = true;
+                self.status_message = Some("Pasted SVG from the clipboard".into());
+                self.set_render_quality(self.renderer.quality());
+            }
+            Err(e) => {
+                log::error!("Failed to parse the clipboard as SVG: {e}");
+                self.show_load_error(Path::new(CLIPBOARD_NAME), e);
+            }
+        }
+    }
+
+    /// Move the open file to the trash, asking first unless `now`. Only files
+    /// on disk can be; not downloads, standard input, pastes or archive entries.
+    fn request_delete(&mut self, now: bool) {
+        let Some(path) = self
+            .document
+            .as_ref()
+            .filter(|doc| !doc.is_in_memory() && !doc.is_remote())
+            .map(|doc| doc.path().to_path_buf())
+        else {
+            return;
+        };
+        if archive::split_path(&path).is_some() {
+            self.status_message = Some("Files inside archives can't be moved to the trash".into());
+            return;
+        }
+        if now {
+            self.trash_file(&path);
+        } else {
+            self.pending_delete = Some(path);
+        }
+    }
+
+    /// Move `path` to the trash and go on to the next file, or back to the
+    /// welcome screen when it was the last. Nothing changes if it can't be.
+    fn trash_file(&mut self, path: &Path) {
+        if let Err(e) = trash::move_to_trash(path) {
+            log::error!("Failed to move {} to the trash: {e}", path.display());
+            self.error_message = Some(format!("Error: {e}"));
+            return;
+        }
+        log::info!("Moved {} to the trash", path.display());
+        self.navigator.remove(path);
+        match self.navigator.current().map(Path::to_path_buf) {
+            Some(next) => self.load_file_keep_navigator(&next),
+            None => self.close_document(),
+        }
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        self.status_message = Some(format!("Moved {} to the trash", name.to_string_lossy()));
+    }
+
+    /// Show the welcome screen again, with nothing open.
+    fn close_document(&mut self) {
+        self.cancel_loads();
+        self.document = None;
+        self.inspector = None;
+        self.inspected = None;
+        self.parse_failure = None;
+        self.error_message = None;
+        self.gallery.open = false;
+        self.render_state.apply(RenderEvent::Invalidate);
+    }
+
+    /// Report a failed load: on the canvas when the source is malformed, so its
+    /// position and surroundings can be shown, otherwise in the status bar.
+    fn show_load_error(&mut self, path: &Path, error: SvgError) {
+        match error {
+            SvgError::Malformed(diagnostic) => {
+                let filename = path.file_name().map_or_else(
+                    || path.display().to_string(),
+                    |n| n.to_string_lossy().into(),
+                );
+                self.error_message = None;
+                self.parse_failure = Some(ParseFailure {
+                    filename,
+                    diagnostic,
+                });
+            }
+            error => self.error_message = Some(format!("Error: {error}")),
+        }
+    }
+
+    /// Stop the load or reload in flight; another file is being opened.
+    fn cancel_loads(&mut self) {
+        self.reloads.clear();
+        self.queued_load = None;
+        if let Some(pending) = self.pending_load.take() {
+            pending.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Re-read the open document from disk, keeping the view as it is. A
+    /// reload already running is cancelled and restarted with the new content.
+    fn reload(&mut self) {
+        let Some(doc) = &self.document else {
+            return;
+        };
+        if doc.is_in_memory() || self.opening_file() {
+            return;
+        }
+        let path = doc.path().to_path_buf();
+        if let Some(cancel) = self.reloads.changed() {
+            self.error_message = None;
+            self.start_background_load(&path, LoadView::Reload(self.viewport.clone()), cancel);
+        }
+    }
+
+    /// Note the zoom the open file is being left at, for the next time it's opened.
+    fn remember_view(&mut self) {
+        let Some(doc) = self.document.as_ref().filter(|doc| !doc.is_in_memory()) else {
+            return;
+        };
+        let zoom = (!self.viewport.fit_mode.is_fit()).then_some(self.viewport.zoom);
+        self.settings.remember_zoom(doc.path(), zoom);
+    }
+
+    /// Zoom the newly opened file to where it was last left. Returns whether
+    /// the view changed.
+    fn restore_view(&mut self) -> bool {
+        let Some(zoom) = self
+            .document
+            .as_ref()
+            .filter(|doc| !doc.is_in_memory())
+            .and_then(|doc| self.settings.remembered_zoom(doc.path()))
+        else {
+            return false;
+        };
+        self.viewport.zoom = zoom.clamp(viewport::MIN_ZOOM, viewport::MAX_ZOOM);
+        self.viewport.pan = egui::Vec2::ZERO;
+        self.viewport.fit_mode = FitMode::Custom;
+        self.viewport.user_adjusted = true;
+        true
+    }
+
+    /// Copy this session's choices into the settings and write them out.
+    fn save_settings(&mut self) {
+        self.remember_view();
+        if let Some(doc) = self.document.as_ref().filter(|doc| !doc.is_in_memory()) {
+            self.settings.last_file = Some(doc.path().to_path_buf());
+        }
+        self.settings.dark_mode = self.dark_mode;
+        self.settings.show_checkerboard = self.show_checkerboard;
+        let color = self.renderer.color_management();
+        self.settings.color_management = color.enabled;
+        self.settings.display_profile = color.profile;
+        self.settings.high_quality_downscale = self.renderer.high_quality_downscale();
+        self.settings.render_quality = self.renderer.quality();
+        self.settings.constrain_pan = self.constrain_pan;
+        self.settings.show_rulers = self.show_rulers;
+        self.settings.show_thumbnails = self.thumbnails.open;
+        self.settings.copy_white_background = self.copy_white_background;
+        self.settings.export = self.export_dialog.remembered();
+        self.settings.save();
+    }
+
+    /// Keep the watcher on the open document's file while auto-reload is on.
+    fn sync_watcher(&mut self, ctx: &egui::Context) {
+        let path = self
+            .document
+            .as_ref()
+            .filter(|doc| self.auto_reload && !doc.is_in_memory() && !doc.is_remote())
+            .map(|doc| doc.path());
+        match (path, &self.watcher) {
+            (None, _) => self.watcher = None,
+            (Some(path), Some(watcher)) if watcher.path() == path => {}
+            (Some(path), _) => {
+                let ctx = ctx.clone();
+                self.watcher = Some(FileWatcher::watch(path, move || ctx.request_repaint()));
+            }
+        }
+        // Changes wait in the watcher while another file is being opened
+        if self.opening_file() {
+            return;
+        }
+        if let Some(watcher) = self.watcher.as_ref().filter(|w| w.changed()) {
+            log::info!("{} changed on disk; reloading", watcher.path().display());
+            self.reload();
+        }
+    }
+
+    /// Keep a watcher on the folder being navigated, and take in the files
+    /// added to it or removed since the last frame.
+    fn sync_dir_watcher(&mut self, ctx: &egui::Context) {
+        match (self.navigator.dir(), &self.dir_watcher) {
+            (None, _) => self.dir_watcher = None,
+            (Some(dir), Some(watcher)) if watcher.dir() == dir => {}
+            (Some(dir), _) => {
+                let ctx = ctx.clone();
+                self.dir_watcher =
+                    Some(DirectoryWatcher::watch(dir, move || ctx.request_repaint()));
+            }
+        }
+        let Some(files) = self
+            .dir_watcher
+            .as_ref()
+            .and_then(DirectoryWatcher::rescanned)
+        else {
+            return;
+        };
+        let changes = self.navigator.update_files(files);
+        if let Some(message) = changes.describe() {
+            log::info!("Folder rescanned: {message}");
+            self.status_message = Some(message);
+        }
+    }
+
+    /// Load `path` in the background, shown with `view`. The load gives up
+    /// once `cancel` is set.
+    fn start_background_load(&mut self, path: &Path, view: LoadView, cancel: Arc<AtomicBool>) {
         let path = path.to_path_buf();
         let (area_w, area_h) = self.last_area_size;
         let ppp = self.last_pixels_per_point;
-        let (tx, rx) = mpsc::channel();
+        let render_scale = self.render_scale;
+        let quality = self.renderer.quality();
+        let pipeline = self.renderer.pipeline();
+        let reload = matches!(view, LoadView::Reload(_));
+        let locked = matches!(view, LoadView::Locked(_));
+        let pending_path = path.clone();
+        let (generation, tx) = self.load_results.start();
+        let thread_cancel = Arc::clone(&cancel);
 
-        std::thread::spawn(move || {
-            let result = (|| -> Result<LoadedFile, String> {
-                let doc = SvgDocument::load(&path).map_err(|e| format!("{e}"))?;
-                let mut viewport = Viewport::default();
-                if area_w > 0.0 && area_h > 0.0 {
-                    viewport.fit_to_area(doc.width, doc.height, area_w, area_h);
+        let thread = std::thread::spawn(move || {
+            let cancel = thread_cancel;
+            let result = (|| -> Result<Option<LoadedFile>, SvgError> {
+                let start = Instant::now();
+                let doc = SvgDocument::load_with_quality(&path, quality)?;
+                let parsed = start.elapsed();
+                if cancel.load(Ordering::Relaxed) {
+                    log::debug!("Load of {} cancelled after parsing", path.display());
+                    return Ok(None);
+                }
+                let (mut viewport, fit) = match view {
+                    LoadView::Fit => (Viewport::default(), true),
+                    LoadView::Reload(viewport) | LoadView::Locked(viewport) => (viewport, false),
+                };
+                if fit && area_w > 0.0 && area_h > 0.0 {
+                    viewport.fit_to_area(doc.width(), doc.height(), area_w, area_h);
                     // Cap initial zoom so small SVGs don't get blown up beyond 4×
                     viewport.zoom = viewport.zoom.min(MAX_RENDER_SCALE);
                 }
-                let pixmap = Renderer::render_to_pixmap(&doc, &viewport, area_w, area_h, ppp)
-                    .map_err(|e| format!("{e}"))?;
-                let displayed_w = doc.width * viewport.zoom;
-                let displayed_h = doc.height * viewport.zoom;
-                let logical_display_w = displayed_w.min(area_w);
-                let logical_display_h = displayed_h.min(area_h);
-                Ok(LoadedFile {
-                    doc,
-                    pixmap,
-                    viewport,
-                    logical_display_w,
-                    logical_display_h,
-                })
-            })();
-            let _ = tx.send(result);
+                let pixmap = renderer::render_to_pixmap_cancellable(
+                    &doc,
+                    &viewport,
+                    area_w,
+                    area_h,
+                    ppp,
+                    render_scale,
+                    &cancel,
+                )?;
+                let Some(pixmap) = pixmap else {
+                    log::debug!("Load of {} cancelled", path.display());
+                    return Ok(None);
+                };
+                log::info!(
+                    "Loaded {} in {:.1} ms (parse {:.1} ms, render {}x{})",
+                    path.display(),
+                    start.elapsed().as_secs_f64() * 1000.0,
+                    parsed.as_secs_f64() * 1000.0,
+                    pixmap.width(),
+                    pixmap.height()
+                );
+                let display = renderer::display_size(
+                    doc.width(),
+                    doc.height(),
+                    &viewport,
+                    area_w,
+                    area_h,
+                    ppp,
+                );
+                let render =
+                    CanvasRender::new(pixmap, &pipeline, viewport.zoom, display, render_scale);
+                Ok(Some(LoadedFile {
+                    doc,
+                    render,
+                    viewport,
+                    reload,
+                    locked,
+                }))
+            })();
+            let _ = tx.send((generation, result));
+        });
+
+        self.pending_load = Some(PendingLoad {
+            path: pending_path,
+            thread,
+            cancel,
+            reload,
+        });
+        self.render_state.apply(RenderEvent::LoadStarted {
+            had_document: self.document.is_some(),
+        });
+    }
+
+    fn poll_pending_load(&mut self, ctx: &egui::Context) {
+        if let Some(pending) = self.pending_load.take() {
+            // Checked first, so a loader that finished has sent its result
+            let finished = pending.thread.is_finished();
+            // `None` when the loader went away without a word
+            let result = match self.load_results.take() {
+                Some(result) => Some(result),
+                None if finished => None,
+                None => {
+                    // Still loading, put it back and keep polling
+                    self.pending_load = Some(pending);
+                    ctx.request_repaint();
+                    return;
+                }
+            };
+            let mut skipped = 0;
+            if pending.reload {
+                match self.reloads.finished() {
+                    Finished::Restart(cancel) => {
+                        // The file changed again while this reload ran
+                        let path = self.document.as_ref().map(|doc| doc.path().to_path_buf());
+                        if let Some(path) = path {
+                            let view = LoadView::Reload(self.viewport.clone());
+                            self.start_background_load(&path, view, cancel);
+                        }
+                        return;
+                    }
+                    Finished::Show { skipped: n } => skipped = n,
+                }
+            }
+            match result {
+                Some(Ok(Some(loaded))) => {
+                    self.parse_failure = None;
+                    self.renderer.show_render(ctx, loaded.render);
+                    // A reload keeps the view, including changes made while it ran
+                    let view_moved = loaded.reload && self.viewport != loaded.viewport;
+                    if !loaded.reload {
+                        self.viewport = loaded.viewport;
+                        // A locked view carries over, and so do the views before it
+                        if !loaded.locked {
+                            self.view_history.clear();
+                        }
+                    }
+                    let replaced = self.document.replace(Arc::new(loaded.doc));
+                    if !loaded.reload {
+                        self.diff_overlay.file_changed(replaced);
+                    }
+                    self.diff_overlay.invalidate();
+                    self.inspector = None;
+                    self.render_state.apply(RenderEvent::LoadFinished);
+                    if !loaded.reload && !loaded.locked && self.restore_view() {
+                        self.render_state.apply(RenderEvent::Invalidate);
+                    }
+                    if view_moved {
+                        self.render_state.apply(RenderEvent::Invalidate);
+                    }
+                    self.update_empty_notice();
+                    // Loads render quickly; the supersampled render follows
+                    // once the view has been still for a moment
+                    let supersample = self
+                        .document
+                        .as_ref()
+                        .is_some_and(|doc| self.renderer.would_supersample(doc, &self.viewport));
+                    if supersample && !self.render_state.needs_render() {
+                        self.render_state
+                            .apply(RenderEvent::Zoomed { at: Instant::now() });
+                    }
+                    if let Some(message) = reload_queue::skipped_message(skipped) {
+                        self.status_message = Some(message);
+                    }
+                }
+                Some(Ok(None)) => self.render_state.apply(RenderEvent::LoadFailed),
+                Some(Err(e)) => {
+                    log::error!("Background load of {} failed: {e}", pending.path.display());
+                    self.show_load_error(&pending.path, e);
+                    self.render_state.apply(RenderEvent::LoadFailed);
+                }
+                None => {
+                    self.error_message = Some("Loading failed unexpectedly".into());
+                    self.render_state.apply(RenderEvent::LoadFailed);
+                }
+            }
+        }
+    }
+
+    /// Render the view of `doc` on the render worker, superseding any render
+    /// asked for before that hasn't started.
+    fn start_render(&mut self, ctx: &egui::Context, doc: &Arc<SvgDocument>, area: (f32, f32)) {
+        self.render_generation += 1;
+        let worker = self.render_worker.get_or_insert_with(|| {
+            let ctx = ctx.clone();
+            RenderWorker::spawn(move || ctx.request_repaint())
+        });
+        worker.request(
+            self.render_generation,
+            CanvasRequest {
+                doc: Arc::clone(doc),
+                viewport: self.viewport.clone(),
+                area,
+                pixels_per_point: ctx.pixels_per_point(),
+                render_scale: self.render_scale,
+                high_quality_downscale: self.renderer.high_quality_downscale(),
+                pipeline: self.renderer.pipeline(),
+            },
+        );
+        self.pending_render = Some(PendingRender {
+            generation: self.render_generation,
+            doc: Arc::clone(doc),
+        });
+        self.render_state.apply(RenderEvent::RenderStarted);
+    }
+
+    /// Stop waiting for the pending render, if any, skipping it if it hasn't
+    /// started. Whether there was one.
+    fn cancel_render(&mut self) -> bool {
+        let Some(pending) = self.pending_render.take() else {
+            return false;
+        };
+        self.render_generation += 1;
+        if let Some(worker) = &self.render_worker {
+            worker.set_generation(self.render_generation);
+        }
+        log::debug!("Cancelled the render of {}", pending.doc.filename());
+        true
+    }
+
+    /// Show the worker's render once it arrives, if it's still of the open
+    /// document and no newer render was started.
+    fn poll_pending_render(&mut self, ctx: &egui::Context) {
+        let Some(pending) = self.pending_render.take() else {
+            return;
+        };
+        let Some(worker) = &self.render_worker else {
+            return;
+        };
+        // Checked first, so a worker that ended has sent all it finished
+        let ended = worker.is_finished();
+        let mut result = None;
+        for (generation, finished) in worker.finished() {
+            if generation == pending.generation {
+                result = Some(finished);
+            } else {
+                log::debug!("Dropped superseded render {generation}");
+            }
+        }
+        if result.is_none() && !ended {
+            self.pending_render = Some(pending);
+            return;
+        }
+        let current = self
+            .document
+            .as_ref()
+            .is_some_and(|doc| Arc::ptr_eq(doc, &pending.doc));
+        if result.is_some() && !current {
+            log::debug!("Dropped an outdated render of {}", pending.doc.filename());
+            return;
+        }
+        match result {
+            Some(Ok(render)) => {
+                // Sample the new texture for the scale it's shown at
+                let texel_scale = 1.0 / (render.render_scale.factor() * render.headroom);
+                self.renderer.set_sampling(renderer::choose_sampling(
+                    self.renderer.sampling(),
+                    texel_scale,
+                    self.pixel_inspection,
+                ));
+                self.renderer.show_render(ctx, render);
+            }
+            Some(Err(e)) => {
+                log::error!("Rendering {} failed: {e}", pending.doc.filename());
+                self.error_message = Some(format!("Render error: {}", e));
+            }
+            None => {
+                // A new worker is started with the next render
+                self.render_worker = None;
+                self.error_message = Some("Rendering failed unexpectedly".into());
+            }
+        }
+        self.render_state.apply(RenderEvent::RenderFinished);
+        self.diff_overlay.invalidate();
+        self.update_empty_notice();
+    }
+
+    fn handle_action(&mut self, action: ToolbarAction, center: egui::Vec2) {
+        if action.open_file {
+            self.open_file_dialog();
+        }
+        if action.paste_svg {
+            self.paste_svg();
+        }
+        if action.prev_file {
+            self.navigate_prev();
+        }
+        if action.next_file {
+            self.navigate_next();
+        }
+        if action.first_file {
+            self.navigate_first();
+        }
+        if action.last_file {
+            self.navigate_last();
+        }
+        if let Some(index) = action.jump_to {
+            self.navigate_to(index);
+        }
+        if action.delete_file {
+            self.request_delete(action.delete_now);
+        }
+        if let Some(filter) = action.set_filter.clone() {
+            if self.navigator.set_filter(Some(filter)) {
+                if let Some(path) = self.navigator.current().map(Path::to_path_buf) {
+                    self.load_file_keep_navigator(&path);
+                }
+            }
+        }
+        let step = if action.rotate_fine {
+            1.0
+        } else {
+            self.rotation_entry.step_deg
+        };
+        self.apply_view(&action.view_actions(step), center);
+        if action.copy_coordinates {
+            self.copy_coordinates();
+        }
+        if action.toggle_lock_view {
+            self.lock_view = !self.lock_view;
+            self.status_message = Some(if self.lock_view {
+                "View locked for the previous and next files".into()
+            } else {
+                "View unlocked".into()
+            });
+        }
+        if action.toggle_zoom_select {
+            self.zoom_select = !self.zoom_select;
+        }
+        if let Some(settings) = action.diff_overlay {
+            if settings.enabled && !self.diff_overlay.settings.enabled {
+                self.status_message =
+                    Some("Diff overlay: step to another file to see this one under it".into());
+            }
+            self.diff_overlay.set_settings(settings);
+        }
+        if action.toggle_eyedropper {
+            self.eyedropper = !self.eyedropper;
+            self.status_message = self
+                .eyedropper
+                .then(|| "Eyedropper: click the image to copy a color; I leaves".into());
+        }
+        if action.view_back || action.view_forward {
+            self.step_view_history(action.view_forward);
+        }
+        if action.show_event_log {
+            self.event_log_window.open = true;
+        }
+        if action.properties {
+            self.properties.open = true;
+        }
+        if action.image_inspector {
+            self.image_inspector.open = true;
+        }
+        if action.toggle_source_panel {
+            self.source_panel.open = !self.source_panel.open;
+        }
+        if action.toggle_force_meet {
+            self.toggle_force_meet();
+        }
+        if action.folder_summary && self.navigator.file_count() > 0 {
+            self.folder_summary.request(self.navigator.files().to_vec());
+        }
+        if action.edit_rotation && self.document.is_some() {
+            self.rotation_entry.open_with(self.viewport.rotation_deg);
+        }
+        if action.export {
+            if let Some(ref doc) = self.document {
+                self.export_dialog.size_warning = doc.size_warning().map(str::to_string);
+                self.export_dialog.aspect_notice = doc.aspect_notice();
+                self.export_dialog.declared_background = doc.declared_background();
+                self.export_dialog.view_stretch = self
+                    .viewport
+                    .is_stretched()
+                    .then_some(self.viewport.stretch_x);
+                let (area_w, area_h) = self.last_area_size;
+                let ppp = self.last_pixels_per_point;
+                self.export_dialog.view_size = (area_w * ppp, area_h * ppp);
+                self.export_dialog.view_rotation = self.viewport.rotation_deg;
+                self.export_dialog.view_mirrored = self.viewport.mirror_h || self.viewport.mirror_v;
+                self.export_dialog.batch_files = self.navigator.file_count();
+                self.export_dialog.unit_inches = export::inches_per_unit(doc);
+                self.export_dialog.memory_budget = self.settings.export_memory_mb << 20;
+                self.export_dialog
+                    .open_with_dimensions(doc.width(), doc.height());
+            }
+        }
+        if action.export_again {
+            self.export_again();
+        }
+        if action.copy_clipboard {
+            self.copy_to_clipboard();
+        }
+        if action.copy_source {
+            self.copy_svg_source();
+        }
+        if action.copy_view {
+            self.copy_view();
+        }
+        if action.save_svg {
+            self.save_svg_source();
+        }
+        if let Some(policy) = action.view_policy {
+            self.view_policy = policy;
+        }
+        if let Some(scale) = action.render_scale {
+            if scale != self.render_scale {
+                self.render_scale = scale;
+                self.render_state.apply(RenderEvent::Invalidate);
+            }
+        }
+        if let Some(quality) = action.render_quality {
+            self.set_render_quality(quality);
+        }
+        if action.reload {
+            self.reload();
+        }
+        if action.toggle_auto_reload {
+            self.auto_reload = !self.auto_reload;
+        }
+        if action.toggle_measure {
+            self.measure.toggle();
+            self.status_message = self
+                .measure
+                .active
+                .then(|| "Measure: drag on the image; Esc clears, M leaves".into());
+        }
+        if action.clear_measurements {
+            self.measure.clear();
+        }
+        if action.toggle_thumbnails {
+            self.thumbnails.open = !self.thumbnails.open;
+        }
+        if action.toggle_gallery && (self.gallery.open || self.navigator.file_count() > 0) {
+            self.gallery.toggle(self.navigator.current_index());
+        }
+        if action.toggle_rulers {
+            self.show_rulers = !self.show_rulers;
+        }
+        if action.toggle_constrain_pan {
+            self.constrain_pan = !self.constrain_pan;
+            self.constrain_pan();
+        }
+        if action.toggle_declared_background {
+            self.use_declared_background = !self.use_declared_background;
+        }
+        if action.toggle_copy_over_declared {
+            self.copy_over_declared = !self.copy_over_declared;
+        }
+        if action.toggle_copy_white_background {
+            self.copy_white_background = !self.copy_white_background;
+        }
+        if action.toggle_pixel_inspection {
+            self.pixel_inspection = !self.pixel_inspection;
+        }
+        if let Some(color) = action.color_management {
+            if color != self.renderer.color_management() {
+                self.renderer.set_color_management(color);
+                self.render_state.apply(RenderEvent::Invalidate);
+            }
+        }
+        if action.toggle_high_quality_downscale {
+            let enabled = !self.renderer.high_quality_downscale();
+            self.renderer.set_high_quality_downscale(enabled);
+            self.render_state.apply(RenderEvent::Invalidate);
+        }
+        if let Some(effects) = action.effects {
+            if effects != self.renderer.effects() {
+                self.renderer.set_effects(effects);
+                self.render_state.apply(RenderEvent::Invalidate);
+            }
+        }
+        if action.toggle_font_substitutions {
+            self.show_font_substitutions = !self.show_font_substitutions;
+        }
+        if action.toggle_bg {
+            self.show_checkerboard = !self.show_checkerboard;
+        }
+        if action.use_theme_bg {
+            self.canvas_background = None;
+            self.show_checkerboard = false;
+        }
+        if action.use_document_bg {
+            if let Some(bg) = self.document.as_ref().and_then(|d| d.background()) {
+                self.canvas_background = Some(bg.color);
+                self.export_dialog.settings.background_color = bg.color;
+                self.show_checkerboard = false;
+            }
+        }
+        if action.toggle_theme {
+            self.dark_mode = !self.dark_mode;
+        }
+    }
+
+    /// Apply view-only actions and the follow-up work they ask for.
+    fn apply_view(&mut self, actions: &[ViewAction], zoom_center: egui::Vec2) {
+        let ctx = ViewContext {
+            doc_size: self
+                .document
+                .as_ref()
+                .map(|doc| (doc.width(), doc.height())),
+            content: self.document.as_deref().map(SvgDocument::content_bbox),
+            area: self.last_area_size,
+            zoom_center,
+        };
+        let before = self.viewport.clone();
+        let effects = view_action::apply_view_actions(&mut self.viewport, actions, &ctx);
+        self.constrain_pan();
+        if self.viewport != before && actions.iter().any(ViewAction::is_history_step) {
+            self.view_history.record(&before);
+        }
+        if effects.cap_zoom {
+            self.cap_initial_zoom = true;
+        }
+        if effects.needs_rerender || effects.needs_refit {
+            self.render_state.apply(RenderEvent::Invalidate);
+        }
+        if effects.schedule_deferred {
+            self.zoom_changed();
+        }
+    }
+
+    /// Go back to an earlier view, or forward again to an undone one.
+    fn step_view_history(&mut self, forward: bool) {
+        let restored = if forward {
+            self.view_history.forward(&self.viewport)
+        } else {
+            self.view_history.back(&self.viewport)
+        };
+        let Some(viewport) = restored else {
+            return;
+        };
+        self.viewport = viewport;
+        self.constrain_pan();
+        self.render_state.apply(RenderEvent::Invalidate);
+    }
+
+    /// Keep part of the image in view after panning or zooming, unless free
+    /// panning was chosen.
+    fn constrain_pan(&mut self) {
+        let Some(doc) = self.document.as_ref().filter(|_| self.constrain_pan) else {
+            return;
+        };
+        let svg_size = (
+            viewport::usable_dimension(doc.width()),
+            viewport::usable_dimension(doc.height()),
+        );
+        let (shown_w, shown_h) = self.viewport.rotated_size(svg_size.0, svg_size.1);
+        let shown = egui::Vec2::new(shown_w, shown_h) * self.viewport.zoom;
+        let (area_w, area_h) = self.last_area_size;
+        self.viewport
+            .clamp_pan(shown, egui::Vec2::new(area_w, area_h));
+    }
+
+    fn copy_to_clipboard(&mut self) {
+        if let Some(ref doc) = self.document {
+            let width = self.renderer.rendered_width.max(doc.width() as u32);
+            let height = self.renderer.rendered_height.max(doc.height() as u32);
+            // Scale the checker squares so the copy matches what is on screen
+            let shown_w = self.renderer.logical_display_w * self.zoom_ratio();
+            let pixels_per_logical = if shown_w > 0.0 {
+                width as f32 / shown_w
+            } else {
+                self.last_pixels_per_point
+            };
+            let backdrop = self.copy_backdrop(doc, pixels_per_logical);
+            let result =
+                clipboard::copy_to_clipboard(doc, &self.viewport, width, height, backdrop.as_ref());
+            match result {
+                Ok(()) => {
+                    log::info!(
+                        "Copied {} to the clipboard at {width}x{height}",
+                        doc.filename()
+                    );
+                    self.status_message = Some("Copied to clipboard".into());
+                }
+                Err(e) => {
+                    log::error!("Copy to clipboard failed: {e}");
+                    self.error_message = Some(format!("Clipboard error: {}", e));
+                }
+            }
+        }
+    }
+
+    /// Copy what the canvas shows, cropped, zoomed and turned as it is.
+    fn copy_view(&mut self) {
+        let Some(doc) = &self.document else {
+            return;
+        };
+        let (area_w, area_h) = self.last_area_size;
+        if area_w <= 0.0 || area_h <= 0.0 {
+            return;
+        }
+        let image = self.view_image_rect();
+        let ppp = self.last_pixels_per_point;
+        let result = renderer::render_view(doc, &self.viewport, (area_w, area_h), image, ppp)
+            .and_then(|pixmap| {
+                let size = (pixmap.width(), pixmap.height());
+                let backdrop = self.copy_backdrop(doc, size.0 as f32 / area_w);
+                clipboard::copy_pixmap(pixmap, backdrop.as_ref()).map(|()| size)
+            });
+        match result {
+            Ok((width, height)) => {
+                log::info!("Copied the view of {} at {width}x{height}", doc.filename());
+                self.status_message = Some(format!("Copied {width}\u{00D7}{height} view"));
+            }
+            Err(e) => {
+                log::error!("Copy to clipboard failed: {e}");
+                self.error_message = Some(format!("Clipboard error: {}", e));
+            }
+        }
+    }
+
+    /// Where the image lies on the canvas, with the canvas's top-left corner
+    /// at the origin.
+    fn view_image_rect(&self) -> egui::Rect {
+        let (area_w, area_h) = self.last_area_size;
+        let canvas = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(area_w, area_h));
+        let display = egui::vec2(
+            self.renderer.logical_display_w,
+            self.renderer.logical_display_h,
+        );
+        canvas::image_rect(canvas, self.viewport.pan, display, self.zoom_ratio())
+    }
+
+    /// What a copy is flattened onto, if anything: white when asked for, else
+    /// the declared background or the canvas background as chosen.
+    fn copy_backdrop(&self, doc: &SvgDocument, pixels_per_logical: f32) -> Option<Backdrop> {
+        if self.copy_white_background {
+            return Some(Backdrop::Solid([255, 255, 255]));
+        }
+        match doc.declared_background() {
+            Some(color) if self.copy_over_declared => Some(Backdrop::Solid(color)),
+            _ => self
+                .copy_include_background
+                .then(|| self.backdrop(pixels_per_logical)),
+        }
+    }
+
+    fn copy_svg_source(&mut self) {
+        let Some(doc) = &self.document else {
+            return;
+        };
+        match clipboard::copy_svg_source(doc) {
+            Ok(()) => {
+                log::info!("Copied the source of {} to the clipboard", doc.filename());
+                self.status_message = Some("Copied the SVG source".into());
+            }
+            Err(e) => {
+                log::error!("Copy to clipboard failed: {e}");
+                self.error_message = Some(format!("Clipboard error: {}", e));
+            }
+        }
+    }
+
+    /// Save the document's SVG source to a file, decompressed if it's SVGZ; how
+    /// a pasted document is kept.
+    fn save_svg_source(&mut self) {
+        let Some(doc) = &self.document else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .set_file_name(format!("{}.svg", doc.file_stem()))
+            .add_filter("SVG Files", &["svg", "svgz"])
+            .save_file()
+        else {
+            return;
+        };
+        let settings = export::ExportSettings {
+            format: ExportFormat::Svg,
+            svg_original: true,
+            ..Default::default()
+        };
+        match export::write_svg(doc, &settings, &path) {
+            Ok(()) => {
+                log::info!("Saved the SVG source to {}", path.display());
+                self.status_message = Some(format!("Saved {}", path.display()));
+            }
+            Err(e) => {
+                log::error!("Saving the SVG source to {} failed: {e}", path.display());
+                self.error_message = Some(format!("Could not save SVG: {e}"));
+            }
+        }
+    }
+
+    /// The document's declared background, when it's shown instead of the checkerboard.
+    fn declared_backdrop(&self) -> Option<[u8; 3]> {
+        if !self.use_declared_background {
+            return None;
+        }
+        self.document.as_ref()?.declared_background()
+    }
+
+    /// Canvas color behind the image when the checkerboard is off.
+    fn canvas_color(&self) -> [u8; 3] {
+        if let Some(color) = self.declared_backdrop() {
+            color
+        } else if let Some(color) = self.canvas_background {
+            color
+        } else if self.dark_mode {
+            [40, 40, 40]
+        } else {
+            [240, 240, 240]
+        }
+    }
+
+    /// The active canvas background, for an image with `pixels_per_logical`
+    /// pixels per logical canvas pixel.
+    fn backdrop(&self, pixels_per_logical: f32) -> Backdrop {
+        if self.show_checkerboard && self.declared_backdrop().is_none() {
+            Backdrop::Checkerboard {
+                square: CHECKER_SIZE * pixels_per_logical,
+            }
+        } else {
+            Backdrop::Solid(self.canvas_color())
+        }
+    }
+
+    /// How much the displayed texture is scaled while a re-render is pending.
+    fn zoom_ratio(&self) -> f32 {
+        if self.renderer.rendered_zoom > 0.0 {
+            self.viewport.zoom / self.renderer.rendered_zoom
+        } else {
+            1.0
+        }
+    }
+
+    fn do_export(&mut self) {
+        let doc = match &self.document {
+            Some(d) => d,
+            None => return,
+        };
+
+        let settings = self.export_dialog.settings.clone();
+        // The last export's folder, or else the document's
+        let dir = self
+            .settings
+            .last_export_dir
+            .clone()
+            .filter(|dir| dir.is_dir())
+            .or_else(|| {
+                if doc.is_in_memory() {
+                    return None;
+                }
+                let source = doc.path();
+                let on_disk = archive::split_path(source).map_or(source, |(zip, _)| zip);
+                on_disk
+                    .parent()
+                    .filter(|dir| dir.is_dir())
+                    .map(Path::to_path_buf)
+            });
+        // Several scales are named from a base name without a size
+        let scales = self.export_dialog.export_scales();
+        let size = if scales.is_empty() {
+            self.export_dialog.output_size()
+        } else {
+            None
+        };
+        let default_name =
+            export::default_export_name(dir.as_deref(), doc.file_stem(), &settings, size);
+
+        let mut dialog = rfd::FileDialog::new().set_file_name(&default_name);
+        if let Some(dir) = &dir {
+            dialog = dialog.set_directory(dir);
+        }
+
+        if let Some(path) = dialog.save_file() {
+            let path = export::with_default_extension(path, &settings);
+            self.settings.last_export_dir = path.parent().map(Path::to_path_buf);
+            self.write_export(LastExport {
+                source: doc.path().to_path_buf(),
+                output: path,
+                settings,
+                current_view: self.export_dialog.current_view,
+                view_scale: self.export_dialog.view_scale,
+                region: self.export_dialog.region,
+                include_background: self.export_dialog.include_background,
+                over_declared_background: self.export_dialog.over_declared_background,
+                scales,
+            });
+        }
+    }
+
+    /// Write an image embedded in the document out as the file it was
+    /// encoded from, asking where.
+    fn save_embedded_image(&mut self, image: &EmbeddedImage, name: &str) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_file_name(name)
+            .add_filter(image.format.to_uppercase(), &[image.format])
+            .save_file()
+        else {
+            return;
+        };
+        match std::fs::write(&path, image.data.as_slice()) {
+            Ok(()) => {
+                log::info!("Saved embedded image to {}", path.display());
+                self.status_message = Some(format!("Saved image to {}", path.display()));
+            }
+            Err(e) => {
+                log::error!("Saving image to {} failed: {e}", path.display());
+                self.error_message = Some(format!("Could not save image: {e}"));
+            }
+        }
+    }
+
+    /// Repeat the last export for the current document without any dialogs,
+    /// overwriting the output.
+    fn export_again(&mut self) {
+        let Some(doc) = &self.document else {
+            return;
+        };
+        let Some(last) = &self.last_export else {
+            self.status_message = Some("Export once first; Ctrl+E repeats it".into());
+            return;
+        };
+        let source = doc.path().to_path_buf();
+        let export = LastExport {
+            output: export::repeat_export_path(&last.output, &last.source, &source),
+            source,
+            settings: last.settings.clone(),
+            current_view: last.current_view,
+            view_scale: last.view_scale,
+            region: last.region,
+            include_background: last.include_background,
+            over_declared_background: last.over_declared_background,
+            scales: last.scales.clone(),
+        };
+        self.write_export(export);
+    }
+
+    /// Export the current document as `export` describes on a background
+    /// thread, remembering it for Export again when it succeeds.
+    fn write_export(&mut self, export: LastExport) {
+        if self.pending_export.is_some() {
+            self.status_message = Some("The last export is still being written".into());
+            return;
+        }
+        let Some(doc) = self.document.clone() else {
+            return;
+        };
+        let mut outputs = export.outputs();
+        if let Some(color) = doc
+            .declared_background()
+            .filter(|_| export.over_declared_background)
+        {
+            for (_, settings) in &mut outputs {
+                settings.background_color = color;
+                settings.include_alpha = false;
+            }
+        }
+        let settings = &outputs[0].1;
+        let notice = if settings.format == ExportFormat::Svg {
+            None
+        } else if export.current_view {
+            (settings.orientation == export::OrientationMode::Metadata
+                && settings.format.supports_orientation_tag())
+            .then(|| "the current view keeps its rotation in the pixels".to_string())
+        } else {
+            let view = export::export_view(settings, &self.viewport);
+            match export::plan_orientation(settings, &view) {
+                export::OrientationPlan::Bake { notice } => notice,
+                export::OrientationPlan::Tag(_) => None,
+            }
+        };
+        let capture = export.current_view.then(|| {
+            let scale = self.last_pixels_per_point * export.view_scale;
+            ViewCapture {
+                viewport: self.viewport.clone(),
+                canvas: self.last_area_size,
+                image: self.view_image_rect(),
+                scale,
+                backdrop: export.include_background.then(|| self.backdrop(scale)),
+            }
+        });
+        let viewport = self.viewport.clone();
+        let region = export.region;
+        let paths = outputs.iter().map(|(path, _)| path.clone()).collect();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for (path, settings) in &outputs {
+                // An SVG is the document however it's viewed
+                let result = if settings.format == ExportFormat::Svg {
+                    export::write_svg(&doc, settings, path)
+                } else if let Some(region) = &region {
+                    export::export_region(&doc, region, settings, path)
+                } else if let Some(capture) = &capture {
+                    Self::export_current_view(&doc, capture, settings, path)
+                } else {
+                    export::export_svg(&doc, &viewport, settings, path)
+                };
+                if tx.send(result).is_err() {
+                    return;
+                }
+            }
+        });
+        self.pending_export = Some(PendingExport {
+            export,
+            outputs: paths,
+            receiver: rx,
+            results: Vec::new(),
+            notice,
         });
-
-        self.pending_load = Some(PendingLoad { receiver: rx });
     }
 
-    fn poll_pending_load(&mut self, ctx: &egui::Context) {
-        if let Some(pending) = self.pending_load.take() {
+    /// Report a background export once all its outputs are written.
+    fn poll_pending_export(&mut self, ctx: &egui::Context) {
+        let Some(mut pending) = self.pending_export.take() else {
+            return;
+        };
+        while pending.results.len() < pending.outputs.len() {
             match pending.receiver.try_recv() {
-                Ok(Ok(loaded)) => {
-                    self.renderer.upload_pixmap(
-                        ctx,
-                        &loaded.pixmap,
-                        loaded.viewport.zoom,
-                        loaded.logical_display_w,
-                        loaded.logical_display_h,
-                    );
-                    self.viewport = loaded.viewport;
-                    self.document = Some(loaded.doc);
-                    self.render_dirty = false;
-                    self.pending_rerender = false;
-                }
-                Ok(Err(msg)) => {
-                    self.error_message = Some(format!("Error: {msg}"));
-                    log::error!("Background load failed: {msg}");
-                }
+                Ok(result) => pending.results.push(result),
                 Err(mpsc::TryRecvError::Empty) => {
-                    // Still loading, put it back and keep polling
-                    self.pending_load = Some(pending);
+                    self.pending_export = Some(pending);
                     ctx.request_repaint();
+                    return;
                 }
-                Err(mpsc::TryRecvError::Disconnected) => {
-                    self.error_message = Some("Loading failed unexpectedly".into());
+                Err(mpsc::TryRecvError::Disconnected) => pending.results.push(Err(
+                    SvgError::Export("the export stopped unexpectedly".into()),
+                )),
+            }
+        }
+        let mut written = Vec::new();
+        let mut failures = Vec::new();
+        for (path, result) in pending.outputs.iter().zip(pending.results) {
+            match result {
+                Ok(()) => {
+                    log::info!(
+                        "Exported {} as {}",
+                        path.display(),
+                        pending.export.settings.format.name()
+                    );
+                    written.push(path);
+                }
+                Err(e) => {
+                    log::error!("Export to {} failed: {e}", path.display());
+                    failures.push((path, e));
                 }
             }
         }
+        let name = |path: &Path| {
+            path.file_name()
+                .unwrap_or(path.as_os_str())
+                .to_string_lossy()
+                .into_owned()
+        };
+        if !written.is_empty() {
+            let names = match written.as_slice() {
+                [path] => path.display().to_string(),
+                paths => paths
+                    .iter()
+                    .map(|path| name(path))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            };
+            self.status_message = Some(match &pending.notice {
+                Some(notice) => format!("Exported to {names} ({notice})"),
+                None => format!("Exported to {names}"),
+            });
+        }
+        match failures.as_slice() {
+            [] => self.last_export = Some(pending.export),
+            [(_, e)] if pending.outputs.len() == 1 => {
+                self.error_message = Some(format!("Export error: {e}"));
+            }
+            failures => {
+                let errors: Vec<_> = failures
+                    .iter()
+                    .map(|(path, e)| format!("{}: {e}", name(path)))
+                    .collect();
+                self.error_message = Some(format!("Export error: {}", errors.join("; ")));
+            }
+        }
     }
 
-    fn handle_action(&mut self, action: ToolbarAction, center: egui::Vec2) {
-        if action.open_file {
-            self.open_file_dialog();
+    /// Export every file in the folder with the dialog's settings on a
+    /// background thread, each at the scale this one would be.
+    fn export_all(&mut self, ctx: &egui::Context) {
+        let dialog = &self.export_dialog;
+        let out_dir = if dialog.batch_to_folder {
+            match rfd::FileDialog::new().pick_folder() {
+                Some(dir) => Some(dir),
+                None => return,
+            }
+        } else {
+            None
+        };
+        let items = batch::plan_export_items(
+            self.navigator.files(),
+            out_dir.as_deref(),
+            dialog.settings.extension(),
+            dialog.batch_collision,
+        );
+        let options = batch::BatchOptions {
+            keep_going: true,
+            skip_existing: dialog.batch_collision == batch::Collision::Skip,
+            ..Default::default()
+        };
+        let renderer = batch::SettingsRenderer {
+            settings: dialog.settings.clone(),
+            scale: dialog.batch_scale(),
+            quality: self.renderer.quality(),
+        };
+        log::info!("Batch exporting {} files", items.len());
+        let ctx = ctx.clone();
+        self.batch_progress.show(batch::BackgroundBatch::start(
+            items,
+            options,
+            renderer,
+            move || ctx.request_repaint(),
+        ));
+    }
+
+    /// Drop the remembered export when the dialog closed with different
+    /// settings, so Export again doesn't repeat something the user moved away from.
+    fn forget_changed_export(&mut self) {
+        if self.export_dialog.result == ExportDialogResult::Export {
+            return;
         }
-        if action.prev_file {
-            self.navigate_prev();
+        let dialog = &self.export_dialog;
+        let changed = self.last_export.as_ref().is_some_and(|last| {
+            last.settings != dialog.settings
+                || last.current_view != dialog.current_view
+                || last.view_scale != dialog.view_scale
+                || last.region != dialog.region
+                || last.include_background != dialog.include_background
+                || last.over_declared_background != dialog.over_declared_background
+        });
+        if changed {
+            self.last_export = None;
         }
-        if action.next_file {
-            self.navigate_next();
+    }
+
+    /// Export what the canvas showed when `capture` was taken, cropped,
+    /// zoomed and turned as it was, optionally flattened onto its background.
+    fn export_current_view(
+        doc: &SvgDocument,
+        capture: &ViewCapture,
+        settings: &export::ExportSettings,
+        path: &Path,
+    ) -> crate::error::Result<()> {
+        let mut pixmap = renderer::render_view_for_export(
+            doc,
+            &capture.viewport,
+            capture.canvas,
+            capture.image,
+            capture.scale,
+        )?;
+        if let Some(backdrop) = &capture.backdrop {
+            checkerboard::flatten(&mut pixmap, backdrop);
         }
-        if action.fit_to_window {
-            if let Some(ref doc) = self.document {
-                let (w, h) = self.last_area_size;
-                self.viewport.fit_to_area(doc.width, doc.height, w, h);
-                self.render_dirty = true;
+        let metadata = export::ImageMetadata::for_export(doc, settings);
+        export::save_pixmap(&pixmap, settings, metadata.as_ref(), path)
+    }
+
+    /// Apply the view policy after the canvas area changed size. The zoom changes
+    /// immediately; the re-render is deferred so dragging a window edge stays smooth.
+    fn handle_resize(&mut self) {
+        let Some(ref doc) = self.document else {
+            return;
+        };
+        let (w, h) = self.last_area_size;
+        match self
+            .view_policy
+            .on_resize(&self.viewport.fit_mode, self.viewport.user_adjusted)
+        {
+            ResizeAction::Refit => {
+                let size = (doc.width(), doc.height());
+                self.viewport.refit(size, doc.content_bbox(), (w, h));
+            }
+            ResizeAction::Recenter => {
+                self.viewport.pan = egui::Vec2::ZERO;
+                // The zoom no longer tracks the canvas, so a later re-render
+                // mustn't refit it either
+                if self.viewport.fit_mode.is_fit() {
+                    self.viewport.fit_mode = FitMode::Custom;
+                }
             }
+            ResizeAction::Keep => {}
         }
-        if action.actual_size {
-            self.viewport.set_actual_size(1.0);
-            self.render_dirty = true;
+        self.schedule_rerender();
+    }
+
+    /// Explain a blank render. A document with nothing in it only gets a status
+    /// message, since a transparent spacer is meant to look empty.
+    fn update_empty_notice(&mut self) {
+        self.empty_notice = None;
+        let Some(ref doc) = self.document else {
+            return;
+        };
+        if !self.renderer.rendered_blank {
+            return;
         }
-        if action.zoom_in {
-            self.viewport.zoom_in(center);
-            self.schedule_rerender();
+        match doc.content_stats().empty_notice() {
+            EmptyNotice::Spacer => {
+                self.status_message = Some("Empty document: it contains nothing to draw".into());
+            }
+            notice => self.empty_notice = Some(notice),
         }
-        if action.zoom_out {
-            self.viewport.zoom_out(center);
-            self.schedule_rerender();
+    }
+
+    /// Find the source element drawn at screen position `pos` on the canvas.
+    fn inspect_at(&mut self, pos: egui::Pos2, img_rect: egui::Rect) -> Option<InspectedElement> {
+        let doc = self.document.as_ref()?;
+        if self.inspector.is_none() {
+            match Inspector::new(doc) {
+                Ok(inspector) => self.inspector = Some(inspector),
+                Err(e) => {
+                    log::warn!("Can't inspect {}: {e}", doc.filename());
+                    return None;
+                }
+            }
         }
-        if action.rotate_cw {
-            self.viewport.rotate_cw();
-            self.render_dirty = true;
+        let svg_size = (
+            viewport::usable_dimension(doc.width()),
+            viewport::usable_dimension(doc.height()),
+        );
+        let [x, y] = canvas::svg_point(&self.viewport, svg_size, img_rect, pos)?;
+        let hit = self.inspector.as_ref()?.element_at(x, y)?;
+        Some(InspectedElement {
+            id: hit.element.id.clone(),
+            selector: hit.element.css_selector(),
+            bounds_json: hit.bounds_json(),
+        })
+    }
+
+    /// Context menu entries for the element right-clicked on the canvas.
+    fn draw_element_menu(&mut self, ui: &mut egui::Ui) {
+        let Some(element) = &self.inspected else {
+            ui.weak("No element here");
+            return;
+        };
+        let mut copied = None;
+        if ui
+            .add_enabled(element.id.is_some(), egui::Button::new("Copy id"))
+            .on_disabled_hover_text("This element has no id")
+            .clicked()
+        {
+            copied = element.id.clone().map(|id| ("id", id));
         }
-        if action.rotate_ccw {
-            self.viewport.rotate_ccw();
-            self.render_dirty = true;
+        if ui
+            .button("Copy CSS selector")
+            .on_hover_text(&element.selector)
+            .clicked()
+        {
+            copied = Some(("selector", element.selector.clone()));
         }
-        if action.mirror_h {
-            self.viewport.toggle_mirror_h();
-            self.render_dirty = true;
+        if ui
+            .button("Copy bounding box")
+            .on_hover_text(&element.bounds_json)
+            .clicked()
+        {
+            copied = Some(("bounding box", element.bounds_json.clone()));
         }
-        if action.mirror_v {
-            self.viewport.toggle_mirror_v();
-            self.render_dirty = true;
+        if let Some((what, text)) = copied {
+            self.status_message = Some(format!("Copied {what}: {text}"));
+            ui.ctx().copy_text(text);
+            ui.close();
         }
-        if action.export {
-            if let Some(ref doc) = self.document {
-                self.export_dialog
-                    .open_with_dimensions(doc.width, doc.height);
-            }
+    }
+
+    /// Context menu entries for the open file itself. A pasted document or
+    /// standard input has no file, and a download none to reveal.
+    fn draw_file_menu(&mut self, ui: &mut egui::Ui) {
+        let Some(doc) = &self.document else {
+            return;
+        };
+        let path = doc.path().to_path_buf();
+        let on_disk = !doc.is_in_memory() && !doc.is_remote();
+        let mut copied = None;
+        if ui
+            .add_enabled(!doc.is_in_memory(), egui::Button::new("Copy file path"))
+            .on_disabled_hover_text("This document isn't from a file")
+            .clicked()
+        {
+            copied = Some(path.display().to_string());
         }
-        if action.copy_clipboard {
-            self.copy_to_clipboard();
+        if ui
+            .add_enabled(!doc.is_in_memory(), egui::Button::new("Copy file name"))
+            .on_disabled_hover_text("This document isn't from a file")
+            .clicked()
+        {
+            copied = Some(doc.filename().to_string());
         }
-        if action.toggle_bg {
-            self.show_checkerboard = !self.show_checkerboard;
+        if let Some(text) = copied {
+            match clipboard::copy_text(text.clone()) {
+                Ok(()) => self.status_message = Some(format!("Copied {text}")),
+                Err(e) => {
+                    log::error!("Copy to clipboard failed: {e}");
+                    self.error_message = Some(format!("Clipboard error: {}", e));
+                }
+            }
+            ui.close();
         }
-        if action.toggle_theme {
-            self.dark_mode = !self.dark_mode;
+        if ui
+            .add_enabled(on_disk, egui::Button::new("Reveal in file manager"))
+            .on_disabled_hover_text("This document isn't a file on this computer")
+            .clicked()
+        {
+            // An archive entry is shown as its archive
+            let target = archive::split_path(&path).map_or(path.as_path(), |(zip, _)| zip);
+            if let Err(e) = reveal::reveal(target) {
+                log::error!("Revealing {} failed: {e}", target.display());
+                self.error_message = Some(format!("Error: {e}"));
+            }
+            ui.close();
         }
-        if action.reset_view {
-            self.viewport.reset();
-            if let Some(ref doc) = self.document {
-                let (w, h) = self.last_area_size;
-                self.viewport.fit_to_area(doc.width, doc.height, w, h);
+    }
+
+    /// Copy the document point under the pointer, as the status bar shows it.
+    fn copy_coordinates(&mut self) {
+        let Some(point) = self.cursor_svg else {
+            self.status_message = Some("Point at the image to copy its coordinates".into());
+            return;
+        };
+        let text = status_bar::format_coordinates(point);
+        match clipboard::copy_text(text.clone()) {
+            Ok(()) => self.status_message = Some(format!("Copied {text}")),
+            Err(e) => {
+                log::error!("Copy to clipboard failed: {e}");
+                self.error_message = Some(format!("Clipboard error: {}", e));
             }
-            self.cap_initial_zoom = true;
-            self.render_dirty = true;
         }
     }
 
-    fn copy_to_clipboard(&mut self) {
-        if let Some(ref doc) = self.document {
-            let width = self.renderer.rendered_width.max(doc.width as u32);
-            let height = self.renderer.rendered_height.max(doc.height as u32);
-            match clipboard::copy_to_clipboard(doc, &self.viewport, width, height) {
-                Ok(()) => {
-                    self.status_message = Some("Copied to clipboard".into());
-                }
-                Err(e) => {
-                    self.error_message = Some(format!("Clipboard error: {}", e));
-                }
+    fn copy_color(&mut self, color: [u8; 4]) {
+        let text = eyedropper::format_color(color);
+        match clipboard::copy_text(text.clone()) {
+            Ok(()) => self.status_message = Some(format!("Copied {text}")),
+            Err(e) => {
+                log::error!("Copy to clipboard failed: {e}");
+                self.error_message = Some(format!("Clipboard error: {}", e));
             }
         }
     }
 
-    fn do_export(&mut self) {
-        let doc = match &self.document {
-            Some(d) => d,
-            None => return,
+    /// Replace the canvas with the content bounds so off-canvas content shows.
+    fn fit_to_content(&mut self, bounds: [f32; 4]) {
+        let Some(ref doc) = self.document else {
+            return;
         };
+        match doc.with_canvas(bounds) {
+            Ok(fitted) => {
+                self.document = Some(Arc::new(fitted));
+                self.inspector = None;
+                let (w, h) = self.last_area_size;
+                if let Some(ref doc) = self.document {
+                    self.viewport.fit_to_area(doc.width(), doc.height(), w, h);
+                }
+                self.empty_notice = None;
+                self.render_state.apply(RenderEvent::Invalidate);
+            }
+            Err(e) => self.error_message = Some(format!("Error: {e}")),
+        }
+    }
 
-        let settings = self.export_dialog.settings.clone();
-        let default_name = format!(
-            "{}.{}",
-            doc.path.file_stem().unwrap_or_default().to_string_lossy(),
-            settings.format.extension()
-        );
-
-        let file = rfd::FileDialog::new()
-            .set_file_name(&default_name)
-            .save_file();
+    /// Draw with `quality` from now on, re-parsing the document for its
+    /// rendering hints if it was parsed with others.
+    fn set_render_quality(&mut self, quality: RenderQuality) {
+        if quality != self.renderer.quality() {
+            self.renderer.set_quality(quality);
+            self.render_state.apply(RenderEvent::Invalidate);
+        }
+        let Some(ref doc) = self.document else {
+            return;
+        };
+        if doc.quality() == quality {
+            return;
+        }
+        match doc.with_quality(quality) {
+            Ok(doc) => {
+                self.document = Some(Arc::new(doc));
+                self.inspector = None;
+                self.render_state.apply(RenderEvent::Invalidate);
+            }
+            Err(e) => self.error_message = Some(format!("Error: {e}")),
+        }
+    }
 
-        if let Some(path) = file {
-            match export::export_svg(doc, &self.viewport, &settings, &path) {
-                Ok(()) => {
-                    self.status_message = Some(format!("Exported to {}", path.display()));
-                }
-                Err(e) => {
-                    self.error_message = Some(format!("Export error: {}", e));
-                }
+    /// Switch between the document's own preserveAspectRatio and showing the
+    /// whole viewBox centered.
+    fn toggle_force_meet(&mut self) {
+        let Some(ref doc) = self.document else {
+            return;
+        };
+        let toggled = if doc.is_meet_forced() {
+            doc.as_declared()
+        } else {
+            doc.with_meet()
+        };
+        match toggled {
+            Ok(doc) => {
+                self.document = Some(Arc::new(doc));
+                self.inspector = None;
+                self.render_state.apply(RenderEvent::Invalidate);
             }
+            Err(e) => self.error_message = Some(format!("Error: {e}")),
         }
     }
 
+    fn apply_position_action(&mut self, action: PositionAction) {
+        let image_size = egui::Vec2::new(
+            self.renderer.logical_display_w,
+            self.renderer.logical_display_h,
+        ) * self.zoom_ratio();
+        let canvas_size = egui::Vec2::new(self.last_area_size.0, self.last_area_size.1);
+        let pan = match action {
+            PositionAction::Set(pan) => pan,
+            PositionAction::Center => egui::Vec2::ZERO,
+            PositionAction::TopLeft => Viewport::top_left_pan(image_size, canvas_size),
+            PositionAction::SnapToPixel => self.viewport.snapped_pan(
+                image_size,
+                self.canvas_origin.to_vec2(),
+                canvas_size,
+                self.last_pixels_per_point,
+            ),
+            PositionAction::Nudge(delta) => self.viewport.pan + delta,
+        };
+        self.viewport.set_pan(pan);
+    }
+
     fn schedule_rerender(&mut self) {
-        self.zoom_idle_since = Some(Instant::now());
-        self.pending_rerender = true;
+        self.render_state
+            .apply(RenderEvent::Zoomed { at: Instant::now() });
     }
 
-    fn check_deferred_rerender(&mut self) {
-        if self.pending_rerender {
-            if let Some(since) = self.zoom_idle_since {
-                if since.elapsed().as_millis() >= 150 {
-                    self.render_dirty = true;
-                    self.pending_rerender = false;
-                    self.zoom_idle_since = None;
-                }
-            }
+    /// Re-render once zooming settles, unless the current render scaled to
+    /// the new zoom is as good as a new one.
+    fn zoom_changed(&mut self) {
+        let (area_w, area_h) = self.last_area_size;
+        let covered = self.document.as_deref().is_some_and(|doc| {
+            self.renderer.covers_zoom(
+                doc,
+                &self.viewport,
+                area_w,
+                area_h,
+                self.last_pixels_per_point,
+            )
+        });
+        if !covered {
+            self.schedule_rerender();
         }
     }
 }
 
 impl eframe::App for SvgViewerApp {
+    fn save(&mut self, _storage: &mut dyn eframe::Storage) {
+        self.save_settings();
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.save_settings();
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.last_pixels_per_point = ctx.pixels_per_point();
 
-        // Load initial file on first frame
-        if let Some(path) = self.initial_file.take() {
-            self.load_file(&path);
+        // Load initial files on first frame. URLs wait for the canvas to be laid
+        // out, so they download in the background rather than blocking it.
+        let laid_out = self.last_area_size.0 > 0.0;
+        if laid_out || !self.initial_files.iter().any(|p| remote::is_url(p)) {
+            let initial_files = std::mem::take(&mut self.initial_files);
+            self.open_files(initial_files);
+        } else {
+            ctx.request_repaint();
         }
 
         // Poll for completed background loads
+        self.start_queued_load(ctx);
         self.poll_pending_load(ctx);
+        self.poll_pending_render(ctx);
+        self.poll_pending_export(ctx);
+        self.sync_watcher(ctx);
+        self.sync_dir_watcher(ctx);
 
         // Apply theme
         if self.dark_mode {
@@ -357,7 +2238,34 @@ impl eframe::App for SvgViewerApp {
 
         // Handle keyboard shortcuts
         let has_file = self.document.is_some();
-        let kb_action = shortcuts::handle_shortcuts(ctx, has_file);
+        // Open dialogs own the keyboard
+        let kb_action = if self.export_dialog.open
+            || self.pending_size_confirm.is_some()
+            || self.pending_delete.is_some()
+            || self.rotation_entry.open
+            || self.position_popover.open
+            || self.readout_entry.is_editing()
+            || ctx.wants_keyboard_input()
+        {
+            ToolbarAction::default()
+        } else if self.gallery.open {
+            // The gallery moves its own selection with the arrow keys
+            let action = shortcuts::handle_shortcuts(ctx, has_file);
+            ToolbarAction {
+                open_file: action.open_file,
+                paste_svg: action.paste_svg,
+                toggle_gallery: action.toggle_gallery,
+                ..ToolbarAction::default()
+            }
+        } else {
+            let mut action = shortcuts::handle_shortcuts(ctx, has_file);
+            // Home centers the image, and once it's centered goes to the first file
+            if action.center_pan && self.viewport.pan == egui::Vec2::ZERO {
+                action.center_pan = false;
+                action.first_file = true;
+            }
+            action
+        };
 
         // Handle dropped files
         let dropped: Vec<PathBuf> = ctx.input(|i| {
@@ -367,19 +2275,110 @@ impl eframe::App for SvgViewerApp {
                 .filter_map(|f| f.path.clone())
                 .collect()
         });
-        if let Some(path) = dropped.into_iter().next() {
-            self.load_file(&path);
-        }
+        self.open_files(dropped);
 
         // Top toolbar
         egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
-            let tb_action = toolbar::draw_toolbar(ui, has_file);
+            let document_bg = self.document.as_ref().and_then(|d| d.background());
+            let state = ToolbarState {
+                has_file,
+                can_reload: self.document.as_ref().is_some_and(|d| !d.is_in_memory()),
+                has_folder: self.navigator.file_count() > 0,
+                can_navigate: has_file
+                    && self
+                        .document
+                        .as_ref()
+                        .is_some_and(|d| !d.is_remote() && !d.is_in_memory()),
+                can_export_again: self.last_export.is_some() && self.pending_export.is_none(),
+                pixel_inspection: self.pixel_inspection,
+                auto_reload: self.auto_reload,
+                constrain_pan: self.constrain_pan,
+                show_rulers: self.show_rulers,
+                show_thumbnails: self.thumbnails.open,
+                gallery: self.gallery.open,
+                has_declared_background: self
+                    .document
+                    .as_ref()
+                    .is_some_and(|d| d.declared_background().is_some()),
+                use_declared_background: self.use_declared_background,
+                copy_over_declared: self.copy_over_declared,
+                copy_white_background: self.copy_white_background,
+                show_font_substitutions: self.show_font_substitutions,
+                force_meet: self
+                    .document
+                    .as_deref()
+                    .filter(|doc| !doc.view_box_fit().is_default())
+                    .map(SvgDocument::is_meet_forced),
+                color_management: self.renderer.color_management(),
+                effects: self.renderer.effects(),
+                high_quality_downscale: self.renderer.high_quality_downscale(),
+                source_panel: self.source_panel.open,
+                zoom_percent: self.viewport.zoom_percent(),
+                zoom_preset: ZoomPreset::current(&self.viewport),
+                can_view_back: self.view_history.can_go_back(),
+                can_view_forward: self.view_history.can_go_forward(),
+                zoom_select: self.zoom_select,
+                eyedropper: self.eyedropper,
+                diff_overlay: self.diff_overlay.settings,
+                measure_mode: self.measure.active,
+                lock_view: self.lock_view,
+                stretched: self.viewport.is_stretched(),
+                view_policy: self.view_policy,
+                render_scale: self.render_scale,
+                render_quality: self.renderer.quality(),
+            };
+            let tb_action = toolbar::draw_toolbar(
+                ui,
+                state,
+                document_bg,
+                &mut self.copy_include_background,
+                &mut self.guides,
+                &mut self.filter_text,
+            );
             // Keyboard/toolbar zoom should zoom centered on the canvas (Vec2::ZERO),
             // not offset by half the area size (which would shift toward top-left).
+            if kb_action.quit {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
             self.handle_action(tb_action, egui::Vec2::ZERO);
             self.handle_action(kb_action, egui::Vec2::ZERO);
         });
 
+        if self.renderer.software_fallback() {
+            egui::TopBottomPanel::top("texture_notice").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        "\u{26A0} The GPU texture couldn't be created; showing a reduced preview",
+                    );
+                    if ui.button("Retry").clicked() {
+                        self.renderer.retry_texture();
+                    }
+                });
+            });
+        }
+
+        if let Some(EmptyNotice::Empty {
+            message,
+            fit_bounds,
+        }) = self.empty_notice.clone()
+        {
+            egui::TopBottomPanel::top("empty_notice").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("\u{2139} {message}"));
+                    if let Some(bounds) = fit_bounds {
+                        if ui
+                            .button("Fit to content")
+                            .on_hover_text("Show the content's bounds instead of the canvas")
+                            .clicked()
+                        {
+                            self.fit_to_content(bounds);
+                        }
+                    }
+                });
+            });
+        }
+
         // Bottom status bar
         egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
             let position = self.navigator.position_display();
@@ -388,98 +2387,560 @@ impl eframe::App for SvgViewerApp {
             } else {
                 None
             };
-            status_bar::draw_status_bar(
+            let status_action = status_bar::draw_status_bar(
                 ui,
-                self.document.as_ref(),
+                self.document.as_deref(),
                 &self.viewport,
                 &position,
                 self.error_message.as_deref(),
-                render_size,
+                status_bar::RenderInfo {
+                    size: render_size,
+                    scale: self.render_scale,
+                    color: self.renderer.color_management(),
+                    supersample: self.renderer.rendered_supersample,
+                    cursor: self.cursor_svg,
+                    color_under_pointer: self.hover_color,
+                },
+                &mut self.readout_entry,
             );
+            if let Some(index) = status_action.jump_to {
+                let action = ToolbarAction {
+                    jump_to: Some(index),
+                    ..ToolbarAction::default()
+                };
+                self.handle_action(action, egui::Vec2::ZERO);
+            }
+            if let Some(zoom) = status_action.set_zoom {
+                self.apply_view(&[ViewAction::SetZoom(zoom)], egui::Vec2::ZERO);
+            }
+            if status_action.reset_orientation {
+                self.apply_view(&[ViewAction::ResetOrientation], egui::Vec2::ZERO);
+            }
+            if status_action.edit_rotation {
+                self.rotation_entry.open_with(self.viewport.rotation_deg);
+            }
+            if status_action.edit_position {
+                self.position_popover.open = !self.position_popover.open;
+            }
             if self.error_message.is_none() {
-                if let Some(ref msg) = self.status_message {
+                if let Some(pending) = &self.pending_export {
+                    ui.spinner();
+                    let (done, total) = (pending.results.len(), pending.outputs.len());
+                    let path = &pending.outputs[done.min(total - 1)];
+                    if total > 1 {
+                        ui.label(format!(
+                            "Exporting {} of {total} to {}\u{2026}",
+                            done + 1,
+                            path.display()
+                        ));
+                    } else {
+                        ui.label(format!("Exporting to {}\u{2026}", path.display()));
+                    }
+                } else if let Some(ref msg) = self.status_message {
                     ui.label(msg);
                 }
             }
         });
 
+        // Size confirmation, drawn before the dialog so the Enter that closed the
+        // dialog doesn't also confirm
+        if let Some(plan) = self.pending_size_confirm.clone() {
+            match export_dialog::draw_size_confirm(ctx, &plan) {
+                Some(SizeConfirmChoice::Proceed) => {
+                    self.pending_size_confirm = None;
+                    self.do_export();
+                }
+                Some(SizeConfirmChoice::Adjust) => {
+                    self.pending_size_confirm = None;
+                    self.export_dialog.adjust_to(plan.actual);
+                    self.export_dialog.open = true;
+                }
+                Some(SizeConfirmChoice::Cancel) => self.pending_size_confirm = None,
+                None => {}
+            }
+        }
+
+        if let Some(path) = self.pending_delete.clone() {
+            match delete_confirm::draw_delete_confirm(ctx, &path) {
+                Some(true) => {
+                    self.pending_delete = None;
+                    self.trash_file(&path);
+                }
+                Some(false) => self.pending_delete = None,
+                None => {}
+            }
+        }
+
+        event_log_window::draw_event_log(ctx, &mut self.event_log_window);
+        properties::draw_properties(ctx, &mut self.properties, self.document.as_deref());
+        if let Some((image, name)) = image_inspector::draw_image_inspector(
+            ctx,
+            &mut self.image_inspector,
+            self.document.as_deref(),
+        ) {
+            self.save_embedded_image(&image, &name);
+        }
+
+        if let Some(path) = folder_summary::draw_folder_summary(ctx, &mut self.folder_summary) {
+            self.load_file(&path);
+        }
+        if let Some(path) = batch_progress::draw_batch_progress(ctx, &mut self.batch_progress) {
+            self.load_file(&path);
+        }
+
+        if let Some(angle) = rotation_entry::draw_rotation_entry(ctx, &mut self.rotation_entry) {
+            self.apply_view(&[ViewAction::SetRotation(angle)], egui::Vec2::ZERO);
+        }
+
+        measurements::draw_measurement_list(ctx, &mut self.measure);
+        if let Some(action) = position_popover::draw_position_popover(
+            ctx,
+            &mut self.position_popover,
+            self.viewport.pan,
+        ) {
+            self.apply_position_action(action);
+        }
+
         // Export dialog
+        self.export_dialog.exporting = self.pending_export.is_some();
+        let dialog_was_open = self.export_dialog.open;
         export_dialog::draw_export_dialog(ctx, &mut self.export_dialog);
+        if dialog_was_open && !self.export_dialog.open {
+            self.forget_changed_export();
+        }
         if self.export_dialog.result == ExportDialogResult::Export {
             self.export_dialog.result = ExportDialogResult::None;
-            self.do_export();
+            self.export_dialog.remember_scale();
+            let settings = &self.export_dialog.settings;
+            let plan = export::plan_size(&settings.format, settings.width, settings.height);
+            let rendered = !self.export_dialog.current_view
+                && settings.format.has_dimensions()
+                && !settings.is_vector_pdf();
+            if plan.is_reduced() && rendered {
+                self.pending_size_confirm = Some(plan);
+            } else {
+                self.do_export();
+            }
+        } else if self.export_dialog.result == ExportDialogResult::ExportAll {
+            self.export_dialog.result = ExportDialogResult::None;
+            self.export_dialog.remember_scale();
+            self.export_all(ctx);
         } else if self.export_dialog.result == ExportDialogResult::Cancel {
             self.export_dialog.result = ExportDialogResult::None;
+        } else if self.export_dialog.result == ExportDialogResult::SelectRegion {
+            self.export_dialog.result = ExportDialogResult::None;
+            self.region_select = true;
+            self.status_message =
+                Some("Drag over the image to choose the region to export (Esc cancels)".into());
+        }
+        if self.region_select && ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.region_select = false;
+            self.status_message = None;
+            self.export_dialog.open = true;
+        }
+
+        if let Some(source) =
+            source_panel::draw_source_panel(ctx, &mut self.source_panel, self.document.as_deref())
+        {
+            match clipboard::copy_text(source) {
+                Ok(()) => self.status_message = Some("Copied the source".into()),
+                Err(e) => {
+                    log::error!("Copy to clipboard failed: {e}");
+                    self.error_message = Some(format!("Clipboard error: {}", e));
+                }
+            }
+        }
+
+        let files = self.navigator.files();
+        let current = self.navigator.current_index();
+        let opened = if self.gallery.open {
+            egui::CentralPanel::default()
+                .show(ctx, |ui| {
+                    gallery::draw_gallery(
+                        ui,
+                        &mut self.gallery,
+                        &mut self.thumbnail_textures,
+                        files,
+                    )
+                })
+                .inner
+        } else {
+            thumbnail_strip::draw_thumbnail_strip(
+                ctx,
+                &mut self.thumbnails,
+                &mut self.thumbnail_textures,
+                files,
+                current,
+            )
+        };
+        self.thumbnail_textures.release_unused();
+        if let Some(index) = opened {
+            self.gallery.open = false;
+            if index != current {
+                if let Some(path) = self.navigator.jump_to(index).map(Path::to_path_buf) {
+                    self.load_file_keep_navigator(&path);
+                }
+            }
+        }
+        if self.gallery.open {
+            return;
         }
 
         // Central panel - canvas
         egui::CentralPanel::default().show(ctx, |ui| {
+            if let Some(failure) = &self.parse_failure {
+                // What the next document opened will be fitted to
+                let area = ui.available_size();
+                self.last_area_size = (area.x, area.y);
+                canvas::draw_parse_error(ui, &failure.filename, &failure.diagnostic);
+                return;
+            }
             if self.document.is_none() {
+                // What a document opened from here will be fitted to
+                let area = ui.available_size();
+                self.last_area_size = (area.x, area.y);
                 canvas::draw_welcome(ui);
                 return;
             }
 
+            // Rulers take a strip off the top and left; the canvas is the rest
+            let full = ui.available_rect_before_wrap();
+            let mut canvas_ui;
+            let ui = if self.show_rulers {
+                let (_, _, canvas_rect) = rulers::layout(full);
+                canvas_ui = ui.new_child(egui::UiBuilder::new().max_rect(canvas_rect));
+                &mut canvas_ui
+            } else {
+                ui
+            };
+
             let area = ui.available_size();
+            let previous_area = self.last_area_size;
             self.last_area_size = (area.x, area.y);
+            if previous_area.0 > 0.0 && previous_area != self.last_area_size {
+                self.handle_resize();
+            }
 
-            // Auto-fit on first render or when area changes significantly
-            if self.render_dirty {
+            // Fit on first render, and again after rotation while fitted
+            if self.render_state.needs_render() {
                 if let Some(ref doc) = self.document {
-                    if self.viewport.fit_mode == crate::viewport::FitMode::Fit {
+                    if self.viewport.fit_mode.is_fit() {
+                        let size = (doc.width(), doc.height());
                         self.viewport
-                            .fit_to_area(doc.width, doc.height, area.x, area.y);
+                            .refit(size, doc.content_bbox(), (area.x, area.y));
                         // Cap initial zoom so small SVGs don't get blown up beyond 4×
                         if self.cap_initial_zoom {
-                            self.viewport.zoom =
-                                self.viewport.zoom.min(MAX_RENDER_SCALE);
+                            self.viewport.zoom = self.viewport.zoom.min(MAX_RENDER_SCALE);
                             self.cap_initial_zoom = false;
                         }
                     }
                 }
             }
 
-            // Render SVG to texture if dirty
-            if self.render_dirty {
-                if let Some(ref doc) = self.document {
-                    if let Err(e) =
-                        self.renderer
-                            .render_and_upload(ctx, doc, &self.viewport, area.x, area.y)
-                    {
-                        self.error_message = Some(format!("Render error: {}", e));
-                    }
-                    self.render_dirty = false;
+            // Pick sampling for the scale the texture is about to be shown at
+            let texel_scale = self
+                .renderer
+                .texel_scale(self.zoom_ratio(), ctx.pixels_per_point());
+            self.renderer.set_sampling(renderer::choose_sampling(
+                self.renderer.sampling(),
+                texel_scale,
+                self.pixel_inspection,
+            ));
+
+            // Documents much larger than the window are drawn as tiles, which
+            // pan without re-rendering; anything else renders the whole view
+            let tiled = self.document.as_ref().is_some_and(|doc| {
+                tiles::wants_tiles(
+                    &self.viewport,
+                    (
+                        viewport::usable_dimension(doc.width()),
+                        viewport::usable_dimension(doc.height()),
+                    ),
+                    (area.x, area.y),
+                )
+            }) && !self.diff_overlay.is_showing()
+                && !self.renderer.software_fallback();
+            if !tiled && self.renderer.is_tiled() {
+                self.render_state.apply(RenderEvent::Invalidate);
+            }
+            if let (true, Some(doc)) = (tiled, &self.document) {
+                self.renderer.show_tiles(
+                    ctx,
+                    doc,
+                    &self.viewport,
+                    (area.x, area.y),
+                    self.render_scale,
+                );
+                // Tiles replace any whole render still running
+                let rendering = self.cancel_render();
+                if self.render_state.needs_render() || rendering {
+                    self.render_state.apply(RenderEvent::Rendered);
+                    self.diff_overlay.invalidate();
+                    self.update_empty_notice();
+                }
+            } else if self.render_state.needs_render() {
+                // Render SVG to texture if dirty, off this thread
+                if let Some(doc) = self.document.clone() {
+                    self.start_render(ctx, &doc, (area.x, area.y));
                 }
             }
+            self.diff_overlay.update(
+                ctx,
+                &self.renderer,
+                self.document.as_deref(),
+                &self.viewport,
+                (area.x, area.y),
+                self.render_scale,
+            );
 
-            let bg_color = if self.dark_mode {
-                egui::Color32::from_rgb(40, 40, 40)
-            } else {
-                egui::Color32::from_rgb(240, 240, 240)
-            };
+            let [r, g, b] = self.canvas_color();
+            let bg_color = egui::Color32::from_rgb(r, g, b);
 
             let display_size = egui::Vec2::new(
                 self.renderer.logical_display_w,
                 self.renderer.logical_display_h,
             );
-            let zoom_ratio = if self.renderer.rendered_zoom > 0.0 {
-                self.viewport.zoom / self.renderer.rendered_zoom
-            } else {
-                1.0
-            };
+            let zoom_ratio = self.zoom_ratio();
 
+            self.renderer.maintain();
+            let image = self.renderer.presentation();
+            // The diff overlay's previous file goes underneath, and while tinting
+            // the current file is drawn from the overlay's tinted render
+            let zoom = self.viewport.zoom;
+            let previous = self.diff_overlay.previous_presentation(zoom);
+            let tinted = self.diff_overlay.current_presentation(zoom);
+            let current = match &tinted {
+                Some((image, size)) => Some((image, *size)),
+                None => image
+                    .as_ref()
+                    .map(|image| (image, display_size * zoom_ratio)),
+            };
+            let layers: Vec<_> = previous
+                .iter()
+                .map(|(image, size)| canvas::CanvasLayer {
+                    image,
+                    size: *size,
+                    tint: self.diff_overlay.previous_tint(),
+                })
+                .chain(current.map(|(image, size)| canvas::CanvasLayer {
+                    image,
+                    size,
+                    tint: egui::Color32::WHITE,
+                }))
+                .collect();
             let (response, rect) = canvas::draw_canvas(
                 ui,
-                self.renderer.texture.as_ref(),
+                &layers,
                 self.viewport.pan,
-                self.show_checkerboard,
+                self.show_checkerboard && self.declared_backdrop().is_none(),
                 bg_color,
-                display_size,
-                zoom_ratio,
             );
+            self.canvas_origin = rect.min;
+            let svg_size = self.document.as_ref().map_or((1.0, 1.0), |doc| {
+                (
+                    viewport::usable_dimension(doc.width()),
+                    viewport::usable_dimension(doc.height()),
+                )
+            });
+            let to_svg = |viewport: &Viewport, pos: egui::Pos2| {
+                viewport.screen_to_svg(pos, rect, display_size * zoom_ratio, svg_size)
+            };
+            self.cursor_svg = response
+                .hover_pos()
+                .and_then(|pos| to_svg(&self.viewport, pos));
+            if let Some(doc) = self.document.as_ref().filter(|_| self.show_rulers) {
+                let view = rulers::RulerView {
+                    viewport: &self.viewport,
+                    svg_size: (
+                        viewport::usable_dimension(doc.width()),
+                        viewport::usable_dimension(doc.height()),
+                    ),
+                    canvas: rect,
+                    display_size: display_size * zoom_ratio,
+                    pointer: response.hover_pos(),
+                };
+                rulers::draw_rulers(ui, full, &view);
+            }
+            self.hover_color = None;
+            if let (Some(doc), Some(_)) = (&self.document, &image) {
+                let img_rect =
+                    canvas::image_rect(rect, self.viewport.pan, display_size, zoom_ratio);
+                if self.eyedropper {
+                    // The render fills img_rect, so the pointer's place across it
+                    // is its place across the pixmap
+                    self.hover_color = response.hover_pos().and_then(|pos| {
+                        let across = (pos - img_rect.min) / img_rect.size();
+                        self.renderer.pick_color([across.x, across.y])
+                    });
+                }
+                let svg_size = (
+                    viewport::usable_dimension(doc.width()),
+                    viewport::usable_dimension(doc.height()),
+                );
+                if self.show_font_substitutions {
+                    canvas::draw_substitutions(
+                        ui,
+                        rect,
+                        img_rect,
+                        doc.substituted_text(),
+                        &self.viewport,
+                        svg_size,
+                    );
+                }
+                canvas::draw_guides(
+                    ui,
+                    rect,
+                    img_rect,
+                    self.guides.active(),
+                    &self.viewport,
+                    svg_size,
+                );
+                let live = self.measure.dragging.and_then(|start| {
+                    let pos = ctx.input(|i| i.pointer.interact_pos())?;
+                    let end = to_svg(&self.viewport, pos)?;
+                    Some(Measurement::new(start, end))
+                });
+                measurements::draw_measurements(
+                    ui,
+                    rect,
+                    img_rect,
+                    &self.viewport,
+                    svg_size,
+                    self.measure.measurements.iter(),
+                    live,
+                );
+            }
+            if let Some(text) = self.render_state.freshness(zoom_ratio).badge_text() {
+                canvas::draw_badge(ui, rect, text);
+            } else if !self.renderer.tiles_complete() {
+                canvas::draw_badge(ui, rect, "rendering tiles\u{2026}");
+            }
+
+            // Right-click names the element under the pointer
+            if response.secondary_clicked() {
+                let img_rect =
+                    canvas::image_rect(rect, self.viewport.pan, display_size, zoom_ratio);
+                self.inspected = response
+                    .interact_pointer_pos()
+                    .and_then(|pos| self.inspect_at(pos, img_rect));
+            }
+            if self.document.is_some() {
+                response.context_menu(|ui| {
+                    self.draw_element_menu(ui);
+                    ui.separator();
+                    self.draw_file_menu(ui);
+                });
+            }
 
-            // Handle drag to pan
-            if response.dragged() {
+            // Primary button: small movements are clicks, larger ones pan.
+            // Holding Space makes any drag a pan, as in design tools; Ctrl,
+            // or the zoom-to-rectangle mode, makes it select an area to zoom to.
+            let (pressed, down, released, pointer, time, space, ctrl) = ctx.input(|i| {
+                (
+                    i.pointer.primary_pressed(),
+                    i.pointer.primary_down(),
+                    i.pointer.primary_released(),
+                    i.pointer.interact_pos(),
+                    i.time,
+                    i.key_down(egui::Key::Space),
+                    i.modifiers.command,
+                )
+            });
+            let space = space && !ctx.wants_keyboard_input();
+            if let Some(pos) = pointer {
+                if pressed && response.hovered() && self.document.is_some() {
+                    if space {
+                        self.canvas_interaction.press_to_pan(pos);
+                    } else if ctrl || self.zoom_select || self.region_select {
+                        self.selection_start = Some(pos);
+                    } else if self.eyedropper {
+                        match self.hover_color {
+                            Some(color) => self.copy_color(color),
+                            None => self.status_message = Some("No color here to copy".into()),
+                        }
+                    } else if self.measure.active {
+                        self.measure.dragging = to_svg(&self.viewport, pos);
+                    } else {
+                        self.canvas_interaction.press(pos);
+                    }
+                }
+                if down {
+                    if let Some(start) = self.selection_start {
+                        canvas::draw_selection(ui, rect, egui::Rect::from_two_pos(start, pos));
+                    } else if let Some(delta) = self.canvas_interaction.drag_to(pos) {
+                        self.viewport.pan_by(delta);
+                        self.constrain_pan();
+                    }
+                }
+                if released {
+                    if let Some(start) = self.selection_start.take() {
+                        let selection = egui::Rect::from_two_pos(start, pos);
+                        if self.region_select {
+                            let corners = [
+                                selection.left_top(),
+                                selection.right_top(),
+                                selection.left_bottom(),
+                                selection.right_bottom(),
+                            ]
+                            .map(|corner| to_svg(&self.viewport, corner));
+                            let corners: Vec<[f32; 2]> = corners.into_iter().flatten().collect();
+                            match export::ExportRegion::from_corners(&corners) {
+                                Ok(region) => {
+                                    self.region_select = false;
+                                    self.status_message = None;
+                                    self.export_dialog.reopen_with_region(region);
+                                }
+                                Err(e) => {
+                                    self.status_message =
+                                        Some(format!("{e}; drag out a rectangle, or press Esc"))
+                                }
+                            }
+                        } else {
+                            let display = canvas::image_rect(
+                                rect,
+                                self.viewport.pan,
+                                display_size,
+                                zoom_ratio,
+                            );
+                            self.apply_view(
+                                &[ViewAction::ZoomToRect { selection, display }],
+                                egui::Vec2::ZERO,
+                            );
+                        }
+                    }
+                    if let Some(start) = self.measure.dragging.take() {
+                        let end = to_svg(&self.viewport, pos);
+                        let measurement = end.map(|end| Measurement::new(start, end));
+                        if let Some(measurement) = measurement.filter(|m| !m.is_empty()) {
+                            self.measure.measurements.push(measurement);
+                        }
+                    }
+                    let click = self.canvas_interaction.release(pos, time);
+                    if click.is_some_and(|c| c.double) {
+                        self.apply_view(&[ViewAction::FitToWindow], egui::Vec2::ZERO);
+                    }
+                }
+            }
+            if !down && !released {
+                self.canvas_interaction.cancel();
+                self.selection_start = None;
+                self.measure.dragging = None;
+            }
+
+            // Middle button: always pans, as in CAD viewers
+            let middle_drag = response.dragged_by(egui::PointerButton::Middle);
+            if middle_drag {
                 self.viewport.pan_by(response.drag_delta());
+                self.constrain_pan();
+            }
+            if middle_drag || self.canvas_interaction.is_panning() {
+                ctx.set_cursor_icon(egui::CursorIcon::Grabbing);
+            } else if space && response.hovered() {
+                ctx.set_cursor_icon(egui::CursorIcon::Grab);
+            } else if (self.eyedropper || self.measure.active || self.region_select)
+                && response.hovered()
+            {
+                ctx.set_cursor_icon(egui::CursorIcon::Crosshair);
             }
 
             // Handle pinch-to-zoom (check first to avoid double-processing with scroll)
@@ -488,8 +2949,11 @@ impl eframe::App for SvgViewerApp {
                 let hover_pos = ctx.input(|i| i.pointer.hover_pos().unwrap_or(rect.center()));
                 let cursor_vec = hover_pos - rect.center();
 
+                self.view_history
+                    .record_zoom_step(&self.viewport, Instant::now());
                 self.viewport.zoom_by(zoom_delta, cursor_vec);
-                self.schedule_rerender();
+                self.constrain_pan();
+                self.zoom_changed();
                 ctx.request_repaint();
             }
 
@@ -501,16 +2965,21 @@ impl eframe::App for SvgViewerApp {
                     let cursor_vec = hover_pos - rect.center();
 
                     let factor = if scroll_delta > 0.0 { 1.1 } else { 0.9 };
+                    self.view_history
+                        .record_zoom_step(&self.viewport, Instant::now());
                     self.viewport.zoom_by(factor, cursor_vec);
-                    self.schedule_rerender();
+                    self.constrain_pan();
+                    self.zoom_changed();
                     ctx.request_repaint();
                 }
             }
         });
 
-        // Check deferred rerender for smooth zoom
-        self.check_deferred_rerender();
-        if self.pending_rerender {
+        // Let a settled zoom turn into a re-render
+        self.render_state.apply(RenderEvent::Tick {
+            now: Instant::now(),
+        });
+        if self.render_state.needs_repaint() || self.render_state.needs_render() {
             ctx.request_repaint();
         }
     }
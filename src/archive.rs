@@ -0,0 +1,379 @@
+//! SVG files inside zip archives, such as icon packs.
+//!
+//! An entry is addressed by a virtual path: the archive's path with the entry's
+//! name appended, as in `icons.zip/solid/home.svg`. Such paths can be opened
+//! and navigated like files on disk; [`split_path`] tells them apart.
+//!
+//! Only what icon packs use is read: stored and deflated entries, without
+//! encryption or zip64.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use flate2::read::DeflateDecoder;
+
+use crate::error::{Result, SvgError};
+
+/// Largest entry that will be extracted, uncompressed.
+pub const MAX_ENTRY_SIZE: u64 = 256 * 1024 * 1024;
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_DIRECTORY: u32 = 0x0605_4b50;
+/// The end-of-directory record is 22 bytes, plus a comment of up to 64 KiB.
+const END_RECORD_LEN: u64 = 22;
+const MAX_COMMENT_LEN: u64 = u16::MAX as u64;
+
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+/// One file in an archive, as listed in its central directory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZipEntry {
+    /// Path within the archive, with `/` separators
+    pub name: String,
+    method: u16,
+    encrypted: bool,
+    crc: u32,
+    compressed_size: u64,
+    pub size: u64,
+    local_offset: u64,
+}
+
+fn archive_error(path: &Path, message: impl std::fmt::Display) -> SvgError {
+    SvgError::Archive(format!("{}: {message}", path.display()))
+}
+
+fn u16_at(data: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([data[at], data[at + 1]])
+}
+
+fn u32_at(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+/// A zip archive's table of contents.
+pub struct ZipArchive {
+    path: PathBuf,
+    entries: Vec<ZipEntry>,
+}
+
+impl ZipArchive {
+    /// Read the central directory of the archive at `path`.
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+        if len < END_RECORD_LEN {
+            return Err(archive_error(path, "not a zip archive"));
+        }
+
+        // The end record is at the very end, unless there's a comment after it
+        let tail_len = len.min(END_RECORD_LEN + MAX_COMMENT_LEN);
+        file.seek(SeekFrom::Start(len - tail_len))?;
+        let mut tail = vec![0; tail_len as usize];
+        file.read_exact(&mut tail)?;
+        let end = (0..=tail.len() - END_RECORD_LEN as usize)
+            .rev()
+            .find(|&i| u32_at(&tail, i) == END_OF_DIRECTORY)
+            .ok_or_else(|| archive_error(path, "not a zip archive"))?;
+        let count = u16_at(&tail, end + 10) as usize;
+        let directory_size = u32_at(&tail, end + 12) as u64;
+        let directory_offset = u32_at(&tail, end + 16) as u64;
+        if count == u16::MAX as usize || directory_offset == u32::MAX as u64 {
+            return Err(archive_error(path, "zip64 archives aren't supported"));
+        }
+        if directory_offset + directory_size > len {
+            return Err(archive_error(path, "truncated central directory"));
+        }
+
+        file.seek(SeekFrom::Start(directory_offset))?;
+        let mut directory = vec![0; directory_size as usize];
+        file.read_exact(&mut directory)?;
+
+        let mut entries = Vec::with_capacity(count);
+        let mut at = 0;
+        for _ in 0..count {
+            if at + 46 > directory.len() || u32_at(&directory, at) != CENTRAL_HEADER {
+                return Err(archive_error(path, "corrupt central directory"));
+            }
+            let name_len = u16_at(&directory, at + 28) as usize;
+            let extra_len = u16_at(&directory, at + 30) as usize;
+            let comment_len = u16_at(&directory, at + 32) as usize;
+            let name_end = at + 46 + name_len;
+            if name_end > directory.len() {
+                return Err(archive_error(path, "corrupt central directory"));
+            }
+            entries.push(ZipEntry {
+                name: String::from_utf8_lossy(&directory[at + 46..name_end]).into_owned(),
+                method: u16_at(&directory, at + 10),
+                encrypted: u16_at(&directory, at + 8) & 1 != 0,
+                crc: u32_at(&directory, at + 16),
+                compressed_size: u32_at(&directory, at + 20) as u64,
+                size: u32_at(&directory, at + 24) as u64,
+                local_offset: u32_at(&directory, at + 42) as u64,
+            });
+            at = name_end + extra_len + comment_len;
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    pub fn entries(&self) -> &[ZipEntry] {
+        &self.entries
+    }
+
+    /// Names of the `.svg`/`.svgz` entries, flattened out of their directories
+    /// and in natural order. Hidden files and macOS resource forks are left out.
+    pub fn svg_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .entries
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .filter(|name| is_svg_name(name))
+            .collect();
+        names.sort_by(|a, b| natord::compare(a, b));
+        names
+    }
+
+    /// The uncompressed contents of the entry called `name`.
+    pub fn read(&self, name: &str) -> Result<Vec<u8>> {
+        let entry = self
+            .entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| archive_error(&self.path, format!("no entry \"{name}\"")))?;
+        if entry.encrypted {
+            return Err(archive_error(
+                &self.path,
+                format!("\"{name}\" is encrypted"),
+            ));
+        }
+        if entry.size > MAX_ENTRY_SIZE {
+            return Err(archive_error(
+                &self.path,
+                format!("\"{name}\" is too large"),
+            ));
+        }
+
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(entry.local_offset))?;
+        let mut header = [0; 30];
+        file.read_exact(&mut header)?;
+        if u32_at(&header, 0) != LOCAL_HEADER {
+            return Err(archive_error(
+                &self.path,
+                format!("corrupt entry \"{name}\""),
+            ));
+        }
+        // The local header's name and extra field can differ from the directory's
+        let skip = u16_at(&header, 26) as i64 + u16_at(&header, 28) as i64;
+        file.seek(SeekFrom::Current(skip))?;
+
+        let compressed = file.take(entry.compressed_size);
+        let mut data = Vec::with_capacity(entry.size as usize);
+        match entry.method {
+            STORED => compressed.take(entry.size).read_to_end(&mut data)?,
+            DEFLATED => DeflateDecoder::new(compressed)
+                .take(entry.size)
+                .read_to_end(&mut data)?,
+            method => {
+                return Err(archive_error(
+                    &self.path,
+                    format!("\"{name}\" uses unsupported compression method {method}"),
+                ))
+            }
+        };
+        if data.len() as u64 != entry.size || crc32fast::hash(&data) != entry.crc {
+            return Err(archive_error(&self.path, format!("\"{name}\" is corrupt")));
+        }
+        Ok(data)
+    }
+}
+
+fn is_svg_name(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    if !(lower.ends_with(".svg") || lower.ends_with(".svgz")) {
+        return false;
+    }
+    !name
+        .split('/')
+        .any(|part| part.starts_with('.') || part == "__MACOSX")
+}
+
+/// Whether `path` names a zip archive on disk.
+pub fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+        && path.is_file()
+}
+
+/// The virtual path of the entry `name` in `archive`.
+pub fn entry_path(archive: &Path, name: &str) -> PathBuf {
+    name.split('/')
+        .fold(archive.to_path_buf(), |path, part| path.join(part))
+}
+
+/// Split a virtual path into its archive and entry name, or `None` for a path
+/// that isn't inside an archive.
+pub fn split_path(path: &Path) -> Option<(&Path, String)> {
+    let archive = path
+        .ancestors()
+        .skip(1)
+        .find(|ancestor| is_archive(ancestor))?;
+    let name = path
+        .strip_prefix(archive)
+        .ok()?
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    Some((archive, name))
+}
+
+/// The virtual paths of the SVG entries in `archive`, in navigation order.
+pub fn svg_paths(archive: &Path) -> Result<Vec<PathBuf>> {
+    let zip = ZipArchive::open(archive)?;
+    Ok(zip
+        .svg_names()
+        .into_iter()
+        .map(|name| entry_path(archive, name))
+        .collect())
+}
+
+/// The contents of the file or archive entry at `path`.
+pub fn read_path(path: &Path) -> Result<Vec<u8>> {
+    match split_path(path) {
+        Some((archive, name)) => ZipArchive::open(archive)?.read(&name),
+        None => Ok(std::fs::read(path)?),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// Write a zip of `(name, data, deflate)` entries for tests.
+    pub(crate) fn write_zip(path: &Path, files: &[(&str, &[u8], bool)]) {
+        let mut out = Vec::new();
+        let mut directory = Vec::new();
+        for (name, data, deflate) in files {
+            let (method, body) = if *deflate {
+                let mut encoder =
+                    flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data).unwrap();
+                (DEFLATED, encoder.finish().unwrap())
+            } else {
+                (STORED, data.to_vec())
+            };
+            let crc = crc32fast::hash(data);
+            let offset = out.len() as u32;
+            let common = |buf: &mut Vec<u8>| {
+                buf.extend(20u16.to_le_bytes()); // version needed
+                buf.extend(0u16.to_le_bytes()); // flags
+                buf.extend(method.to_le_bytes());
+                buf.extend(0u32.to_le_bytes()); // time and date
+                buf.extend(crc.to_le_bytes());
+                buf.extend((body.len() as u32).to_le_bytes());
+                buf.extend((data.len() as u32).to_le_bytes());
+                buf.extend((name.len() as u16).to_le_bytes());
+                buf.extend(0u16.to_le_bytes()); // extra field
+            };
+            out.extend(LOCAL_HEADER.to_le_bytes());
+            common(&mut out);
+            out.extend(name.as_bytes());
+            out.extend(&body);
+
+            directory.extend(CENTRAL_HEADER.to_le_bytes());
+            directory.extend(20u16.to_le_bytes()); // version made by
+            common(&mut directory);
+            directory.extend([0; 6]); // comment, disk, internal attributes
+            directory.extend(0u32.to_le_bytes()); // external attributes
+            directory.extend(offset.to_le_bytes());
+            directory.extend(name.as_bytes());
+        }
+        let directory_offset = out.len() as u32;
+        out.extend(&directory);
+        out.extend(END_OF_DIRECTORY.to_le_bytes());
+        out.extend([0; 4]); // disk numbers
+        out.extend((files.len() as u16).to_le_bytes());
+        out.extend((files.len() as u16).to_le_bytes());
+        out.extend((directory.len() as u32).to_le_bytes());
+        out.extend(directory_offset.to_le_bytes());
+        out.extend(0u16.to_le_bytes()); // comment
+        std::fs::write(path, out).unwrap();
+    }
+
+    pub(crate) const ICON: &[u8] =
+        br#"<svg xmlns="http://www.w3.org/2000/svg" width="24" height="24"><rect width="24" height="24"/></svg>"#;
+
+    pub(crate) fn temp_zip(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("svg_viewer_test_{}_{name}.zip", std::process::id()));
+        write_zip(
+            &path,
+            &[
+                ("solid/icon10.svg", ICON, true),
+                ("solid/icon2.svg", ICON, false),
+                ("README.txt", b"icons", false),
+                ("__MACOSX/solid/._icon2.svg", b"fork", false),
+                ("outline/", b"", false),
+                ("outline/ICON1.SVG", ICON, true),
+            ],
+        );
+        path
+    }
+
+    #[test]
+    fn test_svg_entries_are_flattened_in_natural_order() {
+        let path = temp_zip("order");
+        let zip = ZipArchive::open(&path).unwrap();
+        assert_eq!(zip.entries().len(), 6);
+        assert_eq!(
+            zip.svg_names(),
+            ["outline/ICON1.SVG", "solid/icon2.svg", "solid/icon10.svg"]
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_read_stored_and_deflated_entries() {
+        let path = temp_zip("read");
+        let zip = ZipArchive::open(&path).unwrap();
+        assert_eq!(zip.read("solid/icon2.svg").unwrap(), ICON);
+        assert_eq!(zip.read("solid/icon10.svg").unwrap(), ICON);
+        assert!(matches!(zip.read("missing.svg"), Err(SvgError::Archive(_))));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_virtual_paths() {
+        let path = temp_zip("paths");
+        let entry = entry_path(&path, "solid/icon2.svg");
+        let (archive, name) = split_path(&entry).unwrap();
+        assert_eq!(
+            (archive, name.as_str()),
+            (path.as_path(), "solid/icon2.svg")
+        );
+        assert_eq!(read_path(&entry).unwrap(), ICON);
+        assert!(split_path(&path).is_none());
+        assert!(split_path(Path::new("/no/such.zip/icon.svg")).is_none());
+
+        let paths = svg_paths(&path).unwrap();
+        assert_eq!(paths[0], entry_path(&path, "outline/ICON1.SVG"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_not_an_archive() {
+        let path =
+            std::env::temp_dir().join(format!("svg_viewer_test_{}_bogus.zip", std::process::id()));
+        std::fs::write(&path, b"this is not a zip archive at all").unwrap();
+        assert!(matches!(ZipArchive::open(&path), Err(SvgError::Archive(_))));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,1213 @@
+//! Exporting many files at once, for `--batch`.
+//!
+//! Rendering goes through [`BatchRenderer`] so the scheduling, skipping and
+//! manifest logic can be exercised without rasterizing anything.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::archive;
+use crate::error::{Result, SvgError};
+use crate::export::{self, ExportFormat, ExportSettings, SizePlan};
+use crate::file_navigator;
+use crate::renderer::{self, RenderQuality};
+use crate::svg_document::SvgDocument;
+use crate::viewport::{usable_dimension, Viewport};
+
+/// Bytes that rendering jobs may hold at once across all workers.
+pub const DEFAULT_MEMORY_BUDGET: u64 = 1 << 30;
+
+/// One file to export.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchItem {
+    pub source: PathBuf,
+    pub output: PathBuf,
+}
+
+/// `inputs` with each directory replaced by its SVG files in navigator order.
+fn expand_inputs(inputs: &[PathBuf]) -> Vec<PathBuf> {
+    let mut sources = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            sources.extend(file_navigator::svg_files_in(input));
+        } else {
+            sources.push(input.clone());
+        }
+    }
+    sources
+}
+
+/// Expand `inputs` (files, or directories of SVGs) into items writing
+/// `<stem>.<ext>` into `out_dir`. Sources that would share an output, such
+/// as `a/logo.svg` and `b/logo.svg`, get numbered names as in
+/// [`plan_export_items`].
+pub fn plan_items(inputs: &[PathBuf], out_dir: &Path, format: &ExportFormat) -> Vec<BatchItem> {
+    plan_export_items(
+        &expand_inputs(inputs),
+        Some(out_dir),
+        format.extension(),
+        Collision::Overwrite,
+    )
+}
+
+/// What to do about an output file that's already there, chosen before the
+/// batch starts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Collision {
+    #[default]
+    Overwrite,
+    /// Leave the file alone and record the source as skipped
+    Skip,
+    /// Write to a free name instead, `logo-2.png`
+    Rename,
+}
+
+impl Collision {
+    pub fn all() -> &'static [Collision] {
+        &[Collision::Overwrite, Collision::Skip, Collision::Rename]
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Collision::Overwrite => "Overwrite",
+            Collision::Skip => "Skip",
+            Collision::Rename => "Rename",
+        }
+    }
+}
+
+/// Items writing each of `sources` to `<stem>.<extension>`, into `out_dir` or
+/// else next to the source (next to its archive, for an archive entry).
+/// Outputs that would replace one of the sources, or that two sources would
+/// share, always get a free numbered name. `collision` decides about other
+/// files already there: `Rename` numbers those too, and `Skip` is up to
+/// [`BatchOptions::skip_existing`].
+pub fn plan_export_items(
+    sources: &[PathBuf],
+    out_dir: Option<&Path>,
+    extension: &str,
+    collision: Collision,
+) -> Vec<BatchItem> {
+    let mut taken: HashSet<PathBuf> = sources.iter().cloned().collect();
+    sources
+        .iter()
+        .map(|source| {
+            let dir = out_dir.map_or_else(
+                || {
+                    let on_disk =
+                        archive::split_path(source).map_or(source.as_path(), |(zip, _)| zip);
+                    on_disk.parent().unwrap_or(Path::new("")).to_path_buf()
+                },
+                Path::to_path_buf,
+            );
+            let stem = source.file_stem().unwrap_or_default().to_string_lossy();
+            let free = |path: &PathBuf| {
+                !(taken.contains(path) || collision == Collision::Rename && path.exists())
+            };
+            let output = (1..)
+                .map(|n| match n {
+                    1 => dir.join(format!("{stem}.{extension}")),
+                    n => dir.join(format!("{stem}-{n}.{extension}")),
+                })
+                .find(free)
+                .unwrap_or_default();
+            taken.insert(output.clone());
+            BatchItem {
+                source: source.clone(),
+                output,
+            }
+        })
+        .collect()
+}
+
+/// How a frame whose aspect ratio differs from the sequence's is placed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FrameFit {
+    /// Scale to fit inside the frame, leaving transparent bars.
+    #[default]
+    Contain,
+    /// Scale to cover the frame, cropping the overflow.
+    Cover,
+}
+
+/// A listing of the frames written next to them, for assembling a video.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SequenceIndex {
+    /// An ffmpeg concat demuxer script (`sequence.ffconcat`).
+    FfConcat,
+    /// `sequence.json`: the frame number, source and output of every frame.
+    Json,
+}
+
+/// Sequence mode: every file becomes a numbered frame of the same size.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sequence {
+    pub prefix: String,
+    pub fit: FrameFit,
+    pub index: Option<SequenceIndex>,
+}
+
+/// Digits in frame numbers: at least 4, more when there are that many frames.
+pub fn sequence_padding(count: usize) -> usize {
+    count.to_string().len().max(4)
+}
+
+/// `{prefix}{number}.{ext}`, with `number` zero-padded to `padding` digits.
+pub fn frame_name(prefix: &str, number: usize, padding: usize, format: &ExportFormat) -> String {
+    format!("{prefix}{number:0padding$}.{}", format.extension())
+}
+
+/// Like [`plan_items`], but outputs are numbered frames from 1 in input order,
+/// whatever the sources are called.
+pub fn plan_sequence(
+    inputs: &[PathBuf],
+    out_dir: &Path,
+    prefix: &str,
+    format: &ExportFormat,
+) -> Vec<BatchItem> {
+    let sources = expand_inputs(inputs);
+    let padding = sequence_padding(sources.len());
+    sources
+        .into_iter()
+        .enumerate()
+        .map(|(i, source)| BatchItem {
+            source,
+            output: out_dir.join(frame_name(prefix, i + 1, padding, format)),
+        })
+        .collect()
+}
+
+/// Transform placing a `svg_width`×`svg_height` document centered in a
+/// `width`×`height` frame.
+pub fn frame_transform(
+    svg_width: f32,
+    svg_height: f32,
+    width: u32,
+    height: u32,
+    fit: FrameFit,
+) -> tiny_skia::Transform {
+    let svg_width = usable_dimension(svg_width);
+    let svg_height = usable_dimension(svg_height);
+    let scale_x = width as f32 / svg_width;
+    let scale_y = height as f32 / svg_height;
+    let scale = match fit {
+        FrameFit::Contain => scale_x.min(scale_y),
+        FrameFit::Cover => scale_x.max(scale_y),
+    };
+    tiny_skia::Transform::from_scale(scale, scale).post_translate(
+        (width as f32 - svg_width * scale) / 2.0,
+        (height as f32 - svg_height * scale) / 2.0,
+    )
+}
+
+/// Write the `index` listing for the frames in `records` that have an image,
+/// returning where it was written.
+pub fn write_sequence_index(
+    records: &[BatchRecord],
+    out_dir: &Path,
+    index: SequenceIndex,
+) -> Result<PathBuf> {
+    let frames: Vec<&BatchRecord> = records
+        .iter()
+        .filter(|r| !matches!(r.outcome, Outcome::Failed { .. }))
+        .collect();
+    let (path, text) = match index {
+        SequenceIndex::FfConcat => (out_dir.join("sequence.ffconcat"), ffconcat_script(&frames)),
+        SequenceIndex::Json => {
+            let entries: Vec<_> = frames
+                .iter()
+                .enumerate()
+                .map(|(i, r)| {
+                    serde_json::json!({
+                        "frame": i + 1,
+                        "source": r.source,
+                        "output": r.output.file_name().map(|n| n.to_string_lossy()),
+                    })
+                })
+                .collect();
+            let text = serde_json::to_string_pretty(&entries)
+                .map_err(|e| SvgError::Export(format!("Cannot write sequence index: {e}")))?;
+            (out_dir.join("sequence.json"), text + "\n")
+        }
+    };
+    std::fs::write(&path, text)?;
+    Ok(path)
+}
+
+/// Output file names relative to the script, quoted for the concat demuxer.
+fn ffconcat_script(frames: &[&BatchRecord]) -> String {
+    let mut script = String::from("ffconcat version 1.0\n");
+    for record in frames {
+        let name = record
+            .output
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        script.push_str(&format!("file '{}'\n", name.replace('\'', "'\\''")));
+    }
+    script
+}
+
+/// Whether `output` exists and was written after `source` last changed.
+pub fn is_up_to_date(source: &Path, output: &Path) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(source), modified(output)) {
+        (Some(source), Some(output)) => output >= source,
+        _ => false,
+    }
+}
+
+/// Caps the memory held by concurrent jobs. A job larger than the whole budget
+/// still runs, but only once nothing else holds a reservation.
+pub struct MemoryBudget {
+    total: u64,
+    available: Mutex<u64>,
+    released: Condvar,
+}
+
+impl MemoryBudget {
+    pub fn new(total: u64) -> Self {
+        let total = total.max(1);
+        Self {
+            total,
+            available: Mutex::new(total),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Block until `bytes` can be reserved. The reservation ends when the
+    /// returned guard is dropped.
+    pub fn reserve(&self, bytes: u64) -> Reservation<'_> {
+        let bytes = bytes.min(self.total);
+        let mut available = self.available.lock().unwrap();
+        while *available < bytes {
+            available = self.released.wait(available).unwrap();
+        }
+        *available -= bytes;
+        Reservation {
+            budget: self,
+            bytes,
+        }
+    }
+}
+
+pub struct Reservation<'a> {
+    budget: &'a MemoryBudget,
+    bytes: u64,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        *self.budget.available.lock().unwrap() += self.bytes;
+        self.budget.released.notify_all();
+    }
+}
+
+/// Exports with the viewer's export settings, each file at `scale` times its
+/// own size and drawn with the viewer's `quality`.
+pub struct SettingsRenderer {
+    pub settings: ExportSettings,
+    pub scale: f32,
+    pub quality: RenderQuality,
+}
+
+impl BatchRenderer for SettingsRenderer {
+    fn render(&self, item: &BatchItem, budget: &MemoryBudget) -> Result<SizePlan> {
+        let doc = SvgDocument::load_with_quality(&item.source, self.quality)?;
+        let (width, height) =
+            export::output_size(doc.width(), doc.height(), None, None, self.scale);
+        let plan = export::plan_size(&self.settings.format, width, height);
+        let (width, height) = plan.actual;
+
+        let _reservation = budget.reserve(width as u64 * height as u64 * 8);
+        let settings = ExportSettings {
+            width,
+            height,
+            ..self.settings.clone()
+        };
+        export::export_svg(&doc, &Viewport::default(), &settings, &item.output)?;
+        Ok(plan)
+    }
+}
+
+/// Renders one batch item, returning the size asked for and the written
+/// image's size.
+pub trait BatchRenderer: Sync {
+    /// Implementations reserve their working memory from `budget` before
+    /// allocating it.
+    fn render(&self, item: &BatchItem, budget: &MemoryBudget) -> Result<SizePlan>;
+}
+
+/// Exports through the normal pipeline at a fixed size or scale.
+pub struct ExportRenderer {
+    pub format: ExportFormat,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub scale: f32,
+    /// Render every file at exactly this size instead, as in sequence mode.
+    pub frame: Option<((u32, u32), FrameFit)>,
+}
+
+impl BatchRenderer for ExportRenderer {
+    fn render(&self, item: &BatchItem, budget: &MemoryBudget) -> Result<SizePlan> {
+        let doc = SvgDocument::load(&item.source)?;
+        let (width, height) = match self.frame {
+            Some((size, _)) => size,
+            None => export::output_size(
+                doc.width(),
+                doc.height(),
+                self.width,
+                self.height,
+                self.scale,
+            ),
+        };
+        let plan = export::plan_size(&self.format, width, height);
+        let (width, height) = plan.actual;
+
+        // The pixmap plus the converted copy handed to the encoder
+        let _reservation = budget.reserve(width as u64 * height as u64 * 8);
+        let settings = ExportSettings {
+            include_alpha: self.format.supports_alpha(),
+            format: self.format.clone(),
+            width,
+            height,
+            ..Default::default()
+        };
+        match self.frame {
+            Some((_, fit)) => {
+                let transform = frame_transform(doc.width(), doc.height(), width, height, fit);
+                let pixmap = renderer::render_with_transform(&doc, width, height, transform)?;
+                let metadata = export::ImageMetadata::for_export(&doc, &settings);
+                export::save_pixmap(&pixmap, &settings, metadata.as_ref(), &item.output)?;
+            }
+            None => export::export_svg(&doc, &Viewport::default(), &settings, &item.output)?,
+        }
+        Ok(plan)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum Outcome {
+    Exported {
+        width: u32,
+        height: u32,
+        #[serde(flatten)]
+        reduced: Option<Reduction>,
+    },
+    Skipped,
+    Failed {
+        error: String,
+    },
+}
+
+/// How an export came out smaller than asked for.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Reduction {
+    pub requested_width: u32,
+    pub requested_height: u32,
+    /// Why, as in [`SizePlan::reasons`]
+    pub reasons: Vec<String>,
+}
+
+impl Outcome {
+    /// An export written at `plan`'s actual size.
+    pub fn exported(plan: SizePlan) -> Self {
+        let (width, height) = plan.actual;
+        let reduced = plan.is_reduced().then_some(Reduction {
+            requested_width: plan.requested.0,
+            requested_height: plan.requested.1,
+            reasons: plan.reasons,
+        });
+        Outcome::Exported {
+            width,
+            height,
+            reduced,
+        }
+    }
+}
+
+/// The manifest entry for one input.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BatchRecord {
+    pub source: PathBuf,
+    pub output: PathBuf,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+    pub duration_ms: u64,
+}
+
+impl BatchRecord {
+    pub fn progress_line(&self) -> String {
+        match &self.outcome {
+            Outcome::Exported {
+                width,
+                height,
+                reduced: None,
+            } => format!(
+                "{} -> {} ({width}×{height}, {} ms)",
+                self.source.display(),
+                self.output.display(),
+                self.duration_ms
+            ),
+            Outcome::Exported {
+                width,
+                height,
+                reduced: Some(reduced),
+            } => format!(
+                "{} -> {} ({width}×{height} instead of {}×{}: {}, {} ms)",
+                self.source.display(),
+                self.output.display(),
+                reduced.requested_width,
+                reduced.requested_height,
+                reduced.reasons.join("; "),
+                self.duration_ms
+            ),
+            Outcome::Skipped => format!("{} up to date, skipped", self.source.display()),
+            Outcome::Failed { error } => format!("{} failed: {error}", self.source.display()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BatchOptions {
+    /// Worker threads; 0 is treated as 1.
+    pub jobs: usize,
+    pub skip_up_to_date: bool,
+    /// Keep exporting after a failure. Without it, no new files are started once
+    /// one has failed.
+    pub keep_going: bool,
+    pub memory_budget: u64,
+    /// Skip items whose output file already exists
+    pub skip_existing: bool,
+    /// Set to stop starting new items; those never started are left out of
+    /// the records
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            jobs: 1,
+            skip_up_to_date: false,
+            keep_going: false,
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            skip_existing: false,
+            cancel: None,
+        }
+    }
+}
+
+/// Totals for the final summary line.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BatchSummary {
+    pub exported: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    /// Items never started because an earlier one failed.
+    pub not_run: usize,
+}
+
+impl BatchSummary {
+    pub fn from_records(records: &[BatchRecord], total: usize) -> Self {
+        let mut summary = BatchSummary {
+            not_run: total - records.len(),
+            ..Default::default()
+        };
+        for record in records {
+            match record.outcome {
+                Outcome::Exported { .. } => summary.exported += 1,
+                Outcome::Skipped => summary.skipped += 1,
+                Outcome::Failed { .. } => summary.failed += 1,
+            }
+        }
+        summary
+    }
+
+    pub fn describe(&self) -> String {
+        let mut text = format!(
+            "{} exported, {} skipped, {} failed",
+            self.exported, self.skipped, self.failed
+        );
+        if self.not_run > 0 {
+            text.push_str(&format!(", {} not run", self.not_run));
+        }
+        text
+    }
+}
+
+/// Export `items` on `options.jobs` threads. `on_record` is called on the calling
+/// thread as each item finishes. The returned records are in input order and
+/// leave out items that were never started.
+pub fn run_batch(
+    items: &[BatchItem],
+    options: &BatchOptions,
+    renderer: &dyn BatchRenderer,
+    mut on_record: impl FnMut(&BatchRecord),
+) -> Vec<BatchRecord> {
+    let budget = MemoryBudget::new(options.memory_budget);
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (tx, rx) = mpsc::channel();
+    let workers = options.jobs.clamp(1, items.len().max(1));
+
+    let mut slots: Vec<Option<BatchRecord>> = vec![None; items.len()];
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (budget, next, stop) = (&budget, &next, &stop);
+            scope.spawn(move || loop {
+                let cancelled = options
+                    .cancel
+                    .as_ref()
+                    .is_some_and(|cancel| cancel.load(Ordering::SeqCst));
+                if stop.load(Ordering::SeqCst) || cancelled {
+                    break;
+                }
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                let record = run_item(item, options, renderer, budget);
+                if matches!(record.outcome, Outcome::Failed { .. }) && !options.keep_going {
+                    stop.store(true, Ordering::SeqCst);
+                }
+                if tx.send((index, record)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+
+        for (index, record) in rx {
+            on_record(&record);
+            slots[index] = Some(record);
+        }
+    });
+
+    slots.into_iter().flatten().collect()
+}
+
+fn run_item(
+    item: &BatchItem,
+    options: &BatchOptions,
+    renderer: &dyn BatchRenderer,
+    budget: &MemoryBudget,
+) -> BatchRecord {
+    let start = Instant::now();
+    let skip = (options.skip_up_to_date && is_up_to_date(&item.source, &item.output))
+        || (options.skip_existing && item.output.exists());
+    let outcome = if skip {
+        Outcome::Skipped
+    } else {
+        match renderer.render(item, budget) {
+            Ok(plan) => Outcome::exported(plan),
+            Err(e) => Outcome::Failed {
+                error: e.to_string(),
+            },
+        }
+    };
+    BatchRecord {
+        source: item.source.clone(),
+        output: item.output.clone(),
+        outcome,
+        duration_ms: duration_ms(start.elapsed()),
+    }
+}
+
+/// A batch exporting on a background thread, one file at a time, for the
+/// viewer to show the progress of.
+pub struct BackgroundBatch {
+    items: Vec<BatchItem>,
+    records: Vec<BatchRecord>,
+    receiver: mpsc::Receiver<BatchRecord>,
+    cancel: Arc<AtomicBool>,
+    finished: bool,
+}
+
+impl BackgroundBatch {
+    /// Start exporting `items`, calling `notify` from the worker as each one
+    /// finishes.
+    pub fn start(
+        items: Vec<BatchItem>,
+        options: BatchOptions,
+        renderer: impl BatchRenderer + Send + 'static,
+        notify: impl Fn() + Send + 'static,
+    ) -> Self {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let options = BatchOptions {
+            jobs: 1,
+            cancel: Some(cancel.clone()),
+            ..options
+        };
+        let (tx, receiver) = mpsc::channel();
+        let worker_items = items.clone();
+        std::thread::spawn(move || {
+            run_batch(&worker_items, &options, &renderer, |record| {
+                let _ = tx.send(record.clone());
+                notify();
+            });
+            // Dropping the sender tells the viewer the batch is over
+            drop(tx);
+            notify();
+        });
+        Self {
+            items,
+            records: Vec::new(),
+            receiver,
+            cancel,
+            finished: false,
+        }
+    }
+
+    /// Collect the records of files finished since the last call.
+    pub fn poll(&mut self) {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(record) => self.records.push(record),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Stop once the file being exported is done.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn total(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn records(&self) -> &[BatchRecord] {
+        &self.records
+    }
+
+    /// The source being exported, while the batch runs.
+    pub fn current(&self) -> Option<&Path> {
+        if self.finished {
+            return None;
+        }
+        self.items
+            .get(self.records.len())
+            .map(|item| item.source.as_path())
+    }
+
+    pub fn summary(&self) -> BatchSummary {
+        BatchSummary::from_records(&self.records, self.items.len())
+    }
+}
+
+impl Drop for BackgroundBatch {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+fn duration_ms(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+/// The manifest as pretty-printed JSON: an array with one object per record.
+pub fn manifest_json(records: &[BatchRecord]) -> Result<String> {
+    serde_json::to_string_pretty(records)
+        .map_err(|e| SvgError::Export(format!("Cannot write manifest: {e}")))
+}
+
+pub fn write_manifest(records: &[BatchRecord], path: &Path) -> Result<()> {
+    std::fs::write(path, manifest_json(records)? + "\n")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU64;
+
+    fn items(names: &[&str]) -> Vec<BatchItem> {
+        names
+            .iter()
+            .map(|name| BatchItem {
+                source: PathBuf::from(format!("{name}.svg")),
+                output: PathBuf::from(format!("out/{name}.png")),
+            })
+            .collect()
+    }
+
+    /// Fails sources whose name starts with "bad", and tracks peak concurrency and
+    /// reserved memory.
+    #[derive(Default)]
+    struct FakeRenderer {
+        bytes_per_item: u64,
+        active: AtomicUsize,
+        peak_active: AtomicUsize,
+        reserved: AtomicU64,
+        peak_reserved: AtomicU64,
+        rendered: Mutex<HashSet<PathBuf>>,
+    }
+
+    impl BatchRenderer for FakeRenderer {
+        fn render(&self, item: &BatchItem, budget: &MemoryBudget) -> Result<SizePlan> {
+            let _reservation = budget.reserve(self.bytes_per_item);
+            let reserved = self
+                .reserved
+                .fetch_add(self.bytes_per_item, Ordering::SeqCst)
+                + self.bytes_per_item;
+            self.peak_reserved.fetch_max(reserved, Ordering::SeqCst);
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak_active.fetch_max(active, Ordering::SeqCst);
+
+            std::thread::sleep(Duration::from_millis(5));
+            self.rendered.lock().unwrap().insert(item.source.clone());
+
+            self.active.fetch_sub(1, Ordering::SeqCst);
+            self.reserved
+                .fetch_sub(self.bytes_per_item, Ordering::SeqCst);
+            if item.source.to_string_lossy().starts_with("bad") {
+                Err(SvgError::Parse("broken".into()))
+            } else {
+                Ok(export::plan_size(&ExportFormat::Png, 10, 20))
+            }
+        }
+    }
+
+    #[test]
+    fn test_records_in_input_order() {
+        let items = items(&["a", "b", "c", "d", "e", "f"]);
+        let renderer = FakeRenderer::default();
+        let options = BatchOptions {
+            jobs: 3,
+            ..Default::default()
+        };
+        let mut progress = 0;
+        let records = run_batch(&items, &options, &renderer, |_| progress += 1);
+        assert_eq!(progress, 6);
+        let sources: Vec<_> = records.iter().map(|r| r.source.clone()).collect();
+        let expected: Vec<_> = items.iter().map(|i| i.source.clone()).collect();
+        assert_eq!(sources, expected);
+        assert!(renderer.peak_active.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[test]
+    fn test_memory_budget_limits_concurrency() {
+        let items = items(&["a", "b", "c", "d", "e", "f", "g", "h"]);
+        let renderer = FakeRenderer {
+            bytes_per_item: 600,
+            ..Default::default()
+        };
+        let options = BatchOptions {
+            jobs: 4,
+            memory_budget: 1000,
+            ..Default::default()
+        };
+        run_batch(&items, &options, &renderer, |_| {});
+        assert_eq!(renderer.peak_active.load(Ordering::SeqCst), 1);
+        assert!(renderer.peak_reserved.load(Ordering::SeqCst) <= 1000);
+    }
+
+    #[test]
+    fn test_oversized_item_still_runs() {
+        let budget = MemoryBudget::new(100);
+        let reservation = budget.reserve(1_000_000);
+        drop(reservation);
+        let _again = budget.reserve(100);
+    }
+
+    #[test]
+    fn test_stops_after_failure_without_keep_going() {
+        let items = items(&["a", "bad", "c", "d"]);
+        let renderer = FakeRenderer::default();
+        let records = run_batch(&items, &BatchOptions::default(), &renderer, |_| {});
+        assert_eq!(records.len(), 2);
+        let summary = BatchSummary::from_records(&records, items.len());
+        assert_eq!(
+            summary,
+            BatchSummary {
+                exported: 1,
+                skipped: 0,
+                failed: 1,
+                not_run: 2,
+            }
+        );
+        assert_eq!(
+            summary.describe(),
+            "1 exported, 0 skipped, 1 failed, 2 not run"
+        );
+    }
+
+    #[test]
+    fn test_keep_going_runs_everything() {
+        let items = items(&["a", "bad", "c", "bad2"]);
+        let renderer = FakeRenderer::default();
+        let options = BatchOptions {
+            keep_going: true,
+            jobs: 2,
+            ..Default::default()
+        };
+        let records = run_batch(&items, &options, &renderer, |_| {});
+        let summary = BatchSummary::from_records(&records, items.len());
+        assert_eq!(
+            (summary.exported, summary.failed, summary.not_run),
+            (2, 2, 0)
+        );
+    }
+
+    #[test]
+    fn test_skip_up_to_date() {
+        let dir = std::env::temp_dir().join("svg_viewer_test_batch_skip");
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("fresh.svg");
+        let output = dir.join("fresh.png");
+        std::fs::write(&source, "<svg/>").unwrap();
+        std::fs::write(&output, "png").unwrap();
+        let old = std::time::SystemTime::now() - Duration::from_secs(60);
+        std::fs::File::options()
+            .write(true)
+            .open(&source)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+
+        assert!(is_up_to_date(&source, &output));
+        assert!(!is_up_to_date(&output, &source));
+        assert!(!is_up_to_date(&source, &dir.join("missing.png")));
+
+        let item = BatchItem { source, output };
+        let renderer = FakeRenderer::default();
+        let options = BatchOptions {
+            skip_up_to_date: true,
+            ..Default::default()
+        };
+        let records = run_batch(std::slice::from_ref(&item), &options, &renderer, |_| {});
+        assert_eq!(records[0].outcome, Outcome::Skipped);
+        assert!(renderer.rendered.lock().unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_plan_export_items_names() {
+        let dir = std::env::temp_dir().join("svg_viewer_test_batch_names");
+        std::fs::create_dir_all(dir.join("b")).unwrap();
+        std::fs::write(dir.join("logo.png"), "png").unwrap();
+        let sources: Vec<PathBuf> = ["logo.svg", "logo.svgz", "b/icon.svg", "icon.svg"]
+            .iter()
+            .map(|name| dir.join(name))
+            .collect();
+        let outputs = |out_dir: Option<&Path>, extension, collision| {
+            plan_export_items(&sources, out_dir, extension, collision)
+                .into_iter()
+                .map(|item| item.output.strip_prefix(&dir).unwrap().to_path_buf())
+                .collect::<Vec<_>>()
+        };
+
+        // Two logos can't both be logo.png; the one already there is replaced
+        assert_eq!(
+            outputs(None, "png", Collision::Overwrite),
+            ["logo.png", "logo-2.png", "b/icon.png", "icon.png"].map(PathBuf::from)
+        );
+        assert_eq!(
+            outputs(None, "png", Collision::Rename),
+            ["logo-2.png", "logo-3.png", "b/icon.png", "icon.png"].map(PathBuf::from)
+        );
+        // Into one folder the icons meet too
+        assert_eq!(
+            outputs(Some(&dir.join("out")), "png", Collision::Skip),
+            [
+                "out/logo.png",
+                "out/logo-2.png",
+                "out/icon.png",
+                "out/icon-2.png"
+            ]
+            .map(PathBuf::from)
+        );
+        // An SVG export never writes over its own source
+        assert_eq!(
+            outputs(None, "svg", Collision::Overwrite),
+            ["logo-2.svg", "logo-3.svg", "b/icon-2.svg", "icon-2.svg"].map(PathBuf::from)
+        );
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_skip_existing_and_cancel() {
+        let dir = std::env::temp_dir().join("svg_viewer_test_batch_existing");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("b.png"), "png").unwrap();
+        let items: Vec<BatchItem> = ["a", "b", "c"]
+            .iter()
+            .map(|name| BatchItem {
+                source: PathBuf::from(format!("{name}.svg")),
+                output: dir.join(format!("{name}.png")),
+            })
+            .collect();
+        let renderer = FakeRenderer::default();
+        let options = BatchOptions {
+            skip_existing: true,
+            ..Default::default()
+        };
+        let records = run_batch(&items, &options, &renderer, |_| {});
+        let outcomes: Vec<_> = records.iter().map(|r| r.outcome.clone()).collect();
+        let exported = Outcome::Exported {
+            width: 10,
+            height: 20,
+            reduced: None,
+        };
+        assert_eq!(outcomes, [exported.clone(), Outcome::Skipped, exported]);
+
+        // Once cancelled, no more files are started
+        let options = BatchOptions {
+            cancel: Some(Arc::new(AtomicBool::new(true))),
+            ..Default::default()
+        };
+        let records = run_batch(&items, &options, &renderer, |_| {});
+        assert!(records.is_empty());
+        assert_eq!(BatchSummary::from_records(&records, 3).not_run, 3);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_background_batch_runs_to_the_end() {
+        let mut batch = BackgroundBatch::start(
+            items(&["a", "bad", "c"]),
+            BatchOptions {
+                keep_going: true,
+                ..Default::default()
+            },
+            FakeRenderer::default(),
+            || {},
+        );
+        assert_eq!(batch.total(), 3);
+        let start = Instant::now();
+        while !batch.is_finished() {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            std::thread::sleep(Duration::from_millis(1));
+            batch.poll();
+        }
+        assert_eq!(batch.current(), None);
+        assert_eq!(
+            batch.summary().describe(),
+            "2 exported, 0 skipped, 1 failed"
+        );
+    }
+
+    #[test]
+    fn test_manifest_json() {
+        let records = vec![
+            BatchRecord {
+                source: "a.svg".into(),
+                output: "out/a.png".into(),
+                outcome: Outcome::Exported {
+                    width: 10,
+                    height: 20,
+                    reduced: None,
+                },
+                duration_ms: 7,
+            },
+            BatchRecord {
+                source: "b.svg".into(),
+                output: "out/b.png".into(),
+                outcome: Outcome::Failed {
+                    error: "broken".into(),
+                },
+                duration_ms: 1,
+            },
+        ];
+        let value: serde_json::Value =
+            serde_json::from_str(&manifest_json(&records).unwrap()).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!([
+                {
+                    "source": "a.svg",
+                    "output": "out/a.png",
+                    "status": "exported",
+                    "width": 10,
+                    "height": 20,
+                    "duration_ms": 7
+                },
+                {
+                    "source": "b.svg",
+                    "output": "out/b.png",
+                    "status": "failed",
+                    "error": "broken",
+                    "duration_ms": 1
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_reduced_export_is_recorded() {
+        let record = BatchRecord {
+            source: "wide.svg".into(),
+            output: "out/wide.webp".into(),
+            outcome: Outcome::exported(export::plan_size(&ExportFormat::WebP, 20000, 100)),
+            duration_ms: 3,
+        };
+        let value = serde_json::to_value(&record).unwrap();
+        assert_eq!(value["width"], 16383);
+        assert_eq!(value["requested_width"], 20000);
+        assert_eq!(value["requested_height"], 100);
+        let reasons = value["reasons"].as_array().unwrap();
+        assert!(reasons.iter().any(|r| r.as_str().unwrap().contains("WebP")));
+        assert!(record.progress_line().contains("instead of 20000×100"));
+
+        // Exports at the size asked for record just that
+        let exact = Outcome::exported(export::plan_size(&ExportFormat::Png, 10, 20));
+        let value = serde_json::to_value(&exact).unwrap();
+        assert!(value.get("requested_width").is_none() && value.get("reasons").is_none());
+    }
+
+    #[test]
+    fn test_sequence_padding_adapts_to_count() {
+        assert_eq!(sequence_padding(0), 4);
+        assert_eq!(sequence_padding(9999), 4);
+        assert_eq!(sequence_padding(10000), 5);
+        assert_eq!(frame_name("f_", 7, 4, &ExportFormat::Png), "f_0007.png");
+        assert_eq!(
+            frame_name("", 123456, 6, &ExportFormat::WebP),
+            "123456.webp"
+        );
+    }
+
+    #[test]
+    fn test_plan_sequence_ignores_source_names() {
+        let inputs: Vec<PathBuf> = ["zeta.svg", "alpha.svg", "frame_9.svg"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        let items = plan_sequence(&inputs, Path::new("out"), "shot_", &ExportFormat::Png);
+        let outputs: Vec<_> = items.iter().map(|i| i.output.clone()).collect();
+        assert_eq!(
+            outputs,
+            [
+                "out/shot_0001.png",
+                "out/shot_0002.png",
+                "out/shot_0003.png"
+            ]
+            .map(PathBuf::from)
+        );
+        assert_eq!(items[0].source, Path::new("zeta.svg"));
+    }
+
+    #[test]
+    fn test_frame_transform() {
+        let map = |ts: tiny_skia::Transform, x: f32, y: f32| {
+            let mut p = tiny_skia::Point::from_xy(x, y);
+            ts.map_point(&mut p);
+            (p.x, p.y)
+        };
+        // 200×100 into 100×100: contain letterboxes top and bottom
+        let ts = frame_transform(200.0, 100.0, 100, 100, FrameFit::Contain);
+        assert_eq!(map(ts, 0.0, 0.0), (0.0, 25.0));
+        assert_eq!(map(ts, 200.0, 100.0), (100.0, 75.0));
+        // Cover crops the sides instead
+        let ts = frame_transform(200.0, 100.0, 100, 100, FrameFit::Cover);
+        assert_eq!(map(ts, 0.0, 0.0), (-50.0, 0.0));
+        assert_eq!(map(ts, 200.0, 100.0), (150.0, 100.0));
+    }
+
+    #[test]
+    fn test_sequence_frames_share_dimensions() {
+        let dir = std::env::temp_dir().join("svg_viewer_test_batch_sequence");
+        let out_dir = dir.join("out");
+        std::fs::create_dir_all(&out_dir).unwrap();
+        for (name, w, h) in [("a.svg", 100, 100), ("b.svg", 102, 98), ("c.svg", 64, 80)] {
+            let svg = format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}"><rect width="{w}" height="{h}" fill="red"/></svg>"#
+            );
+            std::fs::write(dir.join(name), svg).unwrap();
+        }
+
+        let items = plan_sequence(
+            std::slice::from_ref(&dir),
+            &out_dir,
+            "frame_",
+            &ExportFormat::Png,
+        );
+        assert_eq!(items.len(), 3);
+        let renderer = ExportRenderer {
+            format: ExportFormat::Png,
+            width: None,
+            height: None,
+            scale: 1.0,
+            frame: Some(((100, 100), FrameFit::Contain)),
+        };
+        let records = run_batch(&items, &BatchOptions::default(), &renderer, |_| {});
+        for record in &records {
+            assert_eq!(
+                record.outcome,
+                Outcome::Exported {
+                    width: 100,
+                    height: 100,
+                    reduced: None,
+                }
+            );
+            let img = image::open(&record.output).unwrap();
+            assert_eq!((img.width(), img.height()), (100, 100));
+        }
+        // The 64×80 frame is letterboxed left and right
+        let img = image::open(out_dir.join("frame_0003.png"))
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(img.get_pixel(2, 50)[3], 0);
+        assert_eq!(img.get_pixel(50, 50)[3], 255);
+
+        let index = write_sequence_index(&records, &out_dir, SequenceIndex::FfConcat).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(index).unwrap(),
+            "ffconcat version 1.0\nfile 'frame_0001.png'\nfile 'frame_0002.png'\nfile 'frame_0003.png'\n"
+        );
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_plan_items_expands_directories() {
+        let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("assets")
+            .join("test_fixtures");
+        let items = plan_items(
+            std::slice::from_ref(&fixtures),
+            Path::new("out"),
+            &ExportFormat::Jpeg,
+        );
+        assert!(!items.is_empty());
+        assert!(items.iter().all(|i| i.source.starts_with(&fixtures)));
+        let rect = items
+            .iter()
+            .find(|i| i.source.ends_with("simple_rect.svg"))
+            .unwrap();
+        assert_eq!(rect.output, Path::new("out/simple_rect.jpg"));
+    }
+
+    #[test]
+    fn test_plan_items_numbers_shared_outputs() {
+        let inputs: Vec<PathBuf> = ["a/logo.svg", "b/logo.svg", "a/logo.svgz"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        let items = plan_items(&inputs, Path::new("out"), &ExportFormat::Png);
+        let outputs: Vec<_> = items.iter().map(|i| i.output.clone()).collect();
+        assert_eq!(
+            outputs,
+            ["out/logo.png", "out/logo-2.png", "out/logo-3.png"].map(PathBuf::from)
+        );
+    }
+}
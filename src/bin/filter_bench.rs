@@ -1,8 +1,13 @@
 //! Benchmark: compare resvg's feMorphology cost vs. a manual implementation
 //! to determine if resvg's filter pipeline has performance issues.
 
+use std::path::Path;
 use std::time::Instant;
 
+use svg_viewer::renderer;
+use svg_viewer::svg_document::SvgDocument;
+use svg_viewer::viewport::Viewport;
+
 fn main() {
     let size: usize = 1620;
     let pixels = size * size;
@@ -109,15 +114,10 @@ fn main() {
     </g>
 </svg>"##;
 
-    let tree = usvg::Tree::from_data(svg_data, &usvg::Options::default()).unwrap();
-    let mut pixmap = tiny_skia::Pixmap::new(size as u32, size as u32).unwrap();
-    let s = size as f32 / 100.0;
+    let doc = SvgDocument::from_data(Path::new("filter.svg"), svg_data.to_vec()).unwrap();
+    let viewport = Viewport::default();
     let t = Instant::now();
-    resvg::render(
-        &tree,
-        tiny_skia::Transform::from_scale(s, s),
-        &mut pixmap.as_mut(),
-    );
+    renderer::render_for_export(&doc, size as u32, size as u32, &viewport).unwrap();
     let resvg_ms = t.elapsed().as_secs_f64() * 1000.0;
     println!("[resvg actual]         {:>8.3} ms", resvg_ms);
     println!(
@@ -129,14 +129,9 @@ fn main() {
     let svg_nofilter = br##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 100 100" width="100" height="100">
     <rect x="10" y="10" width="80" height="80" fill="#00FFFF" stroke="black" stroke-width="2" />
 </svg>"##;
-    let tree2 = usvg::Tree::from_data(svg_nofilter, &usvg::Options::default()).unwrap();
-    let mut pixmap2 = tiny_skia::Pixmap::new(size as u32, size as u32).unwrap();
+    let doc = SvgDocument::from_data(Path::new("no_filter.svg"), svg_nofilter.to_vec()).unwrap();
     let t = Instant::now();
-    resvg::render(
-        &tree2,
-        tiny_skia::Transform::from_scale(s, s),
-        &mut pixmap2.as_mut(),
-    );
+    renderer::render_for_export(&doc, size as u32, size as u32, &viewport).unwrap();
     let no_filter_ms = t.elapsed().as_secs_f64() * 1000.0;
     println!("[resvg no filter]      {:>8.3} ms", no_filter_ms);
     println!(
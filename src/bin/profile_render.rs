@@ -5,6 +5,10 @@
 use std::path::PathBuf;
 use std::time::Instant;
 
+use svg_viewer::renderer::{self, MAX_RENDER_DIM};
+use svg_viewer::svg_document::SvgDocument;
+use svg_viewer::viewport::Viewport;
+
 fn main() {
     let path = std::env::args()
         .nth(1)
@@ -23,14 +27,14 @@ fn main() {
     println!("[read]  {:>8.3} ms", read_ms);
 
     let t = Instant::now();
-    let tree =
-        usvg::Tree::from_data(&raw_data, &usvg::Options::default()).expect("Failed to parse SVG");
+    let doc = SvgDocument::from_data(&path, raw_data).expect("Failed to parse SVG");
     let parse_ms = t.elapsed().as_secs_f64() * 1000.0;
-    let svg_w = tree.size().width();
-    let svg_h = tree.size().height();
+    let svg_w = doc.width();
+    let svg_h = doc.height();
     println!("[parse] {:>8.3} ms  ({}x{})", parse_ms, svg_w, svg_h);
 
     // Test at various render resolutions
+    let viewport = Viewport::default();
     for &(label, rw, rh) in &[
         ("native 1x", svg_w as u32, svg_h as u32),
         ("native 2x", svg_w as u32 * 2, svg_h as u32 * 2),
@@ -75,18 +79,10 @@ fn main() {
             .1,
         ),
     ] {
-        let rw = rw.clamp(1, 4096);
-        let rh = rh.clamp(1, 4096);
-        let mut pixmap = tiny_skia::Pixmap::new(rw, rh).unwrap();
-        let sx = rw as f32 / svg_w;
-        let sy = rh as f32 / svg_h;
-        let s = sx.min(sy);
+        let rw = rw.clamp(1, MAX_RENDER_DIM);
+        let rh = rh.clamp(1, MAX_RENDER_DIM);
         let t = Instant::now();
-        resvg::render(
-            &tree,
-            tiny_skia::Transform::from_scale(s, s),
-            &mut pixmap.as_mut(),
-        );
+        renderer::render_for_export(&doc, rw, rh, &viewport).expect("Failed to render");
         let ms = t.elapsed().as_secs_f64() * 1000.0;
         println!("[render {label:>12}] {rw:>4}x{rh:<4} {:>8.3} ms", ms);
     }
@@ -0,0 +1,105 @@
+//! The transparency checkerboard, shared by the canvas and by captures that
+//! flatten an image onto the canvas background.
+
+use tiny_skia::Pixmap;
+
+/// Checker square size in logical pixels on the canvas.
+pub const CHECKER_SIZE: f32 = 10.0;
+pub const CHECKER_LIGHT: [u8; 3] = [204, 204, 204];
+pub const CHECKER_DARK: [u8; 3] = [170, 170, 170];
+
+/// What an image is flattened onto before it is copied or exported.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backdrop {
+    Solid([u8; 3]),
+    /// Checker squares `square` pixels wide, with a light square at the origin.
+    Checkerboard {
+        square: f32,
+    },
+}
+
+/// Whether the square containing (`x`, `y`) is a dark one. Squares are counted
+/// from the origin, so (0, 0) is always in a light square.
+pub fn is_dark_square(x: f32, y: f32, square: f32) -> bool {
+    let col = (x / square).floor() as i64;
+    let row = (y / square).floor() as i64;
+    (col + row).rem_euclid(2) == 1
+}
+
+impl Backdrop {
+    pub fn color_at(&self, x: f32, y: f32) -> [u8; 3] {
+        match *self {
+            Backdrop::Solid(color) => color,
+            Backdrop::Checkerboard { square } if is_dark_square(x, y, square) => CHECKER_DARK,
+            Backdrop::Checkerboard { .. } => CHECKER_LIGHT,
+        }
+    }
+}
+
+/// Composite the premultiplied `pixmap` over `backdrop`, leaving it fully opaque.
+/// The backdrop's origin is the pixmap's top-left corner.
+pub fn flatten(pixmap: &mut Pixmap, backdrop: &Backdrop) {
+    let width = pixmap.width() as usize;
+    for (i, px) in pixmap.data_mut().chunks_exact_mut(4).enumerate() {
+        let x = (i % width) as f32 + 0.5;
+        let y = (i / width) as f32 + 0.5;
+        let bg = backdrop.color_at(x, y);
+        let inv_a = 1.0 - px[3] as f32 / 255.0;
+        for c in 0..3 {
+            px[c] = (px[c] as f32 + bg[c] as f32 * inv_a).round().min(255.0) as u8;
+        }
+        px[3] = 255;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phase_aligns_with_origin() {
+        assert!(!is_dark_square(0.0, 0.0, 10.0));
+        assert!(!is_dark_square(9.9, 9.9, 10.0));
+        assert!(is_dark_square(10.0, 0.0, 10.0));
+        assert!(is_dark_square(0.0, 10.0, 10.0));
+        assert!(!is_dark_square(10.0, 10.0, 10.0));
+        // Continues consistently to the left of and above the origin
+        assert!(is_dark_square(-0.5, 0.0, 10.0));
+    }
+
+    #[test]
+    fn test_flatten_is_opaque() {
+        let mut pixmap = Pixmap::new(40, 20).unwrap();
+        // Half-transparent red over the left half
+        for y in 0..20 {
+            for x in 0..20 {
+                let i = (y * 40 + x) * 4;
+                pixmap.data_mut()[i..i + 4].copy_from_slice(&[128, 0, 0, 128]);
+            }
+        }
+        flatten(&mut pixmap, &Backdrop::Checkerboard { square: 10.0 });
+        assert!(pixmap.data().chunks_exact(4).all(|px| px[3] == 255));
+
+        // Untouched pixels show the checker with a light square at the origin
+        let px = |x: u32, y: u32| pixmap.pixel(x, y).unwrap();
+        assert_eq!(px(25, 5).red(), CHECKER_LIGHT[0]);
+        assert_eq!(px(35, 5).red(), CHECKER_DARK[0]);
+        assert_eq!(px(35, 15).red(), CHECKER_LIGHT[0]);
+        // Covered pixels blend the color over the backdrop
+        let blended = px(5, 5);
+        assert_eq!(
+            blended.red(),
+            (128.0_f32 + 204.0 * (127.0 / 255.0)).round() as u8
+        );
+    }
+
+    #[test]
+    fn test_flatten_solid() {
+        let mut pixmap = Pixmap::new(2, 2).unwrap();
+        flatten(&mut pixmap, &Backdrop::Solid([10, 20, 30]));
+        assert!(pixmap
+            .data()
+            .chunks_exact(4)
+            .all(|px| px == [10, 20, 30, 255]));
+    }
+}
@@ -1,9 +1,11 @@
 use arboard::{Clipboard, ImageData};
+use tiny_skia::Pixmap;
 
+use crate::checkerboard::{self, Backdrop};
 use crate::error::{Result, SvgError};
 use crate::export::pixmap_to_rgba;
-use crate::renderer::Renderer;
-use crate::svg_document::SvgDocument;
+use crate::renderer;
+use crate::svg_document::{self, SvgDocument};
 use crate::viewport::Viewport;
 
 pub fn copy_to_clipboard(
@@ -11,8 +13,27 @@ pub fn copy_to_clipboard(
     viewport: &Viewport,
     width: u32,
     height: u32,
+    backdrop: Option<&Backdrop>,
 ) -> Result<()> {
-    let pixmap = Renderer::render_for_export(doc, width, height, viewport)?;
+    let pixmap = renderer::render_for_export(doc, width, height, viewport)?;
+    copy_pixmap(pixmap, backdrop)
+}
+
+/// Put `pixmap` on the clipboard, flattened onto `backdrop` if given.
+///
+/// Applications that lose transparency with a raw bitmap take a PNG instead.
+/// `arboard` offers one next to the bitmap on Windows and as the only format
+/// on Linux, but only an `NSImage` on macOS, so there a PNG and a TIFF are
+/// placed through AppleScript, falling back to the `NSImage` if that fails.
+pub fn copy_pixmap(mut pixmap: Pixmap, backdrop: Option<&Backdrop>) -> Result<()> {
+    if let Some(backdrop) = backdrop {
+        checkerboard::flatten(&mut pixmap, backdrop);
+    }
+    #[cfg(target_os = "macos")]
+    match copy_png(&pixmap) {
+        Ok(()) => return Ok(()),
+        Err(e) => log::warn!("Copying as PNG failed, copying the bitmap instead: {e}"),
+    }
     let rgba = pixmap_to_rgba(&pixmap);
 
     let img_data = ImageData {
@@ -28,3 +49,86 @@ pub fn copy_to_clipboard(
 
     Ok(())
 }
+
+/// Set the clipboard to `pixmap` as both PNG and TIFF, through files that
+/// AppleScript reads in.
+#[cfg(target_os = "macos")]
+fn copy_png(pixmap: &Pixmap) -> Result<()> {
+    let png = pixmap
+        .encode_png()
+        .map_err(|e| SvgError::Clipboard(e.to_string()))?;
+    let rgba = image::RgbaImage::from_raw(pixmap.width(), pixmap.height(), pixmap_to_rgba(pixmap))
+        .ok_or_else(|| SvgError::Clipboard("Failed to create RGBA image".into()))?;
+    let dir = std::env::temp_dir();
+    let stem = format!("svg_viewer_clipboard_{}", std::process::id());
+    let png_path = dir.join(format!("{stem}.png"));
+    let tiff_path = dir.join(format!("{stem}.tiff"));
+
+    let result = std::fs::write(&png_path, png)
+        .map_err(SvgError::from)
+        .and_then(|()| {
+            rgba.save_with_format(&tiff_path, image::ImageFormat::Tiff)
+                .map_err(|e| SvgError::Clipboard(e.to_string()))
+        })
+        .and_then(|()| {
+            let quoted = |path: &std::path::Path| {
+                path.display()
+                    .to_string()
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+            };
+            let script = format!(
+                "set the clipboard to {{\u{00AB}class PNGf\u{00BB}:(read (POSIX file \"{}\") \
+                 as \u{00AB}class PNGf\u{00BB}), TIFF picture:(read (POSIX file \"{}\") \
+                 as TIFF picture)}}",
+                quoted(&png_path),
+                quoted(&tiff_path)
+            );
+            let output = std::process::Command::new("osascript")
+                .args(["-e", &script])
+                .stdin(std::process::Stdio::null())
+                .output()
+                .map_err(|e| SvgError::Clipboard(format!("couldn't run osascript: {e}")))?;
+            if output.status.success() {
+                Ok(())
+            } else {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                Err(SvgError::Clipboard(format!(
+                    "osascript failed: {}",
+                    stderr.trim()
+                )))
+            }
+        });
+    let _ = std::fs::remove_file(&png_path);
+    let _ = std::fs::remove_file(&tiff_path);
+    result
+}
+
+/// Put the document's SVG source on the clipboard as text, decompressed if
+/// it's SVGZ. `arboard` can only offer it as plain text, not as
+/// `image/svg+xml`, though vector editors generally accept pasted markup.
+pub fn copy_svg_source(doc: &SvgDocument) -> Result<()> {
+    let text = svg_document::svg_text(doc.raw_data())
+        .map_err(|e| SvgError::Clipboard(format!("the source can't be copied as text: {e}")))?;
+    copy_text(text)
+}
+
+/// Put `text` on the clipboard.
+pub fn copy_text(text: String) -> Result<()> {
+    let mut clipboard = Clipboard::new().map_err(|e| SvgError::Clipboard(e.to_string()))?;
+    clipboard
+        .set_text(text)
+        .map_err(|e| SvgError::Clipboard(e.to_string()))
+}
+
+/// The text on the clipboard, to be parsed as SVG markup.
+pub fn read_svg_text() -> Result<String> {
+    let mut clipboard = Clipboard::new().map_err(|e| SvgError::Clipboard(e.to_string()))?;
+    let text = clipboard
+        .get_text()
+        .map_err(|e| SvgError::Clipboard(e.to_string()))?;
+    if text.trim().is_empty() {
+        return Err(SvgError::Clipboard("the clipboard has no text".into()));
+    }
+    Ok(text)
+}
@@ -0,0 +1,271 @@
+//! Converting renders for the display they're shown on.
+//!
+//! Renders are sRGB. A wide-gamut panel that takes them as its own, wider,
+//! color space shows saturated colors oversaturated, so with color management
+//! on the canvas render is converted to the display's profile before upload.
+//! The profile is chosen by the user; nothing is queried from the OS. Exports
+//! and copies are never converted.
+
+use serde::{Deserialize, Serialize};
+use tiny_skia::{Pixmap, PremultipliedColorU8};
+
+use crate::renderer::{PostPipeline, PostProcess};
+
+/// Color space of the display the canvas is shown on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DisplayProfile {
+    #[default]
+    Srgb,
+    DisplayP3,
+}
+
+impl DisplayProfile {
+    pub fn all() -> &'static [DisplayProfile] {
+        &[DisplayProfile::Srgb, DisplayProfile::DisplayP3]
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            DisplayProfile::Srgb => "sRGB",
+            DisplayProfile::DisplayP3 => "Display P3",
+        }
+    }
+
+    /// Linear sRGB to this profile's linear RGB, or `None` when it is sRGB.
+    fn matrix(&self) -> Option<[[f32; 3]; 3]> {
+        match self {
+            DisplayProfile::Srgb => None,
+            // Both are D65, so only the primaries differ
+            DisplayProfile::DisplayP3 => Some([
+                [0.822_462_1, 0.177_538, 0.0],
+                [0.033_194_2, 0.966_805_8, 0.0],
+                [0.017_082_7, 0.072_397_4, 0.910_519_9],
+            ]),
+        }
+    }
+}
+
+/// Whether and for what display the canvas render is converted. Off by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ColorManagement {
+    pub enabled: bool,
+    pub profile: DisplayProfile,
+}
+
+impl ColorManagement {
+    /// Whether renders are changed at all.
+    pub fn converts(&self) -> bool {
+        self.enabled && self.profile.matrix().is_some()
+    }
+
+    /// The active transform for the status bar, while color management is on.
+    pub fn label(&self) -> Option<String> {
+        if !self.enabled {
+            return None;
+        }
+        Some(match self.profile {
+            DisplayProfile::Srgb => "sRGB (no conversion)".into(),
+            profile => format!("sRGB \u{2192} {}", profile.name()),
+        })
+    }
+}
+
+/// Steps in the linear-to-encoded table; fine enough that near-black values,
+/// where the curve is steepest, land within a quarter level.
+const ENCODE_STEPS: usize = 16384;
+
+/// Lookup tables for one profile's conversion. Display P3 uses the sRGB
+/// transfer curve, so one pair of tables serves both ends.
+struct Conversion {
+    matrix: [[f32; 3]; 3],
+    decode: [f32; 256],
+    encode: Vec<u8>,
+}
+
+fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.040_45 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+impl Conversion {
+    fn new(profile: DisplayProfile) -> Option<Self> {
+        let matrix = profile.matrix()?;
+        let decode = std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0));
+        let encode = (0..ENCODE_STEPS)
+            .map(|i| {
+                let linear = i as f32 / (ENCODE_STEPS - 1) as f32;
+                (linear_to_srgb(linear) * 255.0).round() as u8
+            })
+            .collect();
+        Some(Self {
+            matrix,
+            decode,
+            encode,
+        })
+    }
+
+    fn convert(&self, [r, g, b]: [u8; 3]) -> [u8; 3] {
+        let linear = [
+            self.decode[r as usize],
+            self.decode[g as usize],
+            self.decode[b as usize],
+        ];
+        self.matrix.map(|row| {
+            let v = row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2];
+            let index = (v.clamp(0.0, 1.0) * (ENCODE_STEPS - 1) as f32).round();
+            self.encode[index as usize]
+        })
+    }
+
+    /// Convert premultiplied pixels in place.
+    fn convert_premultiplied(&self, pixels: &mut [PremultipliedColorU8]) {
+        for pixel in pixels {
+            let alpha = pixel.alpha();
+            if alpha == 0 {
+                continue;
+            }
+            let unpremultiply = |c: u8| ((c as u32 * 255 + alpha as u32 / 2) / alpha as u32) as u8;
+            let [r, g, b] = self.convert([
+                unpremultiply(pixel.red()),
+                unpremultiply(pixel.green()),
+                unpremultiply(pixel.blue()),
+            ]);
+            let premultiply = |c: u8| ((c as u32 * alpha as u32 + 127) / 255) as u8;
+            *pixel = PremultipliedColorU8::from_rgba(
+                premultiply(r),
+                premultiply(g),
+                premultiply(b),
+                alpha,
+            )
+            .unwrap_or(*pixel);
+        }
+    }
+}
+
+/// The canvas render's conversion for the display, as a post-processing effect.
+pub struct DisplayConversion(Conversion);
+
+impl DisplayConversion {
+    /// The conversion `color` asks for, or `None` when it leaves renders alone.
+    pub fn new(color: ColorManagement) -> Option<Self> {
+        if !color.enabled {
+            return None;
+        }
+        Conversion::new(color.profile).map(Self)
+    }
+}
+
+impl PostProcess for DisplayConversion {
+    fn apply(&self, pixels: &mut [PremultipliedColorU8], _width: u32, _height: u32) {
+        self.0.convert_premultiplied(pixels);
+    }
+}
+
+/// An opaque sRGB color as `profile` encodes it.
+pub fn convert_color(rgb: [u8; 3], profile: DisplayProfile) -> [u8; 3] {
+    match Conversion::new(profile) {
+        Some(conversion) => conversion.convert(rgb),
+        None => rgb,
+    }
+}
+
+/// Convert an sRGB render to `profile` in place, splitting the rows across
+/// threads for large pixmaps.
+pub fn convert_pixmap(pixmap: &mut Pixmap, profile: DisplayProfile) {
+    let color = ColorManagement {
+        enabled: true,
+        profile,
+    };
+    if let Some(conversion) = DisplayConversion::new(color) {
+        PostPipeline::new().with(conversion).run(pixmap);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: [u8; 3], expected: [u8; 3]) {
+        let close = actual
+            .iter()
+            .zip(expected)
+            .all(|(a, e)| (*a as i32 - e as i32).abs() <= 1);
+        assert!(close, "{actual:?} != {expected:?}");
+    }
+
+    #[test]
+    fn test_srgb_primaries_in_display_p3() {
+        let p3 = DisplayProfile::DisplayP3;
+        assert_near(convert_color([255, 0, 0], p3), [234, 51, 35]);
+        assert_near(convert_color([0, 255, 0], p3), [117, 251, 76]);
+        assert_near(convert_color([0, 0, 255], p3), [0, 0, 245]);
+    }
+
+    #[test]
+    fn test_neutrals_are_unchanged() {
+        for v in [0, 1, 18, 128, 200, 255] {
+            assert_eq!(
+                convert_color([v, v, v], DisplayProfile::DisplayP3),
+                [v, v, v]
+            );
+        }
+        assert_eq!(
+            convert_color([255, 0, 0], DisplayProfile::Srgb),
+            [255, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_pixmap_conversion_keeps_alpha() {
+        let mut pixmap = Pixmap::new(4, 1).unwrap();
+        pixmap.fill(tiny_skia::Color::from_rgba8(255, 0, 0, 128));
+        convert_pixmap(&mut pixmap, DisplayProfile::DisplayP3);
+        let pixel = pixmap.pixels()[0].demultiply();
+        assert_eq!(pixel.alpha(), 128);
+        assert_near([pixel.red(), pixel.green(), pixel.blue()], [234, 51, 35]);
+
+        // Fully transparent pixels stay untouched
+        let mut clear = Pixmap::new(2, 2).unwrap();
+        convert_pixmap(&mut clear, DisplayProfile::DisplayP3);
+        assert!(clear.data().iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_parallel_conversion_matches_serial() {
+        let (w, h) = (300, 300);
+        let mut pixmap = Pixmap::new(w, h).unwrap();
+        for (i, pixel) in pixmap.data_mut().chunks_exact_mut(4).enumerate() {
+            pixel.copy_from_slice(&[(i % 256) as u8, (i / 7 % 256) as u8, 40, 255]);
+        }
+        let conversion = Conversion::new(DisplayProfile::DisplayP3).unwrap();
+        let mut serial = pixmap.pixels().to_vec();
+        conversion.convert_premultiplied(&mut serial);
+        convert_pixmap(&mut pixmap, DisplayProfile::DisplayP3);
+        assert_eq!(pixmap.pixels(), serial.as_slice());
+    }
+
+    #[test]
+    fn test_label() {
+        let mut cm = ColorManagement::default();
+        assert_eq!(cm.label(), None);
+        assert!(!cm.converts());
+        cm.enabled = true;
+        assert_eq!(cm.label().as_deref(), Some("sRGB (no conversion)"));
+        assert!(!cm.converts());
+        cm.profile = DisplayProfile::DisplayP3;
+        assert_eq!(cm.label().as_deref(), Some("sRGB \u{2192} Display P3"));
+        assert!(cm.converts());
+    }
+}
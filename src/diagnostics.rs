@@ -0,0 +1,231 @@
+//! Pinpointing where a malformed document goes wrong.
+//!
+//! When parsing fails at the XML level, the error is located in the source:
+//! its line and column, the tag it's in, and the lines around it, so the
+//! viewer can show what's wrong rather than just that something is.
+
+use usvg::roxmltree;
+
+/// Lines of source shown before and after the offending one.
+pub const SNIPPET_CONTEXT: usize = 2;
+/// Widest stretch of a line shown, in characters.
+pub const SNIPPET_WIDTH: usize = 100;
+
+/// One line of source around a parse error.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceLine {
+    /// 1-based line number
+    pub number: u32,
+    pub text: String,
+}
+
+/// Where and why the source of a document isn't well-formed XML.
+#[derive(Clone, Debug, PartialEq)]
+pub struct XmlDiagnostic {
+    pub message: String,
+    /// 1-based line and column of the error
+    pub line: u32,
+    pub column: u32,
+    /// The tag the error is in, or the close tag that didn't match
+    pub tag: Option<String>,
+    /// The lines around the error, cut to the same stretch of columns
+    pub snippet: Vec<SourceLine>,
+    /// Column of the error within the snippet's lines, 0-based
+    pub snippet_column: usize,
+}
+
+impl std::fmt::Display for XmlDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.message, self.line, self.column
+        )?;
+        if let Some(tag) = &self.tag {
+            write!(f, " (in <{tag}>)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Locate `error`, raised parsing `text`.
+pub fn diagnose(text: &str, error: &roxmltree::Error) -> XmlDiagnostic {
+    let pos = match error {
+        // Reported at the start, though what's missing is at the end
+        roxmltree::Error::UnclosedRootNode => end_position(text),
+        _ => {
+            let pos = error.pos();
+            (pos.row, pos.col)
+        }
+    };
+    // The position is already part of the message
+    let roxml_pos = error.pos();
+    let message = error
+        .to_string()
+        .replace(&format!(" at {}:{}", roxml_pos.row, roxml_pos.col), "");
+    let offset = byte_offset(text, pos);
+    let tag = match error {
+        roxmltree::Error::UnexpectedCloseTag(_, actual, _) => Some(actual.clone()),
+        roxmltree::Error::UnclosedRootNode => root_tag(text),
+        _ => enclosing_tag(&text[..offset]),
+    };
+    at(text, pos, message, tag)
+}
+
+/// Locate the first byte of `data` that isn't UTF-8.
+pub fn diagnose_utf8(data: &[u8], error: &std::str::Utf8Error) -> XmlDiagnostic {
+    let valid = String::from_utf8_lossy(&data[..error.valid_up_to()]);
+    let line = valid.matches('\n').count() as u32 + 1;
+    let column = valid.rsplit('\n').next().map_or(0, |l| l.chars().count()) as u32 + 1;
+    let text = String::from_utf8_lossy(data);
+    let tag = enclosing_tag(&valid);
+    at(&text, (line, column), "not valid UTF-8".into(), tag)
+}
+
+fn at(
+    text: &str,
+    (line, column): (u32, u32),
+    message: String,
+    tag: Option<String>,
+) -> XmlDiagnostic {
+    let index = line.saturating_sub(1) as usize;
+    let first = index.saturating_sub(SNIPPET_CONTEXT);
+    // Long lines are cut to a window around the error's column
+    let column0 = column.saturating_sub(1) as usize;
+    let start = if column0 < SNIPPET_WIDTH {
+        0
+    } else {
+        column0 - SNIPPET_WIDTH / 2
+    };
+    let snippet = text
+        .lines()
+        .enumerate()
+        .skip(first)
+        .take(index - first + SNIPPET_CONTEXT + 1)
+        .map(|(i, line)| SourceLine {
+            number: i as u32 + 1,
+            text: line
+                .chars()
+                .skip(start)
+                .take(SNIPPET_WIDTH)
+                .collect::<String>()
+                .replace('\t', " "),
+        })
+        .collect();
+    XmlDiagnostic {
+        message,
+        line,
+        column,
+        tag,
+        snippet,
+        snippet_column: column0 - start,
+    }
+}
+
+/// Byte offset of a 1-based line and column (in characters).
+fn byte_offset(text: &str, (line, column): (u32, u32)) -> usize {
+    let mut line_start = 0;
+    for _ in 1..line {
+        match text[line_start..].find('\n') {
+            Some(i) => line_start += i + 1,
+            None => return text.len(),
+        }
+    }
+    text[line_start..]
+        .char_indices()
+        .nth(column.saturating_sub(1) as usize)
+        .map_or(text.len(), |(i, _)| line_start + i)
+}
+
+fn end_position(text: &str) -> (u32, u32) {
+    let line = text.matches('\n').count() as u32 + 1;
+    let column = text.rsplit('\n').next().map_or(0, |l| l.chars().count()) as u32 + 1;
+    (line, column)
+}
+
+fn tag_name(after_lt: &str) -> Option<String> {
+    let name: String = after_lt
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || matches!(c, ':' | '_' | '-' | '.'))
+        .collect();
+    (!name.is_empty()).then_some(name)
+}
+
+/// The name of the last tag opened or closed in `before`.
+fn enclosing_tag(before: &str) -> Option<String> {
+    let lt = before.rfind('<')?;
+    tag_name(before[lt + 1..].trim_start_matches('/'))
+}
+
+/// The name of the first element in `text`.
+fn root_tag(text: &str) -> Option<String> {
+    text.match_indices('<')
+        .find_map(|(i, _)| tag_name(&text[i + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn diagnose_str(text: &str) -> XmlDiagnostic {
+        let error = roxmltree::Document::parse(text).unwrap_err();
+        diagnose(text, &error)
+    }
+
+    #[test]
+    fn test_mismatched_close_tag() {
+        let text =
+            "<svg xmlns=\"http://www.w3.org/2000/svg\">\n  <g>\n    <rect/>\n  </rect>\n</svg>\n";
+        let diag = diagnose_str(text);
+        assert_eq!((diag.line, diag.column), (4, 3));
+        assert_eq!(diag.tag.as_deref(), Some("rect"));
+        assert_eq!(diag.message, "expected 'g' tag, not 'rect'");
+        let numbers: Vec<u32> = diag.snippet.iter().map(|l| l.number).collect();
+        assert_eq!(numbers, vec![2, 3, 4, 5]);
+        assert_eq!(diag.snippet[2].text, "  </rect>");
+        assert_eq!(diag.snippet_column, 2);
+        assert_eq!(
+            diag.to_string(),
+            "expected 'g' tag, not 'rect' at line 4, column 3 (in <rect>)"
+        );
+    }
+
+    #[test]
+    fn test_error_inside_a_tag() {
+        let text = "<svg>\n<circle r=\"5\" r=\"6\"/>\n</svg>";
+        let diag = diagnose_str(text);
+        assert_eq!(diag.line, 2);
+        assert_eq!(diag.tag.as_deref(), Some("circle"));
+        assert!(diag.message.contains("'r'"), "{}", diag.message);
+        assert!(!diag.message.contains("2:"), "{}", diag.message);
+    }
+
+    #[test]
+    fn test_unclosed_root_is_reported_at_the_end() {
+        let text = "<svg>\n  <rect/>\n";
+        let diag = diagnose_str(text);
+        assert_eq!((diag.line, diag.column), (3, 1));
+        assert_eq!(diag.tag.as_deref(), Some("svg"));
+    }
+
+    #[test]
+    fn test_long_lines_are_windowed() {
+        let text = format!("<svg>{}<</svg>", " ".repeat(300));
+        let diag = diagnose_str(&text);
+        let line = &diag.snippet[0].text;
+        assert!(line.chars().count() <= SNIPPET_WIDTH);
+        assert_eq!(line.chars().nth(diag.snippet_column), Some('<'));
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let data = b"<svg>\n<text>caf\xe9</text></svg>".to_vec();
+        let result = crate::svg_document::SvgDocument::from_data(Path::new("bad.svg"), data);
+        let Err(crate::error::SvgError::Malformed(diag)) = result else {
+            panic!("expected a located error");
+        };
+        assert_eq!((diag.line, diag.column), (2, 10));
+        assert_eq!(diag.tag.as_deref(), Some("text"));
+    }
+}
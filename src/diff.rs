@@ -0,0 +1,153 @@
+//! Pixel comparison of two renders, for `--diff` regression checks.
+//!
+//! Pixels are compared un-premultiplied, so a change of color under partial
+//! transparency counts at its full size.
+
+use tiny_skia::{Pixmap, PremultipliedColorU8};
+
+use crate::error::{Result, SvgError};
+use crate::export::pixmap_to_rgba;
+
+/// How unchanged pixels show in the heatmap: faded toward white, for context.
+const CONTEXT_FADE: u32 = 4;
+
+/// What comparing two renders found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiffReport {
+    pub width: u32,
+    pub height: u32,
+    /// Pixels where any channel differs
+    pub differing: u64,
+    /// The largest difference in any channel of any pixel, 0 to 255
+    pub max_delta: u8,
+}
+
+impl DiffReport {
+    pub fn total(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    /// Differing pixels as a percentage of all of them.
+    pub fn percent(&self) -> f64 {
+        if self.total() == 0 {
+            return 0.0;
+        }
+        self.differing as f64 / self.total() as f64 * 100.0
+    }
+
+    /// One line for the terminal.
+    pub fn describe(&self) -> String {
+        format!(
+            "{} of {} pixels differ ({:.3}%), max channel delta {}",
+            self.differing,
+            self.total(),
+            self.percent(),
+            self.max_delta
+        )
+    }
+}
+
+/// Compare `a` and `b`, which must be the same size. Returns the report and a
+/// heatmap: differing pixels in red, brighter for larger differences, over a
+/// faded copy of `b`.
+pub fn compare(a: &Pixmap, b: &Pixmap) -> Result<(DiffReport, Pixmap)> {
+    if (a.width(), a.height()) != (b.width(), b.height()) {
+        return Err(SvgError::Render(format!(
+            "Can't compare a {}x{} render with a {}x{} one",
+            a.width(),
+            a.height(),
+            b.width(),
+            b.height()
+        )));
+    }
+    let mut heatmap = Pixmap::new(a.width(), a.height())
+        .ok_or_else(|| SvgError::Render("Failed to create pixmap".into()))?;
+    let (rgba_a, rgba_b) = (pixmap_to_rgba(a), pixmap_to_rgba(b));
+    let mut report = DiffReport {
+        width: a.width(),
+        height: a.height(),
+        differing: 0,
+        max_delta: 0,
+    };
+    let pixels = rgba_a.chunks_exact(4).zip(rgba_b.chunks_exact(4));
+    for ((pa, pb), out) in pixels.zip(heatmap.pixels_mut()) {
+        let delta = pa
+            .iter()
+            .zip(pb)
+            .map(|(ca, cb)| ca.abs_diff(*cb))
+            .max()
+            .unwrap_or(0);
+        *out = if delta > 0 {
+            report.differing += 1;
+            report.max_delta = report.max_delta.max(delta);
+            // Even a one-step difference should stand out
+            let strength = 128 + delta as u32 / 2;
+            opaque(strength as u8, 0, 0)
+        } else {
+            opaque_context(pb)
+        };
+    }
+    Ok((report, heatmap))
+}
+
+/// `rgba`'s luma, composited on white and faded most of the way to white.
+fn opaque_context(rgba: &[u8]) -> PremultipliedColorU8 {
+    let alpha = rgba[3] as u32;
+    let luma = (rgba[0] as u32 * 2126 + rgba[1] as u32 * 7152 + rgba[2] as u32 * 722) / 10000;
+    let on_white = (luma * alpha + 255 * (255 - alpha)) / 255;
+    let faded = (255 * (CONTEXT_FADE - 1) + on_white) / CONTEXT_FADE;
+    opaque(faded as u8, faded as u8, faded as u8)
+}
+
+fn opaque(r: u8, g: u8, b: u8) -> PremultipliedColorU8 {
+    PremultipliedColorU8::from_rgba(r, g, b, 255).expect("opaque colors are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiny_skia::Color;
+
+    fn filled(color: Color) -> Pixmap {
+        let mut pixmap = Pixmap::new(4, 4).unwrap();
+        pixmap.fill(color);
+        pixmap
+    }
+
+    #[test]
+    fn test_identical_renders() {
+        let a = filled(Color::from_rgba8(10, 20, 30, 255));
+        let (report, heatmap) = compare(&a, &a.clone()).unwrap();
+        assert_eq!(report.differing, 0);
+        assert_eq!(report.max_delta, 0);
+        assert_eq!(report.percent(), 0.0);
+        // No red anywhere
+        assert!(heatmap.pixels().iter().all(|p| p.red() == p.green()));
+    }
+
+    #[test]
+    fn test_differing_pixels_are_counted_and_marked() {
+        let a = filled(Color::WHITE);
+        let mut b = a.clone();
+        b.pixels_mut()[5] = PremultipliedColorU8::from_rgba(255, 215, 255, 255).unwrap();
+        b.pixels_mut()[6] = PremultipliedColorU8::from_rgba(0, 0, 0, 0).unwrap();
+        let (report, heatmap) = compare(&a, &b).unwrap();
+        assert_eq!(report.differing, 2);
+        assert_eq!(report.max_delta, 255);
+        assert_eq!(report.percent(), 12.5);
+        assert_eq!(
+            report.describe(),
+            "2 of 16 pixels differ (12.500%), max channel delta 255"
+        );
+        let marked = heatmap.pixels()[5];
+        assert_eq!((marked.red(), marked.green()), (148, 0));
+        assert_eq!(heatmap.pixels()[0].red(), 255);
+    }
+
+    #[test]
+    fn test_sizes_must_match() {
+        let a = filled(Color::WHITE);
+        let b = Pixmap::new(4, 5).unwrap();
+        assert!(compare(&a, &b).is_err());
+    }
+}
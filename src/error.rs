@@ -1,7 +1,9 @@
 use thiserror::Error;
 
+use crate::diagnostics::XmlDiagnostic;
+
+/// Errors from loading, rendering and exporting documents.
 #[derive(Error, Debug)]
-#[allow(dead_code)]
 pub enum SvgError {
     #[error("Failed to read file: {0}")]
     Io(#[from] std::io::Error),
@@ -9,15 +11,31 @@ pub enum SvgError {
     #[error("Failed to parse SVG: {0}")]
     Parse(String),
 
+    /// The source isn't well-formed XML; located for display
+    #[error("Failed to parse SVG: {0}")]
+    Malformed(Box<XmlDiagnostic>),
+
     #[error("Failed to render SVG: {0}")]
     Render(String),
 
     #[error("Failed to export image: {0}")]
     Export(String),
 
+    #[error("Failed to read archive: {0}")]
+    Archive(String),
+
+    #[error("Failed to download: {0}")]
+    Network(String),
+
     #[error("Clipboard error: {0}")]
     Clipboard(String),
 
+    #[error("Invalid region: {0}")]
+    InvalidRegion(String),
+
+    #[error("No element with id \"{0}\"")]
+    ElementNotFound(String),
+
     #[error("No file loaded")]
     NoFile,
 }
@@ -0,0 +1,260 @@
+//! In-memory record of recent user-facing events, for the event log window.
+//!
+//! Events arrive through the `log` crate: [`TeeLogger`] passes records to an
+//! `env_logger` console logger (so `RUST_LOG` still controls the console) and
+//! records everything at info level and above in [`global`].
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+/// Number of events kept; older ones are dropped first.
+pub const EVENT_LOG_CAPACITY: usize = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warn,
+    Info,
+}
+
+impl Severity {
+    pub fn all() -> &'static [Severity] {
+        &[Severity::Error, Severity::Warn, Severity::Info]
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Severity::Error => "Error",
+            Severity::Warn => "Warning",
+            Severity::Info => "Info",
+        }
+    }
+
+    /// `None` for debug and trace records, which aren't kept.
+    pub fn from_level(level: log::Level) -> Option<Severity> {
+        match level {
+            log::Level::Error => Some(Severity::Error),
+            log::Level::Warn => Some(Severity::Warn),
+            log::Level::Info => Some(Severity::Info),
+            log::Level::Debug | log::Level::Trace => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    /// Seconds since the log was created.
+    pub elapsed_secs: f64,
+    pub severity: Severity,
+    pub target: String,
+    pub message: String,
+}
+
+impl Event {
+    pub fn to_line(&self) -> String {
+        format!(
+            "[{:>9.3}s] {:<7} {}: {}",
+            self.elapsed_secs,
+            self.severity.name(),
+            self.target,
+            self.message
+        )
+    }
+}
+
+/// A fixed-size ring buffer of events, safe to push to from any thread.
+pub struct EventLog {
+    start: Instant,
+    capacity: usize,
+    events: Mutex<VecDeque<Event>>,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new(EVENT_LOG_CAPACITY)
+    }
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            start: Instant::now(),
+            capacity,
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn push(&self, severity: Severity, target: &str, message: impl Into<String>) {
+        let event = Event {
+            elapsed_secs: self.start.elapsed().as_secs_f64(),
+            severity,
+            target: target.to_string(),
+            message: message.into(),
+        };
+        let mut events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    /// The kept events, oldest first.
+    pub fn snapshot(&self) -> Vec<Event> {
+        let events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+        events.iter().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.events
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    /// Every kept event as plain text, one per line.
+    pub fn to_text(&self) -> String {
+        self.snapshot().iter().map(|e| e.to_line() + "\n").collect()
+    }
+}
+
+/// The application-wide event log that [`TeeLogger`] fills.
+pub fn global() -> &'static EventLog {
+    static LOG: OnceLock<EventLog> = OnceLock::new();
+    LOG.get_or_init(EventLog::default)
+}
+
+/// Logs to the console as `env_logger` would and records info-and-above events
+/// in an [`EventLog`], whatever `RUST_LOG` says.
+pub struct TeeLogger {
+    console: env_logger::Logger,
+    events: &'static EventLog,
+}
+
+impl TeeLogger {
+    pub fn new(console: env_logger::Logger, events: &'static EventLog) -> Self {
+        Self { console, events }
+    }
+
+    /// Install a tee of the `RUST_LOG`-configured console logger and [`global`].
+    pub fn init() -> Result<(), log::SetLoggerError> {
+        let console = env_logger::Builder::from_default_env().build();
+        let max_level = console.filter().max(log::LevelFilter::Info);
+        log::set_boxed_logger(Box::new(TeeLogger::new(console, global())))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+}
+
+impl log::Log for TeeLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.console.enabled(metadata) || metadata.level() <= log::Level::Info
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.console.matches(record) {
+            self.console.log(record);
+        }
+        if let Some(severity) = Severity::from_level(record.level()) {
+            self.events
+                .push(severity, record.target(), record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {
+        self.console.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Log;
+
+    #[test]
+    fn test_wraps_at_capacity() {
+        let log = EventLog::new(3);
+        for i in 0..5 {
+            log.push(Severity::Info, "test", format!("event {i}"));
+        }
+        let messages: Vec<_> = log.snapshot().into_iter().map(|e| e.message).collect();
+        assert_eq!(messages, ["event 2", "event 3", "event 4"]);
+        log.clear();
+        assert!(log.snapshot().is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_pushes() {
+        let log = EventLog::new(1000);
+        std::thread::scope(|scope| {
+            for t in 0..8 {
+                let log = &log;
+                scope.spawn(move || {
+                    for i in 0..100 {
+                        log.push(Severity::Warn, "worker", format!("{t}:{i}"));
+                    }
+                });
+            }
+        });
+        let events = log.snapshot();
+        assert_eq!(events.len(), 800);
+        // Each thread's events stay in the order it pushed them
+        for t in 0..8 {
+            let prefix = format!("{t}:");
+            let order: Vec<u32> = events
+                .iter()
+                .filter_map(|e| e.message.strip_prefix(&prefix))
+                .map(|i| i.parse().unwrap())
+                .collect();
+            assert_eq!(order, (0..100).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_concurrent_pushes_past_capacity() {
+        let log = EventLog::new(50);
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..100 {
+                        log.push(Severity::Info, "worker", "x");
+                    }
+                });
+            }
+        });
+        assert_eq!(log.snapshot().len(), 50);
+    }
+
+    #[test]
+    fn test_tee_records_info_even_when_console_is_quiet() {
+        static EVENTS: OnceLock<EventLog> = OnceLock::new();
+        let events = EVENTS.get_or_init(EventLog::default);
+        let console = env_logger::Builder::new()
+            .filter_level(log::LevelFilter::Error)
+            .build();
+        let tee = TeeLogger::new(console, events);
+
+        let info = log::Metadata::builder().level(log::Level::Info).build();
+        let debug = log::Metadata::builder().level(log::Level::Debug).build();
+        assert!(tee.enabled(&info));
+        assert!(!tee.enabled(&debug));
+
+        for level in [log::Level::Info, log::Level::Debug] {
+            tee.log(
+                &log::Record::builder()
+                    .level(level)
+                    .target("svg_viewer::app")
+                    .args(format_args!("loaded"))
+                    .build(),
+            );
+        }
+        let kept = events.snapshot();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].severity, Severity::Info);
+        assert_eq!(kept[0].target, "svg_viewer::app");
+        assert!(kept[0]
+            .to_line()
+            .ends_with("Info    svg_viewer::app: loaded"));
+    }
+}
@@ -1,9 +1,15 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use tiny_skia::Pixmap;
 
 use crate::error::{Result, SvgError};
-use crate::renderer::Renderer;
-use crate::svg_document::SvgDocument;
+use crate::folder_info;
+use crate::icon;
+use crate::jpeg::{self, ChromaSubsampling};
+use crate::pdf;
+use crate::renderer::{self, PostPipeline, PreviewEffects, EXPORT_BAND_ROWS, MAX_RENDER_DIM};
+use crate::svg_document::{self, SvgDocument};
+use crate::timestamp;
 use crate::viewport::Viewport;
 
 #[derive(Clone, Debug, PartialEq)]
@@ -12,7 +18,19 @@ pub enum ExportFormat {
     Jpeg,
     Bmp,
     Tiff,
+    /// Always lossless; the `image` crate has no lossy WebP encoder
     WebP,
+    /// Lossy, at `avif_quality`, through the `image` crate's encoder
+    Avif,
+    /// A page drawn from the document's vectors, or holding the rendered
+    /// image at `pdf_dpi` pixels to the inch; see [`ExportSettings::pdf_vector`]
+    Pdf,
+    /// The document itself, simplified or as written; see [`write_svg`]
+    Svg,
+    /// A Windows icon holding a square render at each of `icon_sizes`
+    Ico,
+    /// A macOS icon, as [`ExportFormat::Ico`]
+    Icns,
 }
 
 impl ExportFormat {
@@ -23,13 +41,87 @@ impl ExportFormat {
             ExportFormat::Bmp => "bmp",
             ExportFormat::Tiff => "tiff",
             ExportFormat::WebP => "webp",
+            ExportFormat::Avif => "avif",
+            ExportFormat::Pdf => "pdf",
+            ExportFormat::Svg => "svg",
+            ExportFormat::Ico => "ico",
+            ExportFormat::Icns => "icns",
+        }
+    }
+
+    /// The encoder used, whatever the output file's extension. PDFs are
+    /// written by [`crate::pdf`], SVGs by [`write_svg`] and icons by
+    /// [`crate::icon`] instead.
+    fn image_format(&self) -> image::ImageFormat {
+        match self {
+            ExportFormat::Pdf | ExportFormat::Svg | ExportFormat::Ico | ExportFormat::Icns => {
+                unreachable!("{} isn't an image format", self.name())
+            }
+            ExportFormat::Png => image::ImageFormat::Png,
+            ExportFormat::Jpeg => image::ImageFormat::Jpeg,
+            ExportFormat::Bmp => image::ImageFormat::Bmp,
+            ExportFormat::Tiff => image::ImageFormat::Tiff,
+            ExportFormat::WebP => image::ImageFormat::WebP,
+            ExportFormat::Avif => image::ImageFormat::Avif,
         }
     }
 
     pub fn supports_alpha(&self) -> bool {
         matches!(
             self,
-            ExportFormat::Png | ExportFormat::Tiff | ExportFormat::WebP
+            ExportFormat::Png
+                | ExportFormat::Tiff
+                | ExportFormat::WebP
+                | ExportFormat::Avif
+                | ExportFormat::Pdf
+                | ExportFormat::Svg
+                | ExportFormat::Ico
+                | ExportFormat::Icns
+        )
+    }
+
+    /// Whether the output is sized by `width` and `height`. SVGs aren't
+    /// rendered, and icons come in each of `icon_sizes`.
+    pub fn has_dimensions(&self) -> bool {
+        !matches!(
+            self,
+            ExportFormat::Svg | ExportFormat::Ico | ExportFormat::Icns
+        )
+    }
+
+    pub fn is_icon(&self) -> bool {
+        matches!(self, ExportFormat::Ico | ExportFormat::Icns)
+    }
+
+    /// The sizes an icon format can hold, and none for other formats.
+    pub fn icon_sizes(&self) -> Vec<u32> {
+        match self {
+            ExportFormat::Ico => icon::ICO_SIZES.to_vec(),
+            ExportFormat::Icns => icon::icns_sizes().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether this build has an encoder for the format. AVIF needs the
+    /// `image` crate's `avif` feature, which pulls in an AV1 encoder.
+    pub fn is_available(&self) -> bool {
+        match self {
+            ExportFormat::Avif => self.image_format().writing_enabled(),
+            _ => true,
+        }
+    }
+
+    /// Whether the format can carry an EXIF-style Orientation tag.
+    pub fn supports_orientation_tag(&self) -> bool {
+        matches!(self, ExportFormat::Jpeg | ExportFormat::Tiff)
+    }
+
+    /// Whether exports record [`ImageMetadata`]: as text chunks in a PNG and
+    /// EXIF in a JPEG or WebP.
+    pub fn supports_metadata(&self) -> bool {
+        matches!(
+            self,
+            ExportFormat::Png | ExportFormat::Jpeg | ExportFormat::WebP
         )
     }
 
@@ -40,6 +132,11 @@ impl ExportFormat {
             ExportFormat::Bmp,
             ExportFormat::Tiff,
             ExportFormat::WebP,
+            ExportFormat::Avif,
+            ExportFormat::Pdf,
+            ExportFormat::Svg,
+            ExportFormat::Ico,
+            ExportFormat::Icns,
         ]
     }
 
@@ -50,18 +147,671 @@ impl ExportFormat {
             ExportFormat::Bmp => "BMP",
             ExportFormat::Tiff => "TIFF",
             ExportFormat::WebP => "WebP",
+            ExportFormat::Avif => "AVIF",
+            ExportFormat::Pdf => "PDF",
+            ExportFormat::Svg => "SVG",
+            ExportFormat::Ico => "ICO",
+            ExportFormat::Icns => "ICNS",
+        }
+    }
+
+    /// Largest width and height the encoder for this format accepts.
+    pub fn max_dimensions(&self) -> (u32, u32) {
+        match self {
+            // PNG and BMP store signed 32-bit dimensions
+            ExportFormat::Png | ExportFormat::Bmp => (i32::MAX as u32, i32::MAX as u32),
+            ExportFormat::Jpeg => (65500, 65500),
+            ExportFormat::Tiff => (u32::MAX, u32::MAX),
+            ExportFormat::WebP => (16383, 16383),
+            ExportFormat::Avif => (65535, 65535),
+            // Icons are sized by the sizes they hold instead
+            ExportFormat::Pdf | ExportFormat::Svg | ExportFormat::Ico | ExportFormat::Icns => {
+                (u32::MAX, u32::MAX)
+            }
+        }
+    }
+
+    /// Infer the format from a file extension (case-insensitive).
+    pub fn from_extension(ext: &str) -> Option<ExportFormat> {
+        match ext.to_lowercase().as_str() {
+            "png" => Some(ExportFormat::Png),
+            "jpg" | "jpeg" => Some(ExportFormat::Jpeg),
+            "bmp" => Some(ExportFormat::Bmp),
+            "tif" | "tiff" => Some(ExportFormat::Tiff),
+            "webp" => Some(ExportFormat::WebP),
+            "avif" => Some(ExportFormat::Avif),
+            "pdf" => Some(ExportFormat::Pdf),
+            "svg" | "svgz" => Some(ExportFormat::Svg),
+            "ico" => Some(ExportFormat::Ico),
+            "icns" => Some(ExportFormat::Icns),
+            _ => None,
+        }
+    }
+
+    /// Infer the format from the extension of an output path.
+    pub fn from_path(path: &Path) -> Option<ExportFormat> {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(Self::from_extension)
+    }
+}
+
+/// How a rotated or mirrored view reaches a JPEG or TIFF export.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OrientationMode {
+    /// Rotate and mirror the pixels themselves
+    #[default]
+    Bake,
+    /// Store the image unrotated with an Orientation tag saying how to show it
+    Metadata,
+}
+
+impl OrientationMode {
+    pub fn all() -> &'static [OrientationMode] {
+        &[OrientationMode::Bake, OrientationMode::Metadata]
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            OrientationMode::Bake => "Bake rotation into pixels",
+            OrientationMode::Metadata => "Store orientation as metadata",
+        }
+    }
+}
+
+/// Room left around the drawing, which is shrunk to fit inside it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Padding {
+    /// Pixels on every side
+    Pixels(f32),
+    /// Percent of the shorter side, on every side
+    Percent(f32),
+}
+
+impl Default for Padding {
+    fn default() -> Self {
+        Padding::Pixels(0.0)
+    }
+}
+
+impl Padding {
+    pub fn amount(&self) -> f32 {
+        match *self {
+            Padding::Pixels(amount) | Padding::Percent(amount) => amount,
+        }
+    }
+
+    /// The margin in pixels around a `width`×`height` export. Padding can't
+    /// be negative, nor leave nothing of the export to draw in.
+    pub fn margin(&self, width: u32, height: u32) -> Result<f32> {
+        let shorter = width.min(height) as f32;
+        let margin = match *self {
+            Padding::Pixels(pixels) => pixels,
+            Padding::Percent(percent) => percent / 100.0 * shorter,
+        };
+        if !(self.amount() >= 0.0 && margin.is_finite()) {
+            return Err(SvgError::Export(format!(
+                "padding can't be {}",
+                self.amount()
+            )));
+        }
+        if margin * 2.0 >= shorter {
+            return Err(SvgError::Export(format!(
+                "{margin:.0} px of padding leaves no room in a {width}×{height} export"
+            )));
+        }
+        Ok(margin)
+    }
+}
+
+/// The EXIF Orientation value (1–8) that makes an unrotated image display the
+/// way `viewport` shows it, or `None` when the rotation isn't a quarter turn.
+pub fn exif_orientation(viewport: &Viewport) -> Option<u16> {
+    if viewport.rotation_deg % 90.0 != 0.0 {
+        return None;
+    }
+    // A vertical mirror is a horizontal one turned half way round
+    let flipped = viewport.mirror_h != viewport.mirror_v;
+    let half_turn = if viewport.mirror_v { 180.0 } else { 0.0 };
+    let quarter_turns = ((viewport.rotation_deg + half_turn) / 90.0).rem_euclid(4.0) as u8;
+    Some(match (flipped, quarter_turns) {
+        (false, 0) => 1,
+        (false, 1) => 6,
+        (false, 2) => 3,
+        (false, _) => 8,
+        (true, 0) => 2,
+        (true, 1) => 7,
+        (true, 2) => 4,
+        (true, _) => 5,
+    })
+}
+
+/// How an export will record the view's orientation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OrientationPlan {
+    /// Rotation and mirroring are in the pixels. `notice` explains why when
+    /// metadata was asked for.
+    Bake { notice: Option<String> },
+    /// The image is written unrotated with this Orientation value.
+    Tag(u16),
+}
+
+pub fn plan_orientation(settings: &ExportSettings, viewport: &Viewport) -> OrientationPlan {
+    if settings.orientation != OrientationMode::Metadata {
+        return OrientationPlan::Bake { notice: None };
+    }
+    if !settings.format.supports_orientation_tag() {
+        return OrientationPlan::Bake {
+            notice: Some(format!(
+                "{} files can't store an orientation, so the rotation is in the pixels",
+                settings.format.name()
+            )),
+        };
+    }
+    match exif_orientation(viewport) {
+        Some(orientation) => OrientationPlan::Tag(orientation),
+        None => OrientationPlan::Bake {
+            notice: Some(format!(
+                "EXIF can't store a {}° rotation, so it is in the pixels",
+                viewport.rotation_deg
+            )),
+        },
+    }
+}
+
+/// What an export records about where it came from, when
+/// [`ExportSettings::metadata`] is on.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImageMetadata {
+    /// The document's `<title>`
+    pub title: Option<String>,
+    /// The file name of the SVG it was rendered from
+    pub source: Option<String>,
+    /// Seconds since 1970
+    pub created: Option<u64>,
+}
+
+/// The program named as the software that wrote an export.
+pub const SOFTWARE: &str = concat!("svg-viewer ", env!("CARGO_PKG_VERSION"));
+
+impl ImageMetadata {
+    /// Metadata for an export of `doc` made now, or `None` when `settings`
+    /// leave it out.
+    pub fn for_export(doc: &SvgDocument, settings: &ExportSettings) -> Option<Self> {
+        if !settings.metadata {
+            return None;
+        }
+        let source = (!doc.is_in_memory() && !doc.is_remote())
+            .then(|| doc.path().file_name())
+            .flatten()
+            .map(|name| name.to_string_lossy().into_owned());
+        Some(Self {
+            title: doc.metadata().title.clone(),
+            source,
+            created: Some(timestamp::now()),
+        })
+    }
+
+    /// Keyword and text of each PNG text chunk.
+    fn png_text(&self) -> Vec<(&'static str, String)> {
+        let mut entries = Vec::new();
+        if let Some(title) = &self.title {
+            entries.push(("Title", title.clone()));
+        }
+        entries.push(("Software", SOFTWARE.to_string()));
+        if let Some(source) = &self.source {
+            entries.push(("Source", source.clone()));
+        }
+        if let Some(created) = self.created {
+            entries.push((
+                "Creation Time",
+                format!("{}Z", timestamp::format_iso(created)),
+            ));
+        }
+        entries
+    }
+}
+
+/// A little-endian EXIF (TIFF-structured) block with one IFD holding
+/// `orientation` and what `metadata` records, or `None` if there's neither.
+fn exif(orientation: Option<u16>, metadata: Option<&ImageMetadata>) -> Option<Vec<u8>> {
+    enum Value {
+        Short(u16),
+        Ascii(String),
+    }
+    // In tag order, as TIFF requires
+    let mut entries = Vec::new();
+    if let Some(metadata) = metadata {
+        if let Some(source) = &metadata.source {
+            entries.push((0x010Du16, Value::Ascii(source.clone()))); // DocumentName
+        }
+        if let Some(title) = &metadata.title {
+            entries.push((0x010E, Value::Ascii(title.clone()))); // ImageDescription
+        }
+    }
+    if let Some(orientation) = orientation {
+        entries.push((0x0112, Value::Short(orientation))); // Orientation
+    }
+    if let Some(metadata) = metadata {
+        entries.push((0x0131, Value::Ascii(SOFTWARE.to_string()))); // Software
+        if let Some(created) = metadata.created {
+            entries.push((0x0132, Value::Ascii(timestamp::format_exif(created))));
+            // DateTime
+        }
+    }
+    if entries.is_empty() {
+        return None;
+    }
+
+    // Values over four bytes go after the IFD, at word-aligned offsets
+    let mut offset = 8 + 2 + entries.len() * 12 + 4;
+    let mut exif = b"II*\0".to_vec();
+    exif.extend_from_slice(&8u32.to_le_bytes()); // first IFD offset
+    exif.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    let mut values = Vec::new();
+    for (tag, value) in entries {
+        exif.extend_from_slice(&tag.to_le_bytes());
+        match value {
+            Value::Short(value) => {
+                exif.extend_from_slice(&3u16.to_le_bytes()); // SHORT
+                exif.extend_from_slice(&1u32.to_le_bytes());
+                exif.extend_from_slice(&value.to_le_bytes());
+                exif.extend_from_slice(&[0, 0]);
+            }
+            Value::Ascii(text) => {
+                let mut bytes = text.into_bytes();
+                bytes.push(0);
+                exif.extend_from_slice(&2u16.to_le_bytes()); // ASCII
+                exif.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+                if bytes.len() <= 4 {
+                    bytes.resize(4, 0);
+                    exif.extend_from_slice(&bytes);
+                } else {
+                    exif.extend_from_slice(&(offset as u32).to_le_bytes());
+                    bytes.resize(bytes.len().next_multiple_of(2), 0);
+                    offset += bytes.len();
+                    values.extend_from_slice(&bytes);
+                }
+            }
+        }
+    }
+    exif.extend_from_slice(&0u32.to_le_bytes()); // no next IFD
+    exif.extend_from_slice(&values);
+    Some(exif)
+}
+
+/// A sub-rectangle of the document in SVG units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExportRegion {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl ExportRegion {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Result<Self> {
+        let region = Self {
+            x,
+            y,
+            width,
+            height,
+        };
+        region.validate()?;
+        Ok(region)
+    }
+
+    /// Reject regions that would produce an empty or undefined render.
+    pub fn validate(&self) -> Result<()> {
+        let values = [self.x, self.y, self.width, self.height];
+        if values.iter().any(|v| !v.is_finite()) {
+            return Err(SvgError::InvalidRegion(format!(
+                "{self} contains a non-finite value"
+            )));
+        }
+        if self.width <= 0.0 || self.height <= 0.0 {
+            return Err(SvgError::InvalidRegion(format!(
+                "{self} has zero area (width and height must be > 0)"
+            )));
+        }
+        Ok(())
+    }
+
+    /// The smallest region containing `points`, such as the corners of a
+    /// rectangle dragged on the canvas. Fewer than two distinct points make an
+    /// empty region, which is rejected.
+    pub fn from_corners(points: &[[f32; 2]]) -> Result<Self> {
+        let (mut min, mut max) = ([f32::INFINITY; 2], [f32::NEG_INFINITY; 2]);
+        for point in points {
+            for axis in 0..2 {
+                min[axis] = min[axis].min(point[axis]);
+                max[axis] = max[axis].max(point[axis]);
+            }
+        }
+        if points.is_empty() {
+            (min, max) = ([0.0; 2], [0.0; 2]);
+        }
+        Self::new(min[0], min[1], max[0] - min[0], max[1] - min[1])
+    }
+
+    /// The part of the region inside a `width`×`height` document, or an
+    /// error when none of it is.
+    pub fn clipped_to(&self, width: f32, height: f32) -> Result<Self> {
+        let (left, top) = (self.x.max(0.0), self.y.max(0.0));
+        let right = (self.x + self.width).min(width);
+        let bottom = (self.y + self.height).min(height);
+        if right <= left || bottom <= top {
+            return Err(SvgError::InvalidRegion(format!(
+                "{self} lies outside the document (0,0,{width},{height})"
+            )));
+        }
+        Self::new(left, top, right - left, bottom - top)
+    }
+
+    /// Grow the region by `padding` SVG units on every side.
+    pub fn padded(&self, padding: f32) -> Result<Self> {
+        Self::new(
+            self.x - padding,
+            self.y - padding,
+            self.width + padding * 2.0,
+            self.height + padding * 2.0,
+        )
+    }
+
+    /// Output pixel size for this region. Explicit dimensions win; a single
+    /// dimension keeps the region's aspect ratio; otherwise `scale` applies.
+    pub fn output_size(&self, width: Option<u32>, height: Option<u32>, scale: f32) -> (u32, u32) {
+        output_size(self.width, self.height, width, height, scale)
+    }
+
+    /// Transform mapping the region onto a `render_width`×`render_height` pixmap,
+    /// scaled uniformly and centered.
+    pub fn build_transform(&self, render_width: f32, render_height: f32) -> tiny_skia::Transform {
+        let scale = (render_width / self.width).min(render_height / self.height);
+        let tx = (render_width - self.width * scale) / 2.0 - self.x * scale;
+        let ty = (render_height - self.height * scale) / 2.0 - self.y * scale;
+        tiny_skia::Transform::from_row(scale, 0.0, 0.0, scale, tx, ty)
+    }
+}
+
+impl std::fmt::Display for ExportRegion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+impl FromStr for ExportRegion {
+    type Err = SvgError;
+
+    /// Parse `X,Y,W,H`.
+    fn from_str(s: &str) -> Result<Self> {
+        let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+        if parts.len() != 4 {
+            return Err(SvgError::InvalidRegion(format!(
+                "expected X,Y,W,H but got \"{s}\""
+            )));
         }
+        let mut values = [0.0f32; 4];
+        for (value, part) in values.iter_mut().zip(&parts) {
+            *value = part.parse().map_err(|_| {
+                SvgError::InvalidRegion(format!("\"{part}\" is not a number in \"{s}\""))
+            })?;
+        }
+        Self::new(values[0], values[1], values[2], values[3])
+    }
+}
+
+/// Where repeating an export of `last_source` to `last_output` writes for
+/// `source`: the same directory and extension, with `last_source`'s file stem in
+/// the output name swapped for `source`'s (so `logo@2x.png` becomes `icon@2x.png`).
+pub fn repeat_export_path(last_output: &Path, last_source: &Path, source: &Path) -> PathBuf {
+    if source == last_source {
+        return last_output.to_path_buf();
+    }
+    let stem = |p: &Path| {
+        p.file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    };
+    let (old, new, output) = (stem(last_source), stem(source), stem(last_output));
+    let mut name = if !old.is_empty() && output.contains(&old) {
+        output.replacen(&old, &new, 1)
+    } else {
+        new
+    };
+    if let Some(ext) = last_output.extension() {
+        name.push('.');
+        name.push_str(&ext.to_string_lossy());
+    }
+    last_output.with_file_name(name)
+}
+
+/// Scales offered for exporting one document at several sizes at once.
+pub const MULTI_SCALES: [f32; 5] = [1.0, 1.5, 2.0, 3.0, 4.0];
+
+/// `path` with `@<scale>x` before its extension, as scaled mobile assets are
+/// named (`logo@2x.png`); at 1× it's left alone.
+pub fn scaled_path(path: &Path, scale: f32) -> PathBuf {
+    if scale == 1.0 {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{stem}@{scale}x");
+    if let Some(ext) = path.extension() {
+        name.push('.');
+        name.push_str(&ext.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
+
+/// `settings` for an export `scale` times the size they give, with the
+/// resolution and any padding in pixels scaled along.
+pub fn scaled_settings(settings: &ExportSettings, scale: f32) -> ExportSettings {
+    let (width, height) = fit_dimensions(
+        settings.width as f32 * scale,
+        settings.height as f32 * scale,
+        MAX_EXPORT_DIM,
+    );
+    let padding = match settings.padding {
+        Padding::Pixels(pixels) => Padding::Pixels(pixels * scale),
+        percent => percent,
+    };
+    ExportSettings {
+        width,
+        height,
+        dpi: settings.dpi.map(|dpi| dpi * scale),
+        padding,
+        ..settings.clone()
+    }
+}
+
+/// The file name an export of `stem` is offered as: `<stem>_<w>x<h>.<ext>`
+/// when it has a pixel size, numbered from `_2` while that name is taken in
+/// `dir`.
+pub fn default_export_name(
+    dir: Option<&Path>,
+    stem: &str,
+    settings: &ExportSettings,
+    size: Option<(u32, u32)>,
+) -> String {
+    let base = match size {
+        Some((width, height)) => format!("{stem}_{width}x{height}"),
+        None => stem.to_string(),
+    };
+    let extension = settings.extension();
+    (1..)
+        .map(|n| match n {
+            1 => format!("{base}.{extension}"),
+            n => format!("{base}_{n}.{extension}"),
+        })
+        .find(|name| dir.is_none_or(|dir| !dir.join(name).exists()))
+        .unwrap_or_default()
+}
+
+/// `path`, with the export's extension added when it was given none.
+pub fn with_default_extension(path: PathBuf, settings: &ExportSettings) -> PathBuf {
+    if path.extension().is_some() {
+        path
+    } else {
+        path.with_extension(settings.extension())
+    }
+}
+
+/// Output pixel size for content of `src_w`×`src_h` units.
+pub fn output_size(
+    src_w: f32,
+    src_h: f32,
+    width: Option<u32>,
+    height: Option<u32>,
+    scale: f32,
+) -> (u32, u32) {
+    let (w, h) = match (width, height) {
+        (Some(w), Some(h)) => (w, h),
+        (Some(w), None) => (w, (w as f32 * src_h / src_w).round() as u32),
+        (None, Some(h)) => ((h as f32 * src_w / src_h).round() as u32, h),
+        (None, None) => (
+            (src_w * scale).round() as u32,
+            (src_h * scale).round() as u32,
+        ),
+    };
+    (w.max(1), h.max(1))
+}
+
+/// Largest width or height of an export. Past [`MAX_RENDER_DIM`] it's
+/// rendered in tiles.
+pub const MAX_EXPORT_DIM: u32 = 16384;
+
+/// Pixel size for a `width`×`height` document, scaled down uniformly when either
+/// side exceeds `max`. The minor side never drops below 1 pixel.
+pub fn fit_dimensions(width: f32, height: f32, max: u32) -> (u32, u32) {
+    let width = if width.is_finite() && width > 0.0 {
+        width
+    } else {
+        1.0
+    };
+    let height = if height.is_finite() && height > 0.0 {
+        height
+    } else {
+        1.0
+    };
+    let scale = (max as f32 / width.max(height)).min(1.0);
+    let fit = |v: f32| ((v * scale).round() as u32).clamp(1, max);
+    (fit(width), fit(height))
+}
+
+/// The size an export will actually be written at, compared with what was asked for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SizePlan {
+    pub requested: (u32, u32),
+    pub actual: (u32, u32),
+    /// Why `actual` differs from `requested`; empty when they match.
+    pub reasons: Vec<String>,
+}
+
+impl SizePlan {
+    pub fn is_reduced(&self) -> bool {
+        self.requested != self.actual
+    }
+}
+
+/// Work out the output size for `width`×`height` in `format`. When the render or
+/// format limits are exceeded, the image is scaled down uniformly to fit them.
+pub fn plan_size(format: &ExportFormat, width: u32, height: u32) -> SizePlan {
+    let requested = (width.max(1), height.max(1));
+    let (format_w, format_h) = format.max_dimensions();
+    let mut reasons = Vec::new();
+
+    if requested.0 > MAX_EXPORT_DIM || requested.1 > MAX_EXPORT_DIM {
+        reasons.push(format!(
+            "exports are limited to {MAX_EXPORT_DIM}×{MAX_EXPORT_DIM} px"
+        ));
+    }
+    if requested.0 > format_w || requested.1 > format_h {
+        reasons.push(format!(
+            "{} images are limited to {}×{} px",
+            format.name(),
+            format_w,
+            format_h
+        ));
+    }
+
+    let limit_w = format_w.min(MAX_EXPORT_DIM);
+    let limit_h = format_h.min(MAX_EXPORT_DIM);
+    let scale = (limit_w as f64 / requested.0 as f64)
+        .min(limit_h as f64 / requested.1 as f64)
+        .min(1.0);
+    let actual = (
+        ((requested.0 as f64 * scale).round() as u32).clamp(1, limit_w),
+        ((requested.1 as f64 * scale).round() as u32).clamp(1, limit_h),
+    );
+
+    SizePlan {
+        requested,
+        actual,
+        reasons,
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct ExportSettings {
     pub format: ExportFormat,
     pub width: u32,
     pub height: u32,
     pub include_alpha: bool,
     pub jpeg_quality: u8,
+    /// From 1 to 100, as for JPEG
+    pub avif_quality: u8,
+    pub jpeg_subsampling: ChromaSubsampling,
+    pub jpeg_progressive: bool,
+    /// Write a PNG at 16 bits a channel
+    pub png_16bit: bool,
     pub background_color: [u8; 3],
+    /// Only honored by formats that support an Orientation tag
+    pub orientation: OrientationMode,
+    /// Applied to the render before it's saved, whatever the canvas shows
+    pub effects: PreviewEffects,
+    /// Keep the view's stretch to the window; otherwise the document keeps
+    /// its own proportions
+    pub stretch: bool,
+    /// Turn and mirror the export as the view is; otherwise it's upright
+    pub view_orientation: bool,
+    /// Draw a PDF page from the document's vectors, the size the document
+    /// prints at; otherwise it holds a render at `pdf_dpi`
+    pub pdf_vector: bool,
+    /// Pixels to the inch of a rendered PDF page, which sets the page's size
+    pub pdf_dpi: f32,
+    /// Write an SVG export's own markup instead of the simplified tree
+    pub svg_original: bool,
+    /// Compress an SVG export as SVGZ
+    pub svgz: bool,
+    /// Pixels to the inch, when sized by resolution rather than in pixels;
+    /// recorded in PNG and JPEG files
+    pub dpi: Option<f32>,
+    /// Sides of the square images in an icon export; those the format can't
+    /// hold are left out
+    pub icon_sizes: Vec<u32>,
+    /// Room around the drawing, which is shrunk to fit the size inside it
+    pub padding: Padding,
+    /// Record the title, software, source file and time in PNG, JPEG and
+    /// WebP files; without it the same export gives the same bytes
+    pub metadata: bool,
+}
+
+impl ExportSettings {
+    /// Whether the export is a PDF drawn from the vectors, which isn't
+    /// rendered and so has no pixel size, padding or effects.
+    pub fn is_vector_pdf(&self) -> bool {
+        self.format == ExportFormat::Pdf && self.pdf_vector
+    }
+
+    /// The output file's extension, which for SVG follows `svgz`.
+    pub fn extension(&self) -> &str {
+        match self.format {
+            ExportFormat::Svg if self.svgz => "svgz",
+            ref format => format.extension(),
+        }
+    }
 }
 
 impl Default for ExportSettings {
@@ -72,13 +822,29 @@ impl Default for ExportSettings {
             height: 600,
             include_alpha: true,
             jpeg_quality: 90,
+            avif_quality: 80,
+            jpeg_subsampling: ChromaSubsampling::default(),
+            jpeg_progressive: false,
+            png_16bit: false,
             background_color: [255, 255, 255],
+            orientation: OrientationMode::Bake,
+            effects: PreviewEffects::default(),
+            stretch: false,
+            view_orientation: true,
+            pdf_vector: true,
+            pdf_dpi: 150.0,
+            svg_original: false,
+            svgz: false,
+            dpi: None,
+            icon_sizes: icon::DEFAULT_ICON_SIZES.to_vec(),
+            padding: Padding::default(),
+            metadata: true,
         }
     }
 }
 
 /// Un-premultiply alpha from premultiplied RGBA pixel data.
-fn un_premultiply_alpha(data: &[u8]) -> Vec<u8> {
+pub(crate) fn un_premultiply_alpha(data: &[u8]) -> Vec<u8> {
     let mut result = Vec::with_capacity(data.len());
     for chunk in data.chunks_exact(4) {
         let a = chunk[3] as f32 / 255.0;
@@ -116,54 +882,425 @@ fn composite_over_background(data: &[u8], bg: [u8; 3]) -> Vec<u8> {
     result
 }
 
+/// The view a whole-document export follows: `viewport`'s rotation and
+/// mirroring unless `settings` leave them out, and its stretch only when
+/// they keep it.
+pub fn export_view(settings: &ExportSettings, viewport: &Viewport) -> Viewport {
+    let mut view = viewport.clone();
+    if !settings.stretch {
+        view.stretch_x = 1.0;
+    }
+    if !settings.view_orientation {
+        view.rotation_deg = 0.0;
+        view.mirror_h = false;
+        view.mirror_v = false;
+    }
+    view
+}
+
 pub fn export_svg(
     doc: &SvgDocument,
     viewport: &Viewport,
     settings: &ExportSettings,
     output_path: &Path,
 ) -> Result<()> {
-    let pixmap = Renderer::render_for_export(doc, settings.width, settings.height, viewport)?;
-    save_pixmap(&pixmap, settings, output_path)
+    if settings.format == ExportFormat::Svg {
+        return write_svg(doc, settings, output_path);
+    }
+    if settings.is_vector_pdf() {
+        let (width, height) = (doc.width(), doc.height());
+        return write_vector_pdf(doc, (0.0, 0.0, width, height), settings, output_path);
+    }
+    check_encoder(settings)?;
+    let viewport = &export_view(settings, viewport);
+    if settings.format.is_icon() {
+        return write_icon(settings, output_path, |size| {
+            let margin = settings.padding.margin(size, size)?;
+            renderer::render_padded_for_export(doc, size, size, viewport, margin)
+        });
+    }
+    let (width, height) = plan_size(&settings.format, settings.width, settings.height).actual;
+    let margin = settings.padding.margin(width, height)?;
+    let metadata = ImageMetadata::for_export(doc, settings);
+    match plan_orientation(settings, viewport) {
+        OrientationPlan::Bake { .. } => {
+            let transform = renderer::padded_transform(
+                viewport.build_transform(doc.width(), doc.height(), width as f32, height as f32),
+                width,
+                height,
+                margin,
+            );
+            render_and_save(
+                doc,
+                (width, height),
+                transform,
+                settings,
+                metadata.as_ref(),
+                output_path,
+            )
+        }
+        OrientationPlan::Tag(orientation) => {
+            let upright = Viewport {
+                rotation_deg: 0.0,
+                mirror_h: false,
+                mirror_v: false,
+                ..viewport.clone()
+            };
+            // Orientations 5–8 turn the stored image a quarter turn
+            let (width, height) = if orientation >= 5 {
+                (height, width)
+            } else {
+                (width, height)
+            };
+            let pixmap = renderer::render_padded_for_export(doc, width, height, &upright, margin)?;
+            save_tagged_pixmap(
+                &pixmap,
+                settings,
+                metadata.as_ref(),
+                output_path,
+                Some(orientation),
+            )
+        }
+    }
+}
+
+/// Write the document as SVG, whatever the view: the tree it was parsed
+/// into, with `<use>` expanded, styles resolved and text turned into paths so
+/// it stands alone, or with `svg_original` its own markup. It's compressed
+/// when `svgz` is set or the output is named `.svgz`.
+pub fn write_svg(doc: &SvgDocument, settings: &ExportSettings, output_path: &Path) -> Result<()> {
+    use std::io::Write;
+
+    let text = if settings.svg_original {
+        svg_document::svg_text(doc.raw_data())?
+    } else {
+        doc.tree().to_string(&usvg::WriteOptions::default())
+    };
+    let named_svgz = output_path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svgz"));
+    if settings.svgz || named_svgz {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(text.as_bytes())?;
+        std::fs::write(output_path, encoder.finish()?)?;
+    } else {
+        std::fs::write(output_path, text)?;
+    }
+    Ok(())
+}
+
+/// Write the document's `(x, y, width, height)` area as a vector PDF page,
+/// the size it prints at. Like an SVG export it's the document as written,
+/// whatever the view.
+fn write_vector_pdf(
+    doc: &SvgDocument,
+    (x, y, width, height): (f32, f32, f32, f32),
+    settings: &ExportSettings,
+    output_path: &Path,
+) -> Result<()> {
+    let area = tiny_skia::Rect::from_xywh(x, y, width, height)
+        .ok_or_else(|| SvgError::Export(format!("a PDF page can't be {width}×{height} units")))?;
+    let points_per_unit = inches_per_unit(doc) * pdf::POINTS_PER_INCH;
+    let background = (!settings.include_alpha).then_some(settings.background_color);
+    pdf::write_vector_pdf(doc.tree(), area, points_per_unit, background, output_path)
+}
+
+/// Export only `region` of the document at the size given in `settings`.
+pub fn export_region(
+    doc: &SvgDocument,
+    region: &ExportRegion,
+    settings: &ExportSettings,
+    output_path: &Path,
+) -> Result<()> {
+    if settings.format == ExportFormat::Svg {
+        return Err(SvgError::Export(
+            "SVG exports are the whole document; a region can only be rendered".into(),
+        ));
+    }
+    if settings.is_vector_pdf() {
+        let area = (region.x, region.y, region.width, region.height);
+        return write_vector_pdf(doc, area, settings, output_path);
+    }
+    check_encoder(settings)?;
+    if settings.format.is_icon() {
+        return write_icon(settings, output_path, |size| {
+            let margin = settings.padding.margin(size, size)?;
+            renderer::render_region_for_export(doc, region, size, size, margin)
+        });
+    }
+    let (width, height) = plan_size(&settings.format, settings.width, settings.height).actual;
+    let margin = settings.padding.margin(width, height)?;
+    let metadata = ImageMetadata::for_export(doc, settings);
+    render_and_save(
+        doc,
+        (width, height),
+        renderer::padded_transform(
+            region.build_transform(width as f32, height as f32),
+            width,
+            height,
+            margin,
+        ),
+        settings,
+        metadata.as_ref(),
+        output_path,
+    )
+}
+
+/// Whether a `width`×`height` export is written a band of rows at a time as
+/// it's rendered, rather than rendered whole first. Only PNG is streamed,
+/// and only when it's too big for one render.
+fn is_streamed(format: &ExportFormat, width: u32, height: u32) -> bool {
+    *format == ExportFormat::Png && (width > MAX_RENDER_DIM || height > MAX_RENDER_DIM)
+}
+
+/// Bytes a `width`×`height` export in `format` is expected to hold at once:
+/// the render and the copy converted for the encoder, or a band of each when
+/// it's streamed.
+pub fn estimated_memory(format: &ExportFormat, width: u32, height: u32) -> u64 {
+    let rows = if is_streamed(format, width, height) {
+        height.min(EXPORT_BAND_ROWS)
+    } else {
+        height
+    };
+    width as u64 * rows as u64 * 8
+}
+
+/// Render the `width`×`height` image `transform` maps `doc` into and save it,
+/// streamed when [`is_streamed`] and otherwise stitched from tiles first.
+fn render_and_save(
+    doc: &SvgDocument,
+    (width, height): (u32, u32),
+    transform: tiny_skia::Transform,
+    settings: &ExportSettings,
+    metadata: Option<&ImageMetadata>,
+    output_path: &Path,
+) -> Result<()> {
+    if !is_streamed(&settings.format, width, height) {
+        let pixmap = renderer::render_tiled(doc, width, height, transform)?;
+        return save_pixmap(&pixmap, settings, metadata, output_path);
+    }
+    let pipeline = PostPipeline::for_export(settings.effects);
+    let bands = (0..height).step_by(EXPORT_BAND_ROWS as usize).map(|top| {
+        let rows = (height - top).min(EXPORT_BAND_ROWS);
+        let mut band = renderer::render_band(doc, width, top, rows, transform)?;
+        pipeline.run(&mut band);
+        Ok(png_rows(settings, band.data()))
+    });
+    write_png(settings, metadata, (width, height), output_path, bands)
+}
+
+/// Write an icon holding `render(size)`, a square with the document
+/// centered in it, for each of the chosen sizes the format can hold.
+fn write_icon(
+    settings: &ExportSettings,
+    output_path: &Path,
+    render: impl Fn(u32) -> Result<Pixmap>,
+) -> Result<()> {
+    let offered = settings.format.icon_sizes();
+    let mut sizes: Vec<u32> = settings
+        .icon_sizes
+        .iter()
+        .copied()
+        .filter(|size| offered.contains(size))
+        .collect();
+    sizes.sort_unstable();
+    sizes.dedup();
+    if sizes.is_empty() {
+        return Err(SvgError::Export(format!(
+            "choose at least one {} icon size",
+            settings.format.name()
+        )));
+    }
+    let pipeline = PostPipeline::for_export(settings.effects);
+    let mut images = Vec::with_capacity(sizes.len());
+    for size in sizes {
+        let mut pixmap = render(size)?;
+        pipeline.run(&mut pixmap);
+        let rgba = if settings.include_alpha {
+            un_premultiply_alpha(pixmap.data())
+        } else {
+            composite_over_background(pixmap.data(), settings.background_color)
+                .chunks_exact(3)
+                .flat_map(|px| [px[0], px[1], px[2], 255])
+                .collect()
+        };
+        images.push(icon::IconImage { size, rgba });
+    }
+    match settings.format {
+        ExportFormat::Icns => icon::write_icns(&images, output_path),
+        _ => icon::write_ico(&images, output_path),
+    }
+}
+
+/// Save `pixmap`, recording `metadata` where the format has room for it.
+pub fn save_pixmap(
+    pixmap: &Pixmap,
+    settings: &ExportSettings,
+    metadata: Option<&ImageMetadata>,
+    output_path: &Path,
+) -> Result<()> {
+    save_tagged_pixmap(pixmap, settings, metadata, output_path, None)
 }
 
-pub fn save_pixmap(pixmap: &Pixmap, settings: &ExportSettings, output_path: &Path) -> Result<()> {
+/// Save `pixmap`, recording `orientation` as an Orientation tag. Only JPEG and
+/// TIFF get one; other formats ignore it. `metadata` goes in PNG text chunks
+/// and JPEG and WebP EXIF.
+fn save_tagged_pixmap(
+    pixmap: &Pixmap,
+    settings: &ExportSettings,
+    metadata: Option<&ImageMetadata>,
+    output_path: &Path,
+    orientation: Option<u16>,
+) -> Result<()> {
+    check_encoder(settings)?;
+    let pipeline = PostPipeline::for_export(settings.effects);
+    let processed;
+    let pixmap = if pipeline.is_empty() {
+        pixmap
+    } else {
+        let mut copy = pixmap.clone();
+        pipeline.run(&mut copy);
+        processed = copy;
+        &processed
+    };
     let width = pixmap.width();
     let height = pixmap.height();
     let data = pixmap.data();
 
     match settings.format {
-        ExportFormat::Png if settings.include_alpha => {
-            let rgba = un_premultiply_alpha(data);
-            let img = image::RgbaImage::from_raw(width, height, rgba)
-                .ok_or_else(|| SvgError::Export("Failed to create RGBA image".into()))?;
-            img.save(output_path)
+        ExportFormat::Svg => {
+            return Err(SvgError::Export(
+                "an SVG can't be written from a render".into(),
+            ));
+        }
+        ExportFormat::Ico | ExportFormat::Icns => {
+            return Err(SvgError::Export(
+                "an icon is rendered at each of its sizes, not from one render".into(),
+            ));
+        }
+        ExportFormat::Pdf => {
+            let page = |pixels: u32| pixels as f32 * pdf::POINTS_PER_INCH / settings.pdf_dpi;
+            let (rgb, alpha) = if settings.include_alpha {
+                let rgba = un_premultiply_alpha(data);
+                let rgb = rgba.chunks_exact(4).flat_map(|px| &px[..3]).copied();
+                let alpha: Vec<u8> = rgba.chunks_exact(4).map(|px| px[3]).collect();
+                (rgb.collect(), Some(alpha))
+            } else {
+                (
+                    composite_over_background(data, settings.background_color),
+                    None,
+                )
+            };
+            pdf::write_image_pdf(
+                &rgb,
+                alpha.as_deref(),
+                (width, height),
+                (page(width), page(height)),
+                output_path,
+            )?;
+        }
+        ExportFormat::Png => {
+            write_png(
+                settings,
+                metadata,
+                (width, height),
+                output_path,
+                [Ok(png_rows(settings, data))],
+            )?;
+        }
+        ExportFormat::Tiff if orientation.is_some() => {
+            let file = std::fs::File::create(output_path)?;
+            let mut encoder = tiff::encoder::TiffEncoder::new(std::io::BufWriter::new(file))
                 .map_err(|e| SvgError::Export(e.to_string()))?;
+            let tag = orientation.unwrap_or(1);
+            let result = if settings.include_alpha {
+                let rgba = un_premultiply_alpha(data);
+                write_tagged_tiff::<tiff::encoder::colortype::RGBA8>(
+                    &mut encoder,
+                    width,
+                    height,
+                    &rgba,
+                    tag,
+                )
+            } else {
+                let rgb = composite_over_background(data, settings.background_color);
+                write_tagged_tiff::<tiff::encoder::colortype::RGB8>(
+                    &mut encoder,
+                    width,
+                    height,
+                    &rgb,
+                    tag,
+                )
+            };
+            result.map_err(|e| SvgError::Export(e.to_string()))?;
         }
         ExportFormat::Tiff if settings.include_alpha => {
             let rgba = un_premultiply_alpha(data);
             let img = image::RgbaImage::from_raw(width, height, rgba)
                 .ok_or_else(|| SvgError::Export("Failed to create RGBA image".into()))?;
-            img.save(output_path)
+            img.save_with_format(output_path, settings.format.image_format())
                 .map_err(|e| SvgError::Export(e.to_string()))?;
         }
-        ExportFormat::WebP if settings.include_alpha => {
-            let rgba = un_premultiply_alpha(data);
-            let img = image::RgbaImage::from_raw(width, height, rgba)
-                .ok_or_else(|| SvgError::Export("Failed to create RGBA image".into()))?;
-            img.save(output_path)
+        ExportFormat::WebP => {
+            let (pixels, color) = unassociated(settings, data);
+            let file = std::fs::File::create(output_path)?;
+            let mut encoder =
+                image::codecs::webp::WebPEncoder::new_lossless(std::io::BufWriter::new(file));
+            if let Some(exif) = exif(None, metadata) {
+                image::ImageEncoder::set_exif_metadata(&mut encoder, exif)
+                    .map_err(|e| SvgError::Export(e.to_string()))?;
+            }
+            image::ImageEncoder::write_image(encoder, &pixels, width, height, color)
                 .map_err(|e| SvgError::Export(e.to_string()))?;
         }
-        ExportFormat::Jpeg => {
-            let rgb = composite_over_background(data, settings.background_color);
-            let img = image::RgbImage::from_raw(width, height, rgb)
-                .ok_or_else(|| SvgError::Export("Failed to create RGB image".into()))?;
-            // For quality control, use the jpeg encoder directly
+        ExportFormat::Avif => {
+            let (pixels, color) = unassociated(settings, data);
+            let file = std::fs::File::create(output_path)?;
+            // The encoder's default speed
+            let encoder = image::codecs::avif::AvifEncoder::new_with_speed_quality(
+                std::io::BufWriter::new(file),
+                4,
+                settings.avif_quality.clamp(1, 100),
+            );
+            image::ImageEncoder::write_image(encoder, &pixels, width, height, color)
+                .map_err(|e| SvgError::Export(e.to_string()))?;
+        }
+        ExportFormat::Jpeg
+            if settings.jpeg_subsampling != ChromaSubsampling::Full
+                || settings.jpeg_progressive =>
+        {
+            // Options the image crate's encoder doesn't have
+            let options = jpeg::JpegOptions {
+                quality: settings.jpeg_quality,
+                subsampling: settings.jpeg_subsampling,
+                progressive: settings.jpeg_progressive,
+                dpi: settings
+                    .dpi
+                    .map(|dpi| dpi.round().clamp(1.0, u16::MAX as f32) as u16),
+                exif: exif(orientation, metadata),
+            };
+            let rgb = composite_over_background(data, settings.background_color);
+            jpeg::write_jpeg(&rgb, (width, height), &options, output_path)?;
+        }
+        ExportFormat::Jpeg => {
+            let rgb = composite_over_background(data, settings.background_color);
+            let img = image::RgbImage::from_raw(width, height, rgb)
+                .ok_or_else(|| SvgError::Export("Failed to create RGB image".into()))?;
+            // For quality control, use the jpeg encoder directly
             let file = std::fs::File::create(output_path)?;
             let mut buf_writer = std::io::BufWriter::new(file);
-            let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(
+            let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(
                 &mut buf_writer,
                 settings.jpeg_quality,
             );
+            if let Some(dpi) = settings.dpi {
+                let dpi = dpi.round().clamp(1.0, u16::MAX as f32) as u16;
+                encoder.set_pixel_density(image::codecs::jpeg::PixelDensity::dpi(dpi));
+            }
+            if let Some(exif) = exif(orientation, metadata) {
+                image::ImageEncoder::set_exif_metadata(&mut encoder, exif)
+                    .map_err(|e| SvgError::Export(e.to_string()))?;
+            }
             image::ImageEncoder::write_image(
                 encoder,
                 &img,
@@ -178,7 +1315,7 @@ pub fn save_pixmap(pixmap: &Pixmap, settings: &ExportSettings, output_path: &Pat
             let rgb = composite_over_background(data, settings.background_color);
             let img = image::RgbImage::from_raw(width, height, rgb)
                 .ok_or_else(|| SvgError::Export("Failed to create RGB image".into()))?;
-            img.save(output_path)
+            img.save_with_format(output_path, settings.format.image_format())
                 .map_err(|e| SvgError::Export(e.to_string()))?;
         }
     }
@@ -186,6 +1323,169 @@ pub fn save_pixmap(pixmap: &Pixmap, settings: &ExportSettings, output_path: &Pat
     Ok(())
 }
 
+/// Refuse, before anything is rendered, an export this build has no encoder
+/// for.
+fn check_encoder(settings: &ExportSettings) -> Result<()> {
+    if !settings.format.is_available() {
+        return Err(SvgError::Export(format!(
+            "this build can't write {}; it needs the image crate's `{}` feature",
+            settings.format.name(),
+            settings.format.extension()
+        )));
+    }
+    Ok(())
+}
+
+/// `data` as un-premultiplied RGBA, or composited over the background as RGB
+/// when the export drops alpha.
+fn unassociated(settings: &ExportSettings, data: &[u8]) -> (Vec<u8>, image::ExtendedColorType) {
+    if settings.include_alpha {
+        (un_premultiply_alpha(data), image::ExtendedColorType::Rgba8)
+    } else {
+        (
+            composite_over_background(data, settings.background_color),
+            image::ExtendedColorType::Rgb8,
+        )
+    }
+}
+
+/// `data` as a PNG export stores it: [`unassociated`], or with `png_16bit`
+/// two bytes a channel, big-endian.
+fn png_rows(settings: &ExportSettings, data: &[u8]) -> Vec<u8> {
+    if settings.png_16bit {
+        unassociated_16bit(settings, data)
+    } else {
+        unassociated(settings, data).0
+    }
+}
+
+/// [`unassociated`] at 16 bits a channel, big-endian. It's worked out in
+/// floating point from the premultiplied pixels, so the colour isn't rounded
+/// to 8 bits on the way.
+fn unassociated_16bit(settings: &ExportSettings, data: &[u8]) -> Vec<u8> {
+    let channels = if settings.include_alpha { 4 } else { 3 };
+    let mut result = Vec::with_capacity(data.len() / 4 * channels * 2);
+    let mut push = |value: f32| {
+        let value = (value * 65535.0).round().clamp(0.0, 65535.0) as u16;
+        result.extend_from_slice(&value.to_be_bytes());
+    };
+    let background = settings.background_color.map(|c| f32::from(c) / 255.0);
+    for chunk in data.chunks_exact(4) {
+        let a = f32::from(chunk[3]) / 255.0;
+        for (&premultiplied, bg) in chunk[..3].iter().zip(background) {
+            let premultiplied = f32::from(premultiplied) / 255.0;
+            push(if !settings.include_alpha {
+                premultiplied + bg * (1.0 - a)
+            } else if a > 0.0 {
+                (premultiplied / a).min(1.0)
+            } else {
+                0.0
+            });
+        }
+        if settings.include_alpha {
+            push(a);
+        }
+    }
+    result
+}
+
+/// A pHYs chunk saying the image is `dpi` pixels to the inch, which PNG
+/// stores per meter.
+fn png_resolution_chunk(dpi: f32) -> ([u8; 4], Vec<u8>) {
+    let per_meter = (dpi / 0.0254).round() as u32;
+    let mut data = per_meter.to_be_bytes().to_vec();
+    data.extend_from_slice(&per_meter.to_be_bytes());
+    // Unit: the meter
+    data.push(1);
+    (*b"pHYs", data)
+}
+
+/// A tEXt chunk, or an iTXt chunk for text tEXt's Latin-1 can't hold.
+fn png_text_chunk((keyword, text): &(&str, String)) -> ([u8; 4], Vec<u8>) {
+    let mut data = keyword.as_bytes().to_vec();
+    data.push(0);
+    if text.is_ascii() {
+        data.extend_from_slice(text.as_bytes());
+        (*b"tEXt", data)
+    } else {
+        // Uncompressed, with no language tag or translated keyword
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(text.as_bytes());
+        (*b"iTXt", data)
+    }
+}
+
+/// Write a PNG from `bands` of rows, top to bottom, each as [`unassociated`]
+/// gives them, with the resolution and `metadata` right after its header.
+fn write_png(
+    settings: &ExportSettings,
+    metadata: Option<&ImageMetadata>,
+    (width, height): (u32, u32),
+    output_path: &Path,
+    bands: impl IntoIterator<Item = Result<Vec<u8>>>,
+) -> Result<()> {
+    use std::io::Write;
+
+    let export_error = |e: png::EncodingError| SvgError::Export(e.to_string());
+    let file = std::io::BufWriter::new(std::fs::File::create(output_path)?);
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(if settings.include_alpha {
+        png::ColorType::Rgba
+    } else {
+        png::ColorType::Rgb
+    });
+    encoder.set_depth(if settings.png_16bit {
+        png::BitDepth::Sixteen
+    } else {
+        png::BitDepth::Eight
+    });
+    let mut writer = encoder.write_header().map_err(export_error)?;
+    let mut chunks = Vec::new();
+    if let Some(dpi) = settings.dpi {
+        chunks.push(png_resolution_chunk(dpi));
+    }
+    if let Some(metadata) = metadata {
+        chunks.extend(metadata.png_text().iter().map(png_text_chunk));
+    }
+    for (kind, data) in chunks {
+        writer
+            .write_chunk(png::chunk::ChunkType(kind), &data)
+            .map_err(export_error)?;
+    }
+    let mut stream = writer.stream_writer().map_err(export_error)?;
+    for band in bands {
+        stream.write_all(&band?)?;
+    }
+    stream.finish().map_err(export_error)?;
+    Ok(())
+}
+
+/// Inches each SVG unit of `doc` stands for in print: set by a width declared
+/// in absolute units such as `210mm`, and otherwise 1/96 inch.
+pub fn inches_per_unit(doc: &SvgDocument) -> f32 {
+    doc.metadata()
+        .width
+        .as_deref()
+        .and_then(folder_info::parse_length)
+        .map(|px| px / 96.0 / doc.width())
+        .filter(|inches| inches.is_finite() && *inches > 0.0)
+        .unwrap_or(1.0 / 96.0)
+}
+
+fn write_tagged_tiff<C: tiff::encoder::colortype::ColorType<Inner = u8>>(
+    encoder: &mut tiff::encoder::TiffEncoder<std::io::BufWriter<std::fs::File>>,
+    width: u32,
+    height: u32,
+    data: &[u8],
+    orientation: u16,
+) -> tiff::TiffResult<()> {
+    let mut image = encoder.new_image::<C>(width, height)?;
+    image
+        .encoder()
+        .write_tag(tiff::tags::Tag::Orientation, orientation)?;
+    image.write_data(data)
+}
+
 /// Get pixmap data as un-premultiplied RGBA bytes (for clipboard).
 pub fn pixmap_to_rgba(pixmap: &Pixmap) -> Vec<u8> {
     un_premultiply_alpha(pixmap.data())
@@ -210,6 +1510,97 @@ mod tests {
         assert_eq!(ExportFormat::Bmp.extension(), "bmp");
         assert_eq!(ExportFormat::Tiff.extension(), "tiff");
         assert_eq!(ExportFormat::WebP.extension(), "webp");
+        assert_eq!(ExportFormat::Avif.extension(), "avif");
+        assert_eq!(
+            ExportFormat::from_extension("AVIF"),
+            Some(ExportFormat::Avif)
+        );
+    }
+
+    #[test]
+    fn test_repeat_export_path() {
+        let last = Path::new("/out/logo@2x.png");
+        let source = Path::new("/art/logo.svg");
+        assert_eq!(repeat_export_path(last, source, source), last);
+        assert_eq!(
+            repeat_export_path(last, source, Path::new("/art/icon.svg")),
+            Path::new("/out/icon@2x.png")
+        );
+        // An output name unrelated to the source is replaced outright
+        assert_eq!(
+            repeat_export_path(
+                Path::new("/out/final.webp"),
+                source,
+                Path::new("/art/icon.svg")
+            ),
+            Path::new("/out/icon.webp")
+        );
+    }
+
+    #[test]
+    fn test_default_export_name() {
+        let dir = std::env::temp_dir().join(format!("svg_viewer_test_name_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let settings = ExportSettings::default();
+        let name = |size| default_export_name(Some(&dir), "logo", &settings, size);
+        assert_eq!(name(Some((512, 512))), "logo_512x512.png");
+        assert_eq!(name(None), "logo.png");
+
+        std::fs::write(dir.join("logo_512x512.png"), b"").unwrap();
+        std::fs::write(dir.join("logo_512x512_2.png"), b"").unwrap();
+        assert_eq!(name(Some((512, 512))), "logo_512x512_3.png");
+        assert_eq!(
+            default_export_name(None, "logo", &settings, Some((512, 512))),
+            "logo_512x512.png"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let svgz = ExportSettings {
+            format: ExportFormat::Svg,
+            svgz: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            with_default_extension(PathBuf::from("/out/logo"), &svgz),
+            Path::new("/out/logo.svgz")
+        );
+        assert_eq!(
+            with_default_extension(PathBuf::from("/out/logo.svg"), &svgz),
+            Path::new("/out/logo.svg")
+        );
+    }
+
+    #[test]
+    fn test_scaled_exports() {
+        assert_eq!(
+            scaled_path(Path::new("/out/logo.png"), 1.0),
+            Path::new("/out/logo.png")
+        );
+        assert_eq!(
+            scaled_path(Path::new("/out/logo.png"), 2.0),
+            Path::new("/out/logo@2x.png")
+        );
+        assert_eq!(
+            scaled_path(Path::new("/out/logo.v2.png"), 1.5),
+            Path::new("/out/logo.v2@1.5x.png")
+        );
+
+        let settings = ExportSettings {
+            width: 100,
+            height: 50,
+            dpi: Some(72.0),
+            padding: Padding::Pixels(4.0),
+            ..Default::default()
+        };
+        let scaled = scaled_settings(&settings, 1.5);
+        assert_eq!((scaled.width, scaled.height), (150, 75));
+        assert_eq!(scaled.dpi, Some(108.0));
+        assert_eq!(scaled.padding, Padding::Pixels(6.0));
+        let huge = scaled_settings(&settings, 1000.0);
+        assert_eq!(
+            (huge.width, huge.height),
+            (MAX_EXPORT_DIM, MAX_EXPORT_DIM / 2)
+        );
     }
 
     #[test]
@@ -275,6 +1666,665 @@ mod tests {
         std::fs::remove_file(&output).ok();
     }
 
+    #[test]
+    fn test_export_pdf_page_follows_dpi() {
+        let doc = crate::svg_document::SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+        let settings = ExportSettings {
+            format: ExportFormat::Pdf,
+            width: 300,
+            height: 150,
+            pdf_vector: false,
+            pdf_dpi: 300.0,
+            ..Default::default()
+        };
+        let output = std::env::temp_dir().join("svg_viewer_test_export.pdf");
+        export_svg(&doc, &Viewport::default(), &settings, &output).unwrap();
+        let pdf = std::fs::read(&output).unwrap();
+        // An inch by half an inch
+        assert_eq!(
+            crate::pdf::tests::parse_page_size(&pdf),
+            [0.0, 0.0, 72.0, 36.0]
+        );
+        assert!(String::from_utf8_lossy(&pdf).contains("/Width 300 /Height 150"));
+        std::fs::remove_file(&output).ok();
+    }
+
+    #[test]
+    fn test_export_vector_pdf_page_is_the_document_size() {
+        let doc = crate::svg_document::SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+        let settings = ExportSettings {
+            format: ExportFormat::Pdf,
+            include_alpha: false,
+            ..Default::default()
+        };
+        assert!(settings.is_vector_pdf());
+        let output = std::env::temp_dir().join("svg_viewer_test_export_vector.pdf");
+
+        let page_size = |pdf: &[u8]| {
+            let [_, _, w, h] = crate::pdf::tests::parse_page_size(pdf);
+            ((w * 100.0).round() / 100.0, (h * 100.0).round() / 100.0)
+        };
+
+        // 200×150 units at 96 to the inch, whatever the pixel size
+        export_svg(&doc, &Viewport::default(), &settings, &output).unwrap();
+        let pdf = std::fs::read(&output).unwrap();
+        assert_eq!(page_size(&pdf), (150.0, 112.5));
+        assert!(!String::from_utf8_lossy(&pdf).contains("/Subtype/Image"));
+
+        let region = ExportRegion::new(50.0, 0.0, 100.0, 50.0).unwrap();
+        export_region(&doc, &region, &settings, &output).unwrap();
+        let pdf = std::fs::read(&output).unwrap();
+        assert_eq!(page_size(&pdf), (75.0, 37.5));
+        std::fs::remove_file(&output).ok();
+    }
+
+    #[test]
+    fn test_export_svg_simplified_original_and_svgz() {
+        let doc = crate::svg_document::SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+        let mut settings = ExportSettings {
+            format: ExportFormat::Svg,
+            ..Default::default()
+        };
+        let output = std::env::temp_dir().join("svg_viewer_test_export_out.svg");
+        // The simplified tree has no text left, only its outlines
+        export_svg(&doc, &Viewport::default(), &settings, &output).unwrap();
+        let written = std::fs::read_to_string(&output).unwrap();
+        assert!(!written.contains("<text"));
+        let reloaded = crate::svg_document::SvgDocument::load(&output).unwrap();
+        assert_eq!((reloaded.width(), reloaded.height()), (200.0, 150.0));
+
+        settings.svg_original = true;
+        export_svg(&doc, &Viewport::default(), &settings, &output).unwrap();
+        assert_eq!(std::fs::read(&output).unwrap(), doc.raw_data());
+
+        settings.svgz = true;
+        assert_eq!(settings.extension(), "svgz");
+        export_svg(&doc, &Viewport::default(), &settings, &output).unwrap();
+        let compressed = std::fs::read(&output).unwrap();
+        assert!(crate::svg_document::is_gzip(&compressed));
+        assert_eq!(usvg::decompress_svgz(&compressed).unwrap(), doc.raw_data());
+        std::fs::remove_file(&output).ok();
+
+        // Regions are rendered, so have no SVG to write
+        let region = ExportRegion::new(0.0, 0.0, 10.0, 10.0).unwrap();
+        assert!(export_region(&doc, &region, &settings, &output).is_err());
+    }
+
+    #[test]
+    fn test_export_ico_letterboxes_each_size() {
+        let doc = crate::svg_document::SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+        let settings = ExportSettings {
+            format: ExportFormat::Ico,
+            ..Default::default()
+        };
+        let output = std::env::temp_dir().join("svg_viewer_test_export.ico");
+        export_svg(&doc, &Viewport::default(), &settings, &output).unwrap();
+        let bytes = std::fs::read(&output).unwrap();
+        // 16, 32, 48 and 256 in the directory
+        assert_eq!(u16::from_le_bytes([bytes[4], bytes[5]]), 4);
+        // The largest, with the 200×150 document centered on transparency
+        let image = image::open(&output).unwrap().to_rgba8();
+        assert_eq!(image.dimensions(), (256, 256));
+        assert_eq!(image.get_pixel(128, 8).0[3], 0);
+        assert_eq!(image.get_pixel(128, 128).0[3], 255);
+        std::fs::remove_file(&output).ok();
+
+        let settings = ExportSettings {
+            icon_sizes: vec![512],
+            ..settings
+        };
+        assert!(export_svg(&doc, &Viewport::default(), &settings, &output).is_err());
+    }
+
+    #[test]
+    fn test_export_records_dpi() {
+        let doc = crate::svg_document::SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+        let mut settings = ExportSettings {
+            width: 20,
+            height: 15,
+            dpi: Some(300.0),
+            ..Default::default()
+        };
+        let output = std::env::temp_dir().join("svg_viewer_test_dpi.png");
+        export_svg(&doc, &Viewport::default(), &settings, &output).unwrap();
+        let png = std::fs::read(&output).unwrap();
+        // Right after IHDR: 11811 pixels to the meter both ways
+        assert_eq!(&png[37..41], b"pHYs");
+        assert_eq!(&png[41..45], &11811u32.to_be_bytes());
+        assert_eq!(png[49], 1);
+        // Decoders check the chunk's CRC
+        assert_eq!(image::open(&output).unwrap().width(), 20);
+        std::fs::remove_file(&output).ok();
+
+        settings.format = ExportFormat::Jpeg;
+        let output = output.with_extension("jpg");
+        export_svg(&doc, &Viewport::default(), &settings, &output).unwrap();
+        let jpeg = std::fs::read(&output).unwrap();
+        // The JFIF header's units (inches) and densities
+        assert_eq!(&jpeg[6..11], b"JFIF\0");
+        assert_eq!(&jpeg[13..18], &[1, 1, 44, 1, 44]);
+        std::fs::remove_file(&output).ok();
+    }
+
+    /// Each chunk's type and data, checking its CRC.
+    fn png_chunks(png: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+        let mut chunks = Vec::new();
+        let mut at = 8;
+        while at < png.len() {
+            let len = u32::from_be_bytes(png[at..at + 4].try_into().unwrap()) as usize;
+            let body = &png[at + 4..at + 8 + len];
+            let mut crc = flate2::Crc::new();
+            crc.update(body);
+            assert_eq!(png[at + 8 + len..at + 12 + len], crc.sum().to_be_bytes());
+            chunks.push((body[..4].try_into().unwrap(), body[4..].to_vec()));
+            at += 12 + len;
+        }
+        chunks
+    }
+
+    #[test]
+    fn test_jpeg_subsampling_and_progressive() {
+        let doc = crate::svg_document::SvgDocument::load(&fixture_path("gradient.svg")).unwrap();
+        let output = std::env::temp_dir().join("svg_viewer_test_jpeg_options.jpg");
+        let export = |subsampling, progressive| {
+            let settings = ExportSettings {
+                format: ExportFormat::Jpeg,
+                width: 120,
+                height: 90,
+                include_alpha: false,
+                jpeg_subsampling: subsampling,
+                jpeg_progressive: progressive,
+                ..Default::default()
+            };
+            export_svg(&doc, &Viewport::default(), &settings, &output).unwrap();
+            let bytes = std::fs::read(&output).unwrap();
+            let decoded = image::load_from_memory(&bytes).unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (120, 90));
+            bytes
+        };
+        let full = export(ChromaSubsampling::Full, false);
+        let half = export(ChromaSubsampling::Half, false);
+        let progressive = export(ChromaSubsampling::Full, true);
+        let _ = std::fs::remove_file(&output);
+        assert!(half.len() < full.len(), "{} vs {}", half.len(), full.len());
+        assert_ne!(progressive.len(), full.len());
+        assert!(progressive.windows(2).any(|w| w == [0xFF, 0xC2]));
+    }
+
+    #[test]
+    fn test_unassociated_16bit() {
+        let settings = ExportSettings::default();
+        // Half-covered half red: exactly 0.5 once un-premultiplied in floating
+        // point, where rounding to 8 bits first gives 128/255
+        let rows = unassociated_16bit(&settings, &[64, 0, 0, 128, 0, 0, 0, 0]);
+        let values: Vec<u16> = rows
+            .chunks_exact(2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .collect();
+        assert!(values[0].abs_diff(32768) <= 1);
+        assert_ne!(values[0], 128 * 257);
+        assert_eq!(values[3], 128 * 257);
+        assert_eq!(&values[4..], [0, 0, 0, 0]);
+
+        let flattened = ExportSettings {
+            include_alpha: false,
+            background_color: [0, 0, 255],
+            ..Default::default()
+        };
+        let rows = unassociated_16bit(&flattened, &[64, 0, 0, 128]);
+        assert_eq!(rows.len(), 6);
+        assert_eq!(u16::from_be_bytes([rows[0], rows[1]]), 64 * 257);
+        assert_eq!(u16::from_be_bytes([rows[4], rows[5]]), 127 * 257);
+    }
+
+    #[test]
+    fn test_export_16bit_png() {
+        let doc = crate::svg_document::SvgDocument::load(&fixture_path("gradient.svg")).unwrap();
+        let output = std::env::temp_dir().join("svg_viewer_test_16bit.png");
+        let mut settings = ExportSettings {
+            width: 40,
+            height: 30,
+            png_16bit: true,
+            ..Default::default()
+        };
+        for include_alpha in [true, false] {
+            settings.include_alpha = include_alpha;
+            export_svg(&doc, &Viewport::default(), &settings, &output).unwrap();
+            let decoder = png::Decoder::new(std::io::BufReader::new(
+                std::fs::File::open(&output).unwrap(),
+            ));
+            let reader = decoder.read_info().unwrap();
+            let info = reader.info();
+            assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+            let color = if include_alpha {
+                png::ColorType::Rgba
+            } else {
+                png::ColorType::Rgb
+            };
+            assert_eq!(info.color_type, color);
+            assert_eq!((info.width, info.height), (40, 30));
+        }
+        let _ = std::fs::remove_file(&output);
+    }
+
+    #[test]
+    fn test_export_padding() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="20">
+            <rect width="10" height="20" fill="red"/></svg>"#;
+        let doc =
+            crate::svg_document::SvgDocument::from_data(Path::new("a.svg"), svg.into()).unwrap();
+        let mut settings = ExportSettings {
+            width: 20,
+            height: 40,
+            padding: Padding::Pixels(5.0),
+            ..Default::default()
+        };
+        let output = std::env::temp_dir().join("svg_viewer_test_padding.png");
+        export_svg(&doc, &Viewport::default(), &settings, &output).unwrap();
+        let image = image::open(&output).unwrap().to_rgba8();
+        // The drawing is shrunk to 10×20, centred, with transparency around it
+        assert_eq!(image.dimensions(), (20, 40));
+        assert_eq!(image.get_pixel(4, 20).0[3], 0);
+        assert_eq!(image.get_pixel(10, 9).0[3], 0);
+        assert_eq!(image.get_pixel(5, 10).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(14, 29).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(15, 20).0[3], 0);
+
+        // Over the background when alpha is dropped, and by percent
+        settings.include_alpha = false;
+        settings.background_color = [0, 0, 255];
+        settings.padding = Padding::Percent(25.0);
+        export_svg(&doc, &Viewport::default(), &settings, &output).unwrap();
+        let image = image::open(&output).unwrap().to_rgb8();
+        assert_eq!(image.get_pixel(4, 20).0, [0, 0, 255]);
+        assert_eq!(image.get_pixel(10, 20).0, [255, 0, 0]);
+        let _ = std::fs::remove_file(&output);
+
+        for padding in [Padding::Pixels(-1.0), Padding::Percent(50.0)] {
+            settings.padding = padding;
+            assert!(export_svg(&doc, &Viewport::default(), &settings, &output).is_err());
+        }
+    }
+
+    #[test]
+    fn test_export_webp_and_avif() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="8" height="8">
+            <rect width="5" height="8" fill="red" fill-opacity="0.5"/>
+            <rect x="5" width="3" height="8" fill="blue"/></svg>"#;
+        let doc =
+            crate::svg_document::SvgDocument::from_data(Path::new("a.svg"), svg.into()).unwrap();
+        let mut settings = ExportSettings {
+            format: ExportFormat::WebP,
+            width: 8,
+            height: 8,
+            include_alpha: true,
+            ..Default::default()
+        };
+        let output = std::env::temp_dir().join("svg_viewer_test_lossless.webp");
+        export_svg(&doc, &Viewport::default(), &settings, &output).unwrap();
+        let decoded = image::open(&output).unwrap().to_rgba8();
+        let rendered = renderer::render_for_export(&doc, 8, 8, &Viewport::default()).unwrap();
+        assert_eq!(decoded.dimensions(), (8, 8));
+        assert_eq!(decoded.into_raw(), pixmap_to_rgba(&rendered));
+
+        let _ = std::fs::remove_file(&output);
+
+        // Read back with a parser, as decoding AVIF needs dav1d
+        settings.format = ExportFormat::Avif;
+        let output = std::env::temp_dir().join("svg_viewer_test_export.avif");
+        let read_back = |settings: &ExportSettings| {
+            export_svg(&doc, &Viewport::default(), settings, &output).unwrap();
+            let bytes = std::fs::read(&output).unwrap();
+            let avif = avif_parse::read_avif(&mut bytes.as_slice()).unwrap();
+            let color = avif.primary_item_metadata().unwrap();
+            assert_eq!(
+                (color.max_frame_width.get(), color.max_frame_height.get()),
+                (8, 8)
+            );
+            let alpha = avif.alpha_item_metadata().unwrap();
+            (bytes.len(), alpha.map(|a| a.max_frame_width.get()))
+        };
+        let (_, alpha) = read_back(&settings);
+        assert_eq!(alpha, Some(8));
+        settings.include_alpha = false;
+        let (low, alpha) = read_back(&ExportSettings {
+            avif_quality: 10,
+            ..settings.clone()
+        });
+        assert_eq!(alpha, None);
+        let (high, _) = read_back(&ExportSettings {
+            avif_quality: 100,
+            ..settings
+        });
+        assert!(low < high, "{low} bytes at quality 10, {high} at 100");
+        let _ = std::fs::remove_file(&output);
+    }
+
+    #[test]
+    fn test_export_records_metadata() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <title>Café sign</title><rect width="10" height="10" fill="red"/></svg>"#;
+        let doc =
+            crate::svg_document::SvgDocument::from_data(Path::new("art/sign.svg"), svg.into())
+                .unwrap();
+        let mut settings = ExportSettings {
+            width: 10,
+            height: 10,
+            ..Default::default()
+        };
+        let output = std::env::temp_dir().join("svg_viewer_test_metadata.png");
+        export_svg(&doc, &Viewport::default(), &settings, &output).unwrap();
+        let png = std::fs::read(&output).unwrap();
+        let text: Vec<(String, String)> = png_chunks(&png)
+            .into_iter()
+            .filter_map(|(kind, data)| {
+                let nul = data.iter().position(|&b| b == 0)?;
+                let text = match &kind {
+                    b"tEXt" => &data[nul + 1..],
+                    // Past the compression flags and empty language and keyword
+                    b"iTXt" => &data[nul + 5..],
+                    _ => return None,
+                };
+                let keyword = String::from_utf8(data[..nul].to_vec()).unwrap();
+                Some((keyword, String::from_utf8(text.to_vec()).unwrap()))
+            })
+            .collect();
+        let value = |keyword: &str| {
+            text.iter()
+                .find(|(k, _)| k == keyword)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(value("Title"), Some("Café sign"));
+        assert_eq!(value("Software"), Some(SOFTWARE));
+        assert_eq!(value("Source"), Some("sign.svg"));
+        let created = value("Creation Time").unwrap();
+        assert!(created.len() == 20 && created.ends_with('Z'), "{created}");
+        assert_eq!(image::open(&output).unwrap().width(), 10);
+
+        settings.format = ExportFormat::WebP;
+        let output_webp = output.with_extension("webp");
+        export_svg(&doc, &Viewport::default(), &settings, &output_webp).unwrap();
+        let mut decoder = image::ImageReader::open(&output_webp)
+            .unwrap()
+            .into_decoder()
+            .unwrap();
+        let exif = image::ImageDecoder::exif_metadata(&mut decoder)
+            .unwrap()
+            .unwrap();
+        assert!(exif.starts_with(b"II*\0"));
+        let contains = |needle: &[u8]| exif.windows(needle.len()).any(|w| w == needle);
+        assert!(contains(SOFTWARE.as_bytes()));
+        assert!(contains("Café sign".as_bytes()));
+        assert!(contains(b"sign.svg\0"));
+        std::fs::remove_file(&output_webp).ok();
+
+        // Without it, the same export is the same bytes
+        settings.format = ExportFormat::Png;
+        settings.metadata = false;
+        export_svg(&doc, &Viewport::default(), &settings, &output).unwrap();
+        let first = std::fs::read(&output).unwrap();
+        export_svg(&doc, &Viewport::default(), &settings, &output).unwrap();
+        assert_eq!(first, std::fs::read(&output).unwrap());
+        assert!(png_chunks(&first)
+            .iter()
+            .all(|(kind, _)| kind != b"tEXt" && kind != b"iTXt"));
+        std::fs::remove_file(&output).ok();
+    }
+
+    #[test]
+    fn test_inches_per_unit() {
+        let doc = |svg: &str| {
+            crate::svg_document::SvgDocument::from_data(Path::new("t.svg"), svg.into()).unwrap()
+        };
+        let a4 = doc(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="210mm" height="297mm" viewBox="0 0 210 297"/>"#,
+        );
+        assert!((inches_per_unit(&a4) * a4.width() - 210.0 / 25.4).abs() < 1e-3);
+        let plain = doc(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="50"/>"#);
+        assert!((inches_per_unit(&plain) - 1.0 / 96.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_export_stretch_is_optional() {
+        let doc = asymmetric_doc();
+        let mut viewport = Viewport::default();
+        viewport.stretch_to_area(40.0, 20.0, 40.0, 40.0);
+        let output = std::env::temp_dir().join("svg_viewer_test_stretch.png");
+        let red_rows = |stretch: bool| {
+            let settings = ExportSettings {
+                width: 40,
+                height: 40,
+                stretch,
+                ..Default::default()
+            };
+            export_svg(&doc, &viewport, &settings, &output).unwrap();
+            let image = image::open(&output).unwrap().to_rgba8();
+            (0..40)
+                .filter(|&y| image.get_pixel(2, y).0 == [255, 0, 0, 255])
+                .count()
+        };
+        // Stretched, the 10-unit red block is 20 px tall, and 10 px when not
+        assert_eq!(red_rows(true), 20);
+        assert_eq!(red_rows(false), 10);
+        std::fs::remove_file(&output).ok();
+    }
+
+    #[test]
+    fn test_format_from_extension() {
+        assert_eq!(ExportFormat::from_extension("PNG"), Some(ExportFormat::Png));
+        assert_eq!(
+            ExportFormat::from_extension("jpeg"),
+            Some(ExportFormat::Jpeg)
+        );
+        assert_eq!(
+            ExportFormat::from_extension("tif"),
+            Some(ExportFormat::Tiff)
+        );
+        assert_eq!(
+            ExportFormat::from_extension("svgz"),
+            Some(ExportFormat::Svg)
+        );
+        assert_eq!(
+            ExportFormat::from_extension("ICNS"),
+            Some(ExportFormat::Icns)
+        );
+        assert_eq!(ExportFormat::from_extension("gif"), None);
+        assert_eq!(
+            ExportFormat::from_path(Path::new("out/render.webp")),
+            Some(ExportFormat::WebP)
+        );
+        assert_eq!(ExportFormat::from_path(Path::new("noext")), None);
+    }
+
+    #[test]
+    fn test_parse_region() {
+        let region: ExportRegion = "10, 20.5,30,40".parse().unwrap();
+        assert_eq!(region, ExportRegion::new(10.0, 20.5, 30.0, 40.0).unwrap());
+        assert!("10,20,30".parse::<ExportRegion>().is_err());
+        assert!("10,20,abc,40".parse::<ExportRegion>().is_err());
+    }
+
+    #[test]
+    fn test_degenerate_region_rejected() {
+        let err = "0,0,0,10".parse::<ExportRegion>().unwrap_err();
+        assert!(err.to_string().contains("zero area"));
+        assert!("0,0,10,-5".parse::<ExportRegion>().is_err());
+        assert!(ExportRegion::new(0.0, 0.0, f32::NAN, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_region_from_corners() {
+        let corners = [[30.0, 5.0], [10.0, 5.0], [30.0, 45.0], [10.0, 45.0]];
+        assert_eq!(
+            ExportRegion::from_corners(&corners).unwrap(),
+            ExportRegion::new(10.0, 5.0, 20.0, 40.0).unwrap()
+        );
+        // A click, or a drag along one axis, selects nothing
+        assert!(ExportRegion::from_corners(&[[3.0, 4.0], [3.0, 4.0]]).is_err());
+        assert!(ExportRegion::from_corners(&[[3.0, 4.0], [9.0, 4.0]]).is_err());
+        assert!(ExportRegion::from_corners(&[]).is_err());
+    }
+
+    #[test]
+    fn test_region_clipped_to_document() {
+        let inside = ExportRegion::new(10.0, 10.0, 20.0, 30.0).unwrap();
+        assert_eq!(inside.clipped_to(200.0, 150.0).unwrap(), inside);
+        let overhanging = ExportRegion::new(-10.0, 140.0, 50.0, 20.0).unwrap();
+        assert_eq!(
+            overhanging.clipped_to(200.0, 150.0).unwrap(),
+            ExportRegion::new(0.0, 140.0, 40.0, 10.0).unwrap()
+        );
+        let outside = ExportRegion::new(500.0, 500.0, 10.0, 10.0).unwrap();
+        let err = outside.clipped_to(200.0, 150.0).unwrap_err();
+        assert!(err.to_string().contains("outside the document"));
+        // Touching the edge covers nothing
+        assert!(ExportRegion::new(200.0, 0.0, 10.0, 10.0)
+            .unwrap()
+            .clipped_to(200.0, 150.0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_region_padding() {
+        let region = ExportRegion::new(10.0, 10.0, 20.0, 30.0).unwrap();
+        let padded = region.padded(5.0).unwrap();
+        assert_eq!(padded, ExportRegion::new(5.0, 5.0, 30.0, 40.0).unwrap());
+        // Negative padding that collapses the region is an error
+        assert!(region.padded(-10.0).is_err());
+    }
+
+    #[test]
+    fn test_output_size() {
+        let region = ExportRegion::new(0.0, 0.0, 20.0, 10.0).unwrap();
+        assert_eq!(region.output_size(None, None, 1.0), (20, 10));
+        assert_eq!(region.output_size(None, None, 4.0), (80, 40));
+        assert_eq!(region.output_size(Some(100), None, 1.0), (100, 50));
+        assert_eq!(region.output_size(None, Some(100), 1.0), (200, 100));
+        assert_eq!(region.output_size(Some(7), Some(9), 3.0), (7, 9));
+    }
+
+    #[test]
+    fn test_max_dimensions() {
+        assert_eq!(ExportFormat::WebP.max_dimensions(), (16383, 16383));
+        assert_eq!(ExportFormat::Jpeg.max_dimensions(), (65500, 65500));
+        for fmt in ExportFormat::all() {
+            let (w, h) = fmt.max_dimensions();
+            assert!(w >= MAX_RENDER_DIM && h >= MAX_RENDER_DIM, "{}", fmt.name());
+        }
+    }
+
+    #[test]
+    fn test_plan_size_within_limits() {
+        let plan = plan_size(&ExportFormat::Png, 800, 600);
+        assert!(!plan.is_reduced());
+        assert_eq!(plan.actual, (800, 600));
+        assert!(plan.reasons.is_empty());
+    }
+
+    #[test]
+    fn test_plan_size_export_limit() {
+        // Past one render, which is tiled
+        assert!(!plan_size(&ExportFormat::Png, 8192, 2048).is_reduced());
+        let plan = plan_size(&ExportFormat::Png, 32768, 2048);
+        assert!(plan.is_reduced());
+        assert_eq!(plan.requested, (32768, 2048));
+        assert_eq!(plan.actual, (MAX_EXPORT_DIM, 1024));
+        assert_eq!(plan.reasons.len(), 1);
+        assert!(plan.reasons[0].contains("16384"));
+    }
+
+    #[test]
+    fn test_plan_size_format_limit() {
+        let plan = plan_size(&ExportFormat::WebP, 20000, 100);
+        assert_eq!(plan.actual, (16383, 82));
+        assert!(plan.reasons.iter().any(|r| r.contains("WebP")));
+        // Extreme aspects keep at least one pixel on the minor side
+        assert_eq!(
+            plan_size(&ExportFormat::Png, 100000, 1).actual,
+            (MAX_EXPORT_DIM, 1)
+        );
+    }
+
+    #[test]
+    fn test_export_uses_planned_size() {
+        let doc = SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+        let settings = ExportSettings {
+            width: 32768,
+            height: 16,
+            ..Default::default()
+        };
+        let output = std::env::temp_dir().join("svg_viewer_test_planned_size.png");
+        export_svg(&doc, &Viewport::default(), &settings, &output).unwrap();
+        let img = image::open(&output).unwrap();
+        assert_eq!((img.width(), img.height()), (MAX_EXPORT_DIM, 8));
+        std::fs::remove_file(&output).ok();
+    }
+
+    #[test]
+    fn test_streamed_png_matches_tiled_render() {
+        let doc = SvgDocument::load(&fixture_path("gradient.svg")).unwrap();
+        let (width, height) = (MAX_RENDER_DIM + 904, 1200);
+        assert!(is_streamed(&ExportFormat::Png, width, height));
+        let settings = ExportSettings {
+            width,
+            height,
+            ..Default::default()
+        };
+        let output = std::env::temp_dir().join("svg_viewer_test_streamed.png");
+        export_svg(&doc, &Viewport::default(), &settings, &output).unwrap();
+        let png = image::open(&output).unwrap().into_rgba8();
+        std::fs::remove_file(&output).ok();
+
+        let whole = renderer::render_for_export(&doc, width, height, &Viewport::default()).unwrap();
+        assert_eq!((png.width(), png.height()), (width, height));
+        // Bands and tiles only differ in float rounding
+        let close = png
+            .as_raw()
+            .iter()
+            .zip(un_premultiply_alpha(whole.data()))
+            .all(|(a, b)| a.abs_diff(b) <= 4);
+        assert!(close, "streamed PNG differs from the render");
+        // Held a band at a time
+        assert!(
+            estimated_memory(&ExportFormat::Png, width, height)
+                < estimated_memory(&ExportFormat::Tiff, width, height)
+        );
+    }
+
+    #[test]
+    fn test_fit_dimensions() {
+        assert_eq!(fit_dimensions(100.0, 50.0, 8192), (100, 50));
+        assert_eq!(fit_dimensions(2_000_000.0, 1_000_000.0, 8192), (8192, 4096));
+        assert_eq!(fit_dimensions(1.0, 100000.0, 8192), (1, 8192));
+        assert_eq!(fit_dimensions(0.0, 0.0, 8192), (1, 1));
+        assert_eq!(fit_dimensions(f32::NAN, 10.0, 8192), (1, 10));
+    }
+
+    #[test]
+    fn test_export_region_pixels() {
+        let doc = crate::svg_document::SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+        // Top-left corner of simple_rect.svg: transparent margin, then the blue rect
+        let region: ExportRegion = "0,0,20,20".parse().unwrap();
+        let (width, height) = region.output_size(None, None, 4.0);
+        let settings = ExportSettings {
+            format: ExportFormat::Png,
+            width,
+            height,
+            include_alpha: true,
+            ..Default::default()
+        };
+        let output = std::env::temp_dir().join("svg_viewer_test_export_region.png");
+        export_region(&doc, &region, &settings, &output).unwrap();
+
+        let img = image::open(&output).unwrap().to_rgba8();
+        assert_eq!(img.dimensions(), (80, 80));
+        // Doc (0.5, 0.5) lies outside the rect
+        assert_eq!(img.get_pixel(2, 2)[3], 0);
+        // Doc (19.5, 19.5) is inside the #4A90D9 fill
+        assert_eq!(img.get_pixel(78, 78).0, [0x4A, 0x90, 0xD9, 255]);
+        std::fs::remove_file(&output).ok();
+    }
+
     #[test]
     fn test_export_jpeg() {
         let doc = crate::svg_document::SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
@@ -292,4 +2342,230 @@ mod tests {
         assert!(output.exists());
         std::fs::remove_file(&output).ok();
     }
+
+    /// Rotation, mirror_h, mirror_v and the Orientation value they map to.
+    const ORIENTATION_CASES: [(f32, bool, bool, u16); 8] = [
+        (0.0, false, false, 1),
+        (0.0, true, false, 2),
+        (180.0, false, false, 3),
+        (0.0, false, true, 4),
+        (270.0, true, false, 5),
+        (90.0, false, false, 6),
+        (90.0, true, false, 7),
+        (270.0, false, false, 8),
+    ];
+
+    fn oriented_view(rotation_deg: f32, mirror_h: bool, mirror_v: bool) -> Viewport {
+        Viewport {
+            rotation_deg,
+            mirror_h,
+            mirror_v,
+            ..Viewport::default()
+        }
+    }
+
+    /// A 40×20 document with a red block in its top-left corner, so every
+    /// orientation looks different.
+    fn asymmetric_doc() -> SvgDocument {
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20">
+            <rect width="40" height="20" fill="#0000ff"/>
+            <rect width="10" height="10" fill="#ff0000"/>
+        </svg>"##;
+        SvgDocument::from_data(Path::new("asymmetric.svg"), svg.to_vec()).unwrap()
+    }
+
+    fn decode_oriented(path: &Path) -> (image::metadata::Orientation, image::DynamicImage) {
+        use image::ImageDecoder;
+        let mut decoder = image::ImageReader::open(path)
+            .unwrap()
+            .with_guessed_format()
+            .unwrap()
+            .into_decoder()
+            .unwrap();
+        let orientation = decoder.orientation().unwrap();
+        (
+            orientation,
+            image::DynamicImage::from_decoder(decoder).unwrap(),
+        )
+    }
+
+    fn export_rgb(
+        doc: &SvgDocument,
+        view: &Viewport,
+        settings: &ExportSettings,
+    ) -> image::RgbImage {
+        let output = std::env::temp_dir().join(format!(
+            "svg_viewer_test_view_{}_{}.png",
+            view.rotation_deg, view.mirror_h
+        ));
+        export_svg(doc, view, settings, &output).unwrap();
+        let image = image::open(&output).unwrap().to_rgb8();
+        std::fs::remove_file(&output).ok();
+        image
+    }
+
+    #[test]
+    fn test_export_follows_view_rotation_and_mirroring() {
+        let doc = asymmetric_doc();
+        let upright = ExportSettings {
+            width: 40,
+            height: 20,
+            include_alpha: false,
+            ..Default::default()
+        };
+        let turned = ExportSettings {
+            width: 20,
+            height: 40,
+            ..upright.clone()
+        };
+        let expected = export_rgb(&doc, &Viewport::default(), &upright);
+
+        // The whole turned box fills the output, neither squashed nor clipped
+        let rotated = export_rgb(&doc, &oriented_view(90.0, false, false), &turned);
+        assert_eq!(rotated, image::imageops::rotate90(&expected));
+        let mirrored = export_rgb(&doc, &oriented_view(0.0, true, false), &upright);
+        assert_eq!(mirrored, image::imageops::flip_horizontal(&expected));
+        // The red block ends up top-right once turned
+        assert_eq!(rotated.get_pixel(15, 2).0, [255, 0, 0]);
+
+        let ignored = ExportSettings {
+            view_orientation: false,
+            ..upright.clone()
+        };
+        let view = oriented_view(90.0, true, false);
+        assert_eq!(export_rgb(&doc, &view, &ignored), expected);
+    }
+
+    #[test]
+    fn test_exif_orientation_codes() {
+        for (rotation, mirror_h, mirror_v, code) in ORIENTATION_CASES {
+            let view = oriented_view(rotation, mirror_h, mirror_v);
+            assert_eq!(exif_orientation(&view), Some(code), "{view:?}");
+        }
+        // Both mirrors are a half turn
+        assert_eq!(exif_orientation(&oriented_view(90.0, true, true)), Some(8));
+        assert_eq!(
+            exif_orientation(&oriented_view(180.0, false, true)),
+            Some(2)
+        );
+        assert_eq!(exif_orientation(&oriented_view(45.0, false, false)), None);
+    }
+
+    #[test]
+    fn test_plan_orientation_forces_bake() {
+        let settings = ExportSettings {
+            format: ExportFormat::Jpeg,
+            orientation: OrientationMode::Metadata,
+            ..Default::default()
+        };
+        let tilted = oriented_view(30.0, false, false);
+        assert!(matches!(
+            plan_orientation(&settings, &tilted),
+            OrientationPlan::Bake { notice: Some(_) }
+        ));
+        assert_eq!(
+            plan_orientation(&settings, &oriented_view(90.0, false, false)),
+            OrientationPlan::Tag(6)
+        );
+
+        let png = ExportSettings {
+            format: ExportFormat::Png,
+            ..settings.clone()
+        };
+        assert!(matches!(
+            plan_orientation(&png, &oriented_view(90.0, false, false)),
+            OrientationPlan::Bake { notice: Some(_) }
+        ));
+        let baked = ExportSettings {
+            orientation: OrientationMode::Bake,
+            ..settings
+        };
+        assert_eq!(
+            plan_orientation(&baked, &tilted),
+            OrientationPlan::Bake { notice: None }
+        );
+    }
+
+    #[test]
+    fn test_jpeg_orientation_tag_round_trip() {
+        let doc = asymmetric_doc();
+        let output = std::env::temp_dir().join("svg_viewer_test_oriented.jpg");
+        for (rotation, mirror_h, mirror_v, code) in ORIENTATION_CASES {
+            let view = oriented_view(rotation, mirror_h, mirror_v);
+            let (width, height) = view.rotated_size(40.0, 20.0);
+            let settings = ExportSettings {
+                format: ExportFormat::Jpeg,
+                width: width as u32,
+                height: height as u32,
+                include_alpha: false,
+                orientation: OrientationMode::Metadata,
+                ..Default::default()
+            };
+            export_svg(&doc, &view, &settings, &output).unwrap();
+
+            let (orientation, stored) = decode_oriented(&output);
+            assert_eq!(
+                Some(orientation),
+                image::metadata::Orientation::from_exif(code as u8)
+            );
+            // Stored unrotated
+            assert_eq!(
+                (stored.width(), stored.height()),
+                (40, 20),
+                "orientation {code}"
+            );
+        }
+        std::fs::remove_file(&output).ok();
+    }
+
+    #[test]
+    fn test_tiff_orientation_matches_baked_pixels() {
+        let doc = asymmetric_doc();
+        let tagged_path = std::env::temp_dir().join("svg_viewer_test_oriented_tagged.tiff");
+        let baked_path = std::env::temp_dir().join("svg_viewer_test_oriented_baked.tiff");
+        for (rotation, mirror_h, mirror_v, code) in ORIENTATION_CASES {
+            let view = oriented_view(rotation, mirror_h, mirror_v);
+            let (width, height) = view.rotated_size(40.0, 20.0);
+            let baked = ExportSettings {
+                format: ExportFormat::Tiff,
+                width: width as u32,
+                height: height as u32,
+                include_alpha: false,
+                ..Default::default()
+            };
+            let tagged = ExportSettings {
+                orientation: OrientationMode::Metadata,
+                ..baked.clone()
+            };
+            export_svg(&doc, &view, &baked, &baked_path).unwrap();
+            export_svg(&doc, &view, &tagged, &tagged_path).unwrap();
+
+            let (orientation, mut shown) = decode_oriented(&tagged_path);
+            assert_eq!(
+                Some(orientation),
+                image::metadata::Orientation::from_exif(code as u8)
+            );
+            let (none, expected) = decode_oriented(&baked_path);
+            assert_eq!(none, image::metadata::Orientation::NoTransforms);
+
+            shown.apply_orientation(orientation);
+            let (shown, expected) = (shown.to_rgb8(), expected.to_rgb8());
+            assert_eq!(
+                shown.dimensions(),
+                expected.dimensions(),
+                "orientation {code}"
+            );
+            // The red block lands in a different corner for each orientation
+            let (right, bottom) = (width as u32 - 3, height as u32 - 3);
+            for (x, y) in [(2, 2), (right, 2), (2, bottom), (right, bottom)] {
+                assert_eq!(
+                    shown.get_pixel(x, y),
+                    expected.get_pixel(x, y),
+                    "orientation {code} at {x},{y}"
+                );
+            }
+        }
+        std::fs::remove_file(&tagged_path).ok();
+        std::fs::remove_file(&baked_path).ok();
+    }
 }
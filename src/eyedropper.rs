@@ -0,0 +1,53 @@
+//! Reading colors back off the render for the eyedropper.
+//!
+//! Renders are premultiplied, so a sampled pixel is un-premultiplied the way
+//! exports are before it's reported.
+
+use tiny_skia::Pixmap;
+
+use crate::export::un_premultiply_alpha;
+
+/// The straight RGBA pixel at `uv`, a position across the pixmap from 0 to 1
+/// on each axis, or None outside it.
+pub fn pick(pixmap: &Pixmap, [u, v]: [f32; 2]) -> Option<[u8; 4]> {
+    if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+        return None;
+    }
+    let x = (u * pixmap.width() as f32) as usize;
+    let y = (v * pixmap.height() as f32) as usize;
+    let offset = (y * pixmap.width() as usize + x) * 4;
+    let pixel = un_premultiply_alpha(pixmap.data().get(offset..offset + 4)?);
+    pixel.try_into().ok()
+}
+
+/// `#RRGGBB @ alpha%`, as copied to the clipboard.
+pub fn format_color([r, g, b, a]: [u8; 4]) -> String {
+    let percent = (a as f32 / 255.0 * 100.0).round();
+    format!("#{r:02X}{g:02X}{b:02X} @ {percent:.0}%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiny_skia::Color;
+
+    #[test]
+    fn test_pick_un_premultiplies() {
+        let mut pixmap = Pixmap::new(4, 2).unwrap();
+        pixmap.fill(Color::from_rgba8(0x3A, 0x7B, 0xD5, 204));
+        let picked = pick(&pixmap, [0.9, 0.6]).unwrap();
+        // Premultiplying rounds, so the color comes back to within a step
+        for (got, want) in picked.iter().zip([0x3A, 0x7B, 0xD5]) {
+            assert!(got.abs_diff(want) <= 1, "{picked:?}");
+        }
+        assert_eq!(picked[3], 204);
+        assert_eq!(pick(&pixmap, [1.0, 0.5]), None);
+        assert_eq!(pick(&pixmap, [-0.1, 0.5]), None);
+    }
+
+    #[test]
+    fn test_format_color() {
+        assert_eq!(format_color([0x3A, 0x7B, 0xD5, 204]), "#3A7BD5 @ 80%");
+        assert_eq!(format_color([0, 0, 0, 255]), "#000000 @ 100%");
+    }
+}
@@ -1,8 +1,131 @@
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
+use crate::archive;
+use crate::error::Result;
+
+/// The SVG files in the current file's directory, in natural sort order, an
+/// explicit list of files opened together, or the SVG entries of a zip archive.
+/// A filter narrows what's navigated to the files whose names match it.
 pub struct FileNavigator {
-    pub files: Vec<PathBuf>,
-    pub current_index: usize,
+    /// The files navigated, with the filter applied
+    files: Vec<PathBuf>,
+    current_index: usize,
+    /// The archive whose entries `files` are, as virtual paths
+    archive: Option<PathBuf>,
+    /// The directory whose files `files` are, if it was scanned
+    dir: Option<PathBuf>,
+    /// Every file, for when the filter changes or is cleared
+    all_files: Vec<PathBuf>,
+    filter: Option<String>,
+    /// The current file while the filter matches nothing, to return to when
+    /// it's cleared
+    unmatched_current: Option<PathBuf>,
+}
+
+/// How a rescan changed the files navigated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FileChanges {
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl FileChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added == 0 && self.removed == 0
+    }
+
+    /// Such as "2 files added" or "1 file added, 3 removed".
+    pub fn describe(&self) -> Option<String> {
+        let files = |n: usize| if n == 1 { "file" } else { "files" };
+        match (self.added, self.removed) {
+            (0, 0) => None,
+            (added, 0) => Some(format!("{added} {} added", files(added))),
+            (0, removed) => Some(format!("{removed} {} removed", files(removed))),
+            (added, removed) => Some(format!("{added} {} added, {removed} removed", files(added))),
+        }
+    }
+}
+
+/// Whether `filter` matches the file name of `path`, ignoring case. A filter
+/// with `*` or `?` is a glob matched against the whole name, or the name
+/// without its extension; anything else matches as a substring.
+pub fn matches_filter(path: &Path, filter: &str) -> bool {
+    let Some(name) = path.file_name() else {
+        return false;
+    };
+    let name = name.to_string_lossy().to_lowercase();
+    let filter = filter.to_lowercase();
+    if !filter.contains(['*', '?']) {
+        return name.contains(&filter);
+    }
+    let pattern: Vec<char> = filter.chars().collect();
+    let stem = name
+        .rsplit_once('.')
+        .map_or(name.as_str(), |(stem, _)| stem);
+    glob_matches(&pattern, &name.chars().collect::<Vec<_>>())
+        || glob_matches(&pattern, &stem.chars().collect::<Vec<_>>())
+}
+
+/// `*` matches any run of characters and `?` any one.
+fn glob_matches(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and how much of the text it has taken
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// The `.svg`/`.svgz` files directly inside `dir`, in natural order.
+pub fn svg_files_in(dir: &Path) -> Vec<PathBuf> {
+    let mut svg_files: Vec<PathBuf> = Vec::new();
+
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_file() {
+                if let Some(ext) = path.extension() {
+                    let ext_lower = ext.to_string_lossy().to_lowercase();
+                    if ext_lower == "svg" || ext_lower == "svgz" {
+                        svg_files.push(path);
+                    }
+                }
+            }
+        }
+    }
+
+    // Natural sort
+    svg_files.sort_by(|a, b| {
+        let a_name = a.file_name().unwrap_or_default().to_string_lossy();
+        let b_name = b.file_name().unwrap_or_default().to_string_lossy();
+        natord::compare(&a_name, &b_name)
+    });
+    svg_files
+}
+
+impl Default for FileNavigator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl FileNavigator {
@@ -10,38 +133,32 @@ impl FileNavigator {
         Self {
             files: Vec::new(),
             current_index: 0,
+            archive: None,
+            dir: None,
+            all_files: Vec::new(),
+            filter: None,
+            unmatched_current: None,
         }
     }
 
     /// Scan the directory of the given file for SVG files and set the current index.
+    /// An entry inside an archive navigates the archive's entries instead.
+    /// Either clears the filter.
     pub fn scan_directory(&mut self, file_path: &Path) {
+        if let Some((zip, _)) = archive::split_path(file_path) {
+            let zip = zip.to_path_buf();
+            if self.scan_archive(&zip).is_ok() {
+                self.current_index = self.files.iter().position(|p| p == file_path).unwrap_or(0);
+            }
+            return;
+        }
+        self.archive = None;
         let dir = match file_path.parent() {
             Some(d) => d,
             None => return,
         };
 
-        let mut svg_files: Vec<PathBuf> = Vec::new();
-
-        if let Ok(entries) = std::fs::read_dir(dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.is_file() {
-                    if let Some(ext) = path.extension() {
-                        let ext_lower = ext.to_string_lossy().to_lowercase();
-                        if ext_lower == "svg" || ext_lower == "svgz" {
-                            svg_files.push(path);
-                        }
-                    }
-                }
-            }
-        }
-
-        // Natural sort
-        svg_files.sort_by(|a, b| {
-            let a_name = a.file_name().unwrap_or_default().to_string_lossy();
-            let b_name = b.file_name().unwrap_or_default().to_string_lossy();
-            natord::compare(&a_name, &b_name)
-        });
+        let svg_files = svg_files_in(dir);
 
         // Find current file index
         let canonical = file_path.canonicalize().ok();
@@ -56,10 +173,150 @@ impl FileNavigator {
             })
             .unwrap_or(0);
 
-        self.files = svg_files;
+        self.replace_files(svg_files);
+        self.dir = Some(dir.to_path_buf());
     }
 
-    pub fn next(&mut self) -> Option<&Path> {
+    /// Navigate the SVG files inside `dir`, starting at the first.
+    pub fn scan_dir_direct(&mut self, dir: &Path) {
+        self.set_files(svg_files_in(dir));
+        self.dir = Some(dir.to_path_buf());
+    }
+
+    /// Navigate exactly `files`, in the given order, starting at the first.
+    pub fn set_files(&mut self, files: Vec<PathBuf>) {
+        self.current_index = 0;
+        self.archive = None;
+        self.dir = None;
+        self.replace_files(files);
+    }
+
+    /// The directory being navigated, unless the files were listed
+    /// explicitly or are in an archive.
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// Take in the directory's files as listed again, in order, keeping the
+    /// filter and the current file. If the current file is gone, the next
+    /// one still there is current instead.
+    pub fn update_files(&mut self, files: Vec<PathBuf>) -> FileChanges {
+        let before: HashSet<&PathBuf> = self.all_files.iter().collect();
+        let after: HashSet<&PathBuf> = files.iter().collect();
+        let changes = FileChanges {
+            added: files.iter().filter(|p| !before.contains(p)).count(),
+            removed: self.all_files.iter().filter(|p| !after.contains(p)).count(),
+        };
+        if changes.is_empty() {
+            return changes;
+        }
+        let current = self
+            .files
+            .get(self.current_index..)
+            .unwrap_or_default()
+            .iter()
+            .find(|p| after.contains(p))
+            .cloned();
+        if self
+            .unmatched_current
+            .as_ref()
+            .is_some_and(|p| !after.contains(p))
+        {
+            self.unmatched_current = None;
+        }
+        self.all_files = files;
+        self.files = self.filtered();
+        self.current_index = current
+            .and_then(|current| self.files.iter().position(|p| *p == current))
+            .unwrap_or(0);
+        changes
+    }
+
+    /// Navigate a new list of files, unfiltered.
+    fn replace_files(&mut self, files: Vec<PathBuf>) {
+        self.all_files = files.clone();
+        self.files = files;
+        self.filter = None;
+        self.unmatched_current = None;
+    }
+
+    /// Navigate only the files matching `filter` (see [`matches_filter`]), or
+    /// every file again for `None` or a blank filter. The current file stays
+    /// current if it matches; otherwise the first match is. Returns whether
+    /// the current file changed, and so needs loading.
+    pub fn set_filter(&mut self, filter: Option<String>) -> bool {
+        let filter = filter.filter(|f| !f.trim().is_empty());
+        if filter == self.filter {
+            return false;
+        }
+        let current = self
+            .current()
+            .map(Path::to_path_buf)
+            .or_else(|| self.unmatched_current.take());
+        self.filter = filter;
+        self.files = self.filtered();
+        let kept = current
+            .as_ref()
+            .and_then(|current| self.files.iter().position(|p| p == current));
+        self.current_index = kept.unwrap_or(0);
+        if self.files.is_empty() {
+            self.unmatched_current = current;
+        }
+        kept.is_none() && !self.files.is_empty()
+    }
+
+    /// Every file that the filter matches.
+    fn filtered(&self) -> Vec<PathBuf> {
+        match &self.filter {
+            Some(filter) => self
+                .all_files
+                .iter()
+                .filter(|path| matches_filter(path, filter))
+                .cloned()
+                .collect(),
+            None => self.all_files.clone(),
+        }
+    }
+
+    /// Stop navigating `path`, as when it's been deleted. If it was current,
+    /// the file after it is now, or the first when it was the last.
+    pub fn remove(&mut self, path: &Path) {
+        self.all_files.retain(|p| p != path);
+        if self.unmatched_current.as_deref() == Some(path) {
+            self.unmatched_current = None;
+        }
+        let Some(index) = self.files.iter().position(|p| p == path) else {
+            return;
+        };
+        self.files.remove(index);
+        if index < self.current_index {
+            self.current_index -= 1;
+        }
+        if self.current_index >= self.files.len() {
+            self.current_index = 0;
+        }
+    }
+
+    /// The filter narrowing navigation, if any.
+    pub fn filter(&self) -> Option<&str> {
+        self.filter.as_deref()
+    }
+
+    /// Navigate the SVG entries of the zip archive at `zip`, flattened out of
+    /// their directories in natural order, starting at the first.
+    pub fn scan_archive(&mut self, zip: &Path) -> Result<()> {
+        self.set_files(archive::svg_paths(zip)?);
+        self.archive = Some(zip.to_path_buf());
+        Ok(())
+    }
+
+    /// The archive being navigated, if the files are entries inside one.
+    pub fn archive(&self) -> Option<&Path> {
+        self.archive.as_deref()
+    }
+
+    /// Advance to the next file, wrapping at the end.
+    pub fn next_file(&mut self) -> Option<&Path> {
         if self.files.is_empty() {
             return None;
         }
@@ -67,7 +324,8 @@ impl FileNavigator {
         Some(&self.files[self.current_index])
     }
 
-    pub fn prev(&mut self) -> Option<&Path> {
+    /// Go back to the previous file, wrapping at the start.
+    pub fn prev_file(&mut self) -> Option<&Path> {
         if self.files.is_empty() {
             return None;
         }
@@ -79,22 +337,48 @@ impl FileNavigator {
         Some(&self.files[self.current_index])
     }
 
-    #[allow(dead_code)]
+    /// Go to the file at `index`, if there is one. Unlike stepping, jumping
+    /// past either end doesn't wrap.
+    pub fn jump_to(&mut self, index: usize) -> Option<&Path> {
+        let path = self.files.get(index)?;
+        self.current_index = index;
+        Some(path)
+    }
+
+    /// Go to the first file.
+    pub fn first(&mut self) -> Option<&Path> {
+        self.jump_to(0)
+    }
+
+    /// Go to the last file.
+    pub fn last(&mut self) -> Option<&Path> {
+        self.jump_to(self.files.len().checked_sub(1)?)
+    }
+
     pub fn current(&self) -> Option<&Path> {
         self.files.get(self.current_index).map(|p| p.as_path())
     }
 
     pub fn position_display(&self) -> String {
-        if self.files.is_empty() {
-            String::new()
-        } else {
-            format!("{}/{}", self.current_index + 1, self.files.len())
+        match (self.files.is_empty(), self.filter.is_some()) {
+            (true, false) => String::new(),
+            (true, true) => "No matches (filtered)".into(),
+            (false, false) => format!("{}/{}", self.current_index + 1, self.files.len()),
+            (false, true) => format!("{}/{} (filtered)", self.current_index + 1, self.files.len()),
         }
     }
 
     pub fn file_count(&self) -> usize {
         self.files.len()
     }
+
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.current_index
+    }
 }
 
 #[cfg(test)]
@@ -113,8 +397,8 @@ mod tests {
     #[test]
     fn test_next_prev_empty() {
         let mut nav = FileNavigator::new();
-        assert!(nav.next().is_none());
-        assert!(nav.prev().is_none());
+        assert!(nav.next_file().is_none());
+        assert!(nav.prev_file().is_none());
     }
 
     #[test]
@@ -126,7 +410,41 @@ mod tests {
             PathBuf::from("/c.svg"),
         ];
         nav.current_index = 2;
-        nav.next();
+        nav.next_file();
+        assert_eq!(nav.current_index, 0);
+    }
+
+    #[test]
+    fn test_jump_to() {
+        let mut nav = FileNavigator::new();
+        nav.files = vec![PathBuf::from("/a.svg"), PathBuf::from("/b.svg")];
+        assert_eq!(nav.jump_to(1), Some(Path::new("/b.svg")));
+        assert_eq!(nav.current_index, 1);
+        // Out of range leaves the current file alone
+        assert_eq!(nav.jump_to(2), None);
+        assert_eq!(nav.jump_to(usize::MAX), None);
+        assert_eq!(nav.current_index, 1);
+    }
+
+    #[test]
+    fn test_first_last() {
+        let mut nav = FileNavigator::new();
+        assert!(nav.first().is_none());
+        assert!(nav.last().is_none());
+        assert!(nav.jump_to(0).is_none());
+        assert_eq!(nav.current_index, 0);
+
+        nav.files = vec![
+            PathBuf::from("/a.svg"),
+            PathBuf::from("/b.svg"),
+            PathBuf::from("/c.svg"),
+        ];
+        assert_eq!(nav.last(), Some(Path::new("/c.svg")));
+        assert_eq!(nav.current_index, 2);
+        // Stepping on from the last wraps as usual
+        assert_eq!(nav.next_file(), Some(Path::new("/a.svg")));
+        nav.current_index = 1;
+        assert_eq!(nav.first(), Some(Path::new("/a.svg")));
         assert_eq!(nav.current_index, 0);
     }
 
@@ -139,7 +457,7 @@ mod tests {
             PathBuf::from("/c.svg"),
         ];
         nav.current_index = 0;
-        nav.prev();
+        nav.prev_file();
         assert_eq!(nav.current_index, 2);
     }
 
@@ -153,6 +471,197 @@ mod tests {
         assert_eq!(nav.position_display(), "2/2");
     }
 
+    #[test]
+    fn test_remove() {
+        let mut nav = FileNavigator::new();
+        nav.set_files(vec![
+            PathBuf::from("/a.svg"),
+            PathBuf::from("/b.svg"),
+            PathBuf::from("/c.svg"),
+        ]);
+        // The file after the current one takes its place
+        nav.jump_to(1);
+        nav.remove(Path::new("/b.svg"));
+        assert_eq!(nav.current(), Some(Path::new("/c.svg")));
+        assert_eq!(nav.position_display(), "2/2");
+        // Removing the last wraps to the first
+        nav.remove(Path::new("/c.svg"));
+        assert_eq!(nav.current(), Some(Path::new("/a.svg")));
+        // A file before the current one keeps the current file current
+        nav.set_files(vec![PathBuf::from("/a.svg"), PathBuf::from("/b.svg")]);
+        nav.jump_to(1);
+        nav.remove(Path::new("/a.svg"));
+        assert_eq!(nav.current(), Some(Path::new("/b.svg")));
+        // Unknown files are ignored, and the list can run out
+        nav.remove(Path::new("/z.svg"));
+        assert_eq!(nav.file_count(), 1);
+        nav.remove(Path::new("/b.svg"));
+        assert_eq!(nav.current(), None);
+        assert_eq!(nav.position_display(), "");
+        // Nor does it come back when a filter is cleared
+        nav.set_files(vec![PathBuf::from("/a.svg"), PathBuf::from("/b.svg")]);
+        nav.set_filter(Some("a".into()));
+        nav.remove(Path::new("/a.svg"));
+        nav.set_filter(None);
+        assert_eq!(nav.files(), [PathBuf::from("/b.svg")]);
+    }
+
+    #[test]
+    fn test_update_files_keeps_current() {
+        let mut nav = FileNavigator::new();
+        nav.set_files(vec![
+            PathBuf::from("/icons/b.svg"),
+            PathBuf::from("/icons/d.svg"),
+            PathBuf::from("/icons/f.svg"),
+        ]);
+        nav.jump_to(1);
+
+        // New files go where they sort; the current file stays current
+        let changes = nav.update_files(vec![
+            PathBuf::from("/icons/a.svg"),
+            PathBuf::from("/icons/b.svg"),
+            PathBuf::from("/icons/c.svg"),
+            PathBuf::from("/icons/d.svg"),
+            PathBuf::from("/icons/f.svg"),
+        ]);
+        assert_eq!(changes.describe().as_deref(), Some("2 files added"));
+        assert_eq!(nav.current(), Some(Path::new("/icons/d.svg")));
+        assert_eq!(nav.position_display(), "4/5");
+
+        // The current file went, so the one after it is current
+        let changes = nav.update_files(vec![
+            PathBuf::from("/icons/a.svg"),
+            PathBuf::from("/icons/e.svg"),
+            PathBuf::from("/icons/f.svg"),
+        ]);
+        assert_eq!(
+            changes.describe().as_deref(),
+            Some("1 file added, 3 removed")
+        );
+        assert_eq!(nav.current(), Some(Path::new("/icons/f.svg")));
+
+        // An unchanged listing changes nothing, and the filter is kept
+        nav.set_filter(Some("f".into()));
+        let files = nav.all_files.clone();
+        assert!(nav.update_files(files).is_empty());
+        let changes = nav.update_files(vec![
+            PathBuf::from("/icons/f.svg"),
+            PathBuf::from("/icons/ff.svg"),
+        ]);
+        assert_eq!(
+            changes.describe().as_deref(),
+            Some("1 file added, 2 removed")
+        );
+        assert_eq!(nav.position_display(), "1/2 (filtered)");
+    }
+
+    #[test]
+    fn test_directory_is_known_only_when_scanned() {
+        let fixtures_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("assets")
+            .join("test_fixtures");
+        let mut nav = FileNavigator::new();
+        nav.scan_directory(&fixtures_dir.join("simple_rect.svg"));
+        assert_eq!(nav.dir(), Some(fixtures_dir.as_path()));
+        nav.set_files(vec![PathBuf::from("/a.svg")]);
+        assert_eq!(nav.dir(), None);
+        nav.scan_dir_direct(&fixtures_dir);
+        assert_eq!(nav.dir(), Some(fixtures_dir.as_path()));
+    }
+
+    #[test]
+    fn test_matches_filter() {
+        let path = Path::new("/icons/Arrow-Left.svg");
+        assert!(matches_filter(path, "arrow"));
+        assert!(matches_filter(path, "LEFT.svg"));
+        assert!(!matches_filter(path, "right"));
+        assert!(matches_filter(path, "arrow-*"));
+        assert!(matches_filter(path, "*-left"));
+        assert!(matches_filter(path, "arrow-?eft.svg"));
+        assert!(matches_filter(path, "a*w*t"));
+        assert!(!matches_filter(path, "left*"));
+        assert!(!matches_filter(path, "arrow-?"));
+        // Folders don't count
+        assert!(!matches_filter(path, "icons"));
+    }
+
+    #[test]
+    fn test_filter_keeps_or_moves_current() {
+        let mut nav = FileNavigator::new();
+        nav.set_files(vec![
+            PathBuf::from("/arrow-up.svg"),
+            PathBuf::from("/circle.svg"),
+            PathBuf::from("/arrow-down.svg"),
+            PathBuf::from("/square.svg"),
+        ]);
+        nav.jump_to(2);
+
+        // The current file matches, so it stays current
+        assert!(!nav.set_filter(Some("arrow-*".into())));
+        assert_eq!(nav.current(), Some(Path::new("/arrow-down.svg")));
+        assert_eq!(nav.position_display(), "2/2 (filtered)");
+        assert_eq!(nav.next_file(), Some(Path::new("/arrow-up.svg")));
+
+        // It doesn't, so the first match is current
+        assert!(nav.set_filter(Some("Square".into())));
+        assert_eq!(nav.current(), Some(Path::new("/square.svg")));
+        assert_eq!(nav.position_display(), "1/1 (filtered)");
+
+        // Nothing matches; clearing returns to the file still open
+        assert!(!nav.set_filter(Some("star".into())));
+        assert_eq!(nav.current(), None);
+        assert_eq!(nav.position_display(), "No matches (filtered)");
+        assert!(!nav.set_filter(Some("  ".into())));
+        assert_eq!(nav.filter(), None);
+        assert_eq!(nav.current(), Some(Path::new("/square.svg")));
+        assert_eq!(nav.position_display(), "4/4");
+
+        // A new list starts unfiltered
+        nav.set_filter(Some("arrow".into()));
+        nav.set_files(vec![PathBuf::from("/a.svg"), PathBuf::from("/b.svg")]);
+        assert_eq!(nav.filter(), None);
+        assert_eq!(nav.file_count(), 2);
+    }
+
+    #[test]
+    fn test_explicit_list_keeps_order() {
+        let mut nav = FileNavigator::new();
+        nav.set_files(vec![
+            PathBuf::from("/icons/z.svg"),
+            PathBuf::from("/logos/a.svg"),
+            PathBuf::from("/icons/b.svg"),
+        ]);
+        assert_eq!(nav.current(), Some(Path::new("/icons/z.svg")));
+        assert_eq!(nav.position_display(), "1/3");
+        assert_eq!(nav.next_file(), Some(Path::new("/logos/a.svg")));
+        assert_eq!(nav.next_file(), Some(Path::new("/icons/b.svg")));
+        assert_eq!(nav.next_file(), Some(Path::new("/icons/z.svg")));
+        assert_eq!(nav.prev_file(), Some(Path::new("/icons/b.svg")));
+    }
+
+    #[test]
+    fn test_archive_entries_navigate_in_order() {
+        let zip = archive::tests::temp_zip("navigator");
+        let mut nav = FileNavigator::new();
+        nav.scan_archive(&zip).unwrap();
+        assert_eq!(nav.archive(), Some(zip.as_path()));
+        assert_eq!(nav.position_display(), "1/3");
+        let entry = archive::entry_path(&zip, "solid/icon10.svg");
+        assert_eq!(
+            nav.next_file(),
+            Some(archive::entry_path(&zip, "solid/icon2.svg").as_path())
+        );
+        assert_eq!(nav.next_file(), Some(entry.as_path()));
+
+        // Opening an entry directly navigates its archive from there
+        let mut nav = FileNavigator::new();
+        nav.scan_directory(&entry);
+        assert_eq!(nav.position_display(), "3/3");
+        nav.set_files(vec![PathBuf::from("/a.svg")]);
+        assert_eq!(nav.archive(), None);
+        std::fs::remove_file(&zip).unwrap();
+    }
+
     #[test]
     fn test_scan_directory() {
         let fixtures_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
@@ -172,6 +681,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_scan_dir_direct() {
+        let fixtures_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("assets")
+            .join("test_fixtures");
+        let mut nav = FileNavigator::new();
+        nav.scan_dir_direct(&fixtures_dir);
+        assert_eq!(nav.files(), svg_files_in(&fixtures_dir));
+        assert_eq!(nav.current_index(), 0);
+
+        let empty = std::env::temp_dir().join("svg_viewer_test_empty_dir");
+        std::fs::create_dir_all(&empty).unwrap();
+        nav.scan_dir_direct(&empty);
+        assert!(nav.current().is_none());
+        std::fs::remove_dir_all(&empty).ok();
+    }
+
     #[test]
     fn test_natural_sort_order() {
         let mut nav = FileNavigator::new();
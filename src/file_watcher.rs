@@ -0,0 +1,279 @@
+//! Polling for changes to the open file, so edits made in another program show up,
+//! and to its folder, so files added or removed there can be navigated.
+//!
+//! Editors often save in several writes (truncate, write, rename), so a change is
+//! only reported once the file has looked the same for [`SETTLE`].
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::file_navigator;
+
+/// How often the file's metadata is checked.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// How long a changed file must stay unchanged before it's reported.
+pub const SETTLE: Duration = Duration::from_millis(300);
+/// How often a folder's modification time is checked.
+pub const DIR_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What identifies a version of the file: modification time and size. `None` while
+/// the file can't be read, e.g. between an editor's delete and its rename.
+pub type Stamp = Option<(SystemTime, u64)>;
+
+pub fn stamp(path: &Path) -> Stamp {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Turns a series of observed stamps into change reports, one per burst of writes.
+#[derive(Debug)]
+pub struct Debouncer {
+    /// The version last reported (or seen first)
+    reported: Stamp,
+    /// The latest differing version and when it was first seen
+    pending: Option<(Stamp, Instant)>,
+    settle: Duration,
+}
+
+impl Debouncer {
+    pub fn new(initial: Stamp, settle: Duration) -> Self {
+        Self {
+            reported: initial,
+            pending: None,
+            settle,
+        }
+    }
+
+    /// Record the stamp seen at `now`. True when a change has settled and should
+    /// be reported.
+    pub fn observe(&mut self, stamp: Stamp, now: Instant) -> bool {
+        if stamp == self.reported {
+            self.pending = None;
+            return false;
+        }
+        match self.pending {
+            Some((pending, since)) if pending == stamp => {
+                // A missing file isn't reported; it usually reappears shortly
+                if stamp.is_some() && now.duration_since(since) >= self.settle {
+                    self.reported = stamp;
+                    self.pending = None;
+                    return true;
+                }
+                false
+            }
+            _ => {
+                self.pending = Some((stamp, now));
+                false
+            }
+        }
+    }
+}
+
+/// Watches one file from a background thread until dropped.
+pub struct FileWatcher {
+    path: PathBuf,
+    changes: mpsc::Receiver<()>,
+    stop: Arc<AtomicBool>,
+}
+
+impl FileWatcher {
+    /// Start watching `path`, calling `notify` from the watcher thread whenever
+    /// a change is reported (e.g. to wake the UI).
+    pub fn watch(path: &Path, notify: impl Fn() + Send + 'static) -> Self {
+        Self::with_timing(path, POLL_INTERVAL, SETTLE, notify)
+    }
+
+    pub fn with_timing(
+        path: &Path,
+        poll: Duration,
+        settle: Duration,
+        notify: impl Fn() + Send + 'static,
+    ) -> Self {
+        let (tx, changes) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let thread_path = path.to_path_buf();
+        std::thread::spawn(move || {
+            let mut debouncer = Debouncer::new(stamp(&thread_path), settle);
+            while !thread_stop.load(Ordering::Relaxed) {
+                std::thread::sleep(poll);
+                if debouncer.observe(stamp(&thread_path), Instant::now()) {
+                    if tx.send(()).is_err() {
+                        break;
+                    }
+                    notify();
+                }
+            }
+        });
+        Self {
+            path: path.to_path_buf(),
+            changes,
+            stop,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the file changed since the last call.
+    pub fn changed(&self) -> bool {
+        self.changes.try_iter().count() > 0
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Watches a folder's SVG files from a background thread until dropped. Only
+/// the folder's modification time is checked each poll, which changes as
+/// files are added, removed or renamed; the files are listed only then.
+pub struct DirectoryWatcher {
+    dir: PathBuf,
+    listings: mpsc::Receiver<Vec<PathBuf>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl DirectoryWatcher {
+    /// Start watching `dir`, calling `notify` from the watcher thread whenever
+    /// it's been listed again (e.g. to wake the UI).
+    pub fn watch(dir: &Path, notify: impl Fn() + Send + 'static) -> Self {
+        Self::with_interval(dir, DIR_POLL_INTERVAL, notify)
+    }
+
+    pub fn with_interval(dir: &Path, poll: Duration, notify: impl Fn() + Send + 'static) -> Self {
+        let (tx, listings) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let thread_dir = dir.to_path_buf();
+        std::thread::spawn(move || {
+            let modified = |dir: &Path| std::fs::metadata(dir).and_then(|m| m.modified()).ok();
+            let mut last = modified(&thread_dir);
+            while !thread_stop.load(Ordering::Relaxed) {
+                std::thread::sleep(poll);
+                let now = modified(&thread_dir);
+                if now == last {
+                    continue;
+                }
+                last = now;
+                if tx.send(file_navigator::svg_files_in(&thread_dir)).is_err() {
+                    break;
+                }
+                notify();
+            }
+        });
+        Self {
+            dir: dir.to_path_buf(),
+            listings,
+            stop,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The folder's SVG files, if they may have changed since the last call.
+    pub fn rescanned(&self) -> Option<Vec<PathBuf>> {
+        self.listings.try_iter().last()
+    }
+}
+
+impl Drop for DirectoryWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(secs: u64, len: u64) -> Stamp {
+        Some((SystemTime::UNIX_EPOCH + Duration::from_secs(secs), len))
+    }
+
+    #[test]
+    fn test_burst_of_writes_reports_once() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut debouncer = Debouncer::new(version(1, 10), Duration::from_millis(300));
+
+        assert!(!debouncer.observe(version(1, 10), at(0)));
+        // Truncate, rewrite, then the final write, 100 ms apart
+        assert!(!debouncer.observe(version(2, 0), at(100)));
+        assert!(!debouncer.observe(version(2, 5), at(200)));
+        assert!(!debouncer.observe(version(3, 12), at(300)));
+        assert!(!debouncer.observe(version(3, 12), at(500)));
+        assert!(debouncer.observe(version(3, 12), at(600)));
+        // Nothing more until the next change
+        assert!(!debouncer.observe(version(3, 12), at(2000)));
+        assert!(!debouncer.observe(version(4, 12), at(2100)));
+        assert!(debouncer.observe(version(4, 12), at(2400)));
+    }
+
+    #[test]
+    fn test_missing_file_is_not_reported() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut debouncer = Debouncer::new(version(1, 10), Duration::from_millis(300));
+        assert!(!debouncer.observe(None, at(0)));
+        assert!(!debouncer.observe(None, at(1000)));
+        // Replaced by an identical file: nothing to reload
+        assert!(!debouncer.observe(version(1, 10), at(1100)));
+        assert!(!debouncer.observe(version(5, 10), at(1200)));
+        assert!(debouncer.observe(version(5, 10), at(1500)));
+    }
+
+    #[test]
+    fn test_watcher_sees_rewrite() {
+        let path = std::env::temp_dir().join("svg_viewer_test_watcher.svg");
+        std::fs::write(&path, "<svg/>").unwrap();
+        let watcher = FileWatcher::with_timing(
+            &path,
+            Duration::from_millis(10),
+            Duration::from_millis(30),
+            || {},
+        );
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!watcher.changed());
+
+        std::fs::write(&path, "<svg width=\"2\"/>").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !watcher.changed() {
+            assert!(Instant::now() < deadline, "change not reported");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        drop(watcher);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_directory_watcher_lists_added_files() {
+        let dir = std::env::temp_dir().join("svg_viewer_test_dir_watcher");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.svg"), "<svg/>").unwrap();
+        let watcher = DirectoryWatcher::with_interval(&dir, Duration::from_millis(10), || {});
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(watcher.rescanned(), None);
+
+        std::fs::write(dir.join("b.svg"), "<svg/>").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let files = loop {
+            if let Some(files) = watcher.rescanned() {
+                break files;
+            }
+            assert!(Instant::now() < deadline, "new file not listed");
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(files, [dir.join("a.svg"), dir.join("b.svg")]);
+        drop(watcher);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
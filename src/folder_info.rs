@@ -0,0 +1,417 @@
+//! Summary statistics for a folder of SVG files, for `--folder-info` and the
+//! Folder summary window.
+//!
+//! Sizes come from the root `<svg>` start tag alone, so scanning thousands of
+//! icons doesn't parse any of them. A file counts as unparseable when it has no
+//! readable `<svg>` root; errors deeper in the document aren't noticed.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+
+use serde::Serialize;
+
+use crate::archive;
+use crate::error::{Result, SvgError};
+use crate::svg_document::{self, format_size, root_start_tag, start_tag_attributes};
+
+/// How much of an uncompressed file is read looking for the root element.
+pub const HEADER_BYTES: usize = 16 * 1024;
+
+/// The document size declared by the root `<svg>` tag in `text`, resolved the
+/// way usvg does: `width`/`height`, else the viewBox, else 100×100. `None`
+/// when the declared size is zero or uses units that need a full parse (`%`, `em`).
+pub fn header_size(text: &str) -> Result<Option<(f32, f32)>> {
+    let tag = root_start_tag(text)
+        .ok_or_else(|| SvgError::Parse("no root element in the first 16 KB".into()))?;
+    let name = tag
+        .split(|c: char| c.is_whitespace() || c == '/')
+        .next()
+        .unwrap_or_default();
+    if name.rsplit(':').next() != Some("svg") {
+        return Err(SvgError::Parse(format!(
+            "root element is <{name}>, not <svg>"
+        )));
+    }
+
+    let attributes = start_tag_attributes(&tag[name.len()..]);
+    let attribute = |key: &str| {
+        attributes
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v.trim())
+    };
+    let view_box = attribute("viewBox").and_then(|v| {
+        let numbers: Vec<f32> = v
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|s| !s.is_empty())
+            .map(str::parse)
+            .collect::<std::result::Result<_, _>>()
+            .ok()?;
+        match numbers[..] {
+            [_, _, w, h] if w > 0.0 && h > 0.0 => Some((w, h)),
+            _ => None,
+        }
+    });
+    let length = |key: &str| match attribute(key) {
+        None => Ok(None),
+        Some(value) => parse_length(value).map(Some).ok_or(()),
+    };
+    let (Ok(width), Ok(height)) = (length("width"), length("height")) else {
+        return Ok(None);
+    };
+
+    let size = match (width, height, view_box) {
+        (Some(w), Some(h), _) => (w, h),
+        (Some(w), None, Some((vw, vh))) => (w, w * vh / vw),
+        (None, Some(h), Some((vw, vh))) => (h * vw / vh, h),
+        (None, None, Some(view_box)) => view_box,
+        (w, h, None) => (w.unwrap_or(100.0), h.unwrap_or(100.0)),
+    };
+    Ok((size.0 > 0.0 && size.1 > 0.0).then_some(size))
+}
+
+/// An absolute SVG length in px; `None` for relative units.
+pub(crate) fn parse_length(value: &str) -> Option<f32> {
+    let split = value
+        .find(|c: char| c.is_ascii_alphabetic() || c == '%')
+        .unwrap_or(value.len());
+    let number: f32 = value[..split].trim().parse().ok()?;
+    let px_per_unit = match value[split..].trim() {
+        "" | "px" => 1.0,
+        "pt" => 4.0 / 3.0,
+        "pc" => 16.0,
+        "mm" => 96.0 / 25.4,
+        "cm" => 96.0 / 2.54,
+        "in" => 96.0,
+        _ => return None,
+    };
+    Some(number * px_per_unit).filter(|v| v.is_finite())
+}
+
+/// The file size and header size of `path`. Only an SVGZ file, or an entry
+/// inside an archive, is read in full.
+pub fn scan_file(path: &Path) -> Result<(u64, Option<(f32, f32)>)> {
+    if archive::split_path(path).is_some() {
+        let data = archive::read_path(path)?;
+        return Ok((data.len() as u64, header_size(&head_text(data)?)?));
+    }
+    let mut file = std::fs::File::open(path)?;
+    let bytes = file.metadata()?.len();
+    let mut head = Vec::with_capacity(HEADER_BYTES);
+    (&mut file)
+        .take(HEADER_BYTES as u64)
+        .read_to_end(&mut head)?;
+    if svg_document::is_gzip(&head) {
+        file.read_to_end(&mut head)?;
+    }
+    Ok((bytes, header_size(&head_text(head)?)?))
+}
+
+/// The start of a file's text, decompressing SVGZ data whole.
+fn head_text(mut data: Vec<u8>) -> Result<String> {
+    if svg_document::is_gzip(&data) {
+        data = usvg::decompress_svgz(&data).map_err(|e| SvgError::Parse(e.to_string()))?;
+    }
+    data.truncate(HEADER_BYTES);
+    Ok(String::from_utf8_lossy(&data).into_owned())
+}
+
+/// How many files share one document size.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SizeCount {
+    pub width: u32,
+    pub height: u32,
+    pub count: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Unparseable {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// Totals for a set of files.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FolderStats {
+    pub files: usize,
+    pub total_bytes: u64,
+    /// Files by size rounded to whole pixels
+    sizes: BTreeMap<(u32, u32), usize>,
+    /// Readable files whose size can't be known without a full parse
+    pub unsized_files: usize,
+    pub unparseable: Vec<Unparseable>,
+}
+
+impl FolderStats {
+    /// Add one file's scan result.
+    pub fn record(&mut self, path: &Path, bytes: u64, header: Result<Option<(f32, f32)>>) {
+        self.files += 1;
+        self.total_bytes += bytes;
+        match header {
+            Ok(Some((w, h))) => {
+                let key = (w.round().max(1.0) as u32, h.round().max(1.0) as u32);
+                *self.sizes.entry(key).or_default() += 1;
+            }
+            Ok(None) => self.unsized_files += 1,
+            Err(e) => self.unparseable.push(Unparseable {
+                path: path.to_path_buf(),
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    /// The size distribution, most common first, ties smallest first.
+    pub fn breakdown(&self) -> Vec<SizeCount> {
+        let mut rows: Vec<SizeCount> = self
+            .sizes
+            .iter()
+            .map(|(&(width, height), &count)| SizeCount {
+                width,
+                height,
+                count,
+            })
+            .collect();
+        rows.sort_by_key(|row| std::cmp::Reverse(row.count));
+        rows
+    }
+
+    /// A plain-text report for the terminal.
+    pub fn table(&self) -> String {
+        let mut out = format!(
+            "Files:        {}\nTotal size:   {}\nUnparseable:  {}\n",
+            self.files,
+            format_size(self.total_bytes),
+            self.unparseable.len()
+        );
+        let rows = self.breakdown();
+        if !rows.is_empty() || self.unsized_files > 0 {
+            out.push_str(&format!("\n{:<16}{:>6}\n", "Size", "Files"));
+            for row in &rows {
+                let size = format!("{}×{}", row.width, row.height);
+                out.push_str(&format!("{size:<16}{:>6}\n", row.count));
+            }
+            if self.unsized_files > 0 {
+                out.push_str(&format!("{:<16}{:>6}\n", "unknown", self.unsized_files));
+            }
+        }
+        if !self.unparseable.is_empty() {
+            out.push_str("\nUnparseable files:\n");
+            for file in &self.unparseable {
+                out.push_str(&format!("  {}: {}\n", file.path.display(), file.error));
+            }
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        let report = serde_json::json!({
+            "files": self.files,
+            "total_bytes": self.total_bytes,
+            "sizes": self.breakdown(),
+            "unsized": self.unsized_files,
+            "unparseable": self.unparseable,
+        });
+        serde_json::to_string_pretty(&report).map_err(|e| SvgError::Export(e.to_string()))
+    }
+}
+
+/// Scan `files` in order, calling `progress` with the number done after each.
+/// Returns `None` if `cancel` was set before the scan finished.
+pub fn scan(
+    files: &[PathBuf],
+    cancel: &AtomicBool,
+    mut progress: impl FnMut(usize),
+) -> Option<FolderStats> {
+    let mut stats = FolderStats::default();
+    for (i, path) in files.iter().enumerate() {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        match scan_file(path) {
+            Ok((bytes, size)) => stats.record(path, bytes, Ok(size)),
+            Err(e) => stats.record(path, 0, Err(e)),
+        }
+        progress(i + 1);
+    }
+    Some(stats)
+}
+
+/// A [`scan`] running on a background thread. Dropping it cancels the scan.
+pub struct FolderScan {
+    total: usize,
+    done: Arc<AtomicUsize>,
+    cancel: Arc<AtomicBool>,
+    result: mpsc::Receiver<FolderStats>,
+}
+
+impl FolderScan {
+    /// Start scanning `files`, calling `notify` from the scan thread as it
+    /// progresses (e.g. to wake the UI).
+    pub fn start(files: Vec<PathBuf>, notify: impl Fn() + Send + 'static) -> Self {
+        let (tx, result) = mpsc::channel();
+        let done = Arc::new(AtomicUsize::new(0));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let total = files.len();
+        let (thread_done, thread_cancel) = (Arc::clone(&done), Arc::clone(&cancel));
+        std::thread::spawn(move || {
+            let stats = scan(&files, &thread_cancel, |n| {
+                thread_done.store(n, Ordering::Relaxed);
+                notify();
+            });
+            if let Some(stats) = stats {
+                let _ = tx.send(stats);
+                notify();
+            }
+        });
+        Self {
+            total,
+            done,
+            cancel,
+            result,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Files scanned so far.
+    pub fn done(&self) -> usize {
+        self.done.load(Ordering::Relaxed)
+    }
+
+    /// The stats, once the scan has finished.
+    pub fn finished(&self) -> Option<FolderStats> {
+        self.result.try_recv().ok()
+    }
+}
+
+impl Drop for FolderScan {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_path(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("assets")
+            .join("test_fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn test_header_size() {
+        let size = |svg: &str| header_size(svg).unwrap();
+        assert_eq!(size(r#"<svg width="16" height="24">"#), Some((16.0, 24.0)));
+        assert_eq!(
+            size(r#"<?xml version="1.0"?><!-- a > b --><svg viewBox="0 0 32 32"/>"#),
+            Some((32.0, 32.0))
+        );
+        assert_eq!(
+            size(r#"<svg width="64" viewBox="0, 0, 32, 16">"#),
+            Some((64.0, 32.0))
+        );
+        assert_eq!(
+            size(r#"<svg width="1in" height="72pt">"#),
+            Some((96.0, 96.0))
+        );
+        assert_eq!(size("<svg>"), Some((100.0, 100.0)));
+        assert_eq!(
+            size(r#"<svg:svg width='8' height='8' title="a>b">"#),
+            Some((8.0, 8.0))
+        );
+        assert_eq!(size(r#"<svg width="100%" height="50%">"#), None);
+        assert_eq!(size(r#"<svg width="0" height="10">"#), None);
+        assert_eq!(
+            size(r#"<!DOCTYPE svg [<!ENTITY a "<b>">]><svg width="4" height="4">"#),
+            Some((4.0, 4.0))
+        );
+
+        assert!(header_size("<html><svg width=\"1\" height=\"1\"/></html>").is_err());
+        assert!(header_size("not xml at all").is_err());
+    }
+
+    #[test]
+    fn test_scan_file_fixtures() {
+        let (bytes, size) = scan_file(&fixture_path("simple_rect.svg")).unwrap();
+        assert!(bytes > 0);
+        assert_eq!(size, Some((200.0, 150.0)));
+        assert!(scan_file(&fixture_path("missing.svg")).is_err());
+    }
+
+    #[test]
+    fn test_aggregate_synthetic_set() {
+        let mut stats = FolderStats::default();
+        let icon = |n: usize| PathBuf::from(format!("icon{n}.svg"));
+        for n in 0..5 {
+            stats.record(&icon(n), 100, Ok(Some((16.0, 16.0))));
+        }
+        for n in 5..8 {
+            stats.record(&icon(n), 200, Ok(Some((24.0, 24.0))));
+        }
+        stats.record(&icon(8), 300, Ok(Some((23.6, 24.2))));
+        stats.record(&icon(9), 50, Ok(None));
+        stats.record(
+            &icon(10),
+            10,
+            Err(SvgError::Parse("root element is <html>".into())),
+        );
+
+        assert_eq!(stats.files, 11);
+        assert_eq!(stats.total_bytes, 500 + 600 + 300 + 50 + 10);
+        assert_eq!(stats.unsized_files, 1);
+        assert_eq!(stats.unparseable.len(), 1);
+        assert_eq!(stats.unparseable[0].path, icon(10));
+        let rows: Vec<_> = stats
+            .breakdown()
+            .iter()
+            .map(|r| (r.width, r.height, r.count))
+            .collect();
+        assert_eq!(rows, [(16, 16, 5), (24, 24, 4)]);
+
+        let table = stats.table();
+        assert!(table.contains("Files:        11"));
+        assert!(table.contains("icon10.svg"));
+        let json: serde_json::Value = serde_json::from_str(&stats.to_json().unwrap()).unwrap();
+        assert_eq!(json["sizes"][0]["count"], 5);
+        assert_eq!(json["unparseable"][0]["path"], "icon10.svg");
+    }
+
+    #[test]
+    fn test_scan_reports_progress_and_cancels() {
+        let files = vec![
+            fixture_path("simple_rect.svg"),
+            fixture_path("gradient.svg"),
+            fixture_path("missing.svg"),
+        ];
+        let mut seen = Vec::new();
+        let stats = scan(&files, &AtomicBool::new(false), |n| seen.push(n)).unwrap();
+        assert_eq!(seen, [1, 2, 3]);
+        assert_eq!(stats.files, 3);
+        assert_eq!(stats.unparseable.len(), 1);
+
+        assert_eq!(scan(&files, &AtomicBool::new(true), |_| {}), None);
+    }
+
+    #[test]
+    fn test_background_scan_finishes() {
+        let scan = FolderScan::start(vec![fixture_path("simple_rect.svg")], || {});
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        let stats = loop {
+            if let Some(stats) = scan.finished() {
+                break stats;
+            }
+            assert!(std::time::Instant::now() < deadline, "scan didn't finish");
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        };
+        assert_eq!(stats.files, 1);
+        assert_eq!(scan.done(), scan.total());
+    }
+}
@@ -0,0 +1,286 @@
+//! Fonts for text in documents: the system's, plus a bundled last resort so
+//! text never silently disappears when a document asks for a font that isn't
+//! installed, or when there are no fonts installed at all.
+//!
+//! Every document is parsed against one shared font database, loaded on first
+//! use: the system's fonts, any extra font directories from [`configure`], and
+//! the fallback. Text whose requested family wasn't found is listed by
+//! [`substituted_text`], so the viewer can mark where a substitute was drawn.
+
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+use usvg::fontdb::{self, Database, Family, Query};
+use usvg::{Font, FontFamily, Options, Tree};
+
+/// Family of the bundled fallback font (Ubuntu Light).
+#[cfg(feature = "bundled-font")]
+pub const FALLBACK_FAMILY: &str = "Ubuntu";
+
+/// Font choices from the command line and settings.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FontConfig {
+    /// Directories of extra font files, loaded after the system's fonts
+    pub font_dirs: Vec<PathBuf>,
+    /// Family for text that doesn't name one, instead of usvg's Times New Roman
+    pub default_family: Option<String>,
+}
+
+static CONFIG: OnceLock<FontConfig> = OnceLock::new();
+
+/// Set the font configuration for the rest of the run. Only the first call
+/// counts, and only before the first document is parsed; returns whether it
+/// was taken.
+pub fn configure(config: FontConfig) -> bool {
+    CONFIG.set(config).is_ok()
+}
+
+fn config() -> &'static FontConfig {
+    CONFIG.get_or_init(FontConfig::default)
+}
+
+/// Start loading the shared font database in the background, so the first
+/// document doesn't wait for the system's fonts to be scanned.
+pub fn preload() {
+    std::thread::spawn(fontdb);
+}
+
+/// The shared font database: system fonts, the configured font directories,
+/// then the bundled fallback.
+pub fn fontdb() -> Arc<Database> {
+    static FONTDB: OnceLock<Arc<Database>> = OnceLock::new();
+    FONTDB
+        .get_or_init(|| {
+            let db = load_database(config());
+            log::debug!("Font database has {} faces", db.len());
+            Arc::new(db)
+        })
+        .clone()
+}
+
+fn load_database(config: &FontConfig) -> Database {
+    let mut db = Database::new();
+    db.load_system_fonts();
+    for dir in &config.font_dirs {
+        if dir.is_dir() {
+            db.load_fonts_dir(dir);
+        } else {
+            log::warn!("Font directory {} not found", dir.display());
+        }
+    }
+    #[cfg(feature = "bundled-font")]
+    db.load_font_source(fontdb::Source::Binary(Arc::new(
+        epaint_default_fonts::UBUNTU_LIGHT,
+    )));
+    db
+}
+
+/// Parsing options using the shared font database and the configured default
+/// family, falling back to the bundled font when nothing matches what the
+/// text asks for.
+pub fn usvg_options() -> Options<'static> {
+    options_with(fontdb(), config())
+}
+
+fn options_with(db: Arc<Database>, config: &FontConfig) -> Options<'static> {
+    let mut options = Options {
+        fontdb: db,
+        ..Options::default()
+    };
+    if let Some(family) = &config.default_family {
+        options.font_family = family.clone();
+    }
+    let select = usvg::FontResolver::default_font_selector();
+    options.font_resolver.select_font =
+        Box::new(move |font, db| select(font, db).or_else(|| last_resort(font, db)));
+    options
+}
+
+/// The bundled font, or without it whatever face the database has first.
+fn last_resort(font: &Font, db: &Database) -> Option<fontdb::ID> {
+    #[cfg(feature = "bundled-font")]
+    {
+        let query = Query {
+            families: &[Family::Name(FALLBACK_FAMILY)],
+            weight: fontdb::Weight(font.weight()),
+            stretch: fontdb::Stretch::Normal,
+            style: fontdb::Style::Normal,
+        };
+        if let Some(id) = db.query(&query) {
+            return Some(id);
+        }
+    }
+    #[cfg(not(feature = "bundled-font"))]
+    let _ = font;
+    db.faces().next().map(|face| face.id)
+}
+
+/// Whether `font` names families and none of them is installed. Generic
+/// families alone never count as substituted.
+fn is_substituted(font: &Font, db: &Database) -> bool {
+    let named: Vec<Family> = font
+        .families()
+        .iter()
+        .filter_map(|family| match family {
+            FontFamily::Named(name) => Some(Family::Name(name)),
+            _ => None,
+        })
+        .collect();
+    if named.is_empty() {
+        return false;
+    }
+    let query = Query {
+        families: &named,
+        weight: fontdb::Weight(font.weight()),
+        stretch: fontdb::Stretch::Normal,
+        style: fontdb::Style::Normal,
+    };
+    // The nearest weight of the family will do, so only the family itself counts
+    db.query(&query).is_none()
+}
+
+/// A text element drawn with a substitute because its font wasn't available.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubstitutedText {
+    /// The families the text asked for, as a `font-family` value with names quoted
+    pub requested: String,
+    /// Bounds (x, y, width, height), in SVG units
+    pub bounds: [f32; 4],
+}
+
+/// Text in `tree` whose requested font family wasn't found in `db`.
+pub fn substituted_text(tree: &Tree, db: &Database) -> Vec<SubstitutedText> {
+    let mut found = Vec::new();
+    collect_substituted(tree.root(), db, &mut found);
+    found
+}
+
+fn collect_substituted(group: &usvg::Group, db: &Database, found: &mut Vec<SubstitutedText>) {
+    for node in group.children() {
+        match node {
+            usvg::Node::Group(g) => collect_substituted(g, db, found),
+            usvg::Node::Text(text) => {
+                let missing = text
+                    .chunks()
+                    .iter()
+                    .flat_map(|chunk| chunk.spans())
+                    .map(|span| span.font())
+                    .find(|font| is_substituted(font, db));
+                if let Some(font) = missing {
+                    let bbox = text.abs_bounding_box();
+                    found.push(SubstitutedText {
+                        requested: font
+                            .families()
+                            .iter()
+                            .map(|family| family.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        bounds: [bbox.x(), bbox.y(), bbox.width(), bbox.height()],
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::svg_document::SvgDocument;
+    use std::path::{Path, PathBuf};
+
+    fn fixture_path(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("assets/test_fixtures")
+            .join(name)
+    }
+
+    fn text_in(family: &str) -> SvgDocument {
+        let svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="40">
+  <text x="5" y="30" font-family="{family}" font-size="20">Text</text>
+</svg>"#
+        );
+        SvgDocument::from_data(Path::new("text.svg"), svg.into_bytes()).unwrap()
+    }
+
+    #[cfg(feature = "bundled-font")]
+    #[test]
+    fn test_missing_family_renders_with_fallback() {
+        let doc = SvgDocument::load(&fixture_path("missing_font.svg")).unwrap();
+        let pixmap = crate::renderer::render_to_pixmap(
+            &doc,
+            &crate::viewport::Viewport::default(),
+            doc.width(),
+            doc.height(),
+            1.0,
+        )
+        .unwrap();
+        assert!(!crate::renderer::is_blank(&pixmap));
+
+        let substituted = doc.substituted_text();
+        assert_eq!(substituted.len(), 1);
+        assert_eq!(substituted[0].requested, "\"No Such Font Family\"");
+        // The text sits on its baseline at y = 40
+        let [x, y, width, height] = substituted[0].bounds;
+        assert!(x >= 10.0 && width > 0.0 && y < 40.0 && y + height > 30.0);
+    }
+
+    #[cfg(feature = "bundled-font")]
+    #[test]
+    fn test_installed_and_generic_families_are_not_substituted() {
+        assert!(text_in(FALLBACK_FAMILY).substituted_text().is_empty());
+        assert!(text_in("sans-serif").substituted_text().is_empty());
+        // One installed family anywhere in the list is enough
+        let listed = format!("No Such Font, {FALLBACK_FAMILY}");
+        assert!(text_in(&listed).substituted_text().is_empty());
+    }
+
+    #[cfg(feature = "bundled-font")]
+    #[test]
+    fn test_font_dirs_are_loaded() {
+        let dir =
+            std::env::temp_dir().join(format!("svg_viewer_test_fonts_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("extra.ttf"), epaint_default_fonts::UBUNTU_LIGHT).unwrap();
+        let without = load_database(&FontConfig::default()).len();
+        let config = FontConfig {
+            font_dirs: vec![dir.clone(), dir.join("missing")],
+            ..Default::default()
+        };
+        assert_eq!(load_database(&config).len(), without + 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_default_family_applies_to_unnamed_text() {
+        let config = FontConfig {
+            default_family: Some("Ubuntu".into()),
+            ..Default::default()
+        };
+        let options = options_with(fontdb(), &config);
+        assert_eq!(options.font_family, "Ubuntu");
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="40">
+  <text x="5" y="30" font-size="20">Text</text>
+</svg>"#;
+        let doc =
+            SvgDocument::from_data_with(Path::new("text.svg"), svg.to_vec(), &options).unwrap();
+        assert!(doc.substituted_text().is_empty());
+        let usvg::Node::Text(text) = &doc.tree().root().children()[0] else {
+            panic!("expected text");
+        };
+        let font = text.chunks()[0].spans()[0].font();
+        assert_eq!(font.families()[0].to_string(), "\"Ubuntu\"");
+    }
+
+    #[test]
+    fn test_documents_without_text_have_no_substitutions() {
+        let doc = SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+        assert!(doc.substituted_text().is_empty());
+        assert_eq!(
+            text_in("No Such Font").substituted_text()[0].requested,
+            "\"No Such Font\""
+        );
+    }
+}
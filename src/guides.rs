@@ -0,0 +1,479 @@
+//! Guide overlays (safe areas, keylines, thirds) drawn over the document.
+//!
+//! Guides are sized relative to the document, so they are laid out in SVG
+//! units and follow the document through zoom, pan, rotation and mirroring.
+//! They are only ever drawn on screen, never rendered into exports.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::{Result, SvgError};
+use crate::viewport::Viewport;
+
+/// Used when a user guide doesn't set a color.
+pub const DEFAULT_GUIDE_COLOR: [u8; 4] = [0, 200, 255, 200];
+
+const CIRCLE_SEGMENTS: usize = 64;
+const CORNER_SEGMENTS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GuideShape {
+    /// Centered circle; `size` is the diameter as a fraction of the shorter side.
+    Circle,
+    /// Centered rectangle `size` times the document's width and height.
+    /// `corner` is the radius as a fraction of the rectangle's shorter side.
+    RoundedRect { corner: f32 },
+    /// Lines dividing the document into thirds; `size` is ignored.
+    Thirds,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Guide {
+    pub shape: GuideShape,
+    pub size: f32,
+    /// RGBA, not premultiplied.
+    pub color: [u8; 4],
+}
+
+/// A polyline in SVG units.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outline {
+    pub points: Vec<[f32; 2]>,
+    pub closed: bool,
+}
+
+impl Guide {
+    /// The guide's lines for a `width`×`height` document.
+    pub fn outlines(&self, width: f32, height: f32) -> Vec<Outline> {
+        let (cx, cy) = (width / 2.0, height / 2.0);
+        match self.shape {
+            GuideShape::Circle => {
+                let r = self.size * width.min(height) / 2.0;
+                let points = (0..CIRCLE_SEGMENTS)
+                    .map(|i| {
+                        let a = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+                        [cx + r * a.cos(), cy + r * a.sin()]
+                    })
+                    .collect();
+                vec![Outline {
+                    points,
+                    closed: true,
+                }]
+            }
+            GuideShape::RoundedRect { corner } => {
+                let (w, h) = (self.size * width, self.size * height);
+                let r = (corner * w.min(h)).clamp(0.0, w.min(h) / 2.0);
+                vec![Outline {
+                    points: rounded_rect_points(cx - w / 2.0, cy - h / 2.0, w, h, r),
+                    closed: true,
+                }]
+            }
+            GuideShape::Thirds => {
+                let line = |a: [f32; 2], b: [f32; 2]| Outline {
+                    points: vec![a, b],
+                    closed: false,
+                };
+                (1..3)
+                    .flat_map(|i| {
+                        let x = width * i as f32 / 3.0;
+                        let y = height * i as f32 / 3.0;
+                        [line([x, 0.0], [x, height]), line([0.0, y], [width, y])]
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+fn rounded_rect_points(x: f32, y: f32, w: f32, h: f32, r: f32) -> Vec<[f32; 2]> {
+    if r <= 0.0 {
+        return vec![[x, y], [x + w, y], [x + w, y + h], [x, y + h]];
+    }
+    // Corner centers clockwise from top-right, each with its starting angle
+    let corners = [
+        (x + w - r, y + r, -90.0_f32),
+        (x + w - r, y + h - r, 0.0),
+        (x + r, y + h - r, 90.0),
+        (x + r, y + r, 180.0),
+    ];
+    let mut points = Vec::with_capacity(4 * (CORNER_SEGMENTS + 1));
+    for (ccx, ccy, start) in corners {
+        for i in 0..=CORNER_SEGMENTS {
+            let a = (start + 90.0 * i as f32 / CORNER_SEGMENTS as f32).to_radians();
+            points.push([ccx + r * a.cos(), ccy + r * a.sin()]);
+        }
+    }
+    points
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GuidePreset {
+    pub name: String,
+    pub guides: Vec<Guide>,
+}
+
+pub fn builtin_presets() -> Vec<GuidePreset> {
+    let guide = |shape, size| Guide {
+        shape,
+        size,
+        color: DEFAULT_GUIDE_COLOR,
+    };
+    vec![
+        GuidePreset {
+            // 108 dp canvas: 72 dp visible, 66 dp safe zone
+            name: "Android adaptive icon".into(),
+            guides: vec![
+                guide(GuideShape::Circle, 72.0 / 108.0),
+                guide(GuideShape::Circle, 66.0 / 108.0),
+            ],
+        },
+        GuidePreset {
+            // 824 px body on a 1024 px canvas, ~185 px corner radius
+            name: "macOS app icon".into(),
+            guides: vec![guide(
+                GuideShape::RoundedRect {
+                    corner: 185.4 / 824.0,
+                },
+                824.0 / 1024.0,
+            )],
+        },
+        GuidePreset {
+            name: "Rule of thirds".into(),
+            guides: vec![guide(GuideShape::Thirds, 1.0)],
+        },
+    ]
+}
+
+// User presets, e.g.
+//
+//   [[preset]]
+//   name = "Logo clear space"
+//
+//   [[preset.guide]]
+//   shape = "rounded_rect"   # circle | rounded_rect | thirds
+//   size = 0.8
+//   corner = 0.1
+//   color = "#ff00ff80"      # optional, #rrggbb or #rrggbbaa
+
+#[derive(Deserialize)]
+struct PresetFile {
+    #[serde(default)]
+    preset: Vec<PresetEntry>,
+}
+
+#[derive(Deserialize)]
+struct PresetEntry {
+    name: String,
+    #[serde(default)]
+    guide: Vec<GuideEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GuideEntry {
+    shape: String,
+    #[serde(default = "full_size")]
+    size: f32,
+    #[serde(default)]
+    corner: f32,
+    color: Option<String>,
+}
+
+fn full_size() -> f32 {
+    1.0
+}
+
+/// Parse user presets from TOML.
+pub fn parse_presets(text: &str) -> Result<Vec<GuidePreset>> {
+    let file: PresetFile =
+        toml::from_str(text).map_err(|e| SvgError::Parse(format!("guide presets: {e}")))?;
+    file.preset
+        .into_iter()
+        .map(|entry| {
+            let guides = entry
+                .guide
+                .into_iter()
+                .map(|g| parse_guide(g).map_err(|e| preset_error(&entry.name, &e)))
+                .collect::<std::result::Result<_, _>>()?;
+            Ok(GuidePreset {
+                name: entry.name,
+                guides,
+            })
+        })
+        .collect()
+}
+
+fn preset_error(name: &str, message: &str) -> SvgError {
+    SvgError::Parse(format!("guide preset \"{name}\": {message}"))
+}
+
+fn parse_guide(entry: GuideEntry) -> std::result::Result<Guide, String> {
+    let shape = match entry.shape.as_str() {
+        "circle" => GuideShape::Circle,
+        "rounded_rect" => GuideShape::RoundedRect {
+            corner: entry.corner,
+        },
+        "thirds" => GuideShape::Thirds,
+        other => return Err(format!("unknown shape \"{other}\"")),
+    };
+    if !entry.size.is_finite() || entry.size <= 0.0 {
+        return Err(format!("size must be positive, got {}", entry.size));
+    }
+    if !entry.corner.is_finite() || entry.corner < 0.0 {
+        return Err(format!("corner must not be negative, got {}", entry.corner));
+    }
+    let color = match entry.color {
+        Some(text) => parse_color(&text).ok_or_else(|| format!("invalid color \"{text}\""))?,
+        None => DEFAULT_GUIDE_COLOR,
+    };
+    Ok(Guide {
+        shape,
+        size: entry.size,
+        color,
+    })
+}
+
+/// `#rrggbb` or `#rrggbbaa`.
+pub fn parse_color(text: &str) -> Option<[u8; 4]> {
+    let hex = text.strip_prefix('#')?;
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return None;
+    }
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    let alpha = if hex.len() == 8 { byte(6)? } else { 255 };
+    Some([byte(0)?, byte(2)?, byte(4)?, alpha])
+}
+
+/// Where user presets are read from: `svg-viewer/guides.toml` in the platform
+/// config directory.
+pub fn user_presets_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("svg-viewer").join("guides.toml"))
+}
+
+/// Read user presets from `path`. A missing file is not an error.
+pub fn load_user_presets(path: &Path) -> Result<Vec<GuidePreset>> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => parse_presets(&text),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The available presets and which are shown, for the current session.
+pub struct GuideSet {
+    pub presets: Vec<GuidePreset>,
+    pub enabled: Vec<bool>,
+}
+
+impl Default for GuideSet {
+    fn default() -> Self {
+        Self::new(builtin_presets())
+    }
+}
+
+impl GuideSet {
+    pub fn new(presets: Vec<GuidePreset>) -> Self {
+        let enabled = vec![false; presets.len()];
+        Self { presets, enabled }
+    }
+
+    /// Built-in presets followed by the user's, logging any problem reading them.
+    pub fn load() -> Self {
+        let mut presets = builtin_presets();
+        if let Some(path) = user_presets_path() {
+            match load_user_presets(&path) {
+                Ok(user) => presets.extend(user),
+                Err(e) => log::warn!("Ignoring {}: {e}", path.display()),
+            }
+        }
+        Self::new(presets)
+    }
+
+    pub fn active(&self) -> impl Iterator<Item = &Guide> {
+        self.presets
+            .iter()
+            .zip(&self.enabled)
+            .filter(|(_, on)| **on)
+            .flat_map(|(preset, _)| &preset.guides)
+    }
+}
+
+/// Map SVG units onto the screen rect (`origin`, `size`) the render is shown in.
+/// This matches how the render itself was laid out, so guides line up with it.
+pub fn screen_transform(
+    viewport: &Viewport,
+    svg_width: f32,
+    svg_height: f32,
+    origin: [f32; 2],
+    size: [f32; 2],
+) -> tiny_skia::Transform {
+    viewport
+        .build_transform(svg_width, svg_height, size[0], size[1])
+        .post_translate(origin[0], origin[1])
+}
+
+pub fn map_point(transform: &tiny_skia::Transform, [x, y]: [f32; 2]) -> [f32; 2] {
+    let mut point = tiny_skia::Point::from_xy(x, y);
+    transform.map_point(&mut point);
+    [point.x, point.y]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: [f32; 2], b: [f32; 2]) {
+        assert!(
+            (a[0] - b[0]).abs() < 1e-3 && (a[1] - b[1]).abs() < 1e-3,
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn test_parse_presets() {
+        let presets = parse_presets(
+            r##"
+            [[preset]]
+            name = "Clear space"
+
+            [[preset.guide]]
+            shape = "circle"
+            size = 0.5
+            color = "#ff000080"
+
+            [[preset.guide]]
+            shape = "rounded_rect"
+            size = 0.8
+            corner = 0.25
+            "##,
+        )
+        .unwrap();
+        assert_eq!(presets.len(), 1);
+        assert_eq!(presets[0].name, "Clear space");
+        assert_eq!(
+            presets[0].guides,
+            vec![
+                Guide {
+                    shape: GuideShape::Circle,
+                    size: 0.5,
+                    color: [255, 0, 0, 128],
+                },
+                Guide {
+                    shape: GuideShape::RoundedRect { corner: 0.25 },
+                    size: 0.8,
+                    color: DEFAULT_GUIDE_COLOR,
+                },
+            ]
+        );
+        assert!(parse_presets("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_presets_errors() {
+        let bad = |guide: &str| {
+            let text = format!("[[preset]]\nname = \"p\"\n[[preset.guide]]\n{guide}");
+            parse_presets(&text).unwrap_err().to_string()
+        };
+        assert!(bad("shape = \"hexagon\"").contains("unknown shape"));
+        assert!(bad("shape = \"circle\"\nsize = 0").contains("size must be positive"));
+        assert!(bad("shape = \"circle\"\ncolor = \"red\"").contains("invalid color"));
+        assert!(bad("shape = \"circle\"\nradius = 2").contains("radius"));
+        assert!(parse_presets("[[preset]]").is_err());
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#0a0B0c"), Some([10, 11, 12, 255]));
+        assert_eq!(parse_color("#00000000"), Some([0, 0, 0, 0]));
+        assert_eq!(parse_color("0a0b0c"), None);
+        assert_eq!(parse_color("#abc"), None);
+        assert_eq!(parse_color("#gg0000"), None);
+    }
+
+    #[test]
+    fn test_circle_outline_uses_shorter_side() {
+        let guide = Guide {
+            shape: GuideShape::Circle,
+            size: 0.5,
+            color: DEFAULT_GUIDE_COLOR,
+        };
+        let outline = &guide.outlines(200.0, 100.0)[0];
+        assert!(outline.closed);
+        for [x, y] in &outline.points {
+            let r = ((x - 100.0).powi(2) + (y - 50.0).powi(2)).sqrt();
+            assert!((r - 25.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_rounded_rect_outline_bounds() {
+        let guide = Guide {
+            shape: GuideShape::RoundedRect { corner: 0.25 },
+            size: 0.5,
+            color: DEFAULT_GUIDE_COLOR,
+        };
+        let points = &guide.outlines(100.0, 100.0)[0].points;
+        let min_x = points.iter().map(|p| p[0]).fold(f32::MAX, f32::min);
+        let max_x = points.iter().map(|p| p[0]).fold(f32::MIN, f32::max);
+        assert!((min_x - 25.0).abs() < 1e-3 && (max_x - 75.0).abs() < 1e-3);
+        // The corner is rounded off, so the box corner itself isn't on the outline
+        assert!(!points.contains(&[25.0, 25.0]));
+    }
+
+    #[test]
+    fn test_thirds_outlines() {
+        let guide = Guide {
+            shape: GuideShape::Thirds,
+            size: 1.0,
+            color: DEFAULT_GUIDE_COLOR,
+        };
+        let outlines = guide.outlines(90.0, 30.0);
+        assert_eq!(outlines.len(), 4);
+        assert_eq!(outlines[0].points, vec![[30.0, 0.0], [30.0, 30.0]]);
+        assert_eq!(outlines[1].points, vec![[0.0, 10.0], [90.0, 10.0]]);
+    }
+
+    #[test]
+    fn test_screen_transform_scales_into_rect() {
+        let ts = screen_transform(
+            &Viewport::default(),
+            100.0,
+            50.0,
+            [10.0, 20.0],
+            [200.0, 100.0],
+        );
+        assert_close(map_point(&ts, [0.0, 0.0]), [10.0, 20.0]);
+        assert_close(map_point(&ts, [100.0, 50.0]), [210.0, 120.0]);
+    }
+
+    #[test]
+    fn test_screen_transform_follows_rotation_and_mirror() {
+        let mut viewport = Viewport::default();
+        viewport.rotate_cw();
+        // Rotated 90° clockwise the 100×50 document occupies a 50×100 rect, and its
+        // top-left corner ends up top-right
+        let ts = screen_transform(&viewport, 100.0, 50.0, [0.0, 0.0], [50.0, 100.0]);
+        assert_close(map_point(&ts, [0.0, 0.0]), [50.0, 0.0]);
+        assert_close(map_point(&ts, [100.0, 50.0]), [0.0, 100.0]);
+
+        let mut viewport = Viewport::default();
+        viewport.toggle_mirror_h();
+        let ts = screen_transform(&viewport, 100.0, 50.0, [0.0, 0.0], [100.0, 50.0]);
+        assert_close(map_point(&ts, [0.0, 0.0]), [100.0, 0.0]);
+    }
+
+    #[test]
+    fn test_guide_set_active() {
+        let mut set = GuideSet::default();
+        assert_eq!(set.active().count(), 0);
+        set.enabled[0] = true;
+        assert_eq!(set.active().count(), set.presets[0].guides.len());
+    }
+
+    #[test]
+    fn test_missing_user_file_is_empty() {
+        let path = std::env::temp_dir().join("svg_viewer_test_no_such_guides.toml");
+        assert!(load_user_presets(&path).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,403 @@
+//! Command-line operations that run without opening a window.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+use crate::batch::{self, BatchOptions, BatchSummary, ExportRenderer, Sequence};
+use crate::diff::{self, DiffReport};
+use crate::error::{Result, SvgError};
+use crate::export::{self, ExportFormat, ExportRegion, ExportSettings};
+use crate::file_navigator;
+use crate::folder_info::{self, FolderStats};
+use crate::renderer;
+use crate::svg_document::SvgDocument;
+use crate::viewport::Viewport;
+
+/// Which part of the document a headless export should capture.
+pub enum RegionSource {
+    Rect(ExportRegion),
+    Element { id: String, padding: f32 },
+}
+
+pub struct ExportJob {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub scale: f32,
+    pub region: Option<RegionSource>,
+    /// Inferred from the output's extension when not given.
+    pub format: Option<ExportFormat>,
+    /// Flatten onto this color instead of keeping transparency.
+    pub background: Option<[u8; 3]>,
+}
+
+/// Export every input into `out_dir`, as for `--batch`.
+pub struct BatchJob {
+    /// Files, or directories whose SVGs are all exported.
+    pub inputs: Vec<PathBuf>,
+    pub out_dir: PathBuf,
+    pub format: ExportFormat,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub scale: f32,
+    pub manifest: Option<PathBuf>,
+    pub options: BatchOptions,
+    /// Write numbered frames of one size instead of one image per source name.
+    pub sequence: Option<Sequence>,
+}
+
+/// Compare the renders of two documents, as for `--diff`.
+pub struct DiffJob {
+    pub a: PathBuf,
+    pub b: PathBuf,
+    /// Where to write the heatmap of differing pixels
+    pub out: Option<PathBuf>,
+}
+
+/// Load `path`, or standard input when it is `-`.
+pub fn load_input(path: &Path) -> Result<SvgDocument> {
+    if path == Path::new("-") {
+        SvgDocument::from_stdin()
+    } else {
+        SvgDocument::load(path)
+    }
+}
+
+/// Bounding box (including stroke) of the element with the given id.
+pub fn element_region(doc: &SvgDocument, id: &str) -> Result<ExportRegion> {
+    let node = doc
+        .find_by_id(id)
+        .ok_or_else(|| SvgError::ElementNotFound(id.to_string()))?;
+    let bbox = node.abs_stroke_bounding_box();
+    ExportRegion::new(bbox.x(), bbox.y(), bbox.width(), bbox.height())
+        .map_err(|_| SvgError::InvalidRegion(format!("element \"{id}\" has an empty bounding box")))
+}
+
+fn validate_scale(scale: f32) -> Result<()> {
+    if !scale.is_finite() || scale <= 0.0 {
+        return Err(SvgError::Export(format!(
+            "Scale must be a positive number, got {scale}"
+        )));
+    }
+    Ok(())
+}
+
+pub fn run_export(job: &ExportJob) -> Result<()> {
+    validate_scale(job.scale)?;
+
+    let format = job
+        .format
+        .clone()
+        .or_else(|| ExportFormat::from_path(&job.output))
+        .ok_or_else(|| {
+            SvgError::Export(format!(
+                "Cannot infer image format from \"{}\"",
+                job.output.display()
+            ))
+        })?;
+
+    let doc = load_input(&job.input)?;
+
+    let region = match &job.region {
+        None => None,
+        Some(RegionSource::Rect(region)) => Some(region.clipped_to(doc.width(), doc.height())?),
+        Some(RegionSource::Element { id, padding }) => {
+            Some(element_region(&doc, id)?.padded(*padding)?)
+        }
+    };
+
+    let (width, height) = match &region {
+        Some(region) => region.output_size(job.width, job.height, job.scale),
+        None => export::output_size(doc.width(), doc.height(), job.width, job.height, job.scale),
+    };
+
+    // A PDF is drawn from the vectors, the size the document (or region) prints at
+    let defaults = ExportSettings::default();
+    let mut settings = ExportSettings {
+        include_alpha: format.supports_alpha() && job.background.is_none(),
+        background_color: job.background.unwrap_or(defaults.background_color),
+        format,
+        width,
+        height,
+        ..defaults
+    };
+    if settings.format.has_dimensions() && !settings.is_vector_pdf() {
+        let plan = export::plan_size(&settings.format, width, height);
+        if plan.is_reduced() {
+            eprintln!(
+                "Exporting at {}×{} instead of {width}×{height}: {}",
+                plan.actual.0,
+                plan.actual.1,
+                plan.reasons.join("; ")
+            );
+        }
+        (settings.width, settings.height) = plan.actual;
+    }
+
+    match region {
+        Some(region) => export::export_region(&doc, &region, &settings, &job.output),
+        None => export::export_svg(&doc, &Viewport::default(), &settings, &job.output),
+    }
+}
+
+/// Render both documents at `a`'s size, compare them and write the heatmap
+/// if asked to.
+pub fn run_diff(job: &DiffJob) -> Result<DiffReport> {
+    let (a, b) = (SvgDocument::load(&job.a)?, SvgDocument::load(&job.b)?);
+    let (width, height) = export::output_size(a.width(), a.height(), None, None, 1.0);
+    let viewport = Viewport::default();
+    let render_a = renderer::render_for_export(&a, width, height, &viewport)?;
+    let render_b = renderer::render_for_export(&b, width, height, &viewport)?;
+    let (report, heatmap) = diff::compare(&render_a, &render_b)?;
+    if let Some(ref out) = job.out {
+        let format = ExportFormat::from_path(out).ok_or_else(|| {
+            SvgError::Export(format!(
+                "Cannot infer image format from \"{}\"",
+                out.display()
+            ))
+        })?;
+        let settings = ExportSettings {
+            format,
+            width: heatmap.width(),
+            height: heatmap.height(),
+            ..ExportSettings::default()
+        };
+        export::save_pixmap(&heatmap, &settings, None, out)?;
+    }
+    Ok(report)
+}
+
+/// The size shared by every frame: `--width` and `--height` when both are given,
+/// otherwise the first frame's size after applying them.
+fn frame_size(job: &BatchJob, first: &std::path::Path) -> Result<(u32, u32)> {
+    if let (Some(w), Some(h)) = (job.width, job.height) {
+        return Ok((w, h));
+    }
+    let doc = SvgDocument::load(first)?;
+    Ok(export::output_size(
+        doc.width(),
+        doc.height(),
+        job.width,
+        job.height,
+        job.scale,
+    ))
+}
+
+/// Run a batch, printing a line per file and a summary to stderr. Returns the
+/// summary; the batch failed if `summary.failed > 0`.
+pub fn run_batch(job: &BatchJob) -> Result<BatchSummary> {
+    validate_scale(job.scale)?;
+    std::fs::create_dir_all(&job.out_dir)?;
+
+    let items = match &job.sequence {
+        Some(sequence) => {
+            batch::plan_sequence(&job.inputs, &job.out_dir, &sequence.prefix, &job.format)
+        }
+        None => batch::plan_items(&job.inputs, &job.out_dir, &job.format),
+    };
+    if items.is_empty() {
+        return Err(SvgError::Export("No SVG files to export".into()));
+    }
+    let frame = match &job.sequence {
+        Some(sequence) => Some((frame_size(job, &items[0].source)?, sequence.fit)),
+        None => None,
+    };
+    let renderer = ExportRenderer {
+        format: job.format.clone(),
+        width: job.width,
+        height: job.height,
+        scale: job.scale,
+        frame,
+    };
+    let total = items.len();
+    let mut done = 0;
+    let records = batch::run_batch(&items, &job.options, &renderer, |record| {
+        done += 1;
+        eprintln!("[{done}/{total}] {}", record.progress_line());
+    });
+
+    if let Some(ref path) = job.manifest {
+        batch::write_manifest(&records, path)?;
+    }
+    if let Some(index) = job.sequence.as_ref().and_then(|s| s.index) {
+        let path = batch::write_sequence_index(&records, &job.out_dir, index)?;
+        eprintln!("Wrote {}", path.display());
+    }
+    let summary = BatchSummary::from_records(&records, total);
+    eprintln!("{}", summary.describe());
+    Ok(summary)
+}
+
+/// Summarize the SVG files directly inside `dir`, as for `--folder-info`.
+pub fn folder_info(dir: &Path) -> Result<FolderStats> {
+    // Surface a missing or unreadable directory, which svg_files_in skips
+    std::fs::read_dir(dir)?;
+    let files = file_navigator::svg_files_in(dir);
+    Ok(folder_info::scan(&files, &AtomicBool::new(false), |_| {}).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_path(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("assets")
+            .join("test_fixtures")
+            .join(name)
+    }
+
+    fn job(output: &str, region: Option<RegionSource>) -> ExportJob {
+        ExportJob {
+            input: fixture_path("simple_rect.svg"),
+            output: std::env::temp_dir().join(output),
+            width: None,
+            height: None,
+            scale: 1.0,
+            region,
+            format: None,
+            background: None,
+        }
+    }
+
+    #[test]
+    fn test_explicit_format_and_background() {
+        let mut job = job("svg_viewer_test_headless_format.img", None);
+        assert!(run_export(&job).is_err());
+
+        job.format = Some(ExportFormat::Png);
+        job.background = Some([0, 0, 255]);
+        run_export(&job).unwrap();
+        let written = image::ImageReader::open(&job.output)
+            .unwrap()
+            .with_guessed_format()
+            .unwrap()
+            .decode()
+            .unwrap();
+        assert!(!written.color().has_alpha());
+        // The corner is outside the rect, so it shows the background
+        assert_eq!(written.to_rgb8().get_pixel(0, 0).0, [0, 0, 255]);
+        std::fs::remove_file(&job.output).unwrap();
+    }
+
+    #[test]
+    fn test_unknown_element_id() {
+        let job = job(
+            "svg_viewer_test_headless_missing.png",
+            Some(RegionSource::Element {
+                id: "nope".into(),
+                padding: 0.0,
+            }),
+        );
+        let err = run_export(&job).unwrap_err();
+        assert!(matches!(err, SvgError::ElementNotFound(ref id) if id == "nope"));
+        assert!(!job.output.exists());
+    }
+
+    #[test]
+    fn test_batch_writes_manifest() {
+        let out_dir = std::env::temp_dir().join("svg_viewer_test_headless_batch");
+        let manifest = out_dir.join("manifest.json");
+        let job = BatchJob {
+            inputs: vec![fixture_path("simple_rect.svg"), fixture_path("missing.svg")],
+            out_dir: out_dir.clone(),
+            format: ExportFormat::Png,
+            width: Some(20),
+            height: None,
+            scale: 1.0,
+            manifest: Some(manifest.clone()),
+            options: BatchOptions {
+                keep_going: true,
+                ..Default::default()
+            },
+            sequence: None,
+        };
+        let summary = run_batch(&job).unwrap();
+        assert_eq!((summary.exported, summary.failed), (1, 1));
+        assert!(out_dir.join("simple_rect.png").exists());
+        let text = std::fs::read_to_string(&manifest).unwrap();
+        assert!(text.contains("\"status\": \"failed\""));
+        std::fs::remove_dir_all(&out_dir).ok();
+    }
+
+    #[test]
+    fn test_unknown_extension() {
+        let err = run_export(&job("svg_viewer_test_headless.xyz", None)).unwrap_err();
+        assert!(err.to_string().contains("Cannot infer image format"));
+    }
+
+    #[test]
+    fn test_pdf_page_is_the_document_size() {
+        let mut job = job("svg_viewer_test_headless.pdf", None);
+        job.scale = 2.0;
+        run_export(&job).unwrap();
+        let pdf = std::fs::read(&job.output).unwrap();
+        let doc = SvgDocument::load(&job.input).unwrap();
+        let [_, _, w, h] = crate::pdf::tests::parse_page_size(&pdf);
+        assert!((w - doc.width() * 0.75).abs() < 0.01);
+        assert!((h - doc.height() * 0.75).abs() < 0.01);
+        std::fs::remove_file(&job.output).ok();
+    }
+
+    #[test]
+    fn test_export_region_with_width() {
+        let mut job = job(
+            "svg_viewer_test_headless_region.png",
+            Some(RegionSource::Rect("0,0,100,50".parse().unwrap())),
+        );
+        job.width = Some(60);
+        run_export(&job).unwrap();
+        let img = image::open(&job.output).unwrap();
+        assert_eq!((img.width(), img.height()), (60, 30));
+        std::fs::remove_file(&job.output).ok();
+    }
+
+    #[test]
+    fn test_export_region_outside_document_fails() {
+        let job = job(
+            "svg_viewer_test_headless_region_outside.png",
+            Some(RegionSource::Rect("500,500,10,10".parse().unwrap())),
+        );
+        let err = run_export(&job).unwrap_err();
+        assert!(matches!(err, SvgError::InvalidRegion(_)));
+        assert!(!job.output.exists());
+    }
+
+    #[test]
+    fn test_diff_against_itself_and_another() {
+        let out = std::env::temp_dir().join("svg_viewer_test_headless_diff.png");
+        let same = DiffJob {
+            a: fixture_path("simple_rect.svg"),
+            b: fixture_path("simple_rect.svg"),
+            out: Some(out.clone()),
+        };
+        let report = run_diff(&same).unwrap();
+        assert_eq!((report.width, report.height), (200, 150));
+        assert_eq!(report.differing, 0);
+        assert!(out.exists());
+        std::fs::remove_file(&out).ok();
+
+        let other = DiffJob {
+            b: fixture_path("transparent.svg"),
+            out: None,
+            ..same
+        };
+        assert!(run_diff(&other).unwrap().differing > 0);
+    }
+
+    #[test]
+    fn test_folder_info_over_fixtures() {
+        let dir = fixture_path("simple_rect.svg")
+            .parent()
+            .unwrap()
+            .to_path_buf();
+        let stats = folder_info(&dir).unwrap();
+        assert_eq!(stats.files, file_navigator::svg_files_in(&dir).len());
+        assert!(stats
+            .breakdown()
+            .iter()
+            .any(|r| (r.width, r.height) == (200, 150)));
+        assert!(folder_info(&dir.join("missing")).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! Writing square renders of one document as a multi-size icon: a Windows
+//! `.ico` or a macOS `.icns`, each size stored as a PNG.
+
+use std::path::Path;
+
+use crate::error::{Result, SvgError};
+
+/// Sizes checked for a new icon export.
+pub const DEFAULT_ICON_SIZES: [u32; 4] = [16, 32, 48, 256];
+/// Sizes an ICO can hold; its directory stores each side in a byte.
+pub const ICO_SIZES: [u32; 7] = [16, 24, 32, 48, 64, 128, 256];
+/// Sizes with a PNG type in an ICNS, and the type each is stored as.
+const ICNS_TYPES: [(u32, &[u8; 4]); 7] = [
+    (16, b"icp4"),
+    (32, b"icp5"),
+    (64, b"icp6"),
+    (128, b"ic07"),
+    (256, b"ic08"),
+    (512, b"ic09"),
+    (1024, b"ic10"),
+];
+
+/// Sizes an ICNS can hold.
+pub fn icns_sizes() -> impl Iterator<Item = u32> {
+    ICNS_TYPES.iter().map(|(size, _)| *size)
+}
+
+/// One size of an icon: `size`×`size` pixels of un-premultiplied RGBA.
+pub struct IconImage {
+    pub size: u32,
+    pub rgba: Vec<u8>,
+}
+
+fn png(image: &IconImage) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    image::ImageEncoder::write_image(
+        image::codecs::png::PngEncoder::new(&mut bytes),
+        &image.rgba,
+        image.size,
+        image.size,
+        image::ExtendedColorType::Rgba8,
+    )
+    .map_err(|e| SvgError::Export(e.to_string()))?;
+    Ok(bytes)
+}
+
+/// Write `images` as a Windows icon.
+pub fn write_ico(images: &[IconImage], output_path: &Path) -> Result<()> {
+    let frames = images
+        .iter()
+        .map(|image| {
+            image::codecs::ico::IcoFrame::with_encoded(
+                png(image)?,
+                image.size,
+                image.size,
+                image::ExtendedColorType::Rgba8,
+            )
+            .map_err(|e| SvgError::Export(e.to_string()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut bytes = Vec::new();
+    image::codecs::ico::IcoEncoder::new(&mut bytes)
+        .encode_images(&frames)
+        .map_err(|e| SvgError::Export(e.to_string()))?;
+    std::fs::write(output_path, bytes)?;
+    Ok(())
+}
+
+/// Write `images` as a macOS icon.
+pub fn write_icns(images: &[IconImage], output_path: &Path) -> Result<()> {
+    std::fs::write(output_path, icns(images)?)?;
+    Ok(())
+}
+
+/// The bytes of the ICNS [`write_icns`] writes: a header, then each image
+/// as its type, its length and its PNG data.
+fn icns(images: &[IconImage]) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    for image in images {
+        let (_, icon_type) = ICNS_TYPES
+            .iter()
+            .find(|(size, _)| *size == image.size)
+            .ok_or_else(|| {
+                SvgError::Export(format!("an ICNS can't hold a {0}×{0} icon", image.size))
+            })?;
+        let png = png(image)?;
+        body.extend_from_slice(*icon_type);
+        body.extend_from_slice(&(png.len() as u32 + 8).to_be_bytes());
+        body.extend_from_slice(&png);
+    }
+    let mut bytes = b"icns".to_vec();
+    bytes.extend_from_slice(&(body.len() as u32 + 8).to_be_bytes());
+    bytes.extend_from_slice(&body);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(size: u32) -> IconImage {
+        IconImage {
+            size,
+            rgba: vec![255; (size * size * 4) as usize],
+        }
+    }
+
+    #[test]
+    fn test_icns_layout() {
+        let bytes = icns(&[image(16), image(32)]).unwrap();
+        assert_eq!(&bytes[..4], b"icns");
+        assert_eq!(
+            u32::from_be_bytes(bytes[4..8].try_into().unwrap()) as usize,
+            bytes.len()
+        );
+        assert_eq!(&bytes[8..12], b"icp4");
+        let first = u32::from_be_bytes(bytes[12..16].try_into().unwrap()) as usize;
+        assert_eq!(&bytes[16..20], b"\x89PNG");
+        assert_eq!(&bytes[8 + first..12 + first], b"icp5");
+
+        assert!(icns(&[image(48)]).is_err());
+    }
+}
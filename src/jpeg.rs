@@ -0,0 +1,152 @@
+//! Writing JPEGs through the `jpeg-encoder` crate, which unlike the `image`
+//! crate's encoder can subsample chroma 4:2:0 and write progressive scans.
+
+use std::path::Path;
+
+use jpeg_encoder::{ColorType, Encoder, PixelDensity, SamplingFactor};
+
+use crate::error::{Result, SvgError};
+
+/// How finely colour is kept against brightness.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChromaSubsampling {
+    /// Colour at every pixel, which keeps thin coloured lines sharp
+    #[default]
+    Full,
+    /// Colour for each 2×2 pixels, as cameras store photos
+    Half,
+}
+
+impl ChromaSubsampling {
+    pub fn all() -> &'static [ChromaSubsampling] {
+        &[ChromaSubsampling::Full, ChromaSubsampling::Half]
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            ChromaSubsampling::Full => "4:4:4",
+            ChromaSubsampling::Half => "4:2:0",
+        }
+    }
+
+    fn sampling_factor(&self) -> SamplingFactor {
+        match self {
+            ChromaSubsampling::Full => SamplingFactor::R_4_4_4,
+            ChromaSubsampling::Half => SamplingFactor::R_4_2_0,
+        }
+    }
+}
+
+pub struct JpegOptions {
+    /// 1 to 100
+    pub quality: u8,
+    pub subsampling: ChromaSubsampling,
+    pub progressive: bool,
+    /// Recorded in the JFIF header
+    pub dpi: Option<u16>,
+    /// TIFF-structured EXIF data, written in an APP1 segment
+    pub exif: Option<Vec<u8>>,
+}
+
+/// Write a `width`×`height` image of three bytes a pixel as a JPEG.
+pub fn write_jpeg(
+    rgb: &[u8],
+    (width, height): (u32, u32),
+    options: &JpegOptions,
+    output_path: &Path,
+) -> Result<()> {
+    std::fs::write(output_path, encode(rgb, (width, height), options)?)?;
+    Ok(())
+}
+
+fn encode(rgb: &[u8], (width, height): (u32, u32), options: &JpegOptions) -> Result<Vec<u8>> {
+    let (Ok(w), Ok(h)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(SvgError::Export(format!(
+            "a JPEG can't be {width}×{height}"
+        )));
+    };
+    if rgb.len() != width as usize * height as usize * 3 {
+        return Err(SvgError::Export(
+            "the image data doesn't fit its size".into(),
+        ));
+    }
+    let encode_error = |e: jpeg_encoder::EncodingError| SvgError::Export(e.to_string());
+    let mut out = Vec::new();
+    let mut encoder = Encoder::new(&mut out, options.quality.clamp(1, 100));
+    encoder.set_sampling_factor(options.subsampling.sampling_factor());
+    encoder.set_progressive(options.progressive);
+    if let Some(dpi) = options.dpi {
+        encoder.set_density(PixelDensity::dpi(dpi));
+    }
+    if let Some(exif) = &options.exif {
+        encoder.add_exif_metadata(exif).map_err(encode_error)?;
+    }
+    encoder
+        .encode(rgb, w, h, ColorType::Rgb)
+        .map_err(encode_error)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A smooth colourful `width`×`height` image.
+    fn gradient(width: usize, height: usize) -> Vec<u8> {
+        (0..width * height)
+            .flat_map(|i| {
+                let (x, y) = (i % width, i / width);
+                [
+                    (x * 255 / width) as u8,
+                    (y * 255 / height) as u8,
+                    ((x + y) * 127 / (width + height)) as u8 + 64,
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_encoded_jpegs_decode() {
+        // Sizes that don't fill the last block, nor the last 16×16 unit
+        let (width, height) = (37, 21);
+        let rgb = gradient(width, height);
+        for subsampling in ChromaSubsampling::all() {
+            for progressive in [false, true] {
+                let options = JpegOptions {
+                    quality: 95,
+                    subsampling: *subsampling,
+                    progressive,
+                    dpi: Some(300),
+                    exif: None,
+                };
+                let bytes = encode(&rgb, (width as u32, height as u32), &options).unwrap();
+                let frame = if progressive {
+                    [0xFF, 0xC2]
+                } else {
+                    [0xFF, 0xC0]
+                };
+                let sof = bytes.windows(2).position(|w| w == frame).unwrap();
+                // The luma component's sampling, after the frame's length,
+                // precision, size and component count
+                let sampling = match subsampling {
+                    ChromaSubsampling::Full => 0x11,
+                    ChromaSubsampling::Half => 0x22,
+                };
+                assert_eq!(bytes[sof + 11], sampling, "{subsampling:?}");
+                let decoded = image::load_from_memory(&bytes).unwrap().to_rgb8();
+                assert_eq!(decoded.dimensions(), (width as u32, height as u32));
+                let worst = decoded
+                    .as_raw()
+                    .iter()
+                    .zip(&rgb)
+                    .map(|(a, b)| a.abs_diff(*b))
+                    .max()
+                    .unwrap();
+                assert!(
+                    worst <= 12,
+                    "{subsampling:?} progressive {progressive}: {worst}"
+                );
+            }
+        }
+    }
+}
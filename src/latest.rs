@@ -0,0 +1,80 @@
+//! Results of background jobs where only the latest one counts, such as
+//! loading the file stepped to.
+//!
+//! Every job's result comes in on one channel, tagged with the generation the
+//! job was started as. A job that's superseded may still be running, or have
+//! sent its result already; either way it's dropped as it's read, so only
+//! the latest job's result is ever handed back.
+
+use std::sync::mpsc;
+
+/// Where a job sends its result, tagged with its generation.
+pub type ResultSender<T> = mpsc::Sender<(u64, T)>;
+
+pub struct Latest<T> {
+    sender: ResultSender<T>,
+    receiver: mpsc::Receiver<(u64, T)>,
+    generation: u64,
+}
+
+impl<T> Default for Latest<T> {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sender,
+            receiver,
+            generation: 0,
+        }
+    }
+}
+
+impl<T> Latest<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a job, superseding any before it: the generation to tag its
+    /// result with, and where to send it.
+    pub fn start(&mut self) -> (u64, ResultSender<T>) {
+        self.generation += 1;
+        (self.generation, self.sender.clone())
+    }
+
+    /// The latest job's result, once it has come in. Results of older jobs
+    /// read on the way are dropped.
+    pub fn take(&self) -> Option<T> {
+        while let Ok((generation, result)) = self.receiver.try_recv() {
+            if generation == self.generation {
+                return Some(result);
+            }
+            log::debug!("Dropped the result of superseded job {generation}");
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_results_are_dropped() {
+        let mut latest = Latest::new();
+        let (first, first_tx) = latest.start();
+        assert_eq!(latest.take(), None);
+
+        // The first job finishes after the second has started
+        let (second, second_tx) = latest.start();
+        first_tx.send((first, "first")).unwrap();
+        assert_eq!(latest.take(), None);
+        second_tx.send((second, "second")).unwrap();
+        assert_eq!(latest.take(), Some("second"));
+        assert_eq!(latest.take(), None);
+
+        // Or before the third is read, and is skipped over
+        let (third, third_tx) = latest.start();
+        second_tx.send((second, "late")).unwrap();
+        third_tx.send((third, "third")).unwrap();
+        assert_eq!(latest.take(), Some("third"));
+    }
+}
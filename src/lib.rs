@@ -0,0 +1,65 @@
+//! The SVG viewer's load → viewport → render → export pipeline.
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use svg_viewer::export::{self, ExportSettings};
+//! use svg_viewer::svg_document::SvgDocument;
+//! use svg_viewer::viewport::Viewport;
+//!
+//! let doc = SvgDocument::load(Path::new("logo.svg"))?;
+//! let settings = ExportSettings {
+//!     width: 512,
+//!     height: 512,
+//!     ..Default::default()
+//! };
+//! export::export_svg(&doc, &Viewport::default(), &settings, Path::new("logo.png"))?;
+//! # Ok::<(), svg_viewer::error::SvgError>(())
+//! ```
+//!
+//! The desktop application itself (`app`, `ui`, clipboard support and the GPU
+//! texture side of `renderer`) is only built with the default `gui` feature.
+
+pub mod archive;
+pub mod batch;
+pub mod checkerboard;
+pub mod color_management;
+pub mod diagnostics;
+pub mod diff;
+pub mod error;
+pub mod event_log;
+pub mod export;
+pub mod eyedropper;
+pub mod file_navigator;
+pub mod file_watcher;
+pub mod folder_info;
+pub mod fonts;
+pub mod guides;
+pub mod headless;
+pub mod icon;
+pub mod jpeg;
+pub mod latest;
+pub mod measure;
+pub mod pdf;
+pub mod reload_queue;
+pub mod remote;
+pub mod render_state;
+pub mod renderer;
+pub mod reveal;
+pub mod rulers;
+pub mod settings;
+pub mod structure;
+pub mod svg_document;
+pub mod thumbnails;
+pub mod tiles;
+pub mod timestamp;
+pub mod trash;
+pub mod view_action;
+pub mod view_history;
+pub mod viewport;
+
+#[cfg(feature = "gui")]
+pub mod app;
+#[cfg(feature = "gui")]
+pub mod clipboard;
+#[cfg(feature = "gui")]
+pub mod ui;
@@ -1,18 +1,18 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-mod app;
-mod clipboard;
-mod error;
-mod export;
-mod file_navigator;
-mod renderer;
-mod svg_document;
-mod ui;
-mod viewport;
+use std::path::{Path, PathBuf};
 
-use std::path::PathBuf;
+use clap::{ArgGroup, Parser};
 
-use clap::Parser;
+use svg_viewer::app;
+use svg_viewer::batch::{BatchOptions, FrameFit, Sequence, SequenceIndex};
+use svg_viewer::event_log::TeeLogger;
+use svg_viewer::export::{ExportFormat, ExportRegion};
+use svg_viewer::fonts::{self, FontConfig};
+use svg_viewer::guides;
+use svg_viewer::headless::{self, BatchJob, DiffJob, ExportJob, RegionSource};
+use svg_viewer::settings::Settings;
+use svg_viewer::svg_document::SvgDocument;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -20,15 +20,361 @@ use clap::Parser;
     version,
     about = "A fast, cross-platform SVG viewer"
 )]
+#[command(group(ArgGroup::new("output").args(["export", "batch"])))]
+#[command(group(ArgGroup::new("input").args(["file", "stdin"])))]
 struct Cli {
-    /// SVG file to open
+    /// SVG file, directory or http(s) URL to open, or - to read standard input
     file: Option<PathBuf>,
+
+    /// Read the document from standard input
+    #[arg(long, conflicts_with = "batch")]
+    stdin: bool,
+
+    /// More files to open, navigating between just those; or more files and
+    /// directories to export with --batch
+    #[arg(requires = "file", conflicts_with_all = ["export", "stdin"])]
+    more_inputs: Vec<PathBuf>,
+
+    /// Export FILE to OUTPUT without opening a window
+    #[arg(long, value_name = "OUTPUT", requires = "input")]
+    export: Option<PathBuf>,
+
+    /// Export every input file (directories: every SVG in them) into OUT_DIR
+    #[arg(long, value_name = "OUT_DIR", requires = "file")]
+    batch: Option<PathBuf>,
+
+    /// Image format [default: from the --export extension, png for --batch]
+    #[arg(long, value_parser = parse_format, requires = "output")]
+    format: Option<ExportFormat>,
+
+    /// Flatten the export onto this #rrggbb color instead of keeping transparency
+    #[arg(
+        long,
+        value_name = "COLOR",
+        value_parser = parse_background,
+        requires = "export",
+        conflicts_with = "batch"
+    )]
+    background: Option<[u8; 3]>,
+
+    /// Files to export in parallel with --batch [default: one per CPU]
+    #[arg(long, value_name = "N", requires = "batch")]
+    jobs: Option<usize>,
+
+    /// Write a JSON record of every --batch input to this file
+    #[arg(long, value_name = "PATH", requires = "batch")]
+    manifest: Option<PathBuf>,
+
+    /// Skip inputs whose output is newer than the source
+    #[arg(long, requires = "batch")]
+    skip_up_to_date: bool,
+
+    /// Export every input even after failures, and exit successfully anyway
+    #[arg(long, requires = "batch")]
+    keep_going: bool,
+
+    /// Name --batch outputs PREFIX0001.png, PREFIX0002.png, ... in input order,
+    /// all the same size
+    #[arg(long, requires = "batch")]
+    sequence: bool,
+
+    /// File name prefix for --sequence frames
+    #[arg(
+        long,
+        value_name = "PREFIX",
+        default_value = "frame_",
+        requires = "sequence"
+    )]
+    sequence_prefix: String,
+
+    /// How frames with a different aspect ratio fill the --sequence size
+    #[arg(long, value_enum, default_value = "contain", requires = "sequence")]
+    sequence_fit: FitArg,
+
+    /// Also write a frame listing into OUT_DIR
+    #[arg(long, value_enum, value_name = "KIND", requires = "sequence")]
+    sequence_index: Option<IndexArg>,
+
+    /// Output width in pixels
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), requires = "output")]
+    width: Option<u32>,
+
+    /// Output height in pixels
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), requires = "output")]
+    height: Option<u32>,
+
+    /// Scale factor applied to the document or region size
+    #[arg(long, default_value_t = 1.0, requires = "output")]
+    scale: f32,
+
+    /// Export only this rectangle, in SVG units
+    #[arg(long, value_name = "X,Y,W,H", requires = "export")]
+    export_region: Option<ExportRegion>,
+
+    /// Export only the bounding box of the element with this id
+    #[arg(
+        long,
+        value_name = "ID",
+        requires = "export",
+        conflicts_with = "export_region"
+    )]
+    export_element: Option<String>,
+
+    /// Print a summary of the SVG files in DIR: count, total size, document
+    /// sizes and the files that fail to parse
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["input", "output"])]
+    folder_info: Option<PathBuf>,
+
+    /// Print how many paths, groups, text, images, gradients and filters the
+    /// document has, without opening a window
+    #[arg(long, requires = "input", conflicts_with_all = ["output", "folder_info"])]
+    info: bool,
+
+    /// Render A and B at A's size and compare them pixel by pixel, without
+    /// opening a window. Exits 1 if more than --threshold percent differ, 2 on
+    /// errors
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["A", "B"],
+        conflicts_with_all = ["input", "output", "folder_info", "info"]
+    )]
+    diff: Option<Vec<PathBuf>>,
+
+    /// Write a heatmap of the --diff, differing pixels in red, to this image
+    #[arg(long, value_name = "PATH", requires = "diff")]
+    out: Option<PathBuf>,
+
+    /// Percentage of pixels allowed to differ before --diff fails
+    #[arg(long, value_name = "PERCENT", default_value_t = 0.0, requires = "diff")]
+    threshold: f64,
+
+    /// Print --folder-info as JSON instead of a table
+    #[arg(long, requires = "folder_info")]
+    json: bool,
+
+    /// Extra space around --export-element, in SVG units
+    #[arg(
+        long,
+        value_name = "UNITS",
+        default_value_t = 0.0,
+        requires = "export_element"
+    )]
+    region_padding: f32,
+
+    /// Font family for text that doesn't name one
+    #[arg(long, value_name = "FAMILY")]
+    font_family: Option<String>,
+
+    /// Load the font files in DIR as well as the system's (repeatable)
+    #[arg(long, value_name = "DIR")]
+    font_dir: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+enum FitArg {
+    /// Letterbox with transparent bars
+    Contain,
+    /// Fill the frame, cropping the overflow
+    Cover,
+}
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+enum IndexArg {
+    /// sequence.ffconcat for ffmpeg's concat demuxer
+    Ffconcat,
+    /// sequence.json
+    Json,
+}
+
+fn parse_format(s: &str) -> Result<ExportFormat, String> {
+    ExportFormat::from_extension(s).ok_or_else(|| format!("unknown image format \"{s}\""))
+}
+
+fn parse_background(s: &str) -> Result<[u8; 3], String> {
+    match guides::parse_color(s) {
+        Some([r, g, b, 255]) => Ok([r, g, b]),
+        Some(_) => Err("the background must be opaque".into()),
+        None => Err(format!("expected a #rrggbb color, got \"{s}\"")),
+    }
+}
+
+impl Cli {
+    /// Font options from the command line on top of those in `settings`.
+    fn font_config(&self, settings: &Settings) -> FontConfig {
+        FontConfig {
+            font_dirs: settings
+                .font_dirs
+                .iter()
+                .chain(&self.font_dir)
+                .cloned()
+                .collect(),
+            default_family: self
+                .font_family
+                .clone()
+                .or_else(|| settings.default_font_family.clone()),
+        }
+    }
+
+    /// The file to open, `-` meaning standard input.
+    fn input(&self) -> Option<PathBuf> {
+        if self.stdin {
+            Some(PathBuf::from("-"))
+        } else {
+            self.file.clone()
+        }
+    }
+
+    fn batch_job(&self) -> Option<BatchJob> {
+        let out_dir = self.batch.clone()?;
+        let jobs = self.jobs.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        });
+        let inputs = self.file.iter().chain(&self.more_inputs).cloned().collect();
+        Some(BatchJob {
+            inputs,
+            out_dir,
+            format: self.format.clone().unwrap_or(ExportFormat::Png),
+            width: self.width,
+            height: self.height,
+            scale: self.scale,
+            manifest: self.manifest.clone(),
+            options: BatchOptions {
+                jobs,
+                skip_up_to_date: self.skip_up_to_date,
+                keep_going: self.keep_going,
+                ..Default::default()
+            },
+            sequence: self.sequence.then(|| Sequence {
+                prefix: self.sequence_prefix.clone(),
+                fit: match self.sequence_fit {
+                    FitArg::Contain => FrameFit::Contain,
+                    FitArg::Cover => FrameFit::Cover,
+                },
+                index: self.sequence_index.map(|kind| match kind {
+                    IndexArg::Ffconcat => SequenceIndex::FfConcat,
+                    IndexArg::Json => SequenceIndex::Json,
+                }),
+            }),
+        })
+    }
+
+    fn diff_job(&self) -> Option<DiffJob> {
+        let [a, b] = <[PathBuf; 2]>::try_from(self.diff.clone()?).ok()?;
+        Some(DiffJob {
+            a,
+            b,
+            out: self.out.clone(),
+        })
+    }
+
+    fn export_job(&self) -> Option<ExportJob> {
+        let output = self.export.clone()?;
+        let region = if let Some(region) = self.export_region {
+            Some(RegionSource::Rect(region))
+        } else {
+            self.export_element.clone().map(|id| RegionSource::Element {
+                id,
+                padding: self.region_padding,
+            })
+        };
+        Some(ExportJob {
+            input: self.input()?,
+            output,
+            width: self.width,
+            height: self.height,
+            scale: self.scale,
+            region,
+            format: self.format.clone(),
+            background: self.background,
+        })
+    }
 }
 
 fn main() -> eframe::Result<()> {
-    env_logger::init();
+    TeeLogger::init().expect("logger already installed");
 
     let cli = Cli::parse();
+    fonts::configure(cli.font_config(&Settings::load()));
+
+    if let Some(job) = cli.export_job() {
+        match headless::run_export(&job) {
+            Ok(()) => {
+                println!("{}", job.output.display());
+                std::process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("error: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(job) = cli.diff_job() {
+        let code = match headless::run_diff(&job) {
+            Ok(report) => {
+                println!("{}", report.describe());
+                if let Some(ref out) = job.out {
+                    println!("{}", out.display());
+                }
+                i32::from(report.percent() > cli.threshold)
+            }
+            Err(e) => {
+                eprintln!("error: {e}");
+                2
+            }
+        };
+        std::process::exit(code);
+    }
+
+    if cli.info {
+        match cli.input().map(|path| headless::load_input(&path)) {
+            Some(Ok(doc)) => {
+                print!("{}", doc.stats().table());
+                std::process::exit(0);
+            }
+            Some(Err(e)) => {
+                eprintln!("error: {e}");
+                std::process::exit(1);
+            }
+            None => unreachable!("--info requires an input"),
+        }
+    }
+
+    if let Some(ref dir) = cli.folder_info {
+        match headless::folder_info(dir) {
+            Ok(stats) if cli.json => match stats.to_json() {
+                Ok(json) => println!("{json}"),
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    std::process::exit(1);
+                }
+            },
+            Ok(stats) => print!("{}", stats.table()),
+            Err(e) => {
+                eprintln!("error: {e}");
+                std::process::exit(1);
+            }
+        }
+        std::process::exit(0);
+    }
+
+    if let Some(job) = cli.batch_job() {
+        let code = match headless::run_batch(&job) {
+            Ok(summary) if summary.failed > 0 && !job.options.keep_going => 1,
+            Ok(_) => 0,
+            Err(e) => {
+                eprintln!("error: {e}");
+                1
+            }
+        };
+        std::process::exit(code);
+    }
+
+    // Scanned while the window opens rather than when the first file parses
+    fonts::preload();
 
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
@@ -38,9 +384,294 @@ fn main() -> eframe::Result<()> {
         ..Default::default()
     };
 
+    let app = match cli.input() {
+        Some(path) if path == Path::new("-") => match SvgDocument::from_stdin() {
+            Ok(doc) => app::SvgViewerApp::with_document(doc),
+            Err(e) => {
+                eprintln!("error: {e}");
+                std::process::exit(1);
+            }
+        },
+        Some(first) => {
+            let files = std::iter::once(first).chain(cli.more_inputs).collect();
+            app::SvgViewerApp::with_files(files)
+        }
+        None => app::SvgViewerApp::new(None),
+    };
+
     eframe::run_native(
         "SVG Viewer",
         options,
-        Box::new(move |_cc| Ok(Box::new(app::SvgViewerApp::new(cli.file)))),
+        Box::new(move |_cc| Ok(Box::new(app))),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("svg-viewer").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_no_args_opens_gui() {
+        let cli = parse(&[]).unwrap();
+        assert!(cli.export_job().is_none());
+    }
+
+    #[test]
+    fn test_font_args_extend_settings() {
+        let mut settings = Settings::default();
+        settings.default_font_family = Some("Noto Sans".into());
+        settings.font_dirs = vec![PathBuf::from("/fonts/a")];
+        let cli = parse(&["--font-dir", "/fonts/b", "--font-dir", "/fonts/c"]).unwrap();
+        let config = cli.font_config(&settings);
+        assert_eq!(config.default_family.as_deref(), Some("Noto Sans"));
+        assert_eq!(
+            config.font_dirs,
+            ["/fonts/a", "/fonts/b", "/fonts/c"].map(PathBuf::from)
+        );
+
+        let cli = parse(&["--font-family", "DejaVu Serif"]).unwrap();
+        let config = cli.font_config(&settings);
+        assert_eq!(config.default_family.as_deref(), Some("DejaVu Serif"));
+    }
+
+    #[test]
+    fn test_export_region_args() {
+        let cli = parse(&[
+            "in.svg",
+            "--export",
+            "out.png",
+            "--export-region",
+            "10,20,30,40",
+            "--scale",
+            "2",
+        ])
+        .unwrap();
+        let job = cli.export_job().unwrap();
+        assert_eq!(job.scale, 2.0);
+        match job.region {
+            Some(RegionSource::Rect(r)) => {
+                assert_eq!(r, ExportRegion::new(10.0, 20.0, 30.0, 40.0).unwrap())
+            }
+            _ => panic!("expected a rect region"),
+        }
+    }
+
+    #[test]
+    fn test_export_format_and_background() {
+        let job = parse(&["in.svg", "--export", "out.png"])
+            .unwrap()
+            .export_job()
+            .unwrap();
+        assert_eq!(job.format, None);
+        assert_eq!(job.background, None);
+
+        let cli = parse(&[
+            "in.svg",
+            "--export",
+            "out",
+            "--format",
+            "jpg",
+            "--background",
+            "#ff8000",
+        ])
+        .unwrap();
+        let job = cli.export_job().unwrap();
+        assert_eq!(job.format, Some(ExportFormat::Jpeg));
+        assert_eq!(job.background, Some([255, 128, 0]));
+
+        assert!(parse(&["in.svg", "--export", "o.png", "--background", "red"]).is_err());
+        assert!(parse(&["in.svg", "--background", "#ffffff"]).is_err());
+        assert!(parse(&["in.svg", "--batch", "out", "--background", "#ffffff"]).is_err());
+    }
+
+    #[test]
+    fn test_stdin_input() {
+        let job = parse(&["-", "--export", "out.png"])
+            .unwrap()
+            .export_job()
+            .unwrap();
+        assert_eq!(job.input, PathBuf::from("-"));
+        let cli = parse(&["--stdin", "--export", "out.png"]).unwrap();
+        assert_eq!(cli.export_job().unwrap().input, PathBuf::from("-"));
+
+        assert!(parse(&["--stdin"]).unwrap().export_job().is_none());
+        assert!(parse(&["in.svg", "--stdin"]).is_err());
+        assert!(parse(&["--stdin", "--batch", "out"]).is_err());
+    }
+
+    #[test]
+    fn test_export_element_args() {
+        let cli = parse(&[
+            "in.svg",
+            "--export",
+            "out.png",
+            "--export-element",
+            "logo",
+            "--region-padding",
+            "4",
+            "--width",
+            "512",
+        ])
+        .unwrap();
+        let job = cli.export_job().unwrap();
+        assert_eq!(job.width, Some(512));
+        match job.region {
+            Some(RegionSource::Element { id, padding }) => {
+                assert_eq!(id, "logo");
+                assert_eq!(padding, 4.0);
+            }
+            _ => panic!("expected an element region"),
+        }
+    }
+
+    #[test]
+    fn test_batch_args() {
+        let cli = parse(&[
+            "a.svg",
+            "icons",
+            "--batch",
+            "out",
+            "--format",
+            "webp",
+            "--jobs",
+            "3",
+            "--manifest",
+            "m.json",
+            "--skip-up-to-date",
+            "--width",
+            "64",
+        ])
+        .unwrap();
+        assert!(cli.export_job().is_none());
+        let job = cli.batch_job().unwrap();
+        assert_eq!(
+            job.inputs,
+            vec![PathBuf::from("a.svg"), PathBuf::from("icons")]
+        );
+        assert_eq!(job.format, ExportFormat::WebP);
+        assert_eq!(job.width, Some(64));
+        assert_eq!(job.options.jobs, 3);
+        assert!(job.options.skip_up_to_date);
+        assert!(!job.options.keep_going);
+    }
+
+    #[test]
+    fn test_sequence_args() {
+        let cli = parse(&[
+            "frames",
+            "--batch",
+            "out",
+            "--sequence",
+            "--sequence-fit",
+            "cover",
+            "--sequence-index",
+            "ffconcat",
+        ])
+        .unwrap();
+        let sequence = cli.batch_job().unwrap().sequence.unwrap();
+        assert_eq!(sequence.prefix, "frame_");
+        assert_eq!(sequence.fit, FrameFit::Cover);
+        assert_eq!(sequence.index, Some(SequenceIndex::FfConcat));
+
+        let job = parse(&["frames", "--batch", "out"])
+            .unwrap()
+            .batch_job()
+            .unwrap();
+        assert!(job.sequence.is_none());
+        assert!(parse(&["frames", "--batch", "out", "--sequence-prefix", "x"]).is_err());
+    }
+
+    #[test]
+    fn test_batch_conflicts_with_export() {
+        assert!(parse(&["a.svg", "--batch", "out", "--export", "o.png"]).is_err());
+        assert!(parse(&["a.svg", "--jobs", "2"]).is_err());
+        assert!(parse(&["a.svg", "--batch", "out", "--format", "gif"]).is_err());
+    }
+
+    #[test]
+    fn test_zero_size_rejected() {
+        assert!(parse(&["in.svg", "--export", "o.png", "--width", "0"]).is_err());
+        assert!(parse(&["in.svg", "--export", "o.png", "--height", "0"]).is_err());
+        assert!(parse(&["a.svg", "--batch", "out", "--width", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_region_requires_export() {
+        assert!(parse(&["in.svg", "--export-region", "0,0,1,1"]).is_err());
+        assert!(parse(&["in.svg", "--export", "o.png", "--region-padding", "2"]).is_err());
+    }
+
+    #[test]
+    fn test_region_and_element_conflict() {
+        let result = parse(&[
+            "in.svg",
+            "--export",
+            "o.png",
+            "--export-region",
+            "0,0,1,1",
+            "--export-element",
+            "a",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_degenerate_region_rejected() {
+        let err =
+            parse(&["in.svg", "--export", "o.png", "--export-region", "0,0,0,5"]).unwrap_err();
+        assert!(err.to_string().contains("zero area"));
+    }
+
+    #[test]
+    fn test_several_files_open_in_gui() {
+        let cli = parse(&["a.svg", "other/b.svg", "c.svg"]).unwrap();
+        assert!(cli.export_job().is_none() && cli.batch_job().is_none());
+        assert_eq!(cli.more_inputs.len(), 2);
+        assert!(parse(&["a.svg", "b.svg", "--export", "out.png"]).is_err());
+        assert!(parse(&["--stdin", "b.svg"]).is_err());
+    }
+
+    #[test]
+    fn test_info_args() {
+        assert!(parse(&["in.svg", "--info"]).unwrap().info);
+        assert!(parse(&["--stdin", "--info"]).unwrap().info);
+        assert!(parse(&["--info"]).is_err());
+        assert!(parse(&["in.svg", "--info", "--export", "out.png"]).is_err());
+    }
+
+    #[test]
+    fn test_diff_args() {
+        let cli = parse(&[
+            "--diff",
+            "a.svg",
+            "b.svg",
+            "--out",
+            "diff.png",
+            "--threshold",
+            "0.5",
+        ])
+        .unwrap();
+        let job = cli.diff_job().unwrap();
+        assert_eq!((job.a, job.b), ("a.svg".into(), "b.svg".into()));
+        assert_eq!(job.out, Some(PathBuf::from("diff.png")));
+        assert_eq!(cli.threshold, 0.5);
+        assert!(parse(&["--diff", "a.svg"]).is_err());
+        assert!(parse(&["--out", "diff.png"]).is_err());
+        assert!(parse(&["in.svg", "--diff", "a.svg", "b.svg"]).is_err());
+        assert!(parse(&["--diff", "a.svg", "b.svg", "--export", "o.png"]).is_err());
+    }
+
+    #[test]
+    fn test_folder_info_args() {
+        let cli = parse(&["--folder-info", "icons", "--json"]).unwrap();
+        assert_eq!(cli.folder_info, Some(PathBuf::from("icons")));
+        assert!(cli.json);
+        assert!(parse(&["--json"]).is_err());
+        assert!(parse(&["in.svg", "--folder-info", "icons"]).is_err());
+        assert!(parse(&["--folder-info", "icons", "--batch", "out"]).is_err());
+    }
+}
@@ -0,0 +1,55 @@
+//! Distances measured on the canvas.
+//!
+//! Measurements are kept in SVG units, so they stay on the features they were
+//! taken from however the view is zoomed, panned, rotated or mirrored.
+
+/// A line measured between two document points.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Measurement {
+    pub start: [f32; 2],
+    pub end: [f32; 2],
+}
+
+impl Measurement {
+    pub fn new(start: [f32; 2], end: [f32; 2]) -> Self {
+        Self { start, end }
+    }
+
+    /// Δx and Δy from start to end.
+    pub fn delta(&self) -> [f32; 2] {
+        [self.end[0] - self.start[0], self.end[1] - self.start[1]]
+    }
+
+    pub fn length(&self) -> f32 {
+        let [dx, dy] = self.delta();
+        dx.hypot(dy)
+    }
+
+    /// Whether start and end are the same point, as after a click.
+    pub fn is_empty(&self) -> bool {
+        self.length() == 0.0
+    }
+
+    /// The length, then Δx and Δy, to one decimal place.
+    pub fn label(&self) -> String {
+        let [dx, dy] = self.delta();
+        format!(
+            "{:.1} (\u{0394}x {dx:.1}, \u{0394}y {dy:.1})",
+            self.length()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measurement() {
+        let m = Measurement::new([10.0, 20.0], [40.0, -20.0]);
+        assert_eq!(m.delta(), [30.0, -40.0]);
+        assert_eq!(m.length(), 50.0);
+        assert_eq!(m.label(), "50.0 (\u{0394}x 30.0, \u{0394}y -40.0)");
+        assert!(Measurement::new([1.0, 1.0], [1.0, 1.0]).is_empty());
+    }
+}
@@ -0,0 +1,249 @@
+//! Writing a document as a single-page PDF.
+//!
+//! A vector page is drawn from the parsed tree by `krilla-svg`, so paths and
+//! text stay sharp at any zoom; only filters are rasterized. The fallback
+//! page holds one Flate-compressed RGB image scaled to fill it, with its
+//! alpha as a soft mask when the export keeps transparency.
+
+use std::io::Write;
+use std::path::Path;
+
+use krilla::color::rgb;
+use krilla::geom::{PathBuilder, Rect, Size, Transform};
+use krilla::page::PageSettings;
+use krilla::paint::Fill;
+use krilla_svg::{SurfaceExt, SvgSettings};
+
+use crate::error::{Result, SvgError};
+
+/// PDF units per inch.
+pub const POINTS_PER_INCH: f32 = 72.0;
+
+/// Write the part of `tree` inside `area`, in SVG units, as a PDF page of
+/// vectors at `points_per_unit`, over `background` if given.
+pub fn write_vector_pdf(
+    tree: &usvg::Tree,
+    area: tiny_skia::Rect,
+    points_per_unit: f32,
+    background: Option<[u8; 3]>,
+    output_path: &Path,
+) -> Result<()> {
+    let bytes = vector_pdf(tree, area, points_per_unit, background)?;
+    std::fs::write(output_path, bytes)?;
+    Ok(())
+}
+
+/// The bytes of the PDF [`write_vector_pdf`] writes.
+fn vector_pdf(
+    tree: &usvg::Tree,
+    area: tiny_skia::Rect,
+    points_per_unit: f32,
+    background: Option<[u8; 3]>,
+) -> Result<Vec<u8>> {
+    let (page_w, page_h) = (
+        area.width() * points_per_unit,
+        area.height() * points_per_unit,
+    );
+    let page = PageSettings::from_wh(page_w, page_h)
+        .filter(|_| page_w.is_finite() && page_h.is_finite())
+        .ok_or_else(|| SvgError::Export(format!("a PDF page can't be {page_w}×{page_h} pt")))?;
+    let size = Size::from_wh(tree.size().width(), tree.size().height())
+        .ok_or_else(|| SvgError::Export("the document has no size".into()))?;
+
+    let mut document = krilla::Document::new();
+    let mut page = document.start_page_with(page);
+    let mut surface = page.surface();
+    surface.push_transform(&Transform::from_scale(points_per_unit, points_per_unit));
+    if let Some([r, g, b]) = background {
+        let mut builder = PathBuilder::new();
+        if let Some(rect) = Rect::from_xywh(0.0, 0.0, area.width(), area.height()) {
+            builder.push_rect(rect);
+        }
+        if let Some(path) = builder.finish() {
+            surface.set_fill(Some(Fill {
+                paint: rgb::Color::new(r, g, b).into(),
+                ..Fill::default()
+            }));
+            surface.draw_path(&path);
+        }
+    }
+    surface.push_transform(&Transform::from_translate(-area.x(), -area.y()));
+    let drawn = surface.draw_svg(tree, size, SvgSettings::default());
+    surface.pop();
+    surface.pop();
+    surface.finish();
+    page.finish();
+    drawn.ok_or_else(|| SvgError::Export("the document couldn't be drawn as PDF".into()))?;
+    document
+        .finish()
+        .map_err(|e| SvgError::Export(format!("the PDF couldn't be written: {e}")))
+}
+
+/// Write a `width`×`height` image as a PDF page of `page_size` points.
+/// `rgb` has three bytes a pixel, and `alpha`, if given, one.
+pub fn write_image_pdf(
+    rgb: &[u8],
+    alpha: Option<&[u8]>,
+    (width, height): (u32, u32),
+    page_size: (f32, f32),
+    output_path: &Path,
+) -> Result<()> {
+    let bytes = image_pdf(rgb, alpha, (width, height), page_size)?;
+    std::fs::write(output_path, bytes)?;
+    Ok(())
+}
+
+/// The bytes of the PDF [`write_image_pdf`] writes.
+fn image_pdf(
+    rgb: &[u8],
+    alpha: Option<&[u8]>,
+    (width, height): (u32, u32),
+    (page_w, page_h): (f32, f32),
+) -> Result<Vec<u8>> {
+    if !(page_w > 0.0 && page_h > 0.0 && page_w.is_finite() && page_h.is_finite()) {
+        return Err(SvgError::Export(format!(
+            "a PDF page can't be {page_w}×{page_h} pt"
+        )));
+    }
+    let mut pdf = PdfWriter::new();
+    pdf.object(b"<< /Type /Catalog /Pages 2 0 R >>");
+    pdf.object(b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>");
+    pdf.object(
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {page_w:.3} {page_h:.3}] \
+             /Resources << /XObject << /Im0 5 0 R >> >> /Contents 4 0 R >>"
+        )
+        .as_bytes(),
+    );
+    let content = format!("q {page_w:.3} 0 0 {page_h:.3} 0 0 cm /Im0 Do Q");
+    pdf.stream("", content.as_bytes());
+    let mask = if alpha.is_some() { " /SMask 6 0 R" } else { "" };
+    pdf.stream(
+        &format!(
+            "/Type /XObject /Subtype /Image /Width {width} /Height {height} \
+             /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode{mask}"
+        ),
+        &deflate(rgb)?,
+    );
+    if let Some(alpha) = alpha {
+        pdf.stream(
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {width} /Height {height} \
+                 /ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /FlateDecode"
+            ),
+            &deflate(alpha)?,
+        );
+    }
+    Ok(pdf.finish())
+}
+
+fn deflate(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// Objects numbered from 1 in the order they're added, with the
+/// cross-reference table written at the end.
+struct PdfWriter {
+    bytes: Vec<u8>,
+    offsets: Vec<usize>,
+}
+
+impl PdfWriter {
+    fn new() -> Self {
+        // The comment's high bytes mark the file as binary
+        Self {
+            bytes: b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec(),
+            offsets: Vec::new(),
+        }
+    }
+
+    fn object(&mut self, body: &[u8]) {
+        self.offsets.push(self.bytes.len());
+        let number = self.offsets.len();
+        self.bytes
+            .extend_from_slice(format!("{number} 0 obj\n").as_bytes());
+        self.bytes.extend_from_slice(body);
+        self.bytes.extend_from_slice(b"\nendobj\n");
+    }
+
+    /// A stream object with `entries` added to its dictionary.
+    fn stream(&mut self, entries: &str, data: &[u8]) {
+        let mut body = format!("<< {entries} /Length {} >>\nstream\n", data.len()).into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\nendstream");
+        self.object(&body);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let xref = self.bytes.len();
+        let count = self.offsets.len() + 1;
+        let mut table = format!("xref\n0 {count}\n0000000000 65535 f \n");
+        for offset in &self.offsets {
+            table.push_str(&format!("{offset:010} 00000 n \n"));
+        }
+        table.push_str(&format!(
+            "trailer\n<< /Size {count} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n"
+        ));
+        self.bytes.extend_from_slice(table.as_bytes());
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Check `pdf`'s structure (every cross-reference entry points at its
+    /// object) and return the page's MediaBox.
+    pub(crate) fn parse_page_size(pdf: &[u8]) -> [f32; 4] {
+        assert!(pdf.starts_with(b"%PDF-1."));
+        // Byte for byte, so offsets still line up
+        let text: String = pdf
+            .iter()
+            .map(|&b| if b.is_ascii() { b as char } else { '.' })
+            .collect();
+        assert!(text.trim_end().ends_with("%%EOF"));
+        let startxref = text.rfind("startxref\n").unwrap();
+        let xref: usize = text[startxref + 10..]
+            .lines()
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(text[xref..].starts_with("xref\n"));
+        let mut lines = text[xref..].lines().skip(1);
+        let count: usize = lines.next().unwrap()[2..].parse().unwrap();
+        // After the free entry for object 0
+        let mut entries = lines.skip(1);
+        for number in 1..count {
+            let entry = entries.next().unwrap();
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(text[offset..].starts_with(&format!("{number} 0 obj\n")));
+        }
+        // With or without a space before the array, as writers differ
+        let media_box = text.find("/MediaBox").unwrap() + 9;
+        let media_box = media_box + text[media_box..].find('[').unwrap() + 1;
+        let end = media_box + text[media_box..].find(']').unwrap();
+        let values: Vec<f32> = text[media_box..end]
+            .split_whitespace()
+            .map(|v| v.parse().unwrap())
+            .collect();
+        values.try_into().unwrap()
+    }
+
+    #[test]
+    fn test_image_pdf_structure() {
+        let rgb = [255u8, 0, 0, 0, 0, 255];
+        let pdf = image_pdf(&rgb, None, (2, 1), (150.0, 75.0)).unwrap();
+        assert_eq!(parse_page_size(&pdf), [0.0, 0.0, 150.0, 75.0]);
+        assert!(!String::from_utf8_lossy(&pdf).contains("/SMask"));
+
+        let pdf = image_pdf(&rgb, Some(&[255, 0]), (2, 1), (1.5, 0.75)).unwrap();
+        assert_eq!(parse_page_size(&pdf), [0.0, 0.0, 1.5, 0.75]);
+        assert!(String::from_utf8_lossy(&pdf).contains("/SMask 6 0 R"));
+
+        assert!(image_pdf(&rgb, None, (2, 1), (0.0, 75.0)).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+//! Coalescing reloads of a file that keeps changing while it renders.
+//!
+//! A file being edited live can be saved faster than it renders, especially
+//! when it's heavy with filters. At most one reload renders at a time. A
+//! change that arrives meanwhile cancels it, and once it has stopped the
+//! newest content is loaded instead, so versions in between are never shown
+//! and what ends up on screen always matches the file as last saved.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// What to do with a reload that has just finished or stopped.
+#[derive(Clone, Debug)]
+pub enum Finished {
+    /// Show it; `skipped` versions were superseded since the last one shown.
+    Show { skipped: usize },
+    /// Drop it, it's out of date: start another reload, cancelled with this flag.
+    Restart(Arc<AtomicBool>),
+}
+
+/// Reload bookkeeping for the open file: the reload in flight, if any, and
+/// whether a newer version is waiting for it to stop.
+#[derive(Debug, Default)]
+pub struct ReloadQueue {
+    /// Cancel flag of the reload in flight
+    in_flight: Option<Arc<AtomicBool>>,
+    /// The file changed after the reload in flight started
+    queued: bool,
+    skipped: usize,
+}
+
+impl ReloadQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.in_flight.is_some()
+    }
+
+    /// The file changed. Returns the cancel flag for a reload to start now,
+    /// or `None` when one is already in flight; that one is cancelled and the
+    /// new version loaded once it stops.
+    pub fn changed(&mut self) -> Option<Arc<AtomicBool>> {
+        match &self.in_flight {
+            Some(cancel) => {
+                cancel.store(true, Ordering::Relaxed);
+                // Either the version in flight or the one already queued is
+                // never shown now
+                self.skipped += 1;
+                self.queued = true;
+                None
+            }
+            None => Some(self.start()),
+        }
+    }
+
+    /// The reload in flight finished, failed or stopped after being cancelled.
+    pub fn finished(&mut self) -> Finished {
+        self.in_flight = None;
+        if std::mem::take(&mut self.queued) {
+            Finished::Restart(self.start())
+        } else {
+            Finished::Show {
+                skipped: std::mem::take(&mut self.skipped),
+            }
+        }
+    }
+
+    /// Forget any reload, cancelling the one in flight; another file is being
+    /// opened.
+    pub fn clear(&mut self) {
+        if let Some(cancel) = self.in_flight.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+        self.queued = false;
+        self.skipped = 0;
+    }
+
+    fn start(&mut self) -> Arc<AtomicBool> {
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.in_flight = Some(Arc::clone(&cancel));
+        cancel
+    }
+}
+
+/// "N intermediate versions skipped", for the reload message.
+pub fn skipped_message(skipped: usize) -> Option<String> {
+    match skipped {
+        0 => None,
+        1 => Some("Reloaded (1 intermediate version skipped)".into()),
+        n => Some(format!("Reloaded ({n} intermediate versions skipped)")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A reload of file version `version`, started at `start` (ms).
+    struct Render {
+        version: usize,
+        start: u32,
+        duration: u32,
+        cancel: Arc<AtomicBool>,
+    }
+
+    /// Drive a queue through saves at `saves` (ms), where rendering version
+    /// `v` takes `durations[v]` ms and notices cancellation within `check` ms.
+    /// Returns each shown version with its skipped count, and the most
+    /// renders ever in flight at once.
+    fn simulate(saves: &[u32], durations: &[u32], check: u32) -> (Vec<(usize, usize)>, usize) {
+        let mut queue = ReloadQueue::new();
+        let mut shown = Vec::new();
+        let mut in_flight: Vec<Render> = Vec::new();
+        let mut max_in_flight = 0;
+        let mut latest = 0;
+        let mut saves = saves.iter().copied().peekable();
+        for now in 0..=100_000 {
+            // Finish renders first, so one ending as a save lands is still shown
+            if let Some(i) = in_flight.iter().position(|r| {
+                let done = now >= r.start + r.duration;
+                let stopped = r.cancel.load(Ordering::Relaxed) && (now - r.start) % check == 0;
+                done || stopped
+            }) {
+                let render = in_flight.remove(i);
+                match queue.finished() {
+                    Finished::Show { skipped } => shown.push((render.version, skipped)),
+                    Finished::Restart(cancel) => in_flight.push(Render {
+                        version: latest,
+                        start: now,
+                        duration: durations[latest],
+                        cancel,
+                    }),
+                }
+            }
+            while saves.next_if(|&t| t == now).is_some() {
+                latest += 1;
+                if let Some(cancel) = queue.changed() {
+                    in_flight.push(Render {
+                        version: latest,
+                        start: now,
+                        duration: durations[latest],
+                        cancel,
+                    });
+                }
+            }
+            max_in_flight = max_in_flight.max(in_flight.len());
+            if saves.peek().is_none() && in_flight.is_empty() {
+                break;
+            }
+        }
+        (shown, max_in_flight)
+    }
+
+    #[test]
+    fn test_slow_changes_each_show() {
+        let (shown, max) = simulate(&[0, 500, 1000], &[0, 100, 100, 100], 10);
+        assert_eq!(shown, vec![(1, 0), (2, 0), (3, 0)]);
+        assert_eq!(max, 1);
+    }
+
+    #[test]
+    fn test_fast_saves_skip_to_the_latest() {
+        // Version 1 renders for 2 s; four more saves land meanwhile
+        let (shown, max) = simulate(
+            &[0, 100, 200, 300, 400],
+            &[0, 2000, 2000, 2000, 2000, 2000],
+            50,
+        );
+        assert_eq!(shown, vec![(5, 4)]);
+        assert_eq!(max, 1);
+    }
+
+    #[test]
+    fn test_final_render_matches_last_save() {
+        // Saves every 300 ms while each render takes 1 s
+        let saves: Vec<u32> = (0..10).map(|i| i * 300).collect();
+        let (shown, max) = simulate(&saves, &[1000; 11], 100);
+        assert_eq!(shown.last().map(|(v, _)| *v), Some(10));
+        assert_eq!(shown.iter().map(|(_, s)| s).sum::<usize>(), 9);
+        assert_eq!(max, 1);
+    }
+
+    #[test]
+    fn test_render_that_cannot_stop_is_still_discarded() {
+        // One band: cancellation is only noticed when the render ends
+        let (shown, _) = simulate(&[0, 100], &[0, 1000, 200], 1000);
+        assert_eq!(shown, vec![(2, 1)]);
+    }
+
+    #[test]
+    fn test_clear_cancels_and_forgets() {
+        let mut queue = ReloadQueue::new();
+        let cancel = queue.changed().unwrap();
+        assert!(queue.changed().is_none());
+        queue.clear();
+        assert!(cancel.load(Ordering::Relaxed));
+        assert!(!queue.is_busy());
+        assert!(queue.changed().is_some());
+        assert!(matches!(queue.finished(), Finished::Show { skipped: 0 }));
+    }
+
+    #[test]
+    fn test_skipped_message() {
+        assert_eq!(skipped_message(0), None);
+        assert_eq!(
+            skipped_message(1).as_deref(),
+            Some("Reloaded (1 intermediate version skipped)")
+        );
+        assert_eq!(
+            skipped_message(3).as_deref(),
+            Some("Reloaded (3 intermediate versions skipped)")
+        );
+    }
+}
@@ -0,0 +1,340 @@
+//! Documents downloaded from `http://` and `https://` URLs.
+//!
+//! A URL is carried around as the document's path, like the virtual paths of
+//! archive entries; [`is_url`] tells them apart. Both schemes go through
+//! `ureq`, with TLS from rustls, so HTTPS doesn't depend on what's installed.
+//! Redirects are followed here rather than by `ureq`, so their locations are
+//! resolved as RFC 3986 says.
+//!
+//! Downloads give up after [`DOWNLOAD_TIMEOUT`] and refuse bodies larger than
+//! [`MAX_DOWNLOAD_SIZE`].
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::error::{Result, SvgError};
+
+/// How long a download may take, from connecting to the last byte.
+pub const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Largest document that will be downloaded.
+pub const MAX_DOWNLOAD_SIZE: u64 = 64 * 1024 * 1024;
+
+const MAX_REDIRECTS: u32 = 5;
+
+/// Whether `path` is an `http://` or `https://` URL rather than a file.
+pub fn is_url(path: &Path) -> bool {
+    path.to_str().is_some_and(|s| scheme(s).is_some())
+}
+
+/// The scheme of `url`, if it's one that can be downloaded.
+fn scheme(url: &str) -> Option<&'static str> {
+    let (scheme, rest) = url.split_once("://")?;
+    if rest.is_empty() {
+        return None;
+    }
+    ["http", "https"]
+        .into_iter()
+        .find(|s| s.eq_ignore_ascii_case(scheme))
+}
+
+fn network_error(url: &str, message: impl std::fmt::Display) -> SvgError {
+    SvgError::Network(format!("{url}: {message}"))
+}
+
+/// Limits on one download.
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    pub timeout: Duration,
+    pub max_size: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            timeout: DOWNLOAD_TIMEOUT,
+            max_size: MAX_DOWNLOAD_SIZE,
+        }
+    }
+}
+
+/// Download the body of `url`, following redirects.
+pub fn fetch(url: &str) -> Result<Vec<u8>> {
+    fetch_with(url, Limits::default())
+}
+
+/// Download the body of `url` within `limits`.
+pub fn fetch_with(url: &str, limits: Limits) -> Result<Vec<u8>> {
+    let deadline = Instant::now() + limits.timeout;
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .max_redirects(0)
+        .http_status_as_error(false)
+        .user_agent(concat!("svg-viewer/", env!("CARGO_PKG_VERSION")))
+        .accept("image/svg+xml, */*")
+        .build()
+        .into();
+    let mut url = url.to_string();
+    for _ in 0..=MAX_REDIRECTS {
+        if scheme(&url).is_none() {
+            return Err(network_error(&url, "not an http(s) URL"));
+        }
+        let left = deadline
+            .checked_duration_since(Instant::now())
+            .filter(|left| !left.is_zero())
+            .ok_or_else(|| network_error(&url, "timed out"))?;
+        let mut response = agent
+            .get(&url)
+            .config()
+            .timeout_global(Some(left))
+            .build()
+            .call()
+            .map_err(|e| download_error(&url, e, limits.max_size))?;
+
+        let status = response.status();
+        match status.as_u16() {
+            200..=299 => {}
+            301 | 302 | 303 | 307 | 308 => {
+                let location = response
+                    .headers()
+                    .get("location")
+                    .and_then(|value| value.to_str().ok())
+                    .ok_or_else(|| {
+                        network_error(&url, format!("HTTP {} without a location", status.as_u16()))
+                    })?;
+                url = resolve(&url, location)
+                    .ok_or_else(|| network_error(&url, format!("bad redirect to {location}")))?;
+                continue;
+            }
+            code => {
+                return Err(network_error(
+                    &url,
+                    format!(
+                        "HTTP {code} {}",
+                        status.canonical_reason().unwrap_or_default()
+                    ),
+                ))
+            }
+        }
+        return response
+            .body_mut()
+            .with_config()
+            .limit(limits.max_size)
+            .read_to_vec()
+            .map_err(|e| download_error(&url, e, limits.max_size));
+    }
+    Err(network_error(&url, "too many redirects"))
+}
+
+/// `location` from a redirect of `base`, made absolute.
+fn resolve(base: &str, location: &str) -> Option<String> {
+    let joined = url::Url::parse(base).ok()?.join(location).ok()?;
+    Some(joined.into())
+}
+
+fn download_error(url: &str, error: ureq::Error, max_size: u64) -> SvgError {
+    match error {
+        ureq::Error::Timeout(_) => network_error(url, "timed out"),
+        ureq::Error::Io(e) if e.kind() == std::io::ErrorKind::TimedOut => {
+            network_error(url, "timed out")
+        }
+        ureq::Error::BodyExceedsLimit(_) => network_error(
+            url,
+            format!(
+                "larger than the {} download limit",
+                crate::svg_document::format_size(max_size)
+            ),
+        ),
+        e => network_error(url, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::time::Instant;
+
+    /// Serve each of `responses` to one connection, in order, on a local
+    /// port. Returns the base URL, and the request line and headers of each
+    /// request as they arrive.
+    fn serve_recording(responses: Vec<Vec<u8>>) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                // Read the request headers before answering
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    request.push_str(&line);
+                    line.clear();
+                }
+                let _ = tx.send(request);
+                let _ = stream.write_all(&response);
+            }
+        });
+        (format!("http://{addr}"), rx)
+    }
+
+    fn serve(responses: Vec<Vec<u8>>) -> String {
+        serve_recording(responses).0
+    }
+
+    fn ok(body: &str) -> Vec<u8> {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .into_bytes()
+    }
+
+    const SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10"/></svg>"#;
+
+    #[test]
+    fn test_is_url() {
+        assert!(is_url(Path::new("https://example.com/logo.svg")));
+        assert!(is_url(Path::new("HTTP://example.com")));
+        assert!(!is_url(Path::new("logo.svg")));
+        assert!(!is_url(Path::new("ftp://example.com/logo.svg")));
+        assert!(!is_url(Path::new("https://")));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let base = "http://a.com/icons/x.svg?v=1";
+        assert_eq!(resolve(base, "/y.svg").unwrap(), "http://a.com/y.svg");
+        assert_eq!(resolve(base, "y.svg").unwrap(), "http://a.com/icons/y.svg");
+        assert_eq!(resolve(base, "../y.svg").unwrap(), "http://a.com/y.svg");
+        assert_eq!(
+            resolve(base, "?v=2").unwrap(),
+            "http://a.com/icons/x.svg?v=2"
+        );
+        assert_eq!(
+            resolve(base, "//cdn.example.com/a.svg").unwrap(),
+            "http://cdn.example.com/a.svg"
+        );
+        assert_eq!(
+            resolve(base, "https://b.com/y.svg").unwrap(),
+            "https://b.com/y.svg"
+        );
+    }
+
+    #[test]
+    fn test_fetch_with_length_and_chunked() {
+        let base = serve(vec![
+            ok(SVG),
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\n<svg\r\n3\r\n/>!\r\n0\r\n\r\n"
+                .to_vec(),
+            b"HTTP/1.0 200 OK\r\n\r\nuntil close".to_vec(),
+        ]);
+        assert_eq!(fetch(&format!("{base}/a.svg")).unwrap(), SVG.as_bytes());
+        assert_eq!(fetch(&format!("{base}/b.svg")).unwrap(), b"<svg/>!");
+        assert_eq!(fetch(&format!("{base}/c.svg")).unwrap(), b"until close");
+    }
+
+    #[test]
+    fn test_redirect_is_followed() {
+        let redirect = |location: &str| {
+            format!("HTTP/1.1 302 Found\r\nLocation: {location}\r\nContent-Length: 0\r\n\r\n")
+                .into_bytes()
+        };
+        let (base, requests) = serve_recording(vec![
+            redirect("/moved.svg"),
+            ok(SVG),
+            redirect("?v=2"),
+            ok(SVG),
+            ok(SVG),
+        ]);
+        assert_eq!(fetch(&format!("{base}/a.svg")).unwrap(), SVG.as_bytes());
+        let moved = requests.recv().unwrap();
+        assert!(moved.starts_with("GET /a.svg "), "{moved}");
+        let moved = requests.recv().unwrap();
+        assert!(moved.starts_with("GET /moved.svg "), "{moved}");
+
+        // A query alone replaces the query of the same path
+        assert_eq!(
+            fetch(&format!("{base}/icons/b.svg")).unwrap(),
+            SVG.as_bytes()
+        );
+        requests.recv().unwrap();
+        let moved = requests.recv().unwrap();
+        assert!(moved.starts_with("GET /icons/b.svg?v=2 "), "{moved}");
+
+        // A location without a scheme keeps this one's
+        let authority = base.trim_start_matches("http://");
+        let (other, other_requests) =
+            serve_recording(vec![redirect(&format!("//{authority}/c.svg"))]);
+        assert_eq!(fetch(&format!("{other}/a.svg")).unwrap(), SVG.as_bytes());
+        assert!(other_requests.recv().unwrap().starts_with("GET /a.svg "));
+        let moved = requests.recv().unwrap();
+        assert!(moved.starts_with("GET /c.svg "), "{moved}");
+    }
+
+    #[test]
+    fn test_host_header_carries_the_port() {
+        let (base, requests) = serve_recording(vec![ok(SVG)]);
+        fetch(&format!("{base}/a.svg")).unwrap();
+        let authority = base.trim_start_matches("http://");
+        let request = requests.recv().unwrap().to_ascii_lowercase();
+        assert!(
+            request.contains(&format!("\r\nhost: {authority}\r\n")),
+            "{request}"
+        );
+    }
+
+    #[test]
+    fn test_http_errors() {
+        let base = serve(vec![
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_vec()
+        ]);
+        let err = fetch(&format!("{base}/missing.svg")).unwrap_err();
+        assert!(matches!(err, SvgError::Network(_)));
+        assert!(err.to_string().contains("HTTP 404 Not Found"), "{err}");
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let base = serve(vec![ok(SVG), b"HTTP/1.1 200 OK\r\n\r\n0123456789".to_vec()]);
+        let limits = Limits {
+            max_size: 8,
+            ..Limits::default()
+        };
+        // Refused from the declared length, and while reading without one
+        for name in ["a.svg", "b.svg"] {
+            let err = fetch_with(&format!("{base}/{name}"), limits).unwrap_err();
+            assert!(err.to_string().contains("download limit"), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_timeout() {
+        // Accept the connection but never answer
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let limits = Limits {
+            timeout: Duration::from_millis(200),
+            ..Limits::default()
+        };
+        let start = Instant::now();
+        let err = fetch_with(&format!("{base}/slow.svg"), limits).unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{err}");
+        assert!(start.elapsed() < Duration::from_secs(5));
+        drop(listener);
+    }
+
+    #[test]
+    fn test_load_document_from_url() {
+        let base = serve(vec![ok(SVG)]);
+        let url = format!("{base}/icons/logo.svg");
+        let doc = crate::svg_document::SvgDocument::load(Path::new(&url)).unwrap();
+        assert!(doc.is_remote());
+        assert_eq!(doc.path(), Path::new(&url));
+        assert_eq!(doc.filename(), "logo.svg");
+        assert_eq!((doc.width(), doc.height()), (10.0, 10.0));
+    }
+}
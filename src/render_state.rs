@@ -0,0 +1,274 @@
+//! Bookkeeping for when the viewer's texture has to be re-rendered, and how far
+//! what is on screen may be from what the viewport asks for.
+
+use std::time::{Duration, Instant};
+
+/// How long zooming must pause before the deferred re-render runs.
+pub const ZOOM_SETTLE: Duration = Duration::from_millis(150);
+
+/// How closely the image on screen matches the current viewport.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderFreshness {
+    Fresh,
+    /// An older render stretched by `ratio` until zooming settles.
+    ScaledFromZoom {
+        ratio: f32,
+    },
+    /// The previous file is still shown while the next one loads.
+    StaleDocument,
+    /// A render or first load is under way.
+    Rendering,
+}
+
+impl RenderFreshness {
+    /// Short label for the canvas badge; `None` when the image is up to date.
+    pub fn badge_text(&self) -> Option<&'static str> {
+        match self {
+            RenderFreshness::Fresh => None,
+            RenderFreshness::ScaledFromZoom { .. } => Some("preview \u{2013} rendering\u{2026}"),
+            RenderFreshness::StaleDocument => Some("previous file \u{2013} loading\u{2026}"),
+            RenderFreshness::Rendering => Some("rendering\u{2026}"),
+        }
+    }
+}
+
+/// Something that affects whether the texture is current.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderEvent {
+    /// The view changed in a way that needs a render on the next frame.
+    Invalidate,
+    /// The zoom changed; re-render once it has been still for `ZOOM_SETTLE`.
+    Zoomed {
+        at: Instant,
+    },
+    /// Once per frame, to let a settled zoom turn into a render.
+    Tick {
+        now: Instant,
+    },
+    /// The texture was rendered for the current viewport.
+    Rendered,
+    /// A render for the current viewport was handed to a worker thread.
+    RenderStarted,
+    /// The worker's render arrived; it is current unless the view changed
+    /// while it ran.
+    RenderFinished,
+    LoadStarted {
+        had_document: bool,
+    },
+    /// A background load delivered a document with its first render.
+    LoadFinished,
+    LoadFailed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderState {
+    Fresh,
+    /// A render is due on the next frame.
+    Dirty,
+    /// Zooming; the old render is scaled until `since + ZOOM_SETTLE`.
+    Settling {
+        since: Instant,
+    },
+    Loading {
+        had_document: bool,
+    },
+    /// A worker is rendering; the old render is shown until it arrives.
+    Rendering,
+}
+
+impl Default for RenderState {
+    /// The first frame always renders.
+    fn default() -> Self {
+        RenderState::Dirty
+    }
+}
+
+impl RenderState {
+    pub fn transition(self, event: RenderEvent) -> RenderState {
+        use RenderEvent as E;
+        use RenderState as S;
+        match (self, event) {
+            // A load replaces the document wholesale, so view changes made to the
+            // old one in the meantime don't matter
+            (S::Loading { .. }, E::LoadFinished) => S::Fresh,
+            (S::Loading { .. }, E::LoadFailed) => S::Dirty,
+            (S::Loading { .. }, E::LoadStarted { had_document }) => S::Loading { had_document },
+            (S::Loading { .. }, _) => self,
+
+            (_, E::LoadStarted { had_document }) => S::Loading { had_document },
+            (_, E::Invalidate) => S::Dirty,
+            // A render already due will pick up the new zoom
+            (S::Dirty, E::Zoomed { .. }) => S::Dirty,
+            (_, E::Zoomed { at }) => S::Settling { since: at },
+            (S::Settling { since }, E::Tick { now })
+                if now.duration_since(since) >= ZOOM_SETTLE =>
+            {
+                S::Dirty
+            }
+            (_, E::Rendered) => S::Fresh,
+            (S::Dirty, E::RenderStarted) => S::Rendering,
+            (S::Rendering, E::RenderFinished) => S::Fresh,
+            (
+                _,
+                E::Tick { .. }
+                | E::LoadFinished
+                | E::LoadFailed
+                | E::RenderStarted
+                | E::RenderFinished,
+            ) => self,
+        }
+    }
+
+    pub fn apply(&mut self, event: RenderEvent) {
+        *self = self.transition(event);
+    }
+
+    /// Whether the canvas should render this frame.
+    pub fn needs_render(&self) -> bool {
+        matches!(self, RenderState::Dirty)
+    }
+
+    /// Whether frames must keep coming without input for the state to progress.
+    pub fn needs_repaint(&self) -> bool {
+        matches!(
+            self,
+            RenderState::Settling { .. } | RenderState::Loading { .. } | RenderState::Rendering
+        )
+    }
+
+    /// `zoom_ratio` is the current zoom divided by the zoom of the shown render.
+    pub fn freshness(&self, zoom_ratio: f32) -> RenderFreshness {
+        match self {
+            RenderState::Fresh => RenderFreshness::Fresh,
+            RenderState::Dirty => RenderFreshness::Rendering,
+            RenderState::Settling { .. } => RenderFreshness::ScaledFromZoom { ratio: zoom_ratio },
+            RenderState::Rendering if zoom_ratio != 1.0 => {
+                RenderFreshness::ScaledFromZoom { ratio: zoom_ratio }
+            }
+            RenderState::Rendering => RenderFreshness::Rendering,
+            RenderState::Loading { had_document: true } => RenderFreshness::StaleDocument,
+            RenderState::Loading {
+                had_document: false,
+            } => RenderFreshness::Rendering,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_starts_dirty() {
+        assert!(RenderState::default().needs_render());
+    }
+
+    #[test]
+    fn test_zoom_settles_into_render() {
+        let t0 = Instant::now();
+        let state = RenderState::Fresh.transition(RenderEvent::Zoomed { at: t0 });
+        assert_eq!(state, RenderState::Settling { since: t0 });
+        assert_eq!(
+            state.freshness(1.5),
+            RenderFreshness::ScaledFromZoom { ratio: 1.5 }
+        );
+        assert!(state.needs_repaint());
+
+        let early = state.transition(RenderEvent::Tick {
+            now: t0 + Duration::from_millis(100),
+        });
+        assert_eq!(early, state);
+
+        let settled = early.transition(RenderEvent::Tick {
+            now: t0 + ZOOM_SETTLE,
+        });
+        assert!(settled.needs_render());
+        assert_eq!(
+            settled.transition(RenderEvent::Rendered),
+            RenderState::Fresh
+        );
+    }
+
+    #[test]
+    fn test_continued_zoom_restarts_settle_timer() {
+        let t0 = Instant::now();
+        let t1 = t0 + Duration::from_millis(120);
+        let state = RenderState::Fresh
+            .transition(RenderEvent::Zoomed { at: t0 })
+            .transition(RenderEvent::Zoomed { at: t1 })
+            .transition(RenderEvent::Tick {
+                now: t0 + ZOOM_SETTLE,
+            });
+        assert_eq!(state, RenderState::Settling { since: t1 });
+    }
+
+    #[test]
+    fn test_invalidate_overrides_settling() {
+        let state = RenderState::Settling {
+            since: Instant::now(),
+        };
+        assert!(state.transition(RenderEvent::Invalidate).needs_render());
+        // Zooming while a render is due doesn't postpone it
+        let dirty = RenderState::Dirty.transition(RenderEvent::Zoomed { at: Instant::now() });
+        assert!(dirty.needs_render());
+    }
+
+    #[test]
+    fn test_loading() {
+        let state = RenderState::Settling {
+            since: Instant::now(),
+        }
+        .transition(RenderEvent::LoadStarted { had_document: true });
+        assert_eq!(state.freshness(1.0), RenderFreshness::StaleDocument);
+        assert!(!state.needs_render());
+
+        // View changes to the outgoing document are dropped
+        let state = state.transition(RenderEvent::Invalidate);
+        assert_eq!(state, RenderState::Loading { had_document: true });
+
+        assert_eq!(
+            state.transition(RenderEvent::LoadFinished),
+            RenderState::Fresh
+        );
+        assert!(state.transition(RenderEvent::LoadFailed).needs_render());
+    }
+
+    #[test]
+    fn test_first_load_is_rendering() {
+        let state = RenderState::Fresh.transition(RenderEvent::LoadStarted {
+            had_document: false,
+        });
+        assert_eq!(state.freshness(1.0), RenderFreshness::Rendering);
+    }
+
+    #[test]
+    fn test_background_render() {
+        let state = RenderState::Dirty.transition(RenderEvent::RenderStarted);
+        assert_eq!(state, RenderState::Rendering);
+        assert!(!state.needs_render());
+        assert!(state.needs_repaint());
+        assert_eq!(
+            state.freshness(2.0),
+            RenderFreshness::ScaledFromZoom { ratio: 2.0 }
+        );
+        assert_eq!(
+            state.transition(RenderEvent::RenderFinished),
+            RenderState::Fresh
+        );
+
+        // A render that arrives after the view changed again isn't current
+        let changed = state.transition(RenderEvent::Invalidate);
+        assert_eq!(changed.transition(RenderEvent::RenderFinished), changed);
+        let zoomed = state.transition(RenderEvent::Zoomed { at: Instant::now() });
+        assert_eq!(zoomed.transition(RenderEvent::RenderFinished), zoomed);
+    }
+
+    #[test]
+    fn test_badge_text() {
+        assert_eq!(RenderFreshness::Fresh.badge_text(), None);
+        assert_eq!(
+            RenderFreshness::ScaledFromZoom { ratio: 2.0 }.badge_text(),
+            Some("preview \u{2013} rendering\u{2026}")
+        );
+    }
+}
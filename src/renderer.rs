@@ -1,190 +1,2503 @@
+//! Rasterizing documents into pixmaps. The GPU texture upload used by the
+//! viewer is only built with the `gui` feature.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+
+#[cfg(feature = "gui")]
 use egui::{ColorImage, TextureHandle, TextureOptions};
-use tiny_skia::Pixmap;
+use serde::{Deserialize, Serialize};
+use tiny_skia::{Pixmap, PremultipliedColorU8};
 
+use crate::color_management::{ColorManagement, DisplayConversion};
 use crate::error::{Result, SvgError};
+use crate::export::{ExportRegion, MAX_EXPORT_DIM};
 use crate::svg_document::SvgDocument;
-use crate::viewport::Viewport;
+#[cfg(feature = "gui")]
+use crate::tiles::{PlacedTile, TileCache, TileSource, TileView, TILE_SIZE};
+use crate::viewport::{usable_dimension, Viewport};
 
-const MAX_RENDER_DIM: u32 = 4096;
+/// Largest pixmap width or height rendered in one go. Exports larger than
+/// this are rendered as tiles of at most this size and stitched.
+pub const MAX_RENDER_DIM: u32 = 4096;
+/// Rows in each band of a large export that's written as it's rendered.
+pub const EXPORT_BAND_ROWS: u32 = 1024;
 /// Cap render resolution to this many times the SVG's native size.
 /// Prevents filter-heavy SVGs from being rasterized at huge resolutions
 /// (e.g. a 100x100 SVG rendered at 1620x1620 makes feMorphology take 1.7s).
 /// GPU bilinear scaling handles the rest with no visible quality loss.
 pub const MAX_RENDER_SCALE: f32 = 4.0;
 
+/// A pixel counts as painted when its alpha is above this, so stray antialiasing
+/// from content just off the canvas doesn't count as content.
+pub const BLANK_ALPHA: u8 = 2;
+/// Pixmaps with more pixels than this are checked on a sampled grid.
+pub const BLANK_MAX_SAMPLES: usize = 1 << 16;
+
+/// Whether the pixmap has (as far as sampling can tell) nothing painted. Small
+/// pixmaps are checked exhaustively; larger ones on an evenly spaced grid of
+/// about `BLANK_MAX_SAMPLES` pixels, which can miss content thinner than the
+/// grid spacing.
+pub fn is_blank(pixmap: &Pixmap) -> bool {
+    let width = pixmap.width() as usize;
+    let height = pixmap.height() as usize;
+    let data = pixmap.data();
+    let painted = |x: usize, y: usize| data[(y * width + x) * 4 + 3] > BLANK_ALPHA;
+
+    let stride = if width * height <= BLANK_MAX_SAMPLES {
+        1
+    } else {
+        ((width * height) as f64 / BLANK_MAX_SAMPLES as f64)
+            .sqrt()
+            .ceil() as usize
+    };
+    // Sample the middle of each grid cell rather than its corner
+    let offset = stride / 2;
+    !(offset..height)
+        .step_by(stride)
+        .any(|y| (offset..width).step_by(stride).any(|x| painted(x, y)))
+}
+
+/// Logical on-screen size of the document at the viewport's zoom and rotation,
+/// capped to the area. Each side is at least one physical pixel so extreme
+/// aspect ratios stay visible; the major side is left exact.
+pub fn display_size(
+    svg_width: f32,
+    svg_height: f32,
+    viewport: &Viewport,
+    area_width: f32,
+    area_height: f32,
+    pixels_per_point: f32,
+) -> (f32, f32) {
+    let svg_w = usable_dimension(svg_width);
+    let svg_h = usable_dimension(svg_height);
+    let (effective_svg_w, effective_svg_h) = viewport.rotated_size(svg_w, svg_h);
+    let min_side = 1.0 / pixels_per_point;
+    (
+        (effective_svg_w * viewport.zoom)
+            .min(area_width)
+            .max(min_side),
+        (effective_svg_h * viewport.zoom)
+            .min(area_height)
+            .max(min_side),
+    )
+}
+
+/// Fraction of the display resolution the viewer renders at. Lower settings
+/// render fewer pixels and let the GPU upscale, trading sharpness for speed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RenderScale {
+    Quarter,
+    Half,
+    ThreeQuarters,
+    #[default]
+    Full,
+}
+
+impl RenderScale {
+    pub fn all() -> &'static [RenderScale] {
+        &[
+            RenderScale::Full,
+            RenderScale::ThreeQuarters,
+            RenderScale::Half,
+            RenderScale::Quarter,
+        ]
+    }
+
+    pub fn factor(&self) -> f32 {
+        match self {
+            RenderScale::Quarter => 0.25,
+            RenderScale::Half => 0.5,
+            RenderScale::ThreeQuarters => 0.75,
+            RenderScale::Full => 1.0,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            RenderScale::Quarter => "25%",
+            RenderScale::Half => "50%",
+            RenderScale::ThreeQuarters => "75%",
+            RenderScale::Full => "100%",
+        }
+    }
+}
+
+/// How edges are drawn. Crisp suits pixel art scaled up from a tiny viewBox:
+/// shapes aren't anti-aliased, embedded images and the canvas texture are
+/// scaled nearest-neighbour, and exports of the document are drawn the same.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RenderQuality {
+    #[default]
+    Smooth,
+    Crisp,
+}
+
+impl RenderQuality {
+    pub fn all() -> &'static [RenderQuality] {
+        &[RenderQuality::Smooth, RenderQuality::Crisp]
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            RenderQuality::Smooth => "Smooth",
+            RenderQuality::Crisp => "Crisp",
+        }
+    }
+
+    /// Set the rendering hints elements without their own `shape-rendering`
+    /// or `image-rendering` are parsed with.
+    pub fn apply(&self, options: &mut usvg::Options) {
+        let (shapes, images) = match self {
+            RenderQuality::Smooth => (
+                usvg::ShapeRendering::default(),
+                usvg::ImageRendering::default(),
+            ),
+            RenderQuality::Crisp => (
+                usvg::ShapeRendering::CrispEdges,
+                usvg::ImageRendering::OptimizeSpeed,
+            ),
+        };
+        options.shape_rendering = shapes;
+        options.image_rendering = images;
+    }
+}
+
+/// Pixmap size for a `display_w`×`display_h` logical display of a document whose
+/// rotated size is `rotated_w`×`rotated_h`. `render_scale` shrinks the physical
+/// size; the `MAX_RENDER_SCALE` and `MAX_RENDER_DIM` caps apply on top, so the
+/// smaller of the two limits wins.
+pub fn render_dimensions(
+    (display_w, display_h): (f32, f32),
+    (rotated_w, rotated_h): (f32, f32),
+    pixels_per_point: f32,
+    render_scale: f32,
+) -> (u32, u32) {
+    let render_w = (display_w * pixels_per_point * render_scale).round() as u32;
+    let render_h = (display_h * pixels_per_point * render_scale).round() as u32;
+
+    // Cap to MAX_RENDER_SCALE × native size so filter-heavy SVGs stay fast.
+    // GPU bilinear scaling handles further magnification.
+    let max_w = ((rotated_w * MAX_RENDER_SCALE).round() as u32).max(1);
+    let max_h = ((rotated_h * MAX_RENDER_SCALE).round() as u32).max(1);
+    (
+        render_w.clamp(1, max_w.min(MAX_RENDER_DIM)),
+        render_h.clamp(1, max_h.min(MAX_RENDER_DIM)),
+    )
+}
+
+/// Render the SVG document at the given viewport zoom level and return a pixmap.
+pub fn render_to_pixmap(
+    doc: &SvgDocument,
+    viewport: &Viewport,
+    area_width: f32,
+    area_height: f32,
+    pixels_per_point: f32,
+) -> Result<Pixmap> {
+    render_to_pixmap_scaled(
+        doc,
+        viewport,
+        area_width,
+        area_height,
+        pixels_per_point,
+        RenderScale::Full,
+    )
+}
+
+/// The empty canvas pixmap for the view, and the transform from SVG units
+/// into it.
+fn canvas_target(
+    doc: &SvgDocument,
+    viewport: &Viewport,
+    area_width: f32,
+    area_height: f32,
+    pixels_per_point: f32,
+    density: f32,
+) -> Result<(Pixmap, tiny_skia::Transform)> {
+    let svg_w = usable_dimension(doc.width());
+    let svg_h = usable_dimension(doc.height());
+
+    // Displayed size capped to the visible area
+    let display = display_size(
+        svg_w,
+        svg_h,
+        viewport,
+        area_width,
+        area_height,
+        pixels_per_point,
+    );
+    let (render_w, render_h) = render_dimensions(
+        display,
+        viewport.rotated_size(svg_w, svg_h),
+        pixels_per_point,
+        density,
+    );
+
+    let pixmap = Pixmap::new(render_w, render_h)
+        .ok_or_else(|| SvgError::Render("Failed to create pixmap".into()))?;
+
+    let transform = viewport.build_transform(svg_w, svg_h, render_w as f32, render_h as f32);
+    let transform = widen_subpixel_axes(transform, svg_w, svg_h, render_w, render_h);
+    Ok((pixmap, transform))
+}
+
+/// Like `render_to_pixmap`, at `render_scale` of the display resolution. The
+/// logical display size is unaffected.
+pub fn render_to_pixmap_scaled(
+    doc: &SvgDocument,
+    viewport: &Viewport,
+    area_width: f32,
+    area_height: f32,
+    pixels_per_point: f32,
+    render_scale: RenderScale,
+) -> Result<Pixmap> {
+    let (mut pixmap, transform) = canvas_target(
+        doc,
+        viewport,
+        area_width,
+        area_height,
+        pixels_per_point,
+        render_scale.factor(),
+    )?;
+    resvg::render(doc.tree(), transform, &mut pixmap.as_mut());
+    Ok(pixmap)
+}
+
+/// A view render is drawn at up to this many times the display resolution, so
+/// zooming in that far still shows it sharp without rendering again.
+pub const ZOOM_HEADROOM: f32 = 2.0;
+/// Largest view render drawn with headroom, in bytes; past it the headroom shrinks.
+pub const HEADROOM_BUDGET: usize = 64 * 1024 * 1024;
+
+/// How many times the display resolution to draw a `render_w`×`render_h` view
+/// of a document whose rotated size is `rotated_w`×`rotated_h`: up to
+/// [`ZOOM_HEADROOM`], as far as [`MAX_RENDER_DIM`], [`MAX_RENDER_SCALE`] and
+/// [`HEADROOM_BUDGET`] allow, and never less than 1.
+pub fn headroom_for((render_w, render_h): (u32, u32), (rotated_w, rotated_h): (f32, f32)) -> f32 {
+    let (w, h) = (render_w.max(1) as f32, render_h.max(1) as f32);
+    let by_dim = MAX_RENDER_DIM as f32 / w.max(h);
+    let by_scale = (rotated_w * MAX_RENDER_SCALE / w).min(rotated_h * MAX_RENDER_SCALE / h);
+    let by_budget = (HEADROOM_BUDGET as f32 / (w * h * 4.0)).sqrt();
+    ZOOM_HEADROOM
+        .min(by_dim)
+        .min(by_scale)
+        .min(by_budget)
+        .max(1.0)
+}
+
+/// The headroom [`Renderer::render_and_upload`] draws the view at.
+pub fn zoom_headroom(
+    doc: &SvgDocument,
+    viewport: &Viewport,
+    area_width: f32,
+    area_height: f32,
+    pixels_per_point: f32,
+    render_scale: RenderScale,
+) -> f32 {
+    let rotated = viewport.rotated_size(
+        usable_dimension(doc.width()),
+        usable_dimension(doc.height()),
+    );
+    let display = display_size(
+        doc.width(),
+        doc.height(),
+        viewport,
+        area_width,
+        area_height,
+        pixels_per_point,
+    );
+    let size = render_dimensions(display, rotated, pixels_per_point, render_scale.factor());
+    headroom_for(size, rotated)
+}
+
+/// Renders showing fewer physical pixels than this per SVG unit are
+/// supersampled when high quality downscaling is on.
+pub const SUPERSAMPLE_BELOW_SCALE: f32 = 0.5;
+/// How many times the target size a supersampled render is drawn at, per side.
+pub const SUPERSAMPLE_FACTOR: u32 = 2;
+/// Largest supersampled pixmap, in bytes; bigger targets render directly.
+pub const SUPERSAMPLE_BUDGET: usize = 64 * 1024 * 1024;
+
+/// How many times larger, per side, to draw a `render_w`×`render_h` view of a
+/// document whose rotated size is `rotated_w`×`rotated_h` before scaling it
+/// down: [`SUPERSAMPLE_FACTOR`] when fine detail is shrunk below
+/// [`SUPERSAMPLE_BELOW_SCALE`] and the larger pixmap fits the limits, else 1.
+pub fn supersample_factor(
+    (render_w, render_h): (u32, u32),
+    (rotated_w, rotated_h): (f32, f32),
+) -> u32 {
+    let scale = (render_w as f32 / rotated_w).min(render_h as f32 / rotated_h);
+    let (w, h) = (render_w * SUPERSAMPLE_FACTOR, render_h * SUPERSAMPLE_FACTOR);
+    let fits =
+        w <= MAX_RENDER_DIM && h <= MAX_RENDER_DIM && (w * h) as usize * 4 <= SUPERSAMPLE_BUDGET;
+    if scale < SUPERSAMPLE_BELOW_SCALE && fits {
+        SUPERSAMPLE_FACTOR
+    } else {
+        1
+    }
+}
+
+/// Like `render_to_pixmap_scaled`, but drawn at [`supersample_factor`] times
+/// the size and averaged back down, so detail much finer than a pixel is
+/// blended instead of aliasing. Returns the factor used with the render.
+pub fn render_to_pixmap_supersampled(
+    doc: &SvgDocument,
+    viewport: &Viewport,
+    area_width: f32,
+    area_height: f32,
+    pixels_per_point: f32,
+    render_scale: RenderScale,
+) -> Result<(Pixmap, u32)> {
+    supersampled(
+        doc,
+        viewport,
+        (area_width, area_height),
+        pixels_per_point,
+        render_scale.factor(),
+    )
+}
+
+/// [`render_to_pixmap_supersampled`] at `density` times the display resolution.
+fn supersampled(
+    doc: &SvgDocument,
+    viewport: &Viewport,
+    (area_width, area_height): (f32, f32),
+    pixels_per_point: f32,
+    density: f32,
+) -> Result<(Pixmap, u32)> {
+    let (mut pixmap, transform) = canvas_target(
+        doc,
+        viewport,
+        area_width,
+        area_height,
+        pixels_per_point,
+        density,
+    )?;
+    let rotated = viewport.rotated_size(
+        usable_dimension(doc.width()),
+        usable_dimension(doc.height()),
+    );
+    let factor = supersample_factor((pixmap.width(), pixmap.height()), rotated);
+    if factor == 1 {
+        resvg::render(doc.tree(), transform, &mut pixmap.as_mut());
+        return Ok((pixmap, 1));
+    }
+    let mut large = Pixmap::new(pixmap.width() * factor, pixmap.height() * factor)
+        .ok_or_else(|| SvgError::Render("Failed to create pixmap".into()))?;
+    let transform = transform.post_scale(factor as f32, factor as f32);
+    resvg::render(doc.tree(), transform, &mut large.as_mut());
+    Ok((downsample(&large, factor), factor))
+}
+
+/// A render of the canvas's view, made by [`render_canvas`] on whichever
+/// thread and shown with `Renderer::show_render`. It's finished there too,
+/// so all that's left for the UI thread is the upload.
+pub struct CanvasRender {
+    /// The render with its effects applied
+    pub pixmap: Pixmap,
+    /// Nothing was painted (see [`is_blank`]), before any effects
+    pub blank: bool,
+    /// A copy within [`RETAINED_PIXMAP_BUDGET`], when `pixmap` is over it
+    pub reduced: Option<Pixmap>,
+    /// The zoom it was rendered for
+    pub zoom: f32,
+    /// Its logical display size, from [`display_size`]
+    pub display: (f32, f32),
+    pub render_scale: RenderScale,
+    pub supersample: u32,
+    pub headroom: f32,
+}
+
+impl CanvasRender {
+    /// `pixmap`, rendered at `zoom` to be shown `display` in size, finished
+    /// with `pipeline`.
+    pub fn new(
+        mut pixmap: Pixmap,
+        pipeline: &PostPipeline,
+        zoom: f32,
+        display: (f32, f32),
+        render_scale: RenderScale,
+    ) -> Self {
+        let blank = is_blank(&pixmap);
+        pipeline.run(&mut pixmap);
+        let reduced = (pixmap.data().len() > RETAINED_PIXMAP_BUDGET)
+            .then(|| fit_to_budget(pixmap.clone(), RETAINED_PIXMAP_BUDGET));
+        Self {
+            pixmap,
+            blank,
+            reduced,
+            zoom,
+            display,
+            render_scale,
+            supersample: 1,
+            headroom: 1.0,
+        }
+    }
+}
+
+/// Render `doc` for the canvas at `viewport`, with zoom headroom, and
+/// supersampled when `high_quality_downscale` asks for it, then finish it
+/// with `pipeline`.
+pub fn render_canvas(
+    doc: &SvgDocument,
+    viewport: &Viewport,
+    (area_width, area_height): (f32, f32),
+    pixels_per_point: f32,
+    render_scale: RenderScale,
+    high_quality_downscale: bool,
+    pipeline: &PostPipeline,
+) -> Result<CanvasRender> {
+    let headroom = zoom_headroom(
+        doc,
+        viewport,
+        area_width,
+        area_height,
+        pixels_per_point,
+        render_scale,
+    );
+    let density = render_scale.factor() * headroom;
+    let (pixmap, supersample) = if high_quality_downscale {
+        supersampled(
+            doc,
+            viewport,
+            (area_width, area_height),
+            pixels_per_point,
+            density,
+        )?
+    } else {
+        let (mut pixmap, transform) = canvas_target(
+            doc,
+            viewport,
+            area_width,
+            area_height,
+            pixels_per_point,
+            density,
+        )?;
+        resvg::render(doc.tree(), transform, &mut pixmap.as_mut());
+        (pixmap, 1)
+    };
+
+    // Compute intended logical display size (may be larger than pixmap due to
+    // MAX_RENDER_SCALE cap — GPU bilinear scaling bridges the gap).
+    let display = display_size(
+        doc.width(),
+        doc.height(),
+        viewport,
+        area_width,
+        area_height,
+        pixels_per_point,
+    );
+    Ok(CanvasRender {
+        supersample,
+        headroom,
+        ..CanvasRender::new(pixmap, pipeline, viewport.zoom, display, render_scale)
+    })
+}
+
+/// A render of the canvas for [`RenderWorker`]: what [`render_canvas`] is
+/// given.
+pub struct CanvasRequest {
+    pub doc: Arc<SvgDocument>,
+    pub viewport: Viewport,
+    pub area: (f32, f32),
+    pub pixels_per_point: f32,
+    pub render_scale: RenderScale,
+    pub high_quality_downscale: bool,
+    pub pipeline: PostPipeline,
+}
+
+type FinishedRender = (u64, Result<CanvasRender>);
+
+/// Renders the canvas on a thread of its own, one request at a time. A
+/// request superseded before it starts is skipped, so only the latest of a
+/// burst is rendered. The thread ends once the worker is dropped.
+pub struct RenderWorker {
+    requests: mpsc::Sender<(u64, CanvasRequest)>,
+    finished: mpsc::Receiver<FinishedRender>,
+    /// Requests from before this generation are skipped
+    generation: Arc<AtomicU64>,
+    thread: std::thread::JoinHandle<()>,
+}
+
+impl RenderWorker {
+    /// Start the thread; `on_finished` is called after each render.
+    pub fn spawn(on_finished: impl Fn() + Send + 'static) -> Self {
+        let (requests, queue) = mpsc::channel::<(u64, CanvasRequest)>();
+        let (done, finished) = mpsc::channel();
+        let generation = Arc::new(AtomicU64::new(0));
+        let current = Arc::clone(&generation);
+        let thread = std::thread::spawn(move || {
+            for (generation, request) in queue {
+                if generation != current.load(Ordering::Relaxed) {
+                    log::debug!("Skipped superseded render {generation}");
+                    continue;
+                }
+                let start = std::time::Instant::now();
+                let result = render_canvas(
+                    &request.doc,
+                    &request.viewport,
+                    request.area,
+                    request.pixels_per_point,
+                    request.render_scale,
+                    request.high_quality_downscale,
+                    &request.pipeline,
+                );
+                if result.is_ok() {
+                    log::info!(
+                        "Rendered {} at {:.0}% in {:.1} ms",
+                        request.doc.filename(),
+                        request.viewport.zoom_percent(),
+                        start.elapsed().as_secs_f64() * 1000.0
+                    );
+                }
+                if done.send((generation, result)).is_err() {
+                    break;
+                }
+                on_finished();
+            }
+        });
+        Self {
+            requests,
+            finished,
+            generation,
+            thread,
+        }
+    }
+
+    /// Render `request` as `generation`, skipping any request made before it
+    /// that hasn't started.
+    pub fn request(&self, generation: u64, request: CanvasRequest) {
+        self.set_generation(generation);
+        let _ = self.requests.send((generation, request));
+    }
+
+    /// Skip requests made before `generation` that haven't started.
+    pub fn set_generation(&self, generation: u64) {
+        self.generation.store(generation, Ordering::Relaxed);
+    }
+
+    /// Renders finished since the last call, with the generation each was
+    /// asked for in.
+    pub fn finished(&self) -> impl Iterator<Item = FinishedRender> + '_ {
+        self.finished.try_iter()
+    }
+
+    /// The thread has ended, which it only does early if a render panicked.
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+}
+
+/// `pixmap` shrunk by `factor` per side with a box filter: each pixel is the
+/// average of the `factor`×`factor` block it covers, or of what there is of
+/// it at the right and bottom edges.
+pub fn downsample(pixmap: &Pixmap, factor: u32) -> Pixmap {
+    let factor = factor.max(1) as usize;
+    let (src_w, src_h) = (pixmap.width() as usize, pixmap.height() as usize);
+    let (w, h) = (src_w.div_ceil(factor), src_h.div_ceil(factor));
+    let mut out = Pixmap::new(w as u32, h as u32).expect("smaller than an existing pixmap");
+    let src = pixmap.data();
+    let dst = out.data_mut();
+    let mut sums = vec![0u32; w * 4];
+    for y in 0..h {
+        sums.fill(0);
+        let rows = (y * factor)..((y + 1) * factor).min(src_h);
+        let block_h = rows.len();
+        for row in rows {
+            let line = &src[row * src_w * 4..(row + 1) * src_w * 4];
+            for (i, channel) in line.iter().enumerate() {
+                let x = i / 4 / factor;
+                sums[x * 4 + i % 4] += *channel as u32;
+            }
+        }
+        for x in 0..w {
+            let block_w = (src_w - x * factor).min(factor);
+            let count = (block_w * block_h) as u32;
+            for c in 0..4 {
+                // Premultiplied channels average straight, and stay within alpha
+                dst[(y * w + x) * 4 + c] = ((sums[x * 4 + c] + count / 2) / count) as u8;
+            }
+        }
+    }
+    out
+}
+
+/// Rows rendered between checks of the cancel flag in
+/// [`render_to_pixmap_cancellable`].
+pub const CANCEL_CHECK_ROWS: u32 = 256;
+
+/// Like `render_to_pixmap_scaled`, but rendered in bands of
+/// [`CANCEL_CHECK_ROWS`] rows, giving up between bands once `cancel` is set.
+/// Returns `None` when cancelled.
+///
+/// Documents with filters are rendered in one pass: resvg computes filters
+/// over a region around each band, so banding would repeat that work for
+/// every band. They can only be cancelled before the render starts.
+pub fn render_to_pixmap_cancellable(
+    doc: &SvgDocument,
+    viewport: &Viewport,
+    area_width: f32,
+    area_height: f32,
+    pixels_per_point: f32,
+    render_scale: RenderScale,
+    cancel: &AtomicBool,
+) -> Result<Option<Pixmap>> {
+    let (mut pixmap, transform) = canvas_target(
+        doc,
+        viewport,
+        area_width,
+        area_height,
+        pixels_per_point,
+        render_scale.factor(),
+    )?;
+    let cancelled = || cancel.load(Ordering::Relaxed);
+    if cancelled() {
+        return Ok(None);
+    }
+    if !doc.tree().filters().is_empty() || pixmap.height() <= CANCEL_CHECK_ROWS {
+        resvg::render(doc.tree(), transform, &mut pixmap.as_mut());
+        return Ok(Some(pixmap));
+    }
+
+    let width = pixmap.width();
+    let row_bytes = width as usize * 4;
+    for top in (0..pixmap.height()).step_by(CANCEL_CHECK_ROWS as usize) {
+        if cancelled() {
+            return Ok(None);
+        }
+        let rows = CANCEL_CHECK_ROWS.min(pixmap.height() - top);
+        let mut band = Pixmap::new(width, rows)
+            .ok_or_else(|| SvgError::Render("Failed to create pixmap".into()))?;
+        let shifted = transform.post_translate(0.0, -(top as f32));
+        resvg::render(doc.tree(), shifted, &mut band.as_mut());
+        let start = top as usize * row_bytes;
+        pixmap.data_mut()[start..start + band.data().len()].copy_from_slice(band.data());
+    }
+    Ok(Some(pixmap))
+}
+
+/// An effect applied to a finished render, such as a preview filter or the
+/// conversion for the display.
+pub trait PostProcess: Send + Sync {
+    /// Change `pixels`, `height` rows of `width` pixels, in place. A pipeline
+    /// hands each effect bands of a render's rows rather than all of it, so
+    /// effects work on a pixel (or a row) at a time.
+    fn apply(&self, pixels: &mut [PremultipliedColorU8], width: u32, height: u32);
+}
+
+/// Shows the render in shades of gray, by Rec. 709 luma.
+pub struct Grayscale;
+
+impl PostProcess for Grayscale {
+    fn apply(&self, pixels: &mut [PremultipliedColorU8], _width: u32, _height: u32) {
+        for pixel in pixels {
+            // Luma is linear in the channels, so premultiplied values work as they are
+            let luma = (pixel.red() as u32 * 2126
+                + pixel.green() as u32 * 7152
+                + pixel.blue() as u32 * 722
+                + 5000)
+                / 10000;
+            let luma = luma.min(pixel.alpha() as u32) as u8;
+            *pixel =
+                PremultipliedColorU8::from_rgba(luma, luma, luma, pixel.alpha()).unwrap_or(*pixel);
+        }
+    }
+}
+
+/// Inverts colors, keeping transparency.
+pub struct Invert;
+
+impl PostProcess for Invert {
+    fn apply(&self, pixels: &mut [PremultipliedColorU8], _width: u32, _height: u32) {
+        for pixel in pixels {
+            // Premultiplied, the inverse of c is alpha - c
+            let a = pixel.alpha();
+            *pixel = PremultipliedColorU8::from_rgba(
+                a - pixel.red(),
+                a - pixel.green(),
+                a - pixel.blue(),
+                a,
+            )
+            .unwrap_or(*pixel);
+        }
+    }
+}
+
+/// Colorizes the render: dark colors become the tint and light ones stay
+/// light, keeping transparency, so two tinted renders can be told apart.
+pub struct Tint(pub [u8; 3]);
+
+impl PostProcess for Tint {
+    fn apply(&self, pixels: &mut [PremultipliedColorU8], width: u32, height: u32) {
+        Grayscale.apply(pixels, width, height);
+        for pixel in pixels {
+            // Premultiplied, lerp from tint * alpha at black to alpha at white
+            let (a, luma) = (pixel.alpha() as u32, pixel.red() as u32);
+            let [r, g, b] = self
+                .0
+                .map(|tint| ((tint as u32 * (a - luma) + 127) / 255 + luma).min(a) as u8);
+            *pixel = PremultipliedColorU8::from_rgba(r, g, b, a as u8).unwrap_or(*pixel);
+        }
+    }
+}
+
+/// Preview effects the user has switched on for the canvas, or chosen for an
+/// export.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PreviewEffects {
+    pub grayscale: bool,
+    pub invert: bool,
+}
+
+/// Rows an effect gets at a time; small enough that a band stays in cache
+/// while every effect runs over it.
+const PIPELINE_BAND_ROWS: usize = 16;
+
+/// Renders with fewer pixels than this are processed on the calling thread.
+const PIPELINE_PARALLEL_MIN_PIXELS: usize = 1 << 16;
+
+/// Effects applied in order, in a single pass over a render: its rows are
+/// split between threads, and each band of rows goes through every effect
+/// before the next band is touched.
+#[derive(Default)]
+pub struct PostPipeline {
+    effects: Vec<Box<dyn PostProcess>>,
+}
+
+impl PostPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `effect`, applied after those already added.
+    pub fn with(mut self, effect: impl PostProcess + 'static) -> Self {
+        self.effects.push(Box::new(effect));
+        self
+    }
+
+    /// The preview effects in their fixed order: grayscale, then invert.
+    fn with_previews(mut self, effects: PreviewEffects) -> Self {
+        if effects.grayscale {
+            self.effects.push(Box::new(Grayscale));
+        }
+        if effects.invert {
+            self.effects.push(Box::new(Invert));
+        }
+        self
+    }
+
+    /// The canvas pipeline for the active view options. Preview effects come
+    /// first, changing what's shown; the conversion for the display is last,
+    /// since it's about how it's shown.
+    pub fn for_canvas(effects: PreviewEffects, color: ColorManagement) -> Self {
+        let mut pipeline = Self::new().with_previews(effects);
+        if let Some(conversion) = DisplayConversion::new(color) {
+            pipeline.effects.push(Box::new(conversion));
+        }
+        pipeline
+    }
+
+    /// The pipeline for an export with the effects chosen for it. Exports stay
+    /// sRGB, so there's no display conversion.
+    pub fn for_export(effects: PreviewEffects) -> Self {
+        Self::new().with_previews(effects)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    /// Apply every effect to `pixmap`.
+    pub fn run(&self, pixmap: &mut Pixmap) {
+        if self.effects.is_empty() {
+            return;
+        }
+        let width = pixmap.width() as usize;
+        let rows = pixmap.height() as usize;
+        let pixels = pixmap.pixels_mut();
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        if threads == 1 || pixels.len() < PIPELINE_PARALLEL_MIN_PIXELS {
+            self.run_rows(pixels, width);
+            return;
+        }
+        let share = rows.div_ceil(threads).max(1) * width;
+        std::thread::scope(|scope| {
+            for chunk in pixels.chunks_mut(share) {
+                scope.spawn(move || self.run_rows(chunk, width));
+            }
+        });
+    }
+
+    fn run_rows(&self, pixels: &mut [PremultipliedColorU8], width: usize) {
+        for band in pixels.chunks_mut(PIPELINE_BAND_ROWS * width) {
+            let height = (band.len() / width) as u32;
+            for effect in &self.effects {
+                effect.apply(band, width as u32, height);
+            }
+        }
+    }
+}
+
+/// Render the whole document at `width`×`height` for export, applying the
+/// viewport's rotation and mirroring but not its zoom or pan. Sizes past
+/// [`MAX_RENDER_DIM`] are rendered in tiles, up to [`MAX_EXPORT_DIM`].
+pub fn render_for_export(
+    doc: &SvgDocument,
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+) -> Result<Pixmap> {
+    render_padded_for_export(doc, width, height, viewport, 0.0)
+}
+
+/// [`render_for_export`], with the drawing shrunk to leave `margin` pixels
+/// clear around it.
+pub fn render_padded_for_export(
+    doc: &SvgDocument,
+    width: u32,
+    height: u32,
+    viewport: &Viewport,
+    margin: f32,
+) -> Result<Pixmap> {
+    let width = width.clamp(1, MAX_EXPORT_DIM);
+    let height = height.clamp(1, MAX_EXPORT_DIM);
+    let transform =
+        viewport.build_transform(doc.width(), doc.height(), width as f32, height as f32);
+    render_tiled(
+        doc,
+        width,
+        height,
+        padded_transform(transform, width, height, margin),
+    )
+}
+
+/// `transform`, which fills a `width`×`height` image with the document,
+/// shrunk about the image's centre to leave `margin` pixels clear on the
+/// tighter pair of sides. The proportions are kept, so the other pair gets
+/// more.
+pub fn padded_transform(
+    transform: tiny_skia::Transform,
+    width: u32,
+    height: u32,
+    margin: f32,
+) -> tiny_skia::Transform {
+    if margin <= 0.0 {
+        return transform;
+    }
+    let (width, height) = (width as f32, height as f32);
+    let scale = ((width - 2.0 * margin) / width)
+        .min((height - 2.0 * margin) / height)
+        .max(0.0);
+    transform
+        .post_translate(-width / 2.0, -height / 2.0)
+        .post_scale(scale, scale)
+        .post_translate(width / 2.0, height / 2.0)
+}
+
+/// Render the `width`×`height` image `transform` maps the document into, as
+/// tiles of at most [`MAX_RENDER_DIM`] square stitched together.
+pub fn render_tiled(
+    doc: &SvgDocument,
+    width: u32,
+    height: u32,
+    transform: tiny_skia::Transform,
+) -> Result<Pixmap> {
+    if width <= MAX_RENDER_DIM && height <= MAX_RENDER_DIM {
+        return render_with_transform(doc, width, height, transform);
+    }
+    let mut pixmap = Pixmap::new(width, height)
+        .ok_or_else(|| SvgError::Render("Failed to create pixmap".into()))?;
+    let stride = width as usize * 4;
+    for top in (0..height).step_by(MAX_RENDER_DIM as usize) {
+        let rows = (height - top).min(MAX_RENDER_DIM);
+        let band = render_band(doc, width, top, rows, transform)?;
+        let start = top as usize * stride;
+        pixmap.data_mut()[start..start + band.data().len()].copy_from_slice(band.data());
+    }
+    Ok(pixmap)
+}
+
+/// Render `rows` rows from `top` of the `width`-pixel-wide image `transform`
+/// maps the document into, from tiles at most [`MAX_RENDER_DIM`] wide.
+/// `rows` is at most [`MAX_RENDER_DIM`] too.
+pub fn render_band(
+    doc: &SvgDocument,
+    width: u32,
+    top: u32,
+    rows: u32,
+    transform: tiny_skia::Transform,
+) -> Result<Pixmap> {
+    let rows = rows.clamp(1, MAX_RENDER_DIM);
+    let mut band = Pixmap::new(width, rows)
+        .ok_or_else(|| SvgError::Render("Failed to create pixmap".into()))?;
+    let band_stride = width as usize * 4;
+    for left in (0..width).step_by(MAX_RENDER_DIM as usize) {
+        let tile_width = (width - left).min(MAX_RENDER_DIM);
+        let offset = transform.post_translate(-(left as f32), -(top as f32));
+        if tile_width == width {
+            resvg::render(doc.tree(), offset, &mut band.as_mut());
+            break;
+        }
+        let tile = render_with_transform(doc, tile_width, rows, offset)?;
+        let tile_stride = tile_width as usize * 4;
+        let data = band.data_mut();
+        for (y, row) in tile.data().chunks_exact(tile_stride).enumerate() {
+            let start = y * band_stride + left as usize * 4;
+            data[start..start + tile_stride].copy_from_slice(row);
+        }
+    }
+    Ok(band)
+}
+
+/// Render the document into a `width`×`height` pixmap through `transform`,
+/// which maps SVG units to pixels.
+pub fn render_with_transform(
+    doc: &SvgDocument,
+    width: u32,
+    height: u32,
+    transform: tiny_skia::Transform,
+) -> Result<Pixmap> {
+    let width = width.clamp(1, MAX_RENDER_DIM);
+    let height = height.clamp(1, MAX_RENDER_DIM);
+
+    let mut pixmap = Pixmap::new(width, height)
+        .ok_or_else(|| SvgError::Render("Failed to create pixmap".into()))?;
+    resvg::render(doc.tree(), transform, &mut pixmap.as_mut());
+
+    Ok(pixmap)
+}
+
+/// Render exactly what a `canvas_width`×`canvas_height` canvas shows: the
+/// document laid out over `image`, in the canvas's logical pixels as the
+/// render is shown, cropped to the canvas. The result is `pixels_per_point`
+/// times the canvas size, reduced to fit [`MAX_RENDER_DIM`].
+pub fn render_view(
+    doc: &SvgDocument,
+    viewport: &Viewport,
+    (canvas_width, canvas_height): (f32, f32),
+    image: emath::Rect,
+    pixels_per_point: f32,
+) -> Result<Pixmap> {
+    let limit = MAX_RENDER_DIM as f32;
+    let scale = pixels_per_point
+        .min(limit / canvas_width.max(1.0))
+        .min(limit / canvas_height.max(1.0));
+    let (width, height) = view_dimensions((canvas_width, canvas_height), scale);
+    render_with_transform(
+        doc,
+        width,
+        height,
+        view_transform(doc, viewport, image, scale),
+    )
+}
+
+/// Like [`render_view`] but `scale` times the canvas size exactly, for export.
+/// A view larger than [`MAX_EXPORT_DIM`] is an error rather than reduced.
+pub fn render_view_for_export(
+    doc: &SvgDocument,
+    viewport: &Viewport,
+    canvas: (f32, f32),
+    image: emath::Rect,
+    scale: f32,
+) -> Result<Pixmap> {
+    let (width, height) = view_dimensions(canvas, scale);
+    if width > MAX_EXPORT_DIM || height > MAX_EXPORT_DIM {
+        return Err(SvgError::Render(format!(
+            "the view would be {width}\u{00D7}{height}, over the {MAX_EXPORT_DIM}-pixel limit"
+        )));
+    }
+    let transform = view_transform(doc, viewport, image, scale);
+    render_tiled(doc, width, height, transform)
+}
+
+/// Pixel size of a `canvas` rendered at `scale`.
+pub fn view_dimensions((canvas_width, canvas_height): (f32, f32), scale: f32) -> (u32, u32) {
+    (
+        (canvas_width * scale).round().max(1.0) as u32,
+        (canvas_height * scale).round().max(1.0) as u32,
+    )
+}
+
+/// From SVG units to the pixels of a canvas render at `scale`, with the
+/// document laid out over `image`.
+fn view_transform(
+    doc: &SvgDocument,
+    viewport: &Viewport,
+    image: emath::Rect,
+    scale: f32,
+) -> tiny_skia::Transform {
+    viewport
+        .build_transform(
+            usable_dimension(doc.width()),
+            usable_dimension(doc.height()),
+            image.width(),
+            image.height(),
+        )
+        .post_translate(image.min.x, image.min.y)
+        .post_scale(scale, scale)
+}
+
+/// Render only `region` (in SVG units) of the document for export.
+/// The region is scaled uniformly to fit `width`×`height` less `margin` pixels
+/// on each side, and centered.
+pub fn render_region_for_export(
+    doc: &SvgDocument,
+    region: &ExportRegion,
+    width: u32,
+    height: u32,
+    margin: f32,
+) -> Result<Pixmap> {
+    let width = width.clamp(1, MAX_EXPORT_DIM);
+    let height = height.clamp(1, MAX_EXPORT_DIM);
+    let transform = region.build_transform(width as f32, height as f32);
+    render_tiled(
+        doc,
+        width,
+        height,
+        padded_transform(transform, width, height, margin),
+    )
+}
+
+/// Bytes the renderer may spend keeping a copy of the current render for
+/// re-uploading; larger renders are kept at reduced size.
+pub const RETAINED_PIXMAP_BUDGET: usize = 32 * 1024 * 1024;
+/// Frames between checks that the render texture still exists.
+pub const TEXTURE_CHECK_INTERVAL: u32 = 30;
+/// Failed uploads in a row before the canvas falls back to software drawing.
+pub const MAX_UPLOAD_ATTEMPTS: u32 = 3;
+/// Longest side of the software fallback image, in cells.
+pub const FALLBACK_MAX_DIM: usize = 160;
+
+/// Where the render texture lives: egui's texture manager in the viewer, a mock
+/// in tests.
+#[cfg(feature = "gui")]
+pub trait TextureBackend {
+    /// Upload `image` as the render texture, replacing the previous one.
+    fn upload(&mut self, image: ColorImage, options: TextureOptions) -> Result<egui::TextureId>;
+    /// Whether `id` still refers to a live texture.
+    fn is_valid(&self, id: egui::TextureId) -> bool;
+}
+
+/// The render texture in an egui context.
+#[cfg(feature = "gui")]
+pub struct EguiTextures {
+    ctx: egui::Context,
+    handle: Option<TextureHandle>,
+}
+
+#[cfg(feature = "gui")]
+impl EguiTextures {
+    pub fn new(ctx: egui::Context) -> Self {
+        Self { ctx, handle: None }
+    }
+}
+
+#[cfg(feature = "gui")]
+impl TextureBackend for EguiTextures {
+    fn upload(&mut self, image: ColorImage, options: TextureOptions) -> Result<egui::TextureId> {
+        let size = image.size;
+        let max_side = self.ctx.input(|i| i.max_texture_side);
+        if size[0].max(size[1]) > max_side {
+            return Err(SvgError::Render(format!(
+                "{}x{} texture exceeds the GPU limit of {max_side}",
+                size[0], size[1]
+            )));
+        }
+        if let Some(handle) = self.handle.take() {
+            if self.is_valid(handle.id()) {
+                self.handle = Some(handle);
+            } else {
+                // Its id is already gone from the texture manager; dropping the
+                // handle would free it a second time
+                std::mem::forget(handle);
+            }
+        }
+        let id = match &mut self.handle {
+            Some(handle) => {
+                handle.set(image, options);
+                handle.id()
+            }
+            None => {
+                let handle = self.ctx.load_texture("svg_render", image, options);
+                let id = handle.id();
+                self.handle = Some(handle);
+                id
+            }
+        };
+        let uploaded = self.ctx.tex_manager().read().meta(id).map(|m| m.size);
+        if uploaded == Some(size) {
+            Ok(id)
+        } else {
+            Err(SvgError::Render("texture upload was not accepted".into()))
+        }
+    }
+
+    fn is_valid(&self, id: egui::TextureId) -> bool {
+        self.ctx.tex_manager().read().meta(id).is_some()
+    }
+}
+
+/// What the canvas should draw for the current render.
+#[cfg(feature = "gui")]
+pub enum Presentation {
+    Texture(egui::TextureId),
+    /// A reduced copy, drawn cell by cell because no texture could be uploaded
+    Software(ColorImage),
+    /// Deep zoom tiles (see [`crate::tiles`])
+    Tiles(Vec<PlacedTile>),
+}
+
+/// The viewer's current render, uploaded as a GPU texture. A copy of the pixmap is
+/// kept so a texture that fails to upload or disappears can be re-created.
+#[cfg(feature = "gui")]
 pub struct Renderer {
-    pub texture: Option<TextureHandle>,
+    backend: Option<Box<dyn TextureBackend>>,
+    texture: Option<egui::TextureId>,
+    /// The last render and its sampling before `sampling` is applied
+    retained: Option<(Pixmap, TextureOptions)>,
+    sampling: TextureSampling,
+    /// `sampling` changed since the texture was uploaded
+    sampling_changed: bool,
+    /// Uploads that have failed since the last one that worked
+    upload_failures: u32,
+    frames_since_check: u32,
     pub rendered_width: u32,
     pub rendered_height: u32,
     pub rendered_zoom: f32,
     pub logical_display_w: f32,
     pub logical_display_h: f32,
+    /// The current texture has no painted pixels (see `is_blank`)
+    pub rendered_blank: bool,
+    /// The current texture was drawn this many times larger and scaled down
+    pub rendered_supersample: u32,
+    /// The current texture has this many times the display resolution, so it
+    /// stays sharp up to `rendered_zoom` times this
+    pub rendered_headroom: f32,
+    /// Applied to each render before it's uploaded
+    effects: PreviewEffects,
+    color_management: ColorManagement,
+    /// Supersample renders that shrink the document a lot
+    high_quality_downscale: bool,
+    /// Crisp renders are sampled nearest-neighbour
+    quality: RenderQuality,
+    /// Colorize each render after the other effects, for the diff overlay
+    tint: Option<[u8; 3]>,
+    tiles: TileCache,
+    /// What to draw while the document is shown as tiles
+    tiled: Option<Vec<PlacedTile>>,
+}
+
+#[cfg(feature = "gui")]
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
+#[cfg(feature = "gui")]
 impl Renderer {
+    /// A renderer that uploads to the egui context it's first given.
     pub fn new() -> Self {
         Self {
+            backend: None,
             texture: None,
+            retained: None,
+            sampling: TextureSampling::default(),
+            sampling_changed: false,
+            upload_failures: 0,
+            frames_since_check: 0,
             rendered_width: 0,
             rendered_height: 0,
             rendered_zoom: 0.0,
             logical_display_w: 0.0,
             logical_display_h: 0.0,
+            rendered_blank: false,
+            rendered_supersample: 1,
+            rendered_headroom: 1.0,
+            effects: PreviewEffects::default(),
+            color_management: ColorManagement::default(),
+            high_quality_downscale: false,
+            quality: RenderQuality::default(),
+            tint: None,
+            tiles: TileCache::default(),
+            tiled: None,
         }
     }
 
-    /// Render the SVG document at the given viewport zoom level and return a pixmap.
-    pub fn render_to_pixmap(
+    pub fn with_backend(backend: Box<dyn TextureBackend>) -> Self {
+        Self {
+            backend: Some(backend),
+            ..Self::new()
+        }
+    }
+
+    /// Render SVG and upload as a GPU texture, supersampled when high quality
+    /// downscaling calls for it, and with [`zoom_headroom`] so zooming in a
+    /// little can scale it rather than render again. Interactive zoom never
+    /// gets here: the old texture is scaled until zooming settles.
+    pub fn render_and_upload(
+        &mut self,
+        ctx: &egui::Context,
         doc: &SvgDocument,
         viewport: &Viewport,
         area_width: f32,
         area_height: f32,
-        pixels_per_point: f32,
-    ) -> Result<Pixmap> {
-        let svg_w = doc.width;
-        let svg_h = doc.height;
+        render_scale: RenderScale,
+    ) -> Result<()> {
+        let render = render_canvas(
+            doc,
+            viewport,
+            (area_width, area_height),
+            ctx.pixels_per_point(),
+            render_scale,
+            self.high_quality_downscale,
+            &self.pipeline(),
+        )?;
+        self.show_render(ctx, render);
+        Ok(())
+    }
 
-        if svg_w <= 0.0 || svg_h <= 0.0 {
-            return Err(SvgError::Render("SVG has zero dimensions".into()));
+    /// What renders are finished with before they're uploaded: the preview
+    /// effects, the display conversion and the tint.
+    pub fn pipeline(&self) -> PostPipeline {
+        let pipeline = PostPipeline::for_canvas(self.effects, self.color_management);
+        match self.tint {
+            Some(tint) => pipeline.with(Tint(tint)),
+            None => pipeline,
         }
+    }
 
-        let (effective_svg_w, effective_svg_h) = if (viewport.rotation_deg % 180.0).abs() > 45.0 {
-            (svg_h, svg_w)
-        } else {
-            (svg_w, svg_h)
+    /// Upload `render`, made by [`render_canvas`] or [`CanvasRender::new`]
+    /// with [`Renderer::pipeline`], as the texture shown.
+    pub fn show_render(&mut self, ctx: &egui::Context, render: CanvasRender) {
+        self.backend
+            .get_or_insert_with(|| Box::new(EguiTextures::new(ctx.clone())));
+        let pixmap = render.pixmap;
+        self.rendered_width = pixmap.width();
+        self.rendered_height = pixmap.height();
+        self.rendered_blank = render.blank;
+        (self.logical_display_w, self.logical_display_h) = render.display;
+        self.rendered_zoom = render.zoom;
+        self.rendered_supersample = render.supersample;
+        self.rendered_headroom = render.headroom;
+        self.tiled = None;
+
+        let options = texture_options(
+            pixmap.width(),
+            pixmap.height(),
+            render.render_scale,
+            self.quality,
+        );
+        self.upload_failures = 0;
+        self.sampling_changed = false;
+        self.upload(&pixmap, options);
+        self.retained = Some((render.reduced.unwrap_or(pixmap), options));
+    }
+
+    fn upload(&mut self, pixmap: &Pixmap, options: TextureOptions) {
+        let Some(backend) = self.backend.as_mut() else {
+            return;
+        };
+        let size = [pixmap.width() as usize, pixmap.height() as usize];
+        let image = ColorImage::from_rgba_premultiplied(size, pixmap.data());
+        match backend.upload(image, self.sampling.apply(options)) {
+            Ok(id) => {
+                if self.upload_failures > 0 {
+                    log::info!("Render texture re-created");
+                }
+                self.texture = Some(id);
+                self.upload_failures = 0;
+            }
+            Err(e) => {
+                self.texture = None;
+                self.upload_failures += 1;
+                if self.software_fallback() {
+                    log::error!("{e}; drawing a reduced software preview instead");
+                } else {
+                    log::warn!("{e}");
+                }
+            }
+        }
+    }
+
+    /// Show `doc` as tiles at `viewport`, asking for the ones that aren't
+    /// ready. Call every frame while [`crate::tiles::wants_tiles`] holds; the
+    /// next whole render ends it.
+    pub fn show_tiles(
+        &mut self,
+        ctx: &egui::Context,
+        doc: &std::sync::Arc<SvgDocument>,
+        viewport: &Viewport,
+        (area_width, area_height): (f32, f32),
+        render_scale: RenderScale,
+    ) {
+        let svg_size = (
+            usable_dimension(doc.width()),
+            usable_dimension(doc.height()),
+        );
+        let density = ctx.pixels_per_point() * render_scale.factor();
+        let view = TileView::new(viewport, svg_size, (area_width, area_height), density);
+        let source = TileSource {
+            doc: std::sync::Arc::clone(doc),
+            effects: self.effects,
+            color: self.color_management,
         };
+        let options = texture_options(TILE_SIZE, TILE_SIZE, render_scale, self.quality);
+        self.tiled = Some(self.tiles.update(ctx, source, &view, options));
+        // The tiles fill the document's whole rect at the current zoom
+        self.logical_display_w = svg_size.0 * viewport.zoom;
+        self.logical_display_h = svg_size.1 * viewport.zoom;
+        self.rendered_zoom = viewport.zoom;
+        let [left, top, right, bottom] = view.visible;
+        self.rendered_width = ((right - left) * view.scale).round() as u32;
+        self.rendered_height = ((bottom - top) * view.scale).round() as u32;
+        self.rendered_supersample = 1;
+        self.rendered_headroom = 1.0;
+    }
 
-        // Calculate the displayed size of the SVG on screen (in logical pixels)
-        // zoom represents how many screen pixels per SVG unit
-        let displayed_w = effective_svg_w * viewport.zoom;
-        let displayed_h = effective_svg_h * viewport.zoom;
+    /// Whether the document is being shown as tiles.
+    pub fn is_tiled(&self) -> bool {
+        self.tiled.is_some()
+    }
 
-        // Cap to the available area so we don't render more than what's visible
-        let capped_w = displayed_w.min(area_width);
-        let capped_h = displayed_h.min(area_height);
+    /// Whether everything on screen is drawn at its proper resolution: there
+    /// are no tiles still rendering.
+    pub fn tiles_complete(&self) -> bool {
+        self.tiled.is_none() || self.tiles.is_complete()
+    }
 
-        // Convert to physical pixels
-        let render_w = (capped_w * pixels_per_point).round() as u32;
-        let render_h = (capped_h * pixels_per_point).round() as u32;
+    /// How the texture is sampled from the next upload on. `maintain` re-uploads
+    /// to apply a change, unless a render is about to.
+    pub fn set_sampling(&mut self, sampling: TextureSampling) {
+        if sampling != self.sampling {
+            self.sampling = sampling;
+            self.sampling_changed = true;
+        }
+    }
 
-        // Cap to MAX_RENDER_SCALE × native size so filter-heavy SVGs stay fast.
-        // GPU bilinear scaling handles further magnification.
-        let max_w = (svg_w * MAX_RENDER_SCALE).round() as u32;
-        let max_h = (svg_h * MAX_RENDER_SCALE).round() as u32;
-        let render_w = render_w.clamp(1, max_w.min(MAX_RENDER_DIM));
-        let render_h = render_h.clamp(1, max_h.min(MAX_RENDER_DIM));
+    pub fn sampling(&self) -> TextureSampling {
+        self.sampling
+    }
 
-        let mut pixmap = Pixmap::new(render_w, render_h)
-            .ok_or_else(|| SvgError::Render("Failed to create pixmap".into()))?;
+    /// The preview effects applied from the next render on.
+    pub fn set_effects(&mut self, effects: PreviewEffects) {
+        self.effects = effects;
+    }
 
-        let transform = viewport.build_transform(svg_w, svg_h, render_w as f32, render_h as f32);
-        resvg::render(&doc.tree, transform, &mut pixmap.as_mut());
+    pub fn effects(&self) -> PreviewEffects {
+        self.effects
+    }
 
-        Ok(pixmap)
+    /// The color renders are tinted from the next render on, if any.
+    pub fn set_tint(&mut self, tint: Option<[u8; 3]>) {
+        self.tint = tint;
     }
 
-    /// Render SVG and upload as a GPU texture.
-    pub fn render_and_upload(
-        &mut self,
-        ctx: &egui::Context,
+    /// Whether renders from the next one on are supersampled when they shrink
+    /// the document below [`SUPERSAMPLE_BELOW_SCALE`].
+    pub fn set_high_quality_downscale(&mut self, enabled: bool) {
+        self.high_quality_downscale = enabled;
+    }
+
+    pub fn high_quality_downscale(&self) -> bool {
+        self.high_quality_downscale
+    }
+
+    /// How textures are sampled from the next upload on. The document's
+    /// own rendering hints are set when it's parsed.
+    pub fn set_quality(&mut self, quality: RenderQuality) {
+        self.quality = quality;
+    }
+
+    pub fn quality(&self) -> RenderQuality {
+        self.quality
+    }
+
+    /// Whether the current render would be redrawn supersampled, were high
+    /// quality downscaling applied to it.
+    pub fn would_supersample(&self, doc: &SvgDocument, viewport: &Viewport) -> bool {
+        let rotated = viewport.rotated_size(
+            usable_dimension(doc.width()),
+            usable_dimension(doc.height()),
+        );
+        self.high_quality_downscale
+            && self.rendered_supersample == 1
+            && supersample_factor((self.rendered_width, self.rendered_height), rotated) > 1
+    }
+
+    /// How renders are converted for the display from the next render on.
+    pub fn set_color_management(&mut self, color: ColorManagement) {
+        self.color_management = color;
+    }
+
+    pub fn color_management(&self) -> ColorManagement {
+        self.color_management
+    }
+
+    /// Whether the current render, scaled, can stand in for one at `viewport`'s
+    /// zoom: it's sharp up to `rendered_zoom` times its headroom, and no more
+    /// than twice as detailed as that zoom needs, and the document would be
+    /// shown at the size scaling gives. Only the zoom may differ from the
+    /// rendered view.
+    pub fn covers_zoom(
+        &self,
         doc: &SvgDocument,
         viewport: &Viewport,
         area_width: f32,
         area_height: f32,
-    ) -> Result<()> {
-        let pixels_per_point = ctx.pixels_per_point();
-        let pixmap =
-            Self::render_to_pixmap(doc, viewport, area_width, area_height, pixels_per_point)?;
+        pixels_per_point: f32,
+    ) -> bool {
+        if self.rendered_zoom <= 0.0 || self.software_fallback() {
+            return false;
+        }
+        let sharp_zoom = self.rendered_zoom * self.rendered_headroom;
+        if viewport.zoom > sharp_zoom || viewport.zoom < sharp_zoom / 2.0 {
+            return false;
+        }
+        // The display is capped to the area, so past it a render would show
+        // the document at a different size than scaling does
+        let ratio = viewport.zoom / self.rendered_zoom;
+        let (w, h) = display_size(
+            doc.width(),
+            doc.height(),
+            viewport,
+            area_width,
+            area_height,
+            pixels_per_point,
+        );
+        let tolerance = 0.5 / pixels_per_point;
+        (w - self.logical_display_w * ratio).abs() <= tolerance
+            && (h - self.logical_display_h * ratio).abs() <= tolerance
+    }
 
-        let width = pixmap.width() as usize;
-        let height = pixmap.height() as usize;
+    /// Screen pixels per texture pixel while the render is shown `zoom_ratio`
+    /// times as large as it was rendered.
+    pub fn texel_scale(&self, zoom_ratio: f32, pixels_per_point: f32) -> f32 {
+        // Tiles are drawn near their own size, and sampled as uploaded
+        if self.rendered_width == 0 || self.tiled.is_some() {
+            return 1.0;
+        }
+        self.logical_display_w * pixels_per_point * zoom_ratio / self.rendered_width as f32
+    }
+
+    /// Check the texture, every `TEXTURE_CHECK_INTERVAL` frames, and re-upload it
+    /// from the retained pixmap if it's missing or its sampling changed. Call once
+    /// per frame.
+    pub fn maintain(&mut self) {
+        if self.retained.is_none() || self.software_fallback() || self.tiled.is_some() {
+            return;
+        }
+        // A copy reduced to fit the budget would cost sharpness for the sake of
+        // sampling; that waits for the next render instead
+        let resample = self.sampling_changed
+            && self.retained.as_ref().is_some_and(|(pixmap, _)| {
+                (pixmap.width(), pixmap.height()) == (self.rendered_width, self.rendered_height)
+            });
+        self.sampling_changed = false;
+        self.frames_since_check += 1;
+        let lost = match self.texture {
+            None => true,
+            Some(id) if self.frames_since_check >= TEXTURE_CHECK_INTERVAL => {
+                self.frames_since_check = 0;
+                !self.backend.as_ref().is_some_and(|b| b.is_valid(id))
+            }
+            Some(_) => false,
+        };
+        if lost || resample {
+            if lost && self.texture.is_some() {
+                log::warn!("Render texture was lost; re-uploading");
+            }
+            if let Some((pixmap, options)) = self.retained.take() {
+                self.upload(&pixmap, options);
+                self.retained = Some((pixmap, options));
+            }
+        }
+    }
 
-        let image = ColorImage::from_rgba_premultiplied([width, height], pixmap.data());
+    /// The straight RGBA color of the current render at `uv`, across it from 0
+    /// to 1 on each axis, as displayed after effects and color management.
+    pub fn pick_color(&self, uv: [f32; 2]) -> Option<[u8; 4]> {
+        // Tiled, what shows is the tiles rather than the retained render
+        if self.tiled.is_some() {
+            return self.tiles.pick(uv);
+        }
+        let (pixmap, _) = self.retained.as_ref()?;
+        crate::eyedropper::pick(pixmap, uv)
+    }
+
+    /// Uploads keep failing, so the canvas draws the software preview.
+    pub fn software_fallback(&self) -> bool {
+        self.upload_failures >= MAX_UPLOAD_ATTEMPTS
+    }
+
+    /// Leave the software fallback and try uploading again on the next frame.
+    pub fn retry_texture(&mut self) {
+        self.upload_failures = 0;
+    }
+
+    /// What to draw for the current render, if there is one.
+    pub fn presentation(&self) -> Option<Presentation> {
+        if let Some(tiles) = &self.tiled {
+            Some(Presentation::Tiles(tiles.clone()))
+        } else if self.software_fallback() {
+            let (pixmap, _) = self.retained.as_ref()?;
+            Some(Presentation::Software(software_image(
+                pixmap,
+                FALLBACK_MAX_DIM,
+            )))
+        } else {
+            self.texture.map(Presentation::Texture)
+        }
+    }
+}
 
-        let options = TextureOptions {
-            magnification: egui::TextureFilter::Linear,
-            minification: egui::TextureFilter::Linear,
+/// `pixmap`, halved until its pixel data fits in `budget` bytes.
+fn fit_to_budget(pixmap: Pixmap, budget: usize) -> Pixmap {
+    let mut pixmap = pixmap;
+    while pixmap.data().len() > budget && pixmap.width().max(pixmap.height()) > 1 {
+        let w = (pixmap.width() / 2).max(1);
+        let h = (pixmap.height() / 2).max(1);
+        let Some(mut smaller) = Pixmap::new(w, h) else {
+            break;
+        };
+        let transform = tiny_skia::Transform::from_scale(
+            w as f32 / pixmap.width() as f32,
+            h as f32 / pixmap.height() as f32,
+        );
+        let paint = tiny_skia::PixmapPaint {
+            quality: tiny_skia::FilterQuality::Bilinear,
             ..Default::default()
         };
+        smaller.draw_pixmap(0, 0, pixmap.as_ref(), &paint, transform, None);
+        pixmap = smaller;
+    }
+    pixmap
+}
 
-        match &mut self.texture {
-            Some(handle) => handle.set(image, options),
-            None => {
-                self.texture = Some(ctx.load_texture("svg_render", image, options));
-            }
+/// `pixmap` sampled down to at most `max_dim` on its longer side.
+#[cfg(feature = "gui")]
+fn software_image(pixmap: &Pixmap, max_dim: usize) -> ColorImage {
+    let (src_w, src_h) = (pixmap.width() as usize, pixmap.height() as usize);
+    let step = src_w.max(src_h).div_ceil(max_dim.max(1)).max(1);
+    let (w, h) = (src_w.div_ceil(step), src_h.div_ceil(step));
+    let pixels = pixmap.pixels();
+    let mut data = Vec::with_capacity(w * h);
+    for y in 0..h {
+        for x in 0..w {
+            let p = pixels[(y * step) * src_w + x * step];
+            data.push(egui::Color32::from_rgba_premultiplied(
+                p.red(),
+                p.green(),
+                p.blue(),
+                p.alpha(),
+            ));
         }
+    }
+    ColorImage::new([w, h], data)
+}
 
-        self.rendered_width = width as u32;
-        self.rendered_height = height as u32;
+/// Shown at fewer screen pixels per texel than this, a texture switches to
+/// mipmapped sampling, and back once it's shown at `MINIFY_EXIT` or more.
+pub const MINIFY_ENTER: f32 = 0.8;
+pub const MINIFY_EXIT: f32 = 0.9;
+/// Shown at more screen pixels per texel than this, a texture switches to pixelated
+/// sampling when pixel inspection is on, and back below `MAGNIFY_EXIT`.
+pub const MAGNIFY_ENTER: f32 = 2.0;
+pub const MAGNIFY_EXIT: f32 = 1.8;
 
-        // Compute intended logical display size (may be larger than pixmap due to
-        // MAX_RENDER_SCALE cap — GPU bilinear scaling bridges the gap).
-        let (effective_svg_w, effective_svg_h) =
-            if (viewport.rotation_deg % 180.0).abs() > 45.0 {
-                (doc.height, doc.width)
-            } else {
-                (doc.width, doc.height)
-            };
-        let displayed_w = effective_svg_w * viewport.zoom;
-        let displayed_h = effective_svg_h * viewport.zoom;
-        self.logical_display_w = displayed_w.min(area_width);
-        self.logical_display_h = displayed_h.min(area_height);
-        self.rendered_zoom = viewport.zoom;
+/// How the render texture is filtered for the scale it is shown at.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextureSampling {
+    /// Linear filtering, right near the rendered size.
+    #[default]
+    Plain,
+    /// Linear filtering across mipmaps, so a stale texture shown shrunk during a
+    /// zoom-out doesn't shimmer.
+    Mipmapped,
+    /// Nearest-neighbour magnification, for inspecting individual pixels.
+    Pixelated,
+}
 
-        Ok(())
+#[cfg(feature = "gui")]
+impl TextureSampling {
+    fn apply(&self, options: TextureOptions) -> TextureOptions {
+        match self {
+            TextureSampling::Plain => options,
+            TextureSampling::Mipmapped => TextureOptions {
+                mipmap_mode: Some(egui::TextureFilter::Linear),
+                ..options
+            },
+            TextureSampling::Pixelated => TextureOptions {
+                magnification: egui::TextureFilter::Nearest,
+                ..options
+            },
+        }
     }
+}
 
-    /// Upload a pre-rendered pixmap as a GPU texture (for background-loaded results).
-    pub fn upload_pixmap(
-        &mut self,
-        ctx: &egui::Context,
-        pixmap: &Pixmap,
-        viewport_zoom: f32,
-        logical_display_w: f32,
-        logical_display_h: f32,
-    ) {
-        let width = pixmap.width() as usize;
-        let height = pixmap.height() as usize;
+/// Sampling for a texture shown with `texel_scale` screen pixels per texel, moving
+/// on from `current` only once the ratio is clear of the switch point so a ratio
+/// hovering there doesn't re-upload every frame.
+pub fn choose_sampling(
+    current: TextureSampling,
+    texel_scale: f32,
+    pixel_inspection: bool,
+) -> TextureSampling {
+    let (minify_at, magnify_at) = match current {
+        TextureSampling::Plain => (MINIFY_ENTER, MAGNIFY_ENTER),
+        TextureSampling::Mipmapped => (MINIFY_EXIT, MAGNIFY_ENTER),
+        TextureSampling::Pixelated => (MINIFY_ENTER, MAGNIFY_EXIT),
+    };
+    if texel_scale < minify_at {
+        TextureSampling::Mipmapped
+    } else if pixel_inspection && texel_scale > magnify_at {
+        TextureSampling::Pixelated
+    } else {
+        TextureSampling::Plain
+    }
+}
+
+#[cfg(feature = "gui")]
+/// Texture sampling for a rendered pixmap. Linear filtering blurs a texture only
+/// a few pixels across into a faint smear, so those are sampled nearest instead,
+/// unless the render was deliberately made at reduced scale to be upscaled.
+/// Crisp renders are always sampled nearest.
+fn texture_options(
+    width: u32,
+    height: u32,
+    render_scale: RenderScale,
+    quality: RenderQuality,
+) -> TextureOptions {
+    let thin = width.min(height) < 4 && render_scale == RenderScale::Full;
+    let filter = if thin || quality == RenderQuality::Crisp {
+        egui::TextureFilter::Nearest
+    } else {
+        egui::TextureFilter::Linear
+    };
+    TextureOptions {
+        magnification: filter,
+        minification: filter,
+        ..Default::default()
+    }
+}
+
+/// Stretch any axis on which the document covers less than one pixel so it spans a
+/// full pixel; otherwise anti-aliasing fades a 1×100000 document to nothing.
+fn widen_subpixel_axes(
+    transform: tiny_skia::Transform,
+    svg_width: f32,
+    svg_height: f32,
+    render_width: u32,
+    render_height: u32,
+) -> tiny_skia::Transform {
+    let Some(bounds) = tiny_skia::Rect::from_xywh(0.0, 0.0, svg_width, svg_height)
+        .and_then(|r| r.transform(transform))
+    else {
+        return transform;
+    };
+    let stretch = |extent: f32| {
+        if extent > 0.0 && extent < 1.0 {
+            1.0 / extent
+        } else {
+            1.0
+        }
+    };
+    let (sx, sy) = (stretch(bounds.width()), stretch(bounds.height()));
+    if sx == 1.0 && sy == 1.0 {
+        return transform;
+    }
+    let cx = render_width as f32 / 2.0;
+    let cy = render_height as f32 / 2.0;
+    transform
+        .post_translate(-cx, -cy)
+        .post_scale(sx, sy)
+        .post_translate(cx, cy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture_path(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("assets")
+            .join("test_fixtures")
+            .join(name)
+    }
+
+    fn fitted(doc: &SvgDocument, area_w: f32, area_h: f32) -> Viewport {
+        let mut viewport = Viewport::default();
+        viewport.fit_to_area(doc.width(), doc.height(), area_w, area_h);
+        viewport
+    }
+
+    #[test]
+    fn test_render_view_crops_to_the_canvas() {
+        let doc = SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+        let (w, h) = (doc.width(), doc.height());
+        // Shown at 4x, panned so only the document's top-left quarter shows
+        let image = emath::Rect::from_min_size(emath::pos2(0.0, 0.0), emath::vec2(w, h) * 4.0);
+        let view = render_view(&doc, &Viewport::default(), (w * 2.0, h * 2.0), image, 1.5).unwrap();
+        assert_eq!(
+            (view.width(), view.height()),
+            ((w * 3.0).round() as u32, (h * 3.0).round() as u32)
+        );
+        let whole = render_for_export(
+            &doc,
+            (w * 6.0) as u32,
+            (h * 6.0) as u32,
+            &Viewport::default(),
+        )
+        .unwrap();
+        let (x, y) = (view.width() / 2, view.height() / 2);
+        assert_eq!(view.pixel(x, y), whole.pixel(x, y));
+
+        // Huge canvases are scaled down to the size limit
+        let huge = (MAX_RENDER_DIM as f32 * 2.0, 10.0);
+        let image = emath::Rect::from_min_size(emath::pos2(0.0, 0.0), emath::vec2(10.0, 10.0));
+        let view = render_view(&doc, &Viewport::default(), huge, image, 1.0).unwrap();
+        assert_eq!((view.width(), view.height()), (MAX_RENDER_DIM, 5));
+    }
+
+    #[test]
+    fn test_render_view_for_export_is_not_reduced() {
+        let doc = SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+        let (w, h) = (doc.width(), doc.height());
+        let image = emath::Rect::from_min_size(emath::pos2(0.0, 0.0), emath::vec2(w, h) * 4.0);
+        let canvas = (w * 2.0, h * 2.0);
+        let view = render_view(&doc, &Viewport::default(), canvas, image, 1.5).unwrap();
+        let export = render_view_for_export(&doc, &Viewport::default(), canvas, image, 1.5);
+        assert_eq!(export.unwrap().data(), view.data());
+
+        // Past the render limit but within the export limit
+        let wide = (MAX_RENDER_DIM as f32 * 1.5, 10.0);
+        let view = render_view_for_export(&doc, &Viewport::default(), wide, image, 1.0).unwrap();
+        assert_eq!(view.width(), MAX_RENDER_DIM * 3 / 2);
+        // Past the export limit it fails rather than shrinking
+        let view = render_view_for_export(&doc, &Viewport::default(), wide, image, 3.0);
+        assert!(view.is_err());
+    }
 
-        let image = ColorImage::from_rgba_premultiplied([width, height], pixmap.data());
+    #[test]
+    fn test_tiled_render_matches_single_pass() {
+        let doc = SvgDocument::load(&fixture_path("gradient.svg")).unwrap();
+        // A tile and a bit each way, so there are seams across and down
+        let (width, height) = (MAX_RENDER_DIM + 100, MAX_RENDER_DIM + 50);
+        let transform = Viewport::default().build_transform(
+            doc.width(),
+            doc.height(),
+            width as f32,
+            height as f32,
+        );
+        let tiled = render_tiled(&doc, width, height, transform).unwrap();
+        let mut whole = Pixmap::new(width, height).unwrap();
+        resvg::render(doc.tree(), transform, &mut whole.as_mut());
+        let close = whole
+            .data()
+            .iter()
+            .zip(tiled.data())
+            .all(|(a, b)| a.abs_diff(*b) <= 4);
+        assert!(close, "tiles differ from one render");
+    }
+
+    #[test]
+    fn test_banded_render_matches_single_pass() {
+        for name in ["complex_paths.svg", "gradient.svg"] {
+            let doc = SvgDocument::load(&fixture_path(name)).unwrap();
+            let viewport = fitted(&doc, 900.0, 700.0);
+            let whole = render_to_pixmap(&doc, &viewport, 900.0, 700.0, 1.0).unwrap();
+            assert!(
+                whole.height() > CANCEL_CHECK_ROWS,
+                "{name} renders in one band"
+            );
+            let banded = render_to_pixmap_cancellable(
+                &doc,
+                &viewport,
+                900.0,
+                700.0,
+                1.0,
+                RenderScale::Full,
+                &AtomicBool::new(false),
+            )
+            .unwrap()
+            .unwrap();
+            // Shifting the transform per band only changes float rounding
+            let close = whole
+                .data()
+                .iter()
+                .zip(banded.data())
+                .all(|(a, b)| a.abs_diff(*b) <= 4);
+            assert!(close, "{name} differs when banded");
+        }
+    }
 
-        let options = TextureOptions {
-            magnification: egui::TextureFilter::Linear,
-            minification: egui::TextureFilter::Linear,
+    #[test]
+    fn test_cancelled_render_returns_none() {
+        let doc = SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+        let rendered = render_to_pixmap_cancellable(
+            &doc,
+            &Viewport::default(),
+            800.0,
+            600.0,
+            1.0,
+            RenderScale::Full,
+            &AtomicBool::new(true),
+        )
+        .unwrap();
+        assert!(rendered.is_none());
+    }
+
+    #[test]
+    fn test_extreme_aspect_keeps_one_visible_pixel() {
+        let doc = SvgDocument::load(&fixture_path("extreme_aspect.svg")).unwrap();
+        let viewport = fitted(&doc, 800.0, 600.0);
+        let pixmap = render_to_pixmap(&doc, &viewport, 800.0, 600.0, 1.0).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (1, 600));
+        let pixel = pixmap.pixel(0, 300).unwrap();
+        assert_eq!(pixel.alpha(), 255);
+        assert_eq!(pixel.red(), 0xD5);
+    }
+
+    #[test]
+    fn test_display_size_minor_side_is_one_physical_pixel() {
+        let viewport = Viewport {
+            zoom: 0.006,
             ..Default::default()
         };
+        let (w, h) = display_size(1.0, 100000.0, &viewport, 800.0, 600.0, 2.0);
+        assert_eq!(w, 0.5);
+        assert_eq!(h, 600.0);
 
-        match &mut self.texture {
-            Some(handle) => handle.set(image, options),
-            None => {
-                self.texture = Some(ctx.load_texture("svg_render", image, options));
+        // Rotation swaps which side is minor
+        let rotated = Viewport {
+            rotation_deg: 90.0,
+            ..viewport
+        };
+        let (w, h) = display_size(1.0, 100000.0, &rotated, 800.0, 600.0, 2.0);
+        assert_eq!((w, h), (600.0, 0.5));
+    }
+
+    fn pixmap_with(width: u32, height: u32, painted: &[(u32, u32, u8)]) -> Pixmap {
+        let mut pixmap = Pixmap::new(width, height).unwrap();
+        for &(x, y, alpha) in painted {
+            let i = ((y * width + x) * 4) as usize;
+            pixmap.data_mut()[i + 3] = alpha;
+        }
+        pixmap
+    }
+
+    #[test]
+    fn test_is_blank_threshold() {
+        assert!(is_blank(&pixmap_with(16, 16, &[])));
+        assert!(is_blank(&pixmap_with(16, 16, &[(3, 3, BLANK_ALPHA)])));
+        assert!(!is_blank(&pixmap_with(
+            16,
+            16,
+            &[(15, 15, BLANK_ALPHA + 1)]
+        )));
+    }
+
+    #[test]
+    fn test_is_blank_samples_large_pixmaps() {
+        // 1024² = 16× the sample budget, so every 4th pixel in each direction is
+        // checked, starting at 2
+        let (w, h) = (1024, 1024);
+        assert!(w as usize * h as usize > BLANK_MAX_SAMPLES);
+        assert!(!is_blank(&pixmap_with(w, h, &[(2, 2, 255)])));
+        assert!(!is_blank(&pixmap_with(w, h, &[(1022, 1022, 255)])));
+        // Off the sampling grid: missed by design
+        assert!(is_blank(&pixmap_with(w, h, &[(3, 3, 255)])));
+    }
+
+    #[test]
+    fn test_zero_size_document_renders() {
+        let doc = SvgDocument::load(&fixture_path("zero_size_empty.svg")).unwrap();
+        let viewport = fitted(&doc, 400.0, 400.0);
+        let pixmap = render_to_pixmap(&doc, &viewport, 400.0, 400.0, 1.0).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (4, 4));
+    }
+
+    #[test]
+    fn test_huge_document_render_is_clamped() {
+        let doc = SvgDocument::load(&fixture_path("huge_dimensions.svg")).unwrap();
+        let viewport = Viewport {
+            zoom: 1.0,
+            ..Default::default()
+        };
+        let pixmap = render_to_pixmap(&doc, &viewport, 8000.0, 8000.0, 1.0).unwrap();
+        assert_eq!(
+            (pixmap.width(), pixmap.height()),
+            (MAX_RENDER_DIM, MAX_RENDER_DIM)
+        );
+    }
+
+    #[test]
+    fn test_render_dimensions_scale_physical_size() {
+        // 400×300 logical at 2× DPI is 800×600 physical; half scale renders 400×300
+        let full = render_dimensions((400.0, 300.0), (400.0, 300.0), 2.0, 1.0);
+        assert_eq!(full, (800, 600));
+        let half = render_dimensions((400.0, 300.0), (400.0, 300.0), 2.0, 0.5);
+        assert_eq!(half, (400, 300));
+        let quarter = render_dimensions((400.0, 300.0), (400.0, 300.0), 2.0, 0.25);
+        assert_eq!(quarter, (200, 150));
+    }
+
+    #[test]
+    fn test_render_dimensions_caps_take_the_minimum() {
+        // Zoomed far in, the native-size cap still wins over a reduced scale
+        let capped = render_dimensions((4000.0, 4000.0), (100.0, 100.0), 1.0, 0.75);
+        assert_eq!(capped, (400, 400));
+        // A tiny display never renders to zero pixels
+        assert_eq!(
+            render_dimensions((1.0, 1.0), (100.0, 100.0), 1.0, 0.25),
+            (1, 1)
+        );
+    }
+
+    #[test]
+    fn test_scaled_render_keeps_display_size() {
+        let doc = SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+        let mut viewport = Viewport::default();
+        viewport.fit_to_area(doc.width(), doc.height(), 400.0, 400.0);
+        let full = render_to_pixmap(&doc, &viewport, 400.0, 400.0, 1.0).unwrap();
+        let half =
+            render_to_pixmap_scaled(&doc, &viewport, 400.0, 400.0, 1.0, RenderScale::Half).unwrap();
+        assert_eq!(half.width(), (full.width() as f32 * 0.5).round() as u32);
+        assert_eq!(half.height(), (full.height() as f32 * 0.5).round() as u32);
+    }
+
+    /// Textures held in memory, with switches to fail uploads or drop textures.
+    #[cfg(feature = "gui")]
+    #[derive(Default)]
+    struct MockTextures {
+        live: std::collections::HashMap<u64, [usize; 2]>,
+        next_id: u64,
+        fail_uploads: bool,
+        uploads: u32,
+    }
+
+    #[cfg(feature = "gui")]
+    #[derive(Clone, Default)]
+    struct SharedMock(std::rc::Rc<std::cell::RefCell<MockTextures>>);
+
+    #[cfg(feature = "gui")]
+    impl TextureBackend for SharedMock {
+        fn upload(
+            &mut self,
+            image: ColorImage,
+            _options: TextureOptions,
+        ) -> Result<egui::TextureId> {
+            let mut mock = self.0.borrow_mut();
+            mock.uploads += 1;
+            if mock.fail_uploads {
+                return Err(SvgError::Render("device lost".into()));
+            }
+            mock.next_id += 1;
+            let id = mock.next_id;
+            mock.live.clear();
+            mock.live.insert(id, image.size);
+            Ok(egui::TextureId::User(id))
+        }
+
+        fn is_valid(&self, id: egui::TextureId) -> bool {
+            match id {
+                egui::TextureId::User(id) => self.0.borrow().live.contains_key(&id),
+                egui::TextureId::Managed(_) => false,
             }
         }
+    }
 
-        self.rendered_width = width as u32;
-        self.rendered_height = height as u32;
-        self.logical_display_w = logical_display_w;
-        self.logical_display_h = logical_display_h;
-        self.rendered_zoom = viewport_zoom;
+    #[cfg(feature = "gui")]
+    fn mock_renderer() -> (Renderer, SharedMock) {
+        let mock = SharedMock::default();
+        (Renderer::with_backend(Box::new(mock.clone())), mock)
     }
 
-    /// Render an SVG at a specific resolution for export (no viewport transforms).
-    pub fn render_for_export(
-        doc: &SvgDocument,
-        width: u32,
-        height: u32,
-        viewport: &Viewport,
-    ) -> Result<Pixmap> {
-        let width = width.clamp(1, MAX_RENDER_DIM);
-        let height = height.clamp(1, MAX_RENDER_DIM);
+    #[cfg(feature = "gui")]
+    fn solid_pixmap(width: u32, height: u32) -> Pixmap {
+        let mut pixmap = Pixmap::new(width, height).unwrap();
+        pixmap.fill(tiny_skia::Color::from_rgba8(200, 40, 40, 255));
+        pixmap
+    }
 
-        let mut pixmap = Pixmap::new(width, height)
-            .ok_or_else(|| SvgError::Render("Failed to create pixmap".into()))?;
+    /// `pixmap` as a render at 100%, with no effects.
+    #[cfg(feature = "gui")]
+    fn plain_render(pixmap: Pixmap) -> CanvasRender {
+        let display = (pixmap.width() as f32, pixmap.height() as f32);
+        CanvasRender::new(
+            pixmap,
+            &PostPipeline::new(),
+            1.0,
+            display,
+            RenderScale::Full,
+        )
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_lost_texture_is_reuploaded() {
+        let (mut renderer, mock) = mock_renderer();
+        let ctx = egui::Context::default();
+        renderer.show_render(&ctx, plain_render(solid_pixmap(40, 30)));
+        assert!(matches!(
+            renderer.presentation(),
+            Some(Presentation::Texture(_))
+        ));
+
+        // The texture vanishes; the periodic check notices and re-uploads
+        mock.0.borrow_mut().live.clear();
+        for _ in 0..TEXTURE_CHECK_INTERVAL {
+            renderer.maintain();
+        }
+        assert_eq!(mock.0.borrow().uploads, 2);
+        let Some(Presentation::Texture(id)) = renderer.presentation() else {
+            panic!("expected a texture");
+        };
+        assert_eq!(id, egui::TextureId::User(2));
+        assert_eq!(mock.0.borrow().live[&2], [40, 30]);
+        assert!(!renderer.software_fallback());
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_failing_uploads_fall_back_to_software() {
+        let (mut renderer, mock) = mock_renderer();
+        let ctx = egui::Context::default();
+        mock.0.borrow_mut().fail_uploads = true;
+        renderer.show_render(&ctx, plain_render(solid_pixmap(640, 320)));
+        assert!(renderer.presentation().is_none());
 
-        let transform =
-            viewport.build_transform(doc.width, doc.height, width as f32, height as f32);
-        resvg::render(&doc.tree, transform, &mut pixmap.as_mut());
+        // Retried each frame until the attempts run out
+        for _ in 0..10 {
+            renderer.maintain();
+        }
+        assert_eq!(mock.0.borrow().uploads, MAX_UPLOAD_ATTEMPTS);
+        assert!(renderer.software_fallback());
+        let Some(Presentation::Software(image)) = renderer.presentation() else {
+            panic!("expected the software preview");
+        };
+        assert_eq!(image.size, [FALLBACK_MAX_DIM, FALLBACK_MAX_DIM / 2]);
+        assert!(image
+            .pixels
+            .iter()
+            .all(|p| *p == egui::Color32::from_rgb(200, 40, 40)));
+
+        // Once the backend recovers, retrying returns to the GPU path
+        mock.0.borrow_mut().fail_uploads = false;
+        renderer.retry_texture();
+        renderer.maintain();
+        assert!(matches!(
+            renderer.presentation(),
+            Some(Presentation::Texture(_))
+        ));
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_new_render_retries_after_fallback() {
+        let (mut renderer, mock) = mock_renderer();
+        let ctx = egui::Context::default();
+        mock.0.borrow_mut().fail_uploads = true;
+        for _ in 0..MAX_UPLOAD_ATTEMPTS {
+            renderer.show_render(&ctx, plain_render(solid_pixmap(8, 8)));
+        }
+        assert!(!renderer.software_fallback());
+        mock.0.borrow_mut().fail_uploads = false;
+        renderer.show_render(&ctx, plain_render(solid_pixmap(8, 8)));
+        assert!(matches!(
+            renderer.presentation(),
+            Some(Presentation::Texture(_))
+        ));
+    }
 
-        Ok(pixmap)
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_retained_pixmap_fits_budget() {
+        let pixmap = solid_pixmap(1000, 500);
+        let kept = fit_to_budget(pixmap, 600_000);
+        assert!(kept.data().len() <= 600_000);
+        assert_eq!((kept.width(), kept.height()), (500, 250));
+        let small = fit_to_budget(solid_pixmap(10, 10), 600_000);
+        assert_eq!((small.width(), small.height()), (10, 10));
+    }
+
+    fn sampling_sequence(ratios: &[f32], pixel_inspection: bool) -> Vec<TextureSampling> {
+        let mut current = TextureSampling::default();
+        ratios
+            .iter()
+            .map(|&ratio| {
+                current = choose_sampling(current, ratio, pixel_inspection);
+                current
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sampling_follows_zoom_out_with_hysteresis() {
+        use TextureSampling::*;
+        // Zooming out, wobbling around the switch point, then re-rendered (1.0)
+        assert_eq!(
+            sampling_sequence(&[1.0, 0.9, 0.79, 0.82, 0.85, 0.5, 0.89, 1.0], false),
+            [Plain, Plain, Mipmapped, Mipmapped, Mipmapped, Mipmapped, Mipmapped, Plain]
+        );
+    }
+
+    #[test]
+    fn test_sampling_pixelates_only_with_pixel_inspection() {
+        use TextureSampling::*;
+        let zoom_in = [1.5, 2.1, 1.9, 2.05, 1.7, 1.0];
+        assert_eq!(sampling_sequence(&zoom_in, false), [Plain; 6]);
+        assert_eq!(
+            sampling_sequence(&zoom_in, true),
+            [Plain, Pixelated, Pixelated, Pixelated, Plain, Plain]
+        );
+        // Straight from one extreme to the other
+        assert_eq!(
+            sampling_sequence(&[0.5, 3.0, 0.5], true),
+            [Mipmapped, Pixelated, Mipmapped]
+        );
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_sampling_change_reuploads_retained_pixmap() {
+        let (mut renderer, mock) = mock_renderer();
+        let ctx = egui::Context::default();
+        renderer.show_render(&ctx, plain_render(solid_pixmap(8, 8)));
+        renderer.maintain();
+        assert_eq!(mock.0.borrow().uploads, 1);
+
+        renderer.set_sampling(TextureSampling::Mipmapped);
+        renderer.set_sampling(TextureSampling::Mipmapped);
+        renderer.maintain();
+        renderer.maintain();
+        assert_eq!(mock.0.borrow().uploads, 2);
+
+        // A render that follows the change uploads once, with the new sampling
+        renderer.set_sampling(TextureSampling::Plain);
+        renderer.show_render(&ctx, plain_render(solid_pixmap(8, 8)));
+        renderer.maintain();
+        assert_eq!(mock.0.borrow().uploads, 3);
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_texture_filter_for_thin_renders() {
+        assert_eq!(
+            texture_options(1, 600, RenderScale::Full, RenderQuality::Smooth).magnification,
+            egui::TextureFilter::Nearest
+        );
+        assert_eq!(
+            texture_options(1, 600, RenderScale::Half, RenderQuality::Smooth).magnification,
+            egui::TextureFilter::Linear
+        );
+        assert_eq!(
+            texture_options(600, 400, RenderScale::Full, RenderQuality::Smooth).magnification,
+            egui::TextureFilter::Linear
+        );
+        let crisp = texture_options(600, 400, RenderScale::Half, RenderQuality::Crisp);
+        assert_eq!(crisp.magnification, egui::TextureFilter::Nearest);
+        assert_eq!(crisp.minification, egui::TextureFilter::Nearest);
+    }
+
+    fn pixel(pixmap: &Pixmap) -> (u8, u8, u8, u8) {
+        let p = pixmap.pixels()[0];
+        (p.red(), p.green(), p.blue(), p.alpha())
+    }
+
+    fn filled(width: u32, height: u32, r: u8, g: u8, b: u8, a: u8) -> Pixmap {
+        let mut pixmap = Pixmap::new(width, height).unwrap();
+        pixmap.fill(tiny_skia::Color::from_rgba8(r, g, b, a));
+        pixmap
+    }
+
+    /// A pixmap with every channel varying, for comparing pipelines.
+    fn varied(width: u32, height: u32) -> Pixmap {
+        let mut pixmap = Pixmap::new(width, height).unwrap();
+        for (i, pixel) in pixmap.pixels_mut().iter_mut().enumerate() {
+            let a = (i % 256) as u8;
+            let c = |n: usize| ((i / n % 256) as u32 * a as u32 / 255) as u8;
+            *pixel = PremultipliedColorU8::from_rgba(c(3), c(7), c(11), a).unwrap();
+        }
+        pixmap
+    }
+
+    #[test]
+    fn test_grayscale_and_invert() {
+        let mut pixmap = filled(2, 2, 255, 0, 0, 255);
+        PostPipeline::new().with(Grayscale).run(&mut pixmap);
+        assert_eq!(pixel(&pixmap), (54, 54, 54, 255));
+
+        let mut pixmap = filled(2, 2, 255, 0, 0, 255);
+        PostPipeline::new().with(Invert).run(&mut pixmap);
+        assert_eq!(pixel(&pixmap), (0, 255, 255, 255));
+
+        // Transparent pixels stay transparent
+        let mut pixmap = Pixmap::new(2, 2).unwrap();
+        PostPipeline::new()
+            .with(Grayscale)
+            .with(Invert)
+            .run(&mut pixmap);
+        assert_eq!(pixel(&pixmap), (0, 0, 0, 0));
+    }
+
+    #[test]
+    fn test_tint_colors_dark_and_keeps_light() {
+        let mut black = filled(2, 2, 0, 0, 0, 255);
+        PostPipeline::new()
+            .with(Tint([220, 40, 40]))
+            .run(&mut black);
+        assert_eq!(pixel(&black), (220, 40, 40, 255));
+
+        let mut white = filled(2, 2, 255, 255, 255, 255);
+        PostPipeline::new()
+            .with(Tint([220, 40, 40]))
+            .run(&mut white);
+        assert_eq!(pixel(&white), (255, 255, 255, 255));
+
+        // Half-covered black stays premultiplied
+        let mut faint = filled(2, 2, 0, 0, 0, 128);
+        PostPipeline::new()
+            .with(Tint([220, 40, 40]))
+            .run(&mut faint);
+        assert_eq!(pixel(&faint), (110, 20, 20, 128));
+    }
+
+    #[test]
+    fn test_effect_order_matters() {
+        let color = ColorManagement {
+            enabled: true,
+            profile: crate::color_management::DisplayProfile::DisplayP3,
+        };
+        let conversion = || DisplayConversion::new(color).unwrap();
+        let mut gray_first = filled(2, 2, 255, 0, 0, 255);
+        PostPipeline::new()
+            .with(Grayscale)
+            .with(conversion())
+            .run(&mut gray_first);
+        let mut converted_first = filled(2, 2, 255, 0, 0, 255);
+        PostPipeline::new()
+            .with(conversion())
+            .with(Grayscale)
+            .run(&mut converted_first);
+        assert_ne!(pixel(&gray_first), pixel(&converted_first));
+        // Converting a gray leaves it gray; graying the converted red doesn't
+        // give the same gray
+        let (r, g, b, _) = pixel(&gray_first);
+        assert!(r == g && g == b);
+
+        // The canvas grays what's drawn, then converts it for the display
+        let mut canvas = filled(2, 2, 255, 0, 0, 255);
+        PostPipeline::for_canvas(
+            PreviewEffects {
+                grayscale: true,
+                invert: false,
+            },
+            color,
+        )
+        .run(&mut canvas);
+        assert_eq!(pixel(&canvas), pixel(&gray_first));
+    }
+
+    #[test]
+    fn test_export_pipeline_has_no_display_conversion() {
+        assert!(PostPipeline::for_export(PreviewEffects::default()).is_empty());
+        let color = ColorManagement {
+            enabled: true,
+            profile: crate::color_management::DisplayProfile::DisplayP3,
+        };
+        assert!(!PostPipeline::for_canvas(PreviewEffects::default(), color).is_empty());
+    }
+
+    #[test]
+    fn test_combined_pass_matches_sequential_passes() {
+        // Large enough to be split between threads
+        let source = varied(400, 400);
+        let mut combined = source.clone();
+        PostPipeline::new()
+            .with(Grayscale)
+            .with(Invert)
+            .run(&mut combined);
+        let mut sequential = source;
+        PostPipeline::new().with(Grayscale).run(&mut sequential);
+        PostPipeline::new().with(Invert).run(&mut sequential);
+        assert!(combined.pixels() == sequential.pixels());
+    }
+
+    #[test]
+    fn test_effects_get_bands_of_whole_rows() {
+        use std::sync::{Arc, Mutex};
+
+        struct Record(Arc<Mutex<Vec<(usize, u32, u32)>>>);
+        impl PostProcess for Record {
+            fn apply(&self, pixels: &mut [PremultipliedColorU8], width: u32, height: u32) {
+                self.0.lock().unwrap().push((pixels.len(), width, height));
+            }
+        }
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut pixmap = Pixmap::new(300, 301).unwrap();
+        PostPipeline::new()
+            .with(Record(Arc::clone(&calls)))
+            .run(&mut pixmap);
+        let calls = calls.lock().unwrap();
+        assert!(calls.iter().all(|&(len, width, height)| {
+            width == 300 && len == (width * height) as usize && height > 0
+        }));
+        let rows: u32 = calls.iter().map(|&(_, _, height)| height).sum();
+        assert_eq!(rows, 301);
+    }
+
+    #[test]
+    fn test_downsample_averages_blocks() {
+        let mut pixmap = Pixmap::new(5, 3).unwrap();
+        for (i, pixel) in pixmap.pixels_mut().iter_mut().enumerate() {
+            // Opaque white in even columns, transparent in odd ones
+            let v = if i % 5 % 2 == 0 { 255 } else { 0 };
+            *pixel = PremultipliedColorU8::from_rgba(v, v, v, v).unwrap();
+        }
+        let small = downsample(&pixmap, 2);
+        assert_eq!((small.width(), small.height()), (3, 2));
+        let alpha: Vec<u8> = small.pixels().iter().map(|p| p.alpha()).collect();
+        // Half-covered blocks, then the single white column at the right edge
+        assert_eq!(alpha, vec![128, 128, 255, 128, 128, 255]);
+        assert!(downsample(&pixmap, 1).pixels() == pixmap.pixels());
+    }
+
+    #[test]
+    fn test_supersample_factor_limits() {
+        // Shrunk to 0.1 px per unit
+        assert_eq!(supersample_factor((800, 800), (8000.0, 8000.0)), 2);
+        // At 0.5 px per unit or more there's no need
+        assert_eq!(supersample_factor((800, 800), (1600.0, 1600.0)), 1);
+        // Twice the size would pass MAX_RENDER_DIM
+        assert_eq!(supersample_factor((3000, 100), (60000.0, 2000.0)), 1);
+    }
+
+    #[test]
+    fn test_headroom_limits() {
+        assert_eq!(headroom_for((1000, 800), (10000.0, 10000.0)), ZOOM_HEADROOM);
+        // Twice the size would pass MAX_RENDER_DIM
+        let headroom = headroom_for((3000, 100), (60000.0, 2000.0));
+        assert!((headroom - MAX_RENDER_DIM as f32 / 3000.0).abs() < 1e-4);
+        // Already at MAX_RENDER_SCALE times the document
+        assert_eq!(headroom_for((400, 400), (100.0, 100.0)), 1.0);
+        // Within the dimension limit, but not the memory budget
+        let headroom = headroom_for((3840, 2160), (100000.0, 100000.0));
+        let bytes = 3840.0 * headroom * 2160.0 * headroom * 4.0;
+        assert!(headroom > 1.0 && bytes <= HEADROOM_BUDGET as f32 * 1.001);
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_render_covers_modest_zoom() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <rect width="100" height="100" fill="red"/></svg>"#;
+        let doc = SvgDocument::from_data(std::path::Path::new("r.svg"), svg.into()).unwrap();
+        let (mut renderer, _mock) = mock_renderer();
+        let ctx = egui::Context::default();
+        let at = |zoom| Viewport {
+            zoom,
+            ..Default::default()
+        };
+        renderer
+            .render_and_upload(&ctx, &doc, &at(2.0), 800.0, 600.0, RenderScale::Full)
+            .unwrap();
+        assert_eq!(renderer.rendered_headroom, 2.0);
+        assert_eq!(renderer.rendered_width, 400);
+        assert_eq!(renderer.logical_display_w, 200.0);
+
+        let covers = |zoom, area| renderer.covers_zoom(&doc, &at(zoom), area, area, 1.0);
+        assert!(covers(2.0, 800.0));
+        assert!(covers(3.5, 800.0));
+        assert!(covers(4.0, 800.0));
+        // Past the texture's resolution, or far below it
+        assert!(!covers(4.5, 800.0));
+        assert!(!covers(1.9, 800.0));
+        // A render would be capped to the area rather than grow
+        assert!(!covers(3.5, 300.0));
+    }
+
+    #[test]
+    fn test_render_worker_skips_superseded_requests() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <rect width="100" height="100" fill="red"/></svg>"#;
+        let doc =
+            Arc::new(SvgDocument::from_data(std::path::Path::new("r.svg"), svg.into()).unwrap());
+        let request = || CanvasRequest {
+            doc: Arc::clone(&doc),
+            viewport: Viewport::default(),
+            area: (100.0, 100.0),
+            pixels_per_point: 1.0,
+            render_scale: RenderScale::Full,
+            high_quality_downscale: false,
+            pipeline: PostPipeline::new().with(Invert),
+        };
+        // Each render waits to be let go once it's finished
+        let (entered, wait_entered) = mpsc::channel();
+        let (release, wait_release) = mpsc::channel::<()>();
+        let worker = RenderWorker::spawn(move || {
+            let _ = entered.send(());
+            let _ = wait_release.recv();
+        });
+
+        // Two requests come in while the first is being finished; only the
+        // second of them is rendered
+        worker.request(1, request());
+        wait_entered.recv().unwrap();
+        worker.request(2, request());
+        worker.request(3, request());
+        release.send(()).unwrap();
+        wait_entered.recv().unwrap();
+        release.send(()).unwrap();
+
+        let finished: Vec<_> = worker.finished().collect();
+        let generations: Vec<_> = finished.iter().map(|(g, _)| *g).collect();
+        assert_eq!(generations, [1, 3]);
+        // Finished on the worker, with the effects applied
+        let render = finished[1].1.as_ref().unwrap();
+        assert!(!render.blank && render.reduced.is_none());
+        let pixel = render.pixmap.pixel(50, 50).unwrap();
+        assert_eq!((pixel.red(), pixel.green(), pixel.blue()), (0, 255, 255));
+    }
+
+    #[test]
+    fn test_supersampled_render_keeps_fine_detail() {
+        // Dots 2 units across every 5 units, shown at 0.1 px per unit: each
+        // pixel should be about 12.6% covered
+        let dots: String = (0..200)
+            .flat_map(|i| (0..200).map(move |j| (i * 5 + 2, j * 5 + 2)))
+            .map(|(x, y)| format!("<circle cx=\"{x}\" cy=\"{y}\" r=\"1\"/>"))
+            .collect();
+        let svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="1000" height="1000">{dots}</svg>"#
+        );
+        let doc =
+            SvgDocument::from_data(std::path::Path::new("dots.svg"), svg.into_bytes()).unwrap();
+        let viewport = fitted(&doc, 100.0, 100.0);
+        let direct = render_to_pixmap(&doc, &viewport, 100.0, 100.0, 1.0).unwrap();
+        let (supersampled, factor) =
+            render_to_pixmap_supersampled(&doc, &viewport, 100.0, 100.0, 1.0, RenderScale::Full)
+                .unwrap();
+        assert_eq!(factor, 2);
+        assert_eq!(
+            (supersampled.width(), supersampled.height()),
+            (direct.width(), direct.height())
+        );
+        let mean_alpha = |pixmap: &Pixmap| {
+            pixmap
+                .pixels()
+                .iter()
+                .map(|p| p.alpha() as f32)
+                .sum::<f32>()
+                / pixmap.pixels().len() as f32
+        };
+        let ideal = 255.0 * std::f32::consts::PI / 25.0;
+        let error = |pixmap: &Pixmap| (mean_alpha(pixmap) - ideal).abs();
+        // Drawn directly, dots this small mostly vanish
+        assert!(error(&supersampled) < error(&direct));
+        assert!(mean_alpha(&supersampled) > 0.0);
     }
 }
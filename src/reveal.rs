@@ -0,0 +1,63 @@
+//! Showing a file in the platform's file manager: selected in Explorer or the
+//! Finder, and its folder opened elsewhere, where there's no common way to
+//! select a file.
+
+use std::ffi::OsString;
+use std::path::Path;
+
+use crate::error::{Result, SvgError};
+
+/// Open the file manager at `path`. Only starting it is waited for, so a
+/// file manager that never comes up isn't reported.
+pub fn reveal(path: &Path) -> Result<()> {
+    // Not canonicalized, since Explorer doesn't take `\\?\` paths
+    let path = std::path::absolute(path)?;
+    let (program, args) = command_for(&path);
+    let mut child = std::process::Command::new(program)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .spawn()
+        .map_err(|e| {
+            SvgError::Io(std::io::Error::other(format!(
+                "couldn't run {program}: {e}"
+            )))
+        })?;
+    // Reap it whenever it exits
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// The program that shows `path`, and its arguments.
+fn command_for(path: &Path) -> (&'static str, Vec<OsString>) {
+    if cfg!(target_os = "macos") {
+        ("open", vec!["-R".into(), path.into()])
+    } else if cfg!(windows) {
+        let mut select = OsString::from("/select,");
+        select.push(path);
+        ("explorer", vec![select])
+    } else {
+        let dir = path.parent().unwrap_or(path);
+        ("xdg-open", vec![dir.into()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_for() {
+        let path = Path::new("/art/icons/logo.svg");
+        let (program, args) = command_for(path);
+        if cfg!(target_os = "macos") {
+            assert_eq!(program, "open");
+            assert_eq!(args, ["-R", "/art/icons/logo.svg"]);
+        } else if cfg!(windows) {
+            assert_eq!(program, "explorer");
+            assert_eq!(args, ["/select,/art/icons/logo.svg"]);
+        } else {
+            assert_eq!(program, "xdg-open");
+            assert_eq!(args, ["/art/icons"]);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Tick marks for the rulers along the canvas edges.
+//!
+//! Ticks are laid out in SVG units, at a step of 1, 2 or 5 times a power of
+//! ten chosen so labels don't crowd at any zoom.
+
+/// Thickness of a ruler, in logical pixels.
+pub const RULER_SIZE: f32 = 18.0;
+/// Labelled ticks are at least this far apart on screen, in logical pixels.
+pub const MIN_TICK_SPACING: f32 = 60.0;
+/// Unlabelled ticks between labelled ones.
+pub const MINOR_TICKS: u32 = 5;
+
+/// The smallest step of 1, 2 or 5 times a power of ten that puts labelled
+/// ticks at least [`MIN_TICK_SPACING`] apart at `pixels_per_unit`.
+pub fn tick_step(pixels_per_unit: f32) -> f32 {
+    if !pixels_per_unit.is_finite() || pixels_per_unit <= 0.0 {
+        return 1.0;
+    }
+    let min_step = MIN_TICK_SPACING / pixels_per_unit;
+    let power = 10f32.powf(min_step.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * power)
+        .find(|&step| step >= min_step * 0.999)
+        .unwrap_or(10.0 * power)
+}
+
+/// The multiples of `step` from `start` to `end`, in either order.
+pub fn ticks(start: f32, end: f32, step: f32) -> Vec<f32> {
+    let (low, high) = if start <= end {
+        (start, end)
+    } else {
+        (end, start)
+    };
+    if step <= 0.0 || !low.is_finite() || !high.is_finite() {
+        return Vec::new();
+    }
+    let first = (low / step).ceil() as i64;
+    let last = (high / step).floor() as i64;
+    (first..=last).map(|i| i as f32 * step).collect()
+}
+
+/// A tick's label, with as many decimals as the step needs.
+pub fn format_tick(value: f32, step: f32) -> String {
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    // -0 reads oddly on a ruler
+    let value = if value.abs() < step / 2.0 { 0.0 } else { value };
+    format!("{value:.decimals$}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tick_step_adapts_to_zoom() {
+        // At 1 px per unit, 60 units is too fine a step to be round: 100
+        assert_eq!(tick_step(1.0), 100.0);
+        assert_eq!(tick_step(2.0), 50.0);
+        assert_eq!(tick_step(6.0), 10.0);
+        // Zoomed right in, the steps go below one unit
+        assert!((tick_step(300.0) - 0.2).abs() < 1e-6);
+        assert_eq!(tick_step(0.0), 1.0);
+    }
+
+    #[test]
+    fn test_ticks_and_labels() {
+        assert_eq!(ticks(-15.0, 42.0, 20.0), vec![0.0, 20.0, 40.0]);
+        // Mirrored rulers run backwards
+        assert_eq!(ticks(42.0, -15.0, 20.0), vec![0.0, 20.0, 40.0]);
+        assert_eq!(format_tick(40.0, 20.0), "40");
+        assert_eq!(format_tick(0.6000001, 0.2), "0.6");
+        assert_eq!(format_tick(-0.0000001, 0.05), "0.00");
+    }
+}
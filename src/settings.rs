@@ -0,0 +1,331 @@
+//! What the viewer remembers between sessions: the last file, theme and
+//! checkerboard choices, the last export's choices, and the zoom each
+//! recently viewed file was left at.
+//! Font options are read from here too, though the viewer never changes them.
+//!
+//! Settings live in `svg-viewer/settings.toml` in the platform config
+//! directory. A missing or unreadable file just means the defaults.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::archive;
+use crate::color_management::DisplayProfile;
+use crate::error::{Result, SvgError};
+use crate::export::{ExportFormat, ExportSettings};
+use crate::renderer::RenderQuality;
+
+/// How many files' zoom levels are remembered; older ones are forgotten.
+pub const MAX_REMEMBERED_ZOOMS: usize = 50;
+/// Megabytes an export may be expected to need before the dialog warns.
+pub const DEFAULT_EXPORT_MEMORY_MB: u64 = 1024;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub last_file: Option<PathBuf>,
+    pub dark_mode: bool,
+    pub show_checkerboard: bool,
+    /// Convert the canvas render for `display_profile`
+    pub color_management: bool,
+    pub display_profile: DisplayProfile,
+    /// Supersample renders that shrink a document to a fraction of its size
+    pub high_quality_downscale: bool,
+    /// Smooth or crisp edges, on the canvas and in exports
+    pub render_quality: RenderQuality,
+    /// Panning keeps part of the image on screen
+    pub constrain_pan: bool,
+    /// Rulers in SVG units along the canvas edges
+    pub show_rulers: bool,
+    /// The strip of thumbnails of the files being navigated
+    pub show_thumbnails: bool,
+    /// Copies are flattened onto white, for apps that show transparency as black
+    pub copy_white_background: bool,
+    /// Family for text that doesn't name one; `--font-family` overrides it
+    pub default_font_family: Option<String>,
+    /// Extra font directories, loaded along with any `--font-dir`
+    pub font_dirs: Vec<PathBuf>,
+    /// Megabytes an export may be expected to need before the dialog warns
+    pub export_memory_mb: u64,
+    /// Where the save dialog for an export starts
+    pub last_export_dir: Option<PathBuf>,
+    /// What the export dialog starts with
+    pub export: RememberedExport,
+    /// Most recently viewed first. Files left fitted to the window aren't listed.
+    zoom: Vec<RememberedZoom>,
+}
+
+/// The export dialog's choices as last left, for the next document. The size
+/// is kept as a multiple of the document's own, so it suits any document.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RememberedExport {
+    /// The format's extension
+    pub format: String,
+    /// Times its own size the last document was exported at
+    pub scale: f32,
+    pub include_alpha: bool,
+    pub background_color: [u8; 3],
+    /// Each format's own quality, so switching formats keeps the other's
+    pub jpeg_quality: u8,
+    pub avif_quality: u8,
+}
+
+impl Default for RememberedExport {
+    fn default() -> Self {
+        let settings = ExportSettings::default();
+        Self {
+            format: settings.format.extension().to_string(),
+            scale: 1.0,
+            include_alpha: settings.include_alpha,
+            background_color: settings.background_color,
+            jpeg_quality: settings.jpeg_quality,
+            avif_quality: settings.avif_quality,
+        }
+    }
+}
+
+impl RememberedExport {
+    /// The format to export to; one this version doesn't know is PNG.
+    pub fn format(&self) -> ExportFormat {
+        ExportFormat::from_extension(&self.format).unwrap_or(ExportFormat::Png)
+    }
+
+    /// The scale to size the next export by; an unusable one is 1×.
+    pub fn usable_scale(&self) -> f32 {
+        if self.scale.is_finite() && self.scale > 0.0 {
+            self.scale
+        } else {
+            1.0
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct RememberedZoom {
+    path: PathBuf,
+    zoom: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            last_file: None,
+            dark_mode: true,
+            show_checkerboard: true,
+            color_management: false,
+            display_profile: DisplayProfile::default(),
+            high_quality_downscale: false,
+            render_quality: RenderQuality::default(),
+            constrain_pan: true,
+            show_rulers: false,
+            show_thumbnails: false,
+            copy_white_background: false,
+            default_font_family: None,
+            font_dirs: Vec::new(),
+            export_memory_mb: DEFAULT_EXPORT_MEMORY_MB,
+            last_export_dir: None,
+            export: RememberedExport::default(),
+            zoom: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Parse settings from TOML. Keys that are missing take their defaults.
+    pub fn parse(text: &str) -> Result<Self> {
+        toml::from_str(text).map_err(|e| SvgError::Parse(format!("settings: {e}")))
+    }
+
+    /// Read settings from `path`, falling back to the defaults if it's missing
+    /// or can't be parsed.
+    pub fn load_from(path: &Path) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text).unwrap_or_else(|e| {
+                log::warn!("Ignoring {}: {e}", path.display());
+                Self::default()
+            }),
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Ignoring {}: {e}", path.display());
+                }
+                Self::default()
+            }
+        }
+    }
+
+    /// Write settings to `path`, creating its directory. The file is replaced
+    /// in one step, so a crash mid-write can't leave it half written.
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        let text = toml::to_string(self).map_err(|e| SvgError::Parse(format!("settings: {e}")))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let temp = path.with_extension("toml.tmp");
+        std::fs::write(&temp, text)?;
+        std::fs::rename(&temp, path)?;
+        Ok(())
+    }
+
+    /// The user's settings, or the defaults.
+    pub fn load() -> Self {
+        settings_path()
+            .map(|path| Self::load_from(&path))
+            .unwrap_or_default()
+    }
+
+    /// Save to the user's config directory, logging any failure.
+    pub fn save(&self) {
+        let Some(path) = settings_path() else {
+            return;
+        };
+        if let Err(e) = self.save_to(&path) {
+            log::warn!("Couldn't save settings to {}: {e}", path.display());
+        }
+    }
+
+    /// The last file, if it's still there to open. An archive entry counts
+    /// while its archive is there.
+    pub fn last_existing_file(&self) -> Option<&Path> {
+        self.last_file
+            .as_deref()
+            .filter(|path| path.is_file() || archive::split_path(path).is_some())
+    }
+
+    /// The zoom `path` was left at, unless it was fitted to the window.
+    pub fn remembered_zoom(&self, path: &Path) -> Option<f32> {
+        self.zoom
+            .iter()
+            .find(|entry| entry.path == path)
+            .map(|entry| entry.zoom)
+            .filter(|zoom| zoom.is_finite() && *zoom > 0.0)
+    }
+
+    /// Remember the zoom `path` is being left at; `None` when it's fitted.
+    pub fn remember_zoom(&mut self, path: &Path, zoom: Option<f32>) {
+        self.zoom.retain(|entry| entry.path != path);
+        if let Some(zoom) = zoom {
+            self.zoom.insert(
+                0,
+                RememberedZoom {
+                    path: path.to_path_buf(),
+                    zoom,
+                },
+            );
+            self.zoom.truncate(MAX_REMEMBERED_ZOOMS);
+        }
+    }
+}
+
+/// Where settings are kept: `svg-viewer/settings.toml` in the platform config
+/// directory.
+pub fn settings_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("svg-viewer").join("settings.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("svg_viewer_test_settings_{}", std::process::id()))
+            .join(name)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut settings = Settings {
+            last_file: Some(PathBuf::from("/art/logo.svg")),
+            dark_mode: false,
+            show_checkerboard: false,
+            color_management: true,
+            display_profile: DisplayProfile::DisplayP3,
+            high_quality_downscale: true,
+            render_quality: RenderQuality::Crisp,
+            constrain_pan: false,
+            show_rulers: true,
+            show_thumbnails: true,
+            copy_white_background: true,
+            default_font_family: Some("DejaVu Sans".into()),
+            font_dirs: vec![PathBuf::from("/art/fonts")],
+            export_memory_mb: 4096,
+            last_export_dir: Some(PathBuf::from("/art/exports")),
+            export: RememberedExport {
+                format: "jpg".into(),
+                scale: 2.5,
+                include_alpha: false,
+                background_color: [10, 20, 30],
+                jpeg_quality: 70,
+                avif_quality: 55,
+            },
+            ..Default::default()
+        };
+        settings.remember_zoom(Path::new("/art/logo.svg"), Some(2.5));
+
+        let path = temp_path("round_trip.toml");
+        settings.save_to(&path).unwrap();
+        assert_eq!(Settings::load_from(&path), settings);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_missing_and_corrupt_files_are_defaults() {
+        let path = temp_path("no_such_settings.toml");
+        assert_eq!(Settings::load_from(&path), Settings::default());
+
+        let path = temp_path("corrupt.toml");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "dark_mode = \"sometimes\"\n[[[").unwrap();
+        assert_eq!(Settings::load_from(&path), Settings::default());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_missing_keys_take_defaults() {
+        let settings = Settings::parse("dark_mode = false\nfuture_option = 3\n").unwrap();
+        assert!(!settings.dark_mode);
+        assert!(settings.show_checkerboard);
+        assert_eq!(settings.last_file, None);
+    }
+
+    #[test]
+    fn test_remember_zoom() {
+        let mut settings = Settings::default();
+        let (a, b) = (Path::new("a.svg"), Path::new("b.svg"));
+        settings.remember_zoom(a, Some(2.0));
+        settings.remember_zoom(b, Some(0.5));
+        settings.remember_zoom(a, Some(3.0));
+        assert_eq!(settings.remembered_zoom(a), Some(3.0));
+        assert_eq!(settings.remembered_zoom(b), Some(0.5));
+
+        // Leaving a file fitted forgets its zoom
+        settings.remember_zoom(a, None);
+        assert_eq!(settings.remembered_zoom(a), None);
+
+        for i in 0..MAX_REMEMBERED_ZOOMS {
+            settings.remember_zoom(Path::new(&format!("{i}.svg")), Some(1.5));
+        }
+        // The oldest entry fell off the end
+        assert_eq!(settings.remembered_zoom(b), None);
+        assert_eq!(settings.remembered_zoom(Path::new("0.svg")), Some(1.5));
+    }
+
+    #[test]
+    fn test_remembered_export() {
+        let settings = Settings::parse("[export]\nformat = \"webp\"\nscale = 0.0\n").unwrap();
+        assert_eq!(settings.export.format(), ExportFormat::WebP);
+        assert_eq!(settings.export.usable_scale(), 1.0);
+        assert_eq!(settings.export.jpeg_quality, 90);
+
+        let settings = Settings::parse("[export]\nformat = \"heic\"\n").unwrap();
+        assert_eq!(settings.export.format(), ExportFormat::Png);
+    }
+
+    #[test]
+    fn test_unusable_zoom_is_ignored() {
+        let settings = Settings::parse("[[zoom]]\npath = \"a.svg\"\nzoom = -1.0\n").unwrap();
+        assert_eq!(settings.remembered_zoom(Path::new("a.svg")), None);
+    }
+}
@@ -0,0 +1,308 @@
+//! The document's original XML structure, for naming the element under the
+//! pointer the way a stylesheet would.
+//!
+//! The render tree is flattened (groups merge, `<use>` expands, shapes become
+//! paths), so it can't say where an element sat in the source. [`StructureIndex`]
+//! records each source element's byte range and CSS selector, and [`Inspector`]
+//! re-parses the document with every element tagged by id so a hit in the render
+//! tree leads back to its source element.
+
+use std::ops::Range;
+
+use usvg::Tree;
+
+use crate::error::Result;
+use crate::fonts;
+use crate::svg_document::{self, SvgDocument};
+
+/// Prefix of the ids the inspection copy gives elements that have none.
+const TAG_PREFIX: &str = "svg-viewer-element-";
+
+/// One element of the source document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ElementEntry {
+    /// Local tag name, e.g. `path`.
+    pub tag: String,
+    pub id: Option<String>,
+    /// Byte range of the element in the (decompressed) source text.
+    pub range: Range<usize>,
+    /// Child-index path from the root, e.g. `svg > g:nth-child(2) > path:nth-child(7)`.
+    pub path: String,
+}
+
+impl ElementEntry {
+    /// `#id` when the element has an id, otherwise its child-index path.
+    pub fn css_selector(&self) -> String {
+        match &self.id {
+            Some(id) => format!("#{}", css_escape_ident(id)),
+            None => self.path.clone(),
+        }
+    }
+}
+
+/// Every element of a source document, in document order.
+#[derive(Clone, Debug, Default)]
+pub struct StructureIndex {
+    elements: Vec<ElementEntry>,
+}
+
+impl StructureIndex {
+    /// Index the elements of SVG source `text`.
+    pub fn build(text: &str) -> Result<Self> {
+        let xml = svg_document::parse_xml(text)?;
+        let mut elements = Vec::new();
+        let root = xml.root_element();
+        let root_path = root.tag_name().name().to_string();
+        index_element(root, root_path, &mut elements);
+        Ok(Self { elements })
+    }
+
+    pub fn elements(&self) -> &[ElementEntry] {
+        &self.elements
+    }
+
+    pub fn by_id(&self, id: &str) -> Option<&ElementEntry> {
+        self.elements.iter().find(|e| e.id.as_deref() == Some(id))
+    }
+
+    /// The innermost element whose source contains byte `offset`.
+    pub fn at_offset(&self, offset: usize) -> Option<&ElementEntry> {
+        self.elements
+            .iter()
+            .filter(|e| e.range.contains(&offset))
+            .min_by_key(|e| e.range.len())
+    }
+
+    /// `text` with `id="svg-viewer-element-N"` added to every element without an
+    /// id, N being its position in the index.
+    fn tagged_source(&self, text: &str) -> String {
+        let mut tagged = text.to_string();
+        // Back to front so earlier offsets stay valid
+        for (n, element) in self.elements.iter().enumerate().rev() {
+            if element.id.is_some() {
+                continue;
+            }
+            let start = element.range.start + 1;
+            let name_len = text[start..]
+                .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+                .unwrap_or(0);
+            tagged.insert_str(start + name_len, &format!(" id=\"{TAG_PREFIX}{n}\""));
+        }
+        tagged
+    }
+
+    /// The source element a render-tree node id refers to.
+    fn resolve(&self, id: &str) -> Option<&ElementEntry> {
+        match id.strip_prefix(TAG_PREFIX) {
+            Some(n) => n.parse::<usize>().ok().and_then(|n| self.elements.get(n)),
+            None => self.by_id(id),
+        }
+    }
+}
+
+fn index_element(node: usvg::roxmltree::Node, path: String, elements: &mut Vec<ElementEntry>) {
+    elements.push(ElementEntry {
+        tag: node.tag_name().name().to_string(),
+        id: node.attribute("id").map(str::to_string),
+        range: node.range(),
+        path: path.clone(),
+    });
+    for (i, child) in node.children().filter(|n| n.is_element()).enumerate() {
+        let child_path = format!("{path} > {}:nth-child({})", child.tag_name().name(), i + 1);
+        index_element(child, child_path, elements);
+    }
+}
+
+/// `ident` escaped for use after `#` in a CSS selector.
+pub fn css_escape_ident(ident: &str) -> String {
+    let mut escaped = String::new();
+    for (i, c) in ident.chars().enumerate() {
+        let leading_digit = i == 0 && c.is_ascii_digit();
+        if leading_digit {
+            escaped.push_str(&format!("\\{:x} ", c as u32));
+        } else if c.is_ascii_alphanumeric() || c == '-' || c == '_' || !c.is_ascii() {
+            escaped.push(c);
+        } else {
+            escaped.push('\\');
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+/// The element found under a point.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Inspected<'a> {
+    pub element: &'a ElementEntry,
+    /// Bounds including stroke (x, y, width, height), in SVG units.
+    pub bounds: [f32; 4],
+}
+
+impl Inspected<'_> {
+    pub fn bounds_json(&self) -> String {
+        let [x, y, width, height] = self.bounds;
+        serde_json::json!({ "x": x, "y": y, "width": width, "height": height }).to_string()
+    }
+}
+
+/// Finds the source element drawn at a point of a document.
+pub struct Inspector {
+    index: StructureIndex,
+    tagged: Tree,
+}
+
+impl Inspector {
+    pub fn new(doc: &SvgDocument) -> Result<Self> {
+        let text = svg_document::svg_text(doc.raw_data())?;
+        let index = StructureIndex::build(&text)?;
+        let tagged = index.tagged_source(&text);
+        let (tagged, _) =
+            svg_document::parse_with_size_fallback(tagged.as_bytes(), &fonts::usvg_options())?;
+        Ok(Self { index, tagged })
+    }
+
+    pub fn index(&self) -> &StructureIndex {
+        &self.index
+    }
+
+    /// The topmost element whose bounds contain (`x`, `y`), in SVG units. Bounds
+    /// rather than exact geometry are tested, so the corners of round shapes
+    /// count as hits.
+    pub fn element_at(&self, x: f32, y: f32) -> Option<Inspected<'_>> {
+        let mut hit = None;
+        self.hit_test(self.tagged.root(), None, x, y, &mut hit);
+        hit
+    }
+
+    fn hit_test<'a>(
+        &'a self,
+        group: &usvg::Group,
+        owner: Option<&'a ElementEntry>,
+        x: f32,
+        y: f32,
+        hit: &mut Option<Inspected<'a>>,
+    ) {
+        for node in group.children() {
+            // Nodes usvg made up (with no or generated ids) belong to the nearest
+            // source element above them
+            let element = self.index.resolve(node.id()).or(owner);
+            match node {
+                usvg::Node::Group(g) => self.hit_test(g, element, x, y, hit),
+                _ => {
+                    let bbox = node.abs_stroke_bounding_box();
+                    let inside = x >= bbox.left()
+                        && x <= bbox.right()
+                        && y >= bbox.top()
+                        && y <= bbox.bottom();
+                    if let (true, Some(element)) = (inside, element) {
+                        *hit = Some(Inspected {
+                            element,
+                            bounds: [bbox.x(), bbox.y(), bbox.width(), bbox.height()],
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const NESTED: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <defs><linearGradient id="g1"/></defs>
+  <g>
+    <rect x="0" y="0" width="10" height="10"/>
+    <g id="icons">
+      <circle cx="50" cy="50" r="5"/>
+      <path d="M80 80 L90 90" stroke="black"/>
+    </g>
+  </g>
+  <rect id="2nd.box" x="20" y="20" width="10" height="10"/>
+</svg>"#;
+
+    fn selectors(text: &str) -> Vec<String> {
+        let index = StructureIndex::build(text).unwrap();
+        index.elements().iter().map(|e| e.css_selector()).collect()
+    }
+
+    #[test]
+    fn test_selectors_follow_source_structure() {
+        assert_eq!(
+            selectors(NESTED),
+            [
+                "svg",
+                "svg > defs:nth-child(1)",
+                "#g1",
+                "svg > g:nth-child(2)",
+                "svg > g:nth-child(2) > rect:nth-child(1)",
+                "#icons",
+                "svg > g:nth-child(2) > g:nth-child(2) > circle:nth-child(1)",
+                "svg > g:nth-child(2) > g:nth-child(2) > path:nth-child(2)",
+                "#\\32 nd\\.box",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_at_offset_finds_innermost_element() {
+        let index = StructureIndex::build(NESTED).unwrap();
+        let offset = NESTED.find("<circle").unwrap() + 3;
+        assert_eq!(index.at_offset(offset).unwrap().tag, "circle");
+        let offset = NESTED.find("<g id").unwrap() + 1;
+        assert_eq!(
+            index.at_offset(offset).unwrap().id.as_deref(),
+            Some("icons")
+        );
+    }
+
+    #[test]
+    fn test_hit_maps_back_to_source_element() {
+        let doc = SvgDocument::from_data(Path::new("nested.svg"), NESTED.into()).unwrap();
+        let inspector = Inspector::new(&doc).unwrap();
+
+        let hit = inspector.element_at(50.0, 50.0).unwrap();
+        assert_eq!(
+            hit.element.css_selector(),
+            "svg > g:nth-child(2) > g:nth-child(2) > circle:nth-child(1)"
+        );
+        assert_eq!(hit.bounds, [45.0, 45.0, 10.0, 10.0]);
+        assert_eq!(
+            hit.bounds_json(),
+            r#"{"height":10.0,"width":10.0,"x":45.0,"y":45.0}"#
+        );
+
+        let hit = inspector.element_at(25.0, 25.0).unwrap();
+        assert_eq!(hit.element.id.as_deref(), Some("2nd.box"));
+        assert!(inspector.element_at(60.0, 10.0).is_none());
+    }
+
+    #[test]
+    fn test_topmost_element_wins() {
+        let text = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+  <rect width="10" height="10"/>
+  <rect id="top" x="2" y="2" width="4" height="4"/>
+</svg>"#;
+        let doc = SvgDocument::from_data(Path::new("stack.svg"), text.into()).unwrap();
+        let inspector = Inspector::new(&doc).unwrap();
+        assert_eq!(
+            inspector
+                .element_at(3.0, 3.0)
+                .unwrap()
+                .element
+                .id
+                .as_deref(),
+            Some("top")
+        );
+        assert_eq!(
+            inspector
+                .element_at(8.0, 8.0)
+                .unwrap()
+                .element
+                .css_selector(),
+            "svg > rect:nth-child(1)"
+        );
+    }
+}
@@ -1,38 +1,564 @@
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use usvg::{Options, Tree};
 
+use crate::archive::{self, ZipArchive};
+use crate::diagnostics;
 use crate::error::{Result, SvgError};
+use crate::fonts::{self, SubstitutedText};
+use crate::remote;
+use crate::renderer::RenderQuality;
 
-#[allow(dead_code)]
+/// Name shown for a document read from standard input.
+pub const STDIN_NAME: &str = "<stdin>";
+
+/// Name shown for a document pasted from the clipboard.
+pub const CLIPBOARD_NAME: &str = "<clipboard>";
+
+/// Minimum fraction of the canvas a fill must cover to count as the background.
+const BACKGROUND_COVERAGE: f32 = 0.99;
+
+/// A solid color the document paints behind all of its content.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocumentBackground {
+    pub color: [u8; 3],
+    /// Human-readable name of the element the color was taken from.
+    pub source: String,
+}
+
+/// Shapes the source draws that never reach the render tree, when outside these.
+const DEFINITION_CONTAINERS: &[&str] = &["defs", "symbol", "clipPath", "mask", "pattern", "marker"];
+const SOURCE_SHAPES: &[&str] = &[
+    "path", "rect", "circle", "ellipse", "line", "polyline", "polygon", "text", "image",
+];
+
+/// Where the document's drawable elements ended up, for explaining a blank render.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ContentStats {
+    /// Paths, images and text in the render tree.
+    pub elements: usize,
+    /// Rendered elements entirely outside the canvas.
+    pub outside_canvas: usize,
+    /// Elements that are invisible or were dropped while parsing (`display:none`).
+    pub hidden: usize,
+    /// Source shapes that only appear inside `<defs>`, `<symbol>` and the like.
+    pub defined_only: usize,
+    /// Bounds (x, y, width, height) of everything rendered, when not empty.
+    pub content_bounds: Option<[f32; 4]>,
+}
+
+/// What to tell the user about a render with no visible pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EmptyNotice {
+    /// Nothing in the document draws anything; a blank render is expected.
+    Spacer,
+    Empty {
+        message: String,
+        /// Content bounds to show instead of the canvas, when the content is
+        /// outside it.
+        fit_bounds: Option<[f32; 4]>,
+    },
+}
+
+impl ContentStats {
+    pub fn empty_notice(&self) -> EmptyNotice {
+        if self.elements == 0 && self.hidden == 0 && self.defined_only == 0 {
+            return EmptyNotice::Spacer;
+        }
+        let plural = |n: usize| if n == 1 { "element" } else { "elements" };
+        let mut reasons = Vec::new();
+        if self.outside_canvas > 0 {
+            reasons.push(format!(
+                "{} {} outside the canvas",
+                self.outside_canvas,
+                plural(self.outside_canvas)
+            ));
+        }
+        if self.hidden > 0 {
+            reasons.push(format!("{} {} hidden", self.hidden, plural(self.hidden)));
+        }
+        if self.elements == 0 && self.defined_only > 0 {
+            reasons.push(format!(
+                "{} {} only defined, never used",
+                self.defined_only,
+                plural(self.defined_only)
+            ));
+        }
+        if reasons.is_empty() {
+            reasons.push("all content is transparent".into());
+        }
+        EmptyNotice::Empty {
+            message: format!("Document rendered empty \u{2014} {}", reasons.join(", ")),
+            fit_bounds: self.content_bounds.filter(|_| self.outside_canvas > 0),
+        }
+    }
+}
+
+/// How much is in a document's render tree, as a measure of its complexity.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DocumentStats {
+    pub paths: usize,
+    pub groups: usize,
+    pub texts: usize,
+    /// Embedded or linked PNG, JPEG, GIF and WebP images
+    pub raster_images: usize,
+    /// Nested SVG images
+    pub svg_images: usize,
+    /// Linear and radial gradients, each counted once however often it's used
+    pub gradients: usize,
+    pub patterns: usize,
+    pub filters: usize,
+}
+
+impl DocumentStats {
+    fn of(tree: &Tree) -> Self {
+        let mut stats = Self {
+            gradients: tree.linear_gradients().len() + tree.radial_gradients().len(),
+            patterns: tree.patterns().len(),
+            filters: tree.filters().len(),
+            ..Self::default()
+        };
+        stats.count(tree.root());
+        stats
+    }
+
+    fn count(&mut self, group: &usvg::Group) {
+        for node in group.children() {
+            match node {
+                usvg::Node::Group(g) => {
+                    self.groups += 1;
+                    self.count(g);
+                }
+                usvg::Node::Path(_) => self.paths += 1,
+                usvg::Node::Text(_) => self.texts += 1,
+                usvg::Node::Image(image) => match image.kind() {
+                    usvg::ImageKind::SVG(_) => self.svg_images += 1,
+                    _ => self.raster_images += 1,
+                },
+            }
+        }
+    }
+
+    /// Each count with its name, in display order.
+    pub fn rows(&self) -> [(&'static str, usize); 8] {
+        [
+            ("Paths", self.paths),
+            ("Groups", self.groups),
+            ("Text", self.texts),
+            ("Images", self.raster_images),
+            ("SVG images", self.svg_images),
+            ("Gradients", self.gradients),
+            ("Patterns", self.patterns),
+            ("Filters", self.filters),
+        ]
+    }
+
+    /// The stats as aligned lines, as printed by `--info`.
+    pub fn table(&self) -> String {
+        self.rows()
+            .iter()
+            .map(|(name, count)| format!("{:<14}{count}\n", format!("{name}:")))
+            .collect()
+    }
+}
+
+/// A raster image in the render tree, with its data as encoded: a base64
+/// PNG in a data URI comes out as the PNG file it was.
+#[derive(Clone, Debug)]
+pub struct EmbeddedImage {
+    /// The `<image>` element's id, empty when it has none
+    pub id: String,
+    /// `png`, `jpg`, `gif` or `webp`, as a file extension
+    pub format: &'static str,
+    pub data: std::sync::Arc<Vec<u8>>,
+    /// Width and height in pixels, when the data's header can be read
+    pub dimensions: Option<(u32, u32)>,
+}
+
+impl EmbeddedImage {
+    /// `id` is the element's: usvg moves it to a group around the image.
+    fn of(image: &usvg::Image, id: &str) -> Option<Self> {
+        let (format, data) = match image.kind() {
+            usvg::ImageKind::PNG(data) => ("png", data),
+            usvg::ImageKind::JPEG(data) => ("jpg", data),
+            usvg::ImageKind::GIF(data) => ("gif", data),
+            usvg::ImageKind::WEBP(data) => ("webp", data),
+            usvg::ImageKind::SVG(_) => return None,
+        };
+        let dimensions = image::ImageReader::new(std::io::Cursor::new(data.as_slice()))
+            .with_guessed_format()
+            .ok()
+            .and_then(|reader| reader.into_dimensions().ok());
+        Some(Self {
+            id: id.to_string(),
+            format,
+            data: data.clone(),
+            dimensions,
+        })
+    }
+
+    /// Size of the encoded data in bytes.
+    pub fn encoded_size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// A name to save the image as: its id, or `{stem}_image{n}` for the
+    /// `index`th image of a document named `stem`.
+    pub fn file_name(&self, stem: &str, index: usize) -> String {
+        if self.id.is_empty() {
+            format!("{stem}_image{}.{}", index + 1, self.format)
+        } else {
+            format!("{}.{}", self.id, self.format)
+        }
+    }
+}
+
+/// The raster images in `tree`, in document order.
+pub fn embedded_images(tree: &Tree) -> Vec<EmbeddedImage> {
+    fn collect(group: &usvg::Group, images: &mut Vec<EmbeddedImage>) {
+        for node in group.children() {
+            match node {
+                usvg::Node::Group(g) => collect(g, images),
+                usvg::Node::Image(image) => {
+                    let id = Some(image.id()).filter(|id| !id.is_empty());
+                    images.extend(EmbeddedImage::of(image, id.unwrap_or(group.id())));
+                }
+                _ => {}
+            }
+        }
+    }
+    let mut images = Vec::new();
+    collect(tree.root(), &mut images);
+    images
+}
+
+/// What the document says about itself: its `<title>` and `<desc>`, and the
+/// size its root `<svg>` declares, units and all.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DocumentMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// The `width` attribute as written, such as `210mm` or `100%`
+    pub width: Option<String>,
+    pub height: Option<String>,
+}
+
+impl DocumentMetadata {
+    /// Read from the root `<svg>` start tag in `head`, and from the root's own
+    /// `<title>` and `<desc>` in the full source `data`.
+    fn from_source(head: &str, data: &[u8]) -> Self {
+        let attributes = root_start_tag(head)
+            .and_then(|tag| tag.split_once(char::is_whitespace))
+            .map(|(_, rest)| start_tag_attributes(rest))
+            .unwrap_or_default();
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        };
+        let mut metadata = Self {
+            width: attribute("width"),
+            height: attribute("height"),
+            ..Self::default()
+        };
+        // Only worth parsing the source again when there's something to find
+        if !head.contains("<title") && !head.contains("<desc") {
+            return metadata;
+        }
+        let Ok(text) = svg_text(data) else {
+            return metadata;
+        };
+        let Ok(xml) = parse_xml(&text) else {
+            return metadata;
+        };
+        let child_text = |name: &str| {
+            xml.root_element()
+                .children()
+                .find(|node| node.has_tag_name((SVG_NAMESPACE, name)))
+                .map(|node| {
+                    let text: String = node
+                        .descendants()
+                        .filter(|n| n.is_text())
+                        .filter_map(|n| n.text())
+                        .collect();
+                    text.split_whitespace().collect::<Vec<_>>().join(" ")
+                })
+                .filter(|text| !text.is_empty())
+        };
+        metadata.title = child_text("title");
+        metadata.description = child_text("desc");
+        metadata
+    }
+}
+
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+
+/// How the root `<svg>` fits its viewBox to its canvas, as declared.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ViewBoxFit {
+    /// The `viewBox` attribute as written
+    pub view_box: Option<String>,
+    /// The `preserveAspectRatio` attribute as written
+    pub preserve_aspect_ratio: Option<String>,
+}
+
+impl ViewBoxFit {
+    fn from_source(text: &str) -> Self {
+        let Some(tag) = root_start_tag(text) else {
+            return Self::default();
+        };
+        let attributes = tag
+            .split_once(char::is_whitespace)
+            .map(|(_, rest)| start_tag_attributes(rest))
+            .unwrap_or_default();
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        };
+        Self {
+            view_box: attribute("viewBox"),
+            preserve_aspect_ratio: attribute("preserveAspectRatio"),
+        }
+    }
+
+    /// The declared `preserveAspectRatio`, or the default centered "meet"
+    /// when it's missing or invalid.
+    pub fn aspect_ratio(&self) -> svgtypes::AspectRatio {
+        self.preserve_aspect_ratio
+            .as_deref()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether `preserveAspectRatio` asks for anything but the default.
+    pub fn is_default(&self) -> bool {
+        self.aspect_ratio() == svgtypes::AspectRatio::default()
+    }
+
+    /// What the declared behavior does to a `width`×`height` canvas, when it
+    /// isn't the default and the viewBox doesn't have the canvas's aspect.
+    pub fn notice(&self, width: f32, height: f32) -> Option<String> {
+        let view_box: svgtypes::ViewBox = self.view_box.as_deref()?.parse().ok()?;
+        let (w, h) = (view_box.w as f32, view_box.h as f32);
+        if self.is_default() || width <= 0.0 || height <= 0.0 {
+            return None;
+        }
+        // Equal aspects fit exactly whatever the alignment
+        if ((w / h) / (width / height) - 1.0).abs() < ASPECT_TOLERANCE {
+            return None;
+        }
+        let declared = self.preserve_aspect_ratio.as_deref().unwrap_or_default();
+        let aspect = self.aspect_ratio();
+        let effect = if aspect.align == svgtypes::Align::None {
+            "is stretched to"
+        } else if aspect.slice {
+            "is cropped to fill"
+        } else {
+            "sits off-center in"
+        };
+        Some(format!(
+            "preserveAspectRatio=\"{declared}\": the {w}×{h} viewBox {effect} the {width}×{height} canvas"
+        ))
+    }
+}
+
+/// Relative difference below which a viewBox counts as having the canvas's aspect.
+const ASPECT_TOLERANCE: f32 = 1e-3;
+
+/// A parsed SVG file and what the viewer knows about it.
 pub struct SvgDocument {
-    pub tree: Tree,
-    pub path: PathBuf,
-    pub raw_data: Vec<u8>,
-    pub width: f32,
-    pub height: f32,
-    pub file_size: u64,
+    tree: Tree,
+    path: PathBuf,
+    raw_data: Vec<u8>,
+    width: f32,
+    height: f32,
+    file_size: u64,
+    /// Size of the SVG source once decompressed, when the file is gzipped
+    decompressed_size: Option<u64>,
+    background: Option<DocumentBackground>,
+    declared_background: Option<[u8; 3]>,
+    size_warning: Option<String>,
+    substituted_text: Vec<SubstitutedText>,
+    view_box_fit: ViewBoxFit,
+    metadata: DocumentMetadata,
+    /// Counted on first use
+    stats: OnceLock<DocumentStats>,
+    /// What parsed but couldn't be drawn: missing images, dangling references
+    /// and unsupported features
+    warnings: Vec<String>,
+    /// Parsed with `preserveAspectRatio` replaced by the default
+    meet_forced: bool,
+    /// The rendering hints it was parsed with
+    quality: RenderQuality,
 }
 
 impl SvgDocument {
+    /// Read and parse the SVG or SVGZ file at `path`, which may be an entry
+    /// inside a zip archive (see [`archive`]) or an http(s) URL to download
+    /// (see [`remote`]).
     pub fn load(path: &Path) -> Result<Self> {
-        let raw_data = std::fs::read(path)?;
+        Self::load_with(path, &options_for(path))
+    }
+
+    /// [`load`](Self::load), parsing with `options` rather than the shared
+    /// ones from [`fonts::usvg_options`].
+    pub fn load_with(path: &Path, options: &Options) -> Result<Self> {
+        let raw_data = if let Some((zip, name)) = archive::split_path(path) {
+            ZipArchive::open(zip)?.read(&name)?
+        } else if let Some(url) = path.to_str().filter(|_| remote::is_url(path)) {
+            remote::fetch(url)?
+        } else {
+            std::fs::read(path)?
+        };
+        Self::from_data_with(path, raw_data, options)
+    }
+
+    /// [`load`](Self::load), parsed with `quality`'s rendering hints.
+    pub fn load_with_quality(path: &Path, quality: RenderQuality) -> Result<Self> {
+        let mut options = options_for(path);
+        quality.apply(&mut options);
+        let mut doc = Self::load_with(path, &options)?;
+        doc.quality = quality;
+        Ok(doc)
+    }
+
+    /// Read and parse the entry `name` of the zip archive at `zip`. The
+    /// document's path is the entry's virtual path.
+    pub fn load_from_archive(zip: &Path, name: &str) -> Result<Self> {
+        let raw_data = ZipArchive::open(zip)?.read(name)?;
+        Self::from_data(&archive::entry_path(zip, name), raw_data)
+    }
+
+    /// Download and parse the document at `url`. The document's path is the URL.
+    pub fn from_url(url: &str) -> Result<Self> {
+        let raw_data = remote::fetch(url)?;
+        Self::from_data(Path::new(url), raw_data)
+    }
+
+    /// Read and parse a document from standard input, named [`STDIN_NAME`].
+    pub fn from_stdin() -> Result<Self> {
+        let mut raw_data = Vec::new();
+        std::io::stdin().lock().read_to_end(&mut raw_data)?;
+        Self::from_data(Path::new(STDIN_NAME), raw_data)
+    }
+
+    /// Parse SVG markup pasted from the clipboard, named [`CLIPBOARD_NAME`].
+    pub fn from_clipboard_text(text: String) -> Result<Self> {
+        Self::from_data(Path::new(CLIPBOARD_NAME), text.into_bytes())
+    }
+
+    /// Parse `raw_data` as the contents of `path` without reading the file,
+    /// though images it links to are read from beside it.
+    pub fn from_data(path: &Path, raw_data: Vec<u8>) -> Result<Self> {
+        Self::from_data_with(path, raw_data, &options_for(path))
+    }
+
+    /// [`from_data`](Self::from_data), parsing with `options`.
+    pub fn from_data_with(path: &Path, raw_data: Vec<u8>, options: &Options) -> Result<Self> {
         let file_size = raw_data.len() as u64;
+        // Gzip is recognized by its magic bytes, whatever the extension
+        let source = svg_bytes(&raw_data)?;
+        let decompressed_size = is_gzip(&raw_data).then_some(source.len() as u64);
 
-        let tree = Tree::from_data(&raw_data, &Options::default())
-            .map_err(|e| SvgError::Parse(e.to_string()))?;
+        let (tree, size_warning) = parse_with_size_fallback(&source, options)?;
+        if let Some(ref warning) = size_warning {
+            log::warn!("{}: {}", path.display(), warning);
+        }
 
         let size = tree.size();
         let width = size.width();
         let height = size.height();
 
-        Ok(SvgDocument {
+        let head = source_head(&source);
+        let declared_background = declared_background(&head);
+        let view_box_fit = ViewBoxFit::from_source(&head);
+        let metadata = DocumentMetadata::from_source(&head, &source);
+        let warnings = resource_warnings(&source, options);
+        drop(source);
+        for warning in &warnings {
+            log::warn!("{}: {warning}", path.display());
+        }
+        let substituted_text = fonts::substituted_text(&tree, &options.fontdb);
+        let mut doc = SvgDocument {
             tree,
             path: path.to_path_buf(),
             raw_data,
             width,
             height,
             file_size,
-        })
+            decompressed_size,
+            background: None,
+            declared_background,
+            size_warning,
+            substituted_text,
+            view_box_fit,
+            metadata,
+            stats: OnceLock::new(),
+            warnings,
+            meet_forced: false,
+            quality: RenderQuality::default(),
+        };
+        doc.background = doc.detect_background(false);
+        Ok(doc)
+    }
+
+    pub fn tree(&self) -> &Tree {
+        &self.tree
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The file's bytes as read, possibly gzip-compressed.
+    pub fn raw_data(&self) -> &[u8] {
+        &self.raw_data
+    }
+
+    /// Document width in SVG units.
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// Document height in SVG units.
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    /// File size in bytes.
+    pub fn file_size(&self) -> u64 {
+        self.file_size
+    }
+
+    /// Size of the SVG source in bytes after decompressing, for gzipped (SVGZ)
+    /// files; `None` for plain ones.
+    pub fn decompressed_size(&self) -> Option<u64> {
+        self.decompressed_size
+    }
+
+    /// Text drawn in a substitute font because the one it asks for isn't installed.
+    pub fn substituted_text(&self) -> &[SubstitutedText] {
+        &self.substituted_text
+    }
+
+    /// The full-canvas solid background detected at load time, if any.
+    pub fn background(&self) -> Option<&DocumentBackground> {
+        self.background.as_ref()
+    }
+
+    /// Set when the declared width/height were unusable and a fallback size was chosen.
+    /// The background color the root `<svg>` asks viewers to show, which usvg
+    /// doesn't render. See [`declared_background`].
+    pub fn declared_background(&self) -> Option<[u8; 3]> {
+        self.declared_background
+    }
+
+    pub fn size_warning(&self) -> Option<&str> {
+        self.size_warning.as_deref()
     }
 
     pub fn filename(&self) -> &str {
@@ -42,17 +568,848 @@ impl SvgDocument {
             .unwrap_or("unknown")
     }
 
+    /// The file name without its extension, for naming exports. Documents read from
+    /// standard input are called "stdin", and pasted ones "clipboard".
+    pub fn file_stem(&self) -> &str {
+        if self.is_stdin() {
+            return "stdin";
+        }
+        if self.is_pasted() {
+            return "clipboard";
+        }
+        self.path
+            .file_stem()
+            .and_then(|n| n.to_str())
+            .filter(|n| !n.is_empty())
+            .unwrap_or("untitled")
+    }
+
+    /// Read from standard input rather than a file.
+    pub fn is_stdin(&self) -> bool {
+        self.path == Path::new(STDIN_NAME)
+    }
+
+    /// Pasted from the clipboard rather than read from a file.
+    pub fn is_pasted(&self) -> bool {
+        self.path == Path::new(CLIPBOARD_NAME)
+    }
+
+    /// Read from standard input or pasted, so there's no file to reload,
+    /// watch or remember.
+    pub fn is_in_memory(&self) -> bool {
+        self.is_stdin() || self.is_pasted()
+    }
+
+    /// Downloaded from a URL rather than read from a file.
+    pub fn is_remote(&self) -> bool {
+        remote::is_url(&self.path)
+    }
+
+    /// The URL the document was downloaded from.
+    pub fn url(&self) -> Option<&str> {
+        self.path.to_str().filter(|_| self.is_remote())
+    }
+
+    /// Look up a renderable element by its `id` attribute.
+    pub fn find_by_id(&self, id: &str) -> Option<&usvg::Node> {
+        self.tree.node_by_id(id)
+    }
+
+    /// If the first shape painted is a rectangle covering (nearly) the whole
+    /// canvas, return its fill color. Gradient fills are rejected unless
+    /// `average_gradients` is set, in which case the stop colors are averaged
+    /// over the gradient's length.
+    pub fn detect_background(&self, average_gradients: bool) -> Option<DocumentBackground> {
+        let canvas = usvg::Rect::from_xywh(0.0, 0.0, self.width, self.height)?;
+        let (usvg::Node::Path(path), plain) = first_painted(self.tree.root(), true)? else {
+            return None;
+        };
+        let fill = path.fill()?;
+        if !plain || fill.opacity().get() < 1.0 || !covers_canvas(path, &canvas) {
+            return None;
+        }
+
+        let color = match fill.paint() {
+            usvg::Paint::Color(c) => [c.red, c.green, c.blue],
+            usvg::Paint::LinearGradient(g) if average_gradients => average_stops(g.stops())?,
+            usvg::Paint::RadialGradient(g) if average_gradients => average_stops(g.stops())?,
+            _ => return None,
+        };
+
+        let source = if path.id().is_empty() {
+            "unnamed shape".to_string()
+        } else {
+            format!("#{}", path.id())
+        };
+        Some(DocumentBackground { color, source })
+    }
+
+    /// Walk the render tree (and the source, for dropped elements) to see where
+    /// the drawable content is.
+    pub fn content_stats(&self) -> ContentStats {
+        let mut stats = ContentStats::default();
+        let canvas = usvg::Rect::from_xywh(0.0, 0.0, self.width, self.height);
+        count_elements(self.tree.root(), canvas.as_ref(), true, &mut stats);
+
+        if let Ok(text) = svg_text(&self.raw_data) {
+            if let Ok(xml) = parse_xml(&text) {
+                let (drawn, defined) = count_source_shapes(xml.root_element(), false);
+                stats.hidden += drawn.saturating_sub(stats.elements);
+                stats.defined_only = defined;
+            }
+        }
+
+        let bounds = self.tree.root().abs_stroke_bounding_box();
+        if bounds.width() > 0.0 && bounds.height() > 0.0 && stats.elements > stats.hidden {
+            stats.content_bounds = Some([bounds.x(), bounds.y(), bounds.width(), bounds.height()]);
+        }
+        stats
+    }
+
+    /// The tight bounds (x, y, width, height) of what's drawn on the canvas,
+    /// strokes included, or the whole canvas when nothing with an area is.
+    pub fn content_bbox(&self) -> [f32; 4] {
+        let whole = [0.0, 0.0, self.width, self.height];
+        let Some(canvas) = usvg::Rect::from_xywh(0.0, 0.0, self.width, self.height) else {
+            return whole;
+        };
+        match self
+            .tree
+            .root()
+            .abs_stroke_bounding_box()
+            .intersect(&canvas)
+        {
+            Some(bounds) if bounds.width() > 0.0 && bounds.height() > 0.0 => {
+                [bounds.x(), bounds.y(), bounds.width(), bounds.height()]
+            }
+            _ => whole,
+        }
+    }
+
+    /// The same document with its canvas replaced by `bounds` (x, y, width, height),
+    /// so content outside the declared canvas becomes visible.
+    pub fn with_canvas(&self, bounds: [f32; 4]) -> Result<SvgDocument> {
+        let [x, y, w, h] = bounds;
+        let text = svg_text(&self.raw_data)?;
+        let view_box = format!("{x} {y} {w} {h}");
+        let rewritten = rewrite_root_size(&text, Some(&view_box), Some((w, h)))?;
+        let mut doc = self.reparsed(rewritten.into_bytes(), self.quality)?;
+        doc.file_size = self.file_size;
+        doc.decompressed_size = self.decompressed_size;
+        doc.metadata = self.metadata.clone();
+        doc.size_warning = Some(format!(
+            "Showing the content bounds {w}×{h} instead of the declared canvas"
+        ));
+        Ok(doc)
+    }
+
+    /// The root's viewBox and preserveAspectRatio as declared, even when
+    /// [`with_meet`](Self::with_meet) is overriding them.
+    pub fn view_box_fit(&self) -> &ViewBoxFit {
+        &self.view_box_fit
+    }
+
+    /// The document's title, description and declared size.
+    pub fn metadata(&self) -> &DocumentMetadata {
+        &self.metadata
+    }
+
+    /// Parts of the document that loaded without them, such as images that
+    /// weren't found. Empty when everything resolved.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// How many paths, groups, images and so on the render tree has.
+    pub fn stats(&self) -> &DocumentStats {
+        self.stats.get_or_init(|| DocumentStats::of(&self.tree))
+    }
+
+    /// The document's title, or else its file name (or URL).
+    pub fn display_name(&self) -> &str {
+        match (&self.metadata.title, self.url()) {
+            (Some(title), _) => title,
+            (None, Some(url)) => url,
+            (None, None) => self.filename(),
+        }
+    }
+
+    /// What the declared preserveAspectRatio does to the canvas, when it's
+    /// something other than centering the whole viewBox.
+    pub fn aspect_notice(&self) -> Option<String> {
+        self.view_box_fit.notice(self.width, self.height)
+    }
+
+    /// Whether [`with_meet`](Self::with_meet) made this document.
+    pub fn is_meet_forced(&self) -> bool {
+        self.meet_forced
+    }
+
+    /// The same document parsed as if its preserveAspectRatio were the
+    /// default, so the whole viewBox shows centered and undistorted. The raw
+    /// data stays the file's own.
+    pub fn with_meet(&self) -> Result<SvgDocument> {
+        let text = svg_text(&self.raw_data)?;
+        let rewritten = rewrite_root_attribute(&text, "preserveAspectRatio", "xMidYMid meet")?;
+        let mut doc = self.reparsed(rewritten.into_bytes(), self.quality)?;
+        doc.raw_data = self.raw_data.clone();
+        doc.file_size = self.file_size;
+        doc.decompressed_size = self.decompressed_size;
+        doc.view_box_fit = self.view_box_fit.clone();
+        doc.metadata = self.metadata.clone();
+        doc.meet_forced = true;
+        Ok(doc)
+    }
+
+    /// The document as declared, undoing [`with_meet`](Self::with_meet).
+    pub fn as_declared(&self) -> Result<SvgDocument> {
+        self.reparsed(self.raw_data.clone(), self.quality)
+    }
+
+    /// The rendering hints the document was parsed with.
+    pub fn quality(&self) -> RenderQuality {
+        self.quality
+    }
+
+    /// The same document parsed with `quality`'s rendering hints, keeping
+    /// any canvas or aspect it's being shown with.
+    pub fn with_quality(&self, quality: RenderQuality) -> Result<SvgDocument> {
+        let mut doc = self.reparsed(self.raw_data.clone(), quality)?;
+        doc.file_size = self.file_size;
+        doc.decompressed_size = self.decompressed_size;
+        doc.metadata = self.metadata.clone();
+        doc.size_warning = self.size_warning.clone();
+        if self.meet_forced {
+            doc.with_meet()
+        } else {
+            Ok(doc)
+        }
+    }
+
+    /// `raw_data` parsed in this document's place, with `quality`'s hints.
+    fn reparsed(&self, raw_data: Vec<u8>, quality: RenderQuality) -> Result<SvgDocument> {
+        let mut options = options_for(&self.path);
+        quality.apply(&mut options);
+        let mut doc = SvgDocument::from_data_with(&self.path, raw_data, &options)?;
+        doc.quality = quality;
+        Ok(doc)
+    }
+
+    /// The file size, with the decompressed size for gzipped files:
+    /// "12.3 KB (48.1 KB uncompressed)".
     pub fn file_size_display(&self) -> String {
-        if self.file_size < 1024 {
-            format!("{} B", self.file_size)
-        } else if self.file_size < 1024 * 1024 {
-            format!("{:.1} KB", self.file_size as f64 / 1024.0)
+        match self.decompressed_size {
+            Some(size) => format!(
+                "{} ({} uncompressed)",
+                format_size(self.file_size),
+                format_size(size)
+            ),
+            None => format_size(self.file_size),
+        }
+    }
+}
+
+/// `bytes` as B, KB, MB or GB for display.
+pub fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else if bytes < 1024 * 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{:.1} GB", bytes as f64 / (1024.0 * 1024.0 * 1024.0))
+    }
+}
+
+/// Parse `data`, falling back to a usable canvas when the root `<svg>` declares a
+/// zero or invalid size: first the viewBox, then the content bounds, then 1×1.
+/// Returns the tree plus a warning describing the fallback, if one was needed.
+pub(crate) fn parse_with_size_fallback(
+    data: &[u8],
+    opt: &Options,
+) -> Result<(Tree, Option<String>)> {
+    match Tree::from_data(data, opt) {
+        Ok(tree) => return Ok((tree, None)),
+        Err(usvg::Error::InvalidSize) => {}
+        Err(e) => return Err(parse_error(data, e)),
+    }
+
+    let text = svg_text(data)?;
+    let (declared, has_view_box) = root_size_attributes(&text)?;
+    let parse = |svg: &str| Tree::from_str(svg, opt).map_err(|e| SvgError::Parse(e.to_string()));
+
+    // Without width/height, usvg sizes the canvas from the viewBox (or 100×100)
+    let view_box_sized = parse(&rewrite_root_size(&text, None, None)?)?;
+    if has_view_box {
+        let size = view_box_sized.size();
+        let warning = format!(
+            "Invalid document size ({declared}); using the viewBox size {}×{}",
+            size.width(),
+            size.height()
+        );
+        return Ok((view_box_sized, Some(warning)));
+    }
+
+    let bbox = view_box_sized.root().abs_stroke_bounding_box();
+    if bbox.width() > 0.0 && bbox.height() > 0.0 {
+        let view_box = format!(
+            "{} {} {} {}",
+            bbox.x(),
+            bbox.y(),
+            bbox.width(),
+            bbox.height()
+        );
+        let size = (bbox.width(), bbox.height());
+        let tree = parse(&rewrite_root_size(&text, Some(&view_box), Some(size))?)?;
+        let warning = format!(
+            "Invalid document size ({declared}); using the content bounds {}×{}",
+            size.0, size.1
+        );
+        return Ok((tree, Some(warning)));
+    }
+
+    let tree = parse(&rewrite_root_size(&text, None, Some((1.0, 1.0)))?)?;
+    let warning = format!("Invalid document size ({declared}) and no content; using a 1×1 canvas");
+    Ok((tree, Some(warning)))
+}
+
+/// The shared parsing options, with relative image links resolving against
+/// the folder of a file on disk.
+fn options_for(path: &Path) -> Options<'static> {
+    let mut options = fonts::usvg_options();
+    let on_disk = !remote::is_url(path) && archive::split_path(path).is_none();
+    options.resources_dir = path
+        .parent()
+        .filter(|_| on_disk && path != Path::new(STDIN_NAME) && path != Path::new(CLIPBOARD_NAME))
+        .map(Path::to_path_buf);
+    options
+}
+
+/// At most this many resource warnings are kept per document.
+pub const MAX_WARNINGS: usize = 20;
+
+/// Elements usvg parses past without drawing.
+const UNSUPPORTED_ELEMENTS: &[&str] = &[
+    "foreignObject",
+    "script",
+    "animate",
+    "animateMotion",
+    "animateTransform",
+    "set",
+];
+
+/// What won't show in the document's render though it parsed: `<image>`
+/// links that don't resolve to a file, `<use>` references to ids that aren't
+/// there, external style sheets and elements that are never drawn.
+fn resource_warnings(data: &[u8], options: &Options) -> Vec<String> {
+    let Ok(text) = svg_text(data) else {
+        return Vec::new();
+    };
+    // Most documents have none of these; don't parse them again
+    let markers = ["<image", "<use", "stylesheet", "@import"];
+    let may_warn = markers.iter().any(|m| text.contains(m))
+        || UNSUPPORTED_ELEMENTS
+            .iter()
+            .any(|e| text.contains(&format!("<{e}")));
+    if !may_warn {
+        return Vec::new();
+    }
+    let Ok(xml) = parse_xml(&text) else {
+        return Vec::new();
+    };
+
+    let mut warnings = Vec::new();
+    let mut add = |warning: String| {
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    };
+    const XLINK: &str = "http://www.w3.org/1999/xlink";
+    for node in xml.descendants() {
+        if node.is_pi() {
+            if let Some(pi) = node.pi().filter(|pi| pi.target == "xml-stylesheet") {
+                // Pseudo-attributes: `href="theme.css" type="text/css"`
+                let value = pi.value.unwrap_or_default();
+                let href = value
+                    .split_once("href=")
+                    .and_then(|(_, rest)| rest.get(1..)?.split(['"', '\'']).next())
+                    .unwrap_or(value);
+                add(format!("External style sheet not loaded: {href}"));
+            }
+            continue;
+        }
+        if !node.is_element() {
+            continue;
+        }
+        let name = node.tag_name().name();
+        let href = node
+            .attribute("href")
+            .or_else(|| node.attribute((XLINK, "href")))
+            .map(str::trim);
+        match (name, href) {
+            ("image", Some(href)) if !href.starts_with("data:") => {
+                if remote::is_url(Path::new(href)) {
+                    add(format!("Remote image not loaded: {href}"));
+                } else {
+                    let file = href.strip_prefix("file://").unwrap_or(href);
+                    if !options.get_abs_path(Path::new(file)).is_file() {
+                        add(format!("Image not found: {href}"));
+                    }
+                }
+            }
+            ("use", Some(href)) => match href.split_once('#') {
+                Some(("", id)) => {
+                    if !xml.descendants().any(|n| n.attribute("id") == Some(id)) {
+                        add(format!("<use> refers to missing element #{id}"));
+                    }
+                }
+                _ => add(format!("<use> of another file not supported: {href}")),
+            },
+            ("style", _) => {
+                let imports = node.text().unwrap_or_default().matches("@import").count();
+                if imports > 0 {
+                    add("Style sheet @import not loaded".into());
+                }
+            }
+            ("link", _) if node.attribute("rel") == Some("stylesheet") => {
+                add(format!(
+                    "External style sheet not loaded: {}",
+                    href.unwrap_or_default()
+                ));
+            }
+            _ if UNSUPPORTED_ELEMENTS.contains(&name) => {
+                add(format!("<{name}> isn't rendered"));
+            }
+            _ => {}
+        }
+    }
+    warnings.truncate(MAX_WARNINGS);
+    warnings
+}
+
+/// A parse failure, located in the source when it isn't well-formed XML.
+fn parse_error(data: &[u8], error: usvg::Error) -> SvgError {
+    let Ok(source) = svg_bytes(data) else {
+        return SvgError::Parse(error.to_string());
+    };
+    let diagnostic = match (&error, std::str::from_utf8(&source)) {
+        (usvg::Error::ParsingFailed(xml), Ok(text)) => diagnostics::diagnose(text, xml),
+        (usvg::Error::NotAnUtf8Str, Err(utf8)) => diagnostics::diagnose_utf8(&source, &utf8),
+        _ => return SvgError::Parse(error.to_string()),
+    };
+    SvgError::Malformed(Box::new(diagnostic))
+}
+
+/// Whether `data` starts with the gzip magic bytes, as SVGZ does.
+pub fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&[0x1f, 0x8b])
+}
+
+/// The SVG source bytes, decompressing SVGZ.
+fn svg_bytes(data: &[u8]) -> Result<Vec<u8>> {
+    if is_gzip(data) {
+        usvg::decompress_svgz(data).map_err(|_| {
+            SvgError::Parse(
+                "the file is gzip-compressed but its data is incomplete or corrupt".into(),
+            )
+        })
+    } else {
+        Ok(data.to_vec())
+    }
+}
+
+/// The SVG source, decompressing SVGZ.
+pub(crate) fn svg_text(data: &[u8]) -> Result<String> {
+    String::from_utf8(svg_bytes(data)?).map_err(|e| SvgError::Parse(e.to_string()))
+}
+
+fn count_elements(
+    group: &usvg::Group,
+    canvas: Option<&usvg::Rect>,
+    visible: bool,
+    stats: &mut ContentStats,
+) {
+    let visible = visible && group.opacity().get() > 0.0;
+    for node in group.children() {
+        let shown = match node {
+            usvg::Node::Group(g) => {
+                count_elements(g, canvas, visible, stats);
+                continue;
+            }
+            usvg::Node::Path(p) => visible && p.is_visible(),
+            usvg::Node::Image(i) => visible && i.is_visible(),
+            usvg::Node::Text(_) => visible,
+        };
+        stats.elements += 1;
+        if !shown {
+            stats.hidden += 1;
+        } else if let Some(canvas) = canvas {
+            if node.abs_stroke_bounding_box().intersect(canvas).is_none() {
+                stats.outside_canvas += 1;
+            }
+        }
+    }
+}
+
+/// Source shapes outside and inside definition containers.
+fn count_source_shapes(node: usvg::roxmltree::Node, in_definition: bool) -> (usize, usize) {
+    let mut counts = (0, 0);
+    for child in node.children().filter(|n| n.is_element()) {
+        let name = child.tag_name().name();
+        if SOURCE_SHAPES.contains(&name) {
+            if in_definition {
+                counts.1 += 1;
+            } else {
+                counts.0 += 1;
+            }
+        }
+        // Text content elements (tspan) aren't counted separately
+        if name != "text" {
+            let nested = in_definition || DEFINITION_CONTAINERS.contains(&name);
+            let (drawn, defined) = count_source_shapes(child, nested);
+            counts.0 += drawn;
+            counts.1 += defined;
+        }
+    }
+    counts
+}
+
+/// The start of the source, enough to find the root start tag in, decompressing SVGZ.
+fn source_head(data: &[u8]) -> String {
+    const HEAD_BYTES: usize = 64 * 1024;
+    let decompressed;
+    let data = if is_gzip(data) {
+        decompressed = usvg::decompress_svgz(data).unwrap_or_default();
+        &decompressed
+    } else {
+        data
+    };
+    String::from_utf8_lossy(&data[..data.len().min(HEAD_BYTES)]).into_owned()
+}
+
+/// The background color declared on the root `<svg>` in `text`: `background-color`
+/// or a color in `background` within its `style`, or else a `background`
+/// attribute. Hex, `rgb()` and named colors are understood. Alpha is dropped,
+/// except that a fully transparent color counts as no background.
+pub fn declared_background(text: &str) -> Option<[u8; 3]> {
+    let tag = root_start_tag(text)?;
+    let attributes = start_tag_attributes(tag.split_once(char::is_whitespace)?.1);
+    let attribute = |name: &str| {
+        attributes
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| *value)
+    };
+
+    // The last declaration wins, so search from the end
+    let from_style = attribute("style").and_then(|style| {
+        style
+            .rsplit(';')
+            .filter_map(|declaration| declaration.split_once(':'))
+            .filter(|(property, _)| {
+                let property = property.trim();
+                property.eq_ignore_ascii_case("background-color")
+                    || property.eq_ignore_ascii_case("background")
+            })
+            .filter_map(|(_, value)| {
+                let value = value.trim().trim_end_matches("!important").trim();
+                // The shorthand may mix the color with images and positions
+                parse_css_color(value).or_else(|| css_tokens(value).find_map(parse_css_color))
+            })
+            .next()
+    });
+    let color = from_style.or_else(|| attribute("background").and_then(parse_css_color))?;
+    let [r, g, b, a] = color;
+    (a > 0).then_some([r, g, b])
+}
+
+fn parse_css_color(text: &str) -> Option<[u8; 4]> {
+    let color: svgtypes::Color = text.trim().parse().ok()?;
+    Some([color.red, color.green, color.blue, color.alpha])
+}
+
+/// Whitespace-separated parts of a CSS value, keeping `rgb(1, 2, 3)` whole.
+fn css_tokens(value: &str) -> impl Iterator<Item = &str> {
+    let mut depth = 0usize;
+    value
+        .split(move |c: char| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            c.is_whitespace() && depth == 0
+        })
+        .filter(|token| !token.is_empty())
+}
+
+/// The contents of the first element start tag, between `<` and `>`, skipping
+/// the XML declaration, comments and a doctype.
+pub(crate) fn root_start_tag(text: &str) -> Option<&str> {
+    let mut rest = text;
+    loop {
+        rest = &rest[rest.find('<')?..];
+        if let Some(after) = rest.strip_prefix("<?") {
+            rest = &after[after.find("?>")? + 2..];
+        } else if let Some(after) = rest.strip_prefix("<!--") {
+            rest = &after[after.find("-->")? + 3..];
+        } else if rest.starts_with("<!") {
+            // A doctype's internal subset may contain '>'
+            let end = match (rest.find('['), rest.find('>')) {
+                (Some(open), Some(close)) if open < close => open + rest[open..].find("]>")? + 1,
+                (_, close) => close?,
+            };
+            rest = &rest[end + 1..];
         } else {
-            format!("{:.1} MB", self.file_size as f64 / (1024.0 * 1024.0))
+            let mut quote = None;
+            for (i, c) in rest.char_indices().skip(1) {
+                match (quote, c) {
+                    (None, '"' | '\'') => quote = Some(c),
+                    (Some(q), _) if q == c => quote = None,
+                    (None, '>') => return Some(&rest[1..i]),
+                    _ => {}
+                }
+            }
+            return None;
         }
     }
 }
 
+/// `name="value"` pairs from a start tag, after the element name.
+pub(crate) fn start_tag_attributes(mut rest: &str) -> Vec<(&str, &str)> {
+    let mut pairs = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        let Some(eq) = rest.find('=') else {
+            return pairs;
+        };
+        let name = rest[..eq].trim();
+        let value = rest[eq + 1..].trim_start();
+        let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            return pairs;
+        };
+        let Some(end) = value[1..].find(quote) else {
+            return pairs;
+        };
+        pairs.push((name, &value[1..end + 1]));
+        rest = &value[end + 2..];
+    }
+}
+
+pub(crate) fn parse_xml(text: &str) -> Result<usvg::roxmltree::Document<'_>> {
+    let opt = usvg::roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    usvg::roxmltree::Document::parse_with_options(text, opt)
+        .map_err(|e| SvgError::Parse(e.to_string()))
+}
+
+/// Describe the root's declared width/height for messages, and report whether it
+/// has a viewBox.
+fn root_size_attributes(text: &str) -> Result<(String, bool)> {
+    let xml = parse_xml(text)?;
+    let root = xml.root_element();
+    let attr = |name: &str| root.attribute(name).unwrap_or("auto").to_string();
+    let declared = format!("width=\"{}\" height=\"{}\"", attr("width"), attr("height"));
+    Ok((declared, root.attribute("viewBox").is_some()))
+}
+
+/// Return `text` with the root element's width/height replaced by `size` (or removed),
+/// and its viewBox replaced by `view_box` when given.
+fn rewrite_root_size(
+    text: &str,
+    view_box: Option<&str>,
+    size: Option<(f32, f32)>,
+) -> Result<String> {
+    let xml = parse_xml(text)?;
+    let root = xml.root_element();
+
+    let mut removed: Vec<std::ops::Range<usize>> = root
+        .attributes()
+        .filter(|a| a.namespace().is_none())
+        .filter(|a| match a.name() {
+            "width" | "height" => true,
+            "viewBox" => view_box.is_some(),
+            _ => false,
+        })
+        .map(|a| a.range())
+        .collect();
+    removed.sort_by_key(|r| r.start);
+
+    // New attributes go right after the tag name
+    let name_start = root.range().start + 1;
+    let name_len = text[name_start..]
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(0);
+    let insert_at = name_start + name_len;
+
+    let mut inserted = String::new();
+    if let Some((w, h)) = size {
+        inserted.push_str(&format!(" width=\"{w}\" height=\"{h}\""));
+    }
+    if let Some(vb) = view_box {
+        inserted.push_str(&format!(" viewBox=\"{vb}\""));
+    }
+
+    let mut out = String::with_capacity(text.len() + inserted.len());
+    out.push_str(&text[..insert_at]);
+    out.push_str(&inserted);
+    let mut pos = insert_at;
+    for range in removed {
+        out.push_str(&text[pos..range.start]);
+        pos = range.end;
+    }
+    out.push_str(&text[pos..]);
+    Ok(out)
+}
+
+/// Return `text` with the root element's `name` attribute set to `value`.
+fn rewrite_root_attribute(text: &str, name: &str, value: &str) -> Result<String> {
+    let xml = parse_xml(text)?;
+    let root = xml.root_element();
+    let existing = root
+        .attributes()
+        .find(|a| a.namespace().is_none() && a.name() == name)
+        .map(|a| a.range());
+    let attribute = format!("{name}=\"{value}\"");
+    Ok(match existing {
+        Some(range) => format!("{}{attribute}{}", &text[..range.start], &text[range.end..]),
+        None => {
+            let name_start = root.range().start + 1;
+            let insert_at = name_start
+                + text[name_start..]
+                    .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+                    .unwrap_or(0);
+            format!("{} {attribute}{}", &text[..insert_at], &text[insert_at..])
+        }
+    })
+}
+
+/// The first shape, image or text painted under `group`. Also reports
+/// whether every ancestor group draws it as it is: fully opaque, and not
+/// clipped, masked or filtered.
+fn first_painted(group: &usvg::Group, plain: bool) -> Option<(&usvg::Node, bool)> {
+    let plain = plain
+        && group.opacity().get() >= 1.0
+        && group.clip_path().is_none()
+        && group.mask().is_none()
+        && group.filters().is_empty();
+    for node in group.children() {
+        match node {
+            usvg::Node::Group(g) => {
+                if let Some(found) = first_painted(g, plain) {
+                    return Some(found);
+                }
+            }
+            usvg::Node::Path(p)
+                if p.is_visible() && (p.fill().is_some() || p.stroke().is_some()) =>
+            {
+                return Some((node, plain));
+            }
+            usvg::Node::Image(i) if i.is_visible() => return Some((node, plain)),
+            usvg::Node::Text(_) => return Some((node, plain)),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Whether `path` is a rectangle, upright on the canvas, covering (nearly) all
+/// of `canvas`. Only its outline counts: a circle or a triangle whose bounds
+/// reach the edges leaves the corners showing.
+fn covers_canvas(path: &usvg::Path, canvas: &usvg::Rect) -> bool {
+    const EPSILON: f32 = 1e-3;
+    let transform = path.abs_transform();
+    let mut corners: Vec<usvg::tiny_skia_path::Point> = Vec::with_capacity(5);
+    for segment in path.data().segments() {
+        match segment {
+            usvg::tiny_skia_path::PathSegment::MoveTo(mut p)
+            | usvg::tiny_skia_path::PathSegment::LineTo(mut p) => {
+                transform.map_point(&mut p);
+                let repeated = corners.last().is_some_and(|last| {
+                    (last.x - p.x).abs() < EPSILON && (last.y - p.y).abs() < EPSILON
+                });
+                if !repeated {
+                    corners.push(p);
+                }
+            }
+            usvg::tiny_skia_path::PathSegment::Close => {}
+            // Curves aren't rectangles
+            _ => return false,
+        }
+    }
+    if corners.len() == 5
+        && (corners[0].x - corners[4].x).abs() < EPSILON
+        && (corners[0].y - corners[4].y).abs() < EPSILON
+    {
+        corners.pop();
+    }
+    if corners.len() != 4 {
+        return false;
+    }
+    let upright = (0..4).all(|i| {
+        let (a, b) = (corners[i], corners[(i + 1) % 4]);
+        (a.x - b.x).abs() < EPSILON || (a.y - b.y).abs() < EPSILON
+    });
+    let Some(bounds) = usvg::Rect::from_ltrb(
+        corners.iter().map(|p| p.x).fold(f32::INFINITY, f32::min),
+        corners.iter().map(|p| p.y).fold(f32::INFINITY, f32::min),
+        corners
+            .iter()
+            .map(|p| p.x)
+            .fold(f32::NEG_INFINITY, f32::max),
+        corners
+            .iter()
+            .map(|p| p.y)
+            .fold(f32::NEG_INFINITY, f32::max),
+    ) else {
+        return false;
+    };
+    let covered = bounds
+        .intersect(canvas)
+        .map_or(0.0, |r| r.width() * r.height());
+    upright && covered >= canvas.width() * canvas.height() * BACKGROUND_COVERAGE
+}
+
+/// Mean color of a piecewise-linear gradient over [0, 1]. Returns `None` if any
+/// stop is translucent, since the result wouldn't be a solid background.
+fn average_stops(stops: &[usvg::Stop]) -> Option<[u8; 3]> {
+    let first = stops.first()?;
+    let last = stops.last()?;
+    if stops.iter().any(|s| s.opacity().get() < 1.0) {
+        return None;
+    }
+
+    let rgb = |s: &usvg::Stop| {
+        let c = s.color();
+        [c.red as f32, c.green as f32, c.blue as f32]
+    };
+    let mut sum = [0.0f32; 3];
+    let mut add = |c: [f32; 3], weight: f32| {
+        for (total, v) in sum.iter_mut().zip(c) {
+            *total += v * weight;
+        }
+    };
+
+    // Solid before the first stop and after the last one
+    add(rgb(first), first.offset().get());
+    add(rgb(last), 1.0 - last.offset().get());
+    for pair in stops.windows(2) {
+        let (a, b) = (rgb(&pair[0]), rgb(&pair[1]));
+        let span = pair[1].offset().get() - pair[0].offset().get();
+        let mid = [
+            (a[0] + b[0]) / 2.0,
+            (a[1] + b[1]) / 2.0,
+            (a[2] + b[2]) / 2.0,
+        ];
+        add(mid, span);
+    }
+
+    Some(sum.map(|v| v.round().clamp(0.0, 255.0) as u8))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -67,30 +1424,91 @@ mod tests {
     #[test]
     fn test_load_simple_rect() {
         let doc = SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
-        assert_eq!(doc.width, 200.0);
-        assert_eq!(doc.height, 150.0);
+        assert_eq!(doc.width(), 200.0);
+        assert_eq!(doc.height(), 150.0);
         assert_eq!(doc.filename(), "simple_rect.svg");
-        assert!(doc.file_size > 0);
+        assert!(doc.file_size() > 0);
+    }
+
+    #[test]
+    fn test_declared_background_formats() {
+        let bg = |root: &str| declared_background(&format!("{root}</svg>"));
+        assert_eq!(
+            bg(r##"<svg style="background-color:#1e1e1e">"##),
+            Some([0x1e, 0x1e, 0x1e])
+        );
+        assert_eq!(bg(r##"<svg background="#abc">"##), Some([0xaa, 0xbb, 0xcc]));
+        assert_eq!(
+            bg(r#"<svg style="background-color: rgb(30, 40, 50)">"#),
+            Some([30, 40, 50])
+        );
+        assert_eq!(
+            bg(r#"<svg background="rgb(100%, 0%, 0%)">"#),
+            Some([255, 0, 0])
+        );
+        assert_eq!(bg(r#"<svg style="background: navy">"#), Some([0, 0, 128]));
+        assert_eq!(
+            bg(r#"<svg style="background: url(a.png) no-repeat rgb(1, 2, 3)">"#),
+            Some([1, 2, 3])
+        );
+        assert_eq!(bg(r#"<svg style="background-color: transparent">"#), None);
+        assert_eq!(bg(r#"<svg style="fill: red" background="nonsense">"#), None);
+        assert_eq!(bg("<?xml version=\"1.0\"?>\n<svg>"), None);
+    }
+
+    #[test]
+    fn test_declared_background_precedence() {
+        let bg = |root: &str| declared_background(&format!("{root}</svg>"));
+        // The style wins over the attribute, and the last declaration in it wins
+        assert_eq!(
+            bg(r#"<svg background="white" style="background-color: black">"#),
+            Some([0, 0, 0])
+        );
+        assert_eq!(
+            bg(r#"<svg style="background: red; background-color: lime">"#),
+            Some([0, 255, 0])
+        );
+        // A style without a usable color falls back to the attribute
+        assert_eq!(
+            bg(r#"<svg style="background: url(a.png)" background="blue">"#),
+            Some([0, 0, 255])
+        );
+    }
+
+    #[test]
+    fn test_document_exposes_declared_background() {
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"
+            style="background-color:#1e1e1e"><rect width="5" height="5"/></svg>"#;
+        let doc = SvgDocument::from_data(Path::new("dark.svg"), svg.to_vec()).unwrap();
+        assert_eq!(doc.declared_background(), Some([0x1e, 0x1e, 0x1e]));
+        let plain = SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+        assert_eq!(plain.declared_background(), None);
     }
 
     #[test]
     fn test_load_gradient() {
         let doc = SvgDocument::load(&fixture_path("gradient.svg")).unwrap();
-        assert_eq!(doc.width, 200.0);
-        assert_eq!(doc.height, 200.0);
+        assert_eq!(doc.width(), 200.0);
+        assert_eq!(doc.height(), 200.0);
     }
 
     #[test]
     fn test_load_transparent() {
         let doc = SvgDocument::load(&fixture_path("transparent.svg")).unwrap();
-        assert_eq!(doc.width, 100.0);
-        assert_eq!(doc.height, 100.0);
+        assert_eq!(doc.width(), 100.0);
+        assert_eq!(doc.height(), 100.0);
     }
 
     #[test]
     fn test_load_malformed_fails() {
         let result = SvgDocument::load(&fixture_path("malformed.svg"));
-        assert!(result.is_err());
+        let Err(SvgError::Malformed(diagnostic)) = result else {
+            panic!("expected a located parse error");
+        };
+        // The unclosed <rect> runs into the comment on the next line
+        assert_eq!(diagnostic.line, 3);
+        assert_eq!(diagnostic.tag.as_deref(), Some("rect"));
+        assert!(diagnostic.snippet.iter().any(|l| l.text.contains("<rect")));
     }
 
     #[test]
@@ -99,6 +1517,19 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_load_archive_entry() {
+        let zip = crate::archive::tests::temp_zip("document");
+        let doc = SvgDocument::load_from_archive(&zip, "solid/icon10.svg").unwrap();
+        assert_eq!((doc.width(), doc.height()), (24.0, 24.0));
+        assert_eq!(doc.filename(), "icon10.svg");
+        // The virtual path loads the same entry again
+        let again = SvgDocument::load(doc.path()).unwrap();
+        assert_eq!(again.raw_data(), doc.raw_data());
+        assert!(SvgDocument::load_from_archive(&zip, "solid/missing.svg").is_err());
+        std::fs::remove_file(&zip).unwrap();
+    }
+
     #[test]
     fn test_file_size_display_bytes() {
         let doc = SvgDocument::load(&fixture_path("transparent.svg")).unwrap();
@@ -107,9 +1538,544 @@ mod tests {
         assert!(display.contains("B"));
     }
 
+    #[test]
+    fn test_gzip_is_detected_by_content() {
+        use std::io::Write;
+        let source = std::fs::read(fixture_path("simple_rect.svg")).unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(&source).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        // A plain .svg name, as a build tool might write
+        let doc = SvgDocument::from_data(Path::new("drawing.svg"), gzipped.clone()).unwrap();
+        assert_eq!(doc.file_size(), gzipped.len() as u64);
+        assert_eq!(doc.decompressed_size(), Some(source.len() as u64));
+        assert_eq!(
+            doc.file_size_display(),
+            format!(
+                "{} ({} uncompressed)",
+                format_size(gzipped.len() as u64),
+                format_size(source.len() as u64)
+            )
+        );
+        let plain = SvgDocument::from_data(Path::new("drawing.svg"), source).unwrap();
+        assert_eq!(plain.decompressed_size(), None);
+        assert_eq!(plain.width(), doc.width());
+
+        // Caught mid-write
+        let partial = SvgDocument::from_data(Path::new("drawing.svgz"), gzipped[..20].to_vec());
+        let Err(error) = partial else {
+            panic!("a truncated gzip file loaded");
+        };
+        let message = error.to_string();
+        assert!(message.contains("gzip-compressed"), "{message}");
+    }
+
+    #[test]
+    fn test_detect_solid_background() {
+        let doc = SvgDocument::load(&fixture_path("solid_background.svg")).unwrap();
+        let bg = doc.background().unwrap();
+        assert_eq!(bg.color, [0x1E, 0x1E, 0x1E]);
+        assert_eq!(bg.source, "#backdrop");
+    }
+
+    #[test]
+    fn test_no_background_when_not_covering() {
+        // simple_rect.svg's rect has a 10px margin; transparent.svg has only small shapes
+        let doc = SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+        assert!(doc.background().is_none());
+        let doc = SvgDocument::load(&fixture_path("transparent.svg")).unwrap();
+        assert!(doc.detect_background(true).is_none());
+    }
+
+    #[test]
+    fn test_background_must_be_a_rectangle_painted_first() {
+        let detect = |body: &str| {
+            let source = format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">{body}</svg>"#
+            );
+            SvgDocument::from_data(Path::new("background.svg"), source.into_bytes())
+                .unwrap()
+                .detect_background(true)
+                .map(|bg| bg.color)
+        };
+        let rect = r##"<rect width="100" height="100" fill="#102030"/>"##;
+        let dot = r##"<circle cx="50" cy="50" r="10" fill="#fff"/>"##;
+        assert_eq!(detect(&format!("{rect}{dot}")), Some([0x10, 0x20, 0x30]));
+        // As a path, and with a transform that keeps it upright
+        assert_eq!(
+            detect(r##"<path d="M0 0H50V50H0Z" transform="scale(2)" fill="#102030"/>"##),
+            Some([0x10, 0x20, 0x30])
+        );
+
+        // Shapes whose bounds span the canvas but whose corners don't
+        assert_eq!(
+            detect(r##"<circle cx="50" cy="50" r="50" fill="#102030"/>"##),
+            None
+        );
+        assert_eq!(
+            detect(r##"<path d="M0 0L100 0L0 100Z" fill="#102030"/>"##),
+            None
+        );
+        assert_eq!(
+            detect(
+                r##"<rect width="100" height="100" transform="rotate(10 50 50)" fill="#102030"/>"##
+            ),
+            None
+        );
+        // An overlay drawn over the content isn't a background
+        assert_eq!(detect(&format!("{dot}{rect}")), None);
+        // Nor is a clipped one
+        assert_eq!(
+            detect(&format!(
+                r#"<clipPath id="c"><circle cx="50" cy="50" r="40"/></clipPath><g clip-path="url(#c)">{rect}</g>"#
+            )),
+            None
+        );
+    }
+
+    #[test]
+    fn test_gradient_background_rejected_or_averaged() {
+        let doc = SvgDocument::load(&fixture_path("gradient.svg")).unwrap();
+        assert!(doc.detect_background(false).is_none());
+        // red -> green -> blue at offsets 0, 0.5, 1
+        let bg = doc.detect_background(true).unwrap();
+        assert_eq!(bg.color, [64, 128, 64]);
+    }
+
+    #[test]
+    fn test_valid_size_has_no_warning() {
+        let doc = SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+        assert!(doc.size_warning().is_none());
+    }
+
+    #[test]
+    fn test_zero_width_falls_back_to_view_box() {
+        let doc = SvgDocument::load(&fixture_path("zero_width.svg")).unwrap();
+        assert_eq!((doc.width(), doc.height()), (40.0, 50.0));
+        let warning = doc.size_warning().unwrap();
+        assert!(warning.contains("width=\"0\""));
+        assert!(warning.contains("viewBox"));
+    }
+
+    #[test]
+    fn test_zero_size_falls_back_to_content_bounds() {
+        let doc = SvgDocument::load(&fixture_path("zero_size_no_viewbox.svg")).unwrap();
+        assert_eq!((doc.width(), doc.height()), (20.0, 30.0));
+        assert!(doc.size_warning().unwrap().contains("content bounds"));
+        // The content is shifted so it fills the fallback canvas
+        let bbox = doc.tree().root().abs_bounding_box();
+        assert_eq!((bbox.x(), bbox.y()), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_zero_size_empty_falls_back_to_unit_canvas() {
+        let doc = SvgDocument::load(&fixture_path("zero_size_empty.svg")).unwrap();
+        assert_eq!((doc.width(), doc.height()), (1.0, 1.0));
+        assert!(doc.size_warning().unwrap().contains("1×1"));
+    }
+
+    #[test]
+    fn test_extreme_dimensions_load_exactly() {
+        let doc = SvgDocument::load(&fixture_path("extreme_aspect.svg")).unwrap();
+        assert_eq!((doc.width(), doc.height()), (1.0, 100000.0));
+        let doc = SvgDocument::load(&fixture_path("huge_dimensions.svg")).unwrap();
+        assert_eq!((doc.width(), doc.height()), (2_000_000.0, 1_000_000.0));
+        assert!(doc.size_warning().is_none());
+    }
+
+    #[test]
+    fn test_rewrite_root_size() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="0" height="5"><g/></svg>"#;
+        let out = rewrite_root_size(svg, Some("1 2 3 4"), Some((3.0, 4.0))).unwrap();
+        let xml = parse_xml(&out).unwrap();
+        let root = xml.root_element();
+        assert_eq!(root.attribute("width"), Some("3"));
+        assert_eq!(root.attribute("height"), Some("4"));
+        assert_eq!(root.attribute("viewBox"), Some("1 2 3 4"));
+        assert!(out.ends_with("<g/></svg>"));
+    }
+
+    fn doc_from(svg: &str) -> SvgDocument {
+        SvgDocument::from_data(Path::new("test.svg"), svg.as_bytes().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_content_stats_outside_canvas() {
+        let doc = doc_from(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+                <rect x="200" y="0" width="50" height="50"/>
+                <circle cx="300" cy="20" r="10"/>
+            </svg>"#,
+        );
+        let stats = doc.content_stats();
+        assert_eq!(
+            (stats.elements, stats.outside_canvas, stats.hidden),
+            (2, 2, 0)
+        );
+        assert_eq!(stats.content_bounds, Some([200.0, 0.0, 110.0, 50.0]));
+        match stats.empty_notice() {
+            EmptyNotice::Empty {
+                message,
+                fit_bounds,
+            } => {
+                assert!(
+                    message.contains("2 elements outside the canvas"),
+                    "{message}"
+                );
+                assert_eq!(fit_bounds, stats.content_bounds);
+            }
+            notice => panic!("unexpected {notice:?}"),
+        }
+
+        let fitted = doc.with_canvas(stats.content_bounds.unwrap()).unwrap();
+        assert_eq!((fitted.width(), fitted.height()), (110.0, 50.0));
+        assert_eq!(fitted.content_stats().outside_canvas, 0);
+        assert!(fitted.size_warning().is_some());
+    }
+
+    #[test]
+    fn test_content_bbox() {
+        let corner = doc_from(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="1000" height="500">
+  <rect x="900" y="400" width="50" height="40"/>
+  <rect x="980" y="480" width="100" height="100"/>
+</svg>"#,
+        );
+        // Clipped to the canvas, which is all that's rendered
+        assert_eq!(corner.content_bbox(), [900.0, 400.0, 100.0, 100.0]);
+
+        let empty = doc_from(r#"<svg xmlns="http://www.w3.org/2000/svg" width="30" height="20"/>"#);
+        assert_eq!(empty.content_bbox(), [0.0, 0.0, 30.0, 20.0]);
+        let line = doc_from(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="30" height="20"><path d="M0 5 H30"/></svg>"#,
+        );
+        assert_eq!(line.content_bbox(), [0.0, 0.0, 30.0, 20.0]);
+    }
+
+    #[test]
+    fn test_content_stats_hidden_and_defs() {
+        let hidden = doc_from(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+                <rect width="10" height="10" display="none"/>
+                <rect width="10" height="10" visibility="hidden"/>
+            </svg>"#,
+        );
+        let stats = hidden.content_stats();
+        assert_eq!(stats.hidden, 2);
+        assert_eq!(stats.content_bounds, None);
+
+        let defs_only = doc_from(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+                <defs><path id="p" d="M0 0 L10 10"/></defs>
+            </svg>"#,
+        );
+        let stats = defs_only.content_stats();
+        assert_eq!((stats.elements, stats.defined_only), (0, 1));
+        match stats.empty_notice() {
+            EmptyNotice::Empty {
+                message,
+                fit_bounds,
+            } => {
+                assert!(message.contains("only defined"), "{message}");
+                assert_eq!(fit_bounds, None);
+            }
+            notice => panic!("unexpected {notice:?}"),
+        }
+    }
+
+    #[test]
+    fn test_empty_document_is_a_spacer() {
+        let doc = doc_from(r#"<svg xmlns="http://www.w3.org/2000/svg" width="1" height="1"/>"#);
+        assert_eq!(doc.content_stats().empty_notice(), EmptyNotice::Spacer);
+    }
+
     #[test]
     fn test_filename() {
         let doc = SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
         assert_eq!(doc.filename(), "simple_rect.svg");
+        assert_eq!(doc.file_stem(), "simple_rect");
+        assert!(!doc.is_stdin());
+    }
+
+    #[test]
+    fn test_stdin_names() {
+        let data = br#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="4"/>"#;
+        let doc = SvgDocument::from_data(Path::new(STDIN_NAME), data.to_vec()).unwrap();
+        assert!(doc.is_stdin());
+        assert_eq!(doc.filename(), "<stdin>");
+        assert_eq!(doc.file_stem(), "stdin");
+        assert!(doc.is_in_memory() && !doc.is_pasted());
+
+        let doc = SvgDocument::from_data(Path::new(""), data.to_vec()).unwrap();
+        assert_eq!(doc.file_stem(), "untitled");
+    }
+
+    #[test]
+    fn test_from_clipboard_text() {
+        let text = r#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="4"/>"#;
+        let doc = SvgDocument::from_clipboard_text(text.into()).unwrap();
+        assert!(doc.is_pasted() && doc.is_in_memory());
+        assert_eq!(doc.file_stem(), "clipboard");
+        assert_eq!(doc.raw_data(), text.as_bytes());
+
+        // Anything that isn't markup is a parse error, not an empty document
+        assert!(SvgDocument::from_clipboard_text("just some text".into()).is_err());
+    }
+
+    /// Whether the pixel at (x, 50) of a 200×100 render is painted.
+    fn painted_at(doc: &SvgDocument, x: u32) -> bool {
+        let pixmap = crate::renderer::render_to_pixmap(
+            doc,
+            &crate::viewport::Viewport::default(),
+            200.0,
+            100.0,
+            1.0,
+        )
+        .unwrap();
+        pixmap.pixel(x, 50).unwrap().alpha() > 0
+    }
+
+    #[test]
+    fn test_non_default_aspect_ratio_is_noticed() {
+        for (name, declared, effect) in [
+            ("aspect_none.svg", "none", "stretched"),
+            ("aspect_slice.svg", "xMidYMid slice", "cropped"),
+            ("aspect_xminymin.svg", "xMinYMin", "off-center"),
+        ] {
+            let doc = SvgDocument::load(&fixture_path(name)).unwrap();
+            let fit = doc.view_box_fit();
+            assert_eq!(fit.view_box.as_deref(), Some("0 0 100 100"));
+            assert_eq!(fit.preserve_aspect_ratio.as_deref(), Some(declared));
+            assert!(!fit.is_default());
+            let notice = doc.aspect_notice().unwrap();
+            assert!(notice.contains(effect), "{name}: {notice}");
+        }
+
+        // The default, or a viewBox with the canvas's aspect, needs no notice
+        assert!(SvgDocument::load(&fixture_path("simple_rect.svg"))
+            .unwrap()
+            .aspect_notice()
+            .is_none());
+        let matching = doc_from(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100" viewBox="0 0 50 25" preserveAspectRatio="none"/>"#,
+        );
+        assert!(matching.aspect_notice().is_none());
+    }
+
+    #[test]
+    fn test_render_follows_declared_aspect_ratio() {
+        let none = SvgDocument::load(&fixture_path("aspect_none.svg")).unwrap();
+        assert!(painted_at(&none, 10) && painted_at(&none, 190));
+        let slice = SvgDocument::load(&fixture_path("aspect_slice.svg")).unwrap();
+        assert!(painted_at(&slice, 10) && painted_at(&slice, 190));
+        let xminymin = SvgDocument::load(&fixture_path("aspect_xminymin.svg")).unwrap();
+        assert!(painted_at(&xminymin, 10) && !painted_at(&xminymin, 190));
+    }
+
+    #[test]
+    fn test_forced_meet_centers_the_view_box() {
+        for name in ["aspect_none.svg", "aspect_slice.svg", "aspect_xminymin.svg"] {
+            let declared = SvgDocument::load(&fixture_path(name)).unwrap();
+            let meet = declared.with_meet().unwrap();
+            assert!(meet.is_meet_forced());
+            assert!(!painted_at(&meet, 10), "{name}");
+            assert!(painted_at(&meet, 100), "{name}");
+            assert!(!painted_at(&meet, 190), "{name}");
+            // Still described, and saved, as the file declares
+            assert_eq!(meet.view_box_fit(), declared.view_box_fit());
+            assert!(meet.aspect_notice().is_some());
+            assert_eq!(meet.raw_data(), declared.raw_data());
+
+            let restored = meet.as_declared().unwrap();
+            assert!(!restored.is_meet_forced());
+            assert_eq!(painted_at(&restored, 10), painted_at(&declared, 10));
+        }
+    }
+
+    #[test]
+    fn test_rewrite_root_attribute() {
+        let text = r#"<svg preserveAspectRatio="none" width="1"/>"#;
+        assert_eq!(
+            rewrite_root_attribute(text, "preserveAspectRatio", "xMidYMid meet").unwrap(),
+            r#"<svg preserveAspectRatio="xMidYMid meet" width="1"/>"#
+        );
+        assert_eq!(
+            rewrite_root_attribute("<svg/>", "preserveAspectRatio", "xMidYMid meet").unwrap(),
+            r#"<svg preserveAspectRatio="xMidYMid meet"/>"#
+        );
+    }
+
+    #[test]
+    fn test_metadata_from_source() {
+        let doc = doc_from(
+            r#"<?xml version="1.0"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="210mm" height="100%" viewBox="0 0 210 297">
+  <title>Floor   plan &amp; notes</title>
+  <desc>Ground floor,
+    first draft</desc>
+  <g><title>Not the document's</title></g>
+</svg>"#,
+        );
+        let metadata = doc.metadata();
+        assert_eq!(metadata.title.as_deref(), Some("Floor plan & notes"));
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("Ground floor, first draft")
+        );
+        assert_eq!(metadata.width.as_deref(), Some("210mm"));
+        assert_eq!(metadata.height.as_deref(), Some("100%"));
+        assert_eq!(doc.view_box_fit().view_box.as_deref(), Some("0 0 210 297"));
+        assert_eq!(doc.display_name(), "Floor plan & notes");
+    }
+
+    #[test]
+    fn test_documents_without_metadata() {
+        let doc = SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+        let metadata = doc.metadata();
+        assert_eq!(
+            (metadata.title.as_ref(), metadata.description.as_ref()),
+            (None, None)
+        );
+        assert_eq!(metadata.width.as_deref(), Some("200"));
+        assert_eq!(doc.display_name(), "simple_rect.svg");
+
+        // Only the root's own title counts, and an empty one is no title
+        let doc = doc_from(
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><title> </title><g><title>Group</title></g></svg>"#,
+        );
+        assert_eq!(doc.metadata().title, None);
+        assert_eq!(doc.metadata().width, None);
+    }
+
+    #[test]
+    fn test_stats_count_elements() {
+        let doc = doc_from(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <defs>
+    <linearGradient id="fade"><stop offset="0" stop-color="red"/><stop offset="1" stop-color="blue"/></linearGradient>
+    <filter id="blur"><feGaussianBlur stdDeviation="2"/></filter>
+  </defs>
+  <g opacity="0.5">
+    <rect width="10" height="10" fill="url(#fade)"/>
+    <circle r="5" filter="url(#blur)"/>
+  </g>
+  <text x="5" y="50">Hi</text>
+  <image width="1" height="1" href="data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8BQDwAEhQGAhKmMIQAAAABJRU5ErkJggg=="/>
+</svg>"##,
+        );
+        let stats = doc.stats();
+        assert_eq!(stats.paths, 2);
+        assert!(stats.groups >= 1);
+        assert_eq!(stats.texts, 1);
+        assert_eq!(stats.raster_images, 1);
+        assert_eq!(stats.svg_images, 0);
+        assert_eq!(stats.gradients, 1);
+        assert_eq!(stats.filters, 1);
+        // Cached: the same stats come back
+        assert!(std::ptr::eq(stats, doc.stats()));
+        assert!(stats.table().contains("Paths:        2\n"));
+    }
+
+    #[test]
+    fn test_unresolved_resources_warn() {
+        let doc = doc_from(
+            r##"<?xml-stylesheet href="theme.css"?>
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="10" height="10">
+  <rect id="box" width="4" height="4"/>
+  <use href="#box"/>
+  <use xlink:href="#gone"/>
+  <use href="shapes.svg#star"/>
+  <image width="1" height="1" href="no_such_image.png"/>
+  <image width="1" height="1" href="https://example.com/a.png"/>
+  <animate attributeName="x" to="5"/>
+</svg>"##,
+        );
+        assert_eq!(
+            doc.warnings(),
+            [
+                "External style sheet not loaded: theme.css",
+                "<use> refers to missing element #gone",
+                "<use> of another file not supported: shapes.svg#star",
+                "Image not found: no_such_image.png",
+                "Remote image not loaded: https://example.com/a.png",
+                "<animate> isn't rendered",
+            ]
+        );
+        // Still loaded and drawn
+        assert_eq!(doc.width(), 10.0);
+        assert!(doc_from(r#"<svg xmlns="http://www.w3.org/2000/svg"/>"#)
+            .warnings()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_images_resolve_beside_the_document() {
+        let dir =
+            std::env::temp_dir().join(format!("svg_viewer_test_{}_images", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut png = tiny_skia::Pixmap::new(2, 2).unwrap();
+        png.fill(tiny_skia::Color::from_rgba8(0, 0, 255, 255));
+        png.save_png(dir.join("blue.png")).unwrap();
+        let path = dir.join("page.svg");
+        std::fs::write(
+            &path,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="2" height="2"><image width="2" height="2" href="blue.png"/></svg>"#,
+        )
+        .unwrap();
+
+        let doc = SvgDocument::load(&path).unwrap();
+        assert!(doc.warnings().is_empty(), "{:?}", doc.warnings());
+        assert_eq!(doc.stats().raster_images, 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_embedded_images() {
+        let doc = SvgDocument::load(&fixture_path("embedded_image.svg")).unwrap();
+        let images = embedded_images(doc.tree());
+        assert_eq!(images.len(), 2);
+        let png = &images[0];
+        assert_eq!(png.format, "png");
+        assert_eq!(png.dimensions, Some((3, 2)));
+        assert_eq!(png.encoded_size(), 73);
+        assert!(png.data.starts_with(b"\x89PNG"));
+        assert_eq!(png.file_name("page", 0), "swatch.png");
+        assert_eq!(images[1].file_name("page", 1), "page_image2.png");
+
+        let plain = SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+        assert!(embedded_images(plain.tree()).is_empty());
+    }
+
+    #[test]
+    fn test_with_quality() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="8" height="8"
+            viewBox="0 0 8 8" preserveAspectRatio="none"><circle cx="4" cy="4" r="3.3"/></svg>"#;
+        let alphas = |doc: &SvgDocument| {
+            let mut pixmap = tiny_skia::Pixmap::new(8, 8).unwrap();
+            resvg::render(doc.tree(), Default::default(), &mut pixmap.as_mut());
+            pixmap
+                .pixels()
+                .iter()
+                .map(|p| p.alpha())
+                .collect::<Vec<_>>()
+        };
+        let doc = SvgDocument::from_data(Path::new("dot.svg"), svg.into()).unwrap();
+        assert_eq!(doc.quality(), RenderQuality::Smooth);
+        assert!(alphas(&doc).iter().any(|&a| a > 0 && a < 255));
+
+        let crisp = doc.with_quality(RenderQuality::Crisp).unwrap();
+        assert_eq!(crisp.quality(), RenderQuality::Crisp);
+        let crisp_alphas = alphas(&crisp);
+        assert!(crisp_alphas.contains(&255));
+        assert!(crisp_alphas.iter().all(|&a| a == 0 || a == 255));
+
+        // Re-parsing for the aspect keeps the hints
+        let meet = crisp.with_meet().unwrap();
+        assert_eq!(meet.quality(), RenderQuality::Crisp);
+        assert!(meet
+            .with_quality(RenderQuality::Smooth)
+            .unwrap()
+            .is_meet_forced());
     }
 }
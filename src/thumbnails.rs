@@ -0,0 +1,225 @@
+//! Small renders of every file being navigated, for the thumbnail strip.
+//!
+//! Thumbnails are rendered one at a time on a background thread, most
+//! recently requested first so the ones scrolled to come before those
+//! scrolled past, and kept in a cache that forgets the least recently shown.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+
+use tiny_skia::Pixmap;
+
+use crate::error::Result;
+use crate::renderer;
+use crate::svg_document::SvgDocument;
+use crate::viewport::{usable_dimension, Viewport};
+
+/// Longer side of a thumbnail in the strip, in pixels.
+pub const THUMBNAIL_SIZE: u32 = 96;
+/// Thumbnails kept before the least recently shown are dropped.
+pub const MAX_CACHED: usize = 300;
+
+/// `path` rendered to fit in a `size`×`size` square.
+pub fn render_thumbnail(path: &Path, size: u32) -> Result<Pixmap> {
+    let doc = SvgDocument::load(path)?;
+    let side = size as f32;
+    let mut viewport = Viewport::default();
+    viewport.fit_to_area(
+        usable_dimension(doc.width()),
+        usable_dimension(doc.height()),
+        side,
+        side,
+    );
+    renderer::render_to_pixmap(&doc, &viewport, side, side, 1.0)
+}
+
+/// Where a file's thumbnail has got to.
+pub enum Thumbnail<T> {
+    /// Requested and not rendered yet
+    Pending,
+    Ready(T),
+    /// The file couldn't be read or rendered
+    Broken,
+}
+
+/// Thumbnails by path, dropping the least recently used beyond a capacity.
+pub struct ThumbnailCache<T> {
+    entries: HashMap<PathBuf, (Thumbnail<T>, u64)>,
+    capacity: usize,
+    /// Counts uses, to tell which entry was used longest ago
+    clock: u64,
+}
+
+impl<T> ThumbnailCache<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            capacity: capacity.max(1),
+            clock: 0,
+        }
+    }
+
+    /// The thumbnail for `path`, counting as a use.
+    pub fn get(&mut self, path: &Path) -> Option<&Thumbnail<T>> {
+        self.clock += 1;
+        let (thumbnail, used) = self.entries.get_mut(path)?;
+        *used = self.clock;
+        Some(thumbnail)
+    }
+
+    pub fn insert(&mut self, path: PathBuf, thumbnail: Thumbnail<T>) {
+        self.clock += 1;
+        self.entries.insert(path, (thumbnail, self.clock));
+        while self.entries.len() > self.capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(path, _)| path.clone());
+            match oldest {
+                Some(path) => self.entries.remove(&path),
+                None => break,
+            };
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// A thumbnail the loader has finished: the file, the size asked for and the
+/// render.
+pub type Rendered = (PathBuf, u32, Result<Pixmap>);
+
+/// Paths and sizes waiting to be rendered, and whether the loader has gone
+/// away.
+#[derive(Default)]
+struct Queue {
+    pending: Mutex<(Vec<(PathBuf, u32)>, bool)>,
+    wake: Condvar,
+}
+
+/// Renders requested thumbnails on a thread of its own, which ends when the
+/// loader is dropped.
+pub struct ThumbnailLoader {
+    queue: Arc<Queue>,
+    results: mpsc::Receiver<Rendered>,
+}
+
+impl ThumbnailLoader {
+    /// Start rendering thumbnails, calling `notify` from the thread as each is
+    /// done (e.g. to wake the UI).
+    pub fn start(notify: impl Fn() + Send + 'static) -> Self {
+        let queue = Arc::new(Queue::default());
+        let (tx, results) = mpsc::channel();
+        let thread_queue = Arc::clone(&queue);
+        std::thread::spawn(move || loop {
+            let (path, size) = {
+                let mut pending = thread_queue
+                    .pending
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner());
+                loop {
+                    if pending.1 {
+                        return;
+                    }
+                    if let Some(request) = pending.0.pop() {
+                        break request;
+                    }
+                    pending = thread_queue
+                        .wake
+                        .wait(pending)
+                        .unwrap_or_else(|e| e.into_inner());
+                }
+            };
+            let thumbnail = render_thumbnail(&path, size);
+            if tx.send((path, size, thumbnail)).is_err() {
+                return;
+            }
+            notify();
+        });
+        Self { queue, results }
+    }
+
+    /// Render `path` to fit in `size`×`size` next, ahead of anything requested
+    /// before it.
+    pub fn request(&self, path: PathBuf, size: u32) {
+        let mut pending = self.queue.pending.lock().unwrap_or_else(|e| e.into_inner());
+        pending.0.push((path, size));
+        self.queue.wake.notify_one();
+    }
+
+    /// The thumbnails rendered since the last call.
+    pub fn finished(&self) -> Vec<Rendered> {
+        self.results.try_iter().collect()
+    }
+}
+
+impl Drop for ThumbnailLoader {
+    fn drop(&mut self) {
+        let mut pending = self.queue.pending.lock().unwrap_or_else(|e| e.into_inner());
+        pending.1 = true;
+        self.queue.wake.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn fixture_path(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("assets")
+            .join("test_fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn test_render_thumbnail_fits_the_square() {
+        // simple_rect.svg is 200×150
+        let pixmap = render_thumbnail(&fixture_path("simple_rect.svg"), 96).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (96, 72));
+        assert!(render_thumbnail(&fixture_path("malformed.svg"), 96).is_err());
+    }
+
+    #[test]
+    fn test_cache_drops_least_recently_used() {
+        let mut cache = ThumbnailCache::new(2);
+        cache.insert("a.svg".into(), Thumbnail::Ready(1));
+        cache.insert("b.svg".into(), Thumbnail::Broken);
+        // Using a makes b the oldest
+        assert!(cache.get(Path::new("a.svg")).is_some());
+        cache.insert("c.svg".into(), Thumbnail::Pending);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(Path::new("b.svg")).is_none());
+        assert!(matches!(
+            cache.get(Path::new("a.svg")),
+            Some(Thumbnail::Ready(1))
+        ));
+    }
+
+    #[test]
+    fn test_loader_renders_and_reports_failures() {
+        let loader = ThumbnailLoader::start(|| {});
+        loader.request(fixture_path("simple_rect.svg"), 32);
+        loader.request(fixture_path("malformed.svg"), 32);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut done = Vec::new();
+        while done.len() < 2 && Instant::now() < deadline {
+            done.extend(loader.finished());
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(done.len(), 2);
+        for (path, size, thumbnail) in &done {
+            assert_eq!(*size, 32);
+            assert_eq!(thumbnail.is_ok(), path.ends_with("simple_rect.svg"));
+        }
+    }
+}
@@ -0,0 +1,595 @@
+//! Deep zoom: a document shown larger than the window is drawn as square
+//! tiles, rendered on a worker thread as they come into view and kept as
+//! textures of their own, so panning never renders the whole view again.
+//!
+//! Tiles are rendered at a power of two physical pixels per SVG unit, the
+//! zoom *bucket*, at or above the scale they're shown at, so zooms within a
+//! factor of two share them. Rotated, mirrored and stretched views are still
+//! rendered whole.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+
+use tiny_skia::Pixmap;
+
+use crate::color_management::ColorManagement;
+use crate::error::{Result, SvgError};
+use crate::renderer::{PostPipeline, PreviewEffects};
+use crate::svg_document::SvgDocument;
+use crate::viewport::{usable_dimension, Viewport};
+
+/// Side of a tile, in pixels.
+pub const TILE_SIZE: u32 = 512;
+/// Bytes of tile pixels kept; the tiles shown least recently go first.
+pub const TILE_BUDGET: usize = 256 * 1024 * 1024;
+/// Tiles handed to the worker at a time, so a pan doesn't leave renders of
+/// tiles that have already scrolled away queued ahead of the new ones.
+pub const MAX_PENDING_TILES: usize = 4;
+/// How many buckets either side of the view's a missing tile's stand-in may
+/// come from.
+pub const STAND_IN_BUCKETS: i32 = 3;
+
+/// One tile: column `x` and row `y` of `bucket`'s grid over the document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TileKey {
+    pub x: u32,
+    pub y: u32,
+    pub bucket: i32,
+}
+
+/// Physical pixels per SVG unit in `bucket`'s tiles.
+pub fn bucket_scale(bucket: i32) -> f32 {
+    (bucket as f32).exp2()
+}
+
+/// The bucket to show a document at `scale` physical pixels per SVG unit
+/// from: the smallest power of two at or above it, so tiles are only shrunk.
+pub fn zoom_bucket(scale: f32) -> i32 {
+    // Rounding mustn't push a scale that is a power of two up a bucket
+    (scale.log2() - 1e-4).ceil() as i32
+}
+
+/// Whether `viewport` shows a `svg_width`×`svg_height` document as tiles on
+/// an `area_width`×`area_height` canvas: it's larger than the canvas, which
+/// a whole render is capped to, and not rotated, mirrored or stretched.
+pub fn wants_tiles(
+    viewport: &Viewport,
+    (svg_width, svg_height): (f32, f32),
+    (area_width, area_height): (f32, f32),
+) -> bool {
+    let plain = viewport.rotation_deg == 0.0
+        && !viewport.mirror_h
+        && !viewport.mirror_v
+        && !viewport.is_stretched();
+    let shown_w = usable_dimension(svg_width) * viewport.zoom;
+    let shown_h = usable_dimension(svg_height) * viewport.zoom;
+    plain
+        && area_width > 0.0
+        && area_height > 0.0
+        && (shown_w > area_width || shown_h > area_height)
+}
+
+/// Columns and rows of `bucket`'s tiles over a document `svg_size` units large.
+pub fn tile_grid((svg_width, svg_height): (f32, f32), bucket: i32) -> (u32, u32) {
+    let scale = bucket_scale(bucket);
+    let cells = |extent: f32| (extent * scale / TILE_SIZE as f32).ceil().max(1.0) as u32;
+    (cells(svg_width), cells(svg_height))
+}
+
+/// Pixel size of `key`'s tile; those along the right and bottom stop at the
+/// document's edge.
+pub fn tile_pixels(key: TileKey, (svg_width, svg_height): (f32, f32)) -> (u32, u32) {
+    let scale = bucket_scale(key.bucket);
+    let side = |extent: f32, index: u32| {
+        let left = extent * scale - index as f32 * TILE_SIZE as f32;
+        (left.ceil() as u32).clamp(1, TILE_SIZE)
+    };
+    (side(svg_width, key.x), side(svg_height, key.y))
+}
+
+/// The part of the document `key`'s tile shows, in SVG units: x, y, width
+/// and height.
+pub fn tile_bounds(key: TileKey, svg_size: (f32, f32)) -> [f32; 4] {
+    let scale = bucket_scale(key.bucket);
+    let (w, h) = tile_pixels(key, svg_size);
+    let step = TILE_SIZE as f32 / scale;
+    [
+        key.x as f32 * step,
+        key.y as f32 * step,
+        w as f32 / scale,
+        h as f32 / scale,
+    ]
+}
+
+/// The part of a document on screen, and the scale it's shown at.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileView {
+    /// The document's size in SVG units
+    pub svg_size: (f32, f32),
+    /// Physical pixels per SVG unit
+    pub scale: f32,
+    /// What shows of the document, in SVG units: left, top, right, bottom
+    pub visible: [f32; 4],
+}
+
+impl TileView {
+    /// `viewport`'s view of a document `svg_size` units large on an
+    /// `area_width`×`area_height` canvas, `density` physical pixels per
+    /// logical one.
+    pub fn new(
+        viewport: &Viewport,
+        svg_size: (f32, f32),
+        (area_width, area_height): (f32, f32),
+        density: f32,
+    ) -> Self {
+        let zoom = viewport.zoom;
+        // The document is centered on the pan
+        let left = (svg_size.0 * zoom - area_width) / 2.0 - viewport.pan.x;
+        let top = (svg_size.1 * zoom - area_height) / 2.0 - viewport.pan.y;
+        Self {
+            svg_size,
+            scale: zoom * density,
+            visible: [
+                (left / zoom).clamp(0.0, svg_size.0),
+                (top / zoom).clamp(0.0, svg_size.1),
+                ((left + area_width) / zoom).clamp(0.0, svg_size.0),
+                ((top + area_height) / zoom).clamp(0.0, svg_size.1),
+            ],
+        }
+    }
+
+    /// The bucket the view's own tiles come from.
+    pub fn bucket(&self) -> i32 {
+        zoom_bucket(self.scale)
+    }
+
+    /// `bucket`'s tiles covering `area` (left, top, right, bottom in SVG
+    /// units) of the document.
+    pub fn covering(&self, area: [f32; 4], bucket: i32) -> Vec<TileKey> {
+        let step = TILE_SIZE as f32 / bucket_scale(bucket);
+        let (cols, rows) = tile_grid(self.svg_size, bucket);
+        let range = |from: f32, to: f32, count: u32| {
+            let first = ((from / step).floor().max(0.0) as u32).min(count - 1);
+            let last = ((to / step).ceil() as u32).clamp(first + 1, count);
+            first..last
+        };
+        let xs = range(area[0], area[2], cols);
+        range(area[1], area[3], rows)
+            .flat_map(|y| xs.clone().map(move |x| TileKey { x, y, bucket }))
+            .collect()
+    }
+
+    /// The view's own tiles that show, nearest the middle of the view first.
+    pub fn tiles(&self) -> Vec<TileKey> {
+        let [left, top, right, bottom] = self.visible;
+        let middle = ((left + right) / 2.0, (top + bottom) / 2.0);
+        let mut tiles = self.covering(self.visible, self.bucket());
+        let distance = |key: &TileKey| {
+            let [x, y, w, h] = tile_bounds(*key, self.svg_size);
+            let (dx, dy) = (x + w / 2.0 - middle.0, y + h / 2.0 - middle.1);
+            dx * dx + dy * dy
+        };
+        tiles.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+        tiles
+    }
+}
+
+/// Render `key`'s tile of `doc`, with `pipeline` applied.
+pub fn render_tile(doc: &SvgDocument, key: TileKey, pipeline: &PostPipeline) -> Result<Pixmap> {
+    let svg_size = (
+        usable_dimension(doc.width()),
+        usable_dimension(doc.height()),
+    );
+    let (w, h) = tile_pixels(key, svg_size);
+    let mut pixmap =
+        Pixmap::new(w, h).ok_or_else(|| SvgError::Render("Failed to create pixmap".into()))?;
+    let scale = bucket_scale(key.bucket);
+    let transform = tiny_skia::Transform::from_scale(scale, scale).post_translate(
+        -(key.x as f32 * TILE_SIZE as f32),
+        -(key.y as f32 * TILE_SIZE as f32),
+    );
+    resvg::render(doc.tree(), transform, &mut pixmap.as_mut());
+    pipeline.run(&mut pixmap);
+    Ok(pixmap)
+}
+
+/// What tiles are drawn from: the document and the canvas's effects. Tiles
+/// drawn from another source are thrown away.
+#[derive(Clone)]
+pub struct TileSource {
+    pub doc: Arc<SvgDocument>,
+    pub effects: PreviewEffects,
+    pub color: ColorManagement,
+}
+
+#[cfg(feature = "gui")]
+impl TileSource {
+    fn same(&self, other: &TileSource) -> bool {
+        Arc::ptr_eq(&self.doc, &other.doc)
+            && self.effects == other.effects
+            && self.color == other.color
+    }
+}
+
+type TileRequest = (u64, TileSource, TileKey);
+type FinishedTile = (u64, TileKey, Result<Pixmap>);
+
+/// Renders tiles on a thread of its own, in the order they're asked for.
+/// The thread ends once the worker is dropped.
+pub struct TileWorker {
+    requests: mpsc::Sender<TileRequest>,
+    finished: mpsc::Receiver<FinishedTile>,
+    /// Requests from before this generation are skipped
+    generation: Arc<AtomicU64>,
+}
+
+impl TileWorker {
+    /// Start the thread; `on_finished` is called after each tile.
+    pub fn spawn(on_finished: impl Fn() + Send + 'static) -> Self {
+        let (requests, queue) = mpsc::channel::<TileRequest>();
+        let (done, finished) = mpsc::channel();
+        let generation = Arc::new(AtomicU64::new(0));
+        let current = Arc::clone(&generation);
+        std::thread::spawn(move || {
+            for (generation, source, key) in queue {
+                if generation != current.load(Ordering::Relaxed) {
+                    continue;
+                }
+                let pipeline = PostPipeline::for_canvas(source.effects, source.color);
+                let result = render_tile(&source.doc, key, &pipeline);
+                if done.send((generation, key, result)).is_err() {
+                    break;
+                }
+                on_finished();
+            }
+        });
+        Self {
+            requests,
+            finished,
+            generation,
+        }
+    }
+
+    /// Skip requests made before `generation` that haven't started.
+    pub fn set_generation(&self, generation: u64) {
+        self.generation.store(generation, Ordering::Relaxed);
+    }
+
+    pub fn request(&self, generation: u64, source: &TileSource, key: TileKey) {
+        let _ = self.requests.send((generation, source.clone(), key));
+    }
+
+    /// Tiles finished since the last call, with the generation each was
+    /// asked for in.
+    pub fn finished(&self) -> impl Iterator<Item = FinishedTile> + '_ {
+        self.finished.try_iter()
+    }
+}
+
+/// A tile to draw, placed as fractions of the document's rect on screen,
+/// and the part of that (in the same terms) it's clipped to.
+#[cfg(feature = "gui")]
+#[derive(Clone, Copy, Debug)]
+pub struct PlacedTile {
+    pub texture: egui::TextureId,
+    pub rect: egui::Rect,
+    pub clip: egui::Rect,
+}
+
+#[cfg(feature = "gui")]
+struct CachedTile {
+    texture: egui::TextureHandle,
+    /// Kept for the eyedropper, which can't read the texture back
+    pixmap: Pixmap,
+    /// The texture's and the pixmap's
+    bytes: usize,
+    /// The frame it was last drawn in
+    last_shown: u64,
+}
+
+/// Uploaded tiles, most of a [`TILE_BUDGET`] of them, and the worker
+/// rendering more.
+#[cfg(feature = "gui")]
+#[derive(Default)]
+pub struct TileCache {
+    worker: Option<TileWorker>,
+    source: Option<TileSource>,
+    options: egui::TextureOptions,
+    generation: u64,
+    tiles: std::collections::HashMap<TileKey, CachedTile>,
+    pending: std::collections::HashSet<TileKey>,
+    /// Tiles whose render failed, not asked for again
+    failed: std::collections::HashSet<TileKey>,
+    bytes: usize,
+    frame: u64,
+    /// The view's own tiles not yet uploaded
+    missing: usize,
+    /// The last view's document size and bucket
+    svg_size: (f32, f32),
+    bucket: i32,
+}
+
+#[cfg(feature = "gui")]
+impl TileCache {
+    /// Take in finished tiles, ask for the view's missing ones, and return
+    /// what to draw: each of the view's tiles, or for one not ready yet, the
+    /// tiles of the nearest bucket that cover it.
+    pub fn update(
+        &mut self,
+        ctx: &egui::Context,
+        source: TileSource,
+        view: &TileView,
+        options: egui::TextureOptions,
+    ) -> Vec<PlacedTile> {
+        self.frame += 1;
+        self.svg_size = view.svg_size;
+        self.bucket = view.bucket();
+        if !self.source.as_ref().is_some_and(|s| s.same(&source)) || options != self.options {
+            self.clear();
+            self.source = Some(source);
+            self.options = options;
+        }
+        let worker = self.worker.get_or_insert_with(|| {
+            let ctx = ctx.clone();
+            TileWorker::spawn(move || ctx.request_repaint())
+        });
+        worker.set_generation(self.generation);
+        for (generation, key, result) in worker.finished() {
+            if generation != self.generation {
+                continue;
+            }
+            self.pending.remove(&key);
+            match result {
+                Ok(pixmap) => {
+                    let size = [pixmap.width() as usize, pixmap.height() as usize];
+                    let image = egui::ColorImage::from_rgba_premultiplied(size, pixmap.data());
+                    let name = format!("svg_tile_{}_{}_{}", key.bucket, key.x, key.y);
+                    let texture = ctx.load_texture(name, image, self.options);
+                    let bytes = pixmap.data().len() * 2;
+                    self.bytes += bytes;
+                    let tile = CachedTile {
+                        texture,
+                        pixmap,
+                        bytes,
+                        last_shown: self.frame,
+                    };
+                    if let Some(old) = self.tiles.insert(key, tile) {
+                        self.bytes -= old.bytes;
+                    }
+                }
+                Err(e) => {
+                    log::warn!("Rendering a tile failed: {e}");
+                    self.failed.insert(key);
+                }
+            }
+        }
+
+        let wanted = view.tiles();
+        let source = self.source.as_ref().expect("set above");
+        for key in &wanted {
+            if self.pending.len() >= MAX_PENDING_TILES {
+                break;
+            }
+            if !self.tiles.contains_key(key)
+                && !self.pending.contains(key)
+                && !self.failed.contains(key)
+            {
+                worker.request(self.generation, source, *key);
+                self.pending.insert(*key);
+            }
+        }
+
+        let fraction = |[x, y, w, h]: [f32; 4]| {
+            let (svg_w, svg_h) = view.svg_size;
+            egui::Rect::from_min_size(
+                egui::pos2(x / svg_w, y / svg_h),
+                egui::vec2(w / svg_w, h / svg_h),
+            )
+        };
+        let mut placed = Vec::new();
+        self.missing = 0;
+        for key in &wanted {
+            let bounds = tile_bounds(*key, view.svg_size);
+            let clip = fraction(bounds);
+            if let Some(tile) = self.tiles.get_mut(key) {
+                tile.last_shown = self.frame;
+                placed.push(PlacedTile {
+                    texture: tile.texture.id(),
+                    rect: clip,
+                    clip,
+                });
+                continue;
+            }
+            self.missing += 1;
+            let [x, y, w, h] = bounds;
+            let stand_in = (1..=STAND_IN_BUCKETS)
+                .flat_map(|d| [key.bucket - d, key.bucket + d])
+                .map(|bucket| view.covering([x, y, x + w, y + h], bucket))
+                .find(|keys| keys.iter().all(|k| self.tiles.contains_key(k)));
+            for other in stand_in.into_iter().flatten() {
+                let tile = self.tiles.get_mut(&other).expect("checked above");
+                tile.last_shown = self.frame;
+                placed.push(PlacedTile {
+                    texture: tile.texture.id(),
+                    rect: fraction(tile_bounds(other, view.svg_size)),
+                    clip,
+                });
+            }
+        }
+        self.evict();
+        placed
+    }
+
+    /// The straight RGBA color at `uv`, a position across the document from 0
+    /// to 1 on each axis, read from the tile drawn there in the last frame
+    /// whose bucket is nearest the view's.
+    pub fn pick(&self, [u, v]: [f32; 2]) -> Option<[u8; 4]> {
+        let (x, y) = (u * self.svg_size.0, v * self.svg_size.1);
+        let inside = |[left, top, w, h]: [f32; 4]| {
+            (left..left + w).contains(&x) && (top..top + h).contains(&y)
+        };
+        let (key, tile) = self
+            .tiles
+            .iter()
+            .filter(|(key, tile)| {
+                tile.last_shown == self.frame && inside(tile_bounds(**key, self.svg_size))
+            })
+            .min_by_key(|(key, _)| (key.bucket - self.bucket).abs())?;
+        let [left, top, w, h] = tile_bounds(*key, self.svg_size);
+        crate::eyedropper::pick(&tile.pixmap, [(x - left) / w, (y - top) / h])
+    }
+
+    /// Whether every tile of the last view is uploaded.
+    pub fn is_complete(&self) -> bool {
+        self.missing == 0
+    }
+
+    /// Drop every tile, and skip the worker's queued requests.
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+        self.pending.clear();
+        self.failed.clear();
+        self.bytes = 0;
+        self.missing = 0;
+        self.generation += 1;
+        if let Some(worker) = &self.worker {
+            worker.set_generation(self.generation);
+        }
+    }
+
+    /// Drop the tiles shown least recently until the rest fit the budget,
+    /// keeping any drawn this frame.
+    fn evict(&mut self) {
+        while self.bytes > TILE_BUDGET {
+            let oldest = self
+                .tiles
+                .iter()
+                .filter(|(_, tile)| tile.last_shown < self.frame)
+                .min_by_key(|(_, tile)| tile.last_shown)
+                .map(|(key, _)| *key);
+            let Some(key) = oldest else {
+                break;
+            };
+            if let Some(tile) = self.tiles.remove(&key) {
+                self.bytes -= tile.bytes;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zoom_bucket() {
+        assert_eq!(zoom_bucket(1.0), 0);
+        assert_eq!(zoom_bucket(4.0), 2);
+        assert_eq!(zoom_bucket(4.5), 3);
+        assert_eq!(zoom_bucket(0.3), -1);
+        assert_eq!(bucket_scale(-2), 0.25);
+    }
+
+    #[test]
+    fn test_visible_tiles() {
+        // A 1000-unit document at 8×: 8000 px, 16 tiles a side at bucket 3
+        let viewport = Viewport {
+            zoom: 8.0,
+            ..Default::default()
+        };
+        assert!(wants_tiles(&viewport, (1000.0, 1000.0), (800.0, 600.0)));
+        let view = TileView::new(&viewport, (1000.0, 1000.0), (800.0, 600.0), 1.0);
+        assert_eq!(view.bucket(), 3);
+        assert_eq!(tile_grid(view.svg_size, 3), (16, 16));
+        // Centered, the view is 800×600 px around the middle, 4000×4000
+        assert_eq!(view.visible, [450.0, 462.5, 550.0, 537.5]);
+        let tiles = view.tiles();
+        let xs: Vec<u32> = tiles.iter().map(|k| k.x).collect();
+        assert!(xs.iter().all(|x| (7..=8).contains(x)));
+        assert_eq!(tiles.len(), 4);
+
+        // Panned to the top-left corner
+        let corner = Viewport {
+            pan: emath::vec2(3600.0, 3700.0),
+            ..viewport.clone()
+        };
+        let view = TileView::new(&corner, (1000.0, 1000.0), (800.0, 600.0), 1.0);
+        assert_eq!(view.visible, [0.0, 0.0, 100.0, 75.0]);
+        let mut tiles = view.tiles();
+        tiles.sort_by_key(|k| (k.y, k.x));
+        assert_eq!(
+            tiles,
+            [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(x, y)| TileKey { x, y, bucket: 3 })
+        );
+
+        // Rotated, or small enough to fit, it renders whole
+        let rotated = Viewport {
+            rotation_deg: 90.0,
+            ..viewport.clone()
+        };
+        assert!(!wants_tiles(&rotated, (1000.0, 1000.0), (800.0, 600.0)));
+        assert!(!wants_tiles(&viewport, (50.0, 50.0), (800.0, 600.0)));
+    }
+
+    #[test]
+    fn test_edge_tiles_stop_at_the_document() {
+        let key = TileKey {
+            x: 1,
+            y: 0,
+            bucket: 0,
+        };
+        assert_eq!(tile_pixels(key, (700.0, 100.0)), (188, 100));
+        assert_eq!(tile_bounds(key, (700.0, 100.0)), [512.0, 0.0, 188.0, 100.0]);
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_pick_from_tiles() {
+        // Red above, blue below; at 4× the view shows tiles from both
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="400">
+            <rect width="400" height="200" fill="red"/>
+            <rect y="200" width="400" height="200" fill="blue"/></svg>"#;
+        let doc = SvgDocument::from_data(std::path::Path::new("t.svg"), svg.into()).unwrap();
+        let viewport = Viewport {
+            zoom: 4.0,
+            ..Default::default()
+        };
+        let view = TileView::new(&viewport, (400.0, 400.0), (800.0, 600.0), 1.0);
+        let source = TileSource {
+            doc: Arc::new(doc),
+            effects: PreviewEffects::default(),
+            color: ColorManagement::default(),
+        };
+        let ctx = egui::Context::default();
+        let mut cache = TileCache::default();
+        let options = egui::TextureOptions::default();
+        let started = std::time::Instant::now();
+        cache.update(&ctx, source.clone(), &view, options);
+        while !cache.is_complete() {
+            assert!(started.elapsed().as_secs() < 10, "tiles never finished");
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            cache.update(&ctx, source.clone(), &view, options);
+        }
+        assert_eq!(cache.pick([0.5, 0.45]), Some([255, 0, 0, 255]));
+        assert_eq!(cache.pick([0.5, 0.55]), Some([0, 0, 255, 255]));
+        // Outside what the view shows, no tile was drawn
+        assert_eq!(cache.pick([0.98, 0.98]), None);
+    }
+
+    #[test]
+    fn test_render_tile() {
+        // Left half red, right half blue; the second tile of a 2× bucket is blue
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="512" height="256">
+            <rect width="256" height="256" fill="red"/>
+            <rect x="256" width="256" height="256" fill="blue"/></svg>"#;
+        let doc = SvgDocument::from_data(std::path::Path::new("t.svg"), svg.into()).unwrap();
+        let pipeline = PostPipeline::new();
+        let tile = |x| render_tile(&doc, TileKey { x, y: 0, bucket: 1 }, &pipeline).unwrap();
+        let (left, right) = (tile(0), tile(1));
+        assert_eq!((left.width(), left.height()), (512, 512));
+        let p = left.pixel(10, 10).unwrap();
+        assert_eq!((p.red(), p.blue()), (255, 0));
+        let p = right.pixel(10, 10).unwrap();
+        assert_eq!((p.red(), p.blue()), (0, 255));
+    }
+}
@@ -0,0 +1,50 @@
+//! UTC timestamps as image metadata writes them, without a
+//! date library.
+
+/// Seconds since 1970, now.
+pub fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// `seconds` since 1970 as year, month, day, hours, minutes and seconds.
+fn civil(seconds: u64) -> (i64, i64, i64, u64, u64, u64) {
+    let (days, time) = (seconds / 86_400, seconds % 86_400);
+    // Days to a civil date, after Howard Hinnant's `civil_from_days`
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day, time / 3600, time % 3600 / 60, time % 60)
+}
+
+/// `seconds` since 1970 as `YYYY-MM-DDThh:mm:ss`.
+pub fn format_iso(seconds: u64) -> String {
+    let (year, month, day, hour, minute, second) = civil(seconds);
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}")
+}
+
+/// `seconds` since 1970 as EXIF writes it, `YYYY:MM:DD hh:mm:ss`.
+pub fn format_exif(seconds: u64) -> String {
+    let (year, month, day, hour, minute, second) = civil(seconds);
+    format!("{year:04}:{month:02}:{day:02} {hour:02}:{minute:02}:{second:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_iso(0), "1970-01-01T00:00:00");
+        assert_eq!(format_iso(951_782_400), "2000-02-29T00:00:00");
+        assert_eq!(format_iso(1_791_201_845), "2026-10-05T12:04:05");
+        assert_eq!(format_exif(1_791_201_845), "2026:10:05 12:04:05");
+    }
+}
@@ -0,0 +1,39 @@
+//! Moving files to the system trash rather than deleting them outright.
+//!
+//! The `trash` crate does the work: the freedesktop.org trash on Linux and
+//! the BSDs (in the home trash, or the `.Trash-$uid` at the top of another
+//! mount), the Finder's trash on macOS and the recycle bin on Windows, so
+//! files can be put back from there as usual.
+
+use std::path::Path;
+
+use crate::error::{Result, SvgError};
+
+/// Move the file at `path` to the trash.
+pub fn move_to_trash(path: &Path) -> Result<()> {
+    let path = path.canonicalize()?;
+    if !path.is_file() {
+        return Err(io_error(format!("{} isn't a file", path.display())));
+    }
+    ::trash::delete(&path).map_err(|e| io_error(e.to_string()))
+}
+
+fn io_error(message: String) -> SvgError {
+    SvgError::Io(std::io::Error::other(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_files_are_trashed() {
+        let dir = std::env::temp_dir().join(format!("svg_viewer_trash_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let err = move_to_trash(&dir).unwrap_err();
+        assert!(err.to_string().contains("isn't a file"), "{err}");
+        assert!(dir.exists());
+        assert!(move_to_trash(&dir.join("missing.svg")).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,102 @@
+use std::path::{Path, PathBuf};
+
+use egui::{Context, Window};
+
+use crate::batch::{BackgroundBatch, Outcome};
+
+#[derive(Default)]
+pub struct BatchProgressState {
+    pub open: bool,
+    batch: Option<BackgroundBatch>,
+    /// Whether the finished batch has been logged
+    reported: bool,
+}
+
+impl BatchProgressState {
+    /// Open the window on `batch`, replacing (and cancelling) any earlier one.
+    pub fn show(&mut self, batch: BackgroundBatch) {
+        self.open = true;
+        self.batch = Some(batch);
+        self.reported = false;
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Draw the Batch export window: progress while it runs, then what was
+/// exported and what failed. Returns a failed file the user clicked, to be
+/// opened.
+pub fn draw_batch_progress(ctx: &Context, state: &mut BatchProgressState) -> Option<PathBuf> {
+    if !state.open {
+        return None;
+    }
+    let batch = state.batch.as_mut()?;
+    batch.poll();
+    if batch.is_finished() && !state.reported {
+        state.reported = true;
+        log::info!("Batch export: {}", batch.summary().describe());
+    }
+
+    let mut open = state.open;
+    let mut clicked = None;
+    let mut cancel = false;
+
+    Window::new("Batch export")
+        .open(&mut open)
+        .default_size([360.0, 240.0])
+        .show(ctx, |ui| {
+            let (done, total) = (batch.records().len(), batch.total());
+            if let Some(current) = batch.current() {
+                if batch.is_cancelled() {
+                    ui.label(format!("Cancelling after {}\u{2026}", file_name(current)));
+                } else {
+                    ui.label(format!(
+                        "Exporting {} of {total}: {}",
+                        done + 1,
+                        file_name(current)
+                    ));
+                }
+                ui.add(egui::ProgressBar::new(done as f32 / total.max(1) as f32));
+                cancel = ui.button("Cancel").clicked();
+                return;
+            }
+
+            let summary = batch.summary().describe();
+            if batch.is_cancelled() {
+                ui.label(format!("Cancelled: {summary}"));
+            } else {
+                ui.label(summary);
+            }
+            let failed: Vec<_> = batch
+                .records()
+                .iter()
+                .filter_map(|record| match &record.outcome {
+                    Outcome::Failed { error } => Some((&record.source, error)),
+                    _ => None,
+                })
+                .collect();
+            if failed.is_empty() {
+                return;
+            }
+            ui.separator();
+            ui.strong("Failed files");
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (source, error) in failed {
+                    if ui.link(file_name(source)).on_hover_text(error).clicked() {
+                        clicked = Some(source.clone());
+                    }
+                }
+            });
+        });
+
+    if cancel || !open {
+        batch.cancel();
+    }
+    state.open = open;
+    clicked
+}
@@ -1,73 +1,251 @@
-use egui::{Color32, Rect, Sense, TextureHandle, Ui, Vec2};
+use egui::{Color32, ColorImage, Pos2, Rect, Sense, Ui, Vec2};
 
-const CHECKER_SIZE: f32 = 10.0;
-const CHECKER_LIGHT: Color32 = Color32::from_rgb(204, 204, 204);
-const CHECKER_DARK: Color32 = Color32::from_rgb(170, 170, 170);
+use crate::checkerboard::{self, CHECKER_DARK, CHECKER_LIGHT, CHECKER_SIZE};
+use crate::diagnostics::XmlDiagnostic;
+use crate::fonts::SubstitutedText;
+use crate::guides::{self, Guide};
+use crate::renderer::Presentation;
+use crate::viewport::Viewport;
 
+/// A render drawn on the canvas, centered on the pan.
+pub struct CanvasLayer<'a> {
+    pub image: &'a Presentation,
+    /// Size on screen, in logical pixels
+    pub size: Vec2,
+    /// Multiplied into the render's colors, to fade it
+    pub tint: Color32,
+}
+
+/// Draw the background and `layers`, bottom first. The checkerboard lines up
+/// with the top layer.
 pub fn draw_canvas(
     ui: &mut Ui,
-    texture: Option<&TextureHandle>,
+    layers: &[CanvasLayer],
     pan: Vec2,
     show_checkerboard: bool,
     bg_color: Color32,
-    display_size: Vec2,
-    zoom_ratio: f32,
 ) -> (egui::Response, Rect) {
     let available = ui.available_size();
     let (response, mut painter) = ui.allocate_painter(available, Sense::click_and_drag());
     let rect = response.rect;
 
-    // Draw background
+    // Draw background, with the checker phase anchored to the image's corner so
+    // captures flattened onto the checkerboard match the screen
     if show_checkerboard {
-        draw_checkerboard(&painter, rect);
+        let origin = layers
+            .last()
+            .map_or(rect.min, |layer| image_rect(rect, pan, layer.size, 1.0).min);
+        draw_checkerboard(&painter, rect, origin);
     } else {
         painter.rect_filled(rect, 0.0, bg_color);
     }
 
-    // Draw the SVG texture
-    if let Some(tex) = texture {
-        let img_size = display_size * zoom_ratio;
-        let center = rect.center().to_vec2() + pan;
-        let img_rect = Rect::from_center_size(center.to_pos2(), img_size);
-
-        // Clip to canvas area
-        painter.set_clip_rect(rect);
-
-        painter.image(
-            tex.id(),
-            img_rect,
-            Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
-            Color32::WHITE,
-        );
+    // Clip to canvas area
+    painter.set_clip_rect(rect);
+    for layer in layers {
+        let img_rect = image_rect(rect, pan, layer.size, 1.0);
+        match layer.image {
+            Presentation::Texture(id) => painter.image(
+                *id,
+                img_rect,
+                Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                layer.tint,
+            ),
+            Presentation::Software(pixels) => {
+                painter.add(software_mesh(pixels, img_rect, layer.tint))
+            }
+            Presentation::Tiles(tiles) => {
+                // Tiles are placed as fractions of the document's rect
+                let place = |r: Rect| {
+                    Rect::from_min_max(
+                        img_rect.min + r.min.to_vec2() * img_rect.size(),
+                        img_rect.min + r.max.to_vec2() * img_rect.size(),
+                    )
+                };
+                for tile in tiles {
+                    painter
+                        .with_clip_rect(place(tile.clip).intersect(rect))
+                        .image(
+                            tile.texture,
+                            place(tile.rect),
+                            Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                            layer.tint,
+                        );
+                }
+                egui::layers::ShapeIdx(0)
+            }
+        };
     }
 
     (response, rect)
 }
 
-fn draw_checkerboard(painter: &egui::Painter, rect: Rect) {
-    // Fill with light color first
-    painter.rect_filled(rect, 0.0, CHECKER_LIGHT);
-
-    // Draw dark squares
-    let start_x = rect.left();
-    let start_y = rect.top();
-    let end_x = rect.right();
-    let end_y = rect.bottom();
-
-    let mut y = start_y;
-    let mut row = 0;
-    while y < end_y {
-        let mut x = start_x + if row % 2 == 1 { CHECKER_SIZE } else { 0.0 };
-        while x < end_x {
-            let sq_rect = Rect::from_min_size(
-                egui::pos2(x, y),
-                Vec2::new(CHECKER_SIZE.min(end_x - x), CHECKER_SIZE.min(end_y - y)),
+/// `image` stretched over `rect` as one flat-colored quad per pixel, which needs
+/// no texture of its own.
+fn software_mesh(image: &ColorImage, rect: Rect, tint: Color32) -> egui::Mesh {
+    let [w, h] = image.size;
+    let cell = Vec2::new(rect.width() / w as f32, rect.height() / h as f32);
+    let mut mesh = egui::Mesh::default();
+    for (i, color) in image.pixels.iter().enumerate() {
+        if color.a() == 0 {
+            continue;
+        }
+        let min = rect.min + Vec2::new((i % w) as f32 * cell.x, (i / w) as f32 * cell.y);
+        mesh.add_colored_rect(Rect::from_min_size(min, cell), *color * tint);
+    }
+    mesh
+}
+
+/// Small translucent label in the bottom-right corner of the canvas.
+pub fn draw_badge(ui: &Ui, rect: Rect, text: &str) {
+    let painter = ui.painter_at(rect);
+    let margin = 6.0;
+    let padding = Vec2::new(6.0, 3.0);
+    let galley = painter.layout_no_wrap(
+        text.to_owned(),
+        egui::FontId::proportional(11.0),
+        Color32::from_white_alpha(220),
+    );
+    let size = galley.size() + padding * 2.0;
+    let badge_rect = Rect::from_min_size(rect.max - size - Vec2::splat(margin), size);
+    painter.rect_filled(badge_rect, 4.0, Color32::from_black_alpha(140));
+    painter.galley(badge_rect.min + padding, galley, Color32::WHITE);
+}
+
+/// The rectangle being dragged out to zoom to, clipped to the canvas `rect`.
+pub fn draw_selection(ui: &Ui, rect: Rect, selection: Rect) {
+    let painter = ui.painter_at(rect);
+    let stroke = ui.visuals().selection.stroke;
+    painter.rect_filled(
+        selection,
+        0.0,
+        ui.visuals().selection.bg_fill.gamma_multiply(0.25),
+    );
+    painter.rect_stroke(
+        selection,
+        0.0,
+        egui::Stroke::new(1.0, stroke.color),
+        egui::StrokeKind::Inside,
+    );
+}
+
+/// Where the render is shown within the canvas `rect`.
+pub fn image_rect(rect: Rect, pan: Vec2, display_size: Vec2, zoom_ratio: f32) -> Rect {
+    let center = rect.center().to_vec2() + pan;
+    Rect::from_center_size(center.to_pos2(), display_size * zoom_ratio)
+}
+
+/// The SVG point shown at screen position `pos`, for a render shown at `img_rect`.
+pub fn svg_point(
+    viewport: &Viewport,
+    svg_size: (f32, f32),
+    img_rect: Rect,
+    pos: Pos2,
+) -> Option<[f32; 2]> {
+    let transform = guides::screen_transform(
+        viewport,
+        svg_size.0,
+        svg_size.1,
+        [img_rect.min.x, img_rect.min.y],
+        [img_rect.width(), img_rect.height()],
+    )
+    .invert()?;
+    Some(guides::map_point(&transform, [pos.x, pos.y]))
+}
+
+/// Stroke `guides` over the render shown at `img_rect`, clipped to the canvas.
+pub fn draw_guides<'a>(
+    ui: &Ui,
+    rect: Rect,
+    img_rect: Rect,
+    guides: impl Iterator<Item = &'a Guide>,
+    viewport: &Viewport,
+    svg_size: (f32, f32),
+) {
+    let painter = ui.painter_at(rect);
+    let transform = guides::screen_transform(
+        viewport,
+        svg_size.0,
+        svg_size.1,
+        [img_rect.min.x, img_rect.min.y],
+        [img_rect.width(), img_rect.height()],
+    );
+    for guide in guides {
+        let [r, g, b, a] = guide.color;
+        let stroke = egui::Stroke::new(1.0, Color32::from_rgba_unmultiplied(r, g, b, a));
+        for outline in guide.outlines(svg_size.0, svg_size.1) {
+            let points: Vec<Pos2> = outline
+                .points
+                .iter()
+                .map(|p| {
+                    let [x, y] = guides::map_point(&transform, *p);
+                    egui::pos2(x, y)
+                })
+                .collect();
+            if outline.closed {
+                painter.add(egui::Shape::closed_line(points, stroke));
+            } else {
+                painter.add(egui::Shape::line(points, stroke));
+            }
+        }
+    }
+}
+
+/// Underline text drawn in a substitute font with a dashed line along the
+/// bottom of its bounds, over the render shown at `img_rect`.
+pub fn draw_substitutions(
+    ui: &Ui,
+    rect: Rect,
+    img_rect: Rect,
+    substituted: &[SubstitutedText],
+    viewport: &Viewport,
+    svg_size: (f32, f32),
+) {
+    let painter = ui.painter_at(rect);
+    let transform = guides::screen_transform(
+        viewport,
+        svg_size.0,
+        svg_size.1,
+        [img_rect.min.x, img_rect.min.y],
+        [img_rect.width(), img_rect.height()],
+    );
+    let stroke = egui::Stroke::new(1.5, Color32::from_rgba_unmultiplied(255, 140, 0, 200));
+    for text in substituted {
+        let [x, y, width, height] = text.bounds;
+        let bottom = y + height;
+        let points = [[x, bottom], [x + width, bottom]].map(|p| {
+            let [x, y] = guides::map_point(&transform, p);
+            egui::pos2(x, y)
+        });
+        painter.extend(egui::Shape::dashed_line(&points, stroke, 4.0, 3.0));
+    }
+}
+
+fn draw_checkerboard(painter: &egui::Painter, rect: Rect, origin: Pos2) {
+    let [r, g, b] = CHECKER_LIGHT;
+    painter.rect_filled(rect, 0.0, Color32::from_rgb(r, g, b));
+    let [r, g, b] = CHECKER_DARK;
+    let dark = Color32::from_rgb(r, g, b);
+
+    // Square indices covering the rect, counted from the origin
+    let first_col = ((rect.left() - origin.x) / CHECKER_SIZE).floor() as i64;
+    let first_row = ((rect.top() - origin.y) / CHECKER_SIZE).floor() as i64;
+    let last_col = ((rect.right() - origin.x) / CHECKER_SIZE).ceil() as i64;
+    let last_row = ((rect.bottom() - origin.y) / CHECKER_SIZE).ceil() as i64;
+
+    for row in first_row..last_row {
+        for col in first_col..last_col {
+            let min = egui::pos2(
+                origin.x + col as f32 * CHECKER_SIZE,
+                origin.y + row as f32 * CHECKER_SIZE,
             );
-            painter.rect_filled(sq_rect, 0.0, CHECKER_DARK);
-            x += CHECKER_SIZE * 2.0;
+            let center = min + Vec2::splat(CHECKER_SIZE / 2.0) - origin.to_vec2();
+            if !checkerboard::is_dark_square(center.x, center.y, CHECKER_SIZE) {
+                continue;
+            }
+            let square = Rect::from_min_size(min, Vec2::splat(CHECKER_SIZE)).intersect(rect);
+            painter.rect_filled(square, 0.0, dark);
         }
-        y += CHECKER_SIZE;
-        row += 1;
     }
 }
 
@@ -79,7 +257,55 @@ pub fn draw_welcome(ui: &mut Ui) {
             ui.add_space(10.0);
             ui.label("Open a file or drag & drop an SVG here");
             ui.add_space(5.0);
-            ui.label("Ctrl+O to open  |  Arrow keys to browse");
+            ui.label("Ctrl+O to open  |  Ctrl+V to paste SVG  |  Arrow keys to browse");
+        });
+    });
+}
+
+/// The error screen for a document that isn't well-formed, in place of the
+/// canvas: where parsing stopped, and the source around it.
+pub fn draw_parse_error(ui: &mut Ui, filename: &str, diagnostic: &XmlDiagnostic) {
+    ui.centered_and_justified(|ui| {
+        ui.vertical_centered(|ui| {
+            ui.add_space(ui.available_height() / 4.0);
+            ui.heading(format!("Couldn't open {filename}"));
+            ui.add_space(10.0);
+            ui.colored_label(ui.visuals().error_fg_color, &diagnostic.message);
+            ui.add_space(5.0);
+            let mut position = format!("Line {}, column {}", diagnostic.line, diagnostic.column);
+            if let Some(tag) = &diagnostic.tag {
+                position.push_str(&format!(", in <{tag}>"));
+            }
+            ui.label(position);
+            if diagnostic.snippet.is_empty() {
+                return;
+            }
+            ui.add_space(10.0);
+
+            let width = diagnostic
+                .snippet
+                .last()
+                .map_or(1, |line| line.number.to_string().len());
+            let mut source = String::new();
+            for line in &diagnostic.snippet {
+                let marker = if line.number == diagnostic.line {
+                    '>'
+                } else {
+                    ' '
+                };
+                source.push_str(&format!(
+                    "{marker}{:>width$} | {}\n",
+                    line.number, line.text
+                ));
+                if line.number == diagnostic.line {
+                    let indent = " ".repeat(width + 4 + diagnostic.snippet_column);
+                    source.push_str(&format!("{indent}^\n"));
+                }
+            }
+            egui::Frame::group(ui.style()).show(ui, |ui| {
+                let text = egui::RichText::new(source.trim_end()).monospace();
+                ui.add(egui::Label::new(text).extend().halign(egui::Align::Min));
+            });
         });
     });
 }
@@ -0,0 +1,197 @@
+use egui::{Pos2, Vec2};
+
+/// How far the pointer may move, in logical pixels, while a press still counts
+/// as a click rather than a pan.
+pub const DRAG_THRESHOLD: f32 = 4.0;
+/// Longest gap between two clicks that makes them a double click, in seconds.
+pub const DOUBLE_CLICK_TIME: f64 = 0.4;
+
+/// A click the pointer made on the canvas.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CanvasClick {
+    pub pos: Pos2,
+    /// The second click of a double click.
+    pub double: bool,
+}
+
+/// Tells clicks from pans for the primary button on the canvas. A press only
+/// becomes a pan once the pointer has moved [`DRAG_THRESHOLD`] from where it went
+/// down, so a click never nudges the view; a pan never ends in a click.
+#[derive(Debug, Default)]
+pub struct CanvasInteraction {
+    /// Where the button went down, while it's held
+    press: Option<Pos2>,
+    /// Last position a pan was reported up to
+    last: Pos2,
+    panning: bool,
+    /// Time and position of the last click, for double clicks
+    last_click: Option<(f64, Pos2)>,
+}
+
+impl CanvasInteraction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The primary button went down over the canvas at `pos`.
+    pub fn press(&mut self, pos: Pos2) {
+        self.press = Some(pos);
+        self.last = pos;
+        self.panning = false;
+    }
+
+    /// The primary button went down at `pos` with a key held that makes
+    /// dragging pan, like Space: it pans from the first movement and never
+    /// ends in a click.
+    pub fn press_to_pan(&mut self, pos: Pos2) {
+        self.press(pos);
+        self.panning = true;
+    }
+
+    /// The pointer moved to `pos` with the button held. Returns how far to pan,
+    /// once the movement has passed the threshold.
+    pub fn drag_to(&mut self, pos: Pos2) -> Option<Vec2> {
+        let press = self.press?;
+        if !self.panning {
+            if (pos - press).length() < DRAG_THRESHOLD {
+                return None;
+            }
+            // Catch up with the pointer, so the image stays where it was grabbed
+            self.panning = true;
+        }
+        let delta = pos - self.last;
+        self.last = pos;
+        (delta != Vec2::ZERO).then_some(delta)
+    }
+
+    /// The button came up at `pos`, at `time` seconds. Returns the click, unless
+    /// the press turned into a pan.
+    pub fn release(&mut self, pos: Pos2, time: f64) -> Option<CanvasClick> {
+        self.press.take()?;
+        if std::mem::take(&mut self.panning) {
+            self.last_click = None;
+            return None;
+        }
+        let double = self.last_click.is_some_and(|(at, last)| {
+            time - at <= DOUBLE_CLICK_TIME && (pos - last).length() < DRAG_THRESHOLD
+        });
+        // A third click starts a new pair
+        self.last_click = if double { None } else { Some((time, pos)) };
+        Some(CanvasClick { pos, double })
+    }
+
+    /// Forget a press whose release was never seen, e.g. when focus was lost.
+    pub fn cancel(&mut self) {
+        self.press = None;
+        self.panning = false;
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.press.is_some()
+    }
+
+    pub fn is_panning(&self) -> bool {
+        self.panning
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f32, y: f32) -> Pos2 {
+        Pos2::new(x, y)
+    }
+
+    #[test]
+    fn test_jitter_below_threshold_is_a_click() {
+        let mut interaction = CanvasInteraction::new();
+        interaction.press(at(100.0, 100.0));
+        assert_eq!(interaction.drag_to(at(101.0, 100.0)), None);
+        assert_eq!(interaction.drag_to(at(102.0, 101.5)), None);
+        let click = interaction.release(at(102.0, 101.5), 1.0).unwrap();
+        assert_eq!(click.pos, at(102.0, 101.5));
+        assert!(!click.double);
+        assert!(!interaction.is_pressed());
+    }
+
+    #[test]
+    fn test_drag_past_threshold_pans_without_click() {
+        let mut interaction = CanvasInteraction::new();
+        interaction.press(at(10.0, 10.0));
+        assert_eq!(interaction.drag_to(at(12.0, 10.0)), None);
+        // The first pan covers the whole movement since the press
+        assert_eq!(
+            interaction.drag_to(at(15.0, 10.0)),
+            Some(Vec2::new(5.0, 0.0))
+        );
+        assert_eq!(
+            interaction.drag_to(at(15.0, 13.0)),
+            Some(Vec2::new(0.0, 3.0))
+        );
+        assert_eq!(interaction.drag_to(at(15.0, 13.0)), None);
+        // Moving back near the start doesn't turn it back into a click
+        assert_eq!(
+            interaction.drag_to(at(10.0, 10.0)),
+            Some(Vec2::new(-5.0, -3.0))
+        );
+        assert!(interaction.is_panning());
+        assert_eq!(interaction.release(at(10.0, 10.0), 1.0), None);
+        assert!(!interaction.is_panning());
+    }
+
+    #[test]
+    fn test_press_to_pan_has_no_threshold() {
+        let mut interaction = CanvasInteraction::new();
+        interaction.press_to_pan(at(10.0, 10.0));
+        assert_eq!(
+            interaction.drag_to(at(11.0, 10.0)),
+            Some(Vec2::new(1.0, 0.0))
+        );
+        assert_eq!(interaction.release(at(11.0, 10.0), 0.0), None);
+        // Even without moving, it isn't a click
+        interaction.press_to_pan(at(0.0, 0.0));
+        assert_eq!(interaction.release(at(0.0, 0.0), 0.1), None);
+    }
+
+    #[test]
+    fn test_double_click() {
+        let mut interaction = CanvasInteraction::new();
+        let mut click = |time: f64, x: f32| {
+            interaction.press(at(x, 0.0));
+            interaction.release(at(x, 0.0), time).unwrap().double
+        };
+        assert!(!click(0.0, 0.0));
+        assert!(click(0.3, 1.0));
+        // A third quick click starts over
+        assert!(!click(0.5, 1.0));
+        // Too slow
+        assert!(!click(1.5, 1.0));
+        // Too far apart
+        assert!(!click(1.6, 20.0));
+    }
+
+    #[test]
+    fn test_pan_breaks_double_click() {
+        let mut interaction = CanvasInteraction::new();
+        interaction.press(at(0.0, 0.0));
+        interaction.release(at(0.0, 0.0), 0.0).unwrap();
+        interaction.press(at(0.0, 0.0));
+        interaction.drag_to(at(10.0, 0.0));
+        assert_eq!(interaction.release(at(10.0, 0.0), 0.1), None);
+        interaction.press(at(0.0, 0.0));
+        assert!(!interaction.release(at(0.0, 0.0), 0.2).unwrap().double);
+    }
+
+    #[test]
+    fn test_moves_and_releases_without_a_press_are_ignored() {
+        let mut interaction = CanvasInteraction::new();
+        assert_eq!(interaction.drag_to(at(50.0, 50.0)), None);
+        assert_eq!(interaction.release(at(50.0, 50.0), 0.0), None);
+
+        interaction.press(at(0.0, 0.0));
+        interaction.cancel();
+        assert_eq!(interaction.drag_to(at(50.0, 50.0)), None);
+        assert_eq!(interaction.release(at(50.0, 50.0), 0.1), None);
+    }
+}
@@ -0,0 +1,41 @@
+use std::path::Path;
+
+use egui::{Context, Key, Window};
+
+/// Ask before moving `path` to the trash. Returns `Some(true)` to go ahead,
+/// `Some(false)` to keep the file, and `None` until one is chosen.
+pub fn draw_delete_confirm(ctx: &Context, path: &Path) -> Option<bool> {
+    let mut choice = None;
+    let name = path
+        .file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy();
+    Window::new("Move to Trash")
+        .resizable(false)
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.label(format!("Move \u{201C}{name}\u{201D} to the trash?"));
+            ui.weak("Shift+Delete moves files without asking");
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                if ui.button("Move to Trash").on_hover_text("Enter").clicked() {
+                    choice = Some(true);
+                }
+                if ui.button("Cancel").on_hover_text("Esc").clicked() {
+                    choice = Some(false);
+                }
+            });
+        });
+
+    if choice.is_none() {
+        ctx.input(|i| {
+            if i.key_pressed(Key::Escape) {
+                choice = Some(false);
+            } else if i.key_pressed(Key::Enter) {
+                choice = Some(true);
+            }
+        });
+    }
+    choice
+}
@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use egui::{Color32, Context, Vec2};
+
+use crate::renderer::{Presentation, RenderScale, Renderer};
+use crate::svg_document::SvgDocument;
+use crate::viewport::Viewport;
+
+/// Previous file's tint while tinting, and the current file's.
+const PREVIOUS_TINT: [u8; 3] = [220, 40, 40];
+const CURRENT_TINT: [u8; 3] = [30, 170, 60];
+
+/// What the diff overlay toolbar popup sets.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OverlaySettings {
+    pub enabled: bool,
+    /// Opacity of the previous file under the current one, 0 to 1
+    pub opacity: f32,
+    /// Show the previous file in red and the current one in green
+    pub tint: bool,
+}
+
+impl Default for OverlaySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 0.5,
+            tint: false,
+        }
+    }
+}
+
+/// The previous file shown under the current one while stepping through a
+/// folder, rendered with the same view so the two line up.
+#[derive(Default)]
+pub struct DiffOverlay {
+    pub settings: OverlaySettings,
+    previous: Option<Arc<SvgDocument>>,
+    previous_layer: Option<Renderer>,
+    /// The current file tinted, drawn instead of the plain render while tinting
+    current_layer: Option<Renderer>,
+    /// The view changed since the layers were rendered
+    stale: bool,
+}
+
+impl DiffOverlay {
+    /// Apply new settings. Switching the overlay off releases the previous file
+    /// and its textures.
+    pub fn set_settings(&mut self, settings: OverlaySettings) {
+        if settings.tint != self.settings.tint {
+            self.current_layer = None;
+            self.stale = true;
+        }
+        self.settings = settings;
+        if !settings.enabled {
+            self.previous = None;
+            self.previous_layer = None;
+            self.current_layer = None;
+        }
+    }
+
+    /// Another file replaced `replaced`, which becomes the one shown under it.
+    pub fn file_changed(&mut self, replaced: Option<Arc<SvgDocument>>) {
+        if self.settings.enabled && replaced.is_some() {
+            self.previous = replaced;
+            self.stale = true;
+        }
+    }
+
+    /// The current file was rendered again, so the layers follow.
+    pub fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    /// Whether there's a previous file to show.
+    pub fn is_showing(&self) -> bool {
+        self.settings.enabled && self.previous.is_some()
+    }
+
+    /// Render the layers again for `viewport` in an `area_width`×`area_height`
+    /// canvas if they're out of date, with the effects and color management of
+    /// `main`, the current file's renderer. Call once per frame, after it renders.
+    pub fn update(
+        &mut self,
+        ctx: &Context,
+        main: &Renderer,
+        current: Option<&SvgDocument>,
+        viewport: &Viewport,
+        area: (f32, f32),
+        render_scale: RenderScale,
+    ) {
+        let Some(previous) = self.previous.as_ref().filter(|_| self.stale) else {
+            return;
+        };
+        if !self.settings.enabled {
+            return;
+        }
+        self.stale = false;
+        let tint = self.settings.tint;
+        let render = |layer: &mut Option<Renderer>, doc: &SvgDocument, color: [u8; 3]| {
+            let renderer = layer.get_or_insert_with(Renderer::new);
+            renderer.set_effects(main.effects());
+            renderer.set_color_management(main.color_management());
+            renderer.set_tint(tint.then_some(color));
+            let result =
+                renderer.render_and_upload(ctx, doc, viewport, area.0, area.1, render_scale);
+            if let Err(e) = result {
+                log::warn!(
+                    "Rendering {} for the diff overlay failed: {e}",
+                    doc.filename()
+                );
+            }
+        };
+        render(&mut self.previous_layer, previous, PREVIOUS_TINT);
+        match current.filter(|_| tint) {
+            Some(doc) => render(&mut self.current_layer, doc, CURRENT_TINT),
+            None => self.current_layer = None,
+        }
+    }
+
+    /// What to draw under the current file, if anything, shown at `zoom`.
+    pub fn previous_presentation(&self, zoom: f32) -> Option<(Presentation, Vec2)> {
+        if !self.is_showing() {
+            return None;
+        }
+        layer_presentation(self.previous_layer.as_ref()?, zoom)
+    }
+
+    /// The tinted current file to draw in place of the plain render, if
+    /// tinting, shown at `zoom`.
+    pub fn current_presentation(&self, zoom: f32) -> Option<(Presentation, Vec2)> {
+        if !self.is_showing() {
+            return None;
+        }
+        layer_presentation(self.current_layer.as_ref()?, zoom)
+    }
+
+    /// The tint the previous file is drawn with: faded to the chosen opacity.
+    pub fn previous_tint(&self) -> Color32 {
+        Color32::WHITE.gamma_multiply(self.settings.opacity.clamp(0.0, 1.0))
+    }
+}
+
+/// `renderer`'s render and its size on screen at `zoom`.
+fn layer_presentation(renderer: &Renderer, zoom: f32) -> Option<(Presentation, Vec2)> {
+    let ratio = if renderer.rendered_zoom > 0.0 {
+        zoom / renderer.rendered_zoom
+    } else {
+        1.0
+    };
+    let size = Vec2::new(renderer.logical_display_w, renderer.logical_display_h) * ratio;
+    Some((renderer.presentation()?, size))
+}
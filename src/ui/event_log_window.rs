@@ -0,0 +1,77 @@
+use egui::{Context, Window};
+
+use crate::event_log::{self, Severity};
+
+pub struct EventLogWindowState {
+    pub open: bool,
+    /// Which severities are listed, in `Severity::all()` order
+    shown: [bool; 3],
+}
+
+impl Default for EventLogWindowState {
+    fn default() -> Self {
+        Self {
+            open: false,
+            shown: [true; 3],
+        }
+    }
+}
+
+impl EventLogWindowState {
+    fn is_shown(&self, severity: Severity) -> bool {
+        Severity::all()
+            .iter()
+            .position(|s| *s == severity)
+            .is_some_and(|i| self.shown[i])
+    }
+}
+
+pub fn draw_event_log(ctx: &Context, state: &mut EventLogWindowState) {
+    if !state.open {
+        return;
+    }
+
+    let events = event_log::global().snapshot();
+    let mut open = state.open;
+
+    Window::new("Event log")
+        .open(&mut open)
+        .default_size([560.0, 320.0])
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                for (severity, shown) in Severity::all().iter().zip(state.shown.iter_mut()) {
+                    ui.checkbox(shown, severity.name());
+                }
+                ui.separator();
+                if ui.button("Copy all").clicked() {
+                    ctx.copy_text(event_log::global().to_text());
+                }
+                if ui.button("Clear").clicked() {
+                    event_log::global().clear();
+                }
+            });
+            ui.separator();
+
+            egui::ScrollArea::vertical()
+                .auto_shrink([false, false])
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for event in events.iter().filter(|e| state.is_shown(e.severity)) {
+                        let text = egui::RichText::new(event.to_line()).monospace();
+                        let text = match event.severity {
+                            Severity::Error => text.color(ui.visuals().error_fg_color),
+                            Severity::Warn => text.color(ui.visuals().warn_fg_color),
+                            Severity::Info => text,
+                        };
+                        ui.label(text);
+                    }
+                    if events.is_empty() {
+                        ui.weak("Nothing logged yet");
+                    }
+                });
+        });
+
+    if !open {
+        state.open = false;
+    }
+}
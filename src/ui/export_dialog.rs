@@ -1,6 +1,68 @@
-use egui::{Context, Window};
+use std::collections::HashMap;
 
-use crate::export::{ExportFormat, ExportSettings};
+use egui::{Context, Id, Key, Window};
+
+use crate::batch::Collision;
+use crate::export::{
+    self, ExportFormat, ExportRegion, ExportSettings, OrientationMode, Padding, SizePlan,
+    MAX_EXPORT_DIM,
+};
+use crate::jpeg::ChromaSubsampling;
+use crate::renderer;
+use crate::settings::{self, RememberedExport};
+use crate::svg_document;
+use crate::viewport::Viewport;
+
+/// Widgets reachable with Tab / Shift+Tab, in traversal order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FocusField {
+    Format,
+    Width,
+    Height,
+    Scale,
+    /// A rendered PDF's resolution, in place of the width, height and scale
+    Dpi,
+    Quality,
+    ExportButton,
+}
+
+/// The focus chain for the current settings; fields that aren't shown are left out.
+pub fn focus_chain(settings: &ExportSettings) -> Vec<FocusField> {
+    let mut chain = vec![FocusField::Format];
+    let by_dpi = settings.dpi.is_some() && settings.format.has_dimensions();
+    if settings.format == ExportFormat::Pdf || by_dpi {
+        if !settings.is_vector_pdf() {
+            chain.push(FocusField::Dpi);
+        }
+    } else if settings.format.has_dimensions() {
+        chain.extend([FocusField::Width, FocusField::Height, FocusField::Scale]);
+    }
+    if matches!(settings.format, ExportFormat::Jpeg | ExportFormat::Avif) {
+        chain.push(FocusField::Quality);
+    }
+    chain.push(FocusField::ExportButton);
+    chain
+}
+
+/// Field after (or before, when `backwards`) `current`, wrapping at both ends.
+/// Starts from the first (or last) field when nothing in the chain has focus.
+pub fn next_focus(
+    chain: &[FocusField],
+    current: Option<FocusField>,
+    backwards: bool,
+) -> Option<FocusField> {
+    let len = chain.len();
+    if len == 0 {
+        return None;
+    }
+    let index = match current.and_then(|c| chain.iter().position(|f| *f == c)) {
+        Some(i) if backwards => (i + len - 1) % len,
+        Some(i) => (i + 1) % len,
+        None if backwards => len - 1,
+        None => 0,
+    };
+    Some(chain[index])
+}
 
 pub struct ExportDialogState {
     pub open: bool,
@@ -9,6 +71,57 @@ pub struct ExportDialogState {
     pub original_width: f32,
     pub original_height: f32,
     pub result: ExportDialogResult,
+    /// Shown next to the dimensions when the document's declared size was unusable.
+    pub size_warning: Option<String>,
+    /// Shown next to the dimensions when preserveAspectRatio changes how the
+    /// viewBox fills the canvas
+    pub aspect_notice: Option<String>,
+    /// Capture the image as rendered on screen instead of the whole document
+    pub current_view: bool,
+    /// Times the screen's resolution the current view is captured at
+    pub view_scale: f32,
+    /// The canvas size in screen pixels, which the current view is captured from
+    pub view_size: (f32, f32),
+    /// Flatten a current-view capture onto the canvas background
+    pub include_background: bool,
+    /// Export only this part of the document, in SVG units
+    pub region: Option<ExportRegion>,
+    /// The document's size in SVG units, the region a new one starts as
+    pub document_size: (f32, f32),
+    /// The background the document declares, if any
+    pub declared_background: Option<[u8; 3]>,
+    /// Flatten the export onto `declared_background`
+    pub over_declared_background: bool,
+    /// The view's horizontal stretch, when the document is stretched to the window
+    pub view_stretch: Option<f32>,
+    /// The view's rotation in degrees, which the export follows unless told not to
+    pub view_rotation: f32,
+    /// Whether the view is mirrored either way
+    pub view_mirrored: bool,
+    /// Files in the folder, which Export All exports with these settings
+    pub batch_files: usize,
+    /// Write a batch into a chosen folder rather than next to each file
+    pub batch_to_folder: bool,
+    /// What a batch does about outputs already there
+    pub batch_collision: Collision,
+    /// Pixels to the inch when sizing by resolution, kept while sizing in pixels
+    pub target_dpi: f32,
+    /// Inches each SVG unit stands for in print
+    pub unit_inches: f32,
+    /// Bytes an export may be expected to need before the dialog warns
+    pub memory_budget: u64,
+    /// An export is still being written, so another can't start yet
+    pub exporting: bool,
+    /// Export at each of `scales` times the size, named `@2x` and so on
+    pub multi_scale: bool,
+    /// The scales checked for a multi-scale export
+    pub scales: Vec<f32>,
+    /// Times its own size the last whole document was exported at, which
+    /// the next one starts at
+    pub scale: f32,
+    // Widget ids from the last frame, used to move focus along the chain
+    field_ids: HashMap<FocusField, Id>,
+    pending_focus: Option<FocusField>,
 }
 
 #[derive(Clone, PartialEq)]
@@ -16,6 +129,16 @@ pub enum ExportDialogResult {
     None,
     Export,
     Cancel,
+    /// Hide the dialog while a region is dragged out on the canvas
+    SelectRegion,
+    /// Export every file in the folder
+    ExportAll,
+}
+
+impl Default for ExportDialogState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ExportDialogState {
@@ -27,16 +150,238 @@ impl ExportDialogState {
             original_width: 800.0,
             original_height: 600.0,
             result: ExportDialogResult::None,
+            size_warning: None,
+            aspect_notice: None,
+            current_view: false,
+            view_scale: 1.0,
+            view_size: (0.0, 0.0),
+            region: None,
+            document_size: (0.0, 0.0),
+            include_background: false,
+            declared_background: None,
+            over_declared_background: false,
+            view_stretch: None,
+            view_rotation: 0.0,
+            view_mirrored: false,
+            batch_files: 0,
+            batch_to_folder: false,
+            batch_collision: Collision::default(),
+            target_dpi: 300.0,
+            unit_inches: 1.0 / 96.0,
+            memory_budget: settings::DEFAULT_EXPORT_MEMORY_MB << 20,
+            exporting: false,
+            multi_scale: false,
+            scales: vec![1.0, 2.0, 3.0],
+            scale: 1.0,
+            field_ids: HashMap::new(),
+            pending_focus: None,
         }
     }
 
     pub fn open_with_dimensions(&mut self, width: f32, height: f32) {
         self.open = true;
+        self.document_size = (width, height);
+        self.region = None;
+        (self.original_width, self.original_height) = self.exported_size(width, height);
+        let (fit_w, fit_h) = export::fit_dimensions(
+            self.original_width * self.scale,
+            self.original_height * self.scale,
+            MAX_EXPORT_DIM,
+        );
+        self.settings.width = fit_w;
+        self.settings.height = fit_h;
+        self.result = ExportDialogResult::None;
+        self.pending_focus = Some(FocusField::Width);
+    }
+
+    /// Start from the choices remembered from an earlier session.
+    pub fn restore(&mut self, remembered: &RememberedExport) {
+        self.settings.format = remembered.format();
+        self.settings.include_alpha =
+            remembered.include_alpha && self.settings.format.supports_alpha();
+        self.settings.background_color = remembered.background_color;
+        self.settings.jpeg_quality = remembered.jpeg_quality.clamp(1, 100);
+        self.settings.avif_quality = remembered.avif_quality.clamp(1, 100);
+        self.scale = remembered.usable_scale();
+    }
+
+    /// The choices to remember for the next session.
+    pub fn remembered(&self) -> RememberedExport {
+        RememberedExport {
+            format: self.settings.format.extension().to_string(),
+            scale: self.scale,
+            include_alpha: self.settings.include_alpha,
+            background_color: self.settings.background_color,
+            jpeg_quality: self.settings.jpeg_quality,
+            avif_quality: self.settings.avif_quality,
+        }
+    }
+
+    /// Note the size being exported at as a scale for the next document,
+    /// when it's the whole document sized in pixels.
+    pub fn remember_scale(&mut self) {
+        let whole = !self.current_view && self.region.is_none();
+        let sized = self.settings.format.has_dimensions() && !self.settings.is_vector_pdf();
+        if whole && sized && self.original_width > 0.0 {
+            self.scale = self.batch_scale();
+        }
+    }
+
+    /// Show the dialog again with `region` selected on the canvas.
+    pub fn reopen_with_region(&mut self, region: ExportRegion) {
+        self.region = Some(region);
+        self.current_view = false;
+        self.proportions_changed();
+        self.open = true;
+        self.result = ExportDialogResult::None;
+    }
+
+    /// Whether the settings can be exported: a region has to have an area,
+    /// an icon at least one size, and the padding has to leave room.
+    pub fn can_export(&self) -> bool {
+        let format = &self.settings.format;
+        if format.is_icon() {
+            let offered = format.icon_sizes();
+            if !self.settings.icon_sizes.iter().any(|s| offered.contains(s)) {
+                return false;
+            }
+        }
+        if self.padding_error().is_some() {
+            return false;
+        }
+        if self.offers_multi_scale() && self.multi_scale && self.scales.is_empty() {
+            return false;
+        }
+        *format == ExportFormat::Svg || self.region.is_none_or(|region| region.validate().is_ok())
+    }
+
+    /// Whether the export can be written at several scales: a render sized
+    /// in pixels, rather than a captured view, a PDF page or an icon.
+    fn offers_multi_scale(&self) -> bool {
+        let format = &self.settings.format;
+        !self.current_view && format.has_dimensions() && *format != ExportFormat::Pdf
+    }
+
+    /// The scales each output of the export is written at, smallest first;
+    /// empty for a single output at the size given.
+    pub fn export_scales(&self) -> Vec<f32> {
+        if !(self.multi_scale && self.offers_multi_scale()) {
+            return Vec::new();
+        }
+        let mut scales = self.scales.clone();
+        scales.sort_by(f32::total_cmp);
+        scales
+    }
+
+    /// Why the padding can't be used at the size being exported, if it
+    /// can't: at an icon's smallest size. A captured view isn't padded.
+    fn padding_error(&self) -> Option<String> {
+        let format = &self.settings.format;
+        if self.current_view || *format == ExportFormat::Svg {
+            return None;
+        }
+        let (width, height) = if format.is_icon() {
+            let offered = format.icon_sizes();
+            let smallest = self
+                .settings
+                .icon_sizes
+                .iter()
+                .filter(|size| offered.contains(size))
+                .min()?;
+            (*smallest, *smallest)
+        } else {
+            export::plan_size(format, self.settings.width, self.settings.height).actual
+        };
+        self.settings
+            .padding
+            .margin(width, height)
+            .err()
+            .map(|e| e.to_string())
+    }
+
+    /// Shrink the export to `size` after the size confirmation asked to:
+    /// by lowering the resolution when sizing by one.
+    pub fn adjust_to(&mut self, (width, height): (u32, u32)) {
+        if let Some(dpi) = self.settings.dpi {
+            let ratio = width as f32 / self.settings.width.max(1) as f32;
+            self.target_dpi = (dpi * ratio).floor().max(1.0);
+            self.settings.dpi = Some(self.target_dpi);
+        }
+        self.settings.width = width;
+        self.settings.height = height;
+    }
+
+    /// The exported image's size in pixels, for formats that are sized so.
+    pub fn output_size(&self) -> Option<(u32, u32)> {
+        let format = &self.settings.format;
+        if self.current_view {
+            Some(renderer::view_dimensions(self.view_size, self.view_scale))
+        } else if format.has_dimensions() && !self.settings.is_vector_pdf() {
+            Some(export::plan_size(format, self.settings.width, self.settings.height).actual)
+        } else {
+            None
+        }
+    }
+
+    /// Whether Export All applies: the view and regions belong to this file.
+    fn can_export_all(&self) -> bool {
+        self.batch_files > 1 && !self.current_view && self.region.is_none() && self.can_export()
+    }
+
+    /// Times its own size each file of a batch is exported at, as this one is.
+    pub fn batch_scale(&self) -> f32 {
+        if self.original_width > 0.0 {
+            self.settings.width as f32 / self.original_width
+        } else {
+            1.0
+        }
+    }
+
+    /// Follow the region's proportions, or the document's as it's exported
+    /// without one.
+    fn proportions_changed(&mut self) {
+        let (width, height) = match self.region.filter(|r| r.validate().is_ok()) {
+            Some(region) => (region.width, region.height),
+            None if self.region.is_some() => return,
+            None => self.exported_size(self.document_size.0, self.document_size.1),
+        };
         self.original_width = width;
         self.original_height = height;
-        self.settings.width = width as u32;
-        self.settings.height = height as u32;
-        self.result = ExportDialogResult::None;
+        if self.aspect_locked {
+            self.settings.height = (self.settings.width as f32 * height / width)
+                .round()
+                .clamp(1.0, MAX_EXPORT_DIM as f32) as u32;
+        }
+    }
+
+    /// The proportions of a `width`×`height` document exported whole:
+    /// stretched and turned as the view is, where the settings keep that.
+    fn exported_size(&self, width: f32, height: f32) -> (f32, f32) {
+        let rotation_deg = if self.settings.view_orientation {
+            self.view_rotation
+        } else {
+            0.0
+        };
+        Viewport {
+            rotation_deg,
+            stretch_x: self.applied_stretch(),
+            ..Viewport::default()
+        }
+        .rotated_size(width, height)
+    }
+
+    /// How much wider than its own proportions the document is exported.
+    fn applied_stretch(&self) -> f32 {
+        self.view_stretch
+            .filter(|_| self.settings.stretch)
+            .unwrap_or(1.0)
+    }
+
+    fn field_at(&self, id: Id) -> Option<FocusField> {
+        self.field_ids
+            .iter()
+            .find(|(_, field_id)| **field_id == id)
+            .map(|(field, _)| *field)
     }
 }
 
@@ -47,6 +392,19 @@ pub fn draw_export_dialog(ctx: &Context, state: &mut ExportDialogState) {
 
     let mut open = state.open;
 
+    // Focus is requested before the widgets are drawn so a DragValue sees it as
+    // newly gained and selects its text.
+    if let Some(field) = state.pending_focus {
+        if let Some(id) = state.field_ids.get(&field) {
+            ctx.memory_mut(|m| m.request_focus(*id));
+            state.pending_focus = None;
+        }
+    }
+    let focused_field = ctx
+        .memory(|m| m.focused())
+        .and_then(|id| state.field_at(id));
+    state.field_ids.clear();
+
     Window::new("Export Image")
         .open(&mut open)
         .resizable(false)
@@ -54,115 +412,1000 @@ pub fn draw_export_dialog(ctx: &Context, state: &mut ExportDialogState) {
         .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
         .show(ctx, |ui| {
             ui.horizontal(|ui| {
-                ui.label("Format:");
-                for fmt in ExportFormat::all() {
+                let label = ui.label("Format:");
+                let formats = ExportFormat::all();
+                let mut chosen = None;
+                for fmt in formats {
+                    let selected = state.settings.format == *fmt;
+                    let response = ui
+                        .add_enabled(
+                            fmt.is_available(),
+                            egui::Button::selectable(selected, fmt.name()),
+                        )
+                        .on_disabled_hover_text(format!("This build has no {} encoder", fmt.name()))
+                        .labelled_by(label.id);
+                    if selected {
+                        state.field_ids.insert(FocusField::Format, response.id);
+                    }
+                    if response.clicked() {
+                        chosen = Some(fmt.clone());
+                    }
+                }
+                // Left/Right cycle the format while the selector has focus
+                if focused_field == Some(FocusField::Format) {
+                    let step = ui.input(|i| {
+                        i.key_pressed(Key::ArrowRight) as isize
+                            - i.key_pressed(Key::ArrowLeft) as isize
+                    });
+                    if step != 0 {
+                        let formats: Vec<_> = formats.iter().filter(|f| f.is_available()).collect();
+                        let current = formats
+                            .iter()
+                            .position(|f| **f == state.settings.format)
+                            .unwrap_or(0) as isize;
+                        let len = formats.len() as isize;
+                        chosen = Some(formats[((current + step + len) % len) as usize].clone());
+                        state.pending_focus = Some(FocusField::Format);
+                    }
+                }
+                if let Some(fmt) = chosen {
+                    // Reset alpha if format doesn't support it
+                    if !fmt.supports_alpha() {
+                        state.settings.include_alpha = false;
+                    }
+                    // Nor can the view be captured when the size isn't chosen
+                    let vector = fmt == ExportFormat::Pdf && state.settings.pdf_vector;
+                    if !fmt.has_dimensions() || vector {
+                        state.current_view = false;
+                    }
+                    state.settings.format = fmt;
+                }
+            });
+
+            // An SVG is the document itself, so the rendering options are greyed out
+            let svg = state.settings.format == ExportFormat::Svg;
+            let sized = state.settings.format.has_dimensions() && !state.settings.is_vector_pdf();
+            if svg {
+                draw_svg_options(ui, state);
+            }
+
+            ui.add_space(5.0);
+
+            ui.add_enabled(
+                sized,
+                egui::Checkbox::new(&mut state.current_view, "Export current view"),
+            )
+            .on_hover_text(
+                "Capture what the canvas shows, with the current pan, zoom, rotation \
+                 and mirroring",
+            );
+            ui.add_enabled_ui(state.current_view, |ui| {
+                ui.horizontal(|ui| {
+                    let label = ui.label("View scale:");
+                    ui.add(
+                        egui::DragValue::new(&mut state.view_scale)
+                            .range(0.25..=16.0)
+                            .speed(0.05)
+                            .suffix("\u{00D7}"),
+                    )
+                    .labelled_by(label.id)
+                    .on_hover_text("Times the screen's resolution");
+                    let (w, h) = renderer::view_dimensions(state.view_size, state.view_scale);
+                    if w > MAX_EXPORT_DIM || h > MAX_EXPORT_DIM {
+                        ui.colored_label(
+                            ui.visuals().error_fg_color,
+                            format!("{w}\u{00D7}{h}: over the {MAX_EXPORT_DIM}-pixel limit"),
+                        );
+                    } else {
+                        ui.weak(format!("{w}\u{00D7}{h} pixels"));
+                    }
+                });
+            });
+            ui.add_enabled(
+                state.current_view,
+                egui::Checkbox::new(&mut state.include_background, "Include background"),
+            )
+            .on_hover_text("Flatten onto the canvas background or checkerboard");
+
+            ui.add_enabled_ui(!state.current_view && !svg, |ui| {
+                let before = state.region;
+                ui.horizontal(|ui| {
+                    let mut cropped = state.region.is_some();
+                    if ui
+                        .checkbox(&mut cropped, "Region")
+                        .on_hover_text("Export only part of the document")
+                        .changed()
+                    {
+                        let (width, height) = state.document_size;
+                        state.region = cropped.then_some(ExportRegion {
+                            x: 0.0,
+                            y: 0.0,
+                            width,
+                            height,
+                        });
+                    }
                     if ui
-                        .selectable_label(state.settings.format == *fmt, fmt.name())
+                        .button("Select region\u{2026}")
+                        .on_hover_text("Drag a rectangle on the canvas")
                         .clicked()
                     {
-                        state.settings.format = fmt.clone();
-                        // Reset alpha if format doesn't support it
-                        if !fmt.supports_alpha() {
-                            state.settings.include_alpha = false;
+                        state.result = ExportDialogResult::SelectRegion;
+                        state.open = false;
+                    }
+                });
+                if let Some(region) = &mut state.region {
+                    ui.horizontal(|ui| {
+                        for (name, value) in [
+                            ("X:", &mut region.x),
+                            ("Y:", &mut region.y),
+                            ("W:", &mut region.width),
+                            ("H:", &mut region.height),
+                        ] {
+                            let label = ui.label(name);
+                            ui.add(egui::DragValue::new(value).speed(1.0).max_decimals(2))
+                                .labelled_by(label.id);
                         }
+                    })
+                    .response
+                    .on_hover_text("In SVG units");
+                    if let Err(e) = region.validate() {
+                        ui.colored_label(ui.visuals().error_fg_color, e.to_string());
                     }
                 }
+                if state.region != before {
+                    state.proportions_changed();
+                }
             });
 
             ui.add_space(5.0);
 
-            // Dimensions
-            ui.horizontal(|ui| {
-                ui.label("Width:");
-                let old_w = state.settings.width;
-                let w_response =
-                    ui.add(egui::DragValue::new(&mut state.settings.width).range(1..=8192));
-                if w_response.changed() && state.aspect_locked && old_w > 0 {
-                    let ratio = state.original_height / state.original_width;
-                    state.settings.height = (state.settings.width as f32 * ratio).round() as u32;
-                }
-
-                ui.label("Height:");
-                let old_h = state.settings.height;
-                let h_response =
-                    ui.add(egui::DragValue::new(&mut state.settings.height).range(1..=8192));
-                if h_response.changed() && state.aspect_locked && old_h > 0 {
-                    let ratio = state.original_width / state.original_height;
-                    state.settings.width = (state.settings.height as f32 * ratio).round() as u32;
-                }
-
-                let lock_label = if state.aspect_locked {
-                    "\u{1F512}"
+            // Dimensions come from the view when capturing it
+            ui.add_enabled_ui(!state.current_view && !svg, |ui| {
+                if state.settings.format == ExportFormat::Pdf {
+                    draw_pdf_resolution(ui, state);
+                } else if state.settings.format.is_icon() {
+                    draw_icon_sizes(ui, state);
                 } else {
-                    "\u{1F513}"
-                };
-                if ui
-                    .button(lock_label)
-                    .on_hover_text("Lock aspect ratio")
-                    .clicked()
-                {
-                    state.aspect_locked = !state.aspect_locked;
-                }
-            });
+                    draw_size_mode(ui, state);
+                    if state.settings.dpi.is_some() {
+                        draw_target_dpi(ui, state);
+                    } else {
+                        ui.horizontal(|ui| {
+                            let label = ui.label("Width:");
+                            let old_w = state.settings.width;
+                            let w_response = ui
+                                .add(
+                                    egui::DragValue::new(&mut state.settings.width)
+                                        .range(1..=MAX_EXPORT_DIM),
+                                )
+                                .labelled_by(label.id);
+                            state.field_ids.insert(FocusField::Width, w_response.id);
+                            if w_response.changed() && state.aspect_locked && old_w > 0 {
+                                let ratio = state.original_height / state.original_width;
+                                state.settings.height =
+                                    (state.settings.width as f32 * ratio).round() as u32;
+                            }
 
-            // Scale presets
-            ui.horizontal(|ui| {
-                ui.label("Scale:");
-                for (label, scale) in [("1x", 1.0f32), ("2x", 2.0), ("4x", 4.0)] {
-                    if ui.button(label).clicked() {
-                        state.settings.width = (state.original_width * scale).round() as u32;
-                        state.settings.height = (state.original_height * scale).round() as u32;
+                            let label = ui.label("Height:");
+                            let old_h = state.settings.height;
+                            let h_response = ui
+                                .add(
+                                    egui::DragValue::new(&mut state.settings.height)
+                                        .range(1..=MAX_EXPORT_DIM),
+                                )
+                                .labelled_by(label.id);
+                            state.field_ids.insert(FocusField::Height, h_response.id);
+                            if h_response.changed() && state.aspect_locked && old_h > 0 {
+                                let ratio = state.original_width / state.original_height;
+                                state.settings.width =
+                                    (state.settings.height as f32 * ratio).round() as u32;
+                            }
+
+                            let lock_label = if state.aspect_locked {
+                                "\u{1F512}"
+                            } else {
+                                "\u{1F513}"
+                            };
+                            if ui
+                                .button(lock_label)
+                                .on_hover_text("Lock aspect ratio")
+                                .clicked()
+                            {
+                                state.aspect_locked = !state.aspect_locked;
+                            }
+
+                            if let Some(ref warning) = state.size_warning {
+                                ui.colored_label(ui.visuals().warn_fg_color, "\u{26A0}")
+                                    .on_hover_text(warning);
+                            }
+                            if let Some(ref notice) = state.aspect_notice {
+                                ui.colored_label(ui.visuals().warn_fg_color, "\u{2194}")
+                                    .on_hover_text(notice);
+                            }
+                        });
+                    }
+
+                    if state.view_stretch.is_some()
+                        && ui
+                            .checkbox(&mut state.settings.stretch, "Stretch as shown")
+                            .on_hover_text(
+                                "Keep the stretch to the window instead of the document's \
+                                 proportions",
+                            )
+                            .changed()
+                    {
+                        state.proportions_changed();
                     }
                 }
+
+                let plan = export::plan_size(
+                    &state.settings.format,
+                    state.settings.width,
+                    state.settings.height,
+                );
+                if plan.is_reduced() && !state.current_view && sized {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!(
+                            "\u{26A0} Will export at {}×{}: {}",
+                            plan.actual.0,
+                            plan.actual.1,
+                            plan.reasons.join("; ")
+                        ),
+                    );
+                }
+                let (w, h) = plan.actual;
+                let memory = export::estimated_memory(&state.settings.format, w, h);
+                if memory > state.memory_budget && !state.current_view && sized {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!(
+                            "\u{26A0} Needs about {} of memory, over the {} budget",
+                            svg_document::format_size(memory),
+                            svg_document::format_size(state.memory_budget)
+                        ),
+                    );
+                }
+
+                // Scale presets; a PDF's size comes from its resolution
+                let by_resolution = state.settings.format == ExportFormat::Pdf
+                    || state.settings.format.is_icon()
+                    || state.settings.dpi.is_some();
+                if !by_resolution {
+                    ui.horizontal(|ui| {
+                        let scale_label = ui.label("Scale:");
+                        for (label, scale) in [("1x", 1.0f32), ("2x", 2.0), ("4x", 4.0)] {
+                            let response = ui.button(label).labelled_by(scale_label.id);
+                            if scale == 1.0 {
+                                state.field_ids.insert(FocusField::Scale, response.id);
+                            }
+                            if response.clicked() {
+                                let (w, h) = export::fit_dimensions(
+                                    state.original_width * scale,
+                                    state.original_height * scale,
+                                    MAX_EXPORT_DIM,
+                                );
+                                state.settings.width = w;
+                                state.settings.height = h;
+                            }
+                        }
+                    });
+                }
+                if state.offers_multi_scale() {
+                    draw_multi_scale(ui, state);
+                }
+                if !state.settings.is_vector_pdf() {
+                    draw_padding(ui, state);
+                }
             });
 
             ui.add_space(5.0);
 
             // Alpha / background options
-            if state.settings.format.supports_alpha() {
-                ui.checkbox(&mut state.settings.include_alpha, "Transparent background");
-            }
+            ui.add_enabled_ui(!svg, |ui| {
+                if state.settings.format.supports_alpha() {
+                    ui.checkbox(&mut state.settings.include_alpha, "Transparent background");
+                }
 
-            if !state.settings.include_alpha || !state.settings.format.supports_alpha() {
-                ui.horizontal(|ui| {
-                    ui.label("Background:");
-                    let mut color = egui::Color32::from_rgb(
-                        state.settings.background_color[0],
-                        state.settings.background_color[1],
-                        state.settings.background_color[2],
-                    );
-                    if ui.color_edit_button_srgba(&mut color).changed() {
-                        state.settings.background_color = [color.r(), color.g(), color.b()];
-                    }
+                if !state.settings.include_alpha || !state.settings.format.supports_alpha() {
+                    ui.horizontal(|ui| {
+                        ui.label("Background:");
+                        let mut color = egui::Color32::from_rgb(
+                            state.settings.background_color[0],
+                            state.settings.background_color[1],
+                            state.settings.background_color[2],
+                        );
+                        if ui.color_edit_button_srgba(&mut color).changed() {
+                            state.settings.background_color = [color.r(), color.g(), color.b()];
+                        }
+                    });
+                }
+
+                if let Some([r, g, b]) = state.declared_background {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(
+                            &mut state.over_declared_background,
+                            "Over document background",
+                        )
+                        .on_hover_text("Flatten onto the background color the document declares");
+                        let (rect, _) =
+                            ui.allocate_exact_size(egui::vec2(14.0, 14.0), egui::Sense::hover());
+                        ui.painter()
+                            .rect_filled(rect, 2.0, egui::Color32::from_rgb(r, g, b));
+                    });
+                }
+
+                // Effects work on pixels, which a vector PDF doesn't have
+                ui.add_enabled_ui(!state.settings.is_vector_pdf(), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Effects:");
+                        ui.checkbox(&mut state.settings.effects.grayscale, "Grayscale");
+                        ui.checkbox(&mut state.settings.effects.invert, "Invert colors");
+                    });
                 });
-            }
+            });
 
             // JPEG quality
             if state.settings.format == ExportFormat::Jpeg {
                 ui.horizontal(|ui| {
-                    ui.label("Quality:");
+                    let label = ui.label("Quality:");
                     let mut quality = state.settings.jpeg_quality as i32;
-                    ui.add(egui::Slider::new(&mut quality, 1..=100));
+                    let response = ui
+                        .add(egui::Slider::new(&mut quality, 1..=100))
+                        .labelled_by(label.id);
+                    state.field_ids.insert(FocusField::Quality, response.id);
                     state.settings.jpeg_quality = quality as u8;
                 });
+                ui.horizontal(|ui| {
+                    ui.label("Chroma:");
+                    for subsampling in ChromaSubsampling::all() {
+                        ui.radio_value(
+                            &mut state.settings.jpeg_subsampling,
+                            *subsampling,
+                            subsampling.name(),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text("4:4:4 keeps coloured edges sharp; 4:2:0 makes smaller files");
+                ui.checkbox(&mut state.settings.jpeg_progressive, "Progressive")
+                    .on_hover_text("Shows a rough image early while loading over a network");
+            }
+            if state.settings.format == ExportFormat::Png {
+                ui.checkbox(&mut state.settings.png_16bit, "16-bit")
+                    .on_hover_text("16 bits a channel, for compositing and other editing");
+            }
+
+            if state.settings.format == ExportFormat::Avif {
+                ui.horizontal(|ui| {
+                    let label = ui.label("Quality:");
+                    let mut quality = state.settings.avif_quality as i32;
+                    let response = ui
+                        .add(egui::Slider::new(&mut quality, 1..=100))
+                        .labelled_by(label.id);
+                    state.field_ids.insert(FocusField::Quality, response.id);
+                    state.settings.avif_quality = quality as u8;
+                });
+            }
+
+            if state.view_rotation != 0.0 || state.view_mirrored {
+                ui.add_enabled_ui(
+                    !state.current_view && state.region.is_none() && !svg,
+                    |ui| {
+                        if ui
+                            .checkbox(
+                                &mut state.settings.view_orientation,
+                                "Export with current rotation/mirror",
+                            )
+                            .on_hover_text("Otherwise the document is exported upright")
+                            .changed()
+                        {
+                            state.proportions_changed();
+                        }
+                    },
+                );
+            }
+
+            if state.settings.format.supports_orientation_tag() {
+                ui.add_enabled_ui(state.settings.view_orientation, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Rotation:");
+                        for mode in OrientationMode::all() {
+                            ui.radio_value(&mut state.settings.orientation, *mode, mode.name());
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Metadata keeps the pixels unrotated and records quarter turns and \
+                         mirroring in an EXIF Orientation tag",
+                    );
+                });
+            }
+
+            if state.settings.format.supports_metadata() {
+                ui.checkbox(&mut state.settings.metadata, "Embed metadata")
+                    .on_hover_text(
+                        "Record the title, this program, the source file and the time. \
+                         Without it, exporting the same view again gives the same bytes",
+                    );
+            }
+
+            if state.batch_files > 1 {
+                ui.add_space(5.0);
+                ui.add_enabled_ui(!state.current_view && state.region.is_none(), |ui| {
+                    draw_batch_options(ui, state);
+                });
             }
 
             ui.add_space(10.0);
 
             // Buttons
             ui.horizontal(|ui| {
-                if ui.button("Export").clicked() {
+                let mut export = ui
+                    .add_enabled(
+                        state.can_export() && !state.exporting,
+                        egui::Button::new("Export"),
+                    )
+                    .on_hover_text("Export (Enter)");
+                if state.exporting {
+                    export =
+                        export.on_disabled_hover_text("The last export is still being written");
+                }
+                state.field_ids.insert(FocusField::ExportButton, export.id);
+                if export.clicked() {
                     state.result = ExportDialogResult::Export;
                     state.open = false;
                 }
-                if ui.button("Cancel").clicked() {
+                if state.batch_files > 1
+                    && ui
+                        .add_enabled(
+                            state.can_export_all(),
+                            egui::Button::new(format!("Export All ({})", state.batch_files)),
+                        )
+                        .on_hover_text("Export every file in the folder with these settings")
+                        .clicked()
+                {
+                    state.result = ExportDialogResult::ExportAll;
+                    state.open = false;
+                }
+                if ui.button("Cancel").on_hover_text("Cancel (Esc)").clicked() {
                     state.result = ExportDialogResult::Cancel;
                     state.open = false;
                 }
             });
         });
 
+    // Keyboard workflow. Widgets have already handled this frame's input, so a
+    // DragValue being edited has committed its value by now.
+    if state.open {
+        let (tab, shift, enter, escape) = ctx.input(|i| {
+            (
+                i.key_pressed(Key::Tab),
+                i.modifiers.shift,
+                i.key_pressed(Key::Enter),
+                i.key_pressed(Key::Escape),
+            )
+        });
+        if escape {
+            state.result = ExportDialogResult::Cancel;
+            state.open = false;
+        } else if enter && state.can_export() && !state.exporting {
+            state.result = ExportDialogResult::Export;
+            state.open = false;
+        } else if tab {
+            let chain = focus_chain(&state.settings);
+            state.pending_focus = next_focus(&chain, focused_field, shift);
+        }
+    }
+
     if !open {
         state.open = false;
     }
 }
+
+/// What an SVG export writes, and whether it's compressed.
+fn draw_svg_options(ui: &mut egui::Ui, state: &mut ExportDialogState) {
+    ui.checkbox(&mut state.settings.svg_original, "Original source")
+        .on_hover_text(
+            "Write the file as it was read instead of simplified, with <use> expanded, \
+             styles resolved and text as paths",
+        );
+    ui.checkbox(&mut state.settings.svgz, "Compressed (.svgz)");
+}
+
+/// Where Export All writes, and what it does about files already there.
+fn draw_batch_options(ui: &mut egui::Ui, state: &mut ExportDialogState) {
+    ui.horizontal(|ui| {
+        ui.label("Export all to:");
+        ui.radio_value(&mut state.batch_to_folder, false, "Next to each file");
+        ui.radio_value(&mut state.batch_to_folder, true, "A folder\u{2026}");
+    })
+    .response
+    .on_hover_text("Each file keeps its name, at the same scale as this one");
+    ui.horizontal(|ui| {
+        ui.label("If a file exists:");
+        for collision in Collision::all() {
+            ui.radio_value(&mut state.batch_collision, *collision, collision.name());
+        }
+    });
+}
+
+/// Which sizes an icon holds, in place of the width and height.
+fn draw_icon_sizes(ui: &mut egui::Ui, state: &mut ExportDialogState) {
+    let offered = state.settings.format.icon_sizes();
+    let sizes = &mut state.settings.icon_sizes;
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Sizes:");
+        for &size in &offered {
+            let mut checked = sizes.contains(&size);
+            if ui.checkbox(&mut checked, size.to_string()).changed() {
+                if checked {
+                    sizes.push(size);
+                    sizes.sort_unstable();
+                } else {
+                    sizes.retain(|s| *s != size);
+                }
+            }
+        }
+    })
+    .response
+    .on_hover_text("Square images in pixels; a document that isn't square is centered");
+    if !offered.iter().any(|size| sizes.contains(size)) {
+        ui.colored_label(ui.visuals().error_fg_color, "Choose at least one size");
+    }
+}
+
+/// Whether the size is entered in pixels or worked out from a resolution.
+fn draw_size_mode(ui: &mut egui::Ui, state: &mut ExportDialogState) {
+    ui.horizontal(|ui| {
+        ui.label("Size in:");
+        let mut by_dpi = state.settings.dpi.is_some();
+        ui.radio_value(&mut by_dpi, false, "Pixels");
+        ui.radio_value(&mut by_dpi, true, "DPI")
+            .on_hover_text("Size for print, with the resolution recorded in PNG and JPEG files");
+        state.settings.dpi = by_dpi.then_some(state.target_dpi);
+    });
+}
+
+/// Which scales to write the export at, and the size each comes out.
+fn draw_multi_scale(ui: &mut egui::Ui, state: &mut ExportDialogState) {
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut state.multi_scale, "Export multiple scales")
+            .on_hover_text("Write each checked scale, named like logo@2x.png");
+        ui.add_enabled_ui(state.multi_scale, |ui| {
+            for scale in export::MULTI_SCALES {
+                let mut checked = state.scales.contains(&scale);
+                if ui.checkbox(&mut checked, format!("{scale}x")).changed() {
+                    if checked {
+                        state.scales.push(scale);
+                    } else {
+                        state.scales.retain(|s| *s != scale);
+                    }
+                }
+            }
+        });
+    });
+    if !state.multi_scale {
+        return;
+    }
+    let scales = state.export_scales();
+    if scales.is_empty() {
+        ui.colored_label(ui.visuals().error_fg_color, "Choose at least one scale");
+        return;
+    }
+    let sizes: Vec<String> = scales
+        .iter()
+        .map(|scale| {
+            let scaled = export::scaled_settings(&state.settings, *scale);
+            format!("{}\u{00D7}{}", scaled.width, scaled.height)
+        })
+        .collect();
+    ui.weak(format!("Sizes: {}", sizes.join(", ")));
+}
+
+/// Room around the drawing, in pixels or as a percentage of the shorter side.
+fn draw_padding(ui: &mut egui::Ui, state: &mut ExportDialogState) {
+    ui.horizontal(|ui| {
+        let label = ui.label("Padding:");
+        let mut amount = state.settings.padding.amount();
+        let mut percent = matches!(state.settings.padding, Padding::Percent(_));
+        let (range, speed) = if percent {
+            (0.0..=49.0, 0.5)
+        } else {
+            (0.0..=MAX_EXPORT_DIM as f32, 1.0)
+        };
+        ui.add(egui::DragValue::new(&mut amount).range(range).speed(speed))
+            .labelled_by(label.id)
+            .on_hover_text("Space around the drawing, which is shrunk to fit inside it");
+        ui.radio_value(&mut percent, false, "px");
+        ui.radio_value(&mut percent, true, "%")
+            .on_hover_text("Of the shorter side");
+        state.settings.padding = if percent {
+            Padding::Percent(amount)
+        } else {
+            Padding::Pixels(amount)
+        };
+    });
+    if let Some(error) = state.padding_error() {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+}
+
+/// The resolution to export at, and the pixel size it gives the document's
+/// printed size.
+fn draw_target_dpi(ui: &mut egui::Ui, state: &mut ExportDialogState) {
+    ui.horizontal(|ui| {
+        let label = ui.label("Resolution:");
+        let response = ui
+            .add(
+                egui::DragValue::new(&mut state.target_dpi)
+                    .range(36.0..=2400.0)
+                    .speed(1.0)
+                    .suffix(" DPI"),
+            )
+            .labelled_by(label.id);
+        state.field_ids.insert(FocusField::Dpi, response.id);
+    });
+    let inches_w = state.original_width * state.unit_inches;
+    let inches_h = state.original_height * state.unit_inches;
+    let dpi = state.target_dpi;
+    state.settings.dpi = Some(dpi);
+    state.settings.width = (inches_w * dpi).round().max(1.0) as u32;
+    state.settings.height = (inches_h * dpi).round().max(1.0) as u32;
+    ui.weak(format!(
+        "{}\u{00D7}{} px for {inches_w:.2}\u{00D7}{inches_h:.2} in",
+        state.settings.width, state.settings.height
+    ));
+}
+
+/// Whether a PDF export is drawn from the vectors, and otherwise the
+/// resolution that sizes its pixels so the page is the document's print size.
+fn draw_pdf_resolution(ui: &mut egui::Ui, state: &mut ExportDialogState) {
+    ui.checkbox(&mut state.settings.pdf_vector, "Vector")
+        .on_hover_text("Keep paths and text as vectors; otherwise the page holds a render");
+    if state.settings.pdf_vector {
+        // The document as written, whatever the view's turn or stretch
+        let (width, height) = state
+            .region
+            .map_or(state.document_size, |region| (region.width, region.height));
+        ui.weak(format!(
+            "{:.2}\u{00D7}{:.2} in page",
+            width * state.unit_inches,
+            height * state.unit_inches
+        ));
+        return;
+    }
+    let inches_w = state.original_width * state.unit_inches;
+    let inches_h = state.original_height * state.unit_inches;
+    ui.horizontal(|ui| {
+        let label = ui.label("Resolution:");
+        let response = ui
+            .add(
+                egui::DragValue::new(&mut state.settings.pdf_dpi)
+                    .range(36.0..=1200.0)
+                    .speed(1.0)
+                    .suffix(" DPI"),
+            )
+            .labelled_by(label.id);
+        state.field_ids.insert(FocusField::Dpi, response.id);
+    });
+    let dpi = state.settings.pdf_dpi;
+    state.settings.width = (inches_w * dpi).round().max(1.0) as u32;
+    state.settings.height = (inches_h * dpi).round().max(1.0) as u32;
+    ui.weak(format!(
+        "{inches_w:.2}\u{00D7}{inches_h:.2} in page, {}\u{00D7}{} px",
+        state.settings.width, state.settings.height
+    ));
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SizeConfirmChoice {
+    Proceed,
+    Adjust,
+    Cancel,
+}
+
+/// Ask whether to go ahead with an export whose size had to be reduced.
+/// Returns `None` while the user hasn't decided.
+pub fn draw_size_confirm(ctx: &Context, plan: &SizePlan) -> Option<SizeConfirmChoice> {
+    let mut choice = None;
+    Window::new("Export Size Reduced")
+        .resizable(false)
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "Requested {}×{}, but the image will be exported at {}×{}.",
+                plan.requested.0, plan.requested.1, plan.actual.0, plan.actual.1
+            ));
+            for reason in &plan.reasons {
+                ui.label(format!("\u{2022} {reason}"));
+            }
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                if ui.button("Export Anyway").on_hover_text("Enter").clicked() {
+                    choice = Some(SizeConfirmChoice::Proceed);
+                }
+                if ui
+                    .button("Adjust Size")
+                    .on_hover_text("Return to the export dialog with the reduced size")
+                    .clicked()
+                {
+                    choice = Some(SizeConfirmChoice::Adjust);
+                }
+                if ui.button("Cancel").on_hover_text("Esc").clicked() {
+                    choice = Some(SizeConfirmChoice::Cancel);
+                }
+            });
+        });
+
+    if choice.is_none() {
+        ctx.input(|i| {
+            if i.key_pressed(Key::Escape) {
+                choice = Some(SizeConfirmChoice::Cancel);
+            } else if i.key_pressed(Key::Enter) {
+                choice = Some(SizeConfirmChoice::Proceed);
+            }
+        });
+    }
+    choice
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_with_huge_dimensions() {
+        let mut state = ExportDialogState::new();
+        state.open_with_dimensions(2_000_000.0, 1_000_000.0);
+        assert_eq!(
+            (state.settings.width, state.settings.height),
+            (MAX_EXPORT_DIM, MAX_EXPORT_DIM / 2)
+        );
+        state.open_with_dimensions(1.0, 100000.0);
+        assert_eq!(
+            (state.settings.width, state.settings.height),
+            (1, MAX_EXPORT_DIM)
+        );
+    }
+
+    #[test]
+    fn test_quarter_turned_view_swaps_dimensions() {
+        let mut state = ExportDialogState::new();
+        state.view_rotation = 90.0;
+        state.open_with_dimensions(200.0, 100.0);
+        assert_eq!((state.settings.width, state.settings.height), (100, 200));
+
+        state.settings.view_orientation = false;
+        state.proportions_changed();
+        assert_eq!((state.settings.width, state.settings.height), (100, 50));
+    }
+
+    #[test]
+    fn test_region_sets_the_proportions() {
+        let mut state = ExportDialogState::new();
+        state.open_with_dimensions(100.0, 100.0);
+        assert_eq!((state.settings.width, state.settings.height), (100, 100));
+        state.open = false;
+
+        state.reopen_with_region(ExportRegion::new(10.0, 10.0, 40.0, 20.0).unwrap());
+        assert!(state.open && state.can_export());
+        assert_eq!((state.settings.width, state.settings.height), (100, 50));
+
+        // An empty region can't be exported, and leaves the size alone
+        state.region = Some(ExportRegion {
+            width: 0.0,
+            ..state.region.unwrap()
+        });
+        state.proportions_changed();
+        assert!(!state.can_export());
+        assert_eq!((state.settings.width, state.settings.height), (100, 50));
+
+        state.region = None;
+        state.proportions_changed();
+        assert_eq!((state.settings.width, state.settings.height), (100, 100));
+    }
+
+    #[test]
+    fn test_remembered_scale_and_choices() {
+        let mut state = ExportDialogState::new();
+        state.open_with_dimensions(100.0, 50.0);
+        state.settings.width = 300;
+        state.settings.height = 150;
+        state.settings.format = ExportFormat::Jpeg;
+        state.settings.jpeg_quality = 60;
+        state.settings.avif_quality = 45;
+        state.remember_scale();
+        // The next document starts at the same multiple of its own size
+        state.open_with_dimensions(40.0, 40.0);
+        assert_eq!((state.settings.width, state.settings.height), (120, 120));
+
+        let remembered = state.remembered();
+        let mut restored = ExportDialogState::new();
+        restored.restore(&remembered);
+        assert_eq!(restored.settings.format, ExportFormat::Jpeg);
+        assert_eq!(restored.settings.jpeg_quality, 60);
+        assert_eq!(restored.settings.avif_quality, 45);
+        assert!(!restored.settings.include_alpha);
+        restored.open_with_dimensions(10.0, 20.0);
+        assert_eq!(
+            (restored.settings.width, restored.settings.height),
+            (30, 60)
+        );
+
+        // A region's size isn't a scale of the document
+        restored.reopen_with_region(ExportRegion::new(0.0, 0.0, 5.0, 5.0).unwrap());
+        restored.settings.width = 50;
+        restored.remember_scale();
+        assert_eq!(restored.scale, 3.0);
+    }
+
+    #[test]
+    fn test_export_scales() {
+        let mut state = ExportDialogState::new();
+        assert!(state.export_scales().is_empty());
+        state.multi_scale = true;
+        state.scales = vec![3.0, 1.0, 1.5];
+        assert_eq!(state.export_scales(), [1.0, 1.5, 3.0]);
+
+        state.scales.clear();
+        assert!(!state.can_export());
+        // Icons are already several sizes
+        state.settings.format = ExportFormat::Ico;
+        assert!(state.export_scales().is_empty());
+        assert!(state.can_export());
+    }
+
+    #[test]
+    fn test_padding_has_to_leave_room() {
+        let mut state = ExportDialogState::new();
+        state.open_with_dimensions(100.0, 50.0);
+        state.settings.padding = Padding::Pixels(24.0);
+        assert!(state.can_export());
+        state.settings.padding = Padding::Pixels(25.0);
+        assert!(!state.can_export());
+
+        // At the smallest of an icon's sizes
+        state.settings.format = ExportFormat::Ico;
+        state.settings.padding = Padding::Pixels(4.0);
+        assert!(state.can_export());
+        state.settings.padding = Padding::Pixels(8.0);
+        assert!(!state.can_export());
+    }
+
+    #[test]
+    fn test_focus_chain_order() {
+        let settings = ExportSettings::default();
+        assert_eq!(
+            focus_chain(&settings),
+            vec![
+                FocusField::Format,
+                FocusField::Width,
+                FocusField::Height,
+                FocusField::Scale,
+                FocusField::ExportButton,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_focus_chain_includes_quality_for_jpeg() {
+        let settings = ExportSettings {
+            format: ExportFormat::Jpeg,
+            ..Default::default()
+        };
+        let chain = focus_chain(&settings);
+        let scale = chain.iter().position(|f| *f == FocusField::Scale).unwrap();
+        assert_eq!(chain[scale + 1], FocusField::Quality);
+        assert_eq!(chain.last(), Some(&FocusField::ExportButton));
+    }
+
+    #[test]
+    fn test_focus_chain_for_pdf_has_only_the_resolution() {
+        let settings = ExportSettings {
+            format: ExportFormat::Pdf,
+            pdf_vector: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            focus_chain(&settings),
+            vec![
+                FocusField::Format,
+                FocusField::Dpi,
+                FocusField::ExportButton
+            ]
+        );
+        // A vector page is the document's size, with nothing to set
+        let settings = ExportSettings {
+            pdf_vector: true,
+            ..settings
+        };
+        assert_eq!(
+            focus_chain(&settings),
+            vec![FocusField::Format, FocusField::ExportButton]
+        );
+    }
+
+    #[test]
+    fn test_focus_chain_for_svg_skips_the_raster_options() {
+        let settings = ExportSettings {
+            format: ExportFormat::Svg,
+            ..Default::default()
+        };
+        assert_eq!(
+            focus_chain(&settings),
+            [FocusField::Format, FocusField::ExportButton]
+        );
+    }
+
+    #[test]
+    fn test_icon_needs_a_size_it_can_hold() {
+        let mut state = ExportDialogState::new();
+        state.settings.format = ExportFormat::Icns;
+        // 16 and 32 of the defaults have ICNS types
+        assert!(state.can_export());
+        state.settings.icon_sizes = vec![48];
+        assert!(!state.can_export());
+        state.settings.format = ExportFormat::Ico;
+        assert!(state.can_export());
+        assert_eq!(
+            focus_chain(&state.settings),
+            [FocusField::Format, FocusField::ExportButton]
+        );
+    }
+
+    #[test]
+    fn test_dpi_mode_focus_and_adjust() {
+        let mut state = ExportDialogState::new();
+        state.settings.dpi = Some(300.0);
+        state.settings.width = 3000;
+        assert_eq!(
+            focus_chain(&state.settings),
+            [
+                FocusField::Format,
+                FocusField::Dpi,
+                FocusField::ExportButton
+            ]
+        );
+        // Asked to fit a smaller size, the resolution drops with it
+        state.adjust_to((1500, 1000));
+        assert_eq!(state.target_dpi, 150.0);
+        assert_eq!(state.settings.dpi, Some(150.0));
+        assert_eq!(state.settings.width, 1500);
+    }
+
+    #[test]
+    fn test_next_focus_forward_and_back() {
+        let chain = focus_chain(&ExportSettings::default());
+        let width = Some(FocusField::Width);
+        assert_eq!(next_focus(&chain, width, false), Some(FocusField::Height));
+        assert_eq!(next_focus(&chain, width, true), Some(FocusField::Format));
+    }
+
+    #[test]
+    fn test_next_focus_wraps() {
+        let chain = focus_chain(&ExportSettings::default());
+        let last = Some(FocusField::ExportButton);
+        assert_eq!(next_focus(&chain, last, false), Some(FocusField::Format));
+        let first = Some(FocusField::Format);
+        assert_eq!(
+            next_focus(&chain, first, true),
+            Some(FocusField::ExportButton)
+        );
+    }
+
+    #[test]
+    fn test_next_focus_from_outside_chain() {
+        let chain = focus_chain(&ExportSettings::default());
+        assert_eq!(next_focus(&chain, None, false), Some(FocusField::Format));
+        assert_eq!(
+            next_focus(&chain, None, true),
+            Some(FocusField::ExportButton)
+        );
+        // Quality isn't in the PNG chain
+        let quality = Some(FocusField::Quality);
+        assert_eq!(next_focus(&chain, quality, false), Some(FocusField::Format));
+        assert_eq!(next_focus(&[], None, false), None);
+    }
+}
@@ -0,0 +1,140 @@
+use std::path::{Path, PathBuf};
+
+use egui::{Context, Window};
+
+use crate::folder_info::{FolderScan, FolderStats};
+use crate::svg_document::format_size;
+
+#[derive(Default)]
+pub struct FolderSummaryState {
+    pub open: bool,
+    /// Files waiting for the next frame to start scanning
+    requested: Option<Vec<PathBuf>>,
+    scan: Option<FolderScan>,
+    stats: Option<FolderStats>,
+    /// Files scanned and total when the last scan was cancelled
+    cancelled: Option<(usize, usize)>,
+}
+
+impl FolderSummaryState {
+    /// Open the window and summarize `files`, replacing any earlier summary.
+    pub fn request(&mut self, files: Vec<PathBuf>) {
+        self.open = true;
+        self.requested = Some(files);
+    }
+
+    /// Stop a running scan. The window stays open, saying how far it got.
+    pub fn cancel(&mut self) {
+        if let Some(scan) = self.scan.take() {
+            self.cancelled = Some((scan.done(), scan.total()));
+        }
+    }
+
+    fn poll(&mut self, ctx: &Context) {
+        if let Some(files) = self.requested.take() {
+            self.stats = None;
+            self.cancelled = None;
+            let ctx = ctx.clone();
+            self.scan = Some(FolderScan::start(files, move || ctx.request_repaint()));
+        }
+        if let Some(stats) = self.scan.as_ref().and_then(|scan| scan.finished()) {
+            self.stats = Some(stats);
+            self.scan = None;
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Draw the Folder summary window. Returns an unparseable file the user clicked,
+/// to be opened.
+pub fn draw_folder_summary(ctx: &Context, state: &mut FolderSummaryState) -> Option<PathBuf> {
+    if !state.open {
+        return None;
+    }
+    state.poll(ctx);
+
+    let mut open = state.open;
+    let mut clicked = None;
+    let mut cancel = false;
+
+    Window::new("Folder summary")
+        .open(&mut open)
+        .default_size([360.0, 320.0])
+        .show(ctx, |ui| {
+            if let Some(scan) = &state.scan {
+                let (done, total) = (scan.done(), scan.total());
+                ui.label(format!("Scanning {done} of {total} files\u{2026}"));
+                ui.add(egui::ProgressBar::new(done as f32 / total.max(1) as f32));
+                cancel = ui.button("Cancel").clicked();
+                return;
+            }
+            if let Some((done, total)) = state.cancelled {
+                ui.label(format!("Cancelled after {done} of {total} files."));
+                return;
+            }
+            let Some(stats) = &state.stats else {
+                return;
+            };
+
+            egui::Grid::new("folder_totals").show(ui, |ui| {
+                ui.label("Files:");
+                ui.label(stats.files.to_string());
+                ui.end_row();
+                ui.label("Total size:");
+                ui.label(format_size(stats.total_bytes));
+                ui.end_row();
+                ui.label("Unparseable:");
+                ui.label(stats.unparseable.len().to_string());
+                ui.end_row();
+            });
+
+            ui.separator();
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                egui::Grid::new("folder_sizes")
+                    .striped(true)
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.strong("Size");
+                        ui.strong("Files");
+                        ui.end_row();
+                        for row in stats.breakdown() {
+                            ui.label(format!("{}×{}", row.width, row.height));
+                            ui.label(row.count.to_string());
+                            ui.end_row();
+                        }
+                        if stats.unsized_files > 0 {
+                            ui.label("unknown")
+                                .on_hover_text("Relative or zero size; needs a full parse");
+                            ui.label(stats.unsized_files.to_string());
+                            ui.end_row();
+                        }
+                    });
+
+                if !stats.unparseable.is_empty() {
+                    ui.separator();
+                    ui.strong("Unparseable files");
+                    for file in &stats.unparseable {
+                        if ui
+                            .link(file_name(&file.path))
+                            .on_hover_text(&file.error)
+                            .clicked()
+                        {
+                            clicked = Some(file.path.clone());
+                        }
+                    }
+                }
+            });
+        });
+
+    if cancel || !open {
+        state.cancel();
+    }
+    state.open = open;
+    clicked
+}
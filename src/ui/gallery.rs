@@ -0,0 +1,195 @@
+use std::path::PathBuf;
+
+use egui::{Key, Rect, Sense, Ui, Vec2};
+
+use crate::ui::thumbnail_strip::draw_thumbnail;
+use crate::ui::thumbnail_textures::ThumbnailTextures;
+
+/// Smallest and largest thumbnail cells, in logical pixels.
+pub const MIN_CELL: f32 = 64.0;
+pub const MAX_CELL: f32 = 256.0;
+/// Thumbnails are rendered at multiples of this, so resizing the cells
+/// doesn't render them again at every step.
+const RENDER_STEP: f32 = 64.0;
+const PADDING: f32 = 6.0;
+
+/// The gallery that replaces the canvas with a grid of every file.
+pub struct GalleryState {
+    pub open: bool,
+    /// Side of a thumbnail, in logical pixels
+    pub cell_size: f32,
+    /// The file the keyboard moves and Enter opens
+    selected: usize,
+    /// The selection moved and should be scrolled to
+    reveal: bool,
+}
+
+impl Default for GalleryState {
+    fn default() -> Self {
+        Self {
+            open: false,
+            cell_size: 128.0,
+            selected: 0,
+            reveal: false,
+        }
+    }
+}
+
+impl GalleryState {
+    /// Open the gallery, or close it, with `current` selected on opening.
+    pub fn toggle(&mut self, current: usize) {
+        self.open = !self.open;
+        if self.open {
+            self.selected = current;
+            self.reveal = true;
+        }
+    }
+}
+
+/// The index `selected` moves to for `key` in a grid of `count` files,
+/// `columns` wide, if the key moves it.
+pub fn step_selection(selected: usize, key: Key, columns: usize, count: usize) -> Option<usize> {
+    if count == 0 {
+        return None;
+    }
+    let last = count - 1;
+    let columns = columns.max(1);
+    let moved = match key {
+        Key::ArrowLeft => selected.saturating_sub(1),
+        Key::ArrowRight => (selected + 1).min(last),
+        Key::ArrowUp => selected.checked_sub(columns).unwrap_or(selected),
+        Key::ArrowDown if selected + columns <= last => selected + columns,
+        Key::ArrowDown => selected,
+        Key::Home => 0,
+        Key::End => last,
+        _ => return None,
+    };
+    Some(moved.min(last))
+}
+
+/// Draw the gallery of `files` in `ui`. Returns the index of a file opened
+/// with Enter or a double-click.
+pub fn draw_gallery(
+    ui: &mut Ui,
+    state: &mut GalleryState,
+    textures: &mut ThumbnailTextures,
+    files: &[PathBuf],
+) -> Option<usize> {
+    textures.poll(ui.ctx());
+    if files.is_empty() {
+        ui.centered_and_justified(|ui| ui.label("No files to show"));
+        return None;
+    }
+    state.selected = state.selected.min(files.len() - 1);
+
+    ui.horizontal(|ui| {
+        ui.strong(format!("{} files", files.len()));
+        ui.separator();
+        ui.add(egui::Slider::new(&mut state.cell_size, MIN_CELL..=MAX_CELL).text("Size"));
+        ui.weak("Arrows move, Enter opens, Tab returns to the image");
+    });
+    ui.separator();
+
+    let label_height = ui.text_style_height(&egui::TextStyle::Small) + 4.0;
+    let cell = Vec2::new(state.cell_size, state.cell_size + label_height) + Vec2::splat(PADDING);
+    let columns = ((ui.available_width() / cell.x).floor() as usize).max(1);
+    let rows = files.len().div_ceil(columns);
+    let render_size = ((state.cell_size / RENDER_STEP).ceil() * RENDER_STEP) as u32;
+
+    let mut opened = None;
+    let keys = [
+        Key::ArrowLeft,
+        Key::ArrowRight,
+        Key::ArrowUp,
+        Key::ArrowDown,
+        Key::Home,
+        Key::End,
+    ];
+    ui.input(|i| {
+        for key in keys {
+            if i.key_pressed(key) {
+                if let Some(moved) = step_selection(state.selected, key, columns, files.len()) {
+                    state.selected = moved;
+                    state.reveal = true;
+                }
+            }
+        }
+        if i.key_pressed(Key::Enter) {
+            opened = Some(state.selected);
+        }
+    });
+
+    let row_height = cell.y + ui.spacing().item_spacing.y;
+    let mut scroll = egui::ScrollArea::vertical().auto_shrink(false);
+    if std::mem::take(&mut state.reveal) {
+        let row = (state.selected / columns) as f32;
+        let (top, view) = (row * row_height, ui.available_height());
+        scroll = scroll.vertical_scroll_offset((top - (view - row_height) / 2.0).max(0.0));
+    }
+    scroll.show_rows(ui, cell.y, rows, |ui, visible| {
+        for row in visible {
+            ui.horizontal(|ui| {
+                for (index, path) in files.iter().enumerate().skip(row * columns).take(columns) {
+                    let (rect, response) = ui.allocate_exact_size(cell, Sense::click());
+                    let name = path
+                        .file_name()
+                        .unwrap_or(path.as_os_str())
+                        .to_string_lossy();
+                    if index == state.selected {
+                        ui.painter()
+                            .rect_filled(rect, 4.0, ui.visuals().selection.bg_fill);
+                    }
+                    let image = Rect::from_min_size(
+                        rect.min + Vec2::splat(PADDING / 2.0),
+                        Vec2::splat(state.cell_size),
+                    );
+                    draw_thumbnail(ui, image, textures.get(path, render_size));
+                    let label = Rect::from_min_max(egui::pos2(rect.min.x, image.max.y), rect.max);
+                    let galley = ui.painter().layout(
+                        name.to_string(),
+                        egui::FontId::proportional(11.0),
+                        ui.visuals().text_color(),
+                        label.width(),
+                    );
+                    let first_row = galley.rows.first().map_or(0.0, |r| r.rect().width());
+                    let at = egui::pos2(label.center().x - first_row / 2.0, label.min.y + 2.0);
+                    ui.painter().with_clip_rect(label).galley(
+                        at,
+                        galley,
+                        ui.visuals().text_color(),
+                    );
+                    let response = response.on_hover_text(name);
+                    if response.clicked() {
+                        state.selected = index;
+                    }
+                    if response.double_clicked() {
+                        opened = Some(index);
+                    }
+                }
+            });
+        }
+    });
+    opened
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_selection() {
+        // 10 files, 4 across: 0-3, 4-7, 8-9
+        assert_eq!(step_selection(5, Key::ArrowLeft, 4, 10), Some(4));
+        assert_eq!(step_selection(0, Key::ArrowLeft, 4, 10), Some(0));
+        assert_eq!(step_selection(9, Key::ArrowRight, 4, 10), Some(9));
+        assert_eq!(step_selection(5, Key::ArrowUp, 4, 10), Some(1));
+        assert_eq!(step_selection(1, Key::ArrowUp, 4, 10), Some(1));
+        assert_eq!(step_selection(5, Key::ArrowDown, 4, 10), Some(9));
+        // Nothing below 6 in the last, short row
+        assert_eq!(step_selection(6, Key::ArrowDown, 4, 10), Some(6));
+        assert_eq!(step_selection(6, Key::End, 4, 10), Some(9));
+        assert_eq!(step_selection(6, Key::Home, 4, 10), Some(0));
+        assert_eq!(step_selection(6, Key::Enter, 4, 10), None);
+        assert_eq!(step_selection(0, Key::ArrowRight, 4, 0), None);
+    }
+}
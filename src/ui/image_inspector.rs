@@ -0,0 +1,72 @@
+use egui::{Context, Window};
+
+use crate::svg_document::{self, format_size, EmbeddedImage, SvgDocument};
+
+#[derive(Default)]
+pub struct ImageInspectorState {
+    pub open: bool,
+}
+
+/// Draw the list of `doc`'s raster images, closing it when there's no
+/// document. Returns the image whose "Save as…" was clicked, with the name
+/// to suggest for it.
+pub fn draw_image_inspector(
+    ctx: &Context,
+    state: &mut ImageInspectorState,
+    doc: Option<&SvgDocument>,
+) -> Option<(EmbeddedImage, String)> {
+    let Some(doc) = doc.filter(|_| state.open) else {
+        state.open = false;
+        return None;
+    };
+
+    let images = svg_document::embedded_images(doc.tree());
+    let mut save = None;
+    let mut open = state.open;
+    Window::new("Embedded images")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            if images.is_empty() {
+                ui.label("This document has no raster images.");
+                return;
+            }
+            egui::ScrollArea::vertical()
+                .max_height(320.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("embedded_images")
+                        .num_columns(5)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for heading in ["Image", "Format", "Pixels", "Encoded", ""] {
+                                ui.label(egui::RichText::new(heading).strong());
+                            }
+                            ui.end_row();
+                            for (index, image) in images.iter().enumerate() {
+                                let name = image.file_name(doc.file_stem(), index);
+                                if image.id.is_empty() {
+                                    ui.label(format!("#{}", index + 1));
+                                } else {
+                                    ui.monospace(&image.id);
+                                }
+                                ui.label(image.format.to_uppercase());
+                                ui.monospace(
+                                    image
+                                        .dimensions
+                                        .map_or_else(|| "?".into(), |(w, h)| format!("{w}×{h}")),
+                                );
+                                ui.monospace(format_size(image.encoded_size() as u64));
+                                if ui.button("Save as\u{2026}").clicked() {
+                                    save = Some((image.clone(), name));
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+        });
+
+    if !open {
+        state.open = false;
+    }
+    save
+}
@@ -0,0 +1,114 @@
+use egui::{Color32, Context, FontId, Pos2, Rect, Stroke, Ui, Window};
+
+use crate::guides;
+use crate::measure::Measurement;
+use crate::viewport::Viewport;
+
+const LINE_COLOR: Color32 = Color32::from_rgb(255, 160, 0);
+
+/// The measure mode and the measurements taken in it.
+#[derive(Default)]
+pub struct MeasureState {
+    pub active: bool,
+    pub measurements: Vec<Measurement>,
+    /// Where the measurement being dragged out started, in document units
+    pub dragging: Option<[f32; 2]>,
+}
+
+impl MeasureState {
+    /// Turn the mode on or off; either way starts without measurements.
+    pub fn toggle(&mut self) {
+        self.active = !self.active;
+        self.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.measurements.clear();
+        self.dragging = None;
+    }
+}
+
+/// Draw `measurements`, and `live` while one is being dragged out, over the
+/// render shown at `img_rect`, clipped to the canvas `rect`.
+pub fn draw_measurements<'a>(
+    ui: &Ui,
+    rect: Rect,
+    img_rect: Rect,
+    viewport: &Viewport,
+    svg_size: (f32, f32),
+    measurements: impl Iterator<Item = &'a Measurement>,
+    live: Option<Measurement>,
+) {
+    let painter = ui.painter_at(rect);
+    let transform = guides::screen_transform(
+        viewport,
+        svg_size.0,
+        svg_size.1,
+        [img_rect.min.x, img_rect.min.y],
+        [img_rect.width(), img_rect.height()],
+    );
+    let to_screen = |point: [f32; 2]| {
+        let [x, y] = guides::map_point(&transform, point);
+        Pos2::new(x, y)
+    };
+    let stroke = Stroke::new(1.5, LINE_COLOR);
+    for measurement in measurements.copied().chain(live) {
+        let (start, end) = (to_screen(measurement.start), to_screen(measurement.end));
+        painter.line_segment([start, end], stroke);
+        for end_point in [start, end] {
+            painter.circle_filled(end_point, 2.5, LINE_COLOR);
+        }
+        let galley = painter.layout_no_wrap(
+            measurement.label(),
+            FontId::proportional(11.0),
+            Color32::WHITE,
+        );
+        let at = start.lerp(end, 0.5) + egui::vec2(6.0, -galley.size().y - 4.0);
+        let label = Rect::from_min_size(at, galley.size()).expand(2.0);
+        painter.rect_filled(label, 3.0, Color32::from_black_alpha(180));
+        painter.galley(at, galley, Color32::WHITE);
+    }
+}
+
+/// List the measurements while the mode is on and there are some.
+pub fn draw_measurement_list(ctx: &Context, state: &mut MeasureState) {
+    if !state.active || state.measurements.is_empty() {
+        return;
+    }
+    let mut remove = None;
+    let mut clear = false;
+    Window::new("Measurements")
+        .resizable(false)
+        .show(ctx, |ui| {
+            egui::Grid::new("measurements")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    for (index, measurement) in state.measurements.iter().enumerate() {
+                        ui.monospace(measurement.label());
+                        if ui
+                            .small_button("\u{2715}")
+                            .on_hover_text("Remove")
+                            .clicked()
+                        {
+                            remove = Some(index);
+                        }
+                        ui.end_row();
+                    }
+                });
+            ui.separator();
+            ui.horizontal(|ui| {
+                clear = ui
+                    .button("Clear all")
+                    .on_hover_text("Or press Esc")
+                    .clicked();
+                ui.weak("Drag on the image to measure; M leaves the mode");
+            });
+        });
+    if let Some(index) = remove {
+        state.measurements.remove(index);
+    }
+    if clear {
+        state.clear();
+    }
+}
@@ -1,5 +1,21 @@
+pub mod batch_progress;
 pub mod canvas;
+pub mod canvas_interaction;
+pub mod delete_confirm;
+pub mod diff_overlay;
+pub mod event_log_window;
 pub mod export_dialog;
+pub mod folder_summary;
+pub mod gallery;
+pub mod image_inspector;
+pub mod measurements;
+pub mod position_popover;
+pub mod properties;
+pub mod rotation_entry;
+pub mod rulers;
 pub mod shortcuts;
+pub mod source_panel;
 pub mod status_bar;
+pub mod thumbnail_strip;
+pub mod thumbnail_textures;
 pub mod toolbar;
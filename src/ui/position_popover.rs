@@ -0,0 +1,111 @@
+use egui::{Context, Key, Vec2, Window};
+
+#[derive(Default)]
+pub struct PositionPopoverState {
+    pub open: bool,
+}
+
+/// What the user asked the position popover to do with the pan.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PositionAction {
+    Set(Vec2),
+    Center,
+    TopLeft,
+    SnapToPixel,
+    Nudge(Vec2),
+}
+
+/// Logical pixels moved by an arrow key, or with Shift held.
+const NUDGE: f32 = 1.0;
+const NUDGE_LARGE: f32 = 10.0;
+
+/// Draw the position popover for the current `pan`, in logical pixels.
+pub fn draw_position_popover(
+    ctx: &Context,
+    state: &mut PositionPopoverState,
+    pan: Vec2,
+) -> Option<PositionAction> {
+    if !state.open {
+        return None;
+    }
+
+    let mut action = None;
+    let mut open = state.open;
+
+    Window::new("Position")
+        .open(&mut open)
+        .resizable(false)
+        .collapsible(false)
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -40.0])
+        .show(ctx, |ui| {
+            let mut edited = pan;
+            ui.horizontal(|ui| {
+                let label = ui.label("X:");
+                ui.add(egui::DragValue::new(&mut edited.x).speed(1.0).suffix(" px"))
+                    .labelled_by(label.id);
+                let label = ui.label("Y:");
+                ui.add(egui::DragValue::new(&mut edited.y).speed(1.0).suffix(" px"))
+                    .labelled_by(label.id);
+            });
+            if edited != pan {
+                action = Some(PositionAction::Set(edited));
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Center").clicked() {
+                    action = Some(PositionAction::Center);
+                }
+                if ui
+                    .button("Top-left")
+                    .on_hover_text("Put the image's corner at the canvas's corner")
+                    .clicked()
+                {
+                    action = Some(PositionAction::TopLeft);
+                }
+                if ui
+                    .button("Snap to pixel")
+                    .on_hover_text("Align the image's corner to a device pixel")
+                    .clicked()
+                {
+                    action = Some(PositionAction::SnapToPixel);
+                }
+            });
+            ui.weak("Arrow keys nudge by 1 px, Shift+arrows by 10 px");
+        });
+
+    // Arrow keys nudge unless a field is being typed into
+    if !ctx.wants_keyboard_input() {
+        let nudge = ctx.input(|i| {
+            let step = if i.modifiers.shift {
+                NUDGE_LARGE
+            } else {
+                NUDGE
+            };
+            let mut delta = Vec2::ZERO;
+            if i.key_pressed(Key::ArrowLeft) {
+                delta.x -= step;
+            }
+            if i.key_pressed(Key::ArrowRight) {
+                delta.x += step;
+            }
+            if i.key_pressed(Key::ArrowUp) {
+                delta.y -= step;
+            }
+            if i.key_pressed(Key::ArrowDown) {
+                delta.y += step;
+            }
+            delta
+        });
+        if nudge != Vec2::ZERO {
+            action = Some(PositionAction::Nudge(nudge));
+        }
+    }
+
+    if ctx.input(|i| i.key_pressed(Key::Escape)) {
+        state.open = false;
+    }
+    if !open {
+        state.open = false;
+    }
+    action
+}
@@ -0,0 +1,97 @@
+use egui::{Context, Window};
+
+use crate::svg_document::SvgDocument;
+
+#[derive(Default)]
+pub struct PropertiesState {
+    pub open: bool,
+}
+
+/// Draw the Properties window for `doc`, closing it when there's none.
+pub fn draw_properties(ctx: &Context, state: &mut PropertiesState, doc: Option<&SvgDocument>) {
+    let Some(doc) = doc.filter(|_| state.open) else {
+        state.open = false;
+        return;
+    };
+
+    let not_set = || "(not set)".to_string();
+    let metadata = doc.metadata();
+    let fit = doc.view_box_fit();
+    let mut rows = Vec::new();
+    if let Some(title) = &metadata.title {
+        rows.push(("Title", title.clone()));
+    }
+    rows.extend([
+        (
+            "File",
+            doc.url()
+                .map_or_else(|| doc.path().display().to_string(), str::to_string),
+        ),
+        ("File size", doc.file_size_display()),
+        ("Size", format!("{}×{}", doc.width(), doc.height())),
+        ("width", metadata.width.clone().unwrap_or_else(not_set)),
+        ("height", metadata.height.clone().unwrap_or_else(not_set)),
+        ("viewBox", fit.view_box.clone().unwrap_or_else(not_set)),
+        (
+            "preserveAspectRatio",
+            fit.preserve_aspect_ratio.clone().unwrap_or_else(not_set),
+        ),
+    ]);
+    let [x, y, w, h] = doc.content_bbox();
+    rows.push(("Content", format!("{w}×{h} at {x}, {y}")));
+    if doc.is_meet_forced() {
+        rows.push(("Shown as", "xMidYMid meet".into()));
+    }
+    if let Some([r, g, b]) = doc.declared_background() {
+        rows.push(("Background", format!("#{r:02x}{g:02x}{b:02x}")));
+    }
+
+    let mut open = state.open;
+    Window::new("Properties")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            egui::Grid::new("properties")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    for (name, value) in &rows {
+                        ui.label(*name);
+                        ui.monospace(value);
+                        ui.end_row();
+                    }
+                });
+            ui.separator();
+            ui.label(egui::RichText::new("Contents").strong());
+            egui::Grid::new("properties_stats")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    for (name, count) in doc.stats().rows() {
+                        ui.label(name);
+                        ui.monospace(count.to_string());
+                        ui.end_row();
+                    }
+                });
+            if let Some(description) = &metadata.description {
+                ui.separator();
+                ui.label(egui::RichText::new("Description").strong());
+                egui::ScrollArea::vertical()
+                    .max_height(160.0)
+                    .show(ui, |ui| {
+                        ui.set_max_width(420.0);
+                        ui.label(description);
+                    });
+            }
+            if let Some(notice) = doc.aspect_notice() {
+                ui.separator();
+                ui.colored_label(ui.visuals().warn_fg_color, notice);
+            }
+            if let Some(warning) = doc.size_warning() {
+                ui.colored_label(ui.visuals().warn_fg_color, warning);
+            }
+        });
+
+    if !open {
+        state.open = false;
+    }
+}
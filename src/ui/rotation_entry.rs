@@ -0,0 +1,124 @@
+use egui::{Context, Key, Window};
+
+use crate::viewport::DEFAULT_ROTATION_STEP;
+
+pub struct RotationEntryState {
+    pub open: bool,
+    /// Degrees turned by `[` / `]`
+    pub step_deg: f32,
+    text: String,
+    focus_pending: bool,
+}
+
+impl Default for RotationEntryState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RotationEntryState {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            step_deg: DEFAULT_ROTATION_STEP,
+            text: String::new(),
+            focus_pending: false,
+        }
+    }
+
+    pub fn open_with(&mut self, current_deg: f32) {
+        self.open = true;
+        self.text = format!("{current_deg}");
+        self.focus_pending = true;
+    }
+}
+
+/// Parse a typed angle such as `45`, `-90`, `400°` or `12.5 deg`.
+/// The result is not normalized; `Viewport::set_rotation` does that.
+pub fn parse_angle(text: &str) -> Option<f32> {
+    let text = text.trim();
+    let text = text
+        .strip_suffix('°')
+        .or_else(|| text.strip_suffix("deg"))
+        .unwrap_or(text)
+        .trim();
+    text.parse::<f32>().ok().filter(|v| v.is_finite())
+}
+
+/// Draw the rotation entry. Returns the angle to apply once the user confirms.
+pub fn draw_rotation_entry(ctx: &Context, state: &mut RotationEntryState) -> Option<f32> {
+    if !state.open {
+        return None;
+    }
+
+    let mut applied = None;
+    let mut open = state.open;
+
+    Window::new("Rotation")
+        .open(&mut open)
+        .resizable(false)
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            let parsed = parse_angle(&state.text);
+            ui.horizontal(|ui| {
+                let label = ui.label("Angle:");
+                let response = ui
+                    .add(egui::TextEdit::singleline(&mut state.text).desired_width(80.0))
+                    .labelled_by(label.id);
+                if state.focus_pending {
+                    response.request_focus();
+                    state.focus_pending = false;
+                }
+                ui.label("°");
+                let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                let apply = ui
+                    .add_enabled(parsed.is_some(), egui::Button::new("Apply"))
+                    .clicked();
+                if submitted || apply {
+                    if let Some(angle) = parsed {
+                        applied = Some(angle);
+                        state.open = false;
+                    }
+                }
+            });
+            if parsed.is_none() {
+                ui.colored_label(ui.visuals().error_fg_color, "Enter an angle in degrees");
+            }
+
+            ui.horizontal(|ui| {
+                let label = ui.label("[ / ] step:");
+                ui.add(
+                    egui::DragValue::new(&mut state.step_deg)
+                        .range(0.1..=180.0)
+                        .speed(0.5)
+                        .suffix("°"),
+                )
+                .labelled_by(label.id);
+            });
+        });
+
+    if ctx.input(|i| i.key_pressed(Key::Escape)) {
+        state.open = false;
+    }
+    if !open {
+        state.open = false;
+    }
+    applied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_angle() {
+        assert_eq!(parse_angle("45"), Some(45.0));
+        assert_eq!(parse_angle(" -90 "), Some(-90.0));
+        assert_eq!(parse_angle("400°"), Some(400.0));
+        assert_eq!(parse_angle("12.5 deg"), Some(12.5));
+        assert_eq!(parse_angle(""), None);
+        assert_eq!(parse_angle("abc"), None);
+        assert_eq!(parse_angle("inf"), None);
+    }
+}
@@ -0,0 +1,121 @@
+use egui::{Color32, FontId, Pos2, Rect, Sense, Stroke, Ui, Vec2};
+
+use crate::rulers::{self, MINOR_TICKS, RULER_SIZE};
+use crate::viewport::Viewport;
+
+/// Split the canvas area `full` into the top ruler, the left ruler and
+/// what's left for the canvas.
+pub fn layout(full: Rect) -> (Rect, Rect, Rect) {
+    let top = Rect::from_min_max(
+        full.min + Vec2::new(RULER_SIZE, 0.0),
+        Pos2::new(full.max.x, full.min.y + RULER_SIZE),
+    );
+    let left = Rect::from_min_max(
+        full.min + Vec2::new(0.0, RULER_SIZE),
+        Pos2::new(full.min.x + RULER_SIZE, full.max.y),
+    );
+    let canvas = Rect::from_min_max(full.min + Vec2::splat(RULER_SIZE), full.max);
+    (top, left, canvas)
+}
+
+/// Where a document is shown, for placing ruler ticks.
+pub struct RulerView<'a> {
+    pub viewport: &'a Viewport,
+    pub svg_size: (f32, f32),
+    /// The canvas the rulers run along
+    pub canvas: Rect,
+    /// Size of the image on screen
+    pub display_size: Vec2,
+    /// Marked on both rulers while over the canvas
+    pub pointer: Option<Pos2>,
+}
+
+/// Draw the rulers around `view.canvas` in the area `full`. They show SVG
+/// units, and are left blank while the document is rotated.
+pub fn draw_rulers(ui: &Ui, full: Rect, view: &RulerView) {
+    let (top, left, _) = layout(full);
+    let painter = ui.painter_at(full);
+    let visuals = ui.visuals();
+    let background = visuals.extreme_bg_color;
+    let ink = visuals.weak_text_color();
+    painter.rect_filled(top, 0.0, background);
+    painter.rect_filled(left, 0.0, background);
+    painter.rect_filled(
+        Rect::from_min_size(full.min, Vec2::splat(RULER_SIZE)),
+        0.0,
+        background,
+    );
+    let edge = Stroke::new(1.0, visuals.widgets.noninteractive.bg_stroke.color);
+    painter.hline(top.x_range(), top.max.y, edge);
+    painter.vline(left.max.x, left.y_range(), edge);
+
+    if view.viewport.rotation_deg != 0.0 {
+        let why = "Rulers are blank while the document is rotated; their units \
+                   wouldn't run along the edges. Rotate back to 0° to use them.";
+        for (rect, name) in [(top, "ruler_top"), (left, "ruler_left")] {
+            ui.interact(rect, ui.id().with(name), Sense::hover())
+                .on_hover_text(why);
+        }
+        return;
+    }
+
+    // Rotation is 0, so each axis maps on its own; mirroring just reverses it
+    let canvas = view.canvas;
+    let to_svg = |pos: Pos2| {
+        view.viewport
+            .screen_to_svg(pos, canvas, view.display_size, view.svg_size)
+    };
+    let (Some(start), Some(end)) = (to_svg(canvas.min), to_svg(canvas.max)) else {
+        return;
+    };
+    let font = FontId::proportional(9.0);
+    for horizontal in [true, false] {
+        let axis = usize::from(!horizontal);
+        let (from, to) = (start[axis], end[axis]);
+        let (screen_from, length) = if horizontal {
+            (canvas.min.x, canvas.width())
+        } else {
+            (canvas.min.y, canvas.height())
+        };
+        if (to - from).abs() < f32::EPSILON || length <= 0.0 {
+            continue;
+        }
+        let pixels_per_unit = length / (to - from).abs();
+        let step = rulers::tick_step(pixels_per_unit);
+        let screen = |value: f32| screen_from + (value - from) / (to - from) * length;
+        let minor = step / MINOR_TICKS as f32;
+        for value in rulers::ticks(from, to, minor) {
+            let major = ((value / step).round() * step - value).abs() < minor / 2.0;
+            let at = screen(value);
+            let tick = if major { RULER_SIZE } else { RULER_SIZE / 4.0 };
+            if horizontal {
+                painter.vline(at, (top.max.y - tick)..=top.max.y, Stroke::new(1.0, ink));
+            } else {
+                painter.hline((left.max.x - tick)..=left.max.x, at, Stroke::new(1.0, ink));
+            }
+            if !major {
+                continue;
+            }
+            let galley =
+                painter.layout_no_wrap(rulers::format_tick(value, step), font.clone(), ink);
+            if horizontal {
+                painter.galley(Pos2::new(at + 2.0, top.min.y + 1.0), galley, ink);
+            } else {
+                // Read bottom to top, beside the tick
+                let text = egui::epaint::TextShape::new(
+                    Pos2::new(left.min.x + 1.0, at - 2.0),
+                    galley,
+                    ink,
+                )
+                .with_angle(-std::f32::consts::FRAC_PI_2);
+                painter.add(text);
+            }
+        }
+    }
+
+    if let Some(pointer) = view.pointer.filter(|pos| canvas.contains(*pos)) {
+        let marker = Stroke::new(1.0, Color32::from_rgb(255, 80, 80));
+        painter.vline(pointer.x, top.y_range(), marker);
+        painter.hline(left.x_range(), pointer.y, marker);
+    }
+}
@@ -1,7 +1,12 @@
-use egui::{Context, Key, Modifiers};
+use egui::{Context, Event, Key, Modifiers, Vec2};
 
 use crate::ui::toolbar::ToolbarAction;
 
+/// How far a panning key moves the image, in logical pixels.
+pub const PAN_STEP: f32 = 50.0;
+/// How far it moves with Alt held.
+pub const PAN_STEP_FINE: f32 = 10.0;
+
 pub fn handle_shortcuts(ctx: &Context, has_file: bool) -> ToolbarAction {
     let mut action = ToolbarAction::default();
 
@@ -18,18 +23,68 @@ pub fn handle_shortcuts(ctx: &Context, has_file: bool) -> ToolbarAction {
             action.open_file = true;
         }
 
+        // Paste SVG markup as a new document: Ctrl+V, which arrives as the
+        // pasted text rather than a key press
+        if input
+            .events
+            .iter()
+            .any(|event| matches!(event, Event::Paste(_)))
+        {
+            action.paste_svg = true;
+        }
+
         if !has_file {
             return;
         }
 
         // Navigation: Left/Right arrow
-        if input.key_pressed(Key::ArrowLeft) && !ctrl {
+        if input.key_pressed(Key::ArrowLeft) && !ctrl && !shift {
             action.prev_file = true;
         }
-        if input.key_pressed(Key::ArrowRight) && !ctrl {
+        if input.key_pressed(Key::ArrowRight) && !ctrl && !shift {
             action.next_file = true;
         }
 
+        // Pan: Shift+arrows or WASD, finely with Alt. The keys move the view
+        // over the image, so the image moves the other way, and repeat while held.
+        if !ctrl {
+            let step = if input.modifiers.alt {
+                PAN_STEP_FINE
+            } else {
+                PAN_STEP
+            };
+            let directions = [
+                (Key::ArrowLeft, Key::A, Vec2::new(step, 0.0)),
+                (Key::ArrowRight, Key::D, Vec2::new(-step, 0.0)),
+                (Key::ArrowUp, Key::W, Vec2::new(0.0, step)),
+                (Key::ArrowDown, Key::S, Vec2::new(0.0, -step)),
+            ];
+            for (arrow, letter, delta) in directions {
+                if (shift && input.key_pressed(arrow)) || (!shift && input.key_pressed(letter)) {
+                    action.pan_delta += delta;
+                }
+            }
+        }
+
+        // Gallery of the folder's files: Tab
+        if input.key_pressed(Key::Tab) && input.modifiers == Modifiers::NONE {
+            action.toggle_gallery = true;
+        }
+
+        // Keep the view while stepping through files: L
+        if input.key_pressed(Key::L) && input.modifiers == Modifiers::NONE {
+            action.toggle_lock_view = true;
+        }
+
+        // Center the image: Home, or the first file once it's centered (the
+        // app decides which). The last file: End
+        if input.key_pressed(Key::Home) && input.modifiers == Modifiers::NONE {
+            action.center_pan = true;
+        }
+        if input.key_pressed(Key::End) && input.modifiers == Modifiers::NONE {
+            action.last_file = true;
+        }
+
         // Zoom: Ctrl+Plus / Ctrl+Minus
         if ctrl && input.key_pressed(Key::Plus) {
             action.zoom_in = true;
@@ -41,9 +96,13 @@ pub fn handle_shortcuts(ctx: &Context, has_file: bool) -> ToolbarAction {
             action.zoom_out = true;
         }
 
-        // Fit to window: Ctrl+0
+        // Fit to window: Ctrl+0, to the content: Ctrl+Shift+0
         if ctrl && input.key_pressed(Key::Num0) {
-            action.fit_to_window = true;
+            if shift {
+                action.fit_to_content = true;
+            } else {
+                action.fit_to_window = true;
+            }
         }
 
         // Actual size: Ctrl+1
@@ -52,7 +111,7 @@ pub fn handle_shortcuts(ctx: &Context, has_file: bool) -> ToolbarAction {
         }
 
         // Rotate: R / Shift+R
-        if input.key_pressed(Key::R) && !ctrl {
+        if input.key_pressed(Key::R) && !ctrl && !input.modifiers.alt {
             if shift {
                 action.rotate_ccw = true;
             } else {
@@ -60,6 +119,24 @@ pub fn handle_shortcuts(ctx: &Context, has_file: bool) -> ToolbarAction {
             }
         }
 
+        // Step rotation: ] / [ (Shift for 1°). Shift turns the brackets into
+        // braces on most layouts, so accept either key.
+        if !ctrl {
+            if input.key_pressed(Key::CloseBracket) || input.key_pressed(Key::CloseCurlyBracket) {
+                action.rotate_step_cw = true;
+                action.rotate_fine = shift;
+            }
+            if input.key_pressed(Key::OpenBracket) || input.key_pressed(Key::OpenCurlyBracket) {
+                action.rotate_step_ccw = true;
+                action.rotate_fine = shift;
+            }
+        }
+
+        // Exact rotation: Alt+R. Not Ctrl+Alt, which is AltGr on many layouts
+        if input.key_pressed(Key::R) && input.modifiers.alt && !ctrl {
+            action.edit_rotation = true;
+        }
+
         // Mirror: H / V
         if input.key_pressed(Key::H) && input.modifiers == Modifiers::NONE {
             action.mirror_h = true;
@@ -68,14 +145,64 @@ pub fn handle_shortcuts(ctx: &Context, has_file: bool) -> ToolbarAction {
             action.mirror_v = true;
         }
 
-        // Export: Ctrl+Shift+E
-        if ctrl && shift && input.key_pressed(Key::E) {
-            action.export = true;
+        // Export: Ctrl+Shift+E, again with the last settings: Ctrl+E
+        if ctrl && input.key_pressed(Key::E) {
+            if shift {
+                action.export = true;
+            } else {
+                action.export_again = true;
+            }
+        }
+
+        // Properties: Ctrl+I, the eyedropper: I
+        if ctrl && input.key_pressed(Key::I) {
+            action.properties = true;
+        }
+        if input.key_pressed(Key::I) && input.modifiers == Modifiers::NONE {
+            action.toggle_eyedropper = true;
+        }
+
+        // Rulers: Ctrl+;
+        if ctrl && input.key_pressed(Key::Semicolon) {
+            action.toggle_rulers = true;
+        }
+
+        // Source panel: Ctrl+U
+        if ctrl && input.key_pressed(Key::U) {
+            action.toggle_source_panel = true;
+        }
+
+        // Move the file to the trash: Delete, without asking: Shift+Delete
+        if input.key_pressed(Key::Delete) && !ctrl {
+            action.delete_file = true;
+            action.delete_now = shift;
+        }
+
+        // Reload from disk: F5 or Ctrl+Shift+R
+        if input.key_pressed(Key::F5) || (ctrl && shift && input.key_pressed(Key::R)) {
+            action.reload = true;
+        }
+
+        // Copy: Ctrl+C, the coordinates under the pointer: Ctrl+Shift+C
+        if ctrl && input.key_pressed(Key::C) && !input.modifiers.alt {
+            if shift {
+                action.copy_coordinates = true;
+            } else {
+                action.copy_clipboard = true;
+            }
         }
 
-        // Copy: Ctrl+C
-        if ctrl && input.key_pressed(Key::C) {
-            action.copy_clipboard = true;
+        // Copy the SVG source: Alt+C, as Ctrl+Alt+C types a character through AltGr
+        if input.key_pressed(Key::C) && input.modifiers.alt && !ctrl {
+            action.copy_source = true;
+        }
+
+        // Measure: M, clear the measurements: Esc
+        if input.key_pressed(Key::M) && input.modifiers == Modifiers::NONE {
+            action.toggle_measure = true;
+        }
+        if input.key_pressed(Key::Escape) && input.modifiers == Modifiers::NONE {
+            action.clear_measurements = true;
         }
 
         // Toggle background: T
@@ -83,6 +210,22 @@ pub fn handle_shortcuts(ctx: &Context, has_file: bool) -> ToolbarAction {
             action.toggle_bg = true;
         }
 
+        // View history: Ctrl+Z back, Ctrl+Shift+Z forward, or the mouse's
+        // Back and Forward buttons
+        if ctrl && input.key_pressed(Key::Z) {
+            if shift {
+                action.view_forward = true;
+            } else {
+                action.view_back = true;
+            }
+        }
+        if input.pointer.button_pressed(egui::PointerButton::Extra1) {
+            action.view_back = true;
+        }
+        if input.pointer.button_pressed(egui::PointerButton::Extra2) {
+            action.view_forward = true;
+        }
+
         // Reset view: Ctrl+R
         if ctrl && input.key_pressed(Key::R) && !shift {
             action.reset_view = true;
@@ -90,7 +233,7 @@ pub fn handle_shortcuts(ctx: &Context, has_file: bool) -> ToolbarAction {
 
         // Quit: Ctrl+Q
         if ctrl && input.key_pressed(Key::Q) {
-            std::process::exit(0);
+            action.quit = true;
         }
     });
 
@@ -0,0 +1,326 @@
+use std::ops::Range;
+
+use egui::text::LayoutJob;
+use egui::{Color32, Context, FontId, TextFormat, TextStyle};
+
+use crate::svg_document::{self, format_size, SvgDocument};
+
+/// Larger sources are shown cut off at this many bytes; laying out and
+/// highlighting more makes the panel sluggish.
+pub const MAX_SHOWN_BYTES: usize = 2 * 1024 * 1024;
+
+/// What a stretch of markup is, for coloring.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Token {
+    /// `<`, `</`, `>`, `/>` and `=`
+    Markup,
+    /// An element name
+    Name,
+    /// An attribute name
+    Attribute,
+    /// A quoted attribute value, quotes included
+    Value,
+    /// A comment, processing instruction, doctype or CDATA section
+    Comment,
+}
+
+/// The colored stretches of `text`, in order and not overlapping. Text in
+/// between is character data. It's a tokenizer for coloring, not a parser:
+/// malformed markup is colored as far as it makes sense.
+pub fn highlight(text: &str) -> Vec<(Range<usize>, Token)> {
+    let bytes = text.as_bytes();
+    let mut spans = Vec::new();
+    let mut i = 0;
+    // The end of the next `close`, or the end of the text
+    let until = |from: usize, close: &str| {
+        text[from..]
+            .find(close)
+            .map_or(text.len(), |at| from + at + close.len())
+    };
+    while let Some(lt) = text[i..].find('<').map(|at| i + at) {
+        let rest = &text[lt..];
+        let special = [
+            ("<!--", "-->"),
+            ("<![CDATA[", "]]>"),
+            ("<?", "?>"),
+            ("<!", ">"),
+        ];
+        if let Some((_, close)) = special.iter().find(|(open, _)| rest.starts_with(open)) {
+            i = until(lt, close);
+            spans.push((lt..i, Token::Comment));
+            continue;
+        }
+
+        let name_char = |b: u8| b.is_ascii_alphanumeric() || matches!(b, b':' | b'_' | b'-' | b'.');
+        let mut j = lt + if rest.starts_with("</") { 2 } else { 1 };
+        spans.push((lt..j, Token::Markup));
+        let start = j;
+        while j < bytes.len() && (name_char(bytes[j]) || !bytes[j].is_ascii()) {
+            j += 1;
+        }
+        if j > start {
+            spans.push((start..j, Token::Name));
+        }
+        // Attributes, up to the end of the tag
+        while j < bytes.len() {
+            match bytes[j] {
+                b'>' => {
+                    spans.push((j..j + 1, Token::Markup));
+                    j += 1;
+                    break;
+                }
+                b'/' if bytes.get(j + 1) == Some(&b'>') => {
+                    spans.push((j..j + 2, Token::Markup));
+                    j += 2;
+                    break;
+                }
+                b'=' => {
+                    spans.push((j..j + 1, Token::Markup));
+                    j += 1;
+                }
+                quote @ (b'"' | b'\'') => {
+                    let end = text[j + 1..]
+                        .find(quote as char)
+                        .map_or(text.len(), |at| j + 1 + at + 1);
+                    spans.push((j..end, Token::Value));
+                    j = end;
+                }
+                // Another tag: this one wasn't closed
+                b'<' => break,
+                b if name_char(b) || !b.is_ascii() => {
+                    let start = j;
+                    while j < bytes.len() && (name_char(bytes[j]) || !bytes[j].is_ascii()) {
+                        j += 1;
+                    }
+                    spans.push((start..j, Token::Attribute));
+                }
+                _ => j += 1,
+            }
+        }
+        i = j;
+    }
+    spans
+}
+
+/// The source shown for one document, split into lines and highlighted once.
+struct SourceView {
+    /// Identifies the document it was made for
+    key: (usize, usize),
+    /// The whole source, as copied
+    text: String,
+    /// Bytes of the text shown, less than its length when cut off
+    shown: usize,
+    /// Byte offset of each shown line
+    line_starts: Vec<usize>,
+    spans: Vec<(Range<usize>, Token)>,
+}
+
+impl SourceView {
+    fn new(doc: &SvgDocument) -> Self {
+        let raw = doc.raw_data();
+        // The decompressed text of SVGZ; invalid UTF-8 is shown replaced
+        let text = svg_document::svg_text(raw)
+            .unwrap_or_else(|_| String::from_utf8_lossy(raw).into_owned());
+        let shown = if text.len() > MAX_SHOWN_BYTES {
+            let mut end = MAX_SHOWN_BYTES;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            // Whole lines only
+            text[..end].rfind('\n').map_or(end, |newline| newline + 1)
+        } else {
+            text.len()
+        };
+        let shown_text = &text[..shown];
+        let line_starts = std::iter::once(0)
+            .chain(shown_text.match_indices('\n').map(|(at, _)| at + 1))
+            .filter(|&start| start < shown || start == 0)
+            .collect();
+        let spans = highlight(shown_text);
+        Self {
+            key: key(doc),
+            text,
+            shown,
+            line_starts,
+            spans,
+        }
+    }
+
+    /// Line `index` as colored, numbered text.
+    fn line_job(&self, index: usize, number_width: usize, font: &FontId, dark: bool) -> LayoutJob {
+        let start = self.line_starts[index];
+        let end = self
+            .line_starts
+            .get(index + 1)
+            .map_or(self.shown, |&next| next);
+        let line = self.text[start..end].trim_end_matches(['\n', '\r']);
+        let end = start + line.len();
+
+        let format = |color: Color32| TextFormat::simple(font.clone(), color);
+        let plain = if dark {
+            Color32::from_gray(210)
+        } else {
+            Color32::from_gray(30)
+        };
+        let mut job = LayoutJob::default();
+        job.append(
+            &format!("{:>number_width$}  ", index + 1),
+            0.0,
+            format(Color32::GRAY),
+        );
+        let mut at = start;
+        let first = self.spans.partition_point(|(range, _)| range.end <= start);
+        for (range, token) in &self.spans[first..] {
+            if range.start >= end {
+                break;
+            }
+            let from = range.start.max(start);
+            if from > at {
+                job.append(&self.text[at..from], 0.0, format(plain));
+            }
+            let to = range.end.min(end);
+            job.append(&self.text[from..to], 0.0, format(token_color(*token, dark)));
+            at = to;
+        }
+        if at < end {
+            job.append(&self.text[at..end], 0.0, format(plain));
+        }
+        job
+    }
+}
+
+fn token_color(token: Token, dark: bool) -> Color32 {
+    match (token, dark) {
+        (Token::Markup, _) => Color32::GRAY,
+        (Token::Name, true) => Color32::from_rgb(86, 156, 214),
+        (Token::Name, false) => Color32::from_rgb(0, 80, 160),
+        (Token::Attribute, true) => Color32::from_rgb(156, 220, 254),
+        (Token::Attribute, false) => Color32::from_rgb(160, 60, 0),
+        (Token::Value, true) => Color32::from_rgb(206, 145, 120),
+        (Token::Value, false) => Color32::from_rgb(0, 120, 40),
+        (Token::Comment, true) => Color32::from_rgb(106, 153, 85),
+        (Token::Comment, false) => Color32::from_gray(120),
+    }
+}
+
+/// A new document's data is a new allocation, so where it lives and how long
+/// it is tells documents apart while the view is open.
+fn key(doc: &SvgDocument) -> (usize, usize) {
+    (doc.raw_data().as_ptr() as usize, doc.raw_data().len())
+}
+
+#[derive(Default)]
+pub struct SourcePanelState {
+    pub open: bool,
+    view: Option<SourceView>,
+}
+
+/// Draw the source panel on the right when it's open and there's a
+/// document. Returns the whole source when "Copy source" was clicked.
+pub fn draw_source_panel(
+    ctx: &Context,
+    state: &mut SourcePanelState,
+    doc: Option<&SvgDocument>,
+) -> Option<String> {
+    let Some(doc) = doc.filter(|_| state.open) else {
+        // Made again when next shown; the document may have changed
+        state.view = None;
+        return None;
+    };
+    if state.view.as_ref().is_none_or(|view| view.key != key(doc)) {
+        state.view = Some(SourceView::new(doc));
+    }
+    let view = state.view.as_ref()?;
+
+    let mut copy = None;
+    egui::SidePanel::right("source_panel")
+        .resizable(true)
+        .default_width(420.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.strong("Source");
+                if ui
+                    .button("Copy source")
+                    .on_hover_text(format!(
+                        "Copy all {} of it",
+                        format_size(view.text.len() as u64)
+                    ))
+                    .clicked()
+                {
+                    copy = Some(view.text.clone());
+                }
+            });
+            if view.shown < view.text.len() {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!(
+                        "Showing the first {} of {}; Copy source copies all of it",
+                        format_size(view.shown as u64),
+                        format_size(view.text.len() as u64)
+                    ),
+                );
+            }
+            ui.separator();
+
+            let font = TextStyle::Monospace.resolve(ui.style());
+            let row_height = ui.text_style_height(&TextStyle::Monospace);
+            let number_width = view.line_starts.len().to_string().len();
+            let dark = ui.visuals().dark_mode;
+            egui::ScrollArea::both()
+                .auto_shrink([false, false])
+                .show_rows(ui, row_height, view.line_starts.len(), |ui, rows| {
+                    for index in rows {
+                        let job = view.line_job(index, number_width, &font, dark);
+                        ui.add(egui::Label::new(job).extend());
+                    }
+                });
+        });
+    copy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(text: &str) -> Vec<(&str, Token)> {
+        highlight(text)
+            .into_iter()
+            .map(|(range, token)| (&text[range], token))
+            .collect()
+    }
+
+    #[test]
+    fn test_highlight_tags_and_attributes() {
+        assert_eq!(
+            tokens(r#"<svg width="10"><rect x='1'/></svg>"#),
+            vec![
+                ("<", Token::Markup),
+                ("svg", Token::Name),
+                ("width", Token::Attribute),
+                ("=", Token::Markup),
+                ("\"10\"", Token::Value),
+                (">", Token::Markup),
+                ("<", Token::Markup),
+                ("rect", Token::Name),
+                ("x", Token::Attribute),
+                ("=", Token::Markup),
+                ("'1'", Token::Value),
+                ("/>", Token::Markup),
+                ("</", Token::Markup),
+                ("svg", Token::Name),
+                (">", Token::Markup),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_highlight_comments_and_unclosed_markup() {
+        let text = "<?xml version=\"1.0\"?>\n<!-- a <b> c -->\n<g>text<rect x=\"1";
+        let spans = tokens(text);
+        assert_eq!(spans[0], ("<?xml version=\"1.0\"?>", Token::Comment));
+        assert_eq!(spans[1], ("<!-- a <b> c -->", Token::Comment));
+        assert_eq!(spans.last(), Some(&("\"1", Token::Value)));
+        // Character data isn't colored
+        assert!(spans.iter().all(|(s, _)| !s.contains("text")));
+    }
+}
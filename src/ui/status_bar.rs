@@ -1,16 +1,110 @@
 use egui::Ui;
 
+use crate::color_management::ColorManagement;
+use crate::eyedropper;
+use crate::renderer::RenderScale;
 use crate::svg_document::SvgDocument;
 use crate::viewport::Viewport;
 
+/// Status bar readouts the user clicked this frame.
+#[derive(Default)]
+pub struct StatusBarAction {
+    pub edit_rotation: bool,
+    /// Undo rotation and mirroring, keeping the zoom and pan
+    pub reset_orientation: bool,
+    pub edit_position: bool,
+    /// A zoom factor typed into the zoom readout
+    pub set_zoom: Option<f32>,
+    /// The index of a file whose number was typed into the position readout
+    pub jump_to: Option<usize>,
+}
+
+/// The readouts that can be typed into.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Readout {
+    Zoom,
+    Position,
+}
+
+/// A readout while it's being typed into; one at a time.
+#[derive(Default)]
+pub struct ReadoutEntry {
+    editing: Option<Readout>,
+    text: String,
+}
+
+impl ReadoutEntry {
+    pub fn is_editing(&self) -> bool {
+        self.editing.is_some()
+    }
+
+    fn start(&mut self, readout: Readout, text: String) {
+        self.editing = Some(readout);
+        self.text = text;
+    }
+
+    /// A focused text box for the readout being edited, returning the text
+    /// when Enter finishes it. Escape or clicking away just stops editing.
+    fn show(&mut self, ui: &mut Ui, width: f32, hint: &str) -> Option<String> {
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut self.text)
+                .desired_width(width)
+                .hint_text(hint),
+        );
+        if !response.has_focus() && !response.lost_focus() {
+            response.request_focus();
+        }
+        if !response.lost_focus() {
+            return None;
+        }
+        self.editing = None;
+        ui.input(|i| i.key_pressed(egui::Key::Enter))
+            .then(|| self.text.clone())
+    }
+}
+
+/// Parse a typed zoom such as `250` or `12.5%` into a factor.
+pub fn parse_zoom_percent(text: &str) -> Option<f32> {
+    let text = text.trim();
+    let text = text.strip_suffix('%').unwrap_or(text).trim();
+    let percent = text.parse::<f32>().ok()?;
+    (percent.is_finite() && percent > 0.0).then_some(percent / 100.0)
+}
+
+/// Parse a typed file number, counting from 1, into an index.
+pub fn parse_file_number(text: &str) -> Option<usize> {
+    let number = text.trim().parse::<usize>().ok()?;
+    number.checked_sub(1)
+}
+
+/// What the status bar says about the current render and the pointer over it.
+pub struct RenderInfo {
+    pub size: Option<(u32, u32)>,
+    pub scale: RenderScale,
+    pub color: ColorManagement,
+    /// Times larger the render was drawn before scaling down
+    pub supersample: u32,
+    /// The document point under the pointer, while it's over the canvas
+    pub cursor: Option<[f32; 2]>,
+    /// The rendered color under the pointer, while the eyedropper is on
+    pub color_under_pointer: Option<[u8; 4]>,
+}
+
+/// A document point as the status bar shows it and Ctrl+Shift+C copies it.
+pub fn format_coordinates([x, y]: [f32; 2]) -> String {
+    format!("x: {x:.1}, y: {y:.1}")
+}
+
 pub fn draw_status_bar(
     ui: &mut Ui,
     doc: Option<&SvgDocument>,
     viewport: &Viewport,
     position_display: &str,
     error_msg: Option<&str>,
-    render_size: Option<(u32, u32)>,
-) {
+    render: RenderInfo,
+    entry: &mut ReadoutEntry,
+) -> StatusBarAction {
+    let mut action = StatusBarAction::default();
     ui.horizontal(|ui| {
         if let Some(err) = error_msg {
             ui.colored_label(egui::Color32::RED, err);
@@ -18,18 +112,159 @@ pub fn draw_status_bar(
         }
 
         if let Some(doc) = doc {
-            ui.label(doc.filename());
+            let name = ui.label(doc.display_name());
+            if doc.metadata().title.is_some() {
+                name.on_hover_text(doc.url().unwrap_or(doc.filename()));
+            }
             ui.separator();
-            ui.label(format!("{}x{}", doc.width as u32, doc.height as u32));
-            if let Some((rw, rh)) = render_size {
+            ui.label(format!("{}x{}", doc.width() as u32, doc.height() as u32));
+            if let Some(warning) = doc.size_warning() {
+                ui.colored_label(ui.visuals().warn_fg_color, "\u{26A0}")
+                    .on_hover_text(warning);
+            }
+            if let Some(notice) = doc.aspect_notice() {
+                let hint = if doc.is_meet_forced() {
+                    "Shown centered instead (View \u{25B8} Fit whole viewBox)"
+                } else {
+                    "View \u{25B8} Fit whole viewBox shows it centered"
+                };
+                ui.colored_label(ui.visuals().warn_fg_color, "\u{2194}")
+                    .on_hover_text(format!("{notice}\n{hint}"));
+            }
+            let warnings = doc.warnings();
+            if !warnings.is_empty() {
+                let badge = egui::RichText::new(format!("\u{26A0} {}", warnings.len()))
+                    .color(ui.visuals().warn_fg_color);
+                ui.menu_button(badge, |ui| {
+                    ui.label(egui::RichText::new("Not shown in this render").strong());
+                    for warning in warnings {
+                        ui.label(warning);
+                    }
+                })
+                .response
+                .on_hover_text("Some of the document couldn't be loaded");
+            }
+            if let Some([r, g, b]) = doc.declared_background() {
+                let (rect, response) =
+                    ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+                ui.painter()
+                    .rect_filled(rect, 2.0, egui::Color32::from_rgb(r, g, b));
+                ui.painter().rect_stroke(
+                    rect,
+                    2.0,
+                    ui.visuals().widgets.noninteractive.bg_stroke,
+                    egui::StrokeKind::Inside,
+                );
+                response.on_hover_text(format!(
+                    "Declares background #{r:02x}{g:02x}{b:02x} \
+                     (View \u{25B8} Use document background when declared)"
+                ));
+            }
+            if let Some((rw, rh)) = render.size {
                 ui.separator();
                 ui.label(format!("Render: {}x{}", rw, rh));
             }
+            if render.scale != RenderScale::Full {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("@ {}", render.scale.name()),
+                )
+                .on_hover_text("Reduced render resolution (View \u{25B8} Render resolution)");
+            }
+            if render.supersample > 1 {
+                ui.label(format!("HQ {}\u{00D7}", render.supersample))
+                    .on_hover_text(
+                        "Rendered larger and scaled down for cleaner detail \
+                         (View \u{25B8} High quality downscaling)",
+                    );
+            }
+            if let Some(transform) = render.color.label() {
+                ui.separator();
+                ui.label(transform).on_hover_text(
+                    "Canvas colors converted for the display (View \u{25B8} Color management)",
+                );
+            }
+            ui.separator();
+            if entry.editing == Some(Readout::Zoom) {
+                ui.label("Zoom:");
+                if let Some(text) = entry.show(ui, 48.0, "%") {
+                    action.set_zoom = parse_zoom_percent(&text);
+                }
+            } else if ui
+                .add(
+                    egui::Button::new(format!("Zoom: {:.0}%", viewport.zoom_percent()))
+                        .frame(false),
+                )
+                .on_hover_text("Set zoom")
+                .clicked()
+            {
+                entry.start(Readout::Zoom, format!("{:.0}", viewport.zoom_percent()));
+            }
+            ui.separator();
+            // Stands out while the document is turned, and clicking puts it back
+            let reoriented = viewport.is_reoriented();
+            let mut orientation = egui::RichText::new(format_orientation(viewport));
+            if reoriented {
+                orientation = orientation.color(ui.visuals().warn_fg_color);
+            }
+            let response = ui
+                .add(egui::Button::new(orientation).frame(false))
+                .on_hover_text(if reoriented {
+                    "Click to undo rotation and mirroring; right-click to set the rotation \
+                     (Alt+R)"
+                } else {
+                    "Set rotation (Alt+R)"
+                });
+            if response.clicked() {
+                if reoriented {
+                    action.reset_orientation = true;
+                } else {
+                    action.edit_rotation = true;
+                }
+            }
+            if response.secondary_clicked() {
+                action.edit_rotation = true;
+            }
             ui.separator();
-            ui.label(format!("Zoom: {:.0}%", viewport.zoom_percent()));
+            action.edit_position = ui
+                .add(
+                    egui::Button::new(format!("Pan: {:.0}, {:.0}", viewport.pan.x, viewport.pan.y))
+                        .frame(false),
+                )
+                .on_hover_text("Position the image")
+                .clicked();
+            if let Some(point) = render.cursor {
+                ui.separator();
+                ui.monospace(format_coordinates(point))
+                    .on_hover_text("Under the pointer, in document units (Ctrl+Shift+C copies)");
+            }
+            if let Some(color) = render.color_under_pointer {
+                ui.separator();
+                let [r, g, b, a] = color;
+                let (swatch, _) =
+                    ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+                ui.painter().rect_filled(
+                    swatch,
+                    2.0,
+                    egui::Color32::from_rgba_unmultiplied(r, g, b, a),
+                );
+                ui.monospace(eyedropper::format_color(color))
+                    .on_hover_text("Under the pointer; click to copy");
+            }
             if !position_display.is_empty() {
                 ui.separator();
-                ui.label(position_display);
+                if entry.editing == Some(Readout::Position) {
+                    ui.label("Go to file:");
+                    if let Some(text) = entry.show(ui, 40.0, "#") {
+                        action.jump_to = parse_file_number(&text);
+                    }
+                } else if ui
+                    .add(egui::Button::new(position_display).frame(false))
+                    .on_hover_text("Go to a file by its number")
+                    .clicked()
+                {
+                    entry.start(Readout::Position, String::new());
+                }
             }
             ui.separator();
             ui.label(doc.file_size_display());
@@ -37,4 +272,64 @@ pub fn draw_status_bar(
             ui.label("No file loaded");
         }
     });
+    action
+}
+
+/// Whole angles without decimals, anything else to one decimal place.
+pub fn format_angle(degrees: f32) -> String {
+    if degrees.fract() == 0.0 {
+        format!("\u{21BB} {degrees:.0}°")
+    } else {
+        format!("\u{21BB} {degrees:.1}°")
+    }
+}
+
+/// The rotation, followed by ⇔ and ⇕ for horizontal and vertical mirroring.
+pub fn format_orientation(viewport: &Viewport) -> String {
+    let mut text = format_angle(viewport.rotation_deg);
+    if viewport.mirror_h {
+        text.push_str(" \u{21D4}");
+    }
+    if viewport.mirror_v {
+        text.push_str(" \u{21D5}");
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_orientation() {
+        let mut viewport = Viewport::default();
+        assert_eq!(format_orientation(&viewport), "\u{21BB} 0°");
+        viewport.rotate_cw();
+        viewport.mirror_h = true;
+        assert_eq!(format_orientation(&viewport), "\u{21BB} 90° \u{21D4}");
+        viewport.set_rotation(12.5);
+        viewport.mirror_v = true;
+        assert_eq!(
+            format_orientation(&viewport),
+            "\u{21BB} 12.5° \u{21D4} \u{21D5}"
+        );
+    }
+
+    #[test]
+    fn test_parse_zoom_percent() {
+        assert_eq!(parse_zoom_percent("250"), Some(2.5));
+        assert_eq!(parse_zoom_percent(" 12.5 % "), Some(0.125));
+        assert_eq!(parse_zoom_percent("0"), None);
+        assert_eq!(parse_zoom_percent("-50"), None);
+        assert_eq!(parse_zoom_percent("big"), None);
+    }
+
+    #[test]
+    fn test_parse_file_number() {
+        assert_eq!(parse_file_number("1"), Some(0));
+        assert_eq!(parse_file_number(" 47 "), Some(46));
+        assert_eq!(parse_file_number("0"), None);
+        assert_eq!(parse_file_number("-3"), None);
+        assert_eq!(parse_file_number("3.5"), None);
+    }
 }
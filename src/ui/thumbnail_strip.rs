@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+
+use egui::{Context, Rect, Sense, Vec2};
+
+use crate::thumbnails::{Thumbnail, THUMBNAIL_SIZE};
+use crate::ui::thumbnail_textures::{Shown, ThumbnailTextures};
+
+/// Room around each thumbnail, in logical pixels.
+const PADDING: f32 = 4.0;
+
+/// The thumbnail strip along the bottom of the window.
+#[derive(Default)]
+pub struct ThumbnailStripState {
+    pub open: bool,
+    /// The file scrolled to last, so the strip follows navigation
+    scrolled_to: Option<usize>,
+}
+
+impl ThumbnailStripState {
+    pub fn new(open: bool) -> Self {
+        Self {
+            open,
+            scrolled_to: None,
+        }
+    }
+}
+
+/// Draw the strip of `files` with `current` highlighted. Returns the index of
+/// a thumbnail that was clicked, to be opened.
+pub fn draw_thumbnail_strip(
+    ctx: &Context,
+    state: &mut ThumbnailStripState,
+    textures: &mut ThumbnailTextures,
+    files: &[PathBuf],
+    current: usize,
+) -> Option<usize> {
+    if !state.open || files.len() < 2 {
+        state.scrolled_to = None;
+        return None;
+    }
+    textures.poll(ctx);
+
+    let cell = Vec2::splat(THUMBNAIL_SIZE as f32 + 2.0 * PADDING);
+    let follow = state.scrolled_to != Some(current);
+    state.scrolled_to = Some(current);
+    let mut clicked = None;
+    egui::TopBottomPanel::bottom("thumbnail_strip")
+        .resizable(false)
+        .show(ctx, |ui| {
+            egui::ScrollArea::horizontal().show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    for (index, path) in files.iter().enumerate() {
+                        let (rect, response) = ui.allocate_exact_size(cell, Sense::click());
+                        if index == current && follow {
+                            response.scroll_to_me(Some(egui::Align::Center));
+                        }
+                        if ui.is_rect_visible(rect) {
+                            if index == current {
+                                ui.painter()
+                                    .rect_filled(rect, 4.0, ui.visuals().selection.bg_fill);
+                            }
+                            let thumbnail = textures.get(path, THUMBNAIL_SIZE);
+                            draw_thumbnail(ui, rect.shrink(PADDING), thumbnail);
+                        }
+                        let name = path.file_name().unwrap_or(path.as_os_str());
+                        if response.on_hover_text(name.to_string_lossy()).clicked() {
+                            clicked = Some(index);
+                        }
+                    }
+                });
+            });
+        });
+    clicked.filter(|&index| index != current)
+}
+
+/// A thumbnail centered in `rect`: the render, a placeholder while it's
+/// rendering, or a warning sign when the file couldn't be rendered.
+pub fn draw_thumbnail(ui: &egui::Ui, rect: Rect, thumbnail: Option<&Thumbnail<Shown>>) {
+    let painter = ui.painter();
+    let visuals = ui.visuals();
+    match thumbnail {
+        Some(Thumbnail::Ready(shown)) => {
+            let size = shown.texture.size_vec2();
+            let scale = (rect.width() / size.x).min(rect.height() / size.y);
+            // Never enlarged past twice its pixels, to stay sharp enough
+            let scale = scale.min(2.0);
+            let shown_rect = Rect::from_center_size(rect.center(), size * scale);
+            painter.image(
+                shown.texture.id(),
+                shown_rect,
+                Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                egui::Color32::WHITE,
+            );
+        }
+        Some(Thumbnail::Broken) => {
+            painter.rect_stroke(
+                rect,
+                2.0,
+                egui::Stroke::new(1.0, visuals.warn_fg_color),
+                egui::StrokeKind::Inside,
+            );
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "\u{26A0}",
+                egui::FontId::proportional(24.0),
+                visuals.warn_fg_color,
+            );
+        }
+        Some(Thumbnail::Pending) | None => {
+            painter.rect_filled(rect, 2.0, visuals.faint_bg_color);
+        }
+    }
+}
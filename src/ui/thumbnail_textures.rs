@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use egui::{ColorImage, Context, TextureHandle, TextureOptions};
+
+use crate::thumbnails::{Thumbnail, ThumbnailCache, ThumbnailLoader, MAX_CACHED};
+
+/// A thumbnail uploaded for display, and the size it was rendered to fit.
+pub struct Shown {
+    pub texture: TextureHandle,
+    pub size: u32,
+}
+
+/// Thumbnail textures shared by the strip and the gallery, rendered in the
+/// background as they're asked for. Everything is dropped once neither has
+/// asked for a frame.
+#[derive(Default)]
+pub struct ThumbnailTextures {
+    loader: Option<ThumbnailLoader>,
+    cache: Option<ThumbnailCache<Shown>>,
+    /// Sizes being rendered, so a thumbnail isn't asked for twice
+    requested: HashMap<PathBuf, u32>,
+    used: bool,
+}
+
+impl ThumbnailTextures {
+    /// Take in the thumbnails rendered since the last call. Call once a frame
+    /// while showing any.
+    pub fn poll(&mut self, ctx: &Context) {
+        self.used = true;
+        let loader = self.loader.get_or_insert_with(|| {
+            let ctx = ctx.clone();
+            ThumbnailLoader::start(move || ctx.request_repaint())
+        });
+        let cache = self
+            .cache
+            .get_or_insert_with(|| ThumbnailCache::new(MAX_CACHED));
+        for (path, size, result) in loader.finished() {
+            if self.requested.get(&path) == Some(&size) {
+                self.requested.remove(&path);
+            }
+            let thumbnail = match result {
+                Ok(pixmap) => {
+                    let pixels = [pixmap.width() as usize, pixmap.height() as usize];
+                    let image = ColorImage::from_rgba_premultiplied(pixels, pixmap.data());
+                    let name = format!("thumbnail:{}", path.display());
+                    Thumbnail::Ready(Shown {
+                        texture: ctx.load_texture(name, image, TextureOptions::LINEAR),
+                        size,
+                    })
+                }
+                Err(e) => {
+                    log::debug!("No thumbnail for {}: {e}", path.display());
+                    Thumbnail::Broken
+                }
+            };
+            cache.insert(path, thumbnail);
+        }
+    }
+
+    /// The thumbnail of `path` to show `size` pixels across, asking for it to
+    /// be rendered if it hasn't been at that size. A smaller one is shown
+    /// until the larger one is ready.
+    pub fn get(&mut self, path: &Path, size: u32) -> Option<&Thumbnail<Shown>> {
+        let (loader, cache) = (self.loader.as_ref()?, self.cache.as_mut()?);
+        let in_flight = self.requested.get(path).is_some_and(|&asked| asked >= size);
+        let needed = match cache.get(path) {
+            None => true,
+            Some(Thumbnail::Ready(shown)) => shown.size < size,
+            Some(Thumbnail::Pending | Thumbnail::Broken) => false,
+        };
+        if needed && !in_flight {
+            loader.request(path.to_path_buf(), size);
+            self.requested.insert(path.to_path_buf(), size);
+            if cache.get(path).is_none() {
+                cache.insert(path.to_path_buf(), Thumbnail::Pending);
+            }
+        }
+        cache.get(path)
+    }
+
+    /// Drop the loader and textures if nothing polled since the last call.
+    /// Call once a frame, after the strip and gallery are drawn.
+    pub fn release_unused(&mut self) {
+        if !std::mem::take(&mut self.used) {
+            *self = Self::default();
+        }
+    }
+}
@@ -1,52 +1,343 @@
 use egui::Ui;
 
+use crate::color_management::{ColorManagement, DisplayProfile};
+use crate::guides::GuideSet;
+use crate::renderer::{PreviewEffects, RenderQuality, RenderScale};
+use crate::svg_document::DocumentBackground;
+use crate::ui::diff_overlay::OverlaySettings;
+use crate::view_action::ViewAction;
+use crate::viewport::{ViewPolicy, ZoomPreset};
+
 #[derive(Default)]
 pub struct ToolbarAction {
     pub open_file: bool,
+    /// Open the SVG markup on the clipboard as a new document
+    pub paste_svg: bool,
     pub prev_file: bool,
     pub next_file: bool,
+    pub first_file: bool,
+    pub last_file: bool,
+    /// Go to the file at this index
+    pub jump_to: Option<usize>,
+    /// Navigate only the files matching this, or every file when it's empty
+    pub set_filter: Option<String>,
     pub fit_to_window: bool,
+    pub fit_to_content: bool,
     pub actual_size: bool,
     pub zoom_in: bool,
     pub zoom_out: bool,
     pub rotate_cw: bool,
     pub rotate_ccw: bool,
+    pub rotate_step_cw: bool,
+    pub rotate_step_ccw: bool,
+    /// Step by 1° instead of the configured rotation step
+    pub rotate_fine: bool,
+    pub edit_rotation: bool,
     pub mirror_h: bool,
     pub mirror_v: bool,
     pub export: bool,
+    pub export_again: bool,
+    /// Move the open file to the trash, after asking unless `delete_now`
+    pub delete_file: bool,
+    pub delete_now: bool,
     pub copy_clipboard: bool,
+    /// Copy the SVG markup rather than a render
+    pub copy_source: bool,
+    /// Copy just what the canvas shows, as it shows it
+    pub copy_view: bool,
+    /// Save the SVG source to a file, as for a pasted document
+    pub save_svg: bool,
     pub toggle_bg: bool,
+    pub use_document_bg: bool,
+    pub use_theme_bg: bool,
     pub toggle_theme: bool,
     pub reset_view: bool,
+    /// Move the image by this much, in logical pixels
+    pub pan_delta: egui::Vec2,
+    /// Center the image without changing the zoom
+    pub center_pan: bool,
+    pub set_zoom: Option<ZoomPreset>,
+    pub view_policy: Option<ViewPolicy>,
+    pub render_scale: Option<RenderScale>,
+    pub render_quality: Option<RenderQuality>,
+    pub show_event_log: bool,
+    pub toggle_pixel_inspection: bool,
+    pub reload: bool,
+    pub toggle_auto_reload: bool,
+    pub toggle_constrain_pan: bool,
+    pub toggle_rulers: bool,
+    pub toggle_thumbnails: bool,
+    /// Show every file as a grid in place of the canvas, or the canvas again
+    pub toggle_gallery: bool,
+    pub folder_summary: bool,
+    pub toggle_declared_background: bool,
+    pub toggle_copy_over_declared: bool,
+    pub toggle_copy_white_background: bool,
+    pub toggle_font_substitutions: bool,
+    pub color_management: Option<ColorManagement>,
+    pub effects: Option<PreviewEffects>,
+    pub toggle_force_meet: bool,
+    pub toggle_high_quality_downscale: bool,
+    pub properties: bool,
+    pub image_inspector: bool,
+    pub toggle_source_panel: bool,
+    /// Return to the view before the last zoom, fit or rotation, or redo it
+    pub view_back: bool,
+    pub view_forward: bool,
+    /// Dragging on the canvas zooms to the dragged rectangle
+    pub toggle_zoom_select: bool,
+    pub toggle_lock_view: bool,
+    /// Stretch the document to fill the window, or show it in proportion again
+    pub stretch: Option<bool>,
+    /// Copy the document coordinates under the pointer
+    pub copy_coordinates: bool,
+    /// Dragging on the canvas measures a distance
+    pub toggle_measure: bool,
+    pub clear_measurements: bool,
+    /// Clicking on the canvas copies the color under the pointer
+    pub toggle_eyedropper: bool,
+    /// Show the previous file under the current one, as set
+    pub diff_overlay: Option<OverlaySettings>,
+    /// Close the window, which saves the session on the way out
+    pub quit: bool,
+}
+
+impl ToolbarAction {
+    /// The view changes this action asks for, in the order they apply.
+    /// `rotation_step` is the step, in degrees, for the rotate-step buttons.
+    pub fn view_actions(&self, rotation_step: f32) -> Vec<ViewAction> {
+        // Both step directions at once step counter-clockwise, as one step
+        let step = if self.rotate_step_ccw {
+            -rotation_step
+        } else {
+            rotation_step
+        };
+        let wanted = [
+            (self.fit_to_window, ViewAction::FitToWindow),
+            (self.fit_to_content, ViewAction::FitToContent),
+            (self.actual_size, ViewAction::ActualSize),
+            (self.zoom_in, ViewAction::ZoomIn),
+            (self.zoom_out, ViewAction::ZoomOut),
+            (self.rotate_cw, ViewAction::RotateCw),
+            (self.rotate_ccw, ViewAction::RotateCcw),
+            (
+                self.rotate_step_cw || self.rotate_step_ccw,
+                ViewAction::RotateBy(step),
+            ),
+            (self.mirror_h, ViewAction::MirrorH),
+            (self.mirror_v, ViewAction::MirrorV),
+            (self.reset_view, ViewAction::ResetView),
+            (
+                self.pan_delta != egui::Vec2::ZERO,
+                ViewAction::PanBy(self.pan_delta),
+            ),
+            (self.center_pan, ViewAction::CenterPan),
+            (self.stretch == Some(true), ViewAction::StretchToWindow),
+            (self.stretch == Some(false), ViewAction::FitToWindow),
+        ];
+        let preset = self.set_zoom.map(|preset| match preset {
+            ZoomPreset::Percent(percent) => ViewAction::SetZoom(percent as f32 / 100.0),
+            ZoomPreset::Fit => ViewAction::FitToWindow,
+            ZoomPreset::FitWidth => ViewAction::FitWidth,
+            ZoomPreset::FitHeight => ViewAction::FitHeight,
+        });
+        wanted
+            .into_iter()
+            .filter_map(|(on, action)| on.then_some(action))
+            .chain(preset)
+            .collect()
+    }
 }
 
-pub fn draw_toolbar(ui: &mut Ui, has_file: bool) -> ToolbarAction {
+/// What the toolbar can offer this frame.
+#[derive(Clone, Copy, Default)]
+pub struct ToolbarState {
+    pub has_file: bool,
+    /// The document came from a file that can be read again
+    pub can_reload: bool,
+    /// The open file's folder has SVG files to summarize
+    pub has_folder: bool,
+    /// There are neighbouring files to step through; not for downloads
+    pub can_navigate: bool,
+    /// An earlier export this session can be repeated
+    pub can_export_again: bool,
+    /// Magnified renders show hard pixel edges
+    pub pixel_inspection: bool,
+    /// The document reloads when its file changes
+    pub auto_reload: bool,
+    /// Panning keeps part of the image on screen
+    pub constrain_pan: bool,
+    /// Rulers run along the canvas edges
+    pub show_rulers: bool,
+    pub show_thumbnails: bool,
+    pub gallery: bool,
+    /// The document's root `<svg>` declares a background color
+    pub has_declared_background: bool,
+    /// A declared background replaces the checkerboard
+    pub use_declared_background: bool,
+    /// Copies are flattened onto the declared background
+    pub copy_over_declared: bool,
+    /// Copies are flattened onto white
+    pub copy_white_background: bool,
+    /// Text drawn in a substitute font is underlined
+    pub show_font_substitutions: bool,
+    pub color_management: ColorManagement,
+    pub effects: PreviewEffects,
+    /// Whether the viewBox is shown centered with "meet" instead of the
+    /// document's own preserveAspectRatio; `None` when that's the default
+    pub force_meet: Option<bool>,
+    /// Renders that shrink the document a lot are supersampled
+    pub high_quality_downscale: bool,
+    /// The source panel is showing
+    pub source_panel: bool,
+    /// The zoom, in percent, and the preset it's at
+    pub zoom_percent: f32,
+    pub zoom_preset: Option<ZoomPreset>,
+    /// There are earlier, or undone, views to return to
+    pub can_view_back: bool,
+    pub can_view_forward: bool,
+    /// Dragging zooms to a rectangle rather than panning
+    pub zoom_select: bool,
+    /// Files stepped to are shown with the same view, not fitted
+    pub lock_view: bool,
+    /// The document is shown out of its proportions
+    pub stretched: bool,
+    /// Dragging measures rather than panning
+    pub measure_mode: bool,
+    /// Clicking copies the color under the pointer
+    pub eyedropper: bool,
+    pub diff_overlay: OverlaySettings,
+    pub view_policy: ViewPolicy,
+    pub render_scale: RenderScale,
+    /// Smooth or crisp edges
+    pub render_quality: RenderQuality,
+}
+
+pub fn draw_toolbar(
+    ui: &mut Ui,
+    state: ToolbarState,
+    document_bg: Option<&DocumentBackground>,
+    copy_include_background: &mut bool,
+    guides: &mut GuideSet,
+    filter: &mut String,
+) -> ToolbarAction {
     let mut action = ToolbarAction::default();
+    let has_file = state.has_file;
 
     ui.horizontal(|ui| {
         action.open_file = ui.button("Open").clicked();
+        action.paste_svg = ui
+            .button("Paste")
+            .on_hover_text("Open SVG markup from the clipboard (Ctrl+V)")
+            .clicked();
+        action.reload = ui
+            .add_enabled(state.can_reload, egui::Button::new("Reload"))
+            .on_hover_text("Read the file again, keeping the view (F5 or Ctrl+Shift+R)")
+            .clicked();
 
         ui.separator();
 
-        ui.add_enabled_ui(has_file, |ui| {
+        ui.add_enabled_ui(state.can_navigate, |ui| {
             action.prev_file = ui
                 .button("\u{25C0}")
                 .on_hover_text("Previous file")
                 .clicked();
             action.next_file = ui.button("\u{25B6}").on_hover_text("Next file").clicked();
+            if ui
+                .add(
+                    egui::TextEdit::singleline(filter)
+                        .hint_text("Filter")
+                        .desired_width(80.0),
+                )
+                .on_hover_text(
+                    "Step through only the files whose names contain this, \
+                     or match it with * and ?",
+                )
+                .changed()
+            {
+                action.set_filter = Some(filter.clone());
+            }
+            action.toggle_lock_view = ui
+                .add(egui::Button::new("\u{1F512}").selected(state.lock_view))
+                .on_hover_text(
+                    "Lock view: show the previous and next files at the same zoom, \
+                     pan and rotation, for comparing them (L)",
+                )
+                .clicked();
+            let mut overlay = state.diff_overlay;
+            if ui
+                .add(egui::Button::new("\u{25D0}").selected(overlay.enabled))
+                .on_hover_text("Diff overlay: show the previous file under this one")
+                .clicked()
+            {
+                overlay.enabled = !overlay.enabled;
+            }
+            ui.menu_button("\u{25BC}", |ui| {
+                ui.add(
+                    egui::Slider::new(&mut overlay.opacity, 0.0..=1.0)
+                        .text("Previous file opacity"),
+                );
+                ui.checkbox(&mut overlay.tint, "Tint previous red, current green")
+                    .on_hover_text("Dark lines show which file they're from");
+            })
+            .response
+            .on_hover_text("Diff overlay settings");
+            if overlay != state.diff_overlay {
+                action.diff_overlay = Some(overlay);
+            }
         });
+        action.folder_summary = ui
+            .add_enabled(state.has_folder, egui::Button::new("\u{03A3}"))
+            .on_hover_text("Folder summary\u{2026}")
+            .clicked();
+        action.properties = ui
+            .add_enabled(has_file, egui::Button::new("\u{2139}"))
+            .on_hover_text("Properties\u{2026} (Ctrl+I)")
+            .clicked();
+        action.toggle_source_panel = ui
+            .add_enabled(
+                has_file,
+                egui::Button::new("</>").selected(state.source_panel && has_file),
+            )
+            .on_hover_text("Source (Ctrl+U)")
+            .clicked();
 
         ui.separator();
 
         ui.add_enabled_ui(has_file, |ui| {
             action.fit_to_window = ui
                 .button("Fit")
-                .on_hover_text("Fit to window (Ctrl+0)")
+                .on_hover_text("Fit to window (Ctrl+0, or double-click the image)")
+                .clicked();
+            action.fit_to_content = ui
+                .button("Fit content")
+                .on_hover_text("Fit what's drawn, not the whole canvas (Ctrl+Shift+0)")
                 .clicked();
+            if ui
+                .add(egui::Button::new("Stretch").selected(state.stretched))
+                .on_hover_text("Stretch to fill the window, ignoring the document's proportions")
+                .clicked()
+            {
+                action.stretch = Some(!state.stretched);
+            }
             action.actual_size = ui
                 .button("1:1")
                 .on_hover_text("Actual size (Ctrl+1)")
                 .clicked();
+            let shown = state
+                .zoom_preset
+                .map_or_else(|| format!("{:.0}%", state.zoom_percent), |p| p.name());
+            egui::ComboBox::from_id_salt("zoom_presets")
+                .selected_text(shown)
+                .width(84.0)
+                .show_ui(ui, |ui| {
+                    for preset in ZoomPreset::all() {
+                        let current = state.zoom_preset == Some(*preset);
+                        if ui.selectable_label(current, preset.name()).clicked() {
+                            action.set_zoom = Some(*preset);
+                        }
+                    }
+                });
         });
 
         ui.separator();
@@ -57,6 +348,26 @@ pub fn draw_toolbar(ui: &mut Ui, has_file: bool) -> ToolbarAction {
                 .button("\u{2212}")
                 .on_hover_text("Zoom out (Ctrl+-)")
                 .clicked();
+            action.toggle_zoom_select = ui
+                .add(egui::Button::new("\u{1F50D}").selected(state.zoom_select))
+                .on_hover_text("Drag a rectangle to zoom to (or Ctrl+drag)")
+                .clicked();
+            action.toggle_measure = ui
+                .add(egui::Button::new("\u{1F4CF}").selected(state.measure_mode))
+                .on_hover_text("Measure distances by dragging (M)")
+                .clicked();
+            action.toggle_eyedropper = ui
+                .add(egui::Button::new("\u{1F4A7}").selected(state.eyedropper))
+                .on_hover_text("Pick a color from the image (I)")
+                .clicked();
+            action.view_back = ui
+                .add_enabled(state.can_view_back, egui::Button::new("\u{21B6}"))
+                .on_hover_text("Previous view (Ctrl+Z, or the mouse Back button)")
+                .clicked();
+            action.view_forward = ui
+                .add_enabled(state.can_view_forward, egui::Button::new("\u{21B7}"))
+                .on_hover_text("Next view (Ctrl+Shift+Z, or the mouse Forward button)")
+                .clicked();
         });
 
         ui.separator();
@@ -91,14 +402,303 @@ pub fn draw_toolbar(ui: &mut Ui, has_file: bool) -> ToolbarAction {
                 .button("Copy")
                 .on_hover_text("Copy to clipboard (Ctrl+C)")
                 .clicked();
+            ui.menu_button("\u{25BC}", |ui| {
+                if ui
+                    .add_enabled(
+                        state.can_export_again,
+                        egui::Button::new("Export again (Ctrl+E)"),
+                    )
+                    .on_hover_text("Repeat the last export with the same settings")
+                    .on_disabled_hover_text("Export once first; this repeats it")
+                    .clicked()
+                {
+                    action.export_again = true;
+                    ui.close();
+                }
+                if ui
+                    .button("Copy view")
+                    .on_hover_text("Copy only what's on screen, at the screen's resolution")
+                    .clicked()
+                {
+                    action.copy_view = true;
+                    ui.close();
+                }
+                if ui
+                    .button("Copy SVG source (Alt+C)")
+                    .on_hover_text("Copy the markup as text, to paste into an editor")
+                    .clicked()
+                {
+                    action.copy_source = true;
+                    ui.close();
+                }
+                if ui
+                    .button("Save SVG as\u{2026}")
+                    .on_hover_text("Save the markup to a file, such as after pasting it")
+                    .clicked()
+                {
+                    action.save_svg = true;
+                    ui.close();
+                }
+                ui.separator();
+                ui.checkbox(copy_include_background, "Include background")
+                    .on_hover_text("Flatten the copy onto the canvas background");
+                let mut copy_over_declared = state.copy_over_declared;
+                if ui
+                    .add_enabled(
+                        state.has_declared_background,
+                        egui::Checkbox::new(&mut copy_over_declared, "Over document background"),
+                    )
+                    .on_hover_text("Flatten the copy onto the background the document declares")
+                    .on_disabled_hover_text("The document doesn't declare a background")
+                    .clicked()
+                {
+                    action.toggle_copy_over_declared = true;
+                }
+                let mut copy_white_background = state.copy_white_background;
+                if ui
+                    .checkbox(&mut copy_white_background, "White background")
+                    .on_hover_text(
+                        "Flatten the copy onto white, for apps that show transparency as black",
+                    )
+                    .clicked()
+                {
+                    action.toggle_copy_white_background = true;
+                }
+            });
         });
 
         ui.separator();
 
-        action.toggle_bg = ui
-            .button("BG")
-            .on_hover_text("Toggle background (T)")
-            .clicked();
+        ui.menu_button("View", |ui| {
+            ui.label("When the window resizes:");
+            for policy in ViewPolicy::all() {
+                if ui
+                    .radio(state.view_policy == *policy, policy.name())
+                    .clicked()
+                {
+                    action.view_policy = Some(*policy);
+                    ui.close();
+                }
+            }
+            ui.separator();
+            let mut auto_reload = state.auto_reload;
+            if ui
+                .checkbox(&mut auto_reload, "Reload on change")
+                .on_hover_text("Reload the file when it's saved elsewhere (F5 reloads now)")
+                .clicked()
+            {
+                action.toggle_auto_reload = true;
+            }
+            let mut use_declared = state.use_declared_background;
+            if ui
+                .checkbox(&mut use_declared, "Use document background when declared")
+                .on_hover_text(
+                    "Show the color a document's <svg> style or background attribute \
+                     asks for instead of the checkerboard",
+                )
+                .clicked()
+            {
+                action.toggle_declared_background = true;
+            }
+            let mut force_meet = state.force_meet.unwrap_or(false);
+            if ui
+                .add_enabled(
+                    state.force_meet.is_some(),
+                    egui::Checkbox::new(&mut force_meet, "Fit whole viewBox (meet)"),
+                )
+                .on_hover_text(
+                    "Show the viewBox centered and undistorted, ignoring the document's \
+                     preserveAspectRatio",
+                )
+                .clicked()
+            {
+                action.toggle_force_meet = true;
+            }
+            if ui
+                .add_enabled(has_file, egui::Button::new("Embedded images\u{2026}"))
+                .on_hover_text("List the document's raster images and save them")
+                .clicked()
+            {
+                action.image_inspector = true;
+                ui.close();
+            }
+            ui.separator();
+            ui.menu_button("Render resolution", |ui| {
+                for scale in RenderScale::all() {
+                    if ui
+                        .radio(state.render_scale == *scale, scale.name())
+                        .clicked()
+                    {
+                        action.render_scale = Some(*scale);
+                        ui.close();
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Render fewer pixels and upscale; exports stay full resolution");
+            ui.menu_button("Rendering", |ui| {
+                for quality in RenderQuality::all() {
+                    if ui
+                        .radio(state.render_quality == *quality, quality.name())
+                        .clicked()
+                    {
+                        action.render_quality = Some(*quality);
+                        ui.close();
+                    }
+                }
+            })
+            .response
+            .on_hover_text(
+                "Crisp draws edges and images without smoothing, for pixel art; \
+                 exports are drawn the same way",
+            );
+            ui.menu_button("Color management", |ui| {
+                let mut color = state.color_management;
+                if ui
+                    .checkbox(&mut color.enabled, "Convert for display")
+                    .on_hover_text("Convert canvas colors from sRGB to the display's profile")
+                    .clicked()
+                {
+                    action.color_management = Some(color);
+                }
+                ui.separator();
+                ui.label("Display profile");
+                for profile in DisplayProfile::all() {
+                    if ui
+                        .radio(color.profile == *profile, profile.name())
+                        .clicked()
+                    {
+                        action.color_management = Some(ColorManagement {
+                            enabled: true,
+                            profile: *profile,
+                        });
+                        ui.close();
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Only the canvas is converted; exports and copies stay sRGB");
+            ui.menu_button("Effects", |ui| {
+                let mut effects = state.effects;
+                let grayscale = ui.checkbox(&mut effects.grayscale, "Grayscale").clicked();
+                let invert = ui.checkbox(&mut effects.invert, "Invert colors").clicked();
+                if grayscale || invert {
+                    action.effects = Some(effects);
+                }
+            })
+            .response
+            .on_hover_text("Preview only; exports choose their own effects");
+            let mut high_quality = state.high_quality_downscale;
+            if ui
+                .checkbox(&mut high_quality, "High quality downscaling")
+                .on_hover_text(
+                    "Render documents shown at under half size at twice the resolution \
+                     and scale down, for cleaner thin lines (slower)",
+                )
+                .clicked()
+            {
+                action.toggle_high_quality_downscale = true;
+            }
+            let mut constrain_pan = state.constrain_pan;
+            if ui
+                .checkbox(&mut constrain_pan, "Keep image on screen")
+                .on_hover_text(
+                    "Stop panning before the image leaves the window; \
+                     turn off to pan freely",
+                )
+                .clicked()
+            {
+                action.toggle_constrain_pan = true;
+            }
+            let mut show_rulers = state.show_rulers;
+            if ui
+                .checkbox(&mut show_rulers, "Rulers (Ctrl+;)")
+                .on_hover_text("Show document units along the top and left of the canvas")
+                .clicked()
+            {
+                action.toggle_rulers = true;
+            }
+            let mut show_thumbnails = state.show_thumbnails;
+            if ui
+                .checkbox(&mut show_thumbnails, "Thumbnail strip")
+                .on_hover_text(
+                    "Show every file in the folder along the bottom; click one to open it",
+                )
+                .clicked()
+            {
+                action.toggle_thumbnails = true;
+            }
+            let mut gallery = state.gallery;
+            if ui
+                .add_enabled(
+                    state.can_navigate,
+                    egui::Checkbox::new(&mut gallery, "Gallery (Tab)"),
+                )
+                .on_hover_text("Show every file in the folder as a grid in place of the image")
+                .clicked()
+            {
+                action.toggle_gallery = true;
+            }
+            let mut pixel_inspection = state.pixel_inspection;
+            if ui
+                .checkbox(&mut pixel_inspection, "Pixelated magnification")
+                .on_hover_text("Show hard pixel edges while a render is shown enlarged")
+                .clicked()
+            {
+                action.toggle_pixel_inspection = true;
+            }
+            let mut show_substitutions = state.show_font_substitutions;
+            if ui
+                .checkbox(&mut show_substitutions, "Mark substituted fonts")
+                .on_hover_text(
+                    "Underline text drawn in another font because its own isn't installed",
+                )
+                .clicked()
+            {
+                action.toggle_font_substitutions = true;
+            }
+            ui.menu_button("Guides", |ui| {
+                for (preset, enabled) in guides.presets.iter().zip(guides.enabled.iter_mut()) {
+                    ui.checkbox(enabled, preset.name.as_str());
+                }
+            });
+        });
+
+        ui.menu_button("BG", |ui| {
+            if ui.button("Toggle checkerboard (T)").clicked() {
+                action.toggle_bg = true;
+                ui.close();
+            }
+            if ui.button("Theme background").clicked() {
+                action.use_theme_bg = true;
+                ui.close();
+            }
+            ui.add_enabled_ui(document_bg.is_some(), |ui| {
+                ui.horizontal(|ui| {
+                    let [r, g, b] = document_bg.map(|bg| bg.color).unwrap_or([128, 128, 128]);
+                    let (rect, _) =
+                        ui.allocate_exact_size(egui::vec2(14.0, 14.0), egui::Sense::hover());
+                    ui.painter()
+                        .rect_filled(rect, 2.0, egui::Color32::from_rgb(r, g, b));
+                    let hover = match document_bg {
+                        Some(bg) => format!(
+                            "#{r:02X}{g:02X}{b:02X} from {}; also used for exports",
+                            bg.source
+                        ),
+                        None => "No full-canvas solid fill found".to_string(),
+                    };
+                    if ui
+                        .button("Use document background")
+                        .on_hover_text(hover)
+                        .on_disabled_hover_text("No full-canvas solid fill found")
+                        .clicked()
+                    {
+                        action.use_document_bg = true;
+                        ui.close();
+                    }
+                });
+            });
+        });
         action.toggle_theme = ui
             .button("Theme")
             .on_hover_text("Toggle dark/light theme")
@@ -112,6 +712,15 @@ pub fn draw_toolbar(ui: &mut Ui, has_file: bool) -> ToolbarAction {
                 .on_hover_text("Reset view (Ctrl+R)")
                 .clicked();
         });
+
+        ui.separator();
+
+        ui.menu_button("Help", |ui| {
+            if ui.button("Event log").clicked() {
+                action.show_event_log = true;
+                ui.close();
+            }
+        });
     });
 
     action
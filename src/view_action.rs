@@ -0,0 +1,584 @@
+//! The view side of toolbar and shortcut actions: everything that only moves,
+//! zooms, rotates or mirrors the viewport. Dialogs, the clipboard and file IO
+//! stay in the app, which applies the returned [`ViewEffects`].
+
+use emath::{Rect, Vec2};
+
+use crate::viewport::{FitMode, Viewport};
+
+/// A change to the view.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ViewAction {
+    FitToWindow,
+    /// Fit what's drawn rather than the whole canvas
+    FitToContent,
+    /// Fit the document's shown width, or height, to the canvas
+    FitWidth,
+    FitHeight,
+    /// Scale width and height independently to fill the canvas
+    StretchToWindow,
+    ActualSize,
+    ZoomIn,
+    ZoomOut,
+    RotateCw,
+    RotateCcw,
+    /// Rotate by this many degrees, clockwise when positive
+    RotateBy(f32),
+    SetRotation(f32),
+    MirrorH,
+    MirrorV,
+    /// Undo rotation and mirroring only
+    ResetOrientation,
+    ResetView,
+    /// Move the image by this many logical pixels, as dragging it does
+    PanBy(Vec2),
+    /// Center the image again, keeping the zoom
+    CenterPan,
+    /// Zoom to exactly this factor, about the canvas center
+    SetZoom(f32),
+    /// Zoom so a rectangle dragged out on screen fills the canvas, given
+    /// where the image is shown
+    ZoomToRect {
+        selection: Rect,
+        display: Rect,
+    },
+}
+
+impl ViewAction {
+    /// Whether the view before this action is worth going back to. Panning
+    /// is left out; it's easily redone by hand.
+    pub fn is_history_step(&self) -> bool {
+        !matches!(self, ViewAction::PanBy(_) | ViewAction::CenterPan)
+    }
+}
+
+/// What an action needs to know about its surroundings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewContext {
+    /// The open document's size, if any
+    pub doc_size: Option<(f32, f32)>,
+    /// Bounds (x, y, width, height) of the open document's content
+    pub content: Option<[f32; 4]>,
+    /// The canvas size in logical pixels; zero before the first layout
+    pub area: (f32, f32),
+    /// Where zooming is centered, relative to the canvas center
+    pub zoom_center: Vec2,
+}
+
+impl ViewContext {
+    fn area_known(&self) -> bool {
+        self.area.0 > 0.0 && self.area.1 > 0.0
+    }
+}
+
+/// Follow-up work for the app after applying actions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ViewEffects {
+    /// Re-render at the new view now
+    pub needs_rerender: bool,
+    /// Fit on the next render, because the canvas size wasn't known yet
+    pub needs_refit: bool,
+    /// Re-render once zooming settles, showing the scaled texture meanwhile
+    pub schedule_deferred: bool,
+    /// Cap the zoom of the next fit so small documents aren't blown up
+    pub cap_zoom: bool,
+}
+
+impl ViewEffects {
+    /// Combine the effects of two actions applied one after the other.
+    pub fn merge(self, other: ViewEffects) -> ViewEffects {
+        ViewEffects {
+            needs_rerender: self.needs_rerender || other.needs_rerender,
+            needs_refit: self.needs_refit || other.needs_refit,
+            schedule_deferred: self.schedule_deferred || other.schedule_deferred,
+            cap_zoom: self.cap_zoom || other.cap_zoom,
+        }
+    }
+
+    fn rerender() -> Self {
+        Self {
+            needs_rerender: true,
+            ..Self::default()
+        }
+    }
+}
+
+/// Fit `viewport` to the canvas, or mark it for fitting once the canvas size is known.
+fn fit(viewport: &mut Viewport, (width, height): (f32, f32), ctx: &ViewContext) -> ViewEffects {
+    if ctx.area_known() {
+        viewport.fit_to_area(width, height, ctx.area.0, ctx.area.1);
+        return ViewEffects::rerender();
+    }
+    viewport.pan = Vec2::ZERO;
+    viewport.fit_mode = FitMode::Fit;
+    viewport.user_adjusted = false;
+    ViewEffects {
+        needs_refit: true,
+        ..ViewEffects::rerender()
+    }
+}
+
+pub fn apply_view_action(
+    viewport: &mut Viewport,
+    action: &ViewAction,
+    ctx: &ViewContext,
+) -> ViewEffects {
+    match *action {
+        ViewAction::FitToWindow => match ctx.doc_size {
+            Some(size) => fit(viewport, size, ctx),
+            None => ViewEffects::default(),
+        },
+        ViewAction::FitToContent => match (ctx.doc_size, ctx.content) {
+            (Some(size), Some(content)) if ctx.area_known() => {
+                viewport.fit_content_to_area(size, content, ctx.area.0, ctx.area.1);
+                ViewEffects::rerender()
+            }
+            (Some(size), Some(_)) => {
+                // The first render fits the content once the canvas is laid out
+                let effects = fit(viewport, size, ctx);
+                viewport.fit_mode = FitMode::FitContent;
+                effects
+            }
+            _ => ViewEffects::default(),
+        },
+        ViewAction::FitWidth | ViewAction::FitHeight => match ctx.doc_size {
+            Some(size) => {
+                let mode = if *action == ViewAction::FitWidth {
+                    FitMode::FitWidth
+                } else {
+                    FitMode::FitHeight
+                };
+                // Marked first, so a deferred fit is to the same side
+                let effects = fit(viewport, size, ctx);
+                viewport.fit_mode = mode;
+                viewport.refit(size, [0.0, 0.0, size.0, size.1], ctx.area);
+                effects
+            }
+            None => ViewEffects::default(),
+        },
+        ViewAction::StretchToWindow => match ctx.doc_size {
+            Some(size) => {
+                let effects = fit(viewport, size, ctx);
+                viewport.fit_mode = FitMode::Stretch;
+                viewport.refit(size, [0.0, 0.0, size.0, size.1], ctx.area);
+                effects
+            }
+            None => ViewEffects::default(),
+        },
+        ViewAction::ActualSize => {
+            viewport.set_actual_size(1.0);
+            ViewEffects::rerender()
+        }
+        ViewAction::ZoomIn | ViewAction::ZoomOut => {
+            if *action == ViewAction::ZoomIn {
+                viewport.zoom_in(ctx.zoom_center);
+            } else {
+                viewport.zoom_out(ctx.zoom_center);
+            }
+            ViewEffects {
+                schedule_deferred: true,
+                ..ViewEffects::default()
+            }
+        }
+        ViewAction::RotateCw => {
+            viewport.rotate_cw();
+            ViewEffects::rerender()
+        }
+        ViewAction::RotateCcw => {
+            viewport.rotate_ccw();
+            ViewEffects::rerender()
+        }
+        ViewAction::RotateBy(degrees) => {
+            viewport.rotate_by(degrees);
+            ViewEffects::rerender()
+        }
+        ViewAction::SetRotation(degrees) => {
+            viewport.set_rotation(degrees);
+            ViewEffects::rerender()
+        }
+        ViewAction::MirrorH => {
+            viewport.toggle_mirror_h();
+            ViewEffects::rerender()
+        }
+        ViewAction::MirrorV => {
+            viewport.toggle_mirror_v();
+            ViewEffects::rerender()
+        }
+        ViewAction::ResetOrientation => {
+            if !viewport.is_reoriented() {
+                return ViewEffects::default();
+            }
+            viewport.reset_orientation();
+            ViewEffects::rerender()
+        }
+        ViewAction::PanBy(delta) => {
+            viewport.pan_by(delta);
+            ViewEffects::default()
+        }
+        ViewAction::CenterPan => {
+            if viewport.pan != Vec2::ZERO {
+                viewport.set_pan(Vec2::ZERO);
+            }
+            ViewEffects::default()
+        }
+        ViewAction::SetZoom(zoom) => {
+            viewport.set_zoom(zoom);
+            ViewEffects::rerender()
+        }
+        ViewAction::ZoomToRect { selection, display } => {
+            let area = Vec2::new(ctx.area.0, ctx.area.1);
+            if viewport.zoom_to_rect(selection, display, area) {
+                ViewEffects::rerender()
+            } else {
+                ViewEffects::default()
+            }
+        }
+        ViewAction::ResetView => {
+            viewport.reset();
+            let effects = match ctx.doc_size {
+                Some(size) => fit(viewport, size, ctx),
+                None => ViewEffects::rerender(),
+            };
+            ViewEffects {
+                cap_zoom: true,
+                ..effects
+            }
+        }
+    }
+}
+
+/// Apply `actions` in order, combining their effects.
+pub fn apply_view_actions(
+    viewport: &mut Viewport,
+    actions: &[ViewAction],
+    ctx: &ViewContext,
+) -> ViewEffects {
+    actions
+        .iter()
+        .fold(ViewEffects::default(), |effects, action| {
+            effects.merge(apply_view_action(viewport, action, ctx))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: (f32, f32) = (200.0, 100.0);
+    const AREA: (f32, f32) = (800.0, 600.0);
+    /// Centered in the document, half its size
+    const CONTENT: [f32; 4] = [50.0, 25.0, 100.0, 50.0];
+
+    fn ctx() -> ViewContext {
+        ViewContext {
+            doc_size: Some(DOC),
+            content: Some(CONTENT),
+            area: AREA,
+            zoom_center: Vec2::ZERO,
+        }
+    }
+
+    /// The starting states every action is tried from.
+    fn start_states() -> Vec<(&'static str, Viewport, ViewContext)> {
+        let mut fitted = Viewport::default();
+        fitted.fit_to_area(DOC.0, DOC.1, AREA.0, AREA.1);
+
+        let mut custom = fitted.clone();
+        custom.zoom_by(1.5, Vec2::new(40.0, 0.0));
+        custom.pan_by(Vec2::new(10.0, -5.0));
+
+        let mut rotated = Viewport::default();
+        rotated.rotate_cw();
+        rotated.mirror_h = true;
+        rotated.fit_to_area(DOC.0, DOC.1, AREA.0, AREA.1);
+
+        // The first file is still loading: nothing open, canvas not laid out
+        let loading = ViewContext {
+            doc_size: None,
+            content: None,
+            area: (0.0, 0.0),
+            zoom_center: Vec2::ZERO,
+        };
+        vec![
+            ("fit", fitted, ctx()),
+            ("custom zoom", custom, ctx()),
+            ("rotated", rotated, ctx()),
+            ("pending load", Viewport::default(), loading),
+        ]
+    }
+
+    type Check = fn(&Viewport, &Viewport, &ViewContext, ViewEffects, &str);
+
+    fn fitted_zoom(viewport: &Viewport) -> f32 {
+        let (w, h) = viewport.rotated_size(DOC.0, DOC.1);
+        (AREA.0 / w).min(AREA.1 / h)
+    }
+
+    #[test]
+    fn test_every_action_from_every_state() {
+        let cases: Vec<(ViewAction, Check)> = vec![
+            (
+                ViewAction::FitToWindow,
+                |before, after, ctx, effects, at| {
+                    if ctx.doc_size.is_none() {
+                        assert_eq!(after, before, "{at}");
+                        assert_eq!(effects, ViewEffects::default(), "{at}");
+                        return;
+                    }
+                    assert_eq!(after.fit_mode, FitMode::Fit, "{at}");
+                    assert_eq!(after.pan, Vec2::ZERO, "{at}");
+                    assert_eq!(after.zoom, fitted_zoom(after), "{at}");
+                    assert_eq!(after.rotation_deg, before.rotation_deg, "{at}");
+                    assert!(effects.needs_rerender && !effects.schedule_deferred, "{at}");
+                },
+            ),
+            (
+                ViewAction::FitToContent,
+                |before, after, ctx, effects, at| {
+                    if ctx.doc_size.is_none() {
+                        assert_eq!(after, before, "{at}");
+                        return;
+                    }
+                    assert_eq!(after.fit_mode, FitMode::FitContent, "{at}");
+                    // Already centered; rotating leaves rounding error
+                    assert!(after.pan.length() < 1e-3, "{at}: {:?}", after.pan);
+                    let (w, h) = after.rotated_size(CONTENT[2], CONTENT[3]);
+                    assert_eq!(after.zoom, (AREA.0 / w).min(AREA.1 / h), "{at}");
+                    assert!(after.zoom > fitted_zoom(after), "{at}");
+                    assert!(effects.needs_rerender, "{at}");
+                },
+            ),
+            (ViewAction::FitWidth, |before, after, ctx, effects, at| {
+                if ctx.doc_size.is_none() {
+                    assert_eq!(after, before, "{at}");
+                    return;
+                }
+                assert_eq!(after.fit_mode, FitMode::FitWidth, "{at}");
+                assert_eq!(after.pan, Vec2::ZERO, "{at}");
+                assert_eq!(
+                    after.zoom,
+                    AREA.0 / after.rotated_size(DOC.0, DOC.1).0,
+                    "{at}"
+                );
+                assert!(effects.needs_rerender, "{at}");
+            }),
+            (ViewAction::FitHeight, |before, after, ctx, _, at| {
+                if ctx.doc_size.is_none() {
+                    assert_eq!(after, before, "{at}");
+                    return;
+                }
+                assert_eq!(after.fit_mode, FitMode::FitHeight, "{at}");
+                assert_eq!(
+                    after.zoom,
+                    AREA.1 / after.rotated_size(DOC.0, DOC.1).1,
+                    "{at}"
+                );
+            }),
+            (
+                ViewAction::StretchToWindow,
+                |before, after, ctx, effects, at| {
+                    if ctx.doc_size.is_none() {
+                        assert_eq!(after, before, "{at}");
+                        return;
+                    }
+                    assert_eq!(after.fit_mode, FitMode::Stretch, "{at}");
+                    assert_eq!(after.pan, Vec2::ZERO, "{at}");
+                    // Filled exactly, whatever the rotation
+                    let (w, h) = after.rotated_size(DOC.0, DOC.1);
+                    assert!((w * after.zoom - AREA.0).abs() < 1e-3, "{at}: {after:?}");
+                    assert!((h * after.zoom - AREA.1).abs() < 1e-3, "{at}: {after:?}");
+                    assert!(effects.needs_rerender, "{at}");
+                },
+            ),
+            (ViewAction::ActualSize, |before, after, _, effects, at| {
+                assert_eq!((after.zoom, after.pan), (1.0, Vec2::ZERO), "{at}");
+                assert_eq!(after.fit_mode, FitMode::ActualSize, "{at}");
+                assert_eq!(after.mirror_h, before.mirror_h, "{at}");
+                assert!(effects.needs_rerender, "{at}");
+            }),
+            (ViewAction::ZoomIn, |before, after, _, effects, at| {
+                assert!(after.zoom > before.zoom, "{at}");
+                assert!(after.user_adjusted, "{at}");
+                assert_ne!(after.fit_mode, FitMode::Fit, "{at}");
+                assert!(effects.schedule_deferred && !effects.needs_rerender, "{at}");
+            }),
+            (ViewAction::ZoomOut, |before, after, _, effects, at| {
+                assert!(after.zoom < before.zoom, "{at}");
+                assert!(effects.schedule_deferred && !effects.needs_rerender, "{at}");
+            }),
+            (ViewAction::RotateCw, |before, after, _, effects, at| {
+                assert_eq!(
+                    after.rotation_deg,
+                    (before.rotation_deg + 90.0) % 360.0,
+                    "{at}"
+                );
+                assert_eq!(after.zoom, before.zoom, "{at}");
+                assert!(effects.needs_rerender, "{at}");
+            }),
+            (ViewAction::RotateCcw, |before, after, _, effects, at| {
+                assert_eq!(
+                    after.rotation_deg,
+                    (before.rotation_deg + 270.0) % 360.0,
+                    "{at}"
+                );
+                assert!(effects.needs_rerender, "{at}");
+            }),
+            (
+                ViewAction::RotateBy(-15.0),
+                |before, after, _, effects, at| {
+                    assert_eq!(
+                        after.rotation_deg,
+                        (before.rotation_deg + 345.0) % 360.0,
+                        "{at}"
+                    );
+                    assert!(effects.needs_rerender, "{at}");
+                },
+            ),
+            (
+                ViewAction::SetRotation(450.0),
+                |_, after, _, effects, at| {
+                    assert_eq!(after.rotation_deg, 90.0, "{at}");
+                    assert!(effects.needs_rerender, "{at}");
+                },
+            ),
+            (ViewAction::MirrorH, |before, after, _, effects, at| {
+                assert_eq!(after.mirror_h, !before.mirror_h, "{at}");
+                assert_eq!(after.mirror_v, before.mirror_v, "{at}");
+                assert!(effects.needs_rerender, "{at}");
+            }),
+            (ViewAction::MirrorV, |before, after, _, effects, at| {
+                assert_eq!(after.mirror_v, !before.mirror_v, "{at}");
+                assert!(effects.needs_rerender, "{at}");
+            }),
+            (
+                ViewAction::ResetOrientation,
+                |before, after, _, effects, at| {
+                    assert!(!after.is_reoriented(), "{at}");
+                    assert_eq!((after.zoom, after.pan), (before.zoom, before.pan), "{at}");
+                    assert_eq!(effects.needs_rerender, before.is_reoriented(), "{at}");
+                },
+            ),
+            (
+                ViewAction::PanBy(Vec2::new(-50.0, 10.0)),
+                |before, after, _, effects, at| {
+                    assert_eq!(after.pan, before.pan + Vec2::new(-50.0, 10.0), "{at}");
+                    assert_eq!(after.zoom, before.zoom, "{at}");
+                    assert_eq!(after.fit_mode, FitMode::Custom, "{at}");
+                    assert!(after.user_adjusted, "{at}");
+                    assert_eq!(effects, ViewEffects::default(), "{at}");
+                },
+            ),
+            (ViewAction::CenterPan, |before, after, _, effects, at| {
+                assert_eq!(after.pan, Vec2::ZERO, "{at}");
+                assert_eq!(after.zoom, before.zoom, "{at}");
+                // Already centered: still fitted
+                if before.pan == Vec2::ZERO {
+                    assert_eq!(after, before, "{at}");
+                } else {
+                    assert_eq!(after.fit_mode, FitMode::Custom, "{at}");
+                }
+                assert_eq!(effects, ViewEffects::default(), "{at}");
+            }),
+            (ViewAction::SetZoom(2.5), |before, after, _, effects, at| {
+                assert_eq!(after.zoom, 2.5, "{at}");
+                assert_eq!(after.pan, before.pan * (2.5 / before.zoom), "{at}");
+                assert_eq!(after.fit_mode, FitMode::Custom, "{at}");
+                assert!(effects.needs_rerender, "{at}");
+            }),
+            (
+                ViewAction::ZoomToRect {
+                    selection: Rect::from_center_size(
+                        emath::pos2(400.0, 300.0),
+                        Vec2::new(200.0, 150.0),
+                    ),
+                    display: Rect::from_center_size(
+                        emath::pos2(400.0, 300.0),
+                        Vec2::new(400.0, 200.0),
+                    ),
+                },
+                |before, after, ctx, effects, at| {
+                    if ctx.area == (0.0, 0.0) {
+                        assert_eq!(after, before, "{at}");
+                        assert_eq!(effects, ViewEffects::default(), "{at}");
+                        return;
+                    }
+                    // A quarter of the canvas, at its center
+                    assert_eq!(after.zoom, before.zoom * 4.0, "{at}");
+                    assert_eq!(after.pan, Vec2::ZERO, "{at}");
+                    assert_eq!(after.fit_mode, FitMode::Custom, "{at}");
+                    assert!(effects.needs_rerender, "{at}");
+                },
+            ),
+            (ViewAction::ResetView, |_, after, ctx, effects, at| {
+                assert_eq!((after.rotation_deg, after.mirror_h), (0.0, false), "{at}");
+                assert_eq!(after.fit_mode, FitMode::Fit, "{at}");
+                assert!(!after.user_adjusted, "{at}");
+                if ctx.doc_size.is_some() {
+                    assert_eq!(after.zoom, fitted_zoom(after), "{at}");
+                }
+                assert!(effects.needs_rerender && effects.cap_zoom, "{at}");
+            }),
+        ];
+
+        for (name, start, ctx) in start_states() {
+            for (action, check) in &cases {
+                let mut viewport = start.clone();
+                let effects = apply_view_action(&mut viewport, action, &ctx);
+                check(
+                    &start,
+                    &viewport,
+                    &ctx,
+                    effects,
+                    &format!("{name}: {action:?}"),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_fit_before_layout_is_deferred() {
+        let ctx = ViewContext {
+            area: (0.0, 0.0),
+            ..ctx()
+        };
+        let (_, custom, _) = start_states().remove(1);
+        for action in [ViewAction::FitToWindow, ViewAction::ResetView] {
+            let mut viewport = custom.clone();
+            let effects = apply_view_action(&mut viewport, &action, &ctx);
+            // The zoom is left for the first render to fit
+            assert!(effects.needs_refit, "{action:?}");
+            assert_eq!(viewport.fit_mode, FitMode::Fit);
+            assert_eq!(viewport.pan, Vec2::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_actions_apply_in_order() {
+        let mut viewport = Viewport::default();
+        let effects = apply_view_actions(
+            &mut viewport,
+            &[
+                ViewAction::ZoomIn,
+                ViewAction::RotateCw,
+                ViewAction::MirrorV,
+            ],
+            &ctx(),
+        );
+        assert_eq!(viewport.rotation_deg, 90.0);
+        assert!(viewport.mirror_v);
+        assert!(effects.needs_rerender && effects.schedule_deferred);
+
+        // Reset last wins over everything before it
+        let effects = apply_view_actions(
+            &mut viewport,
+            &[ViewAction::RotateCw, ViewAction::ResetView],
+            &ctx(),
+        );
+        assert_eq!(viewport.rotation_deg, 0.0);
+        assert!(effects.cap_zoom);
+        assert_eq!(
+            apply_view_actions(&mut viewport, &[], &ctx()),
+            ViewEffects::default()
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! Back and forward through earlier views, so a zoom into some detail can be
+//! undone exactly rather than by eye.
+
+use std::time::Instant;
+
+use crate::render_state::ZOOM_SETTLE;
+use crate::viewport::Viewport;
+
+/// Most views kept to go back to; the oldest are dropped beyond it.
+pub const MAX_HISTORY: usize = 50;
+
+#[derive(Debug, Default)]
+pub struct ViewHistory {
+    back: Vec<Viewport>,
+    forward: Vec<Viewport>,
+    /// When the last scroll or pinch zoom step was; steps closer together than
+    /// [`ZOOM_SETTLE`] belong to one gesture and one entry
+    zoom_idle_since: Option<Instant>,
+}
+
+impl ViewHistory {
+    /// The view was `before` until a discrete change such as a fit or rotation.
+    pub fn record(&mut self, before: &Viewport) {
+        self.zoom_idle_since = None;
+        self.push(before);
+    }
+
+    /// The view was `before` until a step of continuous zooming at `now`. Only
+    /// the first step of a gesture is recorded.
+    pub fn record_zoom_step(&mut self, before: &Viewport, now: Instant) {
+        let same_gesture = self
+            .zoom_idle_since
+            .is_some_and(|last| now.saturating_duration_since(last) < ZOOM_SETTLE);
+        if !same_gesture {
+            self.push(before);
+        }
+        self.zoom_idle_since = Some(now);
+    }
+
+    fn push(&mut self, before: &Viewport) {
+        if self.back.last() == Some(before) {
+            return;
+        }
+        self.back.push(before.clone());
+        if self.back.len() > MAX_HISTORY {
+            self.back.remove(0);
+        }
+        self.forward.clear();
+    }
+
+    /// The view before `current`, which then becomes the way forward.
+    pub fn back(&mut self, current: &Viewport) -> Option<Viewport> {
+        let previous = self.back.pop()?;
+        self.forward.push(current.clone());
+        self.zoom_idle_since = None;
+        Some(previous)
+    }
+
+    /// The view gone back from, with `current` becoming the way back again.
+    pub fn forward(&mut self, current: &Viewport) -> Option<Viewport> {
+        let next = self.forward.pop()?;
+        self.back.push(current.clone());
+        self.zoom_idle_since = None;
+        Some(next)
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        !self.back.is_empty()
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        !self.forward.is_empty()
+    }
+
+    /// Forget everything, as when another document is opened.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn zoomed(zoom: f32) -> Viewport {
+        Viewport {
+            zoom,
+            ..Viewport::default()
+        }
+    }
+
+    #[test]
+    fn test_back_and_forward() {
+        let mut history = ViewHistory::default();
+        history.record(&zoomed(1.0));
+        history.record(&zoomed(2.0));
+        let current = zoomed(4.0);
+
+        let back = history.back(&current).unwrap();
+        assert_eq!(back, zoomed(2.0));
+        assert_eq!(history.back(&back), Some(zoomed(1.0)));
+        assert_eq!(history.back(&zoomed(1.0)), None);
+        assert_eq!(history.forward(&zoomed(1.0)), Some(zoomed(2.0)));
+        assert_eq!(history.forward(&zoomed(2.0)), Some(current));
+        assert!(!history.can_go_forward());
+
+        // A new change drops the way forward
+        history.back(&zoomed(4.0));
+        history.record(&zoomed(2.0));
+        assert!(!history.can_go_forward());
+    }
+
+    #[test]
+    fn test_zoom_gesture_is_one_entry() {
+        let mut history = ViewHistory::default();
+        let t0 = Instant::now();
+        history.record_zoom_step(&zoomed(1.0), t0);
+        history.record_zoom_step(&zoomed(1.1), t0 + Duration::from_millis(50));
+        history.record_zoom_step(&zoomed(1.2), t0 + Duration::from_millis(100));
+        // A pause starts a new gesture
+        let later = t0 + Duration::from_millis(100) + ZOOM_SETTLE;
+        history.record_zoom_step(&zoomed(1.3), later);
+
+        assert_eq!(history.back(&zoomed(1.4)), Some(zoomed(1.3)));
+        assert_eq!(history.back(&zoomed(1.3)), Some(zoomed(1.0)));
+        assert!(!history.can_go_back());
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let mut history = ViewHistory::default();
+        for i in 0..MAX_HISTORY + 10 {
+            history.record(&zoomed(i as f32 + 1.0));
+        }
+        let mut current = zoomed(0.5);
+        let mut count = 0;
+        while let Some(previous) = history.back(&current) {
+            current = previous;
+            count += 1;
+        }
+        assert_eq!(count, MAX_HISTORY);
+        assert_eq!(current, zoomed(11.0));
+    }
+}
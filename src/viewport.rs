@@ -1,19 +1,138 @@
-use egui::Vec2;
+use emath::{Pos2, Rect, Vec2};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum FitMode {
     Fit,
+    /// Fitted to the document's content rather than its whole canvas
+    FitContent,
+    /// The width fills the area; the height may not fit
+    FitWidth,
+    FitHeight,
+    /// Width and height scaled independently so the document fills the area
+    Stretch,
     ActualSize,
     Custom,
 }
 
+/// What to do with the view when the canvas area changes size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ViewPolicy {
+    /// Refit whenever the view is in a fit mode. This is the default and matches
+    /// how resizing behaved before the policy existed.
+    #[default]
+    KeepFit,
+    /// Never refit; keep the zoom and re-center the image.
+    KeepZoom,
+    /// Refit only if the view hasn't been zoomed or panned since the last fit.
+    Smart,
+}
+
+/// The view change a resize calls for under a `ViewPolicy`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResizeAction {
+    Refit,
+    Recenter,
+    Keep,
+}
+
+impl ViewPolicy {
+    pub fn all() -> &'static [ViewPolicy] {
+        &[ViewPolicy::KeepFit, ViewPolicy::KeepZoom, ViewPolicy::Smart]
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            ViewPolicy::KeepFit => "Keep fit on resize",
+            ViewPolicy::KeepZoom => "Keep zoom",
+            ViewPolicy::Smart => "Smart",
+        }
+    }
+
+    pub fn on_resize(&self, fit_mode: &FitMode, user_adjusted: bool) -> ResizeAction {
+        match self {
+            ViewPolicy::KeepFit if fit_mode.is_fit() => ResizeAction::Refit,
+            ViewPolicy::KeepFit => ResizeAction::Keep,
+            ViewPolicy::KeepZoom => ResizeAction::Recenter,
+            ViewPolicy::Smart if user_adjusted => ResizeAction::Keep,
+            ViewPolicy::Smart => ResizeAction::Refit,
+        }
+    }
+}
+
+impl FitMode {
+    /// Whether the zoom is derived from the canvas size.
+    pub fn is_fit(&self) -> bool {
+        matches!(
+            self,
+            FitMode::Fit
+                | FitMode::FitContent
+                | FitMode::FitWidth
+                | FitMode::FitHeight
+                | FitMode::Stretch
+        )
+    }
+}
+
+/// The entries of the toolbar's zoom list.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ZoomPreset {
+    /// A zoom in percent, 100 showing one SVG unit per logical pixel
+    Percent(u32),
+    Fit,
+    FitWidth,
+    FitHeight,
+}
+
+impl ZoomPreset {
+    pub fn all() -> &'static [ZoomPreset] {
+        &[
+            ZoomPreset::Percent(25),
+            ZoomPreset::Percent(50),
+            ZoomPreset::Percent(100),
+            ZoomPreset::Percent(200),
+            ZoomPreset::Percent(400),
+            ZoomPreset::Percent(800),
+            ZoomPreset::Fit,
+            ZoomPreset::FitWidth,
+            ZoomPreset::FitHeight,
+        ]
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            ZoomPreset::Percent(percent) => format!("{percent}%"),
+            ZoomPreset::Fit => "Fit".into(),
+            ZoomPreset::FitWidth => "Fit Width".into(),
+            ZoomPreset::FitHeight => "Fit Height".into(),
+        }
+    }
+
+    /// The preset `viewport` is at, if any.
+    pub fn current(viewport: &Viewport) -> Option<ZoomPreset> {
+        match viewport.fit_mode {
+            FitMode::Fit => Some(ZoomPreset::Fit),
+            FitMode::FitWidth => Some(ZoomPreset::FitWidth),
+            FitMode::FitHeight => Some(ZoomPreset::FitHeight),
+            _ => Self::all().iter().copied().find(|preset| {
+                matches!(preset, ZoomPreset::Percent(p) if *p as f32 / 100.0 == viewport.zoom)
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Viewport {
     pub zoom: f32,
     pub pan: Vec2,
     pub rotation_deg: f32,
     pub mirror_h: bool,
     pub mirror_v: bool,
+    /// Horizontal scale relative to `zoom`, in the document's own axes; 1
+    /// except when stretched to fill the window
+    pub stretch_x: f32,
     pub fit_mode: FitMode,
+    /// Zoomed or panned by the user since the last fit
+    pub user_adjusted: bool,
 }
 
 impl Default for Viewport {
@@ -24,11 +143,36 @@ impl Default for Viewport {
             rotation_deg: 0.0,
             mirror_h: false,
             mirror_v: false,
+            stretch_x: 1.0,
             fit_mode: FitMode::Fit,
+            user_adjusted: false,
         }
     }
 }
 
+/// The zoom range, as a factor.
+pub const MIN_ZOOM: f32 = 0.01;
+pub const MAX_ZOOM: f32 = 100.0;
+
+/// Selections narrower or shorter than this, in logical pixels, are taken
+/// for a slip of the mouse and don't zoom.
+pub const MIN_SELECTION: f32 = 4.0;
+
+/// Share of an image larger than the canvas that panning keeps in view.
+pub const KEEP_VISIBLE: f32 = 0.2;
+
+/// Default rotation step for the `[` / `]` keys, in degrees.
+pub const DEFAULT_ROTATION_STEP: f32 = 15.0;
+
+/// `value` if it is a finite, positive size; otherwise 1.0.
+pub fn usable_dimension(value: f32) -> f32 {
+    if value.is_finite() && value > 0.0 {
+        value
+    } else {
+        1.0
+    }
+}
+
 impl Viewport {
     pub fn reset(&mut self) {
         *self = Self::default();
@@ -41,35 +185,202 @@ impl Viewport {
         area_width: f32,
         area_height: f32,
     ) {
-        if svg_width <= 0.0 || svg_height <= 0.0 || area_width <= 0.0 || area_height <= 0.0 {
+        if area_width <= 0.0 || area_height <= 0.0 {
             return;
         }
+        // A degenerate side is fitted as if it were one unit long
+        let svg_width = usable_dimension(svg_width);
+        let svg_height = usable_dimension(svg_height);
 
-        let (effective_w, effective_h) = if (self.rotation_deg % 180.0).abs() > 45.0 {
-            (svg_height, svg_width)
-        } else {
-            (svg_width, svg_height)
-        };
+        self.stretch_x = 1.0;
+        let (effective_w, effective_h) = self.rotated_size(svg_width, svg_height);
 
         let scale_x = area_width / effective_w;
         let scale_y = area_height / effective_h;
         self.zoom = scale_x.min(scale_y);
         self.pan = Vec2::ZERO;
         self.fit_mode = FitMode::Fit;
+        self.user_adjusted = false;
+    }
+
+    /// Fit the `content` rect (x, y, width, height) of a `svg_width`×`svg_height`
+    /// document to the area, panned so the content is centered.
+    pub fn fit_content_to_area(
+        &mut self,
+        (svg_width, svg_height): (f32, f32),
+        content: [f32; 4],
+        area_width: f32,
+        area_height: f32,
+    ) {
+        if area_width <= 0.0 || area_height <= 0.0 {
+            return;
+        }
+        let [x, y, width, height] = content;
+        self.stretch_x = 1.0;
+        let (content_w, content_h) =
+            self.rotated_size(usable_dimension(width), usable_dimension(height));
+        self.zoom = (area_width / content_w).min(area_height / content_h);
+
+        // Where the content's center lands in the render, relative to its center
+        let svg_width = usable_dimension(svg_width);
+        let svg_height = usable_dimension(svg_height);
+        let (render_w, render_h) = self.rotated_size(svg_width, svg_height);
+        let (render_w, render_h) = (render_w * self.zoom, render_h * self.zoom);
+        let transform = self.build_transform(svg_width, svg_height, render_w, render_h);
+        let mut center = tiny_skia::Point::from_xy(x + width / 2.0, y + height / 2.0);
+        transform.map_points(std::slice::from_mut(&mut center));
+        self.pan = Vec2::new(render_w / 2.0 - center.x, render_h / 2.0 - center.y);
+        self.fit_mode = FitMode::FitContent;
+        self.user_adjusted = false;
+    }
+
+    /// Zoom so the document's width fills the area, centered.
+    pub fn fit_width_to_area(&mut self, svg_size: (f32, f32), area_width: f32, area_height: f32) {
+        self.fit_axis_to_area(svg_size, (area_width, area_height), FitMode::FitWidth);
+    }
+
+    /// Zoom so the document's height fills the area, centered.
+    pub fn fit_height_to_area(&mut self, svg_size: (f32, f32), area_width: f32, area_height: f32) {
+        self.fit_axis_to_area(svg_size, (area_width, area_height), FitMode::FitHeight);
+    }
+
+    fn fit_axis_to_area(
+        &mut self,
+        (svg_width, svg_height): (f32, f32),
+        area: (f32, f32),
+        mode: FitMode,
+    ) {
+        if area.0 <= 0.0 || area.1 <= 0.0 {
+            return;
+        }
+        // As shown: a quarter turn fits the document's height to the width
+        self.stretch_x = 1.0;
+        let (shown_w, shown_h) =
+            self.rotated_size(usable_dimension(svg_width), usable_dimension(svg_height));
+        self.zoom = if mode == FitMode::FitWidth {
+            area.0 / shown_w
+        } else {
+            area.1 / shown_h
+        };
+        self.pan = Vec2::ZERO;
+        self.fit_mode = mode;
+        self.user_adjusted = false;
+    }
+
+    /// Scale the document's width and height independently so that, as
+    /// rotated, it fills the area exactly. The stretch is in the document's
+    /// own axes, so a quarter turn stretches its height across the width.
+    /// Near 45° no such stretch exists, and the document is fitted as usual.
+    pub fn stretch_to_area(
+        &mut self,
+        svg_width: f32,
+        svg_height: f32,
+        area_width: f32,
+        area_height: f32,
+    ) {
+        if area_width <= 0.0 || area_height <= 0.0 {
+            return;
+        }
+        let svg_width = usable_dimension(svg_width);
+        let svg_height = usable_dimension(svg_height);
+        // Quarter turns are exact, as in `rotated_size`
+        let (sin, cos) = if self.rotation_deg % 90.0 == 0.0 {
+            if self.rotation_deg % 180.0 == 0.0 {
+                (0.0, 1.0)
+            } else {
+                (1.0, 0.0)
+            }
+        } else {
+            let (sin, cos) = self.rotation_deg.to_radians().sin_cos();
+            (sin.abs(), cos.abs())
+        };
+        // The stretched sides w and h solve
+        //   w·cos + h·sin = area_width, w·sin + h·cos = area_height
+        let det = cos * cos - sin * sin;
+        let sides = (det.abs() > 1e-3)
+            .then(|| {
+                (
+                    (cos * area_width - sin * area_height) / det,
+                    (cos * area_height - sin * area_width) / det,
+                )
+            })
+            .filter(|&(w, h)| w > 0.0 && h > 0.0);
+        let Some((stretched_w, stretched_h)) = sides else {
+            self.fit_to_area(svg_width, svg_height, area_width, area_height);
+            self.fit_mode = FitMode::Stretch;
+            return;
+        };
+        self.zoom = stretched_h / svg_height;
+        self.stretch_x = stretched_w / svg_width / self.zoom;
+        self.pan = Vec2::ZERO;
+        self.fit_mode = FitMode::Stretch;
+        self.user_adjusted = false;
+    }
+
+    /// Whether the document is shown out of its own proportions.
+    pub fn is_stretched(&self) -> bool {
+        self.fit_mode == FitMode::Stretch || self.stretch_x != 1.0
+    }
+
+    /// Fit again the way the view was last fitted: the whole document, one
+    /// of its sides, its `content` rect, or stretched to fill the area.
+    pub fn refit(&mut self, svg_size: (f32, f32), content: [f32; 4], area: (f32, f32)) {
+        match self.fit_mode {
+            FitMode::FitContent => self.fit_content_to_area(svg_size, content, area.0, area.1),
+            FitMode::FitWidth => self.fit_width_to_area(svg_size, area.0, area.1),
+            FitMode::FitHeight => self.fit_height_to_area(svg_size, area.0, area.1),
+            FitMode::Stretch => self.stretch_to_area(svg_size.0, svg_size.1, area.0, area.1),
+            _ => self.fit_to_area(svg_size.0, svg_size.1, area.0, area.1),
+        }
     }
 
     pub fn set_actual_size(&mut self, pixels_per_point: f32) {
         self.zoom = 1.0 / pixels_per_point;
+        self.stretch_x = 1.0;
         self.pan = Vec2::ZERO;
         self.fit_mode = FitMode::ActualSize;
+        self.user_adjusted = true;
     }
 
     pub fn zoom_by(&mut self, factor: f32, cursor_pos: Vec2) {
         let old_zoom = self.zoom;
-        self.zoom = (self.zoom * factor).clamp(0.01, 100.0);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
         let scale_ratio = self.zoom / old_zoom;
         self.pan = cursor_pos - scale_ratio * (cursor_pos - self.pan);
         self.fit_mode = FitMode::Custom;
+        self.user_adjusted = true;
+    }
+
+    /// Zoom to exactly `zoom`, within the zoom range, about the canvas center.
+    pub fn set_zoom(&mut self, zoom: f32) {
+        let old_zoom = self.zoom;
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        self.pan *= self.zoom / old_zoom;
+        self.fit_mode = FitMode::Custom;
+        self.user_adjusted = true;
+    }
+
+    /// Zoom so `selection`, dragged out on screen over the image shown at
+    /// `display_rect`, fills an `area`-sized canvas, within the zoom range.
+    /// Returns false, leaving the view alone, when the selection is too
+    /// small to mean anything.
+    pub fn zoom_to_rect(&mut self, selection: Rect, display_rect: Rect, area: Vec2) -> bool {
+        if selection.width() < MIN_SELECTION
+            || selection.height() < MIN_SELECTION
+            || area.x <= 0.0
+            || area.y <= 0.0
+        {
+            return false;
+        }
+        let factor = (area.x / selection.width()).min(area.y / selection.height());
+        let old_zoom = self.zoom;
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        // The selection's center, from the image's, moves to the canvas center
+        let offset = selection.center() - display_rect.center();
+        self.pan = -offset * (self.zoom / old_zoom);
+        self.fit_mode = FitMode::Custom;
+        self.user_adjusted = true;
+        true
     }
 
     pub fn zoom_in(&mut self, center: Vec2) {
@@ -82,17 +393,113 @@ impl Viewport {
 
     pub fn pan_by(&mut self, delta: Vec2) {
         self.pan += delta;
-        if self.fit_mode == FitMode::Fit {
+        self.user_adjusted = true;
+        if self.fit_mode.is_fit() {
             self.fit_mode = FitMode::Custom;
         }
     }
 
+    /// Limit the pan so the image, shown at `display_size` (its rotated
+    /// bounds at the current zoom), can't be lost off the `area`: all of it
+    /// stays in view when it fits, and [`KEEP_VISIBLE`] of it otherwise.
+    /// The fit mode is left alone.
+    pub fn clamp_pan(&mut self, display_size: Vec2, area: Vec2) {
+        if area.x <= 0.0 || area.y <= 0.0 {
+            return;
+        }
+        let limit = |shown: f32, area: f32| {
+            let keep = if shown <= area {
+                shown
+            } else {
+                (shown * KEEP_VISIBLE).min(area)
+            };
+            ((area + shown) / 2.0 - keep).max(0.0)
+        };
+        let (limit_x, limit_y) = (limit(display_size.x, area.x), limit(display_size.y, area.y));
+        self.pan = Vec2::new(
+            self.pan.x.clamp(-limit_x, limit_x),
+            self.pan.y.clamp(-limit_y, limit_y),
+        );
+    }
+
+    /// Move the image to `pan` exactly, as if the user had dragged it there.
+    pub fn set_pan(&mut self, pan: Vec2) {
+        self.pan_by(pan - self.pan);
+    }
+
+    /// Pan that puts the image's top-left corner at the canvas's top-left.
+    /// Sizes are in logical pixels; `image_size` is the on-screen image size.
+    pub fn top_left_pan(image_size: Vec2, canvas_size: Vec2) -> Vec2 {
+        (image_size - canvas_size) / 2.0
+    }
+
+    /// The current pan nudged by under a device pixel so the image's top-left
+    /// corner lands on a device pixel. `canvas_min` is the canvas's top-left in
+    /// screen coordinates, which matters because the canvas itself need not sit
+    /// on a device pixel.
+    pub fn snapped_pan(
+        &self,
+        image_size: Vec2,
+        canvas_min: Vec2,
+        canvas_size: Vec2,
+        pixels_per_point: f32,
+    ) -> Vec2 {
+        let corner = canvas_min + canvas_size / 2.0 + self.pan - image_size / 2.0;
+        let snap = |v: f32| (v * pixels_per_point).round() / pixels_per_point;
+        let snapped = Vec2::new(snap(corner.x), snap(corner.y));
+        self.pan + (snapped - corner)
+    }
+
     pub fn rotate_cw(&mut self) {
-        self.rotation_deg = (self.rotation_deg + 90.0) % 360.0;
+        self.rotate_by(90.0);
     }
 
     pub fn rotate_ccw(&mut self) {
-        self.rotation_deg = (self.rotation_deg - 90.0 + 360.0) % 360.0;
+        self.rotate_by(-90.0);
+    }
+
+    /// Set the rotation to `degrees`, normalized into [0, 360).
+    /// Non-finite angles are ignored.
+    pub fn set_rotation(&mut self, degrees: f32) {
+        if !degrees.is_finite() {
+            return;
+        }
+        let normalized = degrees.rem_euclid(360.0);
+        // rem_euclid can round tiny negative angles up to exactly 360
+        self.rotation_deg = if normalized >= 360.0 { 0.0 } else { normalized };
+    }
+
+    pub fn rotate_by(&mut self, degrees: f32) {
+        self.set_rotation(self.rotation_deg + degrees);
+    }
+
+    /// Size of the axis-aligned bounding box of a `width`×`height` document
+    /// after applying the current stretch and rotation.
+    pub fn rotated_size(&self, width: f32, height: f32) -> (f32, f32) {
+        let width = width * self.stretch_x;
+        // Quarter turns are exact so fits at 0/90/180/270 have no rounding drift
+        if self.rotation_deg % 90.0 == 0.0 {
+            return if self.rotation_deg % 180.0 == 0.0 {
+                (width, height)
+            } else {
+                (height, width)
+            };
+        }
+        let (sin, cos) = self.rotation_deg.to_radians().sin_cos();
+        let (sin, cos) = (sin.abs(), cos.abs());
+        (width * cos + height * sin, width * sin + height * cos)
+    }
+
+    /// Whether the document is shown rotated or mirrored.
+    pub fn is_reoriented(&self) -> bool {
+        self.rotation_deg != 0.0 || self.mirror_h || self.mirror_v
+    }
+
+    /// Undo rotation and mirroring, keeping the zoom and pan.
+    pub fn reset_orientation(&mut self) {
+        self.rotation_deg = 0.0;
+        self.mirror_h = false;
+        self.mirror_v = false;
     }
 
     pub fn toggle_mirror_h(&mut self) {
@@ -115,8 +522,10 @@ impl Viewport {
         let cx = render_width / 2.0;
         let cy = render_height / 2.0;
 
-        let scale_x = render_width / svg_width;
-        let scale_y = render_height / svg_height;
+        // Fit the rotated bounding box, which is what the pixmap was sized for
+        let (rotated_w, rotated_h) = self.rotated_size(svg_width, svg_height);
+        let scale_x = render_width / rotated_w;
+        let scale_y = render_height / rotated_h;
         let scale = scale_x.min(scale_y);
 
         let mut ts = tiny_skia::Transform::identity();
@@ -133,13 +542,34 @@ impl Viewport {
         if self.mirror_v {
             ts = ts.pre_scale(1.0, -1.0);
         }
-        // Move back and apply scale
-        ts = ts.pre_translate(-svg_width / 2.0 * scale, -svg_height / 2.0 * scale);
-        ts = ts.pre_scale(scale, scale);
+        // Move back and apply scale, stretched in the document's own axes
+        let stretched = scale * self.stretch_x;
+        ts = ts.pre_translate(-svg_width / 2.0 * stretched, -svg_height / 2.0 * scale);
+        ts = ts.pre_scale(stretched, scale);
 
         ts
     }
 
+    /// The document point under screen position `pos`, for a `svg_size`
+    /// document shown `display_size` large on a canvas at `rect`: the
+    /// inverse of [`Viewport::build_transform`], panned into place.
+    pub fn screen_to_svg(
+        &self,
+        pos: Pos2,
+        rect: Rect,
+        display_size: Vec2,
+        (svg_width, svg_height): (f32, f32),
+    ) -> Option<[f32; 2]> {
+        let image = Rect::from_center_size(rect.center() + self.pan, display_size);
+        let inverse = self
+            .build_transform(svg_width, svg_height, image.width(), image.height())
+            .post_translate(image.min.x, image.min.y)
+            .invert()?;
+        let mut point = tiny_skia::Point::from_xy(pos.x, pos.y);
+        inverse.map_point(&mut point);
+        Some([point.x, point.y])
+    }
+
     pub fn zoom_percent(&self) -> f32 {
         self.zoom * 100.0
     }
@@ -149,6 +579,50 @@ impl Viewport {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_set_pan_leaves_fit() {
+        let mut vp = Viewport::default();
+        vp.set_pan(Vec2::new(3.0, -4.0));
+        assert_eq!(vp.pan, Vec2::new(3.0, -4.0));
+        assert_eq!(vp.fit_mode, FitMode::Custom);
+        assert!(vp.user_adjusted);
+    }
+
+    #[test]
+    fn test_top_left_pan() {
+        let pan = Viewport::top_left_pan(Vec2::new(200.0, 100.0), Vec2::new(800.0, 600.0));
+        assert_eq!(pan, Vec2::new(-300.0, -250.0));
+        // Image corner = canvas center + pan - half the image = canvas origin
+        assert_eq!(
+            Vec2::new(400.0, 300.0) + pan - Vec2::new(100.0, 50.0),
+            Vec2::ZERO
+        );
+    }
+
+    #[test]
+    fn test_snapped_pan() {
+        let image = Vec2::new(101.0, 51.0);
+        let canvas_min = Vec2::new(0.0, 30.25);
+        let canvas = Vec2::new(800.0, 600.0);
+        let corner = |pan: Vec2| canvas_min + canvas / 2.0 + pan - image / 2.0;
+
+        let vp = Viewport {
+            pan: Vec2::new(0.3, 0.0),
+            ..Default::default()
+        };
+        // ppp 1: the corner lands on whole logical pixels
+        let pan = vp.snapped_pan(image, canvas_min, canvas, 1.0);
+        let c = corner(pan);
+        assert_eq!((c.x.fract(), c.y.fract()), (0.0, 0.0));
+        assert!((pan - vp.pan).length() <= 1.0);
+
+        // ppp 2: half logical pixels are device pixels
+        let pan = vp.snapped_pan(image, canvas_min, canvas, 2.0);
+        let c = corner(pan) * 2.0;
+        assert_eq!((c.x.fract(), c.y.fract()), (0.0, 0.0));
+        assert!((pan - vp.pan).x.abs() <= 0.25 && (pan - vp.pan).y.abs() <= 0.25);
+    }
+
     #[test]
     fn test_default_viewport() {
         let vp = Viewport::default();
@@ -179,16 +653,39 @@ mod tests {
 
     #[test]
     fn test_fit_to_area_zero_dimensions() {
-        let mut vp = Viewport::default();
-        vp.zoom = 2.0;
+        let mut vp = Viewport {
+            zoom: 2.0,
+            ..Default::default()
+        };
         vp.fit_to_area(0.0, 100.0, 400.0, 400.0);
-        assert_eq!(vp.zoom, 2.0); // Unchanged
+        // The zero side counts as one unit, so the height decides the fit
+        assert_eq!(vp.zoom, 4.0);
+        assert_eq!(vp.fit_mode, FitMode::Fit);
     }
 
     #[test]
-    fn test_zoom_clamp() {
+    fn test_fit_to_area_zero_area_unchanged() {
+        let mut vp = Viewport {
+            zoom: 2.0,
+            ..Default::default()
+        };
+        vp.fit_to_area(100.0, 100.0, 0.0, 400.0);
+        assert_eq!(vp.zoom, 2.0);
+    }
+
+    #[test]
+    fn test_fit_to_area_extreme_aspect() {
         let mut vp = Viewport::default();
-        vp.zoom = 0.02;
+        vp.fit_to_area(1.0, 100000.0, 800.0, 600.0);
+        assert_eq!(vp.zoom, 600.0 / 100000.0);
+    }
+
+    #[test]
+    fn test_zoom_clamp() {
+        let mut vp = Viewport {
+            zoom: 0.02,
+            ..Default::default()
+        };
         vp.zoom_by(0.1, Vec2::ZERO); // Would go to 0.002, clamped to 0.01
         assert_eq!(vp.zoom, 0.01);
 
@@ -219,6 +716,61 @@ mod tests {
         assert_eq!(vp.rotation_deg, 180.0);
     }
 
+    #[test]
+    fn test_set_rotation_normalizes() {
+        let mut vp = Viewport::default();
+        vp.set_rotation(-90.0);
+        assert_eq!(vp.rotation_deg, 270.0);
+        vp.set_rotation(765.0);
+        assert_eq!(vp.rotation_deg, 45.0);
+        vp.set_rotation(360.0);
+        assert_eq!(vp.rotation_deg, 0.0);
+        vp.set_rotation(-1e-6);
+        assert!(vp.rotation_deg >= 0.0 && vp.rotation_deg < 360.0);
+        vp.set_rotation(f32::NAN);
+        assert!(vp.rotation_deg.is_finite());
+    }
+
+    #[test]
+    fn test_rotation_step_accumulation() {
+        let mut vp = Viewport::default();
+        for _ in 0..24 {
+            vp.rotate_by(DEFAULT_ROTATION_STEP);
+        }
+        assert_eq!(vp.rotation_deg, 0.0);
+        vp.rotate_by(-DEFAULT_ROTATION_STEP);
+        assert_eq!(vp.rotation_deg, 345.0);
+        vp.rotate_by(1.0);
+        assert_eq!(vp.rotation_deg, 346.0);
+    }
+
+    #[test]
+    fn test_fit_to_area_at_45_degrees() {
+        let mut vp = Viewport::default();
+        vp.set_rotation(45.0);
+        let (w, h) = vp.rotated_size(100.0, 100.0);
+        let diagonal = 100.0 * std::f32::consts::SQRT_2;
+        assert!((w - diagonal).abs() < 1e-3 && (h - diagonal).abs() < 1e-3);
+
+        vp.fit_to_area(100.0, 100.0, 400.0, 400.0);
+        assert!((vp.zoom - 400.0 / diagonal).abs() < 1e-4);
+        assert_eq!(vp.fit_mode, FitMode::Fit);
+    }
+
+    #[test]
+    fn test_rotated_transform_fills_pixmap() {
+        // A 200×100 document rotated a quarter turn fits a 100×200 pixmap at scale 1
+        let vp = Viewport {
+            rotation_deg: 90.0,
+            ..Default::default()
+        };
+        let ts = vp.build_transform(200.0, 100.0, 100.0, 200.0);
+        let rect = tiny_skia::Rect::from_xywh(0.0, 0.0, 200.0, 100.0).unwrap();
+        let bounds = rect.transform(ts).unwrap();
+        assert!((bounds.width() - 100.0).abs() < 1e-3);
+        assert!((bounds.height() - 200.0).abs() < 1e-3);
+    }
+
     #[test]
     fn test_mirror_toggle() {
         let mut vp = Viewport::default();
@@ -239,12 +791,284 @@ mod tests {
     }
 
     #[test]
-    fn test_reset() {
+    fn test_resize_policy_table() {
+        use ResizeAction::*;
+        use ViewPolicy::*;
+        let cases = [
+            (FitMode::Fit, false, KeepFit, Refit),
+            (FitMode::Fit, true, KeepFit, Refit),
+            (FitMode::ActualSize, true, KeepFit, Keep),
+            (FitMode::Custom, true, KeepFit, Keep),
+            (FitMode::Fit, false, KeepZoom, Recenter),
+            (FitMode::ActualSize, true, KeepZoom, Recenter),
+            (FitMode::Custom, true, KeepZoom, Recenter),
+            (FitMode::Fit, false, Smart, Refit),
+            (FitMode::Fit, true, Smart, Keep),
+            (FitMode::ActualSize, true, Smart, Keep),
+            (FitMode::Custom, false, Smart, Refit),
+            (FitMode::Custom, true, Smart, Keep),
+        ];
+        for (mode, adjusted, policy, expected) in cases {
+            assert_eq!(
+                policy.on_resize(&mode, adjusted),
+                expected,
+                "{mode:?}, adjusted={adjusted}, {policy:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_user_adjusted_tracking() {
+        let mut vp = Viewport::default();
+        assert!(!vp.user_adjusted);
+        vp.zoom_in(Vec2::ZERO);
+        assert!(vp.user_adjusted);
+        vp.fit_to_area(100.0, 100.0, 200.0, 200.0);
+        assert!(!vp.user_adjusted);
+        vp.pan_by(Vec2::new(1.0, 0.0));
+        assert!(vp.user_adjusted);
+        // Rotating keeps a fitted view fitted
+        vp.fit_to_area(100.0, 100.0, 200.0, 200.0);
+        vp.rotate_cw();
+        assert!(!vp.user_adjusted);
+    }
+
+    #[test]
+    fn test_fit_content_centers_it() {
+        // A small drawing in the bottom-right corner of a large canvas
+        let mut vp = Viewport::default();
+        vp.fit_content_to_area((1000.0, 1000.0), [900.0, 900.0, 100.0, 100.0], 400.0, 400.0);
+        assert_eq!(vp.zoom, 4.0);
+        assert_eq!(vp.pan, Vec2::new(-1800.0, -1800.0));
+        assert_eq!(vp.fit_mode, FitMode::FitContent);
+        assert!(vp.fit_mode.is_fit());
+
+        // Turned clockwise, the corner is bottom-left
+        vp.rotate_cw();
+        vp.refit(
+            (1000.0, 1000.0),
+            [900.0, 900.0, 100.0, 100.0],
+            (400.0, 400.0),
+        );
+        assert!(
+            (vp.pan - Vec2::new(1800.0, -1800.0)).length() < 0.01,
+            "{:?}",
+            vp.pan
+        );
+
+        // Panning leaves the content fit; refitting is then a plain fit
+        vp.pan_by(Vec2::new(5.0, 0.0));
+        assert_eq!(vp.fit_mode, FitMode::Custom);
+        vp.fit_to_area(1000.0, 1000.0, 400.0, 400.0);
+        vp.refit(
+            (1000.0, 1000.0),
+            [900.0, 900.0, 100.0, 100.0],
+            (400.0, 400.0),
+        );
+        assert_eq!(vp.fit_mode, FitMode::Fit);
+        assert_eq!(vp.pan, Vec2::ZERO);
+    }
+
+    #[test]
+    fn test_set_zoom_is_exact_and_centered() {
+        let mut vp = Viewport::default();
+        vp.fit_to_area(1000.0, 1000.0, 300.0, 300.0);
+        vp.pan_by(Vec2::new(30.0, -12.0));
+        vp.set_zoom(2.5);
+        assert_eq!(vp.zoom, 2.5);
+        // What was at the canvas center stays there
+        assert!((vp.pan - Vec2::new(250.0, -100.0)).length() < 1e-3);
+        assert_eq!(vp.fit_mode, FitMode::Custom);
+        vp.set_zoom(1000.0);
+        assert_eq!(vp.zoom, MAX_ZOOM);
+        vp.set_zoom(0.0);
+        assert_eq!(vp.zoom, MIN_ZOOM);
+    }
+
+    #[test]
+    fn test_fit_one_side() {
+        let mut vp = Viewport::default();
+        vp.fit_width_to_area((200.0, 100.0), 800.0, 100.0);
+        assert_eq!((vp.zoom, vp.pan), (4.0, Vec2::ZERO));
+        assert_eq!(ZoomPreset::current(&vp), Some(ZoomPreset::FitWidth));
+        vp.fit_height_to_area((200.0, 100.0), 800.0, 100.0);
+        assert_eq!(vp.zoom, 1.0);
+        assert_eq!(ZoomPreset::current(&vp), Some(ZoomPreset::FitHeight));
+
+        // Turned a quarter, the document's height is across
+        vp.rotate_cw();
+        vp.refit((200.0, 100.0), [0.0, 0.0, 200.0, 100.0], (800.0, 100.0));
+        assert_eq!(vp.zoom, 0.5);
+        vp.fit_width_to_area((200.0, 100.0), 800.0, 100.0);
+        assert_eq!(vp.zoom, 8.0);
+    }
+
+    #[test]
+    fn test_zoom_preset_current() {
+        let mut vp = Viewport::default();
+        assert_eq!(ZoomPreset::current(&vp), Some(ZoomPreset::Fit));
+        vp.set_zoom(2.0);
+        assert_eq!(ZoomPreset::current(&vp), Some(ZoomPreset::Percent(200)));
+        vp.set_zoom(1.5);
+        assert_eq!(ZoomPreset::current(&vp), None);
+        assert_eq!(ZoomPreset::Percent(25).name(), "25%");
+    }
+
+    #[test]
+    fn test_clamp_pan() {
+        let area = Vec2::new(800.0, 600.0);
+        // Smaller than the canvas: it stays entirely in view
+        let mut vp = Viewport {
+            pan: Vec2::new(1000.0, -1000.0),
+            ..Default::default()
+        };
+        vp.clamp_pan(Vec2::new(200.0, 100.0), area);
+        assert_eq!(vp.pan, Vec2::new(300.0, -250.0));
+
+        // Larger: a fifth of it stays in view
+        vp.pan = Vec2::new(-5000.0, 5000.0);
+        vp.clamp_pan(Vec2::new(2000.0, 1000.0), area);
+        assert_eq!(vp.pan, Vec2::new(-1000.0, 600.0));
+        // Far larger: it still covers the canvas
+        vp.pan = Vec2::new(-50000.0, 0.0);
+        vp.clamp_pan(Vec2::new(10000.0, 1000.0), area);
+        assert_eq!(vp.pan.x, -4600.0);
+
+        // Within the limits nothing moves
+        let mut fitted = Viewport::default();
+        fitted.fit_to_area(200.0, 100.0, 800.0, 600.0);
+        fitted.clamp_pan(Vec2::new(800.0, 400.0), area);
+        assert_eq!(fitted.pan, Vec2::ZERO);
+        assert_eq!(fitted.fit_mode, FitMode::Fit);
+    }
+
+    #[test]
+    fn test_stretch_to_area() {
+        let corners = |vp: &Viewport| {
+            let ts = vp.build_transform(200.0, 100.0, 800.0, 600.0);
+            let mut points = [
+                tiny_skia::Point::from_xy(0.0, 0.0),
+                tiny_skia::Point::from_xy(200.0, 100.0),
+            ];
+            ts.map_points(&mut points);
+            points.map(|p| (p.x.round(), p.y.round()))
+        };
         let mut vp = Viewport::default();
-        vp.zoom = 3.0;
-        vp.pan = Vec2::new(100.0, 200.0);
-        vp.rotation_deg = 90.0;
+        vp.stretch_to_area(200.0, 100.0, 800.0, 600.0);
+        assert_eq!((vp.zoom, vp.stretch_x), (6.0, 4.0 / 6.0));
+        assert_eq!(vp.fit_mode, FitMode::Stretch);
+        assert_eq!(corners(&vp), [(0.0, 0.0), (800.0, 600.0)]);
+
+        // A quarter turn stretches the document's height across the width
+        vp.rotate_cw();
+        vp.stretch_to_area(200.0, 100.0, 800.0, 600.0);
+        assert_eq!((vp.zoom, vp.stretch_x), (8.0, 3.0 / 8.0));
+        assert_eq!(corners(&vp), [(800.0, 0.0), (0.0, 600.0)]);
+
+        // No stretch fills the area at 45°: fitted as usual
+        vp.set_rotation(45.0);
+        vp.stretch_to_area(200.0, 100.0, 800.0, 600.0);
+        assert_eq!(vp.stretch_x, 1.0);
+        assert_eq!(vp.fit_mode, FitMode::Stretch);
+
+        // Any other fit undoes the stretch
+        vp.set_rotation(0.0);
+        vp.stretch_to_area(200.0, 100.0, 800.0, 600.0);
+        vp.fit_to_area(200.0, 100.0, 800.0, 600.0);
+        assert!(!vp.is_stretched());
+    }
+
+    #[test]
+    fn test_screen_to_svg() {
+        let rect = Rect::from_min_size(emath::pos2(100.0, 50.0), Vec2::new(800.0, 600.0));
+        let close = |a: Option<[f32; 2]>, b: [f32; 2]| {
+            let a = a.unwrap();
+            assert!(
+                (a[0] - b[0]).abs() < 1e-3 && (a[1] - b[1]).abs() < 1e-3,
+                "{a:?}"
+            );
+        };
+        // 200×100 at 2x, panned 40 px right
+        let mut vp = Viewport {
+            zoom: 2.0,
+            pan: Vec2::new(40.0, 0.0),
+            ..Default::default()
+        };
+        let shown = Vec2::new(400.0, 200.0);
+        close(
+            vp.screen_to_svg(emath::pos2(540.0, 350.0), rect, shown, (200.0, 100.0)),
+            [100.0, 50.0],
+        );
+        close(
+            vp.screen_to_svg(emath::pos2(340.0, 250.0), rect, shown, (200.0, 100.0)),
+            [0.0, 0.0],
+        );
+
+        // A quarter turn puts the document's top-left corner at the top right
+        vp.rotate_cw();
+        vp.pan = Vec2::ZERO;
+        let shown = Vec2::new(200.0, 400.0);
+        close(
+            vp.screen_to_svg(emath::pos2(600.0, 150.0), rect, shown, (200.0, 100.0)),
+            [0.0, 0.0],
+        );
+        // Mirrored, it's at the top left
+        vp.rotation_deg = 0.0;
         vp.mirror_h = true;
+        let shown = Vec2::new(400.0, 200.0);
+        close(
+            vp.screen_to_svg(emath::pos2(300.0, 250.0), rect, shown, (200.0, 100.0)),
+            [200.0, 0.0],
+        );
+    }
+
+    #[test]
+    fn test_zoom_to_rect() {
+        let area = Vec2::new(800.0, 600.0);
+        // Shown at 2x, centered on a canvas at (0, 0)
+        let display = Rect::from_center_size(emath::pos2(400.0, 300.0), Vec2::new(400.0, 200.0));
+        let mut vp = Viewport {
+            zoom: 2.0,
+            ..Default::default()
+        };
+        // A 100x100 square right of center: height limits it to 6x more
+        let selection = Rect::from_min_size(emath::pos2(450.0, 250.0), Vec2::splat(100.0));
+        assert!(vp.zoom_to_rect(selection, display, area));
+        assert_eq!(vp.zoom, 12.0);
+        assert_eq!(vp.pan, Vec2::new(-600.0, 0.0));
+        assert_eq!(vp.fit_mode, FitMode::Custom);
+
+        // Clamped to the zoom range, the pan following the clamped zoom
+        let mut vp = Viewport {
+            zoom: 50.0,
+            ..Default::default()
+        };
+        let tiny = Rect::from_min_size(emath::pos2(410.0, 300.0), Vec2::splat(10.0));
+        assert!(vp.zoom_to_rect(tiny, display, area));
+        assert_eq!(vp.zoom, MAX_ZOOM);
+        assert_eq!(vp.pan, Vec2::new(-30.0, -10.0));
+
+        // A click-sized drag does nothing
+        let before = vp.clone();
+        let slip = Rect::from_min_size(emath::pos2(10.0, 10.0), Vec2::new(2.0, 50.0));
+        assert!(!vp.zoom_to_rect(slip, display, area));
+        assert_eq!(vp, before);
+    }
+
+    #[test]
+    fn test_default_policy_keeps_fit() {
+        assert_eq!(ViewPolicy::default(), ViewPolicy::KeepFit);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut vp = Viewport {
+            zoom: 3.0,
+            pan: Vec2::new(100.0, 200.0),
+            rotation_deg: 90.0,
+            mirror_h: true,
+            ..Default::default()
+        };
         vp.reset();
         assert_eq!(vp.zoom, 1.0);
         assert_eq!(vp.pan, Vec2::ZERO);
@@ -254,8 +1078,10 @@ mod tests {
 
     #[test]
     fn test_zoom_percent() {
-        let mut vp = Viewport::default();
-        vp.zoom = 1.5;
+        let vp = Viewport {
+            zoom: 1.5,
+            ..Default::default()
+        };
         assert_eq!(vp.zoom_percent(), 150.0);
     }
 }
@@ -0,0 +1,49 @@
+//! End-to-end load → viewport → render → export through the library API only.
+
+use std::path::PathBuf;
+
+use svg_viewer::export::{self, ExportFormat, ExportSettings};
+use svg_viewer::renderer;
+use svg_viewer::svg_document::SvgDocument;
+use svg_viewer::viewport::Viewport;
+
+fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("assets")
+        .join("test_fixtures")
+        .join(name)
+}
+
+#[test]
+fn test_fit_and_render() {
+    let doc = SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+    let mut viewport = Viewport::default();
+    viewport.fit_to_area(doc.width(), doc.height(), 400.0, 300.0);
+
+    let pixmap = renderer::render_to_pixmap(&doc, &viewport, 400.0, 300.0, 1.0).unwrap();
+    let (w, h) = renderer::display_size(doc.width(), doc.height(), &viewport, 400.0, 300.0, 1.0);
+    assert_eq!((pixmap.width(), pixmap.height()), (w as u32, h as u32));
+    assert!(pixmap.data().chunks_exact(4).any(|px| px[3] == 255));
+}
+
+#[test]
+fn test_export_rotated() {
+    let doc = SvgDocument::load(&fixture_path("simple_rect.svg")).unwrap();
+    let mut viewport = Viewport::default();
+    viewport.rotate_cw();
+    let settings = ExportSettings {
+        format: ExportFormat::Png,
+        width: 150,
+        height: 200,
+        ..Default::default()
+    };
+    let output = std::env::temp_dir().join("svg_viewer_test_pipeline_rotated.png");
+    export::export_svg(&doc, &viewport, &settings, &output).unwrap();
+    let img = image::open(&output).unwrap();
+    assert_eq!((img.width(), img.height()), (150, 200));
+    // The quarter-turned document fills the portrait image edge to edge
+    let img = img.to_rgba8();
+    assert_eq!(img.get_pixel(75, 12)[3], 255);
+    assert_eq!(img.get_pixel(12, 100)[3], 255);
+    std::fs::remove_file(&output).ok();
+}